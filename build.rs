@@ -1,4 +1,47 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
     // Generate UniFFI scaffolding from UDL file
     uniffi::generate_scaffolding("src/template.udl").unwrap();
+
+    // Build information for get_library_info
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=TEMPLATE_GIT_COMMIT={}", commit);
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    println!("cargo:rustc-env=TEMPLATE_BUILD_TIMESTAMP={}", timestamp);
+    println!(
+        "cargo:rustc-env=TEMPLATE_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=TEMPLATE_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+
+    // Pick up new commits; the scaffolding step only watches the UDL file
+    let head = Path::new(".git/HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Some(reference) = std::fs::read_to_string(head)
+            .ok()
+            .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+        {
+            let path = Path::new(".git").join(reference);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
 }
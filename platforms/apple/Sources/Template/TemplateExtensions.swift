@@ -55,6 +55,52 @@ extension TemplateError {
             return "Invalid input: \(message)"
         case .OperationCancelled(let operation):
             return "Operation '\(operation)' was cancelled"
+        case .Timeout(let operation, let timeoutMs):
+            return "Operation '\(operation)' timed out after \(timeoutMs) ms"
+        case .ParseError(let format, let message, let line, let column):
+            if let line = line, let column = column {
+                return "Failed to parse \(format) at line \(line), column \(column): \(message)"
+            }
+            return "Failed to parse \(format): \(message)"
+        case .AlreadyInitialized:
+            return "Library already initialized"
+        case .IoError(let path, let message, let details):
+            if let details = details {
+                return "I/O error at \(path): \(message) (caused by: \(details))"
+            }
+            return "I/O error at \(path): \(message)"
+        case .EntropyUnavailable(let message):
+            return "Secure random source unavailable: \(message)"
+        case .ModelNotFound(let path):
+            return "Model not found: \(path)"
+        case .InvalidModelFormat(let path, let message):
+            return "Invalid model format for \(path): \(message)"
+        case .ModelLoadError(let path, let message, _):
+            return "Failed to load model \(path): \(message)"
+        case .NetworkError(let url, let statusCode, let message, let details):
+            let cause = details.map { " (caused by: \($0))" } ?? ""
+            if let statusCode = statusCode {
+                return "Network error for \(url) (HTTP \(statusCode)): \(message)\(cause)"
+            }
+            return "Network error for \(url): \(message)\(cause)"
+        case .PinningFailure(let host, let message):
+            return "Certificate pinning failed for \(host): \(message)"
+        case .DatabaseError(let code, let message):
+            if let code = code {
+                return "Database error (\(code)): \(message)"
+            }
+            return "Database error: \(message)"
+        case .EncryptionError(let message):
+            return "Encryption error: \(message)"
+        case .InsufficientStorage(let path, let required, let available):
+            return "Insufficient storage at \(path): \(required) bytes required, \(available) available"
+        case .RetriesExhausted(let operation, let attempts, _, let message):
+            return "\(operation) failed after \(attempts) attempts: \(message)"
+        case .Internal(let message, let location, _):
+            if let location = location {
+                return "Internal error: \(message) (at \(location))"
+            }
+            return "Internal error: \(message)"
         }
     }
 
@@ -67,30 +113,98 @@ extension TemplateError {
             return "INVALID_INPUT"
         case .OperationCancelled:
             return "OPERATION_CANCELLED"
+        case .Timeout:
+            return "TIMEOUT"
+        case .ParseError:
+            return "PARSE_ERROR"
+        case .AlreadyInitialized:
+            return "ALREADY_INITIALIZED"
+        case .IoError:
+            return "IO_ERROR"
+        case .EntropyUnavailable:
+            return "ENTROPY_UNAVAILABLE"
+        case .ModelNotFound:
+            return "MODEL_NOT_FOUND"
+        case .InvalidModelFormat:
+            return "INVALID_MODEL_FORMAT"
+        case .ModelLoadError:
+            return "MODEL_LOAD_ERROR"
+        case .NetworkError:
+            return "NETWORK_ERROR"
+        case .PinningFailure:
+            return "PINNING_FAILURE"
+        case .DatabaseError:
+            return "DATABASE_ERROR"
+        case .EncryptionError:
+            return "ENCRYPTION_ERROR"
+        case .InsufficientStorage:
+            return "INSUFFICIENT_STORAGE"
+        case .RetriesExhausted:
+            return "RETRIES_EXHAUSTED"
+        case .Internal:
+            return "INTERNAL"
         }
     }
 
     /// Whether the error is recoverable
     public var isRecoverable: Bool {
         switch self {
-        case .InputTooLarge, .InvalidInput:
+        case .InputTooLarge, .InvalidInput, .Timeout, .ParseError, .IoError, .RetriesExhausted,
+             .ModelNotFound, .InvalidModelFormat, .ModelLoadError, .NetworkError, .DatabaseError,
+             .InsufficientStorage:
             return true
-        case .OperationCancelled:
+        case .OperationCancelled, .AlreadyInitialized, .EntropyUnavailable, .EncryptionError,
+             .PinningFailure, .Internal:
             return false
         }
     }
+
+    /// Retryable / transient / user-input / internal flags, computed by the Rust core
+    ///
+    /// Prefer this over switching on cases: it keeps working when new
+    /// cases are added.
+    public var classification: ErrorClassification {
+        classifyError(error: self)
+    }
+
+    /// Info / warning / recoverable / fatal level, computed by the Rust core
+    public var severity: Severity {
+        errorSeverity(error: self)
+    }
+
+    /// JSON with `code`, `variant`, `message`, and `fields`, for structured logging
+    public var json: String {
+        errorToJson(error: self)
+    }
+
+    /// Host domain and code registered with `setErrorMappings`, if any
+    public var hostCode: HostErrorCode? {
+        hostErrorCode(error: self)
+    }
+
+    /// `NSError` in the registered host domain, or `TemplateError` if unmapped
+    public var nsError: NSError {
+        let mapped = hostCode
+        return NSError(
+            domain: mapped?.domain ?? "TemplateError",
+            code: Int(mapped?.code ?? 0),
+            userInfo: [
+                NSLocalizedDescriptionKey: detailedDescription,
+                "TemplateErrorCode": errorCode,
+            ]
+        )
+    }
 }
 
 // MARK: - CancellationToken Extensions
 
 extension CancellationToken {
     /// Creates a cancellation token that automatically cancels after a timeout
+    ///
+    /// The deadline is tracked by the Rust core, so `remainingMs()` and child
+    /// tokens see the same timeout.
     public static func withTimeout(_ timeout: TimeInterval) -> CancellationToken {
-        let token = CancellationToken()
-        DispatchQueue.global().asyncAfter(deadline: .now() + timeout) {
-            token.cancel()
-        }
-        return token
+        return CancellationToken.withTimeout(durationMs: UInt64(max(timeout, 0) * 1000))
     }
 
     /// Checks if the token is active (not cancelled)
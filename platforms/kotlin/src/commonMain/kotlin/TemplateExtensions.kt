@@ -90,6 +90,58 @@ val TemplateException.detailedDescription: String
         }
         is TemplateException.OperationCancelled ->
             "Operation '$operation' was cancelled"
+        is TemplateException.Timeout ->
+            "Operation '$operation' timed out after $timeoutMs ms"
+        is TemplateException.ParseException ->
+            if (line != null && column != null) {
+                "Failed to parse $format at line $line, column $column: $errorMessage"
+            } else {
+                "Failed to parse $format: $errorMessage"
+            }
+        is TemplateException.AlreadyInitialized ->
+            "Library already initialized"
+        is TemplateException.IoException ->
+            if (details != null) {
+                "I/O error at $path: $errorMessage (caused by: $details)"
+            } else {
+                "I/O error at $path: $errorMessage"
+            }
+        is TemplateException.EntropyUnavailable ->
+            "Secure random source unavailable: $errorMessage"
+        is TemplateException.ModelNotFound ->
+            "Model not found: $path"
+        is TemplateException.InvalidModelFormat ->
+            "Invalid model format for $path: $errorMessage"
+        is TemplateException.ModelLoadException ->
+            "Failed to load model $path: $errorMessage"
+        is TemplateException.NetworkException -> {
+            val cause = if (details != null) " (caused by: $details)" else ""
+            if (statusCode != null) {
+                "Network error for $url (HTTP $statusCode): $errorMessage$cause"
+            } else {
+                "Network error for $url: $errorMessage$cause"
+            }
+        }
+        is TemplateException.PinningFailure ->
+            "Certificate pinning failed for $host: $errorMessage"
+        is TemplateException.DatabaseException ->
+            if (code != null) {
+                "Database error ($code): $errorMessage"
+            } else {
+                "Database error: $errorMessage"
+            }
+        is TemplateException.EncryptionException ->
+            "Encryption error: $errorMessage"
+        is TemplateException.InsufficientStorage ->
+            "Insufficient storage at $path: $required bytes required, $available available"
+        is TemplateException.RetriesExhausted ->
+            "$operation failed after $attempts attempts: $errorMessage"
+        is TemplateException.Internal ->
+            if (location != null) {
+                "Internal error: $errorMessage (at $location)"
+            } else {
+                "Internal error: $errorMessage"
+            }
     }
 
 /**
@@ -100,6 +152,21 @@ val TemplateException.errorCode: String
         is TemplateException.InputTooLarge -> "INPUT_TOO_LARGE"
         is TemplateException.InvalidInput -> "INVALID_INPUT"
         is TemplateException.OperationCancelled -> "OPERATION_CANCELLED"
+        is TemplateException.Timeout -> "TIMEOUT"
+        is TemplateException.ParseException -> "PARSE_ERROR"
+        is TemplateException.AlreadyInitialized -> "ALREADY_INITIALIZED"
+        is TemplateException.IoException -> "IO_ERROR"
+        is TemplateException.EntropyUnavailable -> "ENTROPY_UNAVAILABLE"
+        is TemplateException.ModelNotFound -> "MODEL_NOT_FOUND"
+        is TemplateException.InvalidModelFormat -> "INVALID_MODEL_FORMAT"
+        is TemplateException.ModelLoadException -> "MODEL_LOAD_ERROR"
+        is TemplateException.NetworkException -> "NETWORK_ERROR"
+        is TemplateException.PinningFailure -> "PINNING_FAILURE"
+        is TemplateException.DatabaseException -> "DATABASE_ERROR"
+        is TemplateException.EncryptionException -> "ENCRYPTION_ERROR"
+        is TemplateException.InsufficientStorage -> "INSUFFICIENT_STORAGE"
+        is TemplateException.RetriesExhausted -> "RETRIES_EXHAUSTED"
+        is TemplateException.Internal -> "INTERNAL"
     }
 
 /**
@@ -108,10 +175,51 @@ val TemplateException.errorCode: String
 val TemplateException.isRecoverable: Boolean
     get() = when (this) {
         is TemplateException.InputTooLarge,
-        is TemplateException.InvalidInput -> true
-        is TemplateException.OperationCancelled -> false
+        is TemplateException.InvalidInput,
+        is TemplateException.Timeout,
+        is TemplateException.ParseException,
+        is TemplateException.IoException,
+        is TemplateException.ModelNotFound,
+        is TemplateException.InvalidModelFormat,
+        is TemplateException.ModelLoadException,
+        is TemplateException.NetworkException,
+        is TemplateException.DatabaseException,
+        is TemplateException.InsufficientStorage,
+        is TemplateException.RetriesExhausted -> true
+        is TemplateException.OperationCancelled,
+        is TemplateException.AlreadyInitialized,
+        is TemplateException.EntropyUnavailable,
+        is TemplateException.EncryptionException,
+        is TemplateException.PinningFailure,
+        is TemplateException.Internal -> false
     }
 
+/**
+ * Retryable / transient / user-input / internal flags, computed by the Rust core.
+ *
+ * Prefer this over switching on variants: it keeps working when new
+ * variants are added.
+ */
+val TemplateException.classification: ErrorClassification
+    get() = classifyError(this)
+
+/**
+ * Info / warning / recoverable / fatal level, computed by the Rust core.
+ */
+val TemplateException.severity: Severity
+    get() = errorSeverity(this)
+
+/**
+ * JSON with `code`, `variant`, `message`, and `fields`, for structured logging.
+ */
+fun TemplateException.toJson(): String = errorToJson(this)
+
+/**
+ * Host domain and code registered with [setErrorMappings], if any.
+ */
+val TemplateException.hostCode: HostErrorCode?
+    get() = hostErrorCode(this)
+
 // ============================
 // CancellationToken Extensions
 // ============================
@@ -119,17 +227,14 @@ val TemplateException.isRecoverable: Boolean
 /**
  * Creates a cancellation token that automatically cancels after a timeout.
  *
+ * The deadline is tracked by the Rust core, so `remainingMs()` and child
+ * tokens see the same timeout.
+ *
  * @param timeout Time duration before cancellation
  * @return A new cancellation token that will cancel after the timeout
  */
-fun CancellationToken.Companion.withTimeout(timeout: Duration): CancellationToken {
-    val token = CancellationToken()
-    kotlinx.coroutines.GlobalScope.launch {
-        delay(timeout)
-        token.cancel()
-    }
-    return token
-}
+fun CancellationToken.Companion.withTimeout(timeout: Duration): CancellationToken =
+    CancellationToken.withTimeout(timeout.inWholeMilliseconds.coerceAtLeast(0).toULong())
 
 /**
  * Creates a cancellation token that automatically cancels after a timeout in seconds.
@@ -240,9 +345,28 @@ suspend inline fun <T> withTemplateTimeout(
     operation()
 }
 
+/**
+ * Executes a template operation with a timeout enforced by the Rust core.
+ *
+ * The operation receives a token that cancels itself after [timeout]; pass it
+ * to the library call so the timeout is reported as a Rust error. Only calls
+ * given the token are bounded; use [withTemplateTimeout] for anything else.
+ *
+ * @param timeout Maximum time to wait
+ * @param operation The operation to execute
+ * @return The result of the operation
+ * @throws TemplateException.Timeout if timeout is exceeded
+ */
+suspend inline fun <T> withTemplateTimeoutToken(
+    timeout: Duration,
+    crossinline operation: suspend (CancellationToken) -> T
+): T = operation(CancellationToken.withTimeout(timeout))
+
 /**
  * Retries a template operation up to [maxAttempts] times.
  *
+ * Only errors classified as retryable by the Rust core are retried.
+ *
  * @param maxAttempts Maximum number of attempts (default 3)
  * @param delayBetweenAttempts Delay between retry attempts (default 100ms)
  * @param operation The operation to execute
@@ -261,7 +385,7 @@ suspend inline fun <T> retryTemplateOperation(
             return operation()
         } catch (e: TemplateException) {
             lastException = e
-            if (!e.isRecoverable) {
+            if (!e.classification.retryable) {
                 throw e
             }
             if (attempt < maxAttempts - 1) {
@@ -0,0 +1,342 @@
+//! Content-addressed storage for downloaded models and other large artifacts
+//!
+//! `ArtifactStore` keeps each artifact once, in a file named by the SHA-256
+//! of its contents, and a manifest mapping friendly names to those hashes.
+//! Two names for byte-identical files share one copy on disk, and checking
+//! an artifact is a matter of hashing the file and comparing with its name.
+//!
+//! Layout of the store directory:
+//!
+//! - `objects/<sha256>`: artifact contents, never modified once written
+//! - `manifest.json`: `{"artifacts": {name: sha256}}`, replaced atomically
+//!
+//! An object is deleted when the last name referring to it is removed or
+//! pointed elsewhere.
+
+use crate::directories::{self, StorageCategory};
+use crate::disk_space;
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::{rename_replacing, sync_directory, write_atomic};
+use crate::runtime;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const OBJECTS_DIRECTORY: &str = "objects";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Suffix of the temporary files filled while importing
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// An artifact in an `ArtifactStore`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactInfo {
+    pub name: String,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+    pub size_bytes: u64,
+    /// Path of the stored file, for loading; do not modify it
+    pub path: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Manifest {
+    artifacts: BTreeMap<String, String>,
+}
+
+/// Artifacts stored once per content hash, under friendly names
+pub struct ArtifactStore {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    directory: PathBuf,
+    objects: PathBuf,
+    manifest: Mutex<Manifest>,
+}
+
+impl ArtifactStore {
+    /// Opens the artifact store in `directory`, creating it if missing
+    ///
+    /// Temporary files left by imports that never finished are removed.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory or manifest cannot be read or created
+    /// * `Err(TemplateError::ParseError)` - If the manifest is corrupt
+    pub fn open(directory: String) -> TemplateResult<Self> {
+        shield::guard("ArtifactStore::open", || {
+            let directory = directories::resolve(StorageCategory::Downloads, &directory);
+            let objects = directory.join(OBJECTS_DIRECTORY);
+            fs::create_dir_all(&objects).map_err(|e| TemplateError::io_error(&objects, &e))?;
+            let entries =
+                fs::read_dir(&objects).map_err(|e| TemplateError::io_error(&objects, &e))?;
+            for entry in entries.flatten() {
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(PARTIAL_SUFFIX)
+                {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+            let manifest_path = directory.join(MANIFEST_FILE);
+            let manifest = match fs::read(&manifest_path) {
+                Ok(json) => {
+                    serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))?
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+                Err(e) => return Err(TemplateError::io_error(&manifest_path, &e)),
+            };
+            Ok(Self {
+                inner: Arc::new(Inner {
+                    directory,
+                    objects,
+                    manifest: Mutex::new(manifest),
+                }),
+            })
+        })
+    }
+
+    /// Copies the file at `source_path` into the store under `name` (async)
+    ///
+    /// If the store already holds identical contents, no second copy is
+    /// kept. A previous artifact under `name` is replaced.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `name` is empty
+    /// * `Err(TemplateError::InsufficientStorage)` - If the volume has no room for the file
+    /// * `Err(TemplateError::IoError)` - If the source cannot be read or the store cannot be written
+    pub async fn import_file(
+        &self,
+        name: String,
+        source_path: String,
+    ) -> TemplateResult<ArtifactInfo> {
+        let inner = self.inner.clone();
+        shield::guard_async("ArtifactStore::import_file", async move {
+            runtime::spawn_blocking(move || {
+                check_name(&name)?;
+                let source = PathBuf::from(source_path);
+                let file = File::open(&source).map_err(|e| TemplateError::io_error(&source, &e))?;
+                let size = file
+                    .metadata()
+                    .map_err(|e| TemplateError::io_error(&source, &e))?
+                    .len();
+                disk_space::ensure_space(&inner.objects, size)?;
+                inner.store(name, file)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Stores `data` under `name` (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `name` is empty
+    /// * `Err(TemplateError::InsufficientStorage)` - If the volume has no room for `data`
+    /// * `Err(TemplateError::IoError)` - If the store cannot be written
+    pub async fn put(&self, name: String, data: Vec<u8>) -> TemplateResult<ArtifactInfo> {
+        let inner = self.inner.clone();
+        shield::guard_async("ArtifactStore::put", async move {
+            runtime::spawn_blocking(move || {
+                check_name(&name)?;
+                disk_space::ensure_space(&inner.objects, data.len() as u64)?;
+                inner.store(name, data.as_slice())
+            })
+            .await
+        })
+        .await
+    }
+
+    /// The artifact stored under `name`, if any
+    pub fn get(&self, name: String) -> Option<ArtifactInfo> {
+        let manifest = self.inner.manifest.lock().unwrap();
+        let sha256 = manifest.artifacts.get(&name)?;
+        self.inner.info(&name, sha256)
+    }
+
+    /// Every artifact, sorted by name
+    pub fn list(&self) -> Vec<ArtifactInfo> {
+        let manifest = self.inner.manifest.lock().unwrap();
+        manifest
+            .artifacts
+            .iter()
+            .filter_map(|(name, sha256)| self.inner.info(name, sha256))
+            .collect()
+    }
+
+    /// Removes `name`, and its contents if no other name refers to them,
+    /// returning whether it existed
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the manifest cannot be written
+    pub fn remove(&self, name: String) -> TemplateResult<bool> {
+        shield::guard("ArtifactStore::remove", || {
+            let mut manifest = self.inner.manifest.lock().unwrap();
+            let Some(sha256) = manifest.artifacts.remove(&name) else {
+                return Ok(false);
+            };
+            if let Err(e) = self.inner.save(&manifest) {
+                manifest.artifacts.insert(name, sha256);
+                return Err(e);
+            }
+            self.inner.release(&manifest, &sha256);
+            Ok(true)
+        })
+    }
+
+    /// Rehashes the artifact under `name` and checks it against its hash (async)
+    ///
+    /// Returns `false` if the file was modified or truncated.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If there is no artifact named `name`
+    /// * `Err(TemplateError::IoError)` - If the file cannot be read
+    pub async fn verify(&self, name: String) -> TemplateResult<bool> {
+        let inner = self.inner.clone();
+        shield::guard_async("ArtifactStore::verify", async move {
+            runtime::spawn_blocking(move || {
+                let sha256 = inner
+                    .manifest
+                    .lock()
+                    .unwrap()
+                    .artifacts
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        TemplateError::invalid_input(format!("No artifact named '{}'", name), None)
+                    })?;
+                let path = inner.objects.join(&sha256);
+                let file = File::open(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+                let (actual, _) =
+                    hash_into(file, io::sink()).map_err(|e| TemplateError::io_error(&path, &e))?;
+                Ok(actual == sha256)
+            })
+            .await
+        })
+        .await
+    }
+}
+
+impl Inner {
+    /// Hashes `source` into a temporary file and links it under `name`
+    fn store(&self, name: String, source: impl Read) -> TemplateResult<ArtifactInfo> {
+        let partial = self
+            .objects
+            .join(format!(".{}{}", Uuid::new_v4().simple(), PARTIAL_SUFFIX));
+        let written = File::create(&partial).and_then(|mut file| {
+            let result = hash_into(source, &mut file)?;
+            file.sync_all()?;
+            Ok(result)
+        });
+        let (sha256, size_bytes) = match written {
+            Ok(result) => result,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(TemplateError::io_error(&partial, &e));
+            }
+        };
+
+        // Linking and releasing happen under the lock, so an object is
+        // never deleted while another import is adopting it
+        let mut manifest = self.manifest.lock().unwrap();
+        let object = self.objects.join(&sha256);
+        let linked = if object.exists() {
+            fs::remove_file(&partial)
+        } else {
+            rename_replacing(&partial, &object).and_then(|()| sync_directory(&self.objects))
+        };
+        if let Err(e) = linked {
+            let _ = fs::remove_file(&partial);
+            return Err(TemplateError::io_error(&object, &e));
+        }
+        let previous = manifest.artifacts.insert(name.clone(), sha256.clone());
+        if let Err(e) = self.save(&manifest) {
+            match previous {
+                Some(previous) => manifest.artifacts.insert(name, previous),
+                None => manifest.artifacts.remove(&name),
+            };
+            self.release(&manifest, &sha256);
+            return Err(e);
+        }
+        if let Some(previous) = previous.filter(|previous| *previous != sha256) {
+            self.release(&manifest, &previous);
+        }
+        Ok(ArtifactInfo {
+            name,
+            sha256,
+            size_bytes,
+            path: object.to_string_lossy().into_owned(),
+        })
+    }
+
+    /// Deletes the object `sha256` if no name refers to it anymore
+    fn release(&self, manifest: &Manifest, sha256: &str) {
+        if manifest.artifacts.values().any(|hash| hash == sha256) {
+            return;
+        }
+        let object = self.objects.join(sha256);
+        if let Err(e) = fs::remove_file(&object) {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::warn!("Could not delete artifact {}: {}", object.display(), e);
+            }
+        }
+    }
+
+    fn info(&self, name: &str, sha256: &str) -> Option<ArtifactInfo> {
+        let object = self.objects.join(sha256);
+        let metadata = fs::metadata(&object).ok()?;
+        Some(ArtifactInfo {
+            name: name.to_string(),
+            sha256: sha256.to_string(),
+            size_bytes: metadata.len(),
+            path: object.to_string_lossy().into_owned(),
+        })
+    }
+
+    fn save(&self, manifest: &Manifest) -> TemplateResult<()> {
+        let path = self.directory.join(MANIFEST_FILE);
+        let json = serde_json::to_vec(manifest).map_err(|e| TemplateError::json_error(&e))?;
+        write_atomic(&path, &json).map_err(|e| TemplateError::io_error(&path, &e))
+    }
+}
+
+fn check_name(name: &str) -> TemplateResult<()> {
+    if name.is_empty() {
+        return Err(TemplateError::invalid_input(
+            "Artifact name must not be empty".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Copies `source` to `destination`, returning the hex SHA-256 and length
+fn hash_into(mut source: impl Read, mut destination: impl Write) -> io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+        destination.write_all(&buffer[..read])?;
+        total += read as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), total))
+}
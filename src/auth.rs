@@ -0,0 +1,305 @@
+//! Credentials for remote APIs, attached to outgoing requests per host
+//!
+//! `AuthManager` keeps one token per host in an `EncryptedKvStore`, so
+//! tokens are encrypted at rest under a key from the host's Keychain or
+//! Keystore. Once installed with `set_auth_manager`, requests to a host
+//! with a token get an `Authorization` header unless they set one
+//! themselves: `http_request`, `sse_request`, `upload_file`, `Downloader`,
+//! `Outbox`, and `WebSocketClient` handshakes all go through it. The header
+//! is added when the request is sent, so tokens are never written to the
+//! outbox or download persistence files.
+//!
+//! Tokens that expire within a minute are refreshed before they are sent,
+//! and `http_request` and `Outbox` refresh a token the server rejects with
+//! 401 and send the request once more. Refreshing is done by the host's
+//! `TokenRefresher`, which usually talks to its OAuth server; concurrent
+//! requests wait for a single refresh instead of each starting one.
+
+use crate::encrypted_kv_store::EncryptedKvStore;
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Store keys holding tokens are this followed by the host
+const KEY_PREFIX: &str = "auth.token.";
+
+/// Tokens expiring sooner than this are refreshed before they are sent
+#[cfg(feature = "http")]
+const REFRESH_MARGIN_MS: u64 = 60_000;
+
+/// A credential sent as `Authorization: <scheme> <access_token>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthToken {
+    pub access_token: String,
+    /// Usually `Bearer`
+    pub scheme: String,
+    /// Handed to the `TokenRefresher`; never sent with requests
+    pub refresh_token: Option<String>,
+    /// Unix time in milliseconds when the token stops being accepted
+    pub expires_at_ms: Option<u64>,
+}
+
+/// Obtains new tokens when they expire or are rejected, implemented by the host
+pub trait TokenRefresher: Send + Sync {
+    /// A token for `host` to replace `token`, or `None` if it cannot be
+    /// refreshed, e.g. because the user must sign in again
+    ///
+    /// Called on a background thread, so it may block on a network request.
+    fn refresh_token(&self, host: String, token: AuthToken) -> Option<AuthToken>;
+}
+
+/// Tokens for remote APIs, kept encrypted and attached to requests per host
+pub struct AuthManager {
+    store: Arc<EncryptedKvStore>,
+    refresher: Option<Arc<dyn TokenRefresher>>,
+    /// Held while a token is refreshed, so requests waiting for it reuse the
+    /// new token instead of refreshing again
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+/// Manager whose tokens are attached to requests; `None` until set
+static MANAGER: RwLock<Option<Arc<AuthManager>>> = RwLock::new(None);
+
+/// Makes `manager` attach its tokens to every outgoing request, replacing
+/// any manager set before; `None` stops attaching tokens
+pub fn set_auth_manager(manager: Option<Arc<AuthManager>>) {
+    *MANAGER.write().unwrap() = manager;
+}
+
+impl AuthManager {
+    /// Keeps tokens in `store` under keys starting with `auth.token.`, so
+    /// the store can hold other values too; `refresher` is asked for new
+    /// tokens, and without one expired tokens are sent as they are
+    pub fn new(store: Arc<EncryptedKvStore>, refresher: Option<Box<dyn TokenRefresher>>) -> Self {
+        Self {
+            store,
+            refresher: refresher.map(Arc::from),
+            refreshing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Stores `token` for requests to `host`, e.g. `api.example.com`,
+    /// replacing any previous one
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `host` is not a host name or
+    ///   address, or the token or scheme cannot be sent in a header
+    /// * `Err(TemplateError::IoError)` - If the store cannot be written
+    pub fn set_token(&self, host: String, token: AuthToken) -> TemplateResult<()> {
+        shield::guard("AuthManager::set_token", || {
+            let host = normalize_host(&host)?;
+            self.store_token(&host, &token)
+        })
+    }
+
+    /// The token stored for `host`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `host` is not a host name or address
+    /// * `Err(TemplateError::EncryptionError)` - If the token cannot be decrypted
+    pub fn get_token(&self, host: String) -> TemplateResult<Option<AuthToken>> {
+        shield::guard("AuthManager::get_token", || {
+            self.token(&normalize_host(&host)?)
+        })
+    }
+
+    /// Removes the token for `host`, e.g. on sign-out, returning whether
+    /// there was one
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `host` is not a host name or address
+    /// * `Err(TemplateError::IoError)` - If the store cannot be written
+    pub fn remove_token(&self, host: String) -> TemplateResult<bool> {
+        shield::guard("AuthManager::remove_token", || {
+            let host = normalize_host(&host)?;
+            self.store.delete(format!("{}{}", KEY_PREFIX, host))
+        })
+    }
+
+    /// Hosts with a stored token, in sorted order
+    pub fn hosts(&self) -> Vec<String> {
+        self.store
+            .keys()
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(KEY_PREFIX).map(str::to_string))
+            .collect()
+    }
+
+    /// Asks the refresher for a new token for `host` now, whether or not the
+    /// current one expired (async)
+    ///
+    /// Returns the new token, or `None` if there is no token for `host` or
+    /// the refresher returned none; the stored token is kept in that case.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `host` is not a host name or
+    ///   address, there is no refresher, or it returned an invalid token
+    /// * `Err(TemplateError::EncryptionError)` - If the token cannot be decrypted
+    /// * `Err(TemplateError::IoError)` - If the new token cannot be stored
+    pub async fn refresh_token(&self, host: String) -> TemplateResult<Option<AuthToken>> {
+        shield::guard_async("AuthManager::refresh_token", async move {
+            let host = normalize_host(&host)?;
+            if self.refresher.is_none() {
+                return Err(TemplateError::invalid_input(
+                    "AuthManager has no TokenRefresher".to_string(),
+                    None,
+                ));
+            }
+            match self.token(&host)? {
+                Some(token) => self.refresh(&host, &token).await,
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    fn token(&self, host: &str) -> TemplateResult<Option<AuthToken>> {
+        let key = format!("{}{}", KEY_PREFIX, host);
+        self.store
+            .get_string(key)?
+            .map(|json| serde_json::from_str(&json).map_err(|e| TemplateError::json_error(&e)))
+            .transpose()
+    }
+
+    fn store_token(&self, host: &str, token: &AuthToken) -> TemplateResult<()> {
+        validate_token(token)?;
+        let json = serde_json::to_string(token).map_err(|e| TemplateError::json_error(&e))?;
+        let key = format!("{}{}", KEY_PREFIX, host);
+        self.store.set_string(key, json)
+    }
+
+    /// Replaces `stale` with a token from the refresher, unless another
+    /// request already replaced it; `None` if no new token is available
+    async fn refresh(&self, host: &str, stale: &AuthToken) -> TemplateResult<Option<AuthToken>> {
+        let Some(refresher) = self.refresher.clone() else {
+            return Ok(None);
+        };
+        let _refreshing = self.refreshing.lock().await;
+        match self.token(host)? {
+            Some(current) if current == *stale => {}
+            current => return Ok(current),
+        }
+        let (name, stale) = (host.to_string(), stale.clone());
+        let refreshed = runtime::spawn_blocking(move || refresher.refresh_token(name, stale)).await;
+        if let Some(token) = &refreshed {
+            self.store_token(host, token)?;
+        }
+        Ok(refreshed)
+    }
+}
+
+/// Adds `Authorization` with the token for `url`'s host, unless `headers`
+/// already has one, refreshing a token about to expire first; returns the
+/// token added
+#[cfg(feature = "http")]
+pub(crate) async fn authorize(
+    url: &str,
+    headers: &mut HashMap<String, String>,
+) -> TemplateResult<Option<AuthToken>> {
+    let Some((manager, host)) = manager_for(url) else {
+        return Ok(None);
+    };
+    if headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("authorization"))
+    {
+        return Ok(None);
+    }
+    let Some(mut token) = manager.token(&host)? else {
+        return Ok(None);
+    };
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    if token
+        .expires_at_ms
+        .is_some_and(|expires_at_ms| expires_at_ms <= now_ms + REFRESH_MARGIN_MS)
+    {
+        if let Some(refreshed) = manager.refresh(&host, &token).await? {
+            token = refreshed;
+        }
+    }
+    headers.insert("Authorization".to_string(), header_value(&token));
+    Ok(Some(token))
+}
+
+/// After a 401 to a request sent with `rejected` by `authorize`, puts a
+/// refreshed token in `headers`; returns whether there is one to retry with
+#[cfg(feature = "http")]
+pub(crate) async fn reauthorize(
+    url: &str,
+    rejected: &AuthToken,
+    headers: &mut HashMap<String, String>,
+) -> TemplateResult<bool> {
+    let Some((manager, host)) = manager_for(url) else {
+        return Ok(false);
+    };
+    match manager.refresh(&host, rejected).await? {
+        Some(token) if token != *rejected => {
+            headers.insert("Authorization".to_string(), header_value(&token));
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// The installed manager and `url`'s host, if both exist
+#[cfg(feature = "http")]
+fn manager_for(url: &str) -> Option<(Arc<AuthManager>, String)> {
+    let manager = MANAGER.read().unwrap().clone()?;
+    let url = reqwest::Url::parse(url).ok()?;
+    Some((manager, url.host_str()?.to_ascii_lowercase()))
+}
+
+#[cfg(feature = "http")]
+fn header_value(token: &AuthToken) -> String {
+    format!("{} {}", token.scheme, token.access_token)
+}
+
+/// `host` in lower case, if it is a host name or an IP address
+fn normalize_host(host: &str) -> TemplateResult<String> {
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | ':' | '[' | ']'));
+    if !valid {
+        return Err(TemplateError::invalid_input(
+            format!("Not a host name or address: '{}'", host),
+            None,
+        ));
+    }
+    Ok(host.to_ascii_lowercase())
+}
+
+/// Rejects tokens that would make an invalid `Authorization` header
+fn validate_token(token: &AuthToken) -> TemplateResult<()> {
+    if token.scheme.is_empty() || !token.scheme.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(TemplateError::invalid_input(
+            format!("Invalid authorization scheme: '{}'", token.scheme),
+            None,
+        ));
+    }
+    if token.access_token.is_empty()
+        || !token
+            .access_token
+            .chars()
+            .all(|c| c.is_ascii_graphic() || c == ' ')
+    {
+        return Err(TemplateError::invalid_input(
+            "access_token must be printable ASCII and not empty".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Passphrase-encrypted backup of library data
+//!
+//! `export_data` writes the library configuration and, if given, the entries
+//! of a `KvStore` to one file encrypted under a passphrase, for user-initiated
+//! backups or moving to a new device. `import_data` restores it.
+//!
+//! The file is a fixed header followed by AES-256-GCM ciphertext:
+//!
+//! | Bytes | Contents                                   |
+//! |-------|--------------------------------------------|
+//! | 8     | Magic `TPLBAK\0\0`                         |
+//! | 4     | Format version (big-endian)                |
+//! | 4     | PBKDF2-HMAC-SHA256 iterations (big-endian) |
+//! | 16    | Salt                                       |
+//! | 12    | Nonce                                      |
+//! | rest  | Encrypted JSON payload                     |
+//!
+//! The header is authenticated along with the payload, so neither can be
+//! altered without detection.
+
+use crate::cancellation::{self, CancellationToken};
+use crate::config::{self, LibraryConfig};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use crate::kv_store::{KvStore, KvValue};
+use crate::runtime;
+use crate::secure::{SecureBytes, SecureString};
+use crate::secure_random::fill_secure;
+use crate::shield;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Version of the backup format written by this release
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+const MAGIC: [u8; 8] = *b"TPLBAK\0\0";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const HEADER_LENGTH: usize = MAGIC.len() + 4 + 4 + SALT_LENGTH + NONCE_LENGTH;
+
+/// PBKDF2 iterations for new backups, per current OWASP guidance
+const KDF_ITERATIONS: u32 = 600_000;
+
+/// Fewest iterations accepted when importing, so a forged header cannot
+/// make a passphrase cheap to guess
+const MIN_KDF_ITERATIONS: u32 = 100_000;
+
+/// What a backup contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupSummary {
+    /// When the backup was created, in milliseconds since the Unix epoch
+    pub created_ms: u64,
+    /// Number of key-value entries in the backup
+    pub kv_entry_count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupPayload {
+    created_ms: u64,
+    config: LibraryConfig,
+    #[serde(default)]
+    kv_entries: BTreeMap<String, KvValue>,
+}
+
+/// Writes an encrypted backup of the library configuration and `store` (async)
+///
+/// The file at `path` is replaced atomically. Deriving the key is deliberately
+/// slow (about a second on a phone), so the work runs on a background thread.
+/// The token is checked before deriving the key and again before writing;
+/// the derivation itself runs to completion.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the passphrase is empty
+/// * `Err(TemplateError::IoError)` - If the file cannot be written
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn export_data(
+    path: String,
+    passphrase: String,
+    store: Option<Arc<KvStore>>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<BackupSummary> {
+    let passphrase = SecureString::new(passphrase);
+    shield::guard_async("export_data", async move {
+        check_passphrase(&passphrase)?;
+        let op_token = cancellation::operation_token(token.as_deref());
+        let guard = op_token.drop_guard();
+        let summary = runtime::spawn_blocking(move || {
+            cancellation::check_cancelled(Some(&op_token), "export_data")?;
+            let payload = BackupPayload {
+                created_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis() as u64),
+                config: config::current(),
+                kv_entries: store.map(|store| store.entries()).unwrap_or_default(),
+            };
+            let summary = summarize(&payload);
+            let archive = seal(&payload, &passphrase)?;
+            cancellation::check_cancelled(Some(&op_token), "export_data")?;
+            let path = PathBuf::from(path);
+            write_atomic(&path, &archive).map_err(|e| TemplateError::io_error(&path, &e))?;
+            log::info!("Exported backup to {}", path.display());
+            Ok::<_, TemplateError>(summary)
+        })
+        .await?;
+        guard.disarm();
+        Ok(summary)
+    })
+    .await
+}
+
+/// Restores a backup written by `export_data` (async)
+///
+/// The configuration is applied as with `update_config`. If `store` is given,
+/// its entries are replaced by those in the backup. Nothing is changed unless
+/// the backup decrypts, its configuration is valid, and the store is written.
+/// The token is checked before deriving the key and again before changing
+/// anything.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::EncryptionError)` - If the passphrase is wrong or the file was modified
+/// * `Err(TemplateError::ParseError)` - If the file is not a backup, or was
+///   written by a newer release
+/// * `Err(TemplateError::InvalidInput)` - If the passphrase is empty or the
+///   saved configuration is invalid
+/// * `Err(TemplateError::IoError)` - If the file cannot be read or the store cannot be written
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn import_data(
+    path: String,
+    passphrase: String,
+    store: Option<Arc<KvStore>>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<BackupSummary> {
+    let passphrase = SecureString::new(passphrase);
+    shield::guard_async("import_data", async move {
+        check_passphrase(&passphrase)?;
+        let op_token = cancellation::operation_token(token.as_deref());
+        let guard = op_token.drop_guard();
+        let summary = runtime::spawn_blocking(move || {
+            let path = PathBuf::from(path);
+            let archive = fs::read(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+            cancellation::check_cancelled(Some(&op_token), "import_data")?;
+            let payload = open(&archive, &passphrase)?;
+            let summary = summarize(&payload);
+            payload.config.validate()?;
+            cancellation::check_cancelled(Some(&op_token), "import_data")?;
+            // The store first: unlike the configuration, it can fail to write
+            if let Some(store) = store {
+                store.replace_entries(payload.kv_entries)?;
+            }
+            config::update_config(payload.config)?;
+            log::info!("Imported backup from {}", path.display());
+            Ok::<_, TemplateError>(summary)
+        })
+        .await?;
+        guard.disarm();
+        Ok(summary)
+    })
+    .await
+}
+
+fn check_passphrase(passphrase: &SecureString) -> TemplateResult<()> {
+    if passphrase.is_empty() {
+        return Err(TemplateError::invalid_input(
+            "Passphrase must not be empty".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+fn summarize(payload: &BackupPayload) -> BackupSummary {
+    BackupSummary {
+        created_ms: payload.created_ms,
+        kv_entry_count: payload.kv_entries.len() as u32,
+    }
+}
+
+/// Serializes and encrypts `payload` into a complete backup file
+fn seal(payload: &BackupPayload, passphrase: &SecureString) -> TemplateResult<Vec<u8>> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    fill_secure(&mut salt)?;
+    fill_secure(&mut nonce)?;
+
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&BACKUP_FORMAT_VERSION.to_be_bytes());
+    header.extend_from_slice(&KDF_ITERATIONS.to_be_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let plaintext =
+        SecureBytes::new(serde_json::to_vec(payload).map_err(|e| TemplateError::json_error(&e))?);
+    let ciphertext = cipher(passphrase, &salt, KDF_ITERATIONS)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext.expose(),
+                aad: &header,
+            },
+        )
+        .map_err(|_| TemplateError::encryption_error("Encryption failed"))?;
+    header.extend_from_slice(&ciphertext);
+    Ok(header)
+}
+
+/// Checks the header of a backup file and decrypts its payload
+fn open(archive: &[u8], passphrase: &SecureString) -> TemplateResult<BackupPayload> {
+    let not_a_backup = |message: &str| TemplateError::parse_error("backup", message.to_string());
+    if archive.len() < HEADER_LENGTH || archive[..MAGIC.len()] != MAGIC {
+        return Err(not_a_backup("Not a backup file"));
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_LENGTH);
+    let field = |at: usize| u32::from_be_bytes(header[at..at + 4].try_into().unwrap());
+    let version = field(MAGIC.len());
+    let iterations = field(MAGIC.len() + 4);
+    if version > BACKUP_FORMAT_VERSION {
+        return Err(not_a_backup(&format!(
+            "Backup format {} is newer than this release supports ({})",
+            version, BACKUP_FORMAT_VERSION
+        )));
+    }
+    if iterations < MIN_KDF_ITERATIONS {
+        return Err(not_a_backup("Backup key derivation is too weak"));
+    }
+    let salt = &header[MAGIC.len() + 8..MAGIC.len() + 8 + SALT_LENGTH];
+    let nonce = &header[HEADER_LENGTH - NONCE_LENGTH..];
+
+    let plaintext = SecureBytes::new(
+        cipher(passphrase, salt, iterations)?
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                TemplateError::encryption_error("Wrong passphrase or modified backup file")
+            })?,
+    );
+    serde_json::from_slice(plaintext.expose()).map_err(|e| TemplateError::json_error(&e))
+}
+
+/// Derives the AES-256 key for `passphrase` and `salt`
+fn cipher(passphrase: &SecureString, salt: &[u8], iterations: u32) -> TemplateResult<Aes256Gcm> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.expose().as_bytes(), salt, iterations, &mut *key);
+    Aes256Gcm::new_from_slice(&*key)
+        .map_err(|_| TemplateError::encryption_error("Invalid backup key length"))
+}
@@ -0,0 +1,292 @@
+//! Chunked storage for large binary payloads
+//!
+//! Passing a large payload across the FFI as one byte array copies all of it
+//! at once on both sides. `BlobStore` instead takes payloads in chunks through
+//! a `BlobWriter` and serves them back in ranges through a `BlobReader`, so
+//! neither side holds more than one chunk. Blobs are files named by their id
+//! in the store's directory; a writer fills a hidden temporary file that
+//! `finish` syncs and renames into place, so a blob is either complete or
+//! absent.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::{rename_replacing, sync_directory};
+use crate::shield;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Most bytes returned by a single `BlobReader::read_at` (16MB)
+pub const MAX_BLOB_READ: u32 = 16 * 1024 * 1024;
+
+/// Suffix of the temporary files that writers fill
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Directory of blobs written and read in chunks
+pub struct BlobStore {
+    directory: PathBuf,
+}
+
+impl BlobStore {
+    /// Opens the blob store in `directory`, creating it if missing
+    ///
+    /// Temporary files left by writers that never finished, e.g. because the
+    /// app was killed, are removed.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be created or read
+    pub fn open(directory: String) -> TemplateResult<Self> {
+        shield::guard("BlobStore::open", || {
+            let directory = directories::resolve(StorageCategory::Data, &directory);
+            fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+            let entries =
+                fs::read_dir(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+            for entry in entries.flatten() {
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(PARTIAL_SUFFIX)
+                {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+            Ok(Self { directory })
+        })
+    }
+
+    /// Starts a new blob
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the temporary file cannot be created
+    pub fn writer(&self) -> TemplateResult<Arc<BlobWriter>> {
+        shield::guard("BlobStore::writer", || {
+            let id = Uuid::new_v4().to_string();
+            let partial = self.directory.join(format!(".{}{}", id, PARTIAL_SUFFIX));
+            let file = File::create(&partial).map_err(|e| TemplateError::io_error(&partial, &e))?;
+            Ok(Arc::new(BlobWriter {
+                destination: self.directory.join(&id),
+                id,
+                partial,
+                state: Mutex::new(WriterState::Open {
+                    file,
+                    bytes_written: 0,
+                }),
+            }))
+        })
+    }
+
+    /// Opens the finished blob `blob_id` for reading
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `blob_id` is not an id returned by `finish`
+    /// * `Err(TemplateError::IoError)` - If the blob does not exist or cannot be opened
+    pub fn reader(&self, blob_id: String) -> TemplateResult<Arc<BlobReader>> {
+        shield::guard("BlobStore::reader", || {
+            let path = self.blob_path(&blob_id)?;
+            let file = File::open(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+            let size = file
+                .metadata()
+                .map_err(|e| TemplateError::io_error(&path, &e))?
+                .len();
+            Ok(Arc::new(BlobReader {
+                path,
+                size,
+                file: Mutex::new(file),
+            }))
+        })
+    }
+
+    /// Deletes the blob `blob_id`, returning whether it existed
+    ///
+    /// Readers already open keep working on platforms that allow deleting
+    /// open files.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `blob_id` is not an id returned by `finish`
+    /// * `Err(TemplateError::IoError)` - If the blob cannot be deleted
+    pub fn delete(&self, blob_id: String) -> TemplateResult<bool> {
+        shield::guard("BlobStore::delete", || {
+            let path = self.blob_path(&blob_id)?;
+            match fs::remove_file(&path) {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(TemplateError::io_error(&path, &e)),
+            }
+        })
+    }
+
+    /// The file of `blob_id`, which must be a UUID so it cannot name another path
+    fn blob_path(&self, blob_id: &str) -> TemplateResult<PathBuf> {
+        let id = Uuid::parse_str(blob_id).map_err(|_| {
+            TemplateError::invalid_input(format!("Not a blob id: '{}'", blob_id), None)
+        })?;
+        Ok(self.directory.join(id.to_string()))
+    }
+}
+
+/// Writes one blob in chunks
+///
+/// Dropping a writer without calling `finish` discards what was written.
+pub struct BlobWriter {
+    id: String,
+    partial: PathBuf,
+    destination: PathBuf,
+    state: Mutex<WriterState>,
+}
+
+enum WriterState {
+    Open { file: File, bytes_written: u64 },
+    Finished { bytes_written: u64 },
+    Aborted,
+}
+
+impl BlobWriter {
+    /// Appends a chunk to the blob
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the writer has finished or was aborted
+    /// * `Err(TemplateError::IoError)` - If the chunk cannot be written
+    pub fn append(&self, chunk: Vec<u8>) -> TemplateResult<()> {
+        shield::guard("BlobWriter::append", || {
+            let mut state = self.state.lock().unwrap();
+            let WriterState::Open {
+                file,
+                bytes_written,
+            } = &mut *state
+            else {
+                return Err(closed_error());
+            };
+            file.write_all(&chunk)
+                .map_err(|e| TemplateError::io_error(&self.partial, &e))?;
+            *bytes_written += chunk.len() as u64;
+            Ok(())
+        })
+    }
+
+    /// Bytes appended so far
+    pub fn bytes_written(&self) -> u64 {
+        match &*self.state.lock().unwrap() {
+            WriterState::Open { bytes_written, .. } | WriterState::Finished { bytes_written } => {
+                *bytes_written
+            }
+            WriterState::Aborted => 0,
+        }
+    }
+
+    /// Completes the blob and returns its id
+    ///
+    /// The data is synced to disk before the blob becomes visible to readers.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the writer has finished or was aborted
+    /// * `Err(TemplateError::IoError)` - If the blob cannot be saved; it is discarded
+    pub fn finish(&self) -> TemplateResult<String> {
+        shield::guard("BlobWriter::finish", || {
+            let mut state = self.state.lock().unwrap();
+            let (file, bytes_written) = match std::mem::replace(&mut *state, WriterState::Aborted) {
+                WriterState::Open {
+                    file,
+                    bytes_written,
+                } => (file, bytes_written),
+                closed => {
+                    *state = closed;
+                    return Err(closed_error());
+                }
+            };
+            self.commit(file)
+                .map_err(|e| TemplateError::io_error(&self.destination, &e))?;
+            *state = WriterState::Finished { bytes_written };
+            Ok(self.id.clone())
+        })
+    }
+
+    /// Discards the blob; calling it after `finish` has no effect
+    pub fn abort(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let WriterState::Open { .. } = &*state {
+            // Closes the file before removing it, as Windows requires
+            *state = WriterState::Aborted;
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+
+    /// Syncs, closes, and renames the temporary file into place
+    fn commit(&self, file: File) -> io::Result<()> {
+        let result = file
+            .sync_all()
+            .and_then(|()| {
+                drop(file);
+                rename_replacing(&self.partial, &self.destination)
+            })
+            .and_then(|()| sync_directory(parent(&self.destination)));
+        if result.is_err() {
+            let _ = fs::remove_file(&self.partial);
+        }
+        result
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// Reads ranges of one finished blob
+pub struct BlobReader {
+    path: PathBuf,
+    size: u64,
+    file: Mutex<File>,
+}
+
+impl BlobReader {
+    /// Size of the blob in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Reads up to `len` bytes starting at `offset`
+    ///
+    /// Fewer bytes are returned at the end of the blob, and none at or past it.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `len` is over `MAX_BLOB_READ`
+    /// * `Err(TemplateError::IoError)` - If the blob cannot be read
+    pub fn read_at(&self, offset: u64, len: u32) -> TemplateResult<Vec<u8>> {
+        shield::guard("BlobReader::read_at", || {
+            if len > MAX_BLOB_READ {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "Read of {} bytes exceeds the limit of {}",
+                        len, MAX_BLOB_READ
+                    ),
+                    None,
+                ));
+            }
+            let available = self.size.saturating_sub(offset).min(u64::from(len));
+            let mut buffer = vec![0; available as usize];
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut buffer))
+                .map_err(|e| TemplateError::io_error(&self.path, &e))?;
+            Ok(buffer)
+        })
+    }
+}
+
+fn parent(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new("."))
+}
+
+fn closed_error() -> TemplateError {
+    TemplateError::invalid_input("Blob writer already finished or aborted".to_string(), None)
+}
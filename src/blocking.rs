@@ -0,0 +1,141 @@
+//! Blocking variants of the async API
+//!
+//! Each function runs its async counterpart on the internal runtime and
+//! blocks the calling thread until it completes. They are meant for call
+//! sites that cannot await, such as JVM desktop code or legacy iOS callbacks;
+//! prefer the async functions everywhere else.
+
+use crate::cancellation::CancellationToken;
+use crate::error::TemplateResult;
+use crate::ids::{self, ParsedUuid};
+use crate::models::{self, DiscoveredModel};
+use crate::runtime;
+use crate::secure_random;
+use crate::template::{self, EchoResult, TemplateConfig};
+use std::sync::Arc;
+
+/// Blocking variant of `echo`
+pub fn echo_blocking(
+    input: String,
+    token: Option<Arc<CancellationToken>>,
+    timeout_ms: Option<u64>,
+) -> TemplateResult<Option<EchoResult>> {
+    runtime::block_on(template::echo(input, token, timeout_ms))
+}
+
+/// Blocking variant of `random`
+pub fn random_blocking() -> f64 {
+    runtime::block_on(template::random())
+}
+
+/// Blocking variant of `random_int`
+pub fn random_int_blocking(
+    min: i64,
+    max: i64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<i64> {
+    runtime::block_on(template::random_int(min, max, token))
+}
+
+/// Blocking variant of `random_choice`
+pub fn random_choice_blocking(
+    items: Vec<String>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<String> {
+    runtime::block_on(template::random_choice(items, token))
+}
+
+/// Blocking variant of `random_normal`
+pub fn random_normal_blocking(
+    mean: f64,
+    std_dev: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    runtime::block_on(template::random_normal(mean, std_dev, token))
+}
+
+/// Blocking variant of `random_exponential`
+pub fn random_exponential_blocking(
+    lambda: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    runtime::block_on(template::random_exponential(lambda, token))
+}
+
+/// Blocking variant of `random_uniform`
+pub fn random_uniform_blocking(
+    low: f64,
+    high: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    runtime::block_on(template::random_uniform(low, high, token))
+}
+
+/// Blocking variant of `random_seeded`
+pub fn random_seeded_blocking(seed: u64) -> f64 {
+    runtime::block_on(template::random_seeded(seed))
+}
+
+/// Blocking variant of `random_bytes`
+pub fn random_bytes_blocking(
+    len: u32,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<u8>> {
+    runtime::block_on(template::random_bytes(len, token))
+}
+
+/// Blocking variant of `secure_random_bytes`
+pub fn secure_random_bytes_blocking(
+    len: u32,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<u8>> {
+    runtime::block_on(secure_random::secure_random_bytes(len, token))
+}
+
+/// Blocking variant of `secure_random_double`
+pub fn secure_random_double_blocking(token: Option<Arc<CancellationToken>>) -> TemplateResult<f64> {
+    runtime::block_on(secure_random::secure_random_double(token))
+}
+
+/// Blocking variant of `generate_uuid_v4`
+pub fn generate_uuid_v4_blocking() -> String {
+    runtime::block_on(ids::generate_uuid_v4())
+}
+
+/// Blocking variant of `generate_uuid_v7`
+pub fn generate_uuid_v7_blocking() -> String {
+    runtime::block_on(ids::generate_uuid_v7())
+}
+
+/// Blocking variant of `parse_uuid`
+pub fn parse_uuid_blocking(
+    input: String,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<ParsedUuid> {
+    runtime::block_on(ids::parse_uuid(input, token))
+}
+
+/// Blocking variant of `is_valid_uuid`
+pub fn is_valid_uuid_blocking(input: String) -> bool {
+    runtime::block_on(ids::is_valid_uuid(input))
+}
+
+/// Blocking variant of `discover_models`
+pub fn discover_models_blocking(
+    directory: String,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<DiscoveredModel>> {
+    runtime::block_on(models::discover_models(directory, token))
+}
+
+impl TemplateConfig {
+    /// Blocking variant of `validate_and_echo`
+    pub fn validate_and_echo_blocking(
+        &self,
+        input: String,
+        token: Option<Arc<CancellationToken>>,
+        timeout_ms: Option<u64>,
+    ) -> TemplateResult<Option<EchoResult>> {
+        runtime::block_on(self.validate_and_echo(input, token, timeout_ms))
+    }
+}
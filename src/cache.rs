@@ -0,0 +1,235 @@
+//! In-memory cache with TTL, entry-count, and size limits
+//!
+//! `Cache` keeps byte values under string keys and evicts the least recently
+//! used entries once it holds more than `max_entries` entries or `max_bytes`
+//! bytes. Entries can also expire after a time to live. The library keeps one
+//! shared instance for its own derived data, such as parsed model headers,
+//! which `on_memory_pressure` trims along with the other buffers.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Entries kept by the shared cache
+const SHARED_MAX_ENTRIES: u32 = 1_024;
+
+/// Bytes kept by the shared cache (16MB)
+const SHARED_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Counters and current size of a `Cache`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Lookups that found a live entry
+    pub hits: u64,
+    /// Lookups that found nothing or an expired entry
+    pub misses: u64,
+    /// Entries removed to stay within the entry or byte limit
+    pub evictions: u64,
+    /// Entries removed because their time to live ran out
+    pub expirations: u64,
+    /// Entries currently held
+    pub entry_count: u64,
+    /// Bytes currently held, counting keys and values
+    pub total_bytes: u64,
+}
+
+/// Least-recently-used cache of byte values
+pub struct Cache {
+    max_entries: u32,
+    max_bytes: u64,
+    default_ttl: Option<Duration>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, Entry>,
+    /// Keys by last use, oldest first
+    recency: BTreeMap<u64, String>,
+    next_use: u64,
+    stats: CacheStats,
+}
+
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Option<Instant>,
+    last_use: u64,
+}
+
+impl Entry {
+    fn size(&self, key: &str) -> u64 {
+        (key.len() + self.value.len()) as u64
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+impl Cache {
+    /// Create an empty cache
+    ///
+    /// A `max_entries` or `max_bytes` of 0 means no limit. Entries stored
+    /// without their own time to live expire after `default_ttl_ms`, or never
+    /// if it is `None`.
+    pub fn new(max_entries: u32, max_bytes: u64, default_ttl_ms: Option<u64>) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            default_ttl: default_ttl_ms.map(Duration::from_millis),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// The value stored under `key`, unless it is missing or expired
+    pub fn get(&self, key: String) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match state.entries.get(&key) {
+            Some(entry) if entry.is_expired(now) => {
+                state.remove(&key);
+                state.stats.expirations += 1;
+                state.stats.misses += 1;
+                None
+            }
+            Some(_) => {
+                state.stats.hits += 1;
+                let value = state.touch(&key);
+                Some(value)
+            }
+            None => {
+                state.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores `value` under `key`, replacing any previous value
+    ///
+    /// The entry expires after `ttl_ms` if given, else after the cache's
+    /// default time to live. Least recently used entries are evicted to make
+    /// room; a value larger than `max_bytes` on its own is not stored.
+    pub fn set(&self, key: String, value: Vec<u8>, ttl_ms: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        state.remove(&key);
+        let size = (key.len() + value.len()) as u64;
+        if self.max_bytes > 0 && size > self.max_bytes {
+            log::debug!("Not caching '{}': {} bytes exceeds the limit", key, size);
+            return;
+        }
+        let expires_at = ttl_ms
+            .map(Duration::from_millis)
+            .or(self.default_ttl)
+            .map(|ttl| Instant::now() + ttl);
+        state.insert(key, value, expires_at);
+        self.evict(&mut state);
+    }
+
+    /// Removes `key`, returning whether it was present
+    pub fn remove(&self, key: String) -> bool {
+        self.state.lock().unwrap().remove(&key).is_some()
+    }
+
+    /// Removes every entry; counters are kept
+    pub fn clear(&self) {
+        self.trim(0);
+    }
+
+    /// Removes expired entries now instead of on their next lookup
+    ///
+    /// Returns the number of entries removed.
+    pub fn purge_expired(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let expired: Vec<String> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            state.remove(key);
+        }
+        state.stats.expirations += expired.len() as u64;
+        expired.len() as u32
+    }
+
+    /// Hit, miss, and eviction counters with the current size
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// Evicts least recently used entries until at most `keep` remain
+    ///
+    /// Returns the bytes released. Trimming does not count as eviction.
+    pub(crate) fn trim(&self, keep: usize) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let before = state.stats.total_bytes;
+        while state.entries.len() > keep {
+            state.remove_oldest();
+        }
+        before - state.stats.total_bytes
+    }
+
+    /// Evicts least recently used entries until the limits are met
+    fn evict(&self, state: &mut CacheState) {
+        while (self.max_entries > 0 && state.entries.len() > self.max_entries as usize)
+            || (self.max_bytes > 0 && state.stats.total_bytes > self.max_bytes)
+        {
+            state.remove_oldest();
+            state.stats.evictions += 1;
+        }
+    }
+}
+
+impl CacheState {
+    fn insert(&mut self, key: String, value: Vec<u8>, expires_at: Option<Instant>) {
+        let last_use = self.next_use();
+        let entry = Entry {
+            value,
+            expires_at,
+            last_use,
+        };
+        self.stats.total_bytes += entry.size(&key);
+        self.recency.insert(last_use, key.clone());
+        self.entries.insert(key, entry);
+        self.stats.entry_count = self.entries.len() as u64;
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_use);
+        self.stats.total_bytes -= entry.size(key);
+        self.stats.entry_count = self.entries.len() as u64;
+        Some(entry)
+    }
+
+    fn remove_oldest(&mut self) {
+        if let Some((_, key)) = self.recency.pop_first() {
+            self.remove(&key);
+        }
+    }
+
+    /// Marks `key` as just used and returns a copy of its value
+    fn touch(&mut self, key: &str) -> Vec<u8> {
+        let last_use = self.next_use();
+        let entry = self.entries.get_mut(key).expect("touched entries exist");
+        self.recency.remove(&entry.last_use);
+        entry.last_use = last_use;
+        self.recency.insert(last_use, key.to_string());
+        entry.value.clone()
+    }
+
+    fn next_use(&mut self) -> u64 {
+        self.next_use += 1;
+        self.next_use
+    }
+}
+
+/// The library's own cache for derived data
+///
+/// Keys are prefixed by the subsystem that owns them, e.g. `model_metadata:`.
+pub(crate) fn shared() -> &'static Cache {
+    static SHARED: OnceLock<Cache> = OnceLock::new();
+    SHARED.get_or_init(|| Cache::new(SHARED_MAX_ENTRIES, SHARED_MAX_BYTES, None))
+}
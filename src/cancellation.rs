@@ -0,0 +1,288 @@
+//! Cancellation tokens for async operations
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// Callback notified when a token is cancelled, implemented by the host
+pub trait CancellationListener: Send + Sync {
+    /// Called exactly once when the token is cancelled
+    fn on_cancelled(&self);
+}
+
+type CancelCallback = Box<dyn FnOnce() + Send>;
+
+/// Values of `TokenState::status`: not cancelled yet, or why it was
+const ACTIVE: u8 = 0;
+const CANCELLED: u8 = 1;
+const TIMED_OUT: u8 = 2;
+
+/// State shared by all clones of a token
+#[derive(Default)]
+struct TokenState {
+    /// `ACTIVE` until cancelled, then the reason, set once
+    status: AtomicU8,
+    /// Tokens created with `child()`, cancelled together with this one
+    children: Mutex<Vec<Weak<TokenState>>>,
+    /// Callbacks to run once on cancellation
+    callbacks: Mutex<Vec<CancelCallback>>,
+}
+
+impl std::fmt::Debug for TokenState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenState")
+            .field("status", &self.status)
+            .field("children", &self.children.lock().unwrap().len())
+            .field("callbacks", &self.callbacks.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl TokenState {
+    fn is_cancelled(&self) -> bool {
+        self.status.load(Ordering::Acquire) != ACTIVE
+    }
+
+    /// Cancels this state and its descendants for `reason`, iteratively to
+    /// avoid deep recursion; states already cancelled keep their reason
+    fn cancel(self: &Arc<Self>, reason: u8) {
+        let mut pending = vec![self.clone()];
+        while let Some(state) = pending.pop() {
+            if state
+                .status
+                .compare_exchange(ACTIVE, reason, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                continue;
+            }
+            let children = std::mem::take(&mut *state.children.lock().unwrap());
+            pending.extend(children.iter().filter_map(Weak::upgrade));
+
+            // Run callbacks outside the lock so they may use the token
+            let callbacks = std::mem::take(&mut *state.callbacks.lock().unwrap());
+            for callback in callbacks {
+                callback();
+            }
+        }
+    }
+}
+
+/// Deadline of a token created with `with_timeout`
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    timeout_ms: u64,
+}
+
+/// Cancellation token for async operations
+///
+/// Tokens can form a hierarchy with `child()`: cancelling a token cancels
+/// all of its descendants, while cancelling a child leaves its parent alone.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+    /// When the token cancels itself, if created with a timeout
+    deadline: Option<Deadline>,
+}
+
+impl CancellationToken {
+    /// Create a new cancellation token
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(TokenState::default()),
+            deadline: None,
+        }
+    }
+
+    /// Create a token that cancels itself after `duration_ms` milliseconds
+    ///
+    /// A timer on the internal runtime cancels the token when the deadline
+    /// passes, so hosts do not need to run their own timers. Functions given
+    /// a token that ran out of time fail with `TemplateError::Timeout`
+    /// rather than `OperationCancelled`.
+    pub fn with_timeout(duration_ms: u64) -> Self {
+        let duration = Duration::from_millis(duration_ms);
+        let token = Self {
+            state: Arc::new(TokenState::default()),
+            deadline: Some(Deadline {
+                at: Instant::now() + duration,
+                timeout_ms: duration_ms,
+            }),
+        };
+
+        let timer = token.clone();
+        runtime::handle().spawn(async move {
+            tokio::time::sleep(duration).await;
+            timer.state.cancel(TIMED_OUT);
+        });
+
+        token
+    }
+
+    /// Create a child token that is cancelled whenever this token is
+    ///
+    /// The child inherits this token's deadline. A child created from an
+    /// already cancelled token starts out cancelled, for the same reason.
+    pub fn child(&self) -> Arc<CancellationToken> {
+        let child = Self {
+            state: Arc::new(TokenState::default()),
+            deadline: self.deadline,
+        };
+
+        // Check under the lock so a concurrent cancel() either sees the
+        // child in the list or the child sees the cancelled flag
+        let mut children = self.state.children.lock().unwrap();
+        let status = self.state.status.load(Ordering::Acquire);
+        if status != ACTIVE {
+            child.state.status.store(status, Ordering::Release);
+        } else {
+            children.retain(|c| c.strong_count() > 0);
+            children.push(Arc::downgrade(&child.state));
+        }
+        drop(children);
+
+        Arc::new(child)
+    }
+
+    /// Register a closure to run exactly once when the token is cancelled
+    ///
+    /// Runs immediately on the calling thread if the token is already
+    /// cancelled; otherwise runs on the thread that cancels the token.
+    pub fn on_cancel<F>(&self, callback: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // Same locking pattern as child(): no callback can be missed
+        let mut callbacks = self.state.callbacks.lock().unwrap();
+        if self.state.is_cancelled() {
+            drop(callbacks);
+            callback();
+        } else {
+            callbacks.push(Box::new(callback));
+        }
+    }
+
+    /// Returns a guard that cancels this token when dropped
+    ///
+    /// Exported async functions hold one for the duration of their work:
+    /// when a Swift `Task` or Kotlin coroutine is cancelled, UniFFI drops the
+    /// Rust future, the guard is dropped with it, and background work stops.
+    pub fn drop_guard(&self) -> CancelOnDrop {
+        CancelOnDrop {
+            token: Some(self.clone()),
+        }
+    }
+
+    /// Register a host listener notified exactly once when the token is cancelled
+    pub fn add_listener(&self, listener: Box<dyn CancellationListener>) {
+        self.on_cancel(move || listener.on_cancelled());
+    }
+
+    /// Cancel the operation and all child tokens
+    pub fn cancel(&self) {
+        self.state.cancel(CANCELLED);
+    }
+
+    /// Check if the operation is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        if self.state.is_cancelled() {
+            return true;
+        }
+        // Don't wait for the timer task if the deadline has already passed
+        if self.deadline_passed() {
+            self.state.cancel(TIMED_OUT);
+            return true;
+        }
+        false
+    }
+
+    /// Whether the token was cancelled because its deadline passed
+    ///
+    /// A token cancelled before its deadline stays cancelled rather than
+    /// timed out once the deadline passes.
+    pub fn is_timed_out(&self) -> bool {
+        self.is_cancelled() && self.state.status.load(Ordering::Acquire) == TIMED_OUT
+    }
+
+    /// Milliseconds until the deadline, or `None` if the token has no timeout
+    ///
+    /// Returns `Some(0)` once the token is cancelled.
+    pub fn remaining_ms(&self) -> Option<u64> {
+        let deadline = self.deadline?;
+        if self.is_cancelled() {
+            return Some(0);
+        }
+        let remaining = deadline.at.saturating_duration_since(Instant::now());
+        Some(remaining.as_millis() as u64)
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d.at)
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cancels its token when dropped, unless disarmed
+#[derive(Debug)]
+#[must_use = "the token is cancelled as soon as the guard is dropped"]
+pub struct CancelOnDrop {
+    token: Option<CancellationToken>,
+}
+
+impl CancelOnDrop {
+    /// Releases the guard without cancelling, returning the token
+    pub fn disarm(mut self) -> CancellationToken {
+        self.token.take().expect("guard already disarmed")
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.token.take() {
+            token.cancel();
+        }
+    }
+}
+
+/// Creates the token an operation checks while it runs
+///
+/// The result is a child of the caller's token, if any, so it observes the
+/// caller's cancellation and deadline but can also be cancelled on its own
+/// (by a `CancelOnDrop` guard) without affecting the caller.
+pub(crate) fn operation_token(token: Option<&CancellationToken>) -> Arc<CancellationToken> {
+    match token {
+        Some(t) => t.child(),
+        None => Arc::new(CancellationToken::new()),
+    }
+}
+
+/// Returns an error for `operation` if the token is cancelled
+///
+/// The error is `Timeout` if the token ran out of time and
+/// `OperationCancelled` otherwise.
+pub(crate) fn check_cancelled(
+    token: Option<&CancellationToken>,
+    operation: &str,
+) -> TemplateResult<()> {
+    match token {
+        Some(t) if t.is_cancelled() => Err(cancelled_error(t, operation)),
+        _ => Ok(()),
+    }
+}
+
+/// The error reported for `operation` when `token` is cancelled
+pub(crate) fn cancelled_error(token: &CancellationToken, operation: &str) -> TemplateError {
+    match token.deadline {
+        Some(deadline) if token.is_timed_out() => {
+            TemplateError::timeout(operation, deadline.timeout_ms)
+        }
+        _ => TemplateError::operation_cancelled(operation),
+    }
+}
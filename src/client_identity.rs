@@ -0,0 +1,319 @@
+//! Client certificates for servers that require mutual TLS (`http` feature)
+//!
+//! A `ClientIdentity` is a certificate chain and the private key to prove
+//! it is ours, set as `NetworkConfig::client_identity`. Every connection
+//! the library opens presents it when the server asks for a client
+//! certificate, so enterprise backends that mandate mTLS work for
+//! requests, WebSockets, and gRPC alike.
+//!
+//! `from_pkcs12` reads a `.p12`/`.pfx` file such as an MDM-issued device
+//! identity; the key is then held in memory. `from_signer` keeps the key in
+//! the Secure Enclave or Android Keystore instead: the library only holds
+//! the certificates and asks the host's `ClientKeySigner` to sign each
+//! handshake. Without the `http` feature the types exist so the bindings
+//! stay the same, but creating an identity fails.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+
+#[cfg(feature = "http")]
+use crate::pinning;
+#[cfg(feature = "http")]
+use crate::secure::SecureBytes;
+#[cfg(feature = "http")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+#[cfg(feature = "http")]
+use rustls::sign::{CertifiedKey, Signer, SigningKey};
+#[cfg(feature = "http")]
+use rustls::{SignatureAlgorithm, SignatureScheme};
+#[cfg(feature = "http")]
+use std::sync::Arc;
+
+/// Type of a key held by a `ClientKeySigner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKeyType {
+    /// ECDSA on P-256, as in the Secure Enclave
+    EcdsaP256,
+    /// ECDSA on P-384
+    EcdsaP384,
+    Rsa,
+}
+
+/// Signature the host is asked to make; each hashes the message itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientSignatureAlgorithm {
+    EcdsaSha256,
+    EcdsaSha384,
+    RsaPssSha256,
+    RsaPssSha384,
+    RsaPkcs1Sha256,
+    RsaPkcs1Sha384,
+}
+
+/// Signs TLS handshakes with a key the library never sees, implemented by the host
+pub trait ClientKeySigner: Send + Sync {
+    /// `message` signed with `algorithm`, DER-encoded for ECDSA, or `None`
+    /// if the key cannot be used, which fails the handshake
+    ///
+    /// Called on a library thread in the middle of a handshake, so it
+    /// should return quickly.
+    fn sign(&self, message: Vec<u8>, algorithm: ClientSignatureAlgorithm) -> Option<Vec<u8>>;
+}
+
+/// Certificate chain and key presented to servers that ask for a client certificate
+pub struct ClientIdentity {
+    certificate_chain: Vec<Vec<u8>>,
+    #[cfg(feature = "http")]
+    key: Arc<CertifiedKey>,
+}
+
+impl ClientIdentity {
+    /// Reads the first key and its certificate chain from PKCS#12 `data`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `data` is not PKCS#12, holds
+    ///   no key with a certificate, the key type is not supported, or the
+    ///   library was built without the `http` feature
+    /// * `Err(TemplateError::EncryptionError)` - If `password` is wrong
+    pub fn from_pkcs12(data: Vec<u8>, password: String) -> TemplateResult<Self> {
+        shield::guard("ClientIdentity::from_pkcs12", || {
+            #[cfg(feature = "http")]
+            {
+                let store =
+                    p12_keystore::KeyStore::from_pkcs12(&data, &password).map_err(|e| match e {
+                        p12_keystore::error::Error::MacError(_) => {
+                            TemplateError::encryption_error("Wrong password for the PKCS#12 data")
+                        }
+                        e => TemplateError::invalid_input(
+                            format!("Not a PKCS#12 identity: {}", e),
+                            None,
+                        ),
+                    })?;
+                let (_, chain) = store.private_key_chain().ok_or_else(|| {
+                    TemplateError::invalid_input(
+                        "The PKCS#12 data holds no private key with a certificate".to_string(),
+                        None,
+                    )
+                })?;
+                let pkcs8 = SecureBytes::new(chain.key().to_vec());
+                let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8.expose()));
+                let key = rustls::crypto::ring::sign::any_supported_type(&key).map_err(|e| {
+                    TemplateError::invalid_input(format!("Unsupported private key: {}", e), None)
+                })?;
+                let certificate_chain: Vec<Vec<u8>> = chain
+                    .chain()
+                    .iter()
+                    .map(|certificate| certificate.as_der().to_vec())
+                    .collect();
+                let identity = Self::certified(certificate_chain, key)?;
+                if let Err(rustls::Error::InconsistentKeys(rustls::InconsistentKeys::KeyMismatch)) =
+                    identity.key.keys_match()
+                {
+                    return Err(TemplateError::invalid_input(
+                        "The private key does not match the certificate".to_string(),
+                        None,
+                    ));
+                }
+                Ok(identity)
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (data, password);
+                Err(requires_feature())
+            }
+        })
+    }
+
+    /// An identity whose key stays with the host: `certificate_chain` holds
+    /// DER certificates, the device's own first, and `signer` signs with
+    /// the matching key of type `key_type`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the chain is empty or holds
+    ///   something other than DER certificates, or the library was built
+    ///   without the `http` feature
+    pub fn from_signer(
+        certificate_chain: Vec<Vec<u8>>,
+        key_type: ClientKeyType,
+        signer: Box<dyn ClientKeySigner>,
+    ) -> TemplateResult<Self> {
+        shield::guard("ClientIdentity::from_signer", || {
+            #[cfg(feature = "http")]
+            {
+                let key = HostKey {
+                    key_type,
+                    signer: Arc::from(signer),
+                };
+                Self::certified(certificate_chain, Arc::new(key))
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (certificate_chain, key_type, signer);
+                Err(requires_feature())
+            }
+        })
+    }
+
+    /// DER certificates presented to servers, the device's own first
+    pub fn certificate_chain(&self) -> Vec<Vec<u8>> {
+        self.certificate_chain.clone()
+    }
+
+    /// Resolver presenting this identity in rustls handshakes
+    #[cfg(feature = "http")]
+    pub(crate) fn resolver(&self) -> Arc<dyn rustls::client::ResolvesClientCert> {
+        Arc::new(rustls::sign::SingleCertAndKey::from(self.key.clone()))
+    }
+
+    #[cfg(feature = "http")]
+    fn certified(
+        certificate_chain: Vec<Vec<u8>>,
+        key: Arc<dyn SigningKey>,
+    ) -> TemplateResult<Self> {
+        if certificate_chain.is_empty() {
+            return Err(TemplateError::invalid_input(
+                "A client identity needs at least one certificate".to_string(),
+                None,
+            ));
+        }
+        if certificate_chain
+            .iter()
+            .any(|certificate| pinning::spki_hash(certificate).is_none())
+        {
+            return Err(TemplateError::invalid_input(
+                "Not a DER-encoded X.509 certificate".to_string(),
+                None,
+            ));
+        }
+        let chain = certificate_chain
+            .iter()
+            .map(|certificate| CertificateDer::from(certificate.clone()))
+            .collect();
+        Ok(Self {
+            certificate_chain,
+            key: Arc::new(CertifiedKey::new(chain, key)),
+        })
+    }
+}
+
+/// Identities are compared by reference, as their keys cannot be read back
+impl PartialEq for ClientIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for ClientIdentity {}
+
+impl std::fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("certificates", &self.certificate_chain.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn requires_feature() -> TemplateError {
+    TemplateError::invalid_input(
+        "ClientIdentity requires the `http` feature".to_string(),
+        None,
+    )
+}
+
+/// Key held by a `ClientKeySigner`
+#[cfg(feature = "http")]
+struct HostKey {
+    key_type: ClientKeyType,
+    signer: Arc<dyn ClientKeySigner>,
+}
+
+#[cfg(feature = "http")]
+impl std::fmt::Debug for HostKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostKey")
+            .field("key_type", &self.key_type)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "http")]
+impl SigningKey for HostKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        // TLS 1.3 only offers RSA-PSS, so PKCS#1 comes last for TLS 1.2 servers
+        let supported: &[(SignatureScheme, ClientSignatureAlgorithm)] = match self.key_type {
+            ClientKeyType::EcdsaP256 => &[(
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+                ClientSignatureAlgorithm::EcdsaSha256,
+            )],
+            ClientKeyType::EcdsaP384 => &[(
+                SignatureScheme::ECDSA_NISTP384_SHA384,
+                ClientSignatureAlgorithm::EcdsaSha384,
+            )],
+            ClientKeyType::Rsa => &[
+                (
+                    SignatureScheme::RSA_PSS_SHA256,
+                    ClientSignatureAlgorithm::RsaPssSha256,
+                ),
+                (
+                    SignatureScheme::RSA_PSS_SHA384,
+                    ClientSignatureAlgorithm::RsaPssSha384,
+                ),
+                (
+                    SignatureScheme::RSA_PKCS1_SHA256,
+                    ClientSignatureAlgorithm::RsaPkcs1Sha256,
+                ),
+                (
+                    SignatureScheme::RSA_PKCS1_SHA384,
+                    ClientSignatureAlgorithm::RsaPkcs1Sha384,
+                ),
+            ],
+        };
+        let (scheme, algorithm) = supported
+            .iter()
+            .find(|(scheme, _)| offered.contains(scheme))?;
+        Some(Box::new(HostSigner {
+            scheme: *scheme,
+            algorithm: *algorithm,
+            signer: self.signer.clone(),
+        }))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        match self.key_type {
+            ClientKeyType::EcdsaP256 | ClientKeyType::EcdsaP384 => SignatureAlgorithm::ECDSA,
+            ClientKeyType::Rsa => SignatureAlgorithm::RSA,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+struct HostSigner {
+    scheme: SignatureScheme,
+    algorithm: ClientSignatureAlgorithm,
+    signer: Arc<dyn ClientKeySigner>,
+}
+
+#[cfg(feature = "http")]
+impl std::fmt::Debug for HostSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostSigner")
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "http")]
+impl Signer for HostSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rustls::Error> {
+        self.signer
+            .sign(message.to_vec(), self.algorithm)
+            .ok_or_else(|| rustls::Error::General("The client key signer declined".to_string()))
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
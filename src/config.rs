@@ -0,0 +1,178 @@
+//! Library-wide configuration shared by every call
+
+use crate::config_file;
+use crate::directories::{self, StorageCategory};
+use crate::error::{
+    PreviewMode, TemplateError, TemplateResult, DEFAULT_PREVIEW_LENGTH, MAX_INPUT_SIZE,
+};
+use crate::events::{self, LibraryEvent};
+use crate::logging;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Verbosity of library logging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    /// Logging disabled
+    Off,
+    /// Errors only
+    Error,
+    /// Warnings and errors
+    Warn,
+    /// Informational messages and above
+    Info,
+    /// Debug messages and above
+    Debug,
+    /// Everything
+    Trace,
+}
+
+/// Library-wide defaults set once at app startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryConfig {
+    /// Maximum input size accepted by `echo`, in bytes
+    pub max_input_size: u64,
+    /// Verbosity of library logging
+    pub log_level: LogLevel,
+    /// Worker threads for the internal runtime (only read when it starts)
+    pub runtime_threads: u32,
+    /// Number of input characters shown in `InvalidInput` previews (0 omits them)
+    pub preview_length: u32,
+    /// Whether `InvalidInput` previews show the input or a hash of it
+    pub preview_mode: PreviewMode,
+    /// Whether echo results carry a `CallTiming` breakdown
+    pub collect_timing: bool,
+}
+
+impl LibraryConfig {
+    const DEFAULT: Self = Self {
+        max_input_size: MAX_INPUT_SIZE as u64,
+        log_level: LogLevel::Warn,
+        runtime_threads: 1,
+        preview_length: DEFAULT_PREVIEW_LENGTH,
+        preview_mode: PreviewMode::Truncate,
+        collect_timing: false,
+    };
+
+    pub(crate) fn validate(&self) -> TemplateResult<()> {
+        if self.max_input_size == 0 {
+            return Err(TemplateError::invalid_input(
+                "max_input_size must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        if self.runtime_threads == 0 {
+            return Err(TemplateError::invalid_input(
+                "runtime_threads must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static LIBRARY_CONFIG: RwLock<LibraryConfig> = RwLock::new(LibraryConfig::DEFAULT);
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the library-wide configuration at app startup
+///
+/// Call this once before using the library. Functions that are called
+/// without an explicit `TemplateConfig` read their defaults from here.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the configuration is invalid
+/// * `Err(TemplateError::AlreadyInitialized)` - If called more than once;
+///   use `update_config` to change settings afterwards
+pub fn initialize(config: LibraryConfig) -> TemplateResult<()> {
+    shield::guard("initialize", || {
+        config.validate()?;
+        if INITIALIZED.swap(true, Ordering::AcqRel) {
+            return Err(TemplateError::AlreadyInitialized);
+        }
+        logging::apply_level(config.log_level);
+        log::info!("Library initialized with {:?}", config);
+        *LIBRARY_CONFIG.write().unwrap() = config.clone();
+        events::publish(LibraryEvent::ConfigChanged { config });
+        Ok(())
+    })
+}
+
+/// Replaces the library-wide configuration
+///
+/// Takes effect for calls started afterwards. `runtime_threads` is only
+/// read when the internal runtime starts, so changing it later has no effect.
+pub fn update_config(config: LibraryConfig) -> TemplateResult<()> {
+    shield::guard("update_config", || {
+        config.validate()?;
+        logging::apply_level(config.log_level);
+        log::info!("Library configuration updated to {:?}", config);
+        *LIBRARY_CONFIG.write().unwrap() = config.clone();
+        events::publish(LibraryEvent::ConfigChanged { config });
+        Ok(())
+    })
+}
+
+/// Returns the current library-wide configuration
+pub fn get_config() -> LibraryConfig {
+    current()
+}
+
+/// Saves the current library-wide configuration to `path`
+///
+/// The file is written atomically with a schema version, so it can be read
+/// back by `load_config` in this and later releases.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If the file cannot be written
+pub fn save_config(path: String) -> TemplateResult<()> {
+    shield::guard("save_config", || {
+        config_file::save(
+            &directories::resolve(StorageCategory::Data, &path),
+            &current(),
+        )
+    })
+}
+
+/// Restores a configuration saved by `save_config` and applies it
+///
+/// Fields missing from the file keep their defaults, and fields written by
+/// a newer release that this one does not understand are ignored.
+///
+/// # Returns
+///
+/// * `Ok(None)` - If there is no file at `path`; the configuration is unchanged
+/// * `Ok(Some(config))` - The configuration now in effect
+/// * `Err(TemplateError::ParseError)` - If the file is not a saved configuration
+/// * `Err(TemplateError::InvalidInput)` - If the saved configuration is invalid
+pub fn load_config(path: String) -> TemplateResult<Option<LibraryConfig>> {
+    shield::guard("load_config", || {
+        let path = directories::resolve(StorageCategory::Data, &path);
+        let Some(config) = config_file::load::<LibraryConfig>(&path)? else {
+            return Ok(None);
+        };
+        update_config(config.clone())?;
+        Ok(Some(config))
+    })
+}
+
+/// Changes only the log level, for `set_log_level`
+pub(crate) fn set_log_level(level: LogLevel) {
+    LIBRARY_CONFIG.write().unwrap().log_level = level;
+}
+
+/// Snapshot of the current configuration for internal readers
+pub(crate) fn current() -> LibraryConfig {
+    LIBRARY_CONFIG.read().unwrap().clone()
+}
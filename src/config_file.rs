@@ -0,0 +1,99 @@
+//! Versioned configuration files
+//!
+//! Configurations are saved as JSON inside an envelope carrying the schema
+//! version:
+//!
+//! ```json
+//! {"schema_version": 1, "config": {"max_input_size": 1000000, "log_level": "warn"}}
+//! ```
+//!
+//! Reading is forward-compatible, so a file written by a newer release still
+//! loads: missing fields take their defaults, unknown fields are ignored, and
+//! a field whose value cannot be read (e.g. an enum case added later) keeps
+//! its default with a warning.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Version of the configuration file schema written by this release
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    schema_version: u32,
+    config: &'a T,
+}
+
+#[derive(Deserialize)]
+struct StoredEnvelope {
+    schema_version: u32,
+    config: Value,
+}
+
+/// Writes `config` to `path` atomically
+pub(crate) fn save<T: Serialize>(path: &Path, config: &T) -> TemplateResult<()> {
+    let json = serde_json::to_vec_pretty(&Envelope {
+        schema_version: CONFIG_SCHEMA_VERSION,
+        config,
+    })
+    .map_err(|e| TemplateError::json_error(&e))?;
+    write_atomic(path, &json).map_err(|e| TemplateError::io_error(path, &e))
+}
+
+/// Reads a configuration saved with `save`; a missing file gives `None`
+pub(crate) fn load<T>(path: &Path) -> TemplateResult<Option<T>>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    let stored: StoredEnvelope =
+        serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))?;
+    if stored.schema_version > CONFIG_SCHEMA_VERSION {
+        log::warn!(
+            "{} has schema version {}, newer than {}; reading what is understood",
+            path.display(),
+            stored.schema_version,
+            CONFIG_SCHEMA_VERSION
+        );
+    }
+    let Value::Object(fields) = stored.config else {
+        return Err(TemplateError::parse_error(
+            "JSON",
+            "config must be an object".to_string(),
+        ));
+    };
+    merge_fields(T::default(), fields).map(Some)
+}
+
+/// Overlays each readable field of `fields` on `defaults`
+fn merge_fields<T>(defaults: T, fields: Map<String, Value>) -> TemplateResult<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let Value::Object(mut merged) =
+        serde_json::to_value(defaults).map_err(|e| TemplateError::json_error(&e))?
+    else {
+        unreachable!("configurations serialize as objects");
+    };
+    for (name, value) in fields {
+        let Some(default) = merged.insert(name.clone(), value) else {
+            merged.remove(&name);
+            log::debug!("Ignoring unknown config field '{}'", name);
+            continue;
+        };
+        if serde_json::from_value::<T>(Value::Object(merged.clone())).is_err() {
+            log::warn!("Ignoring unreadable config field '{}'", name);
+            merged.insert(name, default);
+        }
+    }
+    serde_json::from_value(Value::Object(merged)).map_err(|e| TemplateError::json_error(&e))
+}
@@ -0,0 +1,173 @@
+//! Crash records written when the library panics
+//!
+//! Once `enable_crash_reports` is called, every panic in the process is
+//! written as a JSON crash record to the host-chosen directory, whether or
+//! not the panic shield turns it into an error afterwards. On the next launch
+//! the host reads them with `get_pending_crash_reports()`, uploads them, and
+//! removes each one with `delete_crash_report(id)`.
+
+use crate::diagnostics;
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// A panic recorded to disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Unique id, also the file name without `.json`
+    pub id: String,
+    /// Unix timestamp of the panic in milliseconds
+    pub timestamp_ms: u64,
+    /// The panic message
+    pub message: String,
+    /// Source location of the panic (`file:line:column`), if known
+    pub location: Option<String>,
+    /// Name or id of the panicking thread
+    pub thread: String,
+    /// Backtrace of the panicking thread
+    pub backtrace: String,
+    /// Version of this library
+    pub library_version: String,
+    /// Internal steps leading up to the panic; empty without `debug-errors`
+    pub breadcrumbs: Vec<String>,
+}
+
+static CRASH_DIRECTORY: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Starts writing crash records for panics to `directory`
+///
+/// Call at app startup, before other library calls, so early panics are
+/// recorded too. Records from earlier launches are left in place.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If the directory cannot be created
+pub fn enable_crash_reports(directory: String) -> TemplateResult<()> {
+    shield::guard("enable_crash_reports", || {
+        let directory = directories::resolve(StorageCategory::CrashReports, &directory);
+        fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+        *CRASH_DIRECTORY.write().unwrap() = Some(directory);
+        shield::install_hook();
+        Ok(())
+    })
+}
+
+/// Stops writing crash records; existing records are kept
+pub fn disable_crash_reports() {
+    *CRASH_DIRECTORY.write().unwrap() = None;
+}
+
+/// Returns the crash records not yet deleted, oldest first
+///
+/// Records that cannot be read or parsed are skipped.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If crash reports are not enabled
+/// * `Err(TemplateError::IoError)` - If the directory cannot be read
+pub fn get_pending_crash_reports() -> TemplateResult<Vec<CrashReport>> {
+    shield::guard("get_pending_crash_reports", || {
+        let directory = crash_directory()?;
+        let entries =
+            fs::read_dir(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+        let mut reports: Vec<CrashReport> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read_report(&path) {
+                Some(report) => Some(report),
+                None => {
+                    log::warn!("Skipping unreadable crash record {}", path.display());
+                    None
+                }
+            })
+            .collect();
+        reports.sort_by(|a, b| (a.timestamp_ms, &a.id).cmp(&(b.timestamp_ms, &b.id)));
+        Ok(reports)
+    })
+}
+
+/// Deletes a crash record, typically after it has been uploaded
+///
+/// Deleting a record that no longer exists succeeds.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If crash reports are not enabled or
+///   `id` is not a crash record id
+/// * `Err(TemplateError::IoError)` - If the file cannot be deleted
+pub fn delete_crash_report(id: String) -> TemplateResult<()> {
+    shield::guard("delete_crash_report", || {
+        let directory = crash_directory()?;
+        if Uuid::try_parse(&id).is_err() {
+            return Err(TemplateError::invalid_input(
+                "Not a crash report id".to_string(),
+                Some(&id),
+            ));
+        }
+        let path = report_path(&directory, &id);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(TemplateError::io_error(&path, &e)),
+        }
+    })
+}
+
+fn crash_directory() -> TemplateResult<PathBuf> {
+    CRASH_DIRECTORY.read().unwrap().clone().ok_or_else(|| {
+        TemplateError::invalid_input("Crash reports are not enabled".to_string(), None)
+    })
+}
+
+fn report_path(directory: &Path, id: &str) -> PathBuf {
+    directory.join(format!("{}.json", id))
+}
+
+fn read_report(path: &Path) -> Option<CrashReport> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Writes a crash record for a panic, called from the panic hook
+///
+/// Failures are ignored: the process may be about to go down.
+pub(crate) fn record(info: &PanicHookInfo<'_>, location: Option<String>) {
+    let Some(directory) = CRASH_DIRECTORY.read().ok().and_then(|dir| dir.clone()) else {
+        return;
+    };
+    let payload = info.payload();
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    let thread = std::thread::current();
+    let report = CrashReport {
+        id: Uuid::now_v7().hyphenated().to_string(),
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        message,
+        location,
+        thread: thread
+            .name()
+            .map_or_else(|| format!("{:?}", thread.id()), str::to_string),
+        backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        library_version: env!("CARGO_PKG_VERSION").to_string(),
+        breadcrumbs: diagnostics::breadcrumbs(),
+    };
+    if let Ok(json) = serde_json::to_string_pretty(&report) {
+        let _ = files::write_atomic(&report_path(&directory, &report.id), json.as_bytes());
+    }
+}
@@ -0,0 +1,373 @@
+//! SQLite-backed storage (`sqlite` feature)
+//!
+//! `Database` gives both platforms one tested data layer in the Rust core,
+//! instead of Core Data on iOS and Room on Android. Statements run on the
+//! internal runtime's blocking pool, take `?` placeholders bound to
+//! `SqlValue` parameters, and return rows as typed values. Without the
+//! `sqlite` feature the types exist so the bindings stay the same, but
+//! opening a database fails.
+//!
+//! The schema version is kept in SQLite's `user_version`.
+//! `Database::open_with_migrations` runs each migration newer than it in its
+//! own transaction, together with the version bump.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::migrations::MigrationReport;
+use crate::shield;
+use std::sync::Arc;
+
+#[cfg(feature = "sqlite")]
+use crate::cancellation;
+#[cfg(feature = "sqlite")]
+use crate::directories::{self, StorageCategory};
+#[cfg(feature = "sqlite")]
+use crate::migrations;
+#[cfg(feature = "sqlite")]
+use crate::runtime;
+#[cfg(feature = "sqlite")]
+use rusqlite::types::{ToSqlOutput, ValueRef};
+#[cfg(feature = "sqlite")]
+use rusqlite::{params_from_iter, Connection, ToSql};
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+
+/// A value bound to a statement parameter or read from a column
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Integer { value: i64 },
+    Real { value: f64 },
+    Text { value: String },
+    Blob { value: Vec<u8> },
+}
+
+/// One result row, with values in column order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlRow {
+    pub values: Vec<SqlValue>,
+}
+
+/// Rows returned by `Database::query`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    /// Column names, in the order of each row's values
+    pub columns: Vec<String>,
+    pub rows: Vec<SqlRow>,
+}
+
+/// A statement with its parameters, for `Database::transaction`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    pub sql: String,
+    pub params: Vec<SqlValue>,
+}
+
+/// Statements that upgrade a `Database` to schema `version`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseMigration {
+    pub version: u32,
+    pub statements: Vec<SqlStatement>,
+}
+
+/// Connection to a SQLite database file
+///
+/// Statements on one `Database` run one at a time; open the same file more
+/// than once to read while another connection writes.
+pub struct Database {
+    #[cfg(feature = "sqlite")]
+    connection: Arc<Mutex<Connection>>,
+    report: MigrationReport,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it if missing
+    ///
+    /// `:memory:` opens a private in-memory database.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::DatabaseError)` - If the file cannot be opened as a database
+    /// * `Err(TemplateError::InvalidInput)` - If the library was built without
+    ///   the `sqlite` feature
+    pub fn open(path: String) -> TemplateResult<Self> {
+        shield::guard("Database::open", || Self::open_migrated(path, &[]))
+    }
+
+    /// Opens the database at `path` and applies the migrations it has not seen yet
+    ///
+    /// `migrations` must be in ascending version order. Each pending migration
+    /// runs in its own transaction that also records its version, so a failed
+    /// migration is rolled back and the ones before it are kept.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the versions are not ascending
+    ///   and greater than 0, or the database has a newer schema version than
+    ///   the last migration
+    /// * `Err(TemplateError::DatabaseError)` - If the file cannot be opened or
+    ///   a migration statement fails
+    pub fn open_with_migrations(
+        path: String,
+        migrations: Vec<DatabaseMigration>,
+    ) -> TemplateResult<Self> {
+        shield::guard("Database::open_with_migrations", || {
+            Self::open_migrated(path, &migrations)
+        })
+    }
+
+    /// The schema version found on open and the migrations applied since
+    pub fn migration_report(&self) -> MigrationReport {
+        self.report.clone()
+    }
+
+    fn open_migrated(path: String, migrations: &[DatabaseMigration]) -> TemplateResult<Self> {
+        #[cfg(feature = "sqlite")]
+        {
+            let path = match path.as_str() {
+                ":memory:" => path.into(),
+                _ => directories::resolve(StorageCategory::Data, &path),
+            };
+            let mut connection =
+                Connection::open(&path).map_err(|e| TemplateError::sqlite_error(&e))?;
+            let report = migrate(&mut connection, migrations)?;
+            if !report.applied.is_empty() {
+                log::info!(
+                    "Migrated {} from schema {} to {}",
+                    path.display(),
+                    report.from_version,
+                    report.to_version
+                );
+            }
+            Ok(Self {
+                connection: Arc::new(Mutex::new(connection)),
+                report,
+            })
+        }
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let _ = (path, migrations);
+            Err(unavailable())
+        }
+    }
+
+    /// Runs a statement that returns no rows (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(count)` - The number of rows inserted, updated, or deleted
+    /// * `Err(TemplateError::DatabaseError)` - If the statement fails
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn execute(
+        &self,
+        sql: String,
+        params: Vec<SqlValue>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<u64> {
+        shield::guard_async("Database::execute", async move {
+            #[cfg(feature = "sqlite")]
+            {
+                self.run("database_execute", token, move |connection| {
+                    Ok(connection.execute(&sql, params_from_iter(&params))? as u64)
+                })
+                .await
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = (sql, params, token);
+                Err(unavailable())
+            }
+        })
+        .await
+    }
+
+    /// Runs a query and returns all of its rows (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::DatabaseError)` - If the query fails
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn query(
+        &self,
+        sql: String,
+        params: Vec<SqlValue>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<QueryResult> {
+        shield::guard_async("Database::query", async move {
+            #[cfg(feature = "sqlite")]
+            {
+                self.run("database_query", token, move |connection| {
+                    let mut statement = connection.prepare(&sql)?;
+                    let columns: Vec<String> = statement
+                        .column_names()
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect();
+                    let mut rows = Vec::new();
+                    let mut cursor = statement.query(params_from_iter(&params))?;
+                    while let Some(row) = cursor.next()? {
+                        let values = (0..columns.len())
+                            .map(|index| row.get_ref(index).map(SqlValue::from))
+                            .collect::<rusqlite::Result<_>>()?;
+                        rows.push(SqlRow { values });
+                    }
+                    Ok(QueryResult { columns, rows })
+                })
+                .await
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = (sql, params, token);
+                Err(unavailable())
+            }
+        })
+        .await
+    }
+
+    /// Runs statements in one transaction, all or nothing (async)
+    ///
+    /// If any statement fails or the token is cancelled, every change is
+    /// rolled back.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(counts)` - The rows changed by each statement, in order
+    /// * `Err(TemplateError::DatabaseError)` - If a statement or the commit fails
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn transaction(
+        &self,
+        statements: Vec<SqlStatement>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<Vec<u64>> {
+        shield::guard_async("Database::transaction", async move {
+            #[cfg(feature = "sqlite")]
+            {
+                self.run("database_transaction", token, move |connection| {
+                    let transaction = connection.transaction()?;
+                    let mut counts = Vec::with_capacity(statements.len());
+                    for statement in &statements {
+                        let count = transaction
+                            .execute(&statement.sql, params_from_iter(&statement.params))?;
+                        counts.push(count as u64);
+                    }
+                    transaction.commit()?;
+                    Ok(counts)
+                })
+                .await
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                let _ = (statements, token);
+                Err(unavailable())
+            }
+        })
+        .await
+    }
+
+    /// Runs `work` on the blocking pool, interrupting it if the token is cancelled
+    #[cfg(feature = "sqlite")]
+    async fn run<T, F>(
+        &self,
+        operation: &'static str,
+        token: Option<Arc<CancellationToken>>,
+        work: F,
+    ) -> TemplateResult<T>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        cancellation::check_cancelled(token.as_deref(), operation)?;
+
+        let op_token = cancellation::operation_token(token.as_deref());
+        let guard = op_token.drop_guard();
+
+        let connection = self.connection.clone();
+        let worker_token = op_token.clone();
+        let result = runtime::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap();
+            let interrupt = connection.get_interrupt_handle();
+            worker_token.on_cancel(move || interrupt.interrupt());
+            work(&mut connection)
+        })
+        .await;
+
+        guard.disarm();
+
+        result.map_err(|e| {
+            if op_token.is_cancelled() {
+                cancellation::cancelled_error(&op_token, operation)
+            } else {
+                TemplateError::sqlite_error(&e)
+            }
+        })
+    }
+}
+
+/// Applies the migrations newer than the database's `user_version`
+#[cfg(feature = "sqlite")]
+fn migrate(
+    connection: &mut Connection,
+    migrations: &[DatabaseMigration],
+) -> TemplateResult<MigrationReport> {
+    let sqlite_error = |e: rusqlite::Error| TemplateError::sqlite_error(&e);
+    let version: u32 = connection
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(sqlite_error)?;
+    let mut report = MigrationReport::unchanged(version);
+    if migrations.is_empty() {
+        return Ok(report);
+    }
+    let versions: Vec<u32> = migrations.iter().map(|m| m.version).collect();
+    for index in migrations::pending(version, &versions)? {
+        let migration = &migrations[index];
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for statement in &migration.statements {
+            transaction
+                .execute(&statement.sql, params_from_iter(&statement.params))
+                .map_err(sqlite_error)?;
+        }
+        transaction
+            .pragma_update(None, "user_version", migration.version)
+            .map_err(sqlite_error)?;
+        transaction.commit().map_err(sqlite_error)?;
+        report.applied.push(migration.version);
+        report.to_version = migration.version;
+    }
+    Ok(report)
+}
+
+/// The error for database calls in builds without the `sqlite` feature
+#[cfg(not(feature = "sqlite"))]
+fn unavailable() -> TemplateError {
+    TemplateError::invalid_input("Database requires the `sqlite` feature".to_string(), None)
+}
+
+#[cfg(feature = "sqlite")]
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            Self::Null => ValueRef::Null,
+            Self::Integer { value } => ValueRef::Integer(*value),
+            Self::Real { value } => ValueRef::Real(*value),
+            Self::Text { value } => ValueRef::Text(value.as_bytes()),
+            Self::Blob { value } => ValueRef::Blob(value),
+        }))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<ValueRef<'_>> for SqlValue {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Self::Null,
+            ValueRef::Integer(value) => Self::Integer { value },
+            ValueRef::Real(value) => Self::Real { value },
+            ValueRef::Text(text) => Self::Text {
+                value: String::from_utf8_lossy(text).into_owned(),
+            },
+            ValueRef::Blob(blob) => Self::Blob {
+                value: blob.to_vec(),
+            },
+        }
+    }
+}
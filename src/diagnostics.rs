@@ -0,0 +1,75 @@
+//! Debug context captured into errors with the `debug-errors` feature
+//!
+//! Operations record breadcrumbs of their internal steps as they run. When
+//! one of them fails, the trail and a backtrace are attached to the error as
+//! `debug_info`. Without the feature, breadcrumbs compile to nothing and
+//! `debug_info` is always `None`.
+
+#[cfg(feature = "debug-errors")]
+use std::cell::RefCell;
+
+/// Most breadcrumbs kept per thread; older ones are dropped
+#[cfg(feature = "debug-errors")]
+const MAX_BREADCRUMBS: usize = 32;
+
+#[cfg(feature = "debug-errors")]
+thread_local! {
+    static BREADCRUMBS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records an internal step; arguments are only formatted with `debug-errors`
+macro_rules! breadcrumb {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "debug-errors")]
+        $crate::diagnostics::push_breadcrumb(format!($($arg)*));
+    }};
+}
+pub(crate) use breadcrumb;
+
+/// Starts a new breadcrumb trail for `operation` on this thread
+pub(crate) fn begin(operation: &str) {
+    #[cfg(feature = "debug-errors")]
+    BREADCRUMBS.with(|trail| {
+        let mut trail = trail.borrow_mut();
+        trail.clear();
+        trail.push(operation.to_string());
+    });
+    #[cfg(not(feature = "debug-errors"))]
+    let _ = operation;
+}
+
+#[cfg(feature = "debug-errors")]
+pub(crate) fn push_breadcrumb(step: String) {
+    BREADCRUMBS.with(|trail| {
+        let mut trail = trail.borrow_mut();
+        if trail.len() >= MAX_BREADCRUMBS {
+            trail.remove(0);
+        }
+        trail.push(step);
+    });
+}
+
+/// Breadcrumbs on this thread, oldest first; empty without `debug-errors`
+pub(crate) fn breadcrumbs() -> Vec<String> {
+    #[cfg(feature = "debug-errors")]
+    {
+        BREADCRUMBS.with(|trail| trail.borrow().clone())
+    }
+    #[cfg(not(feature = "debug-errors"))]
+    Vec::new()
+}
+
+/// Breadcrumbs on this thread and a backtrace, for attaching to an error
+pub(crate) fn capture() -> Option<String> {
+    #[cfg(feature = "debug-errors")]
+    {
+        let trail = BREADCRUMBS.with(|trail| trail.borrow().join("\n  "));
+        Some(format!(
+            "breadcrumbs:\n  {}\nbacktrace:\n{}",
+            trail,
+            std::backtrace::Backtrace::force_capture()
+        ))
+    }
+    #[cfg(not(feature = "debug-errors"))]
+    None
+}
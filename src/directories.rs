@@ -0,0 +1,221 @@
+//! App directories chosen by the host, shared by every subsystem
+//!
+//! iOS and Android decide where an app may write and what happens to each
+//! location: caches can be purged by the OS and are not backed up, app data
+//! is kept and backed up, and temporary files can vanish at any time. The
+//! host passes its three directories to `set_app_directories` once at
+//! startup (on iOS `Library/Caches`, `Library/Application Support`, and
+//! `NSTemporaryDirectory()`; on Android `cacheDir`, `filesDir`, and a
+//! subdirectory of `cacheDir`).
+//!
+//! From then on, a relative path given to any function that stores files is
+//! resolved inside the directory for its `StorageCategory`, so logs and
+//! downloads land in the cache and stores and crash reports in app data
+//! without the host repeating those rules. Absolute paths are used as given.
+//! `get_storage_breakdown` reports the bytes used in each category.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use crate::shield;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// The directories passed to `set_app_directories`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppDirectories {
+    /// Purgeable, not backed up
+    pub cache_dir: String,
+    /// Kept and backed up
+    pub data_dir: String,
+    /// May be cleared at any time
+    pub temp_dir: String,
+}
+
+/// What a stored file is for, which decides where it goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageCategory {
+    /// Rotating log files, in `<cache_dir>/logs`
+    Logs,
+    /// Crash reports awaiting upload, in `<data_dir>/crash_reports`
+    CrashReports,
+    /// Models and other re-downloadable artifacts, in `<cache_dir>/downloads`
+    Downloads,
+    /// Anything else in `cache_dir`
+    Cache,
+    /// Stores, history, and settings in `data_dir`
+    Data,
+    /// Short-lived files such as log archives, in `temp_dir`
+    Temp,
+}
+
+impl StorageCategory {
+    const ALL: [StorageCategory; 6] = [
+        Self::Logs,
+        Self::CrashReports,
+        Self::Downloads,
+        Self::Cache,
+        Self::Data,
+        Self::Temp,
+    ];
+
+    fn directory(self, directories: &AppDirectories) -> PathBuf {
+        match self {
+            Self::Logs => Path::new(&directories.cache_dir).join("logs"),
+            Self::CrashReports => Path::new(&directories.data_dir).join("crash_reports"),
+            Self::Downloads => Path::new(&directories.cache_dir).join("downloads"),
+            Self::Cache => PathBuf::from(&directories.cache_dir),
+            Self::Data => PathBuf::from(&directories.data_dir),
+            Self::Temp => PathBuf::from(&directories.temp_dir),
+        }
+    }
+}
+
+/// Bytes stored in one category
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub category: StorageCategory,
+    pub path: String,
+    /// Size of the files in the directory, not counting the directories of
+    /// other categories nested inside it
+    pub bytes: u64,
+}
+
+/// Result of `get_storage_breakdown`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageBreakdown {
+    /// One entry per category
+    pub usage: Vec<StorageUsage>,
+    pub total_bytes: u64,
+}
+
+static DIRECTORIES: RwLock<Option<AppDirectories>> = RwLock::new(None);
+
+/// Sets the app's cache, data, and temporary directories
+///
+/// Call once at startup, before enabling logging or opening stores. The
+/// directories are created if missing. Calling it again switches to the new
+/// directories for files opened afterwards.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If a path is not absolute
+/// * `Err(TemplateError::IoError)` - If a directory cannot be created
+pub fn set_app_directories(
+    cache_dir: String,
+    data_dir: String,
+    temp_dir: String,
+) -> TemplateResult<()> {
+    shield::guard("set_app_directories", || {
+        for directory in [&cache_dir, &data_dir, &temp_dir] {
+            let path = Path::new(directory);
+            if !path.is_absolute() {
+                return Err(TemplateError::invalid_input(
+                    format!("App directory must be an absolute path: '{}'", directory),
+                    None,
+                ));
+            }
+            fs::create_dir_all(path).map_err(|e| TemplateError::io_error(path, &e))?;
+        }
+        *DIRECTORIES.write().unwrap() = Some(AppDirectories {
+            cache_dir,
+            data_dir,
+            temp_dir,
+        });
+        Ok(())
+    })
+}
+
+/// The directories set with `set_app_directories`, if any
+pub fn get_app_directories() -> Option<AppDirectories> {
+    DIRECTORIES.read().unwrap().clone()
+}
+
+/// The directory where files of `category` are stored
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `set_app_directories` has not been called
+pub fn storage_directory(category: StorageCategory) -> TemplateResult<String> {
+    shield::guard("storage_directory", || {
+        let directories = DIRECTORIES.read().unwrap();
+        let directories = directories.as_ref().ok_or_else(not_set)?;
+        Ok(category
+            .directory(directories)
+            .to_string_lossy()
+            .into_owned())
+    })
+}
+
+/// Bytes used by each storage category (async)
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `set_app_directories` has not been called
+/// * `Err(TemplateError::IoError)` - If a directory cannot be read
+pub async fn get_storage_breakdown() -> TemplateResult<StorageBreakdown> {
+    shield::guard_async("get_storage_breakdown", async move {
+        let directories = get_app_directories().ok_or_else(not_set)?;
+        runtime::spawn_blocking(move || {
+            let roots: Vec<PathBuf> = StorageCategory::ALL
+                .iter()
+                .map(|category| category.directory(&directories))
+                .collect();
+            let mut usage = Vec::with_capacity(roots.len());
+            for (category, root) in StorageCategory::ALL.into_iter().zip(&roots) {
+                let others: Vec<&PathBuf> = roots.iter().filter(|other| *other != root).collect();
+                usage.push(StorageUsage {
+                    category,
+                    path: root.to_string_lossy().into_owned(),
+                    bytes: directory_size(root, &others)?,
+                });
+            }
+            Ok(StorageBreakdown {
+                total_bytes: usage.iter().map(|u| u.bytes).sum(),
+                usage,
+            })
+        })
+        .await
+    })
+    .await
+}
+
+/// `path` resolved inside the directory for `category` if it is relative
+/// and app directories are set, otherwise unchanged
+pub(crate) fn resolve(category: StorageCategory, path: &str) -> PathBuf {
+    let given = Path::new(path);
+    match DIRECTORIES.read().unwrap().as_ref() {
+        Some(directories) if given.is_relative() => category.directory(directories).join(given),
+        _ => given.to_path_buf(),
+    }
+}
+
+fn not_set() -> TemplateError {
+    TemplateError::invalid_input("set_app_directories has not been called".to_string(), None)
+}
+
+/// Total size of the files under `directory`, skipping `excluded`
+/// directories and not following symlinks; a missing directory is empty
+fn directory_size(directory: &Path, excluded: &[&PathBuf]) -> TemplateResult<u64> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(TemplateError::io_error(directory, &e)),
+    };
+    let mut total = 0;
+    for entry in entries {
+        let entry = entry.map_err(|e| TemplateError::io_error(directory, &e))?;
+        let path = entry.path();
+        let metadata = entry
+            .metadata()
+            .map_err(|e| TemplateError::io_error(&path, &e))?;
+        if metadata.is_dir() {
+            if !excluded.iter().any(|excluded| **excluded == path) {
+                total += directory_size(&path, excluded)?;
+            }
+        } else {
+            total += metadata.len();
+        }
+    }
+    Ok(total)
+}
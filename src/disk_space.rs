@@ -0,0 +1,107 @@
+//! Free disk space checks before large writes
+//!
+//! Running out of space halfway through copying a model leaves a partial
+//! file behind and surfaces as an `IoError` naming whichever write happened
+//! to fail. Checking first lets the app tell the user how much room is
+//! needed before anything is written.
+//!
+//! The space reported is what the app may use, which on most systems is
+//! less than the free space of the volume: blocks reserved for the root user
+//! on Linux and Android, and quota limits on Windows, are left out.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Bytes available to the app on the volume holding `path`
+///
+/// `path` does not need to exist yet: the nearest existing ancestor is
+/// checked instead, so the destination of a download can be passed as is.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If no ancestor of `path` can be queried
+pub fn get_free_space(path: String) -> TemplateResult<u64> {
+    shield::guard("get_free_space", || available_space(Path::new(&path)))
+}
+
+/// Checks that `required_bytes` can be written on the volume holding `path`
+///
+/// Returns the bytes available, so callers can show how much room is left.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InsufficientStorage)` - If less than `required_bytes` is available
+/// * `Err(TemplateError::IoError)` - If no ancestor of `path` can be queried
+pub fn check_disk_space(path: String, required_bytes: u64) -> TemplateResult<u64> {
+    shield::guard("check_disk_space", || {
+        ensure_space(Path::new(&path), required_bytes)
+    })
+}
+
+/// `check_disk_space` for callers inside the library
+pub(crate) fn ensure_space(path: &Path, required_bytes: u64) -> TemplateResult<u64> {
+    let available = available_space(path)?;
+    if available < required_bytes {
+        return Err(TemplateError::insufficient_storage(
+            path,
+            required_bytes,
+            available,
+        ));
+    }
+    Ok(available)
+}
+
+fn available_space(path: &Path) -> TemplateResult<u64> {
+    let existing = nearest_existing(path);
+    free_bytes(&existing).map_err(|e| TemplateError::io_error(&existing, &e))
+}
+
+/// `path` itself if it exists, otherwise its closest existing ancestor
+fn nearest_existing(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut candidate = absolute.as_path();
+    while !candidate.exists() {
+        match candidate.parent() {
+            Some(parent) => candidate = parent,
+            None => break,
+        }
+    }
+    candidate.to_path_buf()
+}
+
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> io::Result<u64> {
+    let stats = rustix::fs::statvfs(path)?;
+    Ok(stats.f_bavail.saturating_mul(stats.f_frsize))
+}
+
+#[cfg(windows)]
+fn free_bytes(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated and outlives the call; the other
+    // out-pointers may be null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+/// Free space cannot be queried here, so nothing is ever rejected
+#[cfg(not(any(unix, windows)))]
+fn free_bytes(_path: &Path) -> io::Result<u64> {
+    Ok(u64::MAX)
+}
@@ -0,0 +1,1345 @@
+//! Concurrent, resumable file downloads (`http` feature)
+//!
+//! `Downloader` fetches URLs into files, `max_concurrent` at a time. Data is
+//! written to `<destination>.partial` and renamed into place once complete,
+//! so a destination never holds a truncated file. Pausing stops the transfer
+//! but keeps the partial file; resuming asks the server for the remaining
+//! bytes with a `Range` request (guarded by `If-Range` with the ETag or
+//! `Last-Modified` of the first response) and starts over if the server
+//! ignores it or the file changed.
+//!
+//! Large files are split into chunks fetched over parallel connections,
+//! which makes up for the latency of mobile networks, when the server
+//! supports ranges and sends a strong validator. Each chunk is written to a
+//! file of its own, retried on its own, and resumed from what it already
+//! holds; the chunks are joined into the partial file once all are in.
+//! `ParallelChunks` sets the number of connections and the smallest file
+//! worth splitting.
+//!
+//! With a persistence path, unfinished downloads are saved after every state
+//! change. A downloader created with the same path picks them up again:
+//! paused downloads stay paused and the others are queued, continuing from
+//! their partial files. Without the `http` feature the types exist so the
+//! bindings stay the same, but creating a downloader fails.
+//!
+//! Downloads only run while the downloader's `NetworkPolicy` allows the
+//! status reported with `set_network_status`. When it stops allowing it,
+//! running downloads go back to the queue, keeping their partial files, and
+//! they continue as soon as the network allows them again.
+//!
+//! Transfers can be capped in bytes per second, so a large model download
+//! leaves room for the app's other traffic and the user's data plan:
+//! `BandwidthLimits` caps all downloads together, with a lower cap while on
+//! cellular data, and `set_download_bandwidth` caps a single download.
+
+use crate::cancellation::CancellationToken;
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::files;
+use crate::network::{self, NetworkPolicy};
+use crate::runtime;
+use crate::shield;
+use crate::throttle::ByteThrottle;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::watch;
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::disk_space;
+#[cfg(feature = "http")]
+use crate::http::{self, HttpMethod, HttpRequest};
+#[cfg(feature = "http")]
+use crate::interceptors;
+#[cfg(feature = "http")]
+use crate::retry::RetryPolicy;
+#[cfg(feature = "http")]
+use std::io::Write;
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+/// Shortest time between two progress callbacks for one download
+pub const DOWNLOAD_PROGRESS_INTERVAL_MS: u64 = 250;
+
+/// Most connections a single download may use
+pub const MAX_DOWNLOAD_CONNECTIONS: u32 = 16;
+
+/// Suffix of the file a download is written to until it completes
+const PARTIAL_SUFFIX: &str = ".partial";
+
+#[cfg(feature = "http")]
+const OPERATION: &str = "download";
+
+/// Lifecycle state of a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadState {
+    /// Waiting for a free slot, or for the network policy to allow a connection
+    Queued,
+    /// Transferring data
+    Running,
+    /// Stopped by `pause`; the partial file is kept for `resume`
+    Paused,
+    /// The file is at its destination
+    Completed,
+    /// Stopped by an error; `resume` retries from the partial file
+    Failed,
+    /// Stopped by `cancel`; the partial file was deleted
+    Cancelled,
+}
+
+/// Snapshot of a download, passed to listeners and returned by `Downloader::downloads`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadInfo {
+    /// Id assigned by the downloader
+    pub id: u64,
+    pub url: String,
+    /// Where the file is placed once complete
+    pub destination: String,
+    pub state: DownloadState,
+    /// Bytes written so far, including those from before a pause or restart
+    pub downloaded_bytes: u64,
+    /// Size of the whole file, once the server has reported it
+    pub total_bytes: Option<u64>,
+    /// Why the download failed, if it did
+    pub error_message: Option<String>,
+    /// Cap set with `Downloader::set_download_bandwidth`, if any
+    pub max_bytes_per_second: Option<u64>,
+}
+
+/// How a downloader splits large files across connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelChunks {
+    /// Connections per download, up to `MAX_DOWNLOAD_CONNECTIONS`; 1 fetches
+    /// every file over a single connection
+    pub connections: u32,
+    /// Smaller files are fetched over a single connection
+    pub min_file_size_bytes: u64,
+    /// Attempts at each chunk before the download fails
+    pub max_attempts_per_chunk: u32,
+}
+
+impl Default for ParallelChunks {
+    fn default() -> Self {
+        Self {
+            connections: 4,
+            min_file_size_bytes: 16 * 1024 * 1024,
+            max_attempts_per_chunk: 3,
+        }
+    }
+}
+
+/// Caps on the combined transfer rate of a downloader's downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BandwidthLimits {
+    /// Cap on every connection; `None` for no limit
+    pub max_bytes_per_second: Option<u64>,
+    /// Cap while `set_network_status` reports cellular data; the lower of
+    /// the two applies
+    pub cellular_max_bytes_per_second: Option<u64>,
+}
+
+/// Progress of a running download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub id: u64,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    /// Transfer rate since the previous progress callback
+    pub bytes_per_second: u64,
+}
+
+/// Callback notified of download progress and state changes, implemented by the host
+pub trait DownloadListener: Send + Sync {
+    /// Called at most every `DOWNLOAD_PROGRESS_INTERVAL_MS` while a download runs
+    fn on_download_progress(&self, progress: DownloadProgress);
+    /// Called with the download's new state; may be called from any thread
+    fn on_download_state_changed(&self, download: DownloadInfo);
+}
+
+/// Unfinished download as written to the persistence file
+#[derive(Serialize, Deserialize)]
+struct PersistedDownload {
+    id: u64,
+    url: String,
+    destination: String,
+    headers: HashMap<String, String>,
+    paused: bool,
+    total_bytes: Option<u64>,
+    validator: Option<String>,
+    #[serde(default)]
+    max_bytes_per_second: Option<u64>,
+    #[serde(default)]
+    chunks: Option<Vec<u64>>,
+}
+
+/// Downloads files in the background with pause, resume, and progress
+pub struct Downloader {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    max_concurrent: u32,
+    persist_path: Option<PathBuf>,
+    state: Mutex<DownloaderState>,
+    listener: Mutex<Option<Arc<dyn DownloadListener>>>,
+    /// Connections downloads may use; queued downloads wait for one
+    policy: Mutex<NetworkPolicy>,
+    /// Number of queued plus running downloads
+    active: watch::Sender<usize>,
+    limits: Mutex<BandwidthLimits>,
+    /// Shared by every transfer, at the rate `limits` allow on the current network
+    throttle: ByteThrottle,
+    parallel: Mutex<ParallelChunks>,
+}
+
+#[derive(Default)]
+struct DownloaderState {
+    next_id: u64,
+    downloads: BTreeMap<u64, Entry>,
+    running: u32,
+}
+
+struct Entry {
+    info: DownloadInfo,
+    headers: HashMap<String, String>,
+    /// ETag or Last-Modified of the first response, sent as `If-Range`
+    validator: Option<String>,
+    /// Set while running; cancelled to stop the transfer
+    token: Option<Arc<CancellationToken>>,
+    /// What the download becomes once its transfer stops
+    stop: Option<DownloadState>,
+    /// Paces this download alone, at `info.max_bytes_per_second`
+    throttle: Arc<ByteThrottle>,
+    /// Start offsets of the chunks, once the download was split
+    chunks: Option<Vec<u64>>,
+}
+
+/// How a transfer ended, short of an error
+#[cfg(feature = "http")]
+enum Transferred {
+    /// The partial file holds all of these bytes
+    Complete(u64),
+    /// The file is to be fetched in chunks starting at these offsets
+    Split(Vec<u64>),
+    /// The file changed on the server since the chunks were planned
+    Changed,
+}
+
+/// Bytes written by a transfer, reported at most every
+/// `DOWNLOAD_PROGRESS_INTERVAL_MS`
+#[cfg(feature = "http")]
+struct Progress {
+    id: u64,
+    total_bytes: Option<u64>,
+    /// Bytes written, and when and at which count they were last reported
+    counts: Mutex<(u64, Instant, u64)>,
+}
+
+impl Downloader {
+    /// Create a downloader, picking up unfinished downloads from `persist_path` if given
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `max_concurrent` is 0, or the
+    ///   library was built without the `http` feature
+    /// * `Err(TemplateError::IoError)` - If the persistence file cannot be read
+    /// * `Err(TemplateError::ParseError)` - If the persistence file is corrupt
+    pub fn new(max_concurrent: u32, persist_path: Option<String>) -> TemplateResult<Self> {
+        shield::guard("Downloader::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "Downloader requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            if max_concurrent == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_concurrent must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+
+            let persist_path =
+                persist_path.map(|path| directories::resolve(StorageCategory::Data, &path));
+            let resumed = match &persist_path {
+                Some(path) => load_downloads(path)?,
+                None => Vec::new(),
+            };
+
+            let mut state = DownloaderState {
+                next_id: 1,
+                ..DownloaderState::default()
+            };
+            for download in resumed {
+                state.next_id = state.next_id.max(download.id + 1);
+                let downloaded_bytes = downloaded_size(Path::new(&download.destination));
+                state.downloads.insert(
+                    download.id,
+                    Entry {
+                        info: DownloadInfo {
+                            id: download.id,
+                            url: download.url,
+                            destination: download.destination,
+                            state: if download.paused {
+                                DownloadState::Paused
+                            } else {
+                                DownloadState::Queued
+                            },
+                            downloaded_bytes,
+                            total_bytes: download.total_bytes,
+                            error_message: None,
+                            max_bytes_per_second: download.max_bytes_per_second,
+                        },
+                        headers: download.headers,
+                        validator: download.validator,
+                        token: None,
+                        stop: None,
+                        throttle: Arc::new(ByteThrottle::new(download.max_bytes_per_second)),
+                        chunks: download.chunks,
+                    },
+                );
+            }
+
+            let downloader = Self {
+                inner: Arc::new(Inner {
+                    max_concurrent,
+                    persist_path,
+                    state: Mutex::new(state),
+                    listener: Mutex::new(None),
+                    policy: Mutex::new(NetworkPolicy::Any),
+                    active: watch::Sender::new(0),
+                    limits: Mutex::new(BandwidthLimits::default()),
+                    throttle: ByteThrottle::new(None),
+                    parallel: Mutex::new(ParallelChunks::default()),
+                }),
+            };
+            downloader
+                .inner
+                .publish_active(&downloader.inner.state.lock().unwrap());
+            runtime::handle().spawn(Inner::follow_network(Arc::downgrade(&downloader.inner)));
+            Inner::pump(&downloader.inner);
+            Ok(downloader)
+        })
+    }
+
+    /// Registers the listener notified of progress and state changes, replacing any previous one
+    pub fn set_listener(&self, listener: Box<dyn DownloadListener>) {
+        *self.inner.listener.lock().unwrap() = Some(Arc::from(listener));
+    }
+
+    /// Sets which connections downloads may use, e.g. `Unmetered` to keep
+    /// large files off cellular data; the default is `Any`
+    ///
+    /// Running downloads the new policy does not allow go back to the queue.
+    pub fn set_network_policy(&self, policy: NetworkPolicy) {
+        *self.inner.policy.lock().unwrap() = policy;
+        Inner::apply_network(&self.inner);
+    }
+
+    /// The connections downloads may use
+    pub fn network_policy(&self) -> NetworkPolicy {
+        *self.inner.policy.lock().unwrap()
+    }
+
+    /// Caps the combined rate of all downloads; the default is no limit
+    ///
+    /// Running downloads slow down or speed up right away.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If a cap is 0
+    pub fn set_bandwidth_limits(&self, limits: BandwidthLimits) -> TemplateResult<()> {
+        shield::guard("Downloader::set_bandwidth_limits", || {
+            validate_rate(limits.max_bytes_per_second)?;
+            validate_rate(limits.cellular_max_bytes_per_second)?;
+            *self.inner.limits.lock().unwrap() = limits;
+            self.inner.apply_limits();
+            Ok(())
+        })
+    }
+
+    /// The caps on the combined rate of all downloads
+    pub fn bandwidth_limits(&self) -> BandwidthLimits {
+        *self.inner.limits.lock().unwrap()
+    }
+
+    /// Sets how large files are split across connections; downloads that
+    /// were already split keep their chunks
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `connections` is 0 or above
+    ///   `MAX_DOWNLOAD_CONNECTIONS`, or `max_attempts_per_chunk` is 0
+    pub fn set_parallel_chunks(&self, parallel: ParallelChunks) -> TemplateResult<()> {
+        shield::guard("Downloader::set_parallel_chunks", || {
+            if !(1..=MAX_DOWNLOAD_CONNECTIONS).contains(&parallel.connections) {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "connections must be between 1 and {}, got {}",
+                        MAX_DOWNLOAD_CONNECTIONS, parallel.connections
+                    ),
+                    None,
+                ));
+            }
+            if parallel.max_attempts_per_chunk == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_attempts_per_chunk must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+            *self.inner.parallel.lock().unwrap() = parallel;
+            Ok(())
+        })
+    }
+
+    /// How large files are split across connections
+    pub fn parallel_chunks(&self) -> ParallelChunks {
+        *self.inner.parallel.lock().unwrap()
+    }
+
+    /// Caps the rate of one download, on top of the downloader's limits, or
+    /// removes its cap with `None`; the cap is kept across pauses and restarts
+    ///
+    /// Returns `false` if the download does not exist or already finished.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the cap is 0
+    pub fn set_download_bandwidth(
+        &self,
+        id: u64,
+        max_bytes_per_second: Option<u64>,
+    ) -> TemplateResult<bool> {
+        shield::guard("Downloader::set_download_bandwidth", || {
+            validate_rate(max_bytes_per_second)?;
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(entry) = state.downloads.get_mut(&id) else {
+                return Ok(false);
+            };
+            if is_finished(entry.info.state) {
+                return Ok(false);
+            }
+            entry.info.max_bytes_per_second = max_bytes_per_second;
+            entry.throttle.set_rate(max_bytes_per_second);
+            self.inner.persist(&state);
+            Ok(true)
+        })
+    }
+
+    /// Queues a download of `url` to `destination` and returns its id
+    ///
+    /// A relative `destination` is placed in the downloads directory set with
+    /// `set_app_directories`. `headers` are sent with every request for it,
+    /// e.g. an authorization token.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid,
+    ///   or another unfinished download writes to `destination`
+    pub fn enqueue(
+        &self,
+        url: String,
+        destination: String,
+        headers: HashMap<String, String>,
+    ) -> TemplateResult<u64> {
+        shield::guard("Downloader::enqueue", || {
+            // Reject what the transfer would fail on before queueing
+            #[cfg(feature = "http")]
+            let _ = http::with_headers(http::client().get(http::parse_url(&url)?), &headers)?;
+            let destination = directories::resolve(StorageCategory::Downloads, &destination)
+                .to_string_lossy()
+                .into_owned();
+            let info = {
+                let mut state = self.inner.state.lock().unwrap();
+                if state.downloads.values().any(|entry| {
+                    entry.info.destination == destination && !is_finished(entry.info.state)
+                }) {
+                    return Err(TemplateError::invalid_input(
+                        format!("A download to '{}' is already in progress", destination),
+                        None,
+                    ));
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                let info = DownloadInfo {
+                    id,
+                    url,
+                    downloaded_bytes: partial_size(Path::new(&destination)),
+                    destination,
+                    state: DownloadState::Queued,
+                    total_bytes: None,
+                    error_message: None,
+                    max_bytes_per_second: None,
+                };
+                state.downloads.insert(
+                    id,
+                    Entry {
+                        info: info.clone(),
+                        headers,
+                        validator: None,
+                        token: None,
+                        stop: None,
+                        throttle: Arc::new(ByteThrottle::new(None)),
+                        chunks: None,
+                    },
+                );
+                self.inner.persist(&state);
+                self.inner.publish_active(&state);
+                info
+            };
+            self.inner.notify(info.clone());
+            Inner::pump(&self.inner);
+            Ok(info.id)
+        })
+    }
+
+    /// Pauses a queued or running download, keeping what was downloaded
+    ///
+    /// Returns `false` if the download does not exist or is not active.
+    pub fn pause(&self, id: u64) -> bool {
+        self.stop(id, DownloadState::Paused)
+    }
+
+    /// Queues a paused or failed download again, continuing where it stopped
+    ///
+    /// Returns `false` if the download does not exist or is not paused or failed.
+    pub fn resume(&self, id: u64) -> bool {
+        let info = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(entry) = state.downloads.get_mut(&id) else {
+                return false;
+            };
+            if !matches!(
+                entry.info.state,
+                DownloadState::Paused | DownloadState::Failed
+            ) {
+                return false;
+            }
+            entry.info.state = DownloadState::Queued;
+            entry.info.error_message = None;
+            let info = entry.info.clone();
+            self.inner.persist(&state);
+            self.inner.publish_active(&state);
+            info
+        };
+        self.inner.notify(info);
+        Inner::pump(&self.inner);
+        true
+    }
+
+    /// Cancels a download that has not completed and deletes its partial file
+    ///
+    /// Returns `false` if the download does not exist or already finished.
+    pub fn cancel(&self, id: u64) -> bool {
+        self.stop(id, DownloadState::Cancelled)
+    }
+
+    /// Snapshot of every download this downloader knows about, ordered by id
+    pub fn downloads(&self) -> Vec<DownloadInfo> {
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .downloads
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    /// Snapshot of a single download
+    pub fn download(&self, id: u64) -> Option<DownloadInfo> {
+        let state = self.inner.state.lock().unwrap();
+        state.downloads.get(&id).map(|entry| entry.info.clone())
+    }
+
+    /// Waits until no downloads are queued or running (async)
+    pub async fn wait_idle(&self) {
+        let mut receiver = self.inner.active.subscribe();
+        let _ = receiver.wait_for(|active| *active == 0).await;
+    }
+
+    /// Moves a download to `Paused` or `Cancelled`
+    fn stop(&self, id: u64, target: DownloadState) -> bool {
+        let info = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(entry) = state.downloads.get_mut(&id) else {
+                return false;
+            };
+            match entry.info.state {
+                DownloadState::Running => {
+                    // The transfer reports the new state itself once it stops
+                    entry.stop = Some(target);
+                    if let Some(token) = &entry.token {
+                        token.cancel();
+                    }
+                    return true;
+                }
+                DownloadState::Queued => {}
+                DownloadState::Paused | DownloadState::Failed
+                    if target == DownloadState::Cancelled => {}
+                _ => return false,
+            }
+            entry.info.state = target;
+            if target == DownloadState::Cancelled {
+                remove_partial(Path::new(&entry.info.destination));
+                entry.info.downloaded_bytes = 0;
+                entry.chunks = None;
+            }
+            let info = entry.info.clone();
+            self.inner.persist(&state);
+            self.inner.publish_active(&state);
+            info
+        };
+        self.inner.notify(info);
+        true
+    }
+}
+
+impl Inner {
+    /// Starts queued downloads while there are free slots and the network allows them
+    fn pump(inner: &Arc<Self>) {
+        if !inner.network_allowed() {
+            return;
+        }
+        // The status watcher may not have caught up with a status reported
+        // just now, so start transfers with the caps for the current one
+        inner.apply_limits();
+        loop {
+            let (info, token) = {
+                let mut state = inner.state.lock().unwrap();
+                if state.running >= inner.max_concurrent {
+                    return;
+                }
+                let Some(entry) = state
+                    .downloads
+                    .values_mut()
+                    .find(|entry| entry.info.state == DownloadState::Queued)
+                else {
+                    return;
+                };
+                entry.info.state = DownloadState::Running;
+                let token = Arc::new(CancellationToken::new());
+                entry.token = Some(token.clone());
+                let info = entry.info.clone();
+                state.running += 1;
+                (info, token)
+            };
+            inner.notify(info.clone());
+
+            let downloader = inner.clone();
+            runtime::handle().spawn(async move {
+                let outcome = downloader.transfer(info.id, &token).await;
+                downloader.finish(info.id, outcome, &token);
+                Inner::pump(&downloader);
+            });
+        }
+    }
+
+    fn network_allowed(&self) -> bool {
+        self.policy
+            .lock()
+            .unwrap()
+            .allows(network::get_network_status())
+    }
+
+    /// Applies every status the host reports until the downloader is dropped
+    async fn follow_network(inner: Weak<Self>) {
+        let mut status = network::watch_status();
+        while status.changed().await.is_ok() {
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            Inner::apply_network(&inner);
+        }
+    }
+
+    /// Starts queued downloads if the network allows them, or sends running
+    /// ones back to the queue if it does not
+    fn apply_network(inner: &Arc<Self>) {
+        inner.apply_limits();
+        if inner.network_allowed() {
+            Inner::pump(inner);
+            return;
+        }
+        let mut state = inner.state.lock().unwrap();
+        for entry in state.downloads.values_mut() {
+            if let (DownloadState::Running, Some(token)) = (entry.info.state, &entry.token) {
+                // `finish` moves it to `Queued` once the transfer stops
+                entry.stop.get_or_insert(DownloadState::Queued);
+                token.cancel();
+            }
+        }
+    }
+
+    /// Sets the shared rate to the lowest cap that applies on the current network
+    fn apply_limits(&self) {
+        let limits = *self.limits.lock().unwrap();
+        let cellular = match network::get_network_status() {
+            network::NetworkStatus::Cellular => limits.cellular_max_bytes_per_second,
+            _ => None,
+        };
+        let rate = match (limits.max_bytes_per_second, cellular) {
+            (Some(cap), Some(cellular)) => Some(cap.min(cellular)),
+            (cap, cellular) => cap.or(cellular),
+        };
+        self.throttle.set_rate(rate);
+    }
+
+    /// Downloads the rest of the file, over several connections when it is
+    /// large enough, and moves it to its destination
+    #[cfg(feature = "http")]
+    async fn transfer(
+        self: &Arc<Self>,
+        id: u64,
+        token: &Arc<CancellationToken>,
+    ) -> TemplateResult<()> {
+        let destination = {
+            let state = self.state.lock().unwrap();
+            PathBuf::from(&state.downloads[&id].info.destination)
+        };
+        let mut split = true;
+        let downloaded = loop {
+            let chunks = self.state.lock().unwrap().downloads[&id].chunks.clone();
+            let transferred = match chunks {
+                Some(starts) => self.transfer_chunks(id, token, starts).await?,
+                None => self.transfer_whole(id, token, split).await?,
+            };
+            let mut state = self.state.lock().unwrap();
+            let Some(entry) = state.downloads.get_mut(&id) else {
+                return Err(TemplateError::operation_cancelled(OPERATION));
+            };
+            match transferred {
+                Transferred::Complete(downloaded) => break downloaded,
+                Transferred::Split(starts) => entry.chunks = Some(starts),
+                Transferred::Changed => {
+                    // Start over, on one connection so a server that never
+                    // honors `If-Range` cannot keep us here
+                    remove_partial(&destination);
+                    entry.chunks = None;
+                    entry.info.downloaded_bytes = 0;
+                    split = false;
+                }
+            }
+            self.persist(&state);
+        };
+
+        let partial = partial_path(&destination);
+        files::rename_replacing(&partial, &destination)
+            .map_err(|e| TemplateError::io_error(&destination, &e))?;
+        let _ = files::sync_directory(destination.parent().unwrap_or(Path::new(".")));
+        // Chunk files a crash left behind, if the download was not persisted
+        remove_partial(&destination);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.downloads.get_mut(&id) {
+            entry.info.downloaded_bytes = downloaded;
+            entry.info.total_bytes = Some(downloaded);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "http"))]
+    async fn transfer(
+        self: &Arc<Self>,
+        _id: u64,
+        _token: &Arc<CancellationToken>,
+    ) -> TemplateResult<()> {
+        unreachable!("Downloader::new fails without the `http` feature")
+    }
+
+    /// Downloads the rest of the file over one connection, or plans chunks
+    /// if `split` allows and the server supports ranges
+    #[cfg(feature = "http")]
+    async fn transfer_whole(
+        &self,
+        id: u64,
+        token: &CancellationToken,
+        split: bool,
+    ) -> TemplateResult<Transferred> {
+        let (url, destination, mut headers, validator, throttle) = {
+            let state = self.state.lock().unwrap();
+            let entry = &state.downloads[&id];
+            (
+                entry.info.url.clone(),
+                PathBuf::from(&entry.info.destination),
+                entry.headers.clone(),
+                entry.validator.clone(),
+                entry.throttle.clone(),
+            )
+        };
+        let parallel = *self.parallel.lock().unwrap();
+        let split = split && parallel.connections > 1;
+        let partial = partial_path(&destination);
+        if let Some(parent) = partial.parent() {
+            fs::create_dir_all(parent).map_err(|e| TemplateError::io_error(parent, &e))?;
+        }
+        let offset = partial_size(&destination);
+
+        auth::authorize(&url, &mut headers).await?;
+        if offset > 0 {
+            headers.insert("Range".to_string(), format!("bytes={}-", offset));
+            if let Some(validator) = validator {
+                headers.insert("If-Range".to_string(), validator);
+            }
+        } else if split {
+            // A 206 answer tells us the server supports ranges
+            headers.insert("Range".to_string(), "bytes=0-".to_string());
+        }
+        let mut response = send_get(&url, headers, token).await?;
+
+        let status = response.status();
+        let resuming = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+        if !status.is_success() {
+            return Err(TemplateError::network_error(
+                &url,
+                Some(status.as_u16()),
+                format!("Server responded with {}", status),
+            ));
+        }
+        let start = if resuming { offset } else { 0 };
+        let total_bytes = response.content_length().map(|length| start + length);
+        let validator = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .or_else(|| response.headers().get(reqwest::header::LAST_MODIFIED))
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(entry) = state.downloads.get_mut(&id) {
+                entry.info.total_bytes = total_bytes;
+                entry.info.downloaded_bytes = start;
+                entry.validator = validator.clone();
+            }
+            self.persist(&state);
+        }
+        if let Some(length) = response.content_length() {
+            disk_space::ensure_space(&partial, length)?;
+        }
+
+        // Chunks are only safe to combine when `If-Range` can tell that the
+        // file is unchanged, which takes a strong validator
+        let strong = validator.is_some_and(|validator| !validator.starts_with("W/"));
+        if let Some(total) = total_bytes.filter(|total| {
+            split
+                && strong
+                && offset == 0
+                && status == reqwest::StatusCode::PARTIAL_CONTENT
+                && *total >= parallel.min_file_size_bytes
+        }) {
+            // Leftovers of an earlier attempt would count as progress
+            remove_partial(&destination);
+            return Ok(Transferred::Split(plan_chunks(total, parallel.connections)));
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resuming)
+            .truncate(!resuming)
+            .open(&partial)
+            .map_err(|e| TemplateError::io_error(&partial, &e))?;
+        let progress = Progress::new(id, total_bytes, start);
+        self.write_body(
+            &mut response,
+            &mut file,
+            &partial,
+            &throttle,
+            &progress,
+            token,
+        )
+        .await?;
+        let downloaded = progress.downloaded();
+        if let Some(total) = total_bytes.filter(|total| *total != downloaded) {
+            return Err(TemplateError::network_error(
+                &url,
+                Some(status.as_u16()),
+                format!("Connection closed after {} of {} bytes", downloaded, total),
+            ));
+        }
+        file.sync_all()
+            .map_err(|e| TemplateError::io_error(&partial, &e))?;
+        Ok(Transferred::Complete(downloaded))
+    }
+
+    /// Downloads the missing parts of every chunk at once, then joins the
+    /// chunks into the partial file
+    #[cfg(feature = "http")]
+    async fn transfer_chunks(
+        self: &Arc<Self>,
+        id: u64,
+        token: &Arc<CancellationToken>,
+        starts: Vec<u64>,
+    ) -> TemplateResult<Transferred> {
+        let (destination, total_bytes) = {
+            let state = self.state.lock().unwrap();
+            let entry = &state.downloads[&id];
+            (
+                PathBuf::from(&entry.info.destination),
+                entry.info.total_bytes,
+            )
+        };
+        let Some(total) = total_bytes else {
+            return Ok(Transferred::Changed);
+        };
+        let ends: Vec<u64> = starts.iter().skip(1).copied().chain([total]).collect();
+        let mut downloaded = 0;
+        for (index, (start, end)) in starts.iter().zip(&ends).enumerate() {
+            let size = file_size(&chunk_path(&destination, index));
+            if size > end - start {
+                return Ok(Transferred::Changed);
+            }
+            downloaded += size;
+        }
+
+        let progress = Arc::new(Progress::new(id, Some(total), downloaded));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, (start, end)) in starts.iter().zip(&ends).enumerate() {
+            let (inner, progress, token) = (self.clone(), progress.clone(), token.clone());
+            let range = (*start, *end);
+            tasks.spawn_on(
+                async move { inner.fetch_chunk(id, index, range, &progress, &token).await },
+                &runtime::handle(),
+            );
+        }
+        while let Some(joined) = tasks.join_next().await {
+            let fetched = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            if !matches!(fetched, Ok(true)) {
+                // Stop the other chunks before their files are looked at
+                tasks.shutdown().await;
+                return fetched.map(|_| Transferred::Changed);
+            }
+        }
+
+        let chunks = starts.len();
+        runtime::spawn_blocking(move || join_chunks(&destination, chunks)).await?;
+        Ok(Transferred::Complete(total))
+    }
+
+    /// Downloads the rest of chunk `index` of download `id`, covering
+    /// `range`, retrying failures the chunk's own progress survives
+    ///
+    /// Returns `false` if the file changed on the server.
+    #[cfg(feature = "http")]
+    async fn fetch_chunk(
+        &self,
+        id: u64,
+        index: usize,
+        range: (u64, u64),
+        progress: &Progress,
+        token: &CancellationToken,
+    ) -> TemplateResult<bool> {
+        let policy = RetryPolicy {
+            max_attempts: self.parallel.lock().unwrap().max_attempts_per_chunk,
+            ..RetryPolicy::default()
+        };
+        let mut attempt = 1;
+        loop {
+            match self.fetch_range(id, index, range, progress, token).await {
+                Err(e)
+                    if !token.is_cancelled()
+                        && e.is_retryable()
+                        && attempt < policy.max_attempts =>
+                {
+                    log::debug!("Chunk {} of download {} failed, retrying: {}", index, id, e);
+                    let delay = Duration::from_millis(policy.delay(attempt));
+                    runtime::sleep_cancellable(delay, Some(token), OPERATION).await?;
+                    attempt += 1;
+                }
+                fetched => return fetched,
+            }
+        }
+    }
+
+    /// One attempt at the rest of a chunk; see `fetch_chunk`
+    #[cfg(feature = "http")]
+    async fn fetch_range(
+        &self,
+        id: u64,
+        index: usize,
+        (start, end): (u64, u64),
+        progress: &Progress,
+        token: &CancellationToken,
+    ) -> TemplateResult<bool> {
+        let (url, destination, mut headers, validator, throttle) = {
+            let state = self.state.lock().unwrap();
+            let entry = &state.downloads[&id];
+            (
+                entry.info.url.clone(),
+                PathBuf::from(&entry.info.destination),
+                entry.headers.clone(),
+                entry.validator.clone(),
+                entry.throttle.clone(),
+            )
+        };
+        let path = chunk_path(&destination, index);
+        let offset = start + file_size(&path);
+        if offset >= end {
+            return Ok(true);
+        }
+
+        auth::authorize(&url, &mut headers).await?;
+        headers.insert("Range".to_string(), format!("bytes={}-{}", offset, end - 1));
+        if let Some(validator) = validator {
+            headers.insert("If-Range".to_string(), validator);
+        }
+        let mut response = send_get(&url, headers, token).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(TemplateError::network_error(
+                &url,
+                Some(status.as_u16()),
+                format!("Server responded with {}", status),
+            ));
+        }
+        if status != reqwest::StatusCode::PARTIAL_CONTENT {
+            // `If-Range` did not match, so the server sent the new file
+            return Ok(false);
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| TemplateError::io_error(&path, &e))?;
+        let written = self
+            .write_body(&mut response, &mut file, &path, &throttle, progress, token)
+            .await?;
+        if offset + written != end {
+            return Err(TemplateError::network_error(
+                &url,
+                Some(status.as_u16()),
+                format!(
+                    "Connection closed after {} of {} bytes",
+                    offset + written - start,
+                    end - start
+                ),
+            ));
+        }
+        Ok(true)
+    }
+
+    /// Writes the response body to `file`, at the pace the bandwidth caps
+    /// allow; returns how many bytes were written
+    #[cfg(feature = "http")]
+    async fn write_body(
+        &self,
+        response: &mut reqwest::Response,
+        file: &mut fs::File,
+        path: &Path,
+        throttle: &ByteThrottle,
+        progress: &Progress,
+        token: &CancellationToken,
+    ) -> TemplateResult<u64> {
+        let url = response.url().to_string();
+        let mut written = 0;
+        loop {
+            let chunk = tokio::select! {
+                chunk = response.chunk() => chunk.map_err(|e| http::request_error(&url, None, &e))?,
+                error = http::cancelled(Some(token), OPERATION) => return Err(error),
+            };
+            let Some(chunk) = chunk else {
+                return Ok(written);
+            };
+            // Chunks are at most a few tens of KB, so writing them inline
+            // keeps the runtime responsive
+            file.write_all(&chunk)
+                .map_err(|e| TemplateError::io_error(path, &e))?;
+            let bytes = chunk.len() as u64;
+            written += bytes;
+            if let Some(report) = progress.add(bytes) {
+                self.progress(progress.id, report, progress.total_bytes);
+            }
+
+            // Waiting before the next read lets TCP flow control slow the server
+            let wait = throttle.consume(bytes).max(self.throttle.consume(bytes));
+            if !wait.is_zero() {
+                runtime::sleep_cancellable(wait, Some(token), OPERATION).await?;
+            }
+        }
+    }
+
+    /// Records the bytes written so far and tells the listener
+    #[cfg(feature = "http")]
+    fn progress(
+        &self,
+        id: u64,
+        (downloaded, bytes_per_second): (u64, u64),
+        total_bytes: Option<u64>,
+    ) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(entry) = state.downloads.get_mut(&id) {
+                entry.info.downloaded_bytes = downloaded;
+            }
+        }
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener.on_download_progress(DownloadProgress {
+                id,
+                downloaded_bytes: downloaded,
+                total_bytes,
+                bytes_per_second,
+            });
+        }
+    }
+
+    /// Records a stopped transfer's outcome
+    fn finish(&self, id: u64, outcome: TemplateResult<()>, token: &CancellationToken) {
+        if let Err(e) = &outcome {
+            if !token.is_cancelled() {
+                log::warn!("Download {} failed: {}", id, e);
+                events::publish(LibraryEvent::BackgroundError {
+                    operation: "downloader".to_string(),
+                    kind: e.kind(),
+                    error_message: e.to_string(),
+                });
+            }
+        }
+        let info = {
+            let mut state = self.state.lock().unwrap();
+            state.running -= 1;
+            let Some(entry) = state.downloads.get_mut(&id) else {
+                return;
+            };
+            entry.token = None;
+            let stop = entry.stop.take();
+            let destination = PathBuf::from(&entry.info.destination);
+            match outcome {
+                Ok(()) => entry.info.state = DownloadState::Completed,
+                Err(_) if token.is_cancelled() => {
+                    let stop = stop.unwrap_or(DownloadState::Cancelled);
+                    entry.info.state = stop;
+                    if stop == DownloadState::Cancelled {
+                        remove_partial(&destination);
+                        entry.info.downloaded_bytes = 0;
+                        entry.chunks = None;
+                    } else {
+                        entry.info.downloaded_bytes = downloaded_size(&destination);
+                    }
+                }
+                Err(e) => {
+                    entry.info.state = DownloadState::Failed;
+                    entry.info.error_message = Some(e.to_string());
+                    entry.info.downloaded_bytes = downloaded_size(&destination);
+                }
+            }
+            let info = entry.info.clone();
+            self.persist(&state);
+            info
+        };
+        self.notify(info);
+        // Only now, so `wait_idle` returns after the listener heard the outcome
+        self.publish_active(&self.state.lock().unwrap());
+    }
+
+    fn notify(&self, info: DownloadInfo) {
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener.on_download_state_changed(info);
+        }
+    }
+
+    fn publish_active(&self, state: &DownloaderState) {
+        let active = state
+            .downloads
+            .values()
+            .filter(|entry| {
+                matches!(
+                    entry.info.state,
+                    DownloadState::Queued | DownloadState::Running
+                )
+            })
+            .count();
+        self.active.send_replace(active);
+    }
+
+    /// Saves unfinished downloads; failures are ignored so downloads keep working
+    fn persist(&self, state: &DownloaderState) {
+        let Some(path) = &self.persist_path else {
+            return;
+        };
+        let unfinished: Vec<PersistedDownload> = state
+            .downloads
+            .values()
+            .filter(|entry| !is_finished(entry.info.state))
+            .map(|entry| PersistedDownload {
+                id: entry.info.id,
+                url: entry.info.url.clone(),
+                destination: entry.info.destination.clone(),
+                headers: entry.headers.clone(),
+                paused: entry.info.state == DownloadState::Paused,
+                total_bytes: entry.info.total_bytes,
+                validator: entry.validator.clone(),
+                max_bytes_per_second: entry.info.max_bytes_per_second,
+                chunks: entry.chunks.clone(),
+            })
+            .collect();
+        let Ok(json) = serde_json::to_vec_pretty(&unfinished) else {
+            return;
+        };
+        // Replace atomically so a crash never leaves half a file
+        let _ = files::write_atomic(path, &json);
+    }
+}
+
+#[cfg(feature = "http")]
+impl Progress {
+    fn new(id: u64, total_bytes: Option<u64>, downloaded: u64) -> Self {
+        Self {
+            id,
+            total_bytes,
+            counts: Mutex::new((downloaded, Instant::now(), downloaded)),
+        }
+    }
+
+    fn downloaded(&self) -> u64 {
+        self.counts.lock().unwrap().0
+    }
+
+    /// Counts `bytes` more; returns the bytes written and the rate since the
+    /// last report when it is time for another
+    fn add(&self, bytes: u64) -> Option<(u64, u64)> {
+        let mut counts = self.counts.lock().unwrap();
+        let (downloaded, reported_at, reported) = &mut *counts;
+        *downloaded += bytes;
+        let elapsed = reported_at.elapsed();
+        if elapsed < Duration::from_millis(DOWNLOAD_PROGRESS_INTERVAL_MS) {
+            return None;
+        }
+        let bytes_per_second = ((*downloaded - *reported) as f64 / elapsed.as_secs_f64()) as u64;
+        *reported_at = Instant::now();
+        *reported = *downloaded;
+        Some((*downloaded, bytes_per_second))
+    }
+}
+
+/// Whether a download in `state` will not transfer again, even if resumed
+fn is_finished(state: DownloadState) -> bool {
+    matches!(state, DownloadState::Completed | DownloadState::Cancelled)
+}
+
+fn validate_rate(max_bytes_per_second: Option<u64>) -> TemplateResult<()> {
+    if max_bytes_per_second == Some(0) {
+        return Err(TemplateError::invalid_input(
+            "A bandwidth cap must be greater than 0 bytes per second".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    chunk_path(destination, 0)
+}
+
+/// File chunk `index` of a split download is written to; the first chunk
+/// goes to the partial file, and the others are appended to it at the end
+fn chunk_path(destination: &Path, index: usize) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    if index > 0 {
+        name.push(format!(".{}", index));
+    }
+    destination.with_file_name(name)
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+/// Bytes already downloaded to the partial file for `destination`
+fn partial_size(destination: &Path) -> u64 {
+    file_size(&partial_path(destination))
+}
+
+/// Bytes already downloaded for `destination`, in the partial file and in
+/// any chunk files
+fn downloaded_size(destination: &Path) -> u64 {
+    (0..MAX_DOWNLOAD_CONNECTIONS as usize)
+        .map(|index| file_size(&chunk_path(destination, index)))
+        .sum()
+}
+
+/// Deletes the partial file and any chunk files
+fn remove_partial(destination: &Path) {
+    for index in 0..MAX_DOWNLOAD_CONNECTIONS as usize {
+        let partial = chunk_path(destination, index);
+        if let Err(e) = fs::remove_file(&partial) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Could not delete {}: {}", partial.display(), e);
+            }
+        }
+    }
+}
+
+/// Sends a `GET` for `url` through the interceptors, returning the response
+/// with its body unread; the interceptors see it without the body
+#[cfg(feature = "http")]
+async fn send_get(
+    url: &str,
+    headers: HashMap<String, String>,
+    token: &CancellationToken,
+) -> TemplateResult<reqwest::Response> {
+    let request = HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers,
+        body: None,
+        timeout_ms: None,
+    };
+    let intercepted = interceptors::intercept(&request).await;
+    let request = intercepted.as_ref().unwrap_or(&request);
+    let builder = http::build(request)?;
+    let response = tokio::select! {
+        sent = builder.send() => sent.map_err(|e| http::request_error(&request.url, None, &e))?,
+        error = http::cancelled(Some(token), OPERATION) => return Err(error),
+    };
+    interceptors::observe(request, &http::head(&response)).await;
+    Ok(response)
+}
+
+/// Start offsets of `connections` chunks of about the same size
+#[cfg(feature = "http")]
+fn plan_chunks(total: u64, connections: u32) -> Vec<u64> {
+    let size = total.div_ceil(u64::from(connections)).max(1);
+    (0..u64::from(connections))
+        .map(|index| index * size)
+        .filter(|start| *start < total)
+        .collect()
+}
+
+/// Appends every chunk file to the partial file, in order, deleting each
+/// once it is copied
+#[cfg(feature = "http")]
+fn join_chunks(destination: &Path, chunks: usize) -> TemplateResult<()> {
+    let partial = partial_path(destination);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&partial)
+        .map_err(|e| TemplateError::io_error(&partial, &e))?;
+    for index in 1..chunks {
+        let path = chunk_path(destination, index);
+        let mut chunk = fs::File::open(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+        std::io::copy(&mut chunk, &mut file).map_err(|e| TemplateError::io_error(&partial, &e))?;
+        fs::remove_file(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+    }
+    file.sync_all()
+        .map_err(|e| TemplateError::io_error(&partial, &e))
+}
+
+/// Reads unfinished downloads saved by a previous downloader; a missing file means none
+fn load_downloads(path: &Path) -> TemplateResult<Vec<PersistedDownload>> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))
+}
@@ -0,0 +1,256 @@
+//! Key-value store whose values are encrypted at rest
+//!
+//! `EncryptedKvStore` stores values like `KvStore`, but encrypts each one
+//! with AES-256-GCM under a key supplied by the host's `KeyProvider`,
+//! typically backed by the iOS Keychain or the Android Keystore. Keys of the
+//! store are kept in plaintext; each value is bound to its key, so values
+//! cannot be swapped between keys in the file.
+//!
+//! The key, the plaintext of each value, and values passed to `set` are
+//! wiped from memory once encrypted; values returned by `get` belong to the
+//! caller.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::kv_store::{typed, KvStore, KvValue};
+use crate::secure::SecureBytes;
+use crate::secure_random::fill_secure;
+use crate::shield;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use zeroize::Zeroize;
+
+/// Length of the AES-256 key the provider must return, in bytes
+pub const ENCRYPTION_KEY_LENGTH: u32 = 32;
+
+/// Length of the random nonce stored in front of each ciphertext
+const NONCE_LENGTH: usize = 12;
+
+/// Supplies encryption keys, implemented by the host
+pub trait KeyProvider: Send + Sync {
+    /// The 32-byte key for `key_id`, or `None` if it is not available
+    ///
+    /// Must return the same key for the same id on every launch, or values
+    /// written earlier can no longer be read.
+    fn get_key(&self, key_id: String) -> Option<Vec<u8>>;
+}
+
+/// Key-value store with values encrypted by a host-provided key
+///
+/// Open each file with a single store, as with `KvStore`.
+pub struct EncryptedKvStore {
+    store: KvStore,
+    cipher: Aes256Gcm,
+}
+
+impl EncryptedKvStore {
+    /// Opens the store at `path`, encrypting with the key `key_id` from `provider`
+    ///
+    /// The key is requested once, when the store is opened.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::EncryptionError)` - If the provider has no key
+    ///   for `key_id` or the key is not 32 bytes
+    /// * `Err(TemplateError::IoError)` - If the file cannot be read or created
+    /// * `Err(TemplateError::ParseError)` - If the file is not a store written by this library
+    pub fn open(
+        path: String,
+        key_id: String,
+        provider: Box<dyn KeyProvider>,
+    ) -> TemplateResult<Self> {
+        shield::guard("EncryptedKvStore::open", || {
+            let key = provider
+                .get_key(key_id.clone())
+                .map(SecureBytes::new)
+                .ok_or_else(|| {
+                    TemplateError::encryption_error(&format!("No key available for '{}'", key_id))
+                })?;
+            let cipher = Aes256Gcm::new_from_slice(key.expose()).map_err(|_| {
+                TemplateError::encryption_error(&format!(
+                    "Key '{}' is {} bytes, expected {}",
+                    key_id,
+                    key.len(),
+                    ENCRYPTION_KEY_LENGTH
+                ))
+            })?;
+            Ok(Self {
+                store: KvStore::open(path)?,
+                cipher,
+            })
+        })
+    }
+
+    /// The value stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::EncryptionError)` - If the value cannot be
+    ///   decrypted, e.g. because the key changed or the file was modified
+    pub fn get(&self, key: String) -> TemplateResult<Option<KvValue>> {
+        shield::guard("EncryptedKvStore::get", || self.decrypted(&key))
+    }
+
+    /// Encrypts `value` and stores it under `key`, replacing any previous value
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written; the
+    ///   store keeps its previous contents
+    pub fn set(&self, key: String, mut value: KvValue) -> TemplateResult<()> {
+        shield::guard("EncryptedKvStore::set", || {
+            let sealed = self.encrypt(&key, &value);
+            wipe(&mut value);
+            self.store.set(key, KvValue::Bytes { value: sealed? })
+        })
+    }
+
+    /// Removes `key`, returning whether it was present
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub fn delete(&self, key: String) -> TemplateResult<bool> {
+        shield::guard("EncryptedKvStore::delete", || self.store.delete(key))
+    }
+
+    /// All keys, in sorted order
+    pub fn keys(&self) -> Vec<String> {
+        self.store.keys()
+    }
+
+    /// The string stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a string
+    /// * `Err(TemplateError::EncryptionError)` - If the value cannot be decrypted
+    pub fn get_string(&self, key: String) -> TemplateResult<Option<String>> {
+        shield::guard("EncryptedKvStore::get_string", || {
+            typed(&key, self.decrypted(&key)?, "string", |value| match value {
+                KvValue::String { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a string under `key`
+    pub fn set_string(&self, key: String, value: String) -> TemplateResult<()> {
+        self.set(key, KvValue::String { value })
+    }
+
+    /// The integer stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not an integer
+    /// * `Err(TemplateError::EncryptionError)` - If the value cannot be decrypted
+    pub fn get_int(&self, key: String) -> TemplateResult<Option<i64>> {
+        shield::guard("EncryptedKvStore::get_int", || {
+            typed(&key, self.decrypted(&key)?, "int", |value| match value {
+                KvValue::Int { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores an integer under `key`
+    pub fn set_int(&self, key: String, value: i64) -> TemplateResult<()> {
+        self.set(key, KvValue::Int { value })
+    }
+
+    /// The boolean stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a boolean
+    /// * `Err(TemplateError::EncryptionError)` - If the value cannot be decrypted
+    pub fn get_bool(&self, key: String) -> TemplateResult<Option<bool>> {
+        shield::guard("EncryptedKvStore::get_bool", || {
+            typed(&key, self.decrypted(&key)?, "bool", |value| match value {
+                KvValue::Bool { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a boolean under `key`
+    pub fn set_bool(&self, key: String, value: bool) -> TemplateResult<()> {
+        self.set(key, KvValue::Bool { value })
+    }
+
+    /// The bytes stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not bytes
+    /// * `Err(TemplateError::EncryptionError)` - If the value cannot be decrypted
+    pub fn get_bytes(&self, key: String) -> TemplateResult<Option<Vec<u8>>> {
+        shield::guard("EncryptedKvStore::get_bytes", || {
+            typed(&key, self.decrypted(&key)?, "bytes", |value| match value {
+                KvValue::Bytes { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores bytes under `key`
+    pub fn set_bytes(&self, key: String, value: Vec<u8>) -> TemplateResult<()> {
+        self.set(key, KvValue::Bytes { value })
+    }
+
+    /// Seals `value` as nonce followed by ciphertext, bound to `key`
+    fn encrypt(&self, key: &str, value: &KvValue) -> TemplateResult<Vec<u8>> {
+        let plaintext =
+            SecureBytes::new(serde_json::to_vec(value).map_err(|e| TemplateError::json_error(&e))?);
+        let mut nonce = [0u8; NONCE_LENGTH];
+        fill_secure(&mut nonce)?;
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.expose(),
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| TemplateError::encryption_error("Encryption failed"))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Reads and opens the value stored under `key`
+    fn decrypted(&self, key: &str) -> TemplateResult<Option<KvValue>> {
+        let unreadable = || {
+            TemplateError::encryption_error(&format!("Value for '{}' could not be decrypted", key))
+        };
+        let sealed = match self.store.get(key.to_string()) {
+            None => return Ok(None),
+            Some(KvValue::Bytes { value }) if value.len() > NONCE_LENGTH => value,
+            Some(_) => return Err(unreadable()),
+        };
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let plaintext = SecureBytes::new(
+            self.cipher
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: key.as_bytes(),
+                    },
+                )
+                .map_err(|_| unreadable())?,
+        );
+        serde_json::from_slice(plaintext.expose())
+            .map(Some)
+            .map_err(|_| unreadable())
+    }
+}
+
+/// Overwrites the contents of a value that has been encrypted
+fn wipe(value: &mut KvValue) {
+    match value {
+        KvValue::String { value } => value.zeroize(),
+        KvValue::Bytes { value } => value.zeroize(),
+        KvValue::Int { value } => value.zeroize(),
+        KvValue::Bool { value } => value.zeroize(),
+    }
+}
@@ -1,11 +1,18 @@
 //! Error types for the template library
 
+use crate::config;
+use crate::diagnostics;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::path::Path;
 use thiserror::Error;
 
 /// Errors that can occur when using the template library
-#[derive(Debug, Error, Clone, PartialEq)]
+#[derive(Debug, Error, Clone, PartialEq, Serialize)]
+#[serde(tag = "variant", content = "fields")]
 pub enum TemplateError {
     /// Input string exceeds maximum allowed size
     #[error("Input too large: {size} bytes exceeds maximum of {max} bytes (hash: {hash})")]
@@ -33,9 +40,553 @@ pub enum TemplateError {
         /// Name of the operation that was cancelled
         operation: String,
     },
+
+    /// Operation did not complete within its timeout
+    #[error("Operation timed out: {operation} after {timeout_ms} ms")]
+    Timeout {
+        /// Name of the operation that timed out
+        operation: String,
+        /// The timeout that elapsed, in milliseconds
+        timeout_ms: u64,
+    },
+
+    /// Serialized data could not be parsed
+    #[error("Failed to parse {format}: {error_message}")]
+    ParseError {
+        /// Format being parsed (e.g. "JSON", "TOML")
+        format: String,
+        /// Description of the problem
+        error_message: String,
+        /// 1-based line of the problem, if known
+        line: Option<u32>,
+        /// 1-based column of the problem, if known
+        column: Option<u32>,
+    },
+
+    /// `initialize` was called more than once
+    #[error("Library already initialized; use update_config to change settings")]
+    AlreadyInitialized,
+
+    /// A filesystem operation failed
+    #[error("I/O error at {path}: {error_message}{}", format_details(details))]
+    IoError {
+        /// Path that was being accessed
+        path: String,
+        /// Error message from the operating system
+        error_message: String,
+        /// The chain of underlying errors, outermost first, if any
+        details: Option<String>,
+    },
+
+    /// The operating system's secure random source failed
+    #[error("Secure random source unavailable: {error_message}")]
+    EntropyUnavailable {
+        /// Error message from the random source
+        error_message: String,
+    },
+
+    /// No model file exists at the given path
+    #[error("Model not found: {path}")]
+    ModelNotFound {
+        /// Path that was looked up
+        path: String,
+    },
+
+    /// A file is not in a supported model format
+    #[error("Invalid model format for {path}: {error_message}")]
+    InvalidModelFormat {
+        /// Path of the file
+        path: String,
+        /// Why the format was rejected
+        error_message: String,
+    },
+
+    /// A model file is in a supported format but could not be read
+    #[error("Failed to load model {path}: {error_message}")]
+    ModelLoadError {
+        /// Path of the model file
+        path: String,
+        /// Why loading failed
+        error_message: String,
+        /// Breadcrumbs and backtrace, with the `debug-errors` feature
+        debug_info: Option<String>,
+    },
+
+    /// A network request failed
+    #[error("Network error for {url}: {error_message}{}", format_details(details))]
+    NetworkError {
+        /// URL that was requested
+        url: String,
+        /// HTTP status code, if a response was received
+        status_code: Option<u16>,
+        /// Description of the failure
+        error_message: String,
+        /// The chain of underlying errors, outermost first, if any
+        details: Option<String>,
+    },
+
+    /// A TLS connection to a pinned host presented none of its pinned keys
+    #[error("Certificate pinning failed for {host}: {error_message}")]
+    PinningFailure {
+        /// Host whose pins did not match
+        host: String,
+        /// Description of the mismatch
+        error_message: String,
+    },
+
+    /// A database statement or transaction failed
+    #[error("Database error: {error_message}")]
+    DatabaseError {
+        /// SQLite result code, e.g. `ConstraintViolation` or `DatabaseBusy`, if any
+        code: Option<String>,
+        /// Description of the failure
+        error_message: String,
+    },
+
+    /// An encryption key was unavailable or a value could not be decrypted
+    #[error("Encryption error: {error_message}")]
+    EncryptionError {
+        /// Description of the failure
+        error_message: String,
+    },
+
+    /// Not enough free disk space for a write
+    #[error("Insufficient storage at {path}: {required} bytes required, {available} available")]
+    InsufficientStorage {
+        /// Path whose volume was checked
+        path: String,
+        /// Bytes the operation needs
+        required: u64,
+        /// Bytes free for the app on that volume
+        available: u64,
+    },
+
+    /// A retried operation failed on every attempt
+    #[error("{operation} failed after {attempts} attempts: {error_message}")]
+    RetriesExhausted {
+        /// Name of the operation that was retried
+        operation: String,
+        /// Number of attempts made
+        attempts: u32,
+        /// Kind of the error from the last attempt
+        last_error_kind: ErrorKind,
+        /// Message of the error from the last attempt
+        error_message: String,
+    },
+
+    /// The library panicked; the panic was caught at the FFI boundary
+    #[error("Internal error: {error_message}{}", format_location(location))]
+    Internal {
+        /// Panic message
+        error_message: String,
+        /// Source location of the panic (`file:line:column`), if known
+        location: Option<String>,
+        /// Backtrace of the panic, with the `debug-errors` feature
+        debug_info: Option<String>,
+    },
+}
+
+/// Fieldless discriminant of `TemplateError`, for matching and configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// `TemplateError::InputTooLarge`
+    InputTooLarge,
+    /// `TemplateError::InvalidInput`
+    InvalidInput,
+    /// `TemplateError::OperationCancelled`
+    OperationCancelled,
+    /// `TemplateError::Timeout`
+    Timeout,
+    /// `TemplateError::ParseError`
+    ParseError,
+    /// `TemplateError::AlreadyInitialized`
+    AlreadyInitialized,
+    /// `TemplateError::IoError`
+    IoError,
+    /// `TemplateError::EntropyUnavailable`
+    EntropyUnavailable,
+    /// `TemplateError::ModelNotFound`
+    ModelNotFound,
+    /// `TemplateError::InvalidModelFormat`
+    InvalidModelFormat,
+    /// `TemplateError::ModelLoadError`
+    ModelLoadError,
+    /// `TemplateError::NetworkError`
+    NetworkError,
+    /// `TemplateError::PinningFailure`
+    PinningFailure,
+    /// `TemplateError::DatabaseError`
+    DatabaseError,
+    /// `TemplateError::EncryptionError`
+    EncryptionError,
+    /// `TemplateError::InsufficientStorage`
+    InsufficientStorage,
+    /// `TemplateError::RetriesExhausted`
+    RetriesExhausted,
+    /// `TemplateError::Internal`
+    Internal,
+}
+
+/// How serious an error is, for host logging and alerting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Expected outcome such as a cancellation; nothing went wrong
+    Info,
+    /// Rejected input or misuse the host should surface but not alert on
+    Warning,
+    /// Failure of the environment (disk, network, time) that may clear up
+    Recoverable,
+    /// A bug in the library; worth alerting on
+    Fatal,
+}
+
+impl ErrorKind {
+    /// Every kind, in declaration order
+    pub const ALL: [ErrorKind; 18] = [
+        Self::InputTooLarge,
+        Self::InvalidInput,
+        Self::OperationCancelled,
+        Self::Timeout,
+        Self::ParseError,
+        Self::AlreadyInitialized,
+        Self::IoError,
+        Self::EntropyUnavailable,
+        Self::ModelNotFound,
+        Self::InvalidModelFormat,
+        Self::ModelLoadError,
+        Self::NetworkError,
+        Self::PinningFailure,
+        Self::DatabaseError,
+        Self::EncryptionError,
+        Self::InsufficientStorage,
+        Self::RetriesExhausted,
+        Self::Internal,
+    ];
+
+    /// The kind with the given `code()`, if any
+    pub fn from_code(code: &str) -> Option<ErrorKind> {
+        Self::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// Severity of this kind of error
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::OperationCancelled => Severity::Info,
+            Self::InputTooLarge
+            | Self::InvalidInput
+            | Self::ParseError
+            | Self::AlreadyInitialized
+            | Self::ModelNotFound
+            | Self::InvalidModelFormat
+            | Self::ModelLoadError => Severity::Warning,
+            Self::Timeout
+            | Self::IoError
+            | Self::EntropyUnavailable
+            | Self::NetworkError
+            | Self::PinningFailure
+            | Self::DatabaseError
+            | Self::EncryptionError
+            | Self::InsufficientStorage
+            | Self::RetriesExhausted => Severity::Recoverable,
+            Self::Internal => Severity::Fatal,
+        }
+    }
+
+    /// Upper-case code such as `TIMEOUT`, for logs and analytics
+    pub fn code(self) -> String {
+        self.message_key()
+            .trim_start_matches("template.error.")
+            .to_ascii_uppercase()
+    }
+
+    /// Stable key for looking up a translated message for this kind of error
+    pub fn message_key(self) -> &'static str {
+        match self {
+            Self::InputTooLarge => "template.error.input_too_large",
+            Self::InvalidInput => "template.error.invalid_input",
+            Self::OperationCancelled => "template.error.operation_cancelled",
+            Self::Timeout => "template.error.timeout",
+            Self::ParseError => "template.error.parse_error",
+            Self::AlreadyInitialized => "template.error.already_initialized",
+            Self::IoError => "template.error.io_error",
+            Self::EntropyUnavailable => "template.error.entropy_unavailable",
+            Self::ModelNotFound => "template.error.model_not_found",
+            Self::InvalidModelFormat => "template.error.invalid_model_format",
+            Self::ModelLoadError => "template.error.model_load_error",
+            Self::NetworkError => "template.error.network_error",
+            Self::PinningFailure => "template.error.pinning_failure",
+            Self::DatabaseError => "template.error.database_error",
+            Self::EncryptionError => "template.error.encryption_error",
+            Self::InsufficientStorage => "template.error.insufficient_storage",
+            Self::RetriesExhausted => "template.error.retries_exhausted",
+            Self::Internal => "template.error.internal",
+        }
+    }
+}
+
+/// Translation key and parameters describing an error, for host-side localization
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalizedMessage {
+    /// Key from `TemplateError::message_key()`
+    pub key: String,
+    /// Values from `TemplateError::message_params()`
+    pub params: HashMap<String, String>,
+}
+
+/// Returns the translation key and parameters for an error
+///
+/// Hosts look `key` up in their own string tables and substitute `params`,
+/// instead of showing the English `Display` text to users.
+pub fn localize_error(error: TemplateError) -> LocalizedMessage {
+    LocalizedMessage {
+        key: error.message_key().to_string(),
+        params: error.message_params(),
+    }
+}
+
+/// How an error should be handled, independent of its variant
+///
+/// Lets hosts write generic retry and reporting logic that keeps working
+/// when new error variants are added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorClassification {
+    /// Repeating the same call may succeed
+    pub retryable: bool,
+    /// Caused by a temporary condition such as a network or disk hiccup
+    pub transient: bool,
+    /// Caused by the input; show it to the user rather than retrying
+    pub user_input: bool,
+    /// Misuse of the library or a bug in it; report it
+    pub internal: bool,
+}
+
+/// Serializes an error as JSON with `code`, `variant`, `message`, and `fields`
+pub fn error_to_json(error: TemplateError) -> String {
+    error.to_json()
+}
+
+/// Returns the severity of an error
+pub fn error_severity(error: TemplateError) -> Severity {
+    error.severity()
+}
+
+/// Returns the classification flags for an error
+pub fn classify_error(error: TemplateError) -> ErrorClassification {
+    error.classification()
 }
 
 impl TemplateError {
+    /// The kind of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::InputTooLarge { .. } => ErrorKind::InputTooLarge,
+            Self::InvalidInput { .. } => ErrorKind::InvalidInput,
+            Self::OperationCancelled { .. } => ErrorKind::OperationCancelled,
+            Self::Timeout { .. } => ErrorKind::Timeout,
+            Self::ParseError { .. } => ErrorKind::ParseError,
+            Self::AlreadyInitialized => ErrorKind::AlreadyInitialized,
+            Self::IoError { .. } => ErrorKind::IoError,
+            Self::EntropyUnavailable { .. } => ErrorKind::EntropyUnavailable,
+            Self::ModelNotFound { .. } => ErrorKind::ModelNotFound,
+            Self::InvalidModelFormat { .. } => ErrorKind::InvalidModelFormat,
+            Self::ModelLoadError { .. } => ErrorKind::ModelLoadError,
+            Self::NetworkError { .. } => ErrorKind::NetworkError,
+            Self::PinningFailure { .. } => ErrorKind::PinningFailure,
+            Self::DatabaseError { .. } => ErrorKind::DatabaseError,
+            Self::EncryptionError { .. } => ErrorKind::EncryptionError,
+            Self::InsufficientStorage { .. } => ErrorKind::InsufficientStorage,
+            Self::RetriesExhausted { .. } => ErrorKind::RetriesExhausted,
+            Self::Internal { .. } => ErrorKind::Internal,
+        }
+    }
+
+    /// Serializes the error for structured logging
+    ///
+    /// The schema is stable across releases and identical on every platform:
+    ///
+    /// ```json
+    /// {"code": "TIMEOUT", "variant": "Timeout", "message": "...",
+    ///  "fields": {"operation": "echo", "timeout_ms": 500}}
+    /// ```
+    ///
+    /// `fields` holds the variant's fields with their JSON types, and is an
+    /// empty object for variants without fields.
+    pub fn to_json(&self) -> String {
+        let mut value = serde_json::to_value(self).expect("errors always serialize");
+        let object = value.as_object_mut().expect("errors serialize as objects");
+        object
+            .entry("fields")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        object.insert("code".to_string(), self.kind().code().into());
+        object.insert("message".to_string(), self.to_string().into());
+        value.to_string()
+    }
+
+    /// Severity of this error, for host logging and alerting
+    pub fn severity(&self) -> Severity {
+        self.kind().severity()
+    }
+
+    /// Stable key for looking up a translated message for this error
+    ///
+    /// Keys have the form `template.error.<kind>`, e.g.
+    /// `template.error.input_too_large`. Use `message_params()` to fill in
+    /// the placeholders; `Display` stays English for logs.
+    pub fn message_key(&self) -> &'static str {
+        self.kind().message_key()
+    }
+
+    /// The values to substitute into the translated message, keyed by field name
+    pub fn message_params(&self) -> HashMap<String, String> {
+        let params: Vec<(&str, String)> = match self {
+            Self::InputTooLarge { size, max, hash } => vec![
+                ("size", size.to_string()),
+                ("max", max.to_string()),
+                ("hash", hash.clone()),
+            ],
+            Self::InvalidInput {
+                error_message,
+                input_preview,
+            } => {
+                let mut params = vec![("error_message", error_message.clone())];
+                if let Some(preview) = input_preview {
+                    params.push(("input_preview", preview.clone()));
+                }
+                params
+            }
+            Self::OperationCancelled { operation } => vec![("operation", operation.clone())],
+            Self::Timeout {
+                operation,
+                timeout_ms,
+            } => vec![
+                ("operation", operation.clone()),
+                ("timeout_ms", timeout_ms.to_string()),
+            ],
+            Self::ParseError {
+                format,
+                error_message,
+                line,
+                column,
+            } => {
+                let mut params = vec![
+                    ("format", format.clone()),
+                    ("error_message", error_message.clone()),
+                ];
+                if let Some(line) = line {
+                    params.push(("line", line.to_string()));
+                }
+                if let Some(column) = column {
+                    params.push(("column", column.to_string()));
+                }
+                params
+            }
+            Self::AlreadyInitialized => Vec::new(),
+            Self::IoError {
+                path,
+                error_message,
+                details,
+            } => {
+                let mut params = vec![
+                    ("path", path.clone()),
+                    ("error_message", error_message.clone()),
+                ];
+                if let Some(details) = details {
+                    params.push(("details", details.clone()));
+                }
+                params
+            }
+            Self::EntropyUnavailable { error_message }
+            | Self::EncryptionError { error_message } => {
+                vec![("error_message", error_message.clone())]
+            }
+            Self::ModelNotFound { path } => vec![("path", path.clone())],
+            Self::InvalidModelFormat {
+                path,
+                error_message,
+            }
+            | Self::ModelLoadError {
+                path,
+                error_message,
+                ..
+            } => vec![
+                ("path", path.clone()),
+                ("error_message", error_message.clone()),
+            ],
+            Self::NetworkError {
+                url,
+                status_code,
+                error_message,
+                details,
+            } => {
+                let mut params = vec![
+                    ("url", url.clone()),
+                    ("error_message", error_message.clone()),
+                ];
+                if let Some(status_code) = status_code {
+                    params.push(("status_code", status_code.to_string()));
+                }
+                if let Some(details) = details {
+                    params.push(("details", details.clone()));
+                }
+                params
+            }
+            Self::PinningFailure {
+                host,
+                error_message,
+            } => vec![
+                ("host", host.clone()),
+                ("error_message", error_message.clone()),
+            ],
+            Self::DatabaseError {
+                code,
+                error_message,
+            } => {
+                let mut params = vec![("error_message", error_message.clone())];
+                if let Some(code) = code {
+                    params.push(("code", code.clone()));
+                }
+                params
+            }
+            Self::InsufficientStorage {
+                path,
+                required,
+                available,
+            } => vec![
+                ("path", path.clone()),
+                ("required", required.to_string()),
+                ("available", available.to_string()),
+            ],
+            Self::RetriesExhausted {
+                operation,
+                attempts,
+                last_error_kind,
+                error_message,
+            } => vec![
+                ("operation", operation.clone()),
+                ("attempts", attempts.to_string()),
+                ("last_error_key", last_error_kind.message_key().to_string()),
+                ("error_message", error_message.clone()),
+            ],
+            Self::Internal {
+                error_message,
+                location,
+                ..
+            } => {
+                let mut params = vec![("error_message", error_message.clone())];
+                if let Some(location) = location {
+                    params.push(("location", location.clone()));
+                }
+                params
+            }
+        };
+        params
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
     /// Create InputTooLarge error with hash
     pub fn input_too_large(size: usize, max: usize, input: &str) -> Self {
         let hash = calculate_hash(input);
@@ -46,15 +597,39 @@ impl TemplateError {
         }
     }
 
-    /// Create InvalidInput error with preview
+    /// Create InputTooLarge error for binary data
+    pub(crate) fn data_too_large(size: u64, max: u64, data: &[u8]) -> Self {
+        Self::InputTooLarge {
+            size,
+            max,
+            hash: format!("{:x}", calculate_hash(data)),
+        }
+    }
+
+    /// Create InvalidInput error with a preview formatted per the library config
     pub fn invalid_input(error_message: String, input: Option<&str>) -> Self {
-        let preview = input.map(|s| {
-            if s.len() > 50 {
-                format!("{}...", &s[..50])
-            } else {
-                s.to_string()
-            }
-        });
+        let config = config::current();
+        Self::invalid_input_with_preview(
+            error_message,
+            input,
+            config.preview_length as usize,
+            config.preview_mode,
+        )
+    }
+
+    /// Create InvalidInput error with a preview of at most `preview_length` characters
+    ///
+    /// With `PreviewMode::Redact` the preview holds a hash of the input
+    /// instead of its content. A `preview_length` of 0 omits the preview.
+    pub fn invalid_input_with_preview(
+        error_message: String,
+        input: Option<&str>,
+        preview_length: usize,
+        mode: PreviewMode,
+    ) -> Self {
+        let preview = input
+            .filter(|_| preview_length > 0)
+            .map(|s| mode.preview(s, preview_length));
         Self::InvalidInput {
             error_message,
             input_preview: preview,
@@ -67,10 +642,303 @@ impl TemplateError {
             operation: operation.to_string(),
         }
     }
+
+    /// Create Timeout error
+    pub fn timeout(operation: &str, timeout_ms: u64) -> Self {
+        Self::Timeout {
+            operation: operation.to_string(),
+            timeout_ms,
+        }
+    }
+
+    /// Create ParseError without a location
+    pub fn parse_error(format: &str, error_message: String) -> Self {
+        Self::ParseError {
+            format: format.to_string(),
+            error_message,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Create ParseError from a JSON error, keeping its location
+    pub(crate) fn json_error(error: &serde_json::Error) -> Self {
+        let nonzero = |n: usize| (n > 0).then_some(n as u32);
+        Self::ParseError {
+            format: "JSON".to_string(),
+            error_message: error.to_string(),
+            line: nonzero(error.line()),
+            column: nonzero(error.column()),
+        }
+    }
+
+    /// Create ParseError from a TOML error, resolving its span in `input`
+    pub(crate) fn toml_error(error: &toml::de::Error, input: &str) -> Self {
+        let location = error.span().map(|span| {
+            let before = &input[..span.start.min(input.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            (line as u32, column as u32)
+        });
+        Self::ParseError {
+            format: "TOML".to_string(),
+            error_message: error.message().to_string(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+        }
+    }
+
+    /// Create IoError from a path and the underlying error, keeping its source chain
+    pub fn io_error(path: &Path, error: &std::io::Error) -> Self {
+        Self::IoError {
+            path: path.display().to_string(),
+            error_message: error.to_string(),
+            details: source_chain(error),
+        }
+    }
+
+    /// The chain of underlying errors carried by this error, if any
+    pub fn details(&self) -> Option<&str> {
+        match self {
+            Self::IoError { details, .. } | Self::NetworkError { details, .. } => {
+                details.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether repeating the same call may succeed
+    ///
+    /// Network errors with a 4xx status other than 408 or 429 are not
+    /// retryable: the request itself was rejected. Database errors are only
+    /// retryable when the database was busy or locked.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout { .. } | Self::IoError { .. } | Self::EntropyUnavailable { .. } => true,
+            Self::NetworkError { status_code, .. } => match status_code {
+                Some(code @ 400..=499) => *code == 408 || *code == 429,
+                _ => true,
+            },
+            Self::DatabaseError { code, .. } => {
+                matches!(code.as_deref(), Some("DatabaseBusy" | "DatabaseLocked"))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the error comes from a temporary condition outside the library
+    ///
+    /// Unlike `is_retryable`, this is also true for `InsufficientStorage` and
+    /// for `RetriesExhausted` when the last attempt failed transiently: trying
+    /// again later may work, retrying right away will not.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::InsufficientStorage { .. } => true,
+            Self::RetriesExhausted {
+                last_error_kind, ..
+            } => matches!(
+                last_error_kind,
+                ErrorKind::Timeout
+                    | ErrorKind::IoError
+                    | ErrorKind::EntropyUnavailable
+                    | ErrorKind::NetworkError
+            ),
+            _ => self.is_retryable(),
+        }
+    }
+
+    /// Whether the error was caused by what the user supplied
+    ///
+    /// These errors are worth showing to the user as-is; retrying the same
+    /// input will fail again.
+    pub fn is_user_error(&self) -> bool {
+        matches!(
+            self,
+            Self::InputTooLarge { .. }
+                | Self::InvalidInput { .. }
+                | Self::ParseError { .. }
+                | Self::ModelNotFound { .. }
+                | Self::InvalidModelFormat { .. }
+        )
+    }
+
+    /// Whether the error points at misuse of the library or a bug in it
+    pub fn is_internal(&self) -> bool {
+        matches!(self, Self::AlreadyInitialized | Self::Internal { .. })
+    }
+
+    /// All classification flags at once
+    pub fn classification(&self) -> ErrorClassification {
+        ErrorClassification {
+            retryable: self.is_retryable(),
+            transient: self.is_transient(),
+            user_input: self.is_user_error(),
+            internal: self.is_internal(),
+        }
+    }
+
+    /// Breadcrumbs and backtrace captured with the `debug-errors` feature
+    ///
+    /// Always `None` without the feature, and for variants that do not
+    /// carry debug context.
+    pub fn debug_info(&self) -> Option<&str> {
+        match self {
+            Self::ModelLoadError { debug_info, .. } | Self::Internal { debug_info, .. } => {
+                debug_info.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Create InsufficientStorage error
+    pub fn insufficient_storage(path: &Path, required: u64, available: u64) -> Self {
+        Self::InsufficientStorage {
+            path: path.display().to_string(),
+            required,
+            available,
+        }
+    }
+
+    /// Create ModelNotFound error
+    pub fn model_not_found(path: &Path) -> Self {
+        Self::ModelNotFound {
+            path: path.display().to_string(),
+        }
+    }
+
+    /// Create InvalidModelFormat error
+    pub fn invalid_model_format(path: &Path, error_message: String) -> Self {
+        Self::InvalidModelFormat {
+            path: path.display().to_string(),
+            error_message,
+        }
+    }
+
+    /// Create ModelLoadError, capturing debug context with the `debug-errors` feature
+    pub fn model_load_error(path: &Path, error_message: String) -> Self {
+        Self::ModelLoadError {
+            path: path.display().to_string(),
+            error_message,
+            debug_info: diagnostics::capture(),
+        }
+    }
+
+    /// Create DatabaseError from a SQLite failure
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn sqlite_error(error: &rusqlite::Error) -> Self {
+        let code = match error {
+            rusqlite::Error::SqliteFailure(failure, _) => Some(format!("{:?}", failure.code)),
+            _ => None,
+        };
+        Self::DatabaseError {
+            code,
+            error_message: error.to_string(),
+        }
+    }
+
+    /// Create EncryptionError
+    pub fn encryption_error(error_message: &str) -> Self {
+        Self::EncryptionError {
+            error_message: error_message.to_string(),
+        }
+    }
+
+    /// Create NetworkError
+    pub fn network_error(url: &str, status_code: Option<u16>, error_message: String) -> Self {
+        Self::NetworkError {
+            url: url.to_string(),
+            status_code,
+            error_message,
+            details: None,
+        }
+    }
+
+    /// Create NetworkError from the underlying error, keeping its source chain
+    pub fn network_failure(
+        url: &str,
+        status_code: Option<u16>,
+        error: &dyn std::error::Error,
+    ) -> Self {
+        Self::NetworkError {
+            url: url.to_string(),
+            status_code,
+            error_message: error.to_string(),
+            details: source_chain(error),
+        }
+    }
+
+    /// Create PinningFailure error
+    pub fn pinning_failure(host: &str, error_message: String) -> Self {
+        Self::PinningFailure {
+            host: host.to_string(),
+            error_message,
+        }
+    }
+
+    /// Create RetriesExhausted error wrapping the last attempt's error
+    pub fn retries_exhausted(operation: &str, attempts: u32, last_error: &TemplateError) -> Self {
+        Self::RetriesExhausted {
+            operation: operation.to_string(),
+            attempts,
+            last_error_kind: last_error.kind(),
+            error_message: last_error.to_string(),
+        }
+    }
+
+    /// Create EntropyUnavailable error
+    pub fn entropy_unavailable(error_message: &str) -> Self {
+        Self::EntropyUnavailable {
+            error_message: error_message.to_string(),
+        }
+    }
+
+    /// Create Internal error for a panic caught in `operation`
+    pub fn internal(operation: &str, panic_message: &str, location: Option<String>) -> Self {
+        Self::Internal {
+            error_message: format!("panic in {}: {}", operation, panic_message),
+            location,
+            debug_info: None,
+        }
+    }
+}
+
+/// Joins the sources below `error` into one string, outermost first
+///
+/// Sources whose message repeats the previous one are skipped, since many
+/// wrappers display their source verbatim.
+pub(crate) fn source_chain(error: &dyn std::error::Error) -> Option<String> {
+    let mut messages: Vec<String> = Vec::new();
+    let mut previous = error.to_string();
+    let mut current = error.source();
+    while let Some(source) = current {
+        let message = source.to_string();
+        if message != previous {
+            messages.push(message.clone());
+        }
+        previous = message;
+        current = source.source();
+    }
+    (!messages.is_empty()).then(|| messages.join(": "))
+}
+
+/// Formats optional details as a suffix for `Display`
+fn format_details(details: &Option<String>) -> String {
+    details
+        .as_ref()
+        .map(|details| format!(" (caused by: {})", details))
+        .unwrap_or_default()
+}
+
+/// Formats the optional panic location of an `Internal` error
+fn format_location(location: &Option<String>) -> String {
+    location
+        .as_ref()
+        .map(|location| format!(" (at {})", location))
+        .unwrap_or_default()
 }
 
 /// Calculate hash for debugging purposes
-fn calculate_hash(input: &str) -> u64 {
+fn calculate_hash<T: Hash + ?Sized>(input: &T) -> u64 {
     let mut hasher = DefaultHasher::new();
     input.hash(&mut hasher);
     hasher.finish()
@@ -82,5 +950,35 @@ pub const MAX_INPUT_SIZE: usize = 1_000_000;
 /// Default maximum size
 pub const DEFAULT_MAX_SIZE: usize = 1_000_000;
 
+/// How `InvalidInput` errors show the offending input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewMode {
+    /// The first characters of the input (default)
+    #[default]
+    Truncate,
+    /// A SHA-256 prefix of the input, so sensitive content never leaves the library
+    Redact,
+}
+
+impl PreviewMode {
+    /// Builds the preview of `input`, cutting it at a character boundary
+    fn preview(self, input: &str, preview_length: usize) -> String {
+        match self {
+            Self::Truncate => match input.char_indices().nth(preview_length) {
+                Some((end, _)) => format!("{}...", &input[..end]),
+                None => input.to_string(),
+            },
+            Self::Redact => {
+                let digest = Sha256::digest(input.as_bytes());
+                format!("[redacted sha256:{}]", &hex::encode(digest)[..16])
+            }
+        }
+    }
+}
+
+/// Default number of input characters shown in `InvalidInput` previews
+pub const DEFAULT_PREVIEW_LENGTH: u32 = 50;
+
 /// Result type for template operations
 pub type TemplateResult<T> = Result<T, TemplateError>;
@@ -0,0 +1,61 @@
+//! Host-registered mapping from library error codes to host error domains
+
+use crate::error::{ErrorKind, TemplateError, TemplateResult};
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Host-side identity of an error, such as an `NSError` domain and code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostErrorCode {
+    /// Host error domain, e.g. `com.example.app.ml`
+    pub domain: String,
+    /// Host error code within the domain
+    pub code: i64,
+}
+
+static ERROR_MAPPINGS: RwLock<Option<HashMap<ErrorKind, HostErrorCode>>> = RwLock::new(None);
+
+/// Replaces the table mapping error codes (e.g. `TIMEOUT`) to host codes
+///
+/// Enterprise apps can use this to align library errors with their existing
+/// `NSError` domains or Kotlin error hierarchies. Errors whose code is not
+/// in the table have no host code. Pass an empty table to clear it.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If a key is not a known error code;
+///   the previous table is kept
+pub fn set_error_mappings(mappings: HashMap<String, HostErrorCode>) -> TemplateResult<()> {
+    shield::guard("set_error_mappings", || {
+        let mut table = HashMap::with_capacity(mappings.len());
+        for (code, host_code) in mappings {
+            let Some(kind) = ErrorKind::from_code(&code) else {
+                return Err(TemplateError::invalid_input(
+                    format!("Unknown error code '{}'", code),
+                    None,
+                ));
+            };
+            table.insert(kind, host_code);
+        }
+        *ERROR_MAPPINGS.write().unwrap() = (!table.is_empty()).then_some(table);
+        Ok(())
+    })
+}
+
+/// Returns the host code registered for an error, if any
+pub fn host_error_code(error: TemplateError) -> Option<HostErrorCode> {
+    error.host_code()
+}
+
+impl TemplateError {
+    /// The host code registered for this error's kind with `set_error_mappings`
+    pub fn host_code(&self) -> Option<HostErrorCode> {
+        ERROR_MAPPINGS
+            .read()
+            .unwrap()
+            .as_ref()?
+            .get(&self.kind())
+            .cloned()
+    }
+}
@@ -0,0 +1,216 @@
+//! Process-wide event bus for observing library activity
+//!
+//! The most recent events are also kept in memory, whether or not anyone is
+//! subscribed, so a diagnostics screen can show them with `get_recent_events`.
+
+use crate::config::LibraryConfig;
+use crate::error::ErrorKind;
+use crate::jobs::JobInfo;
+use crate::models::ModelFormat;
+use crate::tasks::TaskStatus;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most events kept for `get_recent_events`; older ones are dropped
+pub const RECENT_EVENTS_CAPACITY: u32 = 200;
+
+/// Something that happened inside the library
+#[derive(Debug, Clone, PartialEq)]
+pub enum LibraryEvent {
+    /// `discover_models` finished scanning a directory
+    ModelsDiscovered {
+        /// Directory that was scanned
+        directory: String,
+        /// Number of model files found
+        count: u32,
+    },
+    /// A `JobQueue` job changed state
+    JobStateChanged {
+        /// Snapshot of the job
+        job: JobInfo,
+    },
+    /// A background task started with `spawn_echo` finished
+    TaskFinished {
+        /// Id of the task
+        id: u64,
+        /// How it finished
+        status: TaskStatus,
+    },
+    /// Background work failed with no caller to return the error to
+    BackgroundError {
+        /// Name of the failed operation
+        operation: String,
+        /// Kind of the error
+        kind: ErrorKind,
+        /// Error message
+        error_message: String,
+    },
+    /// `load_model_metadata` read a model header
+    ModelLoaded {
+        /// Path of the model file
+        path: String,
+        /// Format of the model file
+        format: ModelFormat,
+    },
+    /// An exported function returned an error; cancellations have kind
+    /// `OperationCancelled`
+    OperationFailed {
+        /// Name of the failed operation
+        operation: String,
+        /// Kind of the error
+        kind: ErrorKind,
+        /// Error message
+        error_message: String,
+    },
+    /// The library-wide configuration was set or changed
+    ConfigChanged {
+        /// The new configuration
+        config: LibraryConfig,
+    },
+}
+
+/// An event kept in the recent-events buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// Position in publication order, starting at 1; gaps mean dropped events
+    pub sequence: u64,
+    /// Unix timestamp in milliseconds when the event was published
+    pub timestamp_ms: u64,
+    /// The event
+    pub event: LibraryEvent,
+}
+
+/// Callback receiving library events, implemented by the host
+pub trait EventListener: Send + Sync {
+    /// Called for every event, in publication order, on the event thread
+    fn on_event(&self, event: LibraryEvent);
+}
+
+/// Single observable channel for library events
+///
+/// Events are delivered on a dedicated thread, so publishing never waits for
+/// host listeners and a slow listener never blocks library work.
+pub struct EventBus {
+    next_id: AtomicU64,
+    listeners: Arc<Mutex<BTreeMap<u64, Arc<dyn EventListener>>>>,
+    /// Queue feeding the delivery thread, started with the first subscriber
+    sender: Mutex<Option<Sender<LibraryEvent>>>,
+}
+
+impl EventBus {
+    fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            listeners: Arc::new(Mutex::new(BTreeMap::new())),
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Registers a listener and returns its subscription id
+    pub fn subscribe(&self, listener: Box<dyn EventListener>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners
+            .lock()
+            .unwrap()
+            .insert(id, Arc::from(listener));
+        id
+    }
+
+    /// Removes a listener; returns `false` if the id is unknown
+    pub fn unsubscribe(&self, subscription_id: u64) -> bool {
+        self.listeners
+            .lock()
+            .unwrap()
+            .remove(&subscription_id)
+            .is_some()
+    }
+
+    /// Number of registered listeners
+    pub fn subscriber_count(&self) -> u32 {
+        self.listeners.lock().unwrap().len() as u32
+    }
+
+    /// Queues an event for delivery to every listener
+    pub(crate) fn publish(&self, event: LibraryEvent) {
+        if self.listeners.lock().unwrap().is_empty() {
+            return;
+        }
+        let mut sender = self.sender.lock().unwrap();
+        let sender = sender.get_or_insert_with(|| self.start_delivery());
+        // The delivery thread never exits, so sending cannot fail
+        let _ = sender.send(event);
+    }
+
+    fn start_delivery(&self) -> Sender<LibraryEvent> {
+        let (sender, receiver) = mpsc::channel::<LibraryEvent>();
+        let listeners = self.listeners.clone();
+        thread::Builder::new()
+            .name("template-events".to_string())
+            .spawn(move || {
+                for event in receiver {
+                    // Snapshot so listeners may subscribe or unsubscribe
+                    let current: Vec<_> = listeners.lock().unwrap().values().cloned().collect();
+                    for listener in current {
+                        listener.on_event(event.clone());
+                    }
+                }
+            })
+            .expect("failed to start event thread");
+        sender
+    }
+}
+
+static EVENT_BUS: OnceLock<Arc<EventBus>> = OnceLock::new();
+
+/// Returns the process-wide event bus
+pub fn event_bus() -> Arc<EventBus> {
+    EVENT_BUS.get_or_init(|| Arc::new(EventBus::new())).clone()
+}
+
+static RECENT_EVENTS: Mutex<VecDeque<RecordedEvent>> = Mutex::new(VecDeque::new());
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+/// Publishes an event on the process-wide bus and records it
+pub(crate) fn publish(event: LibraryEvent) {
+    let recorded = RecordedEvent {
+        sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        event: event.clone(),
+    };
+    {
+        let mut recent = RECENT_EVENTS.lock().unwrap();
+        if recent.len() >= RECENT_EVENTS_CAPACITY as usize {
+            recent.pop_front();
+        }
+        recent.push_back(recorded);
+    }
+    event_bus().publish(event);
+}
+
+/// Drops recorded events, keeping the newest `keep`
+///
+/// Returns an estimate of the bytes freed.
+pub(crate) fn release_memory(keep: usize) -> u64 {
+    let mut recent = RECENT_EVENTS.lock().unwrap();
+    let capacity = recent.capacity();
+    let excess = recent.len().saturating_sub(keep);
+    recent.drain(..excess);
+    recent.shrink_to_fit();
+    ((capacity - recent.capacity()) * std::mem::size_of::<RecordedEvent>()) as u64
+}
+
+/// Returns the most recent events, oldest first
+///
+/// At most `RECENT_EVENTS_CAPACITY` events are kept. `limit` returns only the
+/// newest `limit` of them.
+pub fn get_recent_events(limit: Option<u32>) -> Vec<RecordedEvent> {
+    let recent = RECENT_EVENTS.lock().unwrap();
+    let skip = limit.map_or(0, |limit| recent.len().saturating_sub(limit as usize));
+    recent.iter().skip(skip).cloned().collect()
+}
@@ -0,0 +1,187 @@
+//! Rotating log files that can be zipped up for bug reports
+//!
+//! `enable_file_logging` writes every forwarded log record to
+//! `template.log` in a host-chosen directory. When the file would exceed
+//! `max_size`, it is renamed to `template.1.log`, older files shift up, and
+//! the oldest beyond `max_files` is deleted. `collect_log_files` packs the
+//! current files into one zip archive.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::logging::{self, LogRecord};
+use crate::runtime;
+use crate::shield;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Name of the file currently written to
+const CURRENT_FILE: &str = "template.log";
+
+struct FileSink {
+    directory: PathBuf,
+    max_size: u64,
+    max_files: u32,
+    file: File,
+    size: u64,
+}
+
+static FILE_SINK: Mutex<Option<FileSink>> = Mutex::new(None);
+
+/// Path of the `index`-th file; 0 is the current one, higher is older
+fn log_path(directory: &Path, index: u32) -> PathBuf {
+    match index {
+        0 => directory.join(CURRENT_FILE),
+        n => directory.join(format!("template.{}.log", n)),
+    }
+}
+
+fn open_current(directory: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(directory, 0))?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+impl FileSink {
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = log_path(&self.directory, self.max_files - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (0..self.max_files - 1).rev() {
+            let from = log_path(&self.directory, index);
+            if from.exists() {
+                fs::rename(&from, log_path(&self.directory, index + 1))?;
+            }
+        }
+        (self.file, self.size) = open_current(&self.directory)?;
+        Ok(())
+    }
+
+    /// Existing log files, oldest first
+    fn files(&self) -> Vec<PathBuf> {
+        (0..self.max_files)
+            .rev()
+            .map(|index| log_path(&self.directory, index))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+}
+
+/// Starts writing log records to rotating files in `directory`
+///
+/// Records are filtered like those sent to `set_logger`, and are written
+/// whether or not a host sink is set. Calling this again switches to the
+/// new settings.
+///
+/// # Arguments
+///
+/// * `directory` - Directory for the log files; created if missing
+/// * `max_size` - Size in bytes at which the current file is rotated
+/// * `max_files` - Number of files kept, including the current one
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `max_size` or `max_files` is 0
+/// * `Err(TemplateError::IoError)` - If the directory or file cannot be created
+pub fn enable_file_logging(directory: String, max_size: u64, max_files: u32) -> TemplateResult<()> {
+    shield::guard("enable_file_logging", || {
+        if max_size == 0 || max_files == 0 {
+            return Err(TemplateError::invalid_input(
+                "max_size and max_files must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        let directory = directories::resolve(StorageCategory::Logs, &directory);
+        fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+        let (file, size) =
+            open_current(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+        *FILE_SINK.lock().unwrap() = Some(FileSink {
+            directory,
+            max_size,
+            max_files,
+            file,
+            size,
+        });
+        logging::install();
+        Ok(())
+    })
+}
+
+/// Stops writing log records to files; existing files are kept
+pub fn disable_file_logging() {
+    *FILE_SINK.lock().unwrap() = None;
+}
+
+/// Whether records should be written to files
+pub(crate) fn is_enabled() -> bool {
+    FILE_SINK.lock().unwrap().is_some()
+}
+
+/// Appends `record` to the current log file
+///
+/// Write errors are dropped: there is nowhere left to report them.
+pub(crate) fn write(record: &LogRecord) {
+    if let Some(sink) = FILE_SINK.lock().unwrap().as_mut() {
+        let line = format!(
+            "{} {:?} {}: {}\n",
+            record.timestamp_ms, record.level, record.target, record.message
+        );
+        let _ = sink.write(line.as_bytes());
+    }
+}
+
+/// Packs the current log files into a zip archive at `destination`
+///
+/// Files are added oldest first under their own names. Records logged while
+/// the archive is written wait until it is done.
+///
+/// # Returns
+///
+/// * `Ok(count)` - The number of log files added to the archive
+/// * `Err(TemplateError::InvalidInput)` - If file logging is not enabled
+/// * `Err(TemplateError::IoError)` - If a file cannot be read or the archive written
+pub async fn collect_log_files(destination: String) -> TemplateResult<u32> {
+    shield::guard_async("collect_log_files", async move {
+        runtime::spawn_blocking(move || {
+            let guard = FILE_SINK.lock().unwrap();
+            let sink = guard.as_ref().ok_or_else(|| {
+                TemplateError::invalid_input("File logging is not enabled".to_string(), None)
+            })?;
+            let destination = directories::resolve(StorageCategory::Temp, &destination);
+            let files = sink.files();
+            write_archive(&destination, &files)
+                .map_err(|e| TemplateError::io_error(&destination, &e))?;
+            Ok(files.len() as u32)
+        })
+        .await
+    })
+    .await
+}
+
+fn write_archive(destination: &Path, files: &[PathBuf]) -> io::Result<()> {
+    let mut archive = ZipWriter::new(File::create(destination)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for path in files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        archive.start_file(name, options)?;
+        io::copy(&mut File::open(path)?, &mut archive)?;
+    }
+    archive.finish()?;
+    Ok(())
+}
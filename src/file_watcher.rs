@@ -0,0 +1,159 @@
+//! Notifications when files in a directory are created, modified, or deleted
+//!
+//! `FileWatcher` uses the platform's change notifications (FSEvents on
+//! macOS, kqueue on iOS, inotify on Linux and Android, and
+//! ReadDirectoryChangesW on Windows) through the `notify` crate, so the app
+//! can refresh a model list or reload a config file edited outside of it
+//! without polling.
+//!
+//! Events arrive on a background thread owned by the watcher. A rename is
+//! reported as the old path deleted and the new path created. Platforms may
+//! coalesce or repeat events, so listeners should treat them as hints to
+//! rescan rather than as an exact log.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What happened to a watched file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChangeKind {
+    Created,
+    /// Contents or metadata changed
+    Modified,
+    Deleted,
+}
+
+/// One change seen by a `FileWatcher`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub kind: FileChangeKind,
+    /// Absolute path of the file or directory
+    pub path: String,
+}
+
+/// Callback notified of changes in a watched directory
+pub trait FileWatchListener: Send + Sync {
+    /// Called on the watcher's thread for every change
+    fn on_file_changed(&self, change: FileChange);
+}
+
+/// Watches one directory until `stop` is called or it is dropped
+pub struct FileWatcher {
+    directory: PathBuf,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl FileWatcher {
+    /// Starts watching `directory`, and its subdirectories if `recursive`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory does not exist or cannot be watched
+    pub fn new(
+        directory: String,
+        recursive: bool,
+        listener: Box<dyn FileWatchListener>,
+    ) -> TemplateResult<Self> {
+        shield::guard("FileWatcher::new", || {
+            let directory = directories::resolve(StorageCategory::Data, &directory);
+            // Platforms report resolved paths (`/private/var` on macOS), so
+            // resolve symlinks up front for event paths to start with `directory()`
+            let directory = fs::canonicalize(&directory)
+                .map_err(|e| TemplateError::io_error(&directory, &e))?;
+            let mut watcher =
+                notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+                    Ok(event) => {
+                        for change in changes(event) {
+                            listener.on_file_changed(change);
+                        }
+                    }
+                    Err(e) => log::warn!("File watcher error: {}", e),
+                })
+                .map_err(|e| watch_error(&directory, e))?;
+            let mode = if recursive {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            watcher
+                .watch(&directory, mode)
+                .map_err(|e| watch_error(&directory, e))?;
+            Ok(Self {
+                directory,
+                watcher: Mutex::new(Some(watcher)),
+            })
+        })
+    }
+
+    /// The watched directory, with symlinks resolved
+    pub fn directory(&self) -> String {
+        self.directory.to_string_lossy().into_owned()
+    }
+
+    /// Whether the watcher is still running
+    pub fn is_watching(&self) -> bool {
+        self.watcher.lock().unwrap().is_some()
+    }
+
+    /// Stops watching; an event already being delivered may still arrive
+    pub fn stop(&self) {
+        self.watcher.lock().unwrap().take();
+    }
+}
+
+/// The changes an event stands for; access events and unknown kinds are dropped
+fn changes(event: Event) -> Vec<FileChange> {
+    let change = |kind, path: &PathBuf| FileChange {
+        kind,
+        path: path.to_string_lossy().into_owned(),
+    };
+    match event.kind {
+        EventKind::Create(_) => event
+            .paths
+            .iter()
+            .map(|path| change(FileChangeKind::Created, path))
+            .collect(),
+        EventKind::Remove(_) => event
+            .paths
+            .iter()
+            .map(|path| change(FileChangeKind::Deleted, path))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(mode)) => event
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let kind = match mode {
+                    RenameMode::From => FileChangeKind::Deleted,
+                    RenameMode::To => FileChangeKind::Created,
+                    RenameMode::Both if index == 0 => FileChangeKind::Deleted,
+                    RenameMode::Both => FileChangeKind::Created,
+                    // FSEvents does not say which side of the rename this is
+                    _ if path.exists() => FileChangeKind::Created,
+                    _ => FileChangeKind::Deleted,
+                };
+                change(kind, path)
+            })
+            .collect(),
+        EventKind::Modify(_) => event
+            .paths
+            .iter()
+            .map(|path| change(FileChangeKind::Modified, path))
+            .collect(),
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => Vec::new(),
+    }
+}
+
+fn watch_error(directory: &Path, error: notify::Error) -> TemplateError {
+    match error.kind {
+        notify::ErrorKind::Io(e) => TemplateError::io_error(directory, &e),
+        _ => TemplateError::io_error(directory, &io::Error::other(error.to_string())),
+    }
+}
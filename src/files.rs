@@ -0,0 +1,396 @@
+//! Small file operations confined to a host-provided directory
+//!
+//! A `FileSandbox` is created with a base directory the host chose, such as
+//! the app's Application Support or `filesDir`. Every path is relative to
+//! it: absolute paths, `..`, and symlinks leading outside are rejected, so a
+//! path from untrusted input cannot reach other files of the app.
+//!
+//! `write_file_atomic` replaces a file so that a crash or power loss leaves
+//! either the old or the new contents. The library uses it for everything it
+//! persists: stores, job queues, and crash records.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use crate::shield;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Times a rename is retried on Windows while another process holds the file
+const RENAME_RETRIES: u32 = 5;
+
+/// Size, type, and modification time of a file or directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Path relative to the sandbox's base directory
+    pub path: String,
+    /// Size in bytes; 0 for directories
+    pub size: u64,
+    pub is_directory: bool,
+    /// Unix timestamp of the last modification in milliseconds, if the OS reports it
+    pub modified_ms: Option<u64>,
+}
+
+/// File operations confined to one base directory
+pub struct FileSandbox {
+    sandbox: Sandbox,
+}
+
+#[derive(Clone)]
+struct Sandbox {
+    /// Canonical base directory
+    base: PathBuf,
+    max_file_size: u64,
+}
+
+impl FileSandbox {
+    /// Create a sandbox rooted at `base_directory`, creating it if missing
+    ///
+    /// # Arguments
+    ///
+    /// * `base_directory` - Directory all paths are relative to
+    /// * `max_file_size` - Largest file, in bytes, that can be read or written
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `max_file_size` is 0
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be created
+    pub fn new(base_directory: String, max_file_size: u64) -> TemplateResult<Self> {
+        shield::guard("FileSandbox::new", || {
+            if max_file_size == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_file_size must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+            let base = directories::resolve(StorageCategory::Data, &base_directory);
+            fs::create_dir_all(&base).map_err(|e| TemplateError::io_error(&base, &e))?;
+            let base = base
+                .canonicalize()
+                .map_err(|e| TemplateError::io_error(&base, &e))?;
+            Ok(Self {
+                sandbox: Sandbox {
+                    base,
+                    max_file_size,
+                },
+            })
+        })
+    }
+
+    /// Reads a whole file (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the
+    ///   sandbox or the file is larger than `max_file_size`
+    /// * `Err(TemplateError::IoError)` - If the file cannot be read
+    pub async fn read(&self, path: String) -> TemplateResult<Vec<u8>> {
+        self.run("FileSandbox::read", path, |sandbox, relative, full| {
+            let size = fs::metadata(&full)
+                .map_err(|e| TemplateError::io_error(&full, &e))?
+                .len();
+            if size > sandbox.max_file_size {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "File '{}' is {} bytes, over the limit of {}",
+                        relative, size, sandbox.max_file_size
+                    ),
+                    None,
+                ));
+            }
+            fs::read(&full).map_err(|e| TemplateError::io_error(&full, &e))
+        })
+        .await
+    }
+
+    /// Replaces a file's contents, creating it and its directories if missing (async)
+    ///
+    /// The file is replaced atomically, as with `write_file_atomic`.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InputTooLarge)` - If `data` is larger than `max_file_size`
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the sandbox
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub async fn write(&self, path: String, data: Vec<u8>) -> TemplateResult<()> {
+        self.run("FileSandbox::write", path, move |sandbox, _, full| {
+            sandbox.check_size(data.len() as u64, &data)?;
+            create_parent(&full)?;
+            write_atomic(&full, &data).map_err(|e| TemplateError::io_error(&full, &e))
+        })
+        .await
+    }
+
+    /// Appends to a file, creating it and its directories if missing (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InputTooLarge)` - If the file would grow beyond `max_file_size`
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the sandbox
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub async fn append(&self, path: String, data: Vec<u8>) -> TemplateResult<()> {
+        self.run("FileSandbox::append", path, move |sandbox, _, full| {
+            let existing = match fs::metadata(&full) {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(TemplateError::io_error(&full, &e)),
+            };
+            sandbox.check_size(existing + data.len() as u64, &data)?;
+            create_parent(&full)?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&full)
+                .and_then(|mut file| file.write_all(&data))
+                .map_err(|e| TemplateError::io_error(&full, &e))
+        })
+        .await
+    }
+
+    /// Deletes a file or an empty directory, returning whether it existed (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the
+    ///   sandbox or is the base directory itself
+    /// * `Err(TemplateError::IoError)` - If it cannot be deleted, e.g. a
+    ///   directory that is not empty
+    pub async fn delete(&self, path: String) -> TemplateResult<bool> {
+        self.run("FileSandbox::delete", path, |sandbox, relative, full| {
+            if full == sandbox.base {
+                return Err(TemplateError::invalid_input(
+                    "Cannot delete the sandbox's base directory".to_string(),
+                    Some(&relative),
+                ));
+            }
+            let removed = match fs::symlink_metadata(&full) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir(&full),
+                Ok(_) => fs::remove_file(&full),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => Err(e),
+            };
+            removed
+                .map(|()| true)
+                .map_err(|e| TemplateError::io_error(&full, &e))
+        })
+        .await
+    }
+
+    /// Lists a directory's entries, sorted by name; `""` lists the base directory (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the sandbox
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be read
+    pub async fn list(&self, path: String) -> TemplateResult<Vec<FileMetadata>> {
+        self.run("FileSandbox::list", path, |sandbox, _, full| {
+            let entries = fs::read_dir(&full).map_err(|e| TemplateError::io_error(&full, &e))?;
+            let mut listed = Vec::new();
+            for entry in entries {
+                let entry = entry.map_err(|e| TemplateError::io_error(&full, &e))?;
+                listed.push(sandbox.metadata(&entry.path())?);
+            }
+            listed.sort_by(|a, b| a.path.cmp(&b.path));
+            Ok(listed)
+        })
+        .await
+    }
+
+    /// Whether a file or directory exists at `path` (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the sandbox
+    pub async fn exists(&self, path: String) -> TemplateResult<bool> {
+        self.run("FileSandbox::exists", path, |_, _, full| Ok(full.exists()))
+            .await
+    }
+
+    /// Size, type, and modification time of a file or directory (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the path is outside the sandbox
+    /// * `Err(TemplateError::IoError)` - If nothing exists at `path`
+    pub async fn metadata(&self, path: String) -> TemplateResult<FileMetadata> {
+        self.run("FileSandbox::metadata", path, |sandbox, _, full| {
+            sandbox.metadata(&full)
+        })
+        .await
+    }
+
+    /// Resolves `path` and runs `work` with it on the blocking pool
+    async fn run<T, F>(&self, operation: &'static str, path: String, work: F) -> TemplateResult<T>
+    where
+        F: FnOnce(&Sandbox, String, PathBuf) -> TemplateResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let sandbox = self.sandbox.clone();
+        shield::guard_async(operation, async move {
+            runtime::spawn_blocking(move || {
+                let full = sandbox.resolve(&path)?;
+                work(&sandbox, path, full)
+            })
+            .await
+        })
+        .await
+    }
+}
+
+impl Sandbox {
+    /// The absolute path for `relative`, if it stays inside the base directory
+    ///
+    /// Symlinks are followed for the part of the path that exists.
+    fn resolve(&self, relative: &str) -> TemplateResult<PathBuf> {
+        let outside = || {
+            TemplateError::invalid_input(
+                format!("Path '{}' is outside the sandbox", relative),
+                None,
+            )
+        };
+        let mut full = self.base.clone();
+        for component in Path::new(relative).components() {
+            match component {
+                Component::Normal(part) => full.push(part),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(outside())
+                }
+            }
+        }
+        let existing = full
+            .ancestors()
+            .find(|path| path.symlink_metadata().is_ok())
+            .unwrap_or(&self.base);
+        let canonical = existing
+            .canonicalize()
+            .map_err(|e| TemplateError::io_error(existing, &e))?;
+        if !canonical.starts_with(&self.base) {
+            return Err(outside());
+        }
+        Ok(full)
+    }
+
+    fn check_size(&self, size: u64, data: &[u8]) -> TemplateResult<()> {
+        if size > self.max_file_size {
+            return Err(TemplateError::data_too_large(
+                size,
+                self.max_file_size,
+                data,
+            ));
+        }
+        Ok(())
+    }
+
+    fn metadata(&self, full: &Path) -> TemplateResult<FileMetadata> {
+        let metadata = fs::metadata(full).map_err(|e| TemplateError::io_error(full, &e))?;
+        let path = full.strip_prefix(&self.base).unwrap_or(full);
+        Ok(FileMetadata {
+            path: path.to_string_lossy().into_owned(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            is_directory: metadata.is_dir(),
+            modified_ms: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+        })
+    }
+}
+
+/// Replaces the file at `path` with `data`, durably and atomically (async)
+///
+/// The data is written to a temporary file in the same directory, flushed to
+/// disk, and renamed over `path`; the directory is then synced so the rename
+/// itself survives power loss. Readers and crashes see either the old or the
+/// new contents, never a mix. The directory must exist.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If the file cannot be written or replaced;
+///   the previous contents are left in place
+pub async fn write_file_atomic(path: String, data: Vec<u8>) -> TemplateResult<()> {
+    shield::guard_async("write_file_atomic", async move {
+        runtime::spawn_blocking(move || {
+            let path = PathBuf::from(path);
+            write_atomic(&path, &data).map_err(|e| TemplateError::io_error(&path, &e))
+        })
+        .await
+    })
+    .await
+}
+
+/// Blocking implementation of `write_file_atomic`
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    // A unique name so concurrent writers never share a temporary file
+    let temp = directory.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        Uuid::new_v4().simple()
+    ));
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+            // Closed here: Windows cannot rename a file that is still open
+        })
+        .and_then(|()| rename_replacing(&temp, path))
+        .and_then(|()| sync_directory(directory));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Renames `from` over `to`
+///
+/// On Windows, virus scanners and the search indexer briefly hold files
+/// open, which makes the rename fail with access denied; it is retried.
+pub(crate) fn rename_replacing(from: &Path, to: &Path) -> io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match fs::rename(from, to) {
+            Err(e)
+                if cfg!(windows)
+                    && e.kind() == io::ErrorKind::PermissionDenied
+                    && attempt < RENAME_RETRIES =>
+            {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(10 * u64::from(attempt)));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Flushes the directory entry so a completed rename survives power loss
+///
+/// Needed on Linux and Android. Filesystems that cannot sync directories
+/// (e.g. FAT on external storage) are skipped, as is Windows, where
+/// directories cannot be opened this way.
+pub(crate) fn sync_directory(directory: &Path) -> io::Result<()> {
+    if cfg!(windows) {
+        return Ok(());
+    }
+    match File::open(directory).and_then(|dir| dir.sync_all()) {
+        Err(e) if e.kind() == io::ErrorKind::InvalidInput => Ok(()),
+        result => result,
+    }
+}
+
+fn create_parent(path: &Path) -> TemplateResult<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent).map_err(|e| TemplateError::io_error(parent, &e)),
+        None => Ok(()),
+    }
+}
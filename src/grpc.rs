@@ -0,0 +1,331 @@
+//! gRPC client plumbing for proto-defined backend services (`grpc` feature)
+//!
+//! Service clients are generated in Rust with `tonic-build`, so typed RPC
+//! lives in the shared core instead of duplicated Swift and Kotlin stubs.
+//! The host decides where each service lives: `register_grpc_service`
+//! maps a fully qualified proto service name, such as `chat.v1.ChatService`,
+//! to an endpoint. Rust code then wraps `grpc_channel(name)` in the
+//! generated client and runs calls with `grpc_call`, which moves them onto
+//! the internal runtime, honors a cancellation token, and turns a
+//! `tonic::Status` into a `TemplateError`.
+//!
+//! Channels connect lazily over HTTP/2 with rustls and the bundled roots,
+//! like `http_request`: pinned hosts must present a pinned key, and
+//! connections go through the proxy from `set_network_config`. Changing the
+//! network settings drops the cached channels, so new calls reconnect with
+//! the new settings. Without the `grpc` feature the registration functions
+//! exist so the bindings stay the same, but fail.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+
+#[cfg(feature = "grpc")]
+use crate::cancellation::CancellationToken;
+#[cfg(feature = "grpc")]
+use crate::http;
+#[cfg(feature = "grpc")]
+use crate::network;
+#[cfg(feature = "grpc")]
+use crate::pinning;
+#[cfg(feature = "grpc")]
+use hyper_util::rt::TokioIo;
+#[cfg(feature = "grpc")]
+use rustls::pki_types::ServerName;
+#[cfg(feature = "grpc")]
+use std::collections::BTreeMap;
+#[cfg(feature = "grpc")]
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "grpc")]
+use std::time::Duration;
+#[cfg(feature = "grpc")]
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "grpc")]
+use tokio::net::TcpStream;
+#[cfg(feature = "grpc")]
+use tonic::transport::{Channel, Endpoint, Uri};
+
+/// Where a registered gRPC service is reached
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GrpcServiceConfig {
+    /// Absolute `http` (cleartext HTTP/2) or `https` URL of the server,
+    /// e.g. `https://api.example.com:443`
+    pub endpoint: String,
+    /// Limit for each call, unless the request sets its own deadline
+    pub timeout_ms: Option<u64>,
+    /// Limit for opening a connection
+    pub connect_timeout_ms: Option<u64>,
+}
+
+/// A registered service and, once first used, its channel
+#[cfg(feature = "grpc")]
+struct Registered {
+    config: GrpcServiceConfig,
+    channel: Option<Channel>,
+}
+
+/// Registered services by proto service name
+#[cfg(feature = "grpc")]
+static SERVICES: RwLock<BTreeMap<String, Registered>> = RwLock::new(BTreeMap::new());
+
+/// Registers (or replaces) the endpoint of a proto service
+///
+/// # Arguments
+///
+/// * `name` - Fully qualified proto service name, e.g. `chat.v1.ChatService`
+/// * `config` - Endpoint and timeouts for calls to the service
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the name is empty, the endpoint
+///   is not an absolute `http(s)` URL, or the library was built without the
+///   `grpc` feature
+pub fn register_grpc_service(name: String, config: GrpcServiceConfig) -> TemplateResult<()> {
+    shield::guard("register_grpc_service", || {
+        #[cfg(feature = "grpc")]
+        {
+            if name.trim().is_empty() || name.contains('/') {
+                return Err(TemplateError::invalid_input(
+                    format!("Invalid gRPC service name: '{}'", name),
+                    None,
+                ));
+            }
+            endpoint(&config)?;
+            log::info!("gRPC service {} registered at {}", name, config.endpoint);
+            SERVICES.write().unwrap().insert(
+                name,
+                Registered {
+                    config,
+                    channel: None,
+                },
+            );
+            Ok(())
+        }
+        #[cfg(not(feature = "grpc"))]
+        {
+            let _ = (name, config);
+            Err(TemplateError::invalid_input(
+                "register_grpc_service requires the `grpc` feature".to_string(),
+                None,
+            ))
+        }
+    })
+}
+
+/// Removes a registered service; returns `false` if it was not registered
+///
+/// Clients already holding its channel keep working until dropped.
+pub fn unregister_grpc_service(name: String) -> bool {
+    #[cfg(feature = "grpc")]
+    {
+        SERVICES.write().unwrap().remove(&name).is_some()
+    }
+    #[cfg(not(feature = "grpc"))]
+    {
+        let _ = name;
+        false
+    }
+}
+
+/// Names of the registered services, in alphabetical order
+pub fn get_grpc_services() -> Vec<String> {
+    #[cfg(feature = "grpc")]
+    {
+        SERVICES.read().unwrap().keys().cloned().collect()
+    }
+    #[cfg(not(feature = "grpc"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Channel to a registered service, for wrapping in a generated client
+/// (Rust only)
+///
+/// The channel is shared by every caller, connects on first use, and
+/// reconnects after failures. Clone it freely: clones share connections.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the service is not registered
+#[cfg(feature = "grpc")]
+pub fn grpc_channel(name: &str) -> TemplateResult<Channel> {
+    if let Some(channel) = SERVICES
+        .read()
+        .unwrap()
+        .get(name)
+        .and_then(|service| service.channel.clone())
+    {
+        return Ok(channel);
+    }
+    let mut services = SERVICES.write().unwrap();
+    let service = services.get_mut(name).ok_or_else(|| {
+        TemplateError::invalid_input(format!("gRPC service '{}' is not registered", name), None)
+    })?;
+    let endpoint = endpoint(&service.config)?;
+    Ok(service
+        .channel
+        .get_or_insert_with(|| endpoint.connect_with_connector_lazy(tower::service_fn(connect)))
+        .clone())
+}
+
+/// Runs a call made with a generated client on the internal runtime (async,
+/// Rust only)
+///
+/// tonic needs a tokio reactor, which the Swift and Kotlin executors lack.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::NetworkError)` - If the server could not be
+///   reached, or answered with an error status; see `grpc_status_error`
+/// * `Err(TemplateError::PinningFailure)` - If a pinned host presented no pinned key
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+///   or the server cancelled the call
+#[cfg(feature = "grpc")]
+pub async fn grpc_call<T, F>(
+    name: &str,
+    token: Option<Arc<CancellationToken>>,
+    call: F,
+) -> TemplateResult<T>
+where
+    T: Send + 'static,
+    F: std::future::Future<Output = Result<tonic::Response<T>, tonic::Status>> + Send + 'static,
+{
+    let service = name.to_string();
+    http::on_runtime(token.as_deref(), name, async move {
+        call.await
+            .map(tonic::Response::into_inner)
+            .map_err(|status| grpc_status_error(&service, &status))
+    })
+    .await
+}
+
+/// The `TemplateError` for a failed call to service `name` (Rust only)
+///
+/// Failures to reach the server are `NetworkError`s without a status
+/// code, or the `PinningFailure` or proxy error raised while connecting.
+/// Error statuses from the server are `NetworkError`s with the HTTP status
+/// gRPC gateways map them to (`NotFound` is 404, `Unavailable` 503), so
+/// retry logic treats them like HTTP errors. `Cancelled` is
+/// `OperationCancelled`.
+#[cfg(feature = "grpc")]
+pub fn grpc_status_error(name: &str, status: &tonic::Status) -> TemplateError {
+    use tonic::Code;
+
+    let mut source = std::error::Error::source(status);
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<TemplateError>() {
+            return error.clone();
+        }
+        source = error.source();
+    }
+    let code = status.code();
+    if code == Code::Cancelled {
+        return TemplateError::operation_cancelled(name);
+    }
+    let status_code = match code {
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => 400,
+        Code::Unauthenticated => 401,
+        Code::PermissionDenied => 403,
+        Code::NotFound => 404,
+        Code::AlreadyExists | Code::Aborted => 409,
+        Code::ResourceExhausted => 429,
+        Code::Unimplemented => 501,
+        Code::Unavailable => 503,
+        Code::DeadlineExceeded => 504,
+        _ => 500,
+    };
+    // A transport failure carries its cause; the server never answered
+    let status_code = std::error::Error::source(status)
+        .is_none()
+        .then_some(status_code);
+    TemplateError::network_error(
+        &service_url(name),
+        status_code,
+        format!("gRPC {:?}: {}", code, status.message()),
+    )
+}
+
+/// Drops every cached channel, after the network settings change; calls
+/// in flight keep the old one
+#[cfg(feature = "grpc")]
+pub(crate) fn reset_channels() {
+    for service in SERVICES.write().unwrap().values_mut() {
+        service.channel = None;
+    }
+}
+
+/// `config` checked and turned into a tonic endpoint
+#[cfg(feature = "grpc")]
+fn endpoint(config: &GrpcServiceConfig) -> TemplateResult<Endpoint> {
+    http::parse_url(&config.endpoint)?;
+    let mut endpoint = Endpoint::from_shared(config.endpoint.clone()).map_err(|e| {
+        TemplateError::invalid_input(
+            format!("Invalid gRPC endpoint '{}': {}", config.endpoint, e),
+            None,
+        )
+    })?;
+    if let Some(ms) = config.timeout_ms {
+        endpoint = endpoint.timeout(Duration::from_millis(ms));
+    }
+    if let Some(ms) = config.connect_timeout_ms {
+        endpoint = endpoint.connect_timeout(Duration::from_millis(ms));
+    }
+    Ok(endpoint)
+}
+
+/// Endpoint and service name, for errors
+#[cfg(feature = "grpc")]
+fn service_url(name: &str) -> String {
+    match SERVICES.read().unwrap().get(name) {
+        Some(service) => format!("{}/{}", service.config.endpoint.trim_end_matches('/'), name),
+        None => name.to_string(),
+    }
+}
+
+/// A connection tonic can speak HTTP/2 over, with or without TLS
+#[cfg(feature = "grpc")]
+trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+#[cfg(feature = "grpc")]
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// Opens a connection to `uri`, through the proxy and with pinned TLS when
+/// the settings in effect ask for them
+#[cfg(feature = "grpc")]
+async fn connect(uri: Uri) -> TemplateResult<TokioIo<Box<dyn Io>>> {
+    let url = uri.to_string();
+    let host = uri
+        .host()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let https = uri.scheme_str() == Some("https");
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+    let stream = match network::proxy().filter(|proxy| !proxy.bypasses(&host)) {
+        Some(proxy) => proxy.tunnel(&host, port).await?,
+        None => TcpStream::connect((host.as_str(), port))
+            .await
+            .map_err(|e| {
+                TemplateError::network_error(&url, None, format!("Cannot connect: {}", e))
+            })?,
+    };
+    let _ = stream.set_nodelay(true);
+    if !https {
+        return Ok(TokioIo::new(Box::new(stream)));
+    }
+    let mut tls = pinning::tls_config(network::pins(), network::client_identity());
+    tls.alpn_protocols = vec![b"h2".to_vec()];
+    let server_name = ServerName::try_from(host.clone()).map_err(|_| {
+        TemplateError::network_error(&url, None, format!("Invalid server name: '{}'", host))
+    })?;
+    let stream = tokio_rustls::TlsConnector::from(Arc::new(tls))
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| {
+            pinning::failure(&e).unwrap_or_else(|| {
+                TemplateError::network_error(&url, None, format!("TLS handshake failed: {}", e))
+            })
+        })?;
+    Ok(TokioIo::new(Box::new(stream)))
+}
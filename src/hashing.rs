@@ -0,0 +1,74 @@
+//! Content hashing for echo results
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hash algorithm used to fill `EchoResult::hash`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// SHA-256, 64 hex characters (default)
+    #[default]
+    Sha256,
+    /// BLAKE3, 64 hex characters
+    Blake3,
+    /// XXH3 64-bit, 16 hex characters (fast, not cryptographic)
+    Xxhash,
+    /// Do not compute a hash
+    None,
+}
+
+/// Hashes `input` with the given algorithm, returning lowercase hex
+///
+/// Returns `None` when the algorithm is `HashAlgorithm::None`.
+pub(crate) fn hash_text(input: &str, algorithm: HashAlgorithm) -> Option<String> {
+    let bytes = input.as_bytes();
+    match algorithm {
+        HashAlgorithm::Sha256 => Some(hex::encode(Sha256::digest(bytes))),
+        HashAlgorithm::Blake3 => Some(blake3::hash(bytes).to_hex().to_string()),
+        HashAlgorithm::Xxhash => Some(format!("{:016x}", xxhash_rust::xxh3::xxh3_64(bytes))),
+        HashAlgorithm::None => None,
+    }
+}
+
+/// Incremental hasher producing the same output as `hash_text`
+pub(crate) enum StreamingHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Xxhash(Box<xxhash_rust::xxh3::Xxh3>),
+    None,
+}
+
+impl StreamingHasher {
+    /// Create a hasher for the given algorithm
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Xxhash => Self::Xxhash(Box::default()),
+            HashAlgorithm::None => Self::None,
+        }
+    }
+
+    /// Feed more bytes into the hash
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(bytes),
+            Self::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+            Self::Xxhash(hasher) => hasher.update(bytes),
+            Self::None => {}
+        }
+    }
+
+    /// Finish hashing, returning lowercase hex
+    pub(crate) fn finalize(self) -> Option<String> {
+        match self {
+            Self::Sha256(hasher) => Some(hex::encode(hasher.finalize())),
+            Self::Blake3(hasher) => Some(hasher.finalize().to_hex().to_string()),
+            Self::Xxhash(hasher) => Some(format!("{:016x}", hasher.digest())),
+            Self::None => None,
+        }
+    }
+}
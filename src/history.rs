@@ -0,0 +1,360 @@
+//! Opt-in persistent history of operation results
+//!
+//! Once `enable_history(path, max_entries)` is called, every `echo` and
+//! `validate_and_echo` call, including those started by `spawn_echo`, is
+//! recorded with a SHA-256 hash of its output (never the text itself),
+//! when it finished, how long it took, and whether it succeeded. Records are
+//! appended to a JSON Lines file and reloaded on the next launch, so a
+//! "recent activity" screen can be built on `query_history` alone.
+//!
+//! Only the newest `max_entries` records are kept. The file is compacted
+//! once it holds twice that many lines, so appends stay cheap.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{ErrorKind, TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use crate::hashing::{hash_text, HashAlgorithm};
+use crate::shield;
+use crate::template::EchoResult;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Most entries returned by one `query_history` call
+pub const MAX_HISTORY_PAGE: u32 = 500;
+
+/// How a recorded operation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryStatus {
+    Succeeded,
+    Failed,
+    /// Cancelled by a token, the host, or a timeout
+    Cancelled,
+}
+
+/// One recorded operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Increasing id, unique within the history file
+    pub id: u64,
+    /// Name of the operation, e.g. `echo`
+    pub operation: String,
+    /// Hex SHA-256 of the output text, if the operation produced any
+    pub text_hash: Option<String>,
+    /// When the operation finished, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub duration_us: u64,
+    pub status: HistoryStatus,
+    /// Kind of the error, if the operation did not succeed
+    pub error_kind: Option<ErrorKind>,
+}
+
+/// Which entries `query_history` returns; unset fields match everything
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HistoryFilter {
+    pub operation: Option<String>,
+    pub status: Option<HistoryStatus>,
+    /// Only entries at or after this time, in milliseconds since the Unix epoch
+    pub since_ms: Option<u64>,
+    /// Only entries before this time, in milliseconds since the Unix epoch
+    pub until_ms: Option<u64>,
+}
+
+/// A window of the matching entries, newest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Matching entries to skip
+    pub offset: u32,
+    /// Most entries to return, at most `MAX_HISTORY_PAGE`
+    pub limit: u32,
+}
+
+/// One page of `query_history` results
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryPage {
+    /// Matching entries, newest first
+    pub entries: Vec<HistoryEntry>,
+    /// Number of entries matching the filter across all pages
+    pub total_count: u32,
+    /// Offset of the next page, if there is one
+    pub next_offset: Option<u32>,
+}
+
+/// An entry as stored in the history file
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    id: u64,
+    operation: String,
+    text_hash: Option<String>,
+    timestamp_ms: u64,
+    duration_us: u64,
+    status: HistoryStatus,
+    error_code: Option<String>,
+}
+
+impl From<&HistoryEntry> for StoredEntry {
+    fn from(entry: &HistoryEntry) -> Self {
+        Self {
+            id: entry.id,
+            operation: entry.operation.clone(),
+            text_hash: entry.text_hash.clone(),
+            timestamp_ms: entry.timestamp_ms,
+            duration_us: entry.duration_us,
+            status: entry.status,
+            error_code: entry.error_kind.map(ErrorKind::code),
+        }
+    }
+}
+
+impl From<StoredEntry> for HistoryEntry {
+    fn from(stored: StoredEntry) -> Self {
+        Self {
+            id: stored.id,
+            operation: stored.operation,
+            text_hash: stored.text_hash,
+            timestamp_ms: stored.timestamp_ms,
+            duration_us: stored.duration_us,
+            status: stored.status,
+            error_kind: stored.error_code.as_deref().and_then(ErrorKind::from_code),
+        }
+    }
+}
+
+struct History {
+    path: PathBuf,
+    max_entries: usize,
+    /// Newest last
+    entries: VecDeque<HistoryEntry>,
+    next_id: u64,
+    file: File,
+    lines_in_file: usize,
+}
+
+static HISTORY: Mutex<Option<History>> = Mutex::new(None);
+
+/// Starts recording operation results to the file at `path`
+///
+/// Entries already in the file are kept, up to `max_entries`. Calling this
+/// again switches to the new file and limit.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `max_entries` is 0
+/// * `Err(TemplateError::IoError)` - If the file cannot be read or created
+pub fn enable_history(path: String, max_entries: u32) -> TemplateResult<()> {
+    shield::guard("enable_history", || {
+        if max_entries == 0 {
+            return Err(TemplateError::invalid_input(
+                "max_entries must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        let path = directories::resolve(StorageCategory::Data, &path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| TemplateError::io_error(parent, &e))?;
+        }
+        let (mut entries, intact) = load_entries(&path)?;
+        let lines_in_file = entries.len();
+        let excess = entries.len().saturating_sub(max_entries as usize);
+        entries.drain(..excess);
+        let next_id = entries.back().map_or(1, |entry| entry.id + 1);
+        let mut history = History {
+            file: open_for_append(&path)?,
+            path,
+            max_entries: max_entries as usize,
+            entries,
+            next_id,
+            lines_in_file,
+        };
+        if excess > 0 || !intact {
+            history.compact()?;
+        }
+        *HISTORY.lock().unwrap() = Some(history);
+        Ok(())
+    })
+}
+
+/// Stops recording; the history file is left in place
+pub fn disable_history() {
+    *HISTORY.lock().unwrap() = None;
+}
+
+/// Deletes every recorded entry
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If the history file cannot be rewritten
+pub fn clear_history() -> TemplateResult<()> {
+    shield::guard("clear_history", || {
+        let mut history = HISTORY.lock().unwrap();
+        match history.as_mut() {
+            Some(history) => {
+                history.entries.clear();
+                history.compact()
+            }
+            None => Ok(()),
+        }
+    })
+}
+
+/// Returns one page of the entries matching `filter`, newest first
+///
+/// An empty page is returned while history is disabled.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `page.limit` is 0 or over `MAX_HISTORY_PAGE`
+pub fn query_history(filter: HistoryFilter, page: PageRequest) -> TemplateResult<HistoryPage> {
+    shield::guard("query_history", || {
+        if page.limit == 0 || page.limit > MAX_HISTORY_PAGE {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "limit must be between 1 and {}, got {}",
+                    MAX_HISTORY_PAGE, page.limit
+                ),
+                None,
+            ));
+        }
+        let history = HISTORY.lock().unwrap();
+        let matching: Vec<&HistoryEntry> = history
+            .iter()
+            .flat_map(|history| history.entries.iter().rev())
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        let total_count = matching.len() as u32;
+        let entries: Vec<HistoryEntry> = matching
+            .into_iter()
+            .skip(page.offset as usize)
+            .take(page.limit as usize)
+            .cloned()
+            .collect();
+        let end = page.offset.saturating_add(entries.len() as u32);
+        Ok(HistoryPage {
+            next_offset: (end < total_count).then_some(end),
+            entries,
+            total_count,
+        })
+    })
+}
+
+/// Records the result of an echo operation, if history is enabled
+pub(crate) fn record(
+    operation: &str,
+    started: Instant,
+    result: &TemplateResult<Option<EchoResult>>,
+) {
+    let mut history = HISTORY.lock().unwrap();
+    let Some(history) = history.as_mut() else {
+        return;
+    };
+    let (status, error_kind) = match result {
+        Ok(_) => (HistoryStatus::Succeeded, None),
+        Err(e) => match e.kind() {
+            kind @ (ErrorKind::OperationCancelled | ErrorKind::Timeout) => {
+                (HistoryStatus::Cancelled, Some(kind))
+            }
+            kind => (HistoryStatus::Failed, Some(kind)),
+        },
+    };
+    let entry = HistoryEntry {
+        id: history.next_id,
+        operation: operation.to_string(),
+        text_hash: match result {
+            Ok(Some(echoed)) => hash_text(&echoed.text, HashAlgorithm::Sha256),
+            _ => None,
+        },
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        duration_us: started.elapsed().as_micros().min(u64::MAX as u128) as u64,
+        status,
+        error_kind,
+    };
+    if let Err(e) = history.append(entry) {
+        log::warn!("Could not record history: {}", e);
+    }
+}
+
+impl History {
+    fn append(&mut self, entry: HistoryEntry) -> TemplateResult<()> {
+        self.next_id = entry.id + 1;
+        let mut line = serde_json::to_vec(&StoredEntry::from(&entry))
+            .map_err(|e| TemplateError::json_error(&e))?;
+        line.push(b'\n');
+        self.entries.push_back(entry);
+        if self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+        self.file
+            .write_all(&line)
+            .map_err(|e| TemplateError::io_error(&self.path, &e))?;
+        self.lines_in_file += 1;
+        if self.lines_in_file >= self.max_entries * 2 {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the file with only the entries kept in memory
+    fn compact(&mut self) -> TemplateResult<()> {
+        let mut contents = Vec::new();
+        for entry in &self.entries {
+            serde_json::to_writer(&mut contents, &StoredEntry::from(entry))
+                .map_err(|e| TemplateError::json_error(&e))?;
+            contents.push(b'\n');
+        }
+        write_atomic(&self.path, &contents).map_err(|e| TemplateError::io_error(&self.path, &e))?;
+        self.file = open_for_append(&self.path)?;
+        self.lines_in_file = self.entries.len();
+        Ok(())
+    }
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        self.operation
+            .as_ref()
+            .is_none_or(|operation| *operation == entry.operation)
+            && self.status.is_none_or(|status| status == entry.status)
+            && self
+                .since_ms
+                .is_none_or(|since| entry.timestamp_ms >= since)
+            && self.until_ms.is_none_or(|until| entry.timestamp_ms < until)
+    }
+}
+
+fn open_for_append(path: &Path) -> TemplateResult<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| TemplateError::io_error(path, &e))
+}
+
+/// Reads the history file, oldest first; a missing file means no history
+///
+/// Lines that cannot be read, such as one cut short by a crash mid-append,
+/// are skipped, and the returned flag is false so the file gets rewritten.
+fn load_entries(path: &Path) -> TemplateResult<(VecDeque<HistoryEntry>, bool)> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((VecDeque::new(), true)),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    let mut intact = contents.is_empty() || contents.ends_with('\n');
+    let entries = contents
+        .lines()
+        .filter_map(|line| {
+            let entry = serde_json::from_str::<StoredEntry>(line).ok();
+            intact &= entry.is_some();
+            entry
+        })
+        .map(HistoryEntry::from)
+        .collect();
+    Ok((entries, intact))
+}
@@ -0,0 +1,363 @@
+//! HTTP client for networking logic kept in the shared core (`http` feature)
+//!
+//! `http_request` sends one request with reqwest on the internal runtime,
+//! using rustls and bundled root certificates, so requests behave the same
+//! on both platforms regardless of the system TLS stack. Hosts pinned with
+//! `set_network_config` must also present a pinned key, requests go through
+//! the proxy it sets, and its client identity answers servers that require
+//! mutual TLS. Redirects are followed (up to 10) and any status code is
+//! returned as a response; only failures to get a response are errors. Once
+//! `enable_http_cache` is called, `GET` requests are answered from the cache
+//! while fresh and revalidated with their `ETag` or `Last-Modified` after
+//! that. Tokens from the `AuthManager` set with `set_auth_manager` are
+//! attached per host, and a request rejected with 401 is sent once more
+//! after refreshing its token. Interceptors added with
+//! `add_http_interceptor` may change each request and see its response.
+//! Without the `http` feature the types exist so the bindings stay the
+//! same, but requests fail.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::http_cache::{self, CacheLookup};
+#[cfg(feature = "http")]
+use crate::interceptors;
+#[cfg(feature = "http")]
+use crate::network;
+#[cfg(feature = "http")]
+use crate::pinning;
+#[cfg(feature = "http")]
+use crate::runtime;
+#[cfg(feature = "http")]
+use std::sync::RwLock;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+/// Operation name used in errors
+#[cfg(feature = "http")]
+const OPERATION: &str = "http_request";
+
+/// HTTP request method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+/// A request for `http_request`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Absolute `http` or `https` URL
+    pub url: String,
+    pub method: HttpMethod,
+    pub headers: HashMap<String, String>,
+    pub body: Option<Vec<u8>>,
+    /// Limit for the whole request, including reading the body
+    pub timeout_ms: Option<u64>,
+}
+
+/// A response received by `http_request`, whatever its status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Header names in lower case; repeated headers are joined with `, `
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+    /// URL of the final response, after redirects
+    pub url: String,
+}
+
+impl HttpResponse {
+    /// Whether the status is in the 2xx range
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends `request` and reads the whole response (async)
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid,
+///   or the library was built without the `http` feature
+/// * `Err(TemplateError::NetworkError)` - If no response was received
+/// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn http_request(
+    request: HttpRequest,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<HttpResponse> {
+    shield::guard_async("http_request", async move {
+        #[cfg(feature = "http")]
+        {
+            let mut request = request;
+            let sent = auth::authorize(&request.url, &mut request.headers).await?;
+            let response = send(&request, token.clone()).await?;
+            if let Some(sent) = sent.filter(|_| response.status == 401) {
+                if auth::reauthorize(&request.url, &sent, &mut request.headers).await? {
+                    return send(&request, token).await;
+                }
+            }
+            Ok(response)
+        }
+        #[cfg(not(feature = "http"))]
+        {
+            let _ = (request, token);
+            Err(TemplateError::invalid_input(
+                "http_request requires the `http` feature".to_string(),
+                None,
+            ))
+        }
+    })
+    .await
+}
+
+/// Sends `request` once, through the interceptors and the HTTP cache
+#[cfg(feature = "http")]
+async fn send(
+    request: &HttpRequest,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<HttpResponse> {
+    let intercepted = interceptors::intercept(request).await;
+    let request = intercepted.as_ref().unwrap_or(request);
+    let response = fetch(request, token).await?;
+    interceptors::observe(request, &response).await;
+    Ok(response)
+}
+
+/// Answers `request` from the HTTP cache, or sends it and caches the response
+#[cfg(feature = "http")]
+async fn fetch(
+    request: &HttpRequest,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<HttpResponse> {
+    let mut builder = build(request)?;
+    let cached = match http_cache::lookup(request) {
+        Some(CacheLookup::Fresh(response)) => return Ok(response),
+        Some(CacheLookup::Fetch(validators)) => {
+            builder = with_headers(builder, &validators)?;
+            true
+        }
+        None => false,
+    };
+    let url = request.url.clone();
+    let timeout_ms = request.timeout_ms;
+    let response = on_runtime(token.as_deref(), OPERATION, async move {
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| request_error(&url, timeout_ms, &e))?;
+        read(response, timeout_ms).await
+    })
+    .await?;
+    Ok(if cached {
+        http_cache::store(request, response)
+    } else {
+        response
+    })
+}
+
+/// Runs `request` on the internal runtime until it finishes or `token` is cancelled
+///
+/// reqwest needs a tokio reactor, which the Swift and Kotlin executors lack.
+#[cfg(feature = "http")]
+pub(crate) async fn on_runtime<T: Send + 'static>(
+    token: Option<&CancellationToken>,
+    operation: &str,
+    request: impl std::future::Future<Output = TemplateResult<T>> + Send + 'static,
+) -> TemplateResult<T> {
+    let mut task = runtime::handle().spawn(request);
+    let result = tokio::select! {
+        joined = &mut task => match joined {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        },
+        error = cancelled(token, operation) => Err(error),
+    };
+    task.abort();
+    result
+}
+
+/// Client shared by every request, so connections are reused; `None` until
+/// first used, and again after the network settings change
+#[cfg(feature = "http")]
+static CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+
+/// Client shared by every request, built with the current network settings
+#[cfg(feature = "http")]
+pub(crate) fn client() -> reqwest::Client {
+    if let Some(client) = CLIENT.read().unwrap().as_ref() {
+        return client.clone();
+    }
+    CLIENT
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| {
+            let mut builder = reqwest::Client::builder().user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ));
+            let pins = network::pins();
+            let identity = network::client_identity();
+            if !pins.is_empty() || identity.is_some() {
+                builder = builder.use_preconfigured_tls(pinning::tls_config(pins, identity));
+            }
+            if let Some(proxy) = network::proxy() {
+                builder = builder.proxy(proxy.reqwest_proxy());
+            }
+            builder.build().expect("failed to build HTTP client")
+        })
+        .clone()
+}
+
+/// Drops the shared client so the next request builds one with the new
+/// network settings; requests in flight keep the old one
+#[cfg(feature = "http")]
+pub(crate) fn reset_client() {
+    *CLIENT.write().unwrap() = None;
+}
+
+/// `url` parsed, if it is an absolute `http` or `https` URL
+#[cfg(feature = "http")]
+pub(crate) fn parse_url(url: &str) -> TemplateResult<reqwest::Url> {
+    reqwest::Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| {
+            TemplateError::invalid_input(format!("Not an absolute http(s) URL: '{}'", url), None)
+        })
+}
+
+/// Adds `headers` to `builder`, rejecting invalid names and values
+#[cfg(feature = "http")]
+pub(crate) fn with_headers(
+    mut builder: reqwest::RequestBuilder,
+    headers: &HashMap<String, String>,
+) -> TemplateResult<reqwest::RequestBuilder> {
+    for (name, value) in headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+            TemplateError::invalid_input(format!("Invalid header name: '{}'", name), None)
+        })?;
+        let value = reqwest::header::HeaderValue::from_str(value).map_err(|_| {
+            TemplateError::invalid_input(format!("Invalid value for header '{}'", name), None)
+        })?;
+        builder = builder.header(name, value);
+    }
+    Ok(builder)
+}
+
+/// A request builder for `request`, rejecting an invalid URL or header
+#[cfg(feature = "http")]
+pub(crate) fn build(request: &HttpRequest) -> TemplateResult<reqwest::RequestBuilder> {
+    let url = parse_url(&request.url)?;
+    let method = match request.method {
+        HttpMethod::Get => reqwest::Method::GET,
+        HttpMethod::Head => reqwest::Method::HEAD,
+        HttpMethod::Post => reqwest::Method::POST,
+        HttpMethod::Put => reqwest::Method::PUT,
+        HttpMethod::Patch => reqwest::Method::PATCH,
+        HttpMethod::Delete => reqwest::Method::DELETE,
+    };
+    let mut builder = with_headers(client().request(method, url), &request.headers)?;
+    if let Some(body) = &request.body {
+        builder = builder.body(body.clone());
+    }
+    if let Some(ms) = request.timeout_ms {
+        builder = builder.timeout(Duration::from_millis(ms));
+    }
+    Ok(builder)
+}
+
+/// Collects `response` into an `HttpResponse`, reading the whole body
+#[cfg(feature = "http")]
+pub(crate) async fn read(
+    response: reqwest::Response,
+    timeout_ms: Option<u64>,
+) -> TemplateResult<HttpResponse> {
+    let head = head(&response);
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| request_error(&head.url, timeout_ms, &e))?;
+    Ok(HttpResponse {
+        body: body.to_vec(),
+        ..head
+    })
+}
+
+/// `response` without its body, for telling interceptors about a response
+/// whose body is streamed
+#[cfg(feature = "http")]
+pub(crate) fn head(response: &reqwest::Response) -> HttpResponse {
+    HttpResponse {
+        status: response.status().as_u16(),
+        headers: joined_headers(response.headers()),
+        body: Vec::new(),
+        url: response.url().to_string(),
+    }
+}
+
+/// `headers` by name, with the values of a repeated header joined by `, `
+#[cfg(feature = "http")]
+pub(crate) fn joined_headers(headers: &reqwest::header::HeaderMap) -> HashMap<String, String> {
+    let mut joined: HashMap<String, String> = HashMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        joined
+            .entry(name.as_str().to_string())
+            .and_modify(|values| {
+                values.push_str(", ");
+                values.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    joined
+}
+
+#[cfg(feature = "http")]
+pub(crate) fn request_error(
+    url: &str,
+    timeout_ms: Option<u64>,
+    error: &reqwest::Error,
+) -> TemplateError {
+    if let Some(failure) = pinning::failure(error) {
+        return failure;
+    }
+    match timeout_ms {
+        Some(ms) if error.is_timeout() => TemplateError::timeout(OPERATION, ms),
+        // reqwest's own message is generic ("error sending request"), so
+        // keep the causes that say what went wrong
+        _ => {
+            TemplateError::network_failure(url, error.status().map(|status| status.as_u16()), error)
+        }
+    }
+}
+
+/// Resolves with the cancellation error once `token` is cancelled; never
+/// resolves without a token
+#[cfg(feature = "http")]
+pub(crate) async fn cancelled(token: Option<&CancellationToken>, operation: &str) -> TemplateError {
+    let Some(token) = token else {
+        return std::future::pending().await;
+    };
+    loop {
+        if let Err(error) =
+            runtime::sleep_cancellable(Duration::from_secs(3600), Some(token), operation).await
+        {
+            return error;
+        }
+    }
+}
@@ -0,0 +1,540 @@
+//! Opt-in on-disk cache of HTTP responses
+//!
+//! Once `enable_http_cache(directory, max_bytes)` is called, `GET`
+//! requests sent with `http_request` go through a private cache, as in a
+//! browser. A `200` response is stored when its `Cache-Control` allows it,
+//! and served without touching the network for `max-age` seconds. After
+//! that, or right away for `no-cache`, the stored `ETag` or `Last-Modified`
+//! is sent as `If-None-Match` or `If-Modified-Since`, and a `304 Not
+//! Modified` answer returns the stored body again. Responses with
+//! `no-store`, `Vary: *`, or neither `max-age` nor a validator are never
+//! stored, and no heuristic freshness is applied.
+//!
+//! Bodies are kept in one file each, next to an index file. The least
+//! recently used entries are evicted once the cache holds more than
+//! `max_bytes`. `get_http_cache_entries` lists what is stored, for a
+//! storage settings screen, and `clear_http_cache` deletes it all.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(feature = "http")]
+use crate::http::{HttpMethod, HttpRequest, HttpResponse};
+#[cfg(feature = "http")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the index file kept in the cache directory
+const INDEX_FILE: &str = "index.json";
+
+/// Extension of the files holding response bodies
+const BODY_EXTENSION: &str = "body";
+
+/// A response stored in the HTTP cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpCacheEntry {
+    /// URL that was requested
+    pub url: String,
+    pub size_bytes: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the response was stored or last revalidated, in milliseconds
+    /// since the Unix epoch
+    pub stored_at_ms: u64,
+    /// Until when the response is served without asking the server, in
+    /// milliseconds since the Unix epoch
+    pub fresh_until_ms: u64,
+    /// When the response was last served, in milliseconds since the Unix epoch
+    pub last_used_ms: u64,
+    /// How many requests the entry has answered
+    pub hit_count: u64,
+}
+
+/// An entry as stored in the index file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredEntry {
+    url: String,
+    /// URL of the response, after redirects
+    final_url: String,
+    status: u16,
+    headers: HashMap<String, String>,
+    /// Values of the request headers named by `Vary`, by lower-case name
+    #[serde(default)]
+    vary: BTreeMap<String, Option<String>>,
+    size_bytes: u64,
+    stored_at_ms: u64,
+    fresh_until_ms: u64,
+    last_used_ms: u64,
+    #[serde(default)]
+    hit_count: u64,
+}
+
+impl From<&StoredEntry> for HttpCacheEntry {
+    fn from(stored: &StoredEntry) -> Self {
+        Self {
+            url: stored.url.clone(),
+            size_bytes: stored.size_bytes,
+            etag: stored.headers.get("etag").cloned(),
+            last_modified: stored.headers.get("last-modified").cloned(),
+            stored_at_ms: stored.stored_at_ms,
+            fresh_until_ms: stored.fresh_until_ms,
+            last_used_ms: stored.last_used_ms,
+            hit_count: stored.hit_count,
+        }
+    }
+}
+
+struct HttpCache {
+    directory: PathBuf,
+    max_bytes: u64,
+    /// By key, the hex SHA-256 of the URL
+    entries: HashMap<String, StoredEntry>,
+}
+
+static CACHE: Mutex<Option<HttpCache>> = Mutex::new(None);
+
+/// What `http_request` should do with a request, from `lookup`
+#[cfg(feature = "http")]
+pub(crate) enum CacheLookup {
+    /// A fresh stored response answers the request
+    Fresh(HttpResponse),
+    /// Send the request with these extra headers, which validate a stale
+    /// entry if there is one, and pass the response to `store`
+    Fetch(HashMap<String, String>),
+}
+
+/// Starts caching HTTP responses in `directory`, up to `max_bytes`
+///
+/// A relative `directory` is resolved in the app's cache directory.
+/// Responses already stored there are kept. Calling this again switches to
+/// the new directory and limit.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `max_bytes` is 0
+/// * `Err(TemplateError::IoError)` - If the directory cannot be created
+pub fn enable_http_cache(directory: String, max_bytes: u64) -> TemplateResult<()> {
+    shield::guard("enable_http_cache", || {
+        if max_bytes == 0 {
+            return Err(TemplateError::invalid_input(
+                "max_bytes must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        let directory = directories::resolve(StorageCategory::Cache, &directory);
+        fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+        let mut cache = HttpCache {
+            entries: load_index(&directory),
+            directory,
+            max_bytes,
+        };
+        cache.remove_orphans();
+        cache.evict(0);
+        cache.save();
+        *CACHE.lock().unwrap() = Some(cache);
+        Ok(())
+    })
+}
+
+/// Stops caching; stored responses are left in place
+pub fn disable_http_cache() {
+    *CACHE.lock().unwrap() = None;
+}
+
+/// Deletes every stored response
+///
+/// # Returns
+///
+/// * `Err(TemplateError::IoError)` - If a stored file cannot be deleted
+pub fn clear_http_cache() -> TemplateResult<()> {
+    shield::guard("clear_http_cache", || {
+        let mut cache = CACHE.lock().unwrap();
+        let Some(cache) = cache.as_mut() else {
+            return Ok(());
+        };
+        let keys: Vec<String> = cache.entries.keys().cloned().collect();
+        let mut result = Ok(());
+        for key in keys {
+            if let Err(e) = cache.remove(&key) {
+                result = Err(e);
+            }
+        }
+        cache.save();
+        result
+    })
+}
+
+/// Deletes the stored response for `url`; returns `false` if there was none
+pub fn remove_http_cache_entry(url: String) -> bool {
+    let mut cache = CACHE.lock().unwrap();
+    let Some(cache) = cache.as_mut() else {
+        return false;
+    };
+    let key = key(&url);
+    if !cache.entries.contains_key(&key) {
+        return false;
+    }
+    if let Err(e) = cache.remove(&key) {
+        log::warn!("Could not delete cached response: {}", e);
+    }
+    cache.save();
+    true
+}
+
+/// Stored responses, most recently used first; empty while the cache is
+/// disabled
+pub fn get_http_cache_entries() -> Vec<HttpCacheEntry> {
+    let cache = CACHE.lock().unwrap();
+    let mut entries: Vec<HttpCacheEntry> = cache
+        .iter()
+        .flat_map(|cache| cache.entries.values())
+        .map(HttpCacheEntry::from)
+        .collect();
+    entries.sort_by(|a, b| {
+        b.last_used_ms
+            .cmp(&a.last_used_ms)
+            .then_with(|| a.url.cmp(&b.url))
+    });
+    entries
+}
+
+/// How the cache takes part in `request`; `None` if it does not
+#[cfg(feature = "http")]
+pub(crate) fn lookup(request: &HttpRequest) -> Option<CacheLookup> {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.as_mut()?;
+    if request.method != HttpMethod::Get {
+        // An unsafe request may change what a GET returns
+        if request.method != HttpMethod::Head {
+            let key = key(&request.url);
+            if cache.entries.contains_key(&key) {
+                if let Err(e) = cache.remove(&key) {
+                    log::warn!("Could not delete cached response: {}", e);
+                }
+                cache.save();
+            }
+        }
+        return None;
+    }
+    let directives = directives(header(&request.headers, "cache-control"));
+    // Requests that validate or ask for part of the resource themselves
+    // need the server's answer as is
+    if directives.contains_key("no-store")
+        || ["if-none-match", "if-modified-since", "range"]
+            .iter()
+            .any(|name| header(&request.headers, name).is_some())
+    {
+        return None;
+    }
+    let key = key(&request.url);
+    let Some(entry) = cache
+        .entries
+        .get(&key)
+        .filter(|entry| entry.url == request.url && entry.varies_with(request))
+    else {
+        return Some(CacheLookup::Fetch(HashMap::new()));
+    };
+    let now = now_ms();
+    if now < entry.fresh_until_ms && !directives.contains_key("no-cache") {
+        match cache.hit(&key, now) {
+            Some(response) => return Some(CacheLookup::Fresh(response)),
+            None => return Some(CacheLookup::Fetch(HashMap::new())),
+        }
+    }
+    let mut validators = HashMap::new();
+    if let Some(etag) = entry.headers.get("etag") {
+        validators.insert("If-None-Match".to_string(), etag.clone());
+    }
+    if let Some(last_modified) = entry.headers.get("last-modified") {
+        validators.insert("If-Modified-Since".to_string(), last_modified.clone());
+    }
+    Some(CacheLookup::Fetch(validators))
+}
+
+/// Stores `response` to `request` if it may be cached, or answers a `304`
+/// with the stored response; returns the response for the caller
+#[cfg(feature = "http")]
+pub(crate) fn store(request: &HttpRequest, response: HttpResponse) -> HttpResponse {
+    let mut cache = CACHE.lock().unwrap();
+    let Some(cache) = cache.as_mut() else {
+        return response;
+    };
+    let key = key(&request.url);
+    let now = now_ms();
+    if response.status == 304 {
+        let Some(entry) = cache.entries.get_mut(&key) else {
+            return response;
+        };
+        // The 304 carries the headers that changed, such as a new max-age
+        entry.headers.extend(response.headers.clone());
+        entry.stored_at_ms = now;
+        entry.fresh_until_ms = freshness_ms(&entry.headers).map_or(now, |ms| now + ms);
+        return cache.hit(&key, now).unwrap_or(response);
+    }
+    if response.status != 200 {
+        return response;
+    }
+    let Some(fresh_ms) = freshness_ms(&response.headers) else {
+        return response;
+    };
+    let vary: BTreeMap<String, Option<String>> = match response.headers.get("vary") {
+        Some(vary) if vary.split(',').any(|name| name.trim() == "*") => return response,
+        Some(vary) => vary
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let value = header(&request.headers, &name).map(str::to_string);
+                (name, value)
+            })
+            .collect(),
+        None => BTreeMap::new(),
+    };
+    let size_bytes = response.body.len() as u64;
+    if size_bytes > cache.max_bytes {
+        return response;
+    }
+    let _ = cache.remove(&key);
+    cache.evict(size_bytes);
+    let path = cache.body_path(&key);
+    if let Err(e) = write_atomic(&path, &response.body) {
+        log::warn!(
+            "Could not cache response: {}",
+            TemplateError::io_error(&path, &e)
+        );
+        cache.save();
+        return response;
+    }
+    cache.entries.insert(
+        key,
+        StoredEntry {
+            url: request.url.clone(),
+            final_url: response.url.clone(),
+            status: response.status,
+            headers: response.headers.clone(),
+            vary,
+            size_bytes,
+            stored_at_ms: now,
+            fresh_until_ms: now + fresh_ms,
+            last_used_ms: now,
+            hit_count: 0,
+        },
+    );
+    cache.save();
+    response
+}
+
+impl HttpCache {
+    fn body_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{}.{}", key, BODY_EXTENSION))
+    }
+
+    /// The stored response for `key`, counted as a hit; `None`, and the
+    /// entry dropped, if its body cannot be read
+    #[cfg(feature = "http")]
+    fn hit(&mut self, key: &str, now: u64) -> Option<HttpResponse> {
+        let path = self.body_path(key);
+        let body = match fs::read(&path) {
+            Ok(body) => body,
+            Err(e) => {
+                log::warn!(
+                    "Dropping cached response: {}",
+                    TemplateError::io_error(&path, &e)
+                );
+                let _ = self.remove(key);
+                self.save();
+                return None;
+            }
+        };
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used_ms = now;
+        entry.hit_count += 1;
+        let response = HttpResponse {
+            status: entry.status,
+            headers: entry.headers.clone(),
+            body,
+            url: entry.final_url.clone(),
+        };
+        self.save();
+        Some(response)
+    }
+
+    /// Forgets `key` and deletes its body
+    fn remove(&mut self, key: &str) -> TemplateResult<()> {
+        if self.entries.remove(key).is_none() {
+            return Ok(());
+        }
+        let path = self.body_path(key);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(TemplateError::io_error(&path, &e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evicts the least recently used entries until `incoming` more bytes fit
+    fn evict(&mut self, incoming: u64) {
+        let mut total: u64 = self.entries.values().map(|entry| entry.size_bytes).sum();
+        let mut by_use: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used_ms, key.clone()))
+            .collect();
+        by_use.sort();
+        for (_, key) in by_use {
+            if total + incoming <= self.max_bytes {
+                break;
+            }
+            total -= self.entries[&key].size_bytes;
+            if let Err(e) = self.remove(&key) {
+                log::warn!("Could not evict cached response: {}", e);
+            }
+        }
+    }
+
+    /// Deletes body files the index does not know, and forgets entries
+    /// whose body is gone, as left by a crash between the two writes
+    fn remove_orphans(&mut self) {
+        let Ok(files) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        let mut found = Vec::new();
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_some_and(|e| e == BODY_EXTENSION) {
+                let key = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if self.entries.contains_key(&key) {
+                    found.push(key);
+                } else {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        self.entries.retain(|key, _| found.contains(key));
+    }
+
+    /// Writes the index; the cache keeps working in memory if it cannot
+    fn save(&self) {
+        let path = self.directory.join(INDEX_FILE);
+        let result = serde_json::to_vec(&self.entries)
+            .map_err(|e| TemplateError::json_error(&e))
+            .and_then(|contents| {
+                write_atomic(&path, &contents).map_err(|e| TemplateError::io_error(&path, &e))
+            });
+        if let Err(e) = result {
+            log::warn!("Could not save the HTTP cache index: {}", e);
+        }
+    }
+}
+
+impl StoredEntry {
+    /// Whether `request` sends the same values for the headers named by
+    /// `Vary` as the request this response answered
+    #[cfg(feature = "http")]
+    fn varies_with(&self, request: &HttpRequest) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| header(&request.headers, name) == value.as_deref())
+    }
+}
+
+/// Reads the index; a missing or unreadable one means an empty cache, as
+/// everything in it can be fetched again
+fn load_index(directory: &Path) -> HashMap<String, StoredEntry> {
+    let path = directory.join(INDEX_FILE);
+    let contents = match fs::read(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!(
+                    "Starting with an empty HTTP cache: {}",
+                    TemplateError::io_error(&path, &e)
+                );
+            }
+            return HashMap::new();
+        }
+    };
+    serde_json::from_slice(&contents).unwrap_or_else(|e| {
+        log::warn!(
+            "Starting with an empty HTTP cache: {}",
+            TemplateError::json_error(&e)
+        );
+        HashMap::new()
+    })
+}
+
+/// Key of the entry for `url`, also the name of its body file
+fn key(url: &str) -> String {
+    hex::encode(Sha256::digest(url.as_bytes()))
+}
+
+/// For how long a response with `headers` is fresh, from `max-age` less
+/// its `Age`; `None` if it must not be stored
+#[cfg(feature = "http")]
+fn freshness_ms(headers: &HashMap<String, String>) -> Option<u64> {
+    let directives = directives(headers.get("cache-control").map(String::as_str));
+    if directives.contains_key("no-store") {
+        return None;
+    }
+    let validated = headers.contains_key("etag") || headers.contains_key("last-modified");
+    if directives.contains_key("no-cache") {
+        return validated.then_some(0);
+    }
+    match directives
+        .get("max-age")
+        .and_then(|value| value.as_deref()?.parse::<u64>().ok())
+    {
+        Some(max_age) => {
+            let age = headers
+                .get("age")
+                .and_then(|age| age.trim().parse::<u64>().ok())
+                .unwrap_or(0);
+            Some(max_age.saturating_sub(age).saturating_mul(1000))
+        }
+        None => validated.then_some(0),
+    }
+}
+
+/// `Cache-Control` directives by lower-case name, with their values
+#[cfg(feature = "http")]
+fn directives(cache_control: Option<&str>) -> HashMap<String, Option<String>> {
+    cache_control
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .filter_map(|directive| {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"').to_string())),
+                None => (directive, None),
+            };
+            let name = name.trim().to_ascii_lowercase();
+            (!name.is_empty()).then_some((name, value))
+        })
+        .collect()
+}
+
+/// Value of the header `name` (lower case) among `headers`, whatever their case
+#[cfg(feature = "http")]
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(feature = "http")]
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
@@ -0,0 +1,604 @@
+//! Hugging Face Hub API client for in-app model browsers (`http` feature)
+//!
+//! `HubClient` searches the Hub's models (`search_models`), lists the files
+//! of a repository with their sizes and LFS hashes (`list_files`), and
+//! fetches its model card (`get_model_card`), so a model browser is written
+//! once in the shared core instead of in Swift and Kotlin. Requests go
+//! through `http_request`, so the proxy, certificate pins, and the HTTP
+//! cache from `enable_http_cache` apply to them.
+//!
+//! Gated and private repositories need an access token from the user's
+//! Hugging Face account; it is sent as `Authorization: Bearer <token>`.
+//! `file_url` and `download_headers` give a `Downloader` what it needs to
+//! fetch a file with the same token. Without the `http` feature the types
+//! exist so the bindings stay the same, but creating a client fails.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+use crate::http::{self, HttpMethod, HttpRequest, HttpResponse};
+#[cfg(feature = "http")]
+use serde::Deserialize;
+
+/// The public Hugging Face Hub
+pub const HUB_ENDPOINT: &str = "https://huggingface.co";
+
+/// Revision used when none is given
+const DEFAULT_REVISION: &str = "main";
+
+/// Longest repository id the Hub accepts
+const MAX_REPO_ID_LENGTH: usize = 96;
+
+/// Most models one search returns
+const MAX_SEARCH_LIMIT: u32 = 1000;
+
+/// Model fields requested from a search
+#[cfg(feature = "http")]
+const SEARCH_FIELDS: [&str; 8] = [
+    "downloads",
+    "likes",
+    "pipeline_tag",
+    "library_name",
+    "tags",
+    "private",
+    "gated",
+    "lastModified",
+];
+
+/// Hub server and credentials for a `HubClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubConfig {
+    /// Root of the Hub, `HUB_ENDPOINT` unless a mirror is used
+    pub endpoint: String,
+    /// User access token, needed for gated and private repositories
+    pub access_token: Option<String>,
+    /// Limit for each request
+    pub timeout_ms: Option<u64>,
+}
+
+impl Default for HubConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: HUB_ENDPOINT.to_string(),
+            access_token: None,
+            timeout_ms: None,
+        }
+    }
+}
+
+/// Order of search results, most first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModelSort {
+    #[default]
+    Downloads,
+    Likes,
+    Trending,
+    LastModified,
+}
+
+/// Narrows a model search; unset filters match every model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSearchFilters {
+    /// User or organization owning the models, e.g. `meta-llama`
+    pub author: Option<String>,
+    /// Tags every model must have, e.g. `gguf` or `license:mit`
+    pub tags: Vec<String>,
+    /// Task, e.g. `text-generation`
+    pub pipeline_tag: Option<String>,
+    /// Library, e.g. `transformers` or `mlx`
+    pub library: Option<String>,
+    pub sort: ModelSort,
+    /// Most models to return, up to 1000
+    pub limit: u32,
+}
+
+impl Default for ModelSearchFilters {
+    fn default() -> Self {
+        Self {
+            author: None,
+            tags: Vec::new(),
+            pipeline_tag: None,
+            library: None,
+            sort: ModelSort::default(),
+            limit: 20,
+        }
+    }
+}
+
+/// A model repository found by `HubClient::search_models`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubModel {
+    /// Repository id, e.g. `Qwen/Qwen2.5-0.5B-Instruct-GGUF`
+    pub id: String,
+    /// Owner part of the id, if it has one
+    pub author: Option<String>,
+    /// Downloads over the last 30 days
+    pub downloads: u64,
+    pub likes: u64,
+    pub pipeline_tag: Option<String>,
+    pub library_name: Option<String>,
+    pub tags: Vec<String>,
+    pub private: bool,
+    /// Whether users must accept conditions before downloading
+    pub gated: bool,
+    /// ISO 8601 time of the last commit
+    pub last_modified: Option<String>,
+}
+
+/// A file in a model repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubFile {
+    /// Path within the repository, e.g. `onnx/model.onnx`
+    pub path: String,
+    pub size_bytes: u64,
+    /// SHA-256 of the content, for files stored with Git LFS
+    pub sha256: Option<String>,
+}
+
+/// A repository's README, split into its YAML metadata and Markdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelCard {
+    pub repo_id: String,
+    /// YAML front matter (license, tags, datasets...), without the `---` lines
+    pub metadata: Option<String>,
+    /// Markdown after the front matter
+    pub content: String,
+}
+
+/// Client for the Hugging Face Hub API
+pub struct HubClient {
+    config: HubConfig,
+}
+
+/// Model as returned by `/api/models`
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiModel {
+    id: String,
+    #[serde(default)]
+    downloads: u64,
+    #[serde(default)]
+    likes: u64,
+    #[serde(rename = "pipeline_tag")]
+    pipeline_tag: Option<String>,
+    #[serde(rename = "library_name")]
+    library_name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    private: bool,
+    /// `false`, or how access is granted (`"auto"` or `"manual"`)
+    #[serde(default)]
+    gated: serde_json::Value,
+    last_modified: Option<String>,
+}
+
+/// Entry of a repository tree
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct TreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+    lfs: Option<LfsPointer>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct LfsPointer {
+    oid: String,
+    size: u64,
+}
+
+/// Error body returned by the Hub
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl HubClient {
+    /// Create a client; nothing is sent until a method is called
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `endpoint` is not an
+    ///   absolute `http(s)` URL, or the library was built without the `http`
+    ///   feature
+    pub fn new(config: HubConfig) -> TemplateResult<Self> {
+        shield::guard("HubClient::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "HubClient requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            #[cfg(feature = "http")]
+            http::parse_url(&config.endpoint)?;
+            Ok(Self { config })
+        })
+    }
+
+    /// The client's configuration
+    pub fn config(&self) -> HubConfig {
+        self.config.clone()
+    }
+
+    /// Finds models whose id contains `query` and that match `filters`
+    /// (async)
+    ///
+    /// An empty query lists every model matching the filters.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `limit` is 0 or above 1000
+    /// * `Err(TemplateError::NetworkError)` - If the Hub could not be reached
+    ///   or responded with an error status
+    /// * `Err(TemplateError::ParseError)` - If the response is not a model list
+    /// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn search_models(
+        &self,
+        query: String,
+        filters: ModelSearchFilters,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<Vec<HubModel>> {
+        shield::guard_async("HubClient::search_models", async move {
+            if !(1..=MAX_SEARCH_LIMIT).contains(&filters.limit) {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "limit must be between 1 and {}, got {}",
+                        MAX_SEARCH_LIMIT, filters.limit
+                    ),
+                    None,
+                ));
+            }
+            #[cfg(feature = "http")]
+            {
+                let mut url = self.url(&["api", "models"])?;
+                {
+                    let mut pairs = url.query_pairs_mut();
+                    if !query.trim().is_empty() {
+                        pairs.append_pair("search", query.trim());
+                    }
+                    if let Some(author) = &filters.author {
+                        pairs.append_pair("author", author);
+                    }
+                    for tag in &filters.tags {
+                        pairs.append_pair("filter", tag);
+                    }
+                    if let Some(pipeline_tag) = &filters.pipeline_tag {
+                        pairs.append_pair("pipeline_tag", pipeline_tag);
+                    }
+                    if let Some(library) = &filters.library {
+                        pairs.append_pair("library", library);
+                    }
+                    let sort = match filters.sort {
+                        ModelSort::Downloads => "downloads",
+                        ModelSort::Likes => "likes",
+                        ModelSort::Trending => "trendingScore",
+                        ModelSort::LastModified => "lastModified",
+                    };
+                    pairs
+                        .append_pair("sort", sort)
+                        .append_pair("direction", "-1")
+                        .append_pair("limit", &filters.limit.to_string());
+                    for field in SEARCH_FIELDS {
+                        pairs.append_pair("expand[]", field);
+                    }
+                }
+                let response = self.get(url.to_string(), token).await?;
+                let models: Vec<ApiModel> = serde_json::from_slice(&response.body)
+                    .map_err(|e| TemplateError::json_error(&e))?;
+                Ok(models
+                    .into_iter()
+                    .map(|model| HubModel {
+                        author: model
+                            .id
+                            .split_once('/')
+                            .map(|(author, _)| author.to_string()),
+                        id: model.id,
+                        downloads: model.downloads,
+                        likes: model.likes,
+                        pipeline_tag: model.pipeline_tag,
+                        library_name: model.library_name,
+                        tags: model.tags,
+                        private: model.private,
+                        gated: !matches!(
+                            model.gated,
+                            serde_json::Value::Null | serde_json::Value::Bool(false)
+                        ),
+                        last_modified: model.last_modified,
+                    })
+                    .collect())
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (query, token);
+                unreachable!("HubClient::new fails without the `http` feature")
+            }
+        })
+        .await
+    }
+
+    /// Every file of a model repository at `revision` (the `main` branch by
+    /// default), in the Hub's order (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `repo_id` or `revision` is invalid
+    /// * `Err(TemplateError::NetworkError)` - If the Hub could not be reached
+    ///   or responded with an error status: 401 for a gated or private
+    ///   repository without a token that grants access, 404 for an unknown one
+    /// * `Err(TemplateError::ParseError)` - If the response is not a file list
+    /// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn list_files(
+        &self,
+        repo_id: String,
+        revision: Option<String>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<Vec<HubFile>> {
+        shield::guard_async("HubClient::list_files", async move {
+            validate_repo_id(&repo_id)?;
+            let revision = revision_or_default(revision)?;
+            #[cfg(feature = "http")]
+            {
+                let mut segments = vec!["api", "models"];
+                segments.extend(repo_id.split('/'));
+                segments.extend(["tree", revision.as_str()]);
+                let mut url = self.url(&segments)?;
+                url.query_pairs_mut().append_pair("recursive", "true");
+
+                // Large repositories come in pages linked with `Link: rel="next"`
+                let mut files = Vec::new();
+                let mut next = Some(url.to_string());
+                while let Some(url) = next {
+                    let response = self.get(url, token.clone()).await?;
+                    let entries: Vec<TreeEntry> = serde_json::from_slice(&response.body)
+                        .map_err(|e| TemplateError::json_error(&e))?;
+                    files.extend(
+                        entries
+                            .into_iter()
+                            .filter(|entry| entry.kind == "file")
+                            .map(|entry| HubFile {
+                                path: entry.path,
+                                size_bytes: entry.lfs.as_ref().map_or(entry.size, |lfs| lfs.size),
+                                sha256: entry.lfs.map(|lfs| lfs.oid),
+                            }),
+                    );
+                    next = next_page(&response);
+                }
+                Ok(files)
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (revision, token);
+                unreachable!("HubClient::new fails without the `http` feature")
+            }
+        })
+        .await
+    }
+
+    /// The model card (README.md) of a repository at `revision` (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `repo_id` or `revision` is invalid
+    /// * `Err(TemplateError::NetworkError)` - If the Hub could not be reached
+    ///   or responded with an error status, e.g. 404 when the repository has
+    ///   no README
+    /// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn get_model_card(
+        &self,
+        repo_id: String,
+        revision: Option<String>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<ModelCard> {
+        shield::guard_async("HubClient::get_model_card", async move {
+            let url = self.file_url(repo_id.clone(), "README.md".to_string(), revision)?;
+            #[cfg(feature = "http")]
+            {
+                let response = self.get(url, token).await?;
+                let text = String::from_utf8_lossy(&response.body);
+                let (metadata, content) = split_front_matter(&text);
+                Ok(ModelCard {
+                    repo_id,
+                    metadata,
+                    content,
+                })
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (url, token);
+                unreachable!("HubClient::new fails without the `http` feature")
+            }
+        })
+        .await
+    }
+
+    /// URL that downloads `path` from a repository at `revision`, for a
+    /// `Downloader` along with `download_headers`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `repo_id`, `path`, or
+    ///   `revision` is invalid
+    pub fn file_url(
+        &self,
+        repo_id: String,
+        path: String,
+        revision: Option<String>,
+    ) -> TemplateResult<String> {
+        shield::guard("HubClient::file_url", || {
+            validate_repo_id(&repo_id)?;
+            let revision = revision_or_default(revision)?;
+            if path.is_empty()
+                || path
+                    .split('/')
+                    .any(|part| part.is_empty() || part == "." || part == "..")
+            {
+                return Err(TemplateError::invalid_input(
+                    format!("Invalid path in repository: '{}'", path),
+                    None,
+                ));
+            }
+            #[cfg(feature = "http")]
+            {
+                let mut segments: Vec<&str> = repo_id.split('/').collect();
+                segments.extend(["resolve", revision.as_str()]);
+                segments.extend(path.split('/'));
+                Ok(self.url(&segments)?.to_string())
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = revision;
+                unreachable!("HubClient::new fails without the `http` feature")
+            }
+        })
+    }
+
+    /// Headers that authorize requests with the access token, if there is one
+    pub fn download_headers(&self) -> HashMap<String, String> {
+        self.config
+            .access_token
+            .iter()
+            .map(|token| ("Authorization".to_string(), format!("Bearer {}", token)))
+            .collect()
+    }
+
+    /// `endpoint` with `segments` appended to its path, each percent-encoded
+    #[cfg(feature = "http")]
+    fn url(&self, segments: &[&str]) -> TemplateResult<reqwest::Url> {
+        let mut url = http::parse_url(&self.config.endpoint)?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                TemplateError::invalid_input(
+                    format!("Invalid Hub endpoint: '{}'", self.config.endpoint),
+                    None,
+                )
+            })?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Sends an authorized `GET`; error statuses are errors
+    #[cfg(feature = "http")]
+    async fn get(
+        &self,
+        url: String,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<HttpResponse> {
+        let request = HttpRequest {
+            url,
+            method: HttpMethod::Get,
+            headers: self.download_headers(),
+            body: None,
+            timeout_ms: self.config.timeout_ms,
+        };
+        let response = http::http_request(request, token).await?;
+        if !response.is_success() {
+            return Err(status_error(&response));
+        }
+        Ok(response)
+    }
+}
+
+/// Checks an id of the form `name` or `owner/name`
+fn validate_repo_id(repo_id: &str) -> TemplateResult<()> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with(['.', '-'])
+            && !part.contains("..")
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    let parts: Vec<&str> = repo_id.split('/').collect();
+    if repo_id.len() > MAX_REPO_ID_LENGTH || parts.len() > 2 || !parts.into_iter().all(valid_part) {
+        return Err(TemplateError::invalid_input(
+            format!("Invalid repository id: '{}'", repo_id),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+fn revision_or_default(revision: Option<String>) -> TemplateResult<String> {
+    match revision {
+        None => Ok(DEFAULT_REVISION.to_string()),
+        Some(revision) if revision.trim().is_empty() => Err(TemplateError::invalid_input(
+            "revision must not be empty".to_string(),
+            None,
+        )),
+        Some(revision) => Ok(revision),
+    }
+}
+
+/// URL of the next page from a `Link` header, if there is one
+#[cfg(feature = "http")]
+fn next_page(response: &HttpResponse) -> Option<String> {
+    response.headers.get("link")?.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+            .map(str::to_string)
+    })
+}
+
+/// `NetworkError` for an error status, with the Hub's message if it sent one
+#[cfg(feature = "http")]
+fn status_error(response: &HttpResponse) -> TemplateError {
+    let message = response
+        .headers
+        .get("x-error-message")
+        .cloned()
+        .or_else(|| {
+            serde_json::from_slice::<ErrorResponse>(&response.body)
+                .ok()
+                .map(|error| error.error)
+        })
+        .unwrap_or_else(|| {
+            String::from_utf8_lossy(&response.body)
+                .chars()
+                .take(200)
+                .collect()
+        });
+    TemplateError::network_error(
+        &response.url,
+        Some(response.status),
+        format!("Server responded with {}: {}", response.status, message),
+    )
+}
+
+/// YAML front matter between `---` lines at the top, and the rest
+#[cfg(feature = "http")]
+fn split_front_matter(text: &str) -> (Option<String>, String) {
+    let text = text.trim_start_matches('\u{feff}');
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text.to_string());
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let content = rest[offset + line.len()..].trim_start_matches(['\r', '\n']);
+            return (Some(rest[..offset].to_string()), content.to_string());
+        }
+        offset += line.len();
+    }
+    (None, text.to_string())
+}
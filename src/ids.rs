@@ -0,0 +1,85 @@
+//! UUID generation and parsing shared across platforms
+
+use crate::cancellation::{check_cancelled, CancellationToken};
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A parsed and validated UUID
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedUuid {
+    /// Canonical lowercase hyphenated form
+    pub canonical: String,
+    /// UUID version number (e.g. 4 or 7), 0 if not an RFC 9562 version
+    pub version: u8,
+    /// Embedded Unix timestamp in milliseconds, for time-based versions
+    pub timestamp_ms: Option<u64>,
+}
+
+/// Generates a random (version 4) UUID in canonical form (async)
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::generate_uuid_v4;
+///
+/// # tokio_test::block_on(async {
+/// let id = generate_uuid_v4().await;
+/// assert_eq!(id.len(), 36);
+/// # })
+/// ```
+pub async fn generate_uuid_v4() -> String {
+    tokio::task::yield_now().await;
+    Uuid::new_v4().hyphenated().to_string()
+}
+
+/// Generates a time-ordered (version 7) UUID in canonical form (async)
+///
+/// Version 7 UUIDs embed a millisecond Unix timestamp in their most
+/// significant bits, so ids generated later sort after ids generated earlier.
+pub async fn generate_uuid_v7() -> String {
+    tokio::task::yield_now().await;
+    Uuid::now_v7().hyphenated().to_string()
+}
+
+/// Parses a UUID from any common textual form (async)
+///
+/// Accepts hyphenated, simple (no hyphens), braced, and URN forms in any
+/// letter case.
+///
+/// # Returns
+///
+/// * `Ok(ParsedUuid)` - The canonical form and version information
+/// * `Err(TemplateError::InvalidInput)` - If `input` is not a valid UUID
+pub async fn parse_uuid(
+    input: String,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<ParsedUuid> {
+    shield::guard_async("parse_uuid", async move {
+        check_cancelled(token.as_deref(), "parse_uuid")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "parse_uuid")?;
+        let uuid = Uuid::try_parse(&input).map_err(|e| {
+            TemplateError::invalid_input(format!("Invalid UUID: {}", e), Some(&input))
+        })?;
+
+        let timestamp_ms = uuid.get_timestamp().map(|ts| {
+            let (secs, nanos) = ts.to_unix();
+            secs * 1000 + u64::from(nanos) / 1_000_000
+        });
+
+        Ok(ParsedUuid {
+            canonical: uuid.hyphenated().to_string(),
+            version: uuid.get_version_num() as u8,
+            timestamp_ms,
+        })
+    })
+    .await
+}
+
+/// Checks whether `input` is a valid UUID in any common textual form (async)
+pub async fn is_valid_uuid(input: String) -> bool {
+    tokio::task::yield_now().await;
+    Uuid::try_parse(&input).is_ok()
+}
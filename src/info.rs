@@ -0,0 +1,107 @@
+//! Version, build, and capability information for support and feature gating
+
+use crate::config;
+use crate::hashing::HashAlgorithm;
+use crate::models::ModelFormat;
+
+/// Cargo features this library can be built with, and whether each is enabled
+const FEATURES: [(&str, bool); 6] = [
+    ("cbor", cfg!(feature = "cbor")),
+    ("debug-errors", cfg!(feature = "debug-errors")),
+    ("grpc", cfg!(feature = "grpc")),
+    ("http", cfg!(feature = "http")),
+    ("otel", cfg!(feature = "otel")),
+    ("sqlite", cfg!(feature = "sqlite")),
+];
+
+/// Identifies exactly which build of the library is running
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryInfo {
+    /// Crate name
+    pub name: String,
+    /// Crate version, e.g. `0.1.0`
+    pub version: String,
+    /// Abbreviated git commit the library was built from, if known
+    pub git_commit: Option<String>,
+    /// Unix timestamp in seconds of when the build script last ran
+    pub build_timestamp: u64,
+    /// Rust target triple, e.g. `aarch64-apple-ios`
+    pub target: String,
+    /// Cargo profile, `debug` or `release`
+    pub profile: String,
+    /// Enabled cargo features, in alphabetical order
+    pub features: Vec<String>,
+    /// Version of the UniFFI contract the scaffolding was generated for
+    pub uniffi_contract_version: u32,
+}
+
+/// Returns version and build information for this library
+///
+/// Support can compare `git_commit`, `target`, and `features` with a
+/// customer's report to know which binary they are running. The bindings
+/// check `uniffi_contract_version` themselves when loading the library.
+pub fn get_library_info() -> LibraryInfo {
+    LibraryInfo {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: Some(env!("TEMPLATE_GIT_COMMIT"))
+            .filter(|commit| !commit.is_empty())
+            .map(str::to_string),
+        build_timestamp: env!("TEMPLATE_BUILD_TIMESTAMP").parse().unwrap_or(0),
+        target: env!("TEMPLATE_BUILD_TARGET").to_string(),
+        profile: env!("TEMPLATE_BUILD_PROFILE").to_string(),
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        uniffi_contract_version: crate::ffi_rust_multiplatform_template_lib_uniffi_contract_version(
+        ),
+    }
+}
+
+/// What this build of the library can do
+///
+/// Optional functionality depends on cargo features chosen per build flavor;
+/// bindings can check these flags to hide UI for features compiled out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Whether errors carry breadcrumbs and backtraces (`debug-errors` feature)
+    pub has_debug_errors: bool,
+    /// Whether `enable_otel_export` can succeed (`otel` feature)
+    pub has_otel_export: bool,
+    /// Whether `Database::open` can succeed (`sqlite` feature)
+    pub has_database: bool,
+    /// Whether `http_request` can succeed (`http` feature)
+    pub has_http: bool,
+    /// Whether `register_grpc_service` can succeed (`grpc` feature)
+    pub has_grpc: bool,
+    /// Whether `encode_cbor` and `decode_cbor` can succeed (`cbor` feature)
+    pub has_cbor: bool,
+    /// Hash algorithms accepted by `TemplateConfig`
+    pub hash_algorithms: Vec<HashAlgorithm>,
+    /// Model file formats `load_model_metadata` can read
+    pub model_formats: Vec<ModelFormat>,
+    /// Largest input accepted by `echo`, from `LibraryConfig::max_input_size`
+    pub max_input_size: u64,
+}
+
+/// Returns what this build of the library supports
+pub fn get_capabilities() -> Capabilities {
+    Capabilities {
+        has_debug_errors: cfg!(feature = "debug-errors"),
+        has_otel_export: cfg!(feature = "otel"),
+        has_database: cfg!(feature = "sqlite"),
+        has_http: cfg!(feature = "http"),
+        has_grpc: cfg!(feature = "grpc"),
+        has_cbor: cfg!(feature = "cbor"),
+        hash_algorithms: vec![
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Xxhash,
+            HashAlgorithm::None,
+        ],
+        model_formats: vec![ModelFormat::Gguf, ModelFormat::Safetensors],
+        max_input_size: config::current().max_input_size,
+    }
+}
@@ -0,0 +1,135 @@
+//! Host hooks on outgoing HTTP requests and their responses
+//!
+//! An `HttpInterceptor` registered with `add_http_interceptor` sees each
+//! request just before it is sent and may return a changed one, e.g. with a
+//! signature of the payload or an app-specific auth header, so such schemes
+//! do not force networking back into platform code. It is then told about
+//! the response. Interceptors run in the order they were added, each seeing
+//! the request as the one before it left it, after the `AuthManager` token
+//! is attached; a request sent again after a 401 goes through them again.
+//!
+//! Every request the library sends goes through interceptors: `http_request`
+//! (and so the Hugging Face Hub client), `Outbox` deliveries, `Downloader`
+//! transfers, `upload_file`, `RemoteLlmClient` calls, `sse_request` and the
+//! `WebSocketClient` opening handshake. Responses from the HTTP cache are
+//! reported like any other. Streamed responses (downloads, server-sent
+//! events, `chat_stream`) are reported as soon as their headers arrive, with
+//! an empty body, and an upload's file is streamed separately, so the
+//! request interceptors see has no body and any body they set is ignored.
+//! Interceptors can be added without the `http` feature, but nothing is sent
+//! then.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::http::{HttpRequest, HttpResponse};
+use crate::shield;
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "http")]
+use crate::runtime;
+
+/// Inspects and modifies outgoing requests, implemented by the host
+pub trait HttpInterceptor: Send + Sync {
+    /// The request to send in place of `request`; return it unchanged to
+    /// only look at it
+    ///
+    /// Called on a background thread, so it may block, e.g. on a signing key.
+    fn intercept_request(&self, request: HttpRequest) -> HttpRequest;
+    /// Called with every response to a request sent after `intercept_request`,
+    /// whatever its status; `request` is the one actually sent
+    fn on_response(&self, request: HttpRequest, response: HttpResponse);
+}
+
+/// Interceptors by name, in the order they run
+static INTERCEPTORS: RwLock<Vec<(String, Arc<dyn HttpInterceptor>)>> = RwLock::new(Vec::new());
+
+/// Adds `interceptor` after the others, or replaces the one called `name`
+/// where it is
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `name` is empty
+pub fn add_http_interceptor(
+    name: String,
+    interceptor: Box<dyn HttpInterceptor>,
+) -> TemplateResult<()> {
+    shield::guard("add_http_interceptor", || {
+        if name.trim().is_empty() {
+            return Err(TemplateError::invalid_input(
+                "Interceptor name must not be empty".to_string(),
+                None,
+            ));
+        }
+        let interceptor: Arc<dyn HttpInterceptor> = Arc::from(interceptor);
+        let mut interceptors = INTERCEPTORS.write().unwrap();
+        match interceptors.iter_mut().find(|(added, _)| *added == name) {
+            Some((_, replaced)) => *replaced = interceptor,
+            None => {
+                log::info!("HTTP interceptor {} added", name);
+                interceptors.push((name, interceptor));
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Removes the interceptor called `name`, returning whether there was one
+pub fn remove_http_interceptor(name: String) -> bool {
+    let mut interceptors = INTERCEPTORS.write().unwrap();
+    let before = interceptors.len();
+    interceptors.retain(|(added, _)| *added != name);
+    interceptors.len() != before
+}
+
+/// Names of the interceptors, in the order they run
+pub fn get_http_interceptors() -> Vec<String> {
+    INTERCEPTORS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+#[cfg(feature = "http")]
+fn interceptors() -> Vec<Arc<dyn HttpInterceptor>> {
+    INTERCEPTORS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, interceptor)| interceptor.clone())
+        .collect()
+}
+
+/// `request` as the interceptors changed it, or `None` if there are none
+#[cfg(feature = "http")]
+pub(crate) async fn intercept(request: &HttpRequest) -> Option<HttpRequest> {
+    let interceptors = interceptors();
+    if interceptors.is_empty() {
+        return None;
+    }
+    let request = request.clone();
+    Some(
+        runtime::spawn_blocking(move || {
+            interceptors.iter().fold(request, |request, interceptor| {
+                interceptor.intercept_request(request)
+            })
+        })
+        .await,
+    )
+}
+
+/// Tells the interceptors about `response` to `request`
+#[cfg(feature = "http")]
+pub(crate) async fn observe(request: &HttpRequest, response: &HttpResponse) {
+    let interceptors = interceptors();
+    if interceptors.is_empty() {
+        return;
+    }
+    let (request, response) = (request.clone(), response.clone());
+    runtime::spawn_blocking(move || {
+        for interceptor in interceptors {
+            interceptor.on_response(request.clone(), response.clone());
+        }
+    })
+    .await;
+}
@@ -0,0 +1,427 @@
+//! Background job queue with priorities, concurrency limits, and persistence
+
+use crate::cancellation::{self, CancellationToken};
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::files;
+use crate::hashing::{HashAlgorithm, StreamingHasher};
+use crate::models;
+use crate::runtime;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Size of the buffer used when hashing files
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Work a job performs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    /// Hash a file's contents; the output is the lowercase hex digest
+    HashFile {
+        /// File to hash
+        path: String,
+        /// Algorithm to hash with
+        algorithm: HashAlgorithm,
+    },
+    /// Scan a directory for models; the output is the number found
+    DiscoverModels {
+        /// Directory to scan
+        directory: String,
+    },
+}
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    /// Waiting for a free slot
+    Queued,
+    /// Currently running
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Cancelled before it finished
+    Cancelled,
+}
+
+/// Snapshot of a job, passed to listeners and returned by `JobQueue::jobs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobInfo {
+    /// Id assigned by the queue
+    pub id: u64,
+    /// Name given when the job was enqueued
+    pub name: String,
+    /// The work this job performs
+    pub kind: JobKind,
+    /// Higher priorities run first; equal priorities run in enqueue order
+    pub priority: i32,
+    /// Current state
+    pub state: JobState,
+    /// Result of a completed job (see `JobKind`)
+    pub output: Option<String>,
+    /// Why the job failed, if it did
+    pub error_message: Option<String>,
+}
+
+/// Callback notified whenever a job changes state, implemented by the host
+pub trait JobListener: Send + Sync {
+    /// Called with the job's new state; may be called from any thread
+    fn on_job_state_changed(&self, job: JobInfo);
+}
+
+/// Unfinished job as written to the persistence file
+#[derive(Serialize, Deserialize)]
+struct PersistedJob {
+    id: u64,
+    name: String,
+    kind: JobKind,
+    priority: i32,
+}
+
+/// Runs named jobs in the background, highest priority first
+///
+/// At most `max_concurrent` jobs run at once. With a persistence path,
+/// unfinished jobs are saved after every change and re-queued when a queue
+/// is created with the same path, so work resumes after an app restart.
+pub struct JobQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    max_concurrent: u32,
+    persist_path: Option<PathBuf>,
+    state: Mutex<QueueState>,
+    listener: Mutex<Option<Arc<dyn JobListener>>>,
+    /// Number of queued plus running jobs
+    active: watch::Sender<usize>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    jobs: BTreeMap<u64, JobInfo>,
+    tokens: BTreeMap<u64, Arc<CancellationToken>>,
+    running: u32,
+}
+
+impl JobQueue {
+    /// Create a queue, resuming unfinished jobs from `persist_path` if given
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `max_concurrent` is 0
+    /// * `Err(TemplateError::IoError)` - If the persistence file cannot be read
+    /// * `Err(TemplateError::ParseError)` - If the persistence file is corrupt
+    pub fn new(max_concurrent: u32, persist_path: Option<String>) -> TemplateResult<Self> {
+        shield::guard("JobQueue::new", || {
+            if max_concurrent == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_concurrent must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+
+            let persist_path =
+                persist_path.map(|path| directories::resolve(StorageCategory::Data, &path));
+            let resumed = match &persist_path {
+                Some(path) => load_jobs(path)?,
+                None => Vec::new(),
+            };
+
+            let mut state = QueueState {
+                next_id: 1,
+                ..QueueState::default()
+            };
+            for job in resumed {
+                state.next_id = state.next_id.max(job.id + 1);
+                state.jobs.insert(
+                    job.id,
+                    JobInfo {
+                        id: job.id,
+                        name: job.name,
+                        kind: job.kind,
+                        priority: job.priority,
+                        state: JobState::Queued,
+                        output: None,
+                        error_message: None,
+                    },
+                );
+            }
+            let active = state.jobs.len();
+
+            let queue = Self {
+                inner: Arc::new(Inner {
+                    max_concurrent,
+                    persist_path,
+                    state: Mutex::new(state),
+                    listener: Mutex::new(None),
+                    active: watch::Sender::new(active),
+                }),
+            };
+            Inner::pump(&queue.inner);
+            Ok(queue)
+        })
+    }
+
+    /// Registers the listener notified of every state change, replacing any previous one
+    pub fn set_listener(&self, listener: Box<dyn JobListener>) {
+        *self.inner.listener.lock().unwrap() = Some(Arc::from(listener));
+    }
+
+    /// Adds a job to the queue and returns its id
+    pub fn enqueue(&self, name: String, kind: JobKind, priority: i32) -> u64 {
+        let job = {
+            let mut state = self.inner.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            let job = JobInfo {
+                id,
+                name,
+                kind,
+                priority,
+                state: JobState::Queued,
+                output: None,
+                error_message: None,
+            };
+            state.jobs.insert(id, job.clone());
+            self.inner.persist(&state);
+            self.inner.publish_active(&state);
+            job
+        };
+        self.inner.notify(job.clone());
+        Inner::pump(&self.inner);
+        job.id
+    }
+
+    /// Cancels a queued or running job
+    ///
+    /// Returns `false` if the job does not exist or already finished.
+    pub fn cancel(&self, id: u64) -> bool {
+        let cancelled = {
+            let mut state = self.inner.state.lock().unwrap();
+            match state.jobs.get(&id).map(|job| job.state) {
+                Some(JobState::Queued) => {
+                    let job = state.jobs.get_mut(&id).unwrap();
+                    job.state = JobState::Cancelled;
+                    let job = job.clone();
+                    self.inner.persist(&state);
+                    self.inner.publish_active(&state);
+                    Some(job)
+                }
+                Some(JobState::Running) => {
+                    // The job reports Cancelled itself once it stops
+                    if let Some(token) = state.tokens.get(&id) {
+                        token.cancel();
+                    }
+                    return true;
+                }
+                _ => None,
+            }
+        };
+        match cancelled {
+            Some(job) => {
+                self.inner.notify(job);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Snapshot of every job this queue knows about, ordered by id
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .jobs
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Snapshot of a single job
+    pub fn job(&self, id: u64) -> Option<JobInfo> {
+        self.inner.state.lock().unwrap().jobs.get(&id).cloned()
+    }
+
+    /// Waits until no jobs are queued or running (async)
+    pub async fn wait_idle(&self) {
+        let mut receiver = self.inner.active.subscribe();
+        let _ = receiver.wait_for(|active| *active == 0).await;
+    }
+}
+
+impl Inner {
+    /// Starts queued jobs while there are free slots
+    fn pump(inner: &Arc<Self>) {
+        loop {
+            let (job, token) = {
+                let mut state = inner.state.lock().unwrap();
+                if state.running >= inner.max_concurrent {
+                    return;
+                }
+                let next = state
+                    .jobs
+                    .values()
+                    .filter(|job| job.state == JobState::Queued)
+                    .max_by(|a, b| a.priority.cmp(&b.priority).then(b.id.cmp(&a.id)))
+                    .map(|job| job.id);
+                let Some(id) = next else {
+                    return;
+                };
+
+                let job = state.jobs.get_mut(&id).unwrap();
+                job.state = JobState::Running;
+                let job = job.clone();
+                let token = Arc::new(CancellationToken::new());
+                state.tokens.insert(id, token.clone());
+                state.running += 1;
+                (job, token)
+            };
+            inner.notify(job.clone());
+
+            let queue = inner.clone();
+            runtime::handle().spawn(async move {
+                let kind = job.kind.clone();
+                let work_token = token.clone();
+                let outcome = runtime::spawn_blocking(move || run_job(&kind, &work_token)).await;
+                queue.finish(job.id, outcome, &token);
+                Inner::pump(&queue);
+            });
+        }
+    }
+
+    /// Records a finished job's outcome
+    fn finish(&self, id: u64, outcome: TemplateResult<String>, token: &CancellationToken) {
+        if let Err(e) = &outcome {
+            if !token.is_cancelled() {
+                log::warn!("Job {} failed: {}", id, e);
+                events::publish(LibraryEvent::BackgroundError {
+                    operation: "job_queue".to_string(),
+                    kind: e.kind(),
+                    error_message: e.to_string(),
+                });
+            }
+        }
+        let job = {
+            let mut state = self.state.lock().unwrap();
+            state.running -= 1;
+            state.tokens.remove(&id);
+            let Some(job) = state.jobs.get_mut(&id) else {
+                return;
+            };
+            match outcome {
+                Ok(output) => {
+                    job.state = JobState::Completed;
+                    job.output = Some(output);
+                }
+                Err(_) if token.is_cancelled() => job.state = JobState::Cancelled,
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error_message = Some(e.to_string());
+                }
+            }
+            let job = job.clone();
+            self.persist(&state);
+            self.publish_active(&state);
+            job
+        };
+        self.notify(job);
+    }
+
+    fn notify(&self, job: JobInfo) {
+        events::publish(LibraryEvent::JobStateChanged { job: job.clone() });
+
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener.on_job_state_changed(job);
+        }
+    }
+
+    fn publish_active(&self, state: &QueueState) {
+        let active = state
+            .jobs
+            .values()
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running))
+            .count();
+        self.active.send_replace(active);
+    }
+
+    /// Saves unfinished jobs; failures are ignored so the queue keeps working
+    fn persist(&self, state: &QueueState) {
+        let Some(path) = &self.persist_path else {
+            return;
+        };
+        let unfinished: Vec<PersistedJob> = state
+            .jobs
+            .values()
+            .filter(|job| matches!(job.state, JobState::Queued | JobState::Running))
+            .map(|job| PersistedJob {
+                id: job.id,
+                name: job.name.clone(),
+                kind: job.kind.clone(),
+                priority: job.priority,
+            })
+            .collect();
+        let Ok(json) = serde_json::to_vec_pretty(&unfinished) else {
+            return;
+        };
+        // Replace atomically so a crash never leaves half a file
+        let _ = files::write_atomic(path, &json);
+    }
+}
+
+/// Reads unfinished jobs saved by a previous queue; a missing file means none
+fn load_jobs(path: &Path) -> TemplateResult<Vec<PersistedJob>> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))
+}
+
+/// Performs a job's work on the current (blocking) thread
+fn run_job(kind: &JobKind, token: &CancellationToken) -> TemplateResult<String> {
+    match kind {
+        JobKind::HashFile { path, algorithm } => hash_file(Path::new(path), *algorithm, token),
+        JobKind::DiscoverModels { directory } => {
+            let models = runtime::block_on(models::discover_models(
+                directory.clone(),
+                Some(Arc::new(token.clone())),
+            ))?;
+            Ok(models.len().to_string())
+        }
+    }
+}
+
+/// Hashes a file in chunks, checking for cancellation between chunks
+fn hash_file(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    token: &CancellationToken,
+) -> TemplateResult<String> {
+    let mut file = File::open(path).map_err(|e| TemplateError::io_error(path, &e))?;
+    let mut hasher = StreamingHasher::new(algorithm);
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE];
+    loop {
+        cancellation::check_cancelled(Some(token), "hash_file")?;
+        let read = file
+            .read(&mut buf)
+            .map_err(|e| TemplateError::io_error(path, &e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher.finalize().unwrap_or_default())
+}
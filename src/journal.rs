@@ -0,0 +1,134 @@
+//! Write-ahead journal for multi-step storage updates
+//!
+//! A store records what it is about to change in a journal file next to its
+//! data file, syncs it, applies the change, and then deletes the journal.
+//! If the process dies in between, the journal is still there on the next
+//! open and the change is applied again, so updates that touch several keys
+//! are all-or-nothing across crashes. Changes must therefore be idempotent.
+//!
+//! A journal is the SHA-256 of its payload in hex, a newline, then the
+//! payload. One whose checksum does not match was cut short while being
+//! written; its change never started and is discarded.
+//!
+//! `verify_integrity` looks for pending and torn journals and for temporary
+//! files left by interrupted atomic writes, and can clean them up.
+
+use crate::files::sync_directory;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// What a journal file holds
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum JournalState {
+    /// No journal: the last change completed
+    Empty,
+    /// A change that was recorded but may not have been applied
+    Pending(Vec<u8>),
+    /// A journal cut short while being written
+    Torn,
+}
+
+/// Path of the journal kept for the data file at `path`
+pub(crate) fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".journal");
+    path.with_file_name(name)
+}
+
+/// Durably records `payload` as the pending change for `path`
+pub(crate) fn begin(path: &Path, payload: &[u8]) -> io::Result<()> {
+    let journal = journal_path(path);
+    let mut file = File::create(&journal)?;
+    file.write_all(hex::encode(Sha256::digest(payload)).as_bytes())?;
+    file.write_all(b"\n")?;
+    file.write_all(payload)?;
+    file.sync_all()?;
+    sync_directory(journal.parent().unwrap_or(Path::new(".")))
+}
+
+/// Removes the journal for `path` once its change is applied
+pub(crate) fn commit(path: &Path) -> io::Result<()> {
+    match fs::remove_file(journal_path(path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the journal for `path`
+pub(crate) fn read(path: &Path) -> io::Result<JournalState> {
+    let contents = match fs::read(journal_path(path)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(JournalState::Empty),
+        Err(e) => return Err(e),
+    };
+    let Some(newline) = contents.iter().position(|&b| b == b'\n') else {
+        return Ok(JournalState::Torn);
+    };
+    let (checksum, payload) = (&contents[..newline], &contents[newline + 1..]);
+    if checksum != hex::encode(Sha256::digest(payload)).as_bytes() {
+        return Ok(JournalState::Torn);
+    }
+    Ok(JournalState::Pending(payload.to_vec()))
+}
+
+/// A problem found by `verify_integrity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityIssueKind {
+    /// A recorded multi-key update that was not finished
+    PendingJournal,
+    /// A journal cut short by a crash; its update never started
+    TornJournal,
+    /// A temporary file left by an interrupted atomic write
+    LeftoverTempFile,
+    /// The data file cannot be parsed
+    UnreadableFile,
+}
+
+/// One problem found by `verify_integrity`, and the file it concerns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    pub path: String,
+}
+
+/// Result of `verify_integrity`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntegrityReport {
+    /// Everything found, empty if the store is healthy
+    pub issues: Vec<IntegrityIssue>,
+    /// Whether the issues were repaired
+    pub repaired: bool,
+}
+
+impl IntegrityIssue {
+    pub(crate) fn new(kind: IntegrityIssueKind, path: &Path) -> Self {
+        Self {
+            kind,
+            path: path.display().to_string(),
+        }
+    }
+}
+
+/// Temporary files left by `write_atomic` for the file at `path`
+pub(crate) fn leftover_temp_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        ".{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let mut found = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&prefix) && name.ends_with(".tmp") {
+            found.push(entry.path());
+        }
+    }
+    Ok(found)
+}
@@ -0,0 +1,111 @@
+//! JSON round-tripping of the library's records
+//!
+//! Hosts persist and transmit results with these helpers instead of writing
+//! the same mapper in Swift, Kotlin, and Java. The JSON is identical on
+//! every platform: fields keep their Rust names, enums are `snake_case`
+//! strings (`"sha256"`, `"input_sanitized"`), and unset optional fields
+//! are `null`. Parsing accepts JSON written by any platform, or by a backend
+//! following the same shape. `TemplateConfig` has `to_json` and `from_json`
+//! of its own, and errors are serialized with `error_to_json`.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::models::{DiscoveredModel, ModelMetadata};
+use crate::remote_llm::{ChatMessage, GenerationParams, GenerationResult};
+use crate::shield;
+use crate::template::EchoResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Serializes an `EchoResult` as JSON
+pub fn echo_result_to_json(result: EchoResult) -> String {
+    to_json(&result)
+}
+
+/// Parses an `EchoResult` serialized with `echo_result_to_json`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not an `EchoResult`
+pub fn echo_result_from_json(json: String) -> TemplateResult<EchoResult> {
+    from_json("echo_result_from_json", &json)
+}
+
+/// Serializes `ModelMetadata` as JSON
+pub fn model_metadata_to_json(metadata: ModelMetadata) -> String {
+    to_json(&metadata)
+}
+
+/// Parses `ModelMetadata` serialized with `model_metadata_to_json`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not model metadata
+pub fn model_metadata_from_json(json: String) -> TemplateResult<ModelMetadata> {
+    from_json("model_metadata_from_json", &json)
+}
+
+/// Serializes a `DiscoveredModel` as JSON, with its metadata nested
+pub fn discovered_model_to_json(model: DiscoveredModel) -> String {
+    to_json(&model)
+}
+
+/// Parses a `DiscoveredModel` serialized with `discovered_model_to_json`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not a discovered model
+pub fn discovered_model_from_json(json: String) -> TemplateResult<DiscoveredModel> {
+    from_json("discovered_model_from_json", &json)
+}
+
+/// Serializes `GenerationParams` as JSON, leaving out unset values
+pub fn generation_params_to_json(params: GenerationParams) -> String {
+    to_json(&params)
+}
+
+/// Parses `GenerationParams`; missing values are left unset
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not generation parameters
+pub fn generation_params_from_json(json: String) -> TemplateResult<GenerationParams> {
+    from_json("generation_params_from_json", &json)
+}
+
+/// Serializes a `GenerationResult` as JSON
+pub fn generation_result_to_json(result: GenerationResult) -> String {
+    to_json(&result)
+}
+
+/// Parses a `GenerationResult` serialized with `generation_result_to_json`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not a generation result
+pub fn generation_result_from_json(json: String) -> TemplateResult<GenerationResult> {
+    from_json("generation_result_from_json", &json)
+}
+
+/// Serializes a conversation as a JSON array of `{"role", "content"}` objects
+pub fn chat_messages_to_json(messages: Vec<ChatMessage>) -> String {
+    to_json(&messages)
+}
+
+/// Parses a conversation serialized with `chat_messages_to_json`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `json` is not an array of messages
+pub fn chat_messages_from_json(json: String) -> TemplateResult<Vec<ChatMessage>> {
+    from_json("chat_messages_from_json", &json)
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("records always serialize")
+}
+
+fn from_json<T: DeserializeOwned>(operation: &'static str, json: &str) -> TemplateResult<T> {
+    shield::guard(operation, || {
+        serde_json::from_str(json).map_err(|e| TemplateError::json_error(&e))
+    })
+}
@@ -0,0 +1,578 @@
+//! Persistent key-value store shared by both platforms
+//!
+//! `KvStore` replaces the usual UserDefaults / SharedPreferences pair with one
+//! implementation. The whole store is kept in memory and written to a single
+//! JSON file after every change. Writes go to a sibling file that is synced
+//! and renamed over the old one, so a crash leaves either the old or the new
+//! contents, never a mix.
+//!
+//! The file records a schema version. `KvStore::open_with_migrations` renames,
+//! removes, or sets keys for each migration newer than that version, so an
+//! app can reshape its stored settings between releases.
+//!
+//! Single changes are atomic through the rename. `KvStore::write_batch`
+//! changes several keys at once through a write-ahead journal (see
+//! `journal`), so after a crash either every change in the batch is present
+//! or none is; an unfinished batch is completed when the store next opens.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use crate::journal::{self, IntegrityIssue, IntegrityIssueKind, IntegrityReport, JournalState};
+use crate::migrations::{self, MigrationReport};
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A value held by a `KvStore`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KvValue {
+    String { value: String },
+    Int { value: i64 },
+    Bool { value: bool },
+    Bytes { value: Vec<u8> },
+}
+
+impl KvValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::String { .. } => "string",
+            Self::Int { .. } => "int",
+            Self::Bool { .. } => "bool",
+            Self::Bytes { .. } => "bytes",
+        }
+    }
+}
+
+/// One change made by a `KvMigration`
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvMigrationStep {
+    /// Moves the value of `from` to `to`, replacing any value there;
+    /// nothing happens if `from` is missing
+    Rename { from: String, to: String },
+    /// Removes `key` if present
+    Remove { key: String },
+    /// Stores `value` under `key`, replacing any previous value
+    Set { key: String, value: KvValue },
+}
+
+/// One change in a `KvStore::write_batch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum KvChange {
+    /// Stores `value` under `key`, replacing any previous value
+    Set { key: String, value: KvValue },
+    /// Removes `key` if present
+    Delete { key: String },
+}
+
+/// Steps that upgrade a `KvStore` to schema `version`
+#[derive(Debug, Clone, PartialEq)]
+pub struct KvMigration {
+    pub version: u32,
+    pub steps: Vec<KvMigrationStep>,
+}
+
+/// The file contents: a schema version and the entries
+#[derive(Serialize)]
+struct Contents<'a> {
+    schema_version: u32,
+    entries: &'a BTreeMap<String, KvValue>,
+}
+
+#[derive(Deserialize)]
+struct StoredEntries {
+    schema_version: u32,
+    entries: BTreeMap<String, KvValue>,
+}
+
+/// Files written before the schema version was recorded are a bare map
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredFile {
+    Versioned(StoredEntries),
+    Unversioned(BTreeMap<String, KvValue>),
+}
+
+/// Key-value store persisted to one file
+///
+/// Open each file with a single store: two stores on the same path do not
+/// see each other's changes and the last write wins.
+pub struct KvStore {
+    path: PathBuf,
+    report: MigrationReport,
+    entries: Mutex<BTreeMap<String, KvValue>>,
+}
+
+impl KvStore {
+    /// Opens the store at `path`, creating it on the first write if missing
+    ///
+    /// Missing parent directories are created.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file or its directory cannot be read or created
+    /// * `Err(TemplateError::ParseError)` - If the file is not a store written by this library
+    pub fn open(path: String) -> TemplateResult<Self> {
+        shield::guard("KvStore::open", || Self::open_migrated(path, &[]))
+    }
+
+    /// Opens the store at `path` and applies the migrations it has not seen yet
+    ///
+    /// `migrations` must be in ascending version order. Pending migrations
+    /// run in order and are saved together with the new schema version in
+    /// one atomic write, so a failure leaves the file as it was.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the versions are not ascending
+    ///   and greater than 0, or the file has a newer schema version than the
+    ///   last migration
+    /// * `Err(TemplateError::IoError)` - If the file or its directory cannot be read or written
+    /// * `Err(TemplateError::ParseError)` - If the file is not a store written by this library
+    pub fn open_with_migrations(
+        path: String,
+        migrations: Vec<KvMigration>,
+    ) -> TemplateResult<Self> {
+        shield::guard("KvStore::open_with_migrations", || {
+            Self::open_migrated(path, &migrations)
+        })
+    }
+
+    /// The schema version found on open and the migrations applied since
+    pub fn migration_report(&self) -> MigrationReport {
+        self.report.clone()
+    }
+
+    /// The value stored under `key`, if any
+    pub fn get(&self, key: String) -> Option<KvValue> {
+        self.entries.lock().unwrap().get(&key).cloned()
+    }
+
+    /// Stores `value` under `key`, replacing any previous value
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written; the
+    ///   store keeps its previous contents
+    pub fn set(&self, key: String, value: KvValue) -> TemplateResult<()> {
+        shield::guard("KvStore::set", || {
+            self.update(|entries| {
+                entries.insert(key, value);
+            })
+        })
+    }
+
+    /// Removes `key`, returning whether it was present
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written; the
+    ///   store keeps its previous contents
+    pub fn delete(&self, key: String) -> TemplateResult<bool> {
+        shield::guard("KvStore::delete", || {
+            if !self.entries.lock().unwrap().contains_key(&key) {
+                return Ok(false);
+            }
+            self.update(|entries| entries.remove(&key).is_some())
+        })
+    }
+
+    /// Applies every change in `changes`, in order, as one atomic update
+    ///
+    /// The batch is recorded in a journal before the file is replaced, so a
+    /// crash partway through is completed on the next open.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the journal or the file cannot be
+    ///   written; none of the changes are applied
+    pub fn write_batch(&self, changes: Vec<KvChange>) -> TemplateResult<()> {
+        shield::guard("KvStore::write_batch", || {
+            if changes.is_empty() {
+                return Ok(());
+            }
+            let payload =
+                serde_json::to_vec(&changes).map_err(|e| TemplateError::json_error(&e))?;
+            let mut entries = self.entries.lock().unwrap();
+            let mut updated = entries.clone();
+            apply_changes(&mut updated, &changes);
+            journal::begin(&self.path, &payload)
+                .map_err(|e| TemplateError::io_error(&self.path, &e))?;
+            if let Err(e) = save_entries(&self.path, self.report.to_version, &updated) {
+                let _ = journal::commit(&self.path);
+                return Err(e);
+            }
+            *entries = updated;
+            if let Err(e) = journal::commit(&self.path) {
+                // The batch is saved; replaying it on the next open is harmless
+                log::warn!(
+                    "Could not remove journal for {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Checks the store's files for damage left by crashes, repairing it if `repair` is set
+    ///
+    /// Finds unfinished batches (completed when repairing), journals cut
+    /// short while being written and temporary files from interrupted writes
+    /// (both deleted when repairing), and a data file that cannot be parsed
+    /// (rewritten from memory when repairing). Repair only while no other
+    /// process is writing to the store.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the files cannot be inspected or repaired
+    pub fn verify_integrity(&self, repair: bool) -> TemplateResult<IntegrityReport> {
+        shield::guard("KvStore::verify_integrity", || {
+            let mut entries = self.entries.lock().unwrap();
+            let io_error = |e: std::io::Error| TemplateError::io_error(&self.path, &e);
+            let journal_path = journal::journal_path(&self.path);
+            let mut report = IntegrityReport::default();
+            let mut updated = None;
+
+            match journal::read(&self.path).map_err(io_error)? {
+                JournalState::Empty => {}
+                JournalState::Pending(payload) => {
+                    let kind = match serde_json::from_slice::<Vec<KvChange>>(&payload) {
+                        Ok(changes) => {
+                            let mut replayed = entries.clone();
+                            apply_changes(&mut replayed, &changes);
+                            updated = Some(replayed);
+                            IntegrityIssueKind::PendingJournal
+                        }
+                        Err(_) => IntegrityIssueKind::TornJournal,
+                    };
+                    report.issues.push(IntegrityIssue::new(kind, &journal_path));
+                }
+                JournalState::Torn => report.issues.push(IntegrityIssue::new(
+                    IntegrityIssueKind::TornJournal,
+                    &journal_path,
+                )),
+            }
+            let temp_files = journal::leftover_temp_files(&self.path).map_err(io_error)?;
+            for temp in &temp_files {
+                report.issues.push(IntegrityIssue::new(
+                    IntegrityIssueKind::LeftoverTempFile,
+                    temp,
+                ));
+            }
+            if load_entries(&self.path).is_err() {
+                report.issues.push(IntegrityIssue::new(
+                    IntegrityIssueKind::UnreadableFile,
+                    &self.path,
+                ));
+                updated.get_or_insert_with(|| entries.clone());
+            }
+
+            if repair && !report.issues.is_empty() {
+                if let Some(updated) = updated {
+                    save_entries(&self.path, self.report.to_version, &updated)?;
+                    *entries = updated;
+                }
+                journal::commit(&self.path).map_err(io_error)?;
+                for temp in &temp_files {
+                    fs::remove_file(temp).map_err(|e| TemplateError::io_error(temp, &e))?;
+                }
+                report.repaired = true;
+                log::info!(
+                    "Repaired {} integrity issues in {}",
+                    report.issues.len(),
+                    self.path.display()
+                );
+            }
+            Ok(report)
+        })
+    }
+
+    /// All keys, in sorted order
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// The string stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a string
+    pub fn get_string(&self, key: String) -> TemplateResult<Option<String>> {
+        shield::guard("KvStore::get_string", || {
+            self.get_typed(key, "string", |value| match value {
+                KvValue::String { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a string under `key`
+    pub fn set_string(&self, key: String, value: String) -> TemplateResult<()> {
+        self.set(key, KvValue::String { value })
+    }
+
+    /// The integer stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not an integer
+    pub fn get_int(&self, key: String) -> TemplateResult<Option<i64>> {
+        shield::guard("KvStore::get_int", || {
+            self.get_typed(key, "int", |value| match value {
+                KvValue::Int { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores an integer under `key`
+    pub fn set_int(&self, key: String, value: i64) -> TemplateResult<()> {
+        self.set(key, KvValue::Int { value })
+    }
+
+    /// The boolean stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a boolean
+    pub fn get_bool(&self, key: String) -> TemplateResult<Option<bool>> {
+        shield::guard("KvStore::get_bool", || {
+            self.get_typed(key, "bool", |value| match value {
+                KvValue::Bool { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a boolean under `key`
+    pub fn set_bool(&self, key: String, value: bool) -> TemplateResult<()> {
+        self.set(key, KvValue::Bool { value })
+    }
+
+    /// The bytes stored under `key`, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not bytes
+    pub fn get_bytes(&self, key: String) -> TemplateResult<Option<Vec<u8>>> {
+        shield::guard("KvStore::get_bytes", || {
+            self.get_typed(key, "bytes", |value| match value {
+                KvValue::Bytes { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores bytes under `key`
+    pub fn set_bytes(&self, key: String, value: Vec<u8>) -> TemplateResult<()> {
+        self.set(key, KvValue::Bytes { value })
+    }
+
+    fn open_migrated(path: String, migrations: &[KvMigration]) -> TemplateResult<Self> {
+        let path = directories::resolve(StorageCategory::Data, &path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| TemplateError::io_error(parent, &e))?;
+        }
+        let (version, mut entries) = load_entries(&path)?;
+        recover(&path, version, &mut entries)?;
+        let mut report = MigrationReport::unchanged(version);
+        if !migrations.is_empty() {
+            let versions: Vec<u32> = migrations.iter().map(|m| m.version).collect();
+            for index in migrations::pending(version, &versions)? {
+                let migration = &migrations[index];
+                for step in &migration.steps {
+                    apply_step(&mut entries, step);
+                }
+                report.applied.push(migration.version);
+                report.to_version = migration.version;
+            }
+            if !report.applied.is_empty() {
+                save_entries(&path, report.to_version, &entries)?;
+                log::info!(
+                    "Migrated {} from schema {} to {}",
+                    path.display(),
+                    report.from_version,
+                    report.to_version
+                );
+            }
+        }
+        Ok(Self {
+            path,
+            report,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// A copy of every entry, for `export_data`
+    pub(crate) fn entries(&self) -> BTreeMap<String, KvValue> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Replaces every entry at once, for `import_data`
+    pub(crate) fn replace_entries(&self, entries: BTreeMap<String, KvValue>) -> TemplateResult<()> {
+        self.update(|current| *current = entries)
+    }
+
+    /// Stores `value` under `key`, or removes it if `None`, returning the previous value
+    pub(crate) fn swap(
+        &self,
+        key: String,
+        value: Option<KvValue>,
+    ) -> TemplateResult<Option<KvValue>> {
+        self.update(|entries| match value {
+            Some(value) => entries.insert(key, value),
+            None => entries.remove(&key),
+        })
+    }
+
+    /// Rereads the file, picking up writes from other processes, and returns
+    /// the keys whose values changed
+    pub(crate) fn reload(&self) -> TemplateResult<Vec<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        let (_, loaded) = load_entries(&self.path)?;
+        let changed = entries
+            .keys()
+            .chain(loaded.keys())
+            .filter(|key| entries.get(*key) != loaded.get(*key))
+            .cloned()
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        *entries = loaded;
+        Ok(changed)
+    }
+
+    /// Path of the backing file
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Looks up `key` and unwraps it with `extract`, failing on another type
+    fn get_typed<T>(
+        &self,
+        key: String,
+        expected: &str,
+        extract: impl FnOnce(KvValue) -> Option<T>,
+    ) -> TemplateResult<Option<T>> {
+        let value = self.get(key.clone());
+        typed(&key, value, expected, extract)
+    }
+
+    /// Applies `change` and saves the result, or leaves the store untouched
+    fn update<R>(
+        &self,
+        change: impl FnOnce(&mut BTreeMap<String, KvValue>) -> R,
+    ) -> TemplateResult<R> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut updated = entries.clone();
+        let result = change(&mut updated);
+        save_entries(&self.path, self.report.to_version, &updated)?;
+        *entries = updated;
+        Ok(result)
+    }
+}
+
+/// Unwraps `value` with `extract`, failing if it has another type than `expected`
+pub(crate) fn typed<T>(
+    key: &str,
+    value: Option<KvValue>,
+    expected: &str,
+    extract: impl FnOnce(KvValue) -> Option<T>,
+) -> TemplateResult<Option<T>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let actual = value.type_name();
+    extract(value).map(Some).ok_or_else(|| {
+        TemplateError::invalid_input(
+            format!("Value for '{}' is {}, not {}", key, actual, expected),
+            None,
+        )
+    })
+}
+
+fn apply_changes(entries: &mut BTreeMap<String, KvValue>, changes: &[KvChange]) {
+    for change in changes {
+        match change {
+            KvChange::Set { key, value } => {
+                entries.insert(key.clone(), value.clone());
+            }
+            KvChange::Delete { key } => {
+                entries.remove(key);
+            }
+        }
+    }
+}
+
+/// Completes a batch interrupted by a crash, or discards one that never started
+fn recover(
+    path: &Path,
+    schema_version: u32,
+    entries: &mut BTreeMap<String, KvValue>,
+) -> TemplateResult<()> {
+    let io_error = |e: std::io::Error| TemplateError::io_error(path, &e);
+    match journal::read(path).map_err(io_error)? {
+        JournalState::Empty => return Ok(()),
+        JournalState::Pending(payload) => match serde_json::from_slice::<Vec<KvChange>>(&payload) {
+            Ok(changes) => {
+                apply_changes(entries, &changes);
+                save_entries(path, schema_version, entries)?;
+                log::info!("Completed an interrupted batch in {}", path.display());
+            }
+            Err(e) => log::warn!("Discarded unreadable journal for {}: {}", path.display(), e),
+        },
+        JournalState::Torn => {
+            log::warn!("Discarded incomplete journal for {}", path.display());
+        }
+    }
+    journal::commit(path).map_err(io_error)
+}
+
+fn apply_step(entries: &mut BTreeMap<String, KvValue>, step: &KvMigrationStep) {
+    match step {
+        KvMigrationStep::Rename { from, to } => {
+            if let Some(value) = entries.remove(from) {
+                entries.insert(to.clone(), value);
+            }
+        }
+        KvMigrationStep::Remove { key } => {
+            entries.remove(key);
+        }
+        KvMigrationStep::Set { key, value } => {
+            entries.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Reads a saved store and its schema version; a missing file means an empty one
+fn load_entries(path: &Path) -> TemplateResult<(u32, BTreeMap<String, KvValue>)> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, BTreeMap::new())),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    match serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))? {
+        StoredFile::Versioned(stored) => Ok((stored.schema_version, stored.entries)),
+        StoredFile::Unversioned(entries) => Ok((0, entries)),
+    }
+}
+
+/// Writes the store atomically
+fn save_entries(
+    path: &Path,
+    schema_version: u32,
+    entries: &BTreeMap<String, KvValue>,
+) -> TemplateResult<()> {
+    let json = serde_json::to_vec(&Contents {
+        schema_version,
+        entries,
+    })
+    .map_err(|e| TemplateError::json_error(&e))?;
+    write_atomic(path, &json).map_err(|e| TemplateError::io_error(path, &e))
+}
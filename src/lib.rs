@@ -8,26 +8,693 @@
 //!
 //! ## Functions (All Async)
 //!
-//! - `echo(input, token)`: Returns the input string with metadata, or None if empty (async with cancellation)
+//! - `initialize(config)` / `update_config(config)` / `get_config()`: Library-wide settings (sync)
+//! - `save_config(path)` / `load_config(path)`: Persist library-wide settings across restarts (sync)
+//! - `set_app_directories(cache_dir, data_dir, temp_dir)` / `storage_directory(category)`: Where the library stores files (sync)
+//! - `get_storage_breakdown()`: Bytes used per storage category (async)
+//! - `get_free_space(path)` / `check_disk_space(path, required_bytes)`: Room left on the volume before a large write (sync)
+//! - `enable_history(path, max_entries)` / `query_history(filter, page)` / `clear_history()`: Persistent record of echo results
+//! - `export_data(path, passphrase, store, token)` / `import_data(path, passphrase, store, token)`: Encrypted backup and restore (async)
+//! - `http_request(request, token)`: Sends an HTTP request with the `http` feature (async with cancellation)
+//! - `enable_http_cache(directory, max_bytes)` / `get_http_cache_entries()` / `clear_http_cache()`: On-disk cache of `GET` responses honoring `ETag` and `Cache-Control` (sync)
+//! - `upload_file(url, path, fields, headers, listener, token)`: Streams a file as a multipart upload with the `http` feature (async with cancellation)
+//! - `sse_request(request, listener, token)`: Delivers a Server-Sent Events stream to a listener with the `http` feature (async with cancellation)
+//! - `set_network_config(config)` / `get_network_config()`: Settings applied to every connection, such as pinned keys and a proxy (sync)
+//! - `set_auth_manager(manager)`: Attaches an `AuthManager`'s tokens to outgoing requests per host (sync)
+//! - `add_http_interceptor(name, interceptor)` / `remove_http_interceptor(name)` / `get_http_interceptors()`: Host hooks that change outgoing requests and observe responses (sync)
+//! - `set_network_status(status)` / `get_network_status()`: Connectivity reported by the host, which pauses and resumes network work (sync)
+//! - `register_grpc_service(name, config)` / `unregister_grpc_service(name)` / `get_grpc_services()`: Endpoints of proto-defined backend services with the `grpc` feature (sync)
+//! - `grpc_channel(name)` / `grpc_call(name, token, call)`: Channel for a generated tonic client, and calls on it with cancellation (Rust only)
+//! - `certificate_pin(certificate_der)`: The `sha256/<base64>` pin of a certificate's public key (sync)
+//! - `run_with_timeout(operation, timeout_ms, future)`: Races any future against a timer (Rust only)
+//! - `retrying(policy, operation, token, f)`: Retries an idempotent operation with backoff (Rust only)
+//! - `retry_delay_ms(policy, attempt)` / `should_retry(policy, kind, attempts)`: Backoff for host-driven retries
+//! - `init_runtime(options)` / `shutdown_runtime(grace_ms)`: Explicit control of the internal runtime (sync)
+//! - `echo(input, token, timeout_ms)`: Returns the input string with metadata, or None if empty (async with cancellation and timeout)
+//! - `spawn_echo(input, token, timeout_ms)` / `get_task(id)`: Runs `echo` in the background and tracks it by id
+//! - `echo_stream(token)`: Starts a chunked echo for inputs larger than 1MB
 //! - `random()`: Returns a random double between 0.0 and 1.0 (async)
+//! - `random_int(min, max)`: Returns a random integer in [min, max] (async)
+//! - `random_bytes(len)`: Returns `len` random bytes (async)
+//! - `random_choice(items)`: Returns a random element of `items` (async)
+//! - `random_normal(mean, std_dev)`: Samples a normal distribution (async)
+//! - `random_exponential(lambda)`: Samples an exponential distribution (async)
+//! - `random_uniform(low, high)`: Samples a uniform distribution over [low, high) (async)
+//! - `random_seeded(seed)`: Returns a deterministic random double for a seed (async)
+//! - `secure_random_bytes(len)`: Returns bytes from the OS CSPRNG, for nonces and tokens (async)
+//! - `secure_random_double()`: Returns a CSPRNG-backed double in [0.0, 1.0) (async)
+//! - `generate_uuid_v4()` / `generate_uuid_v7()`: Returns a canonical UUID string (async)
+//! - `parse_uuid(input)` / `is_valid_uuid(input)`: Parses and validates UUIDs (async)
+//! - `discover_models(directory, token)`: Scans a folder for GGUF/safetensors models (async)
+//! - `load_model_metadata(path, token)`: Reads the header of a single model file (async)
+//! - `echo_result_to_json(result)` / `echo_result_from_json(json)`: JSON round-tripping of records, with the same pair for `ModelMetadata`, `DiscoveredModel`, `GenerationParams`, `GenerationResult`, and `chat_messages` (sync)
+//! - `encode_msgpack(record)` / `decode_msgpack(data)`: Compact MessagePack encoding of any `Record` (sync)
+//! - `encode_cbor(record)` / `decode_cbor(data)`: CBOR encoding of any `Record` with the `cbor` feature (sync)
+//! - `encode_protobuf(record)` / `decode_protobuf(data)` / `error_to_protobuf(error)`: Protocol Buffers encoding following `proto/records.proto` (sync)
+//!
+//! ## Blocking Variants
+//!
+//! For call sites that cannot await, `echo`, the `random_*` and
+//! `secure_random_*` functions, the UUID functions, `discover_models` and
+//! `TemplateConfig::validate_and_echo` each have a `_blocking` variant, such as
+//! `echo_blocking`, that runs the async function on the internal runtime and
+//! blocks the calling thread until it completes.
 //!
 //! ## Types
 //!
-//! - `EchoResult`: Rich result type with text, length, timestamp, and hash
+//! - `LibraryConfig`: Library-wide defaults (max input size, log level, runtime threads, input previews)
+//! - `RuntimeOptions`: Worker threads and thread names for the internal runtime
+//! - `LogLevel`: Verbosity of library logging
+//! - `LogRecord` / `LoggerCallback`: Log messages forwarded to the host sink set with `set_logger`
+//! - `LogThrottle`: Per-module sampling and rate cap for `set_log_throttles`
+//! - `OtelConfig`: OTLP collector endpoint and resource attributes for `enable_otel_export`
+//! - `HttpRequest` / `HttpResponse` / `HttpMethod`: Request and response for `http_request`
+//! - `HttpCacheEntry`: A response stored by the HTTP cache, from `get_http_cache_entries`
+//! - `NetworkConfig`: Network settings, such as pinned public keys per host, for `set_network_config`
+//! - `ProxyConfig` / `ProxyKind`: HTTP, HTTPS, or SOCKS5 proxy with credentials and a bypass list
+//! - `ClientIdentity` / `ClientKeySigner` / `ClientKeyType`: Client certificate for mutual TLS, from PKCS#12 or a host-held key
+//! - `NetworkStatus` / `NetworkPolicy`: Connectivity reported by the host, and which connections a component may use
+//! - `Downloader` / `DownloadInfo` / `DownloadState` / `DownloadProgress`: Concurrent, resumable downloads with the `http` feature
+//! - `DownloadListener`: Host callback notified of download progress, speed, and state changes
+//! - `BandwidthLimits`: Caps on a downloader's combined rate, lower on cellular data
+//! - `ParallelChunks`: How many connections split a large download, and from which size
+//! - `Outbox` / `OutboxOptions` / `OutboxEntry`: Persisted requests replayed with backoff until delivered, with the `http` feature
+//! - `OutboxListener` / `OutboxDropReason`: Host callback notified when outbox entries are delivered or dropped
+//! - `Telemetry` / `TelemetryConfig` / `TelemetryEvent`: Analytics events uploaded in compressed batches, with user opt-out, with the `http` feature
+//! - `UploadListener` / `UploadProgress`: Host callback notified of bytes sent by `upload_file`
+//! - `WebSocketClient` / `WebSocketOptions` / `ReconnectPolicy` / `WebSocketState`: WebSocket connection with pings and reconnection, with the `http` feature
+//! - `WebSocketListener`: Host callback receiving WebSocket messages and connection state changes
+//! - `GrpcServiceConfig`: Endpoint and timeouts of a service for `register_grpc_service`
+//! - `SseEvent` / `SseListener`: Server-Sent Events delivered by `sse_request`
+//! - `SseParser`: Incremental `text/event-stream` parser (Rust only)
+//! - `RemoteLlmClient` / `RemoteLlmConfig`: OpenAI-compatible chat-completions client with the `http` feature
+//! - `ChatMessage` / `ChatRole` / `GenerationParams` / `GenerationResult`: Conversation, sampling settings, and reply for text generation
+//! - `GenerationListener`: Host callback receiving generated text as it streams in
+//! - `HubClient` / `HubConfig`: Hugging Face Hub API client for model browsers, with the `http` feature
+//! - `ModelSearchFilters` / `ModelSort` / `HubModel` / `HubFile` / `ModelCard`: Searches, results, file listings, and model cards from the Hub
+//! - `MemoryPressureLevel`: How urgently the OS wants memory back, passed to `on_memory_pressure`
+//! - `LibraryInfo`: Version, git commit, build target, and features from `get_library_info`
+//! - `Capabilities`: Optional features, hash algorithms, and model formats from `get_capabilities`
+//! - `SelfTestReport` / `SelfTestCheck`: Results of the startup health check `run_self_test`
+//! - `CrashReport`: A panic recorded to disk, returned by `get_pending_crash_reports`
+//! - `MetricsSnapshot` / `OperationMetrics` / `HistogramBucket`: Call counts, errors, and latencies from `get_metrics_snapshot`
+//! - `SpanEvent` / `SpanEventKind` / `SpanListener`: Span open/close events forwarded with `set_span_listener`
+//! - `PreviewMode`: Whether `InvalidInput` previews show the input or a hash of it
+//! - `EchoResult`: Rich result type with text, length, timestamp, hash, warnings, and optional timing
+//! - `CallTiming`: Per-phase timing on `EchoResult` when `LibraryConfig::collect_timing` is set
+//! - `Warning` / `WarningKind`: Non-fatal issues reported alongside successful results
 //! - `TemplateConfig`: Configuration object for template operations
+//! - `TemplateConfigBuilder`: Builder for `TemplateConfig` with sensible defaults
+//! - `TextTransform`: Transformation step (trim, case, whitespace, reverse) applied by `TemplateConfig`
+//! - `SanitizationOptions` / `SanitizationReport`: Input sanitization settings and results
+//! - `ValidationReport` / `ValidationIssue`: Every problem in an input with its position, from `validate`
+//! - `UnicodeNormalization` / `LengthUnit`: Normalization form and length unit for echo results
+//! - `HashAlgorithm`: Algorithm used for `EchoResult::hash` (SHA-256, BLAKE3, XXH3, none)
+//! - `EchoStream`: Writer-style echo with incremental validation and hashing
+//! - `TaskHandle` / `TaskStatus`: Background task started by `spawn_echo`, with status, result, and cancel
+//! - `RetryPolicy`: Attempts, exponential backoff, jitter, and retryable error kinds
+//! - `ErrorKind`: Fieldless discriminant of `TemplateError`
+//! - `HostErrorCode`: Host error domain and code registered with `set_error_mappings`
+//! - `Severity`: Info / warning / recoverable / fatal level of an error, from `error_severity`
+//! - `ErrorReport` / `ErrorListener`: Errors passed to the host hook set with `set_error_listener`
+//! - `RateLimiter` / `Debouncer`: Throttle expensive operations driven by user input
+//! - `JobQueue` / `JobKind` / `JobInfo` / `JobState`: Prioritized background jobs with optional persistence
+//! - `JobListener`: Host callback notified when a job changes state
+//! - `AppDirectories` / `StorageCategory`: Host cache, data, and temp directories and what goes in each
+//! - `StorageBreakdown` / `StorageUsage`: Bytes used per storage category
+//! - `FileSandbox` / `FileMetadata`: File operations confined to a host-provided base directory
+//! - `FileWatcher` / `FileChange` / `FileChangeKind` / `FileWatchListener`: Created, modified, and deleted notifications for a directory
+//! - `HistoryEntry` / `HistoryFilter` / `PageRequest` / `HistoryPage`: Recorded operations and paged queries over them
+//! - `BackupSummary`: Creation time and entry count of a backup
+//! - `ArtifactStore` / `ArtifactInfo`: Content-addressed storage of large artifacts under friendly names
+//! - `BlobStore` / `BlobWriter` / `BlobReader`: Large binary payloads written and read in chunks
+//! - `Cache` / `CacheStats`: In-memory LRU cache with TTL, entry-count, and byte limits
+//! - `KvStore` / `KvValue`: Persistent key-value store with string, int, bool, and bytes values
+//! - `KvChange`: One set or delete in an atomic `KvStore::write_batch`
+//! - `IntegrityReport` / `IntegrityIssue` / `IntegrityIssueKind`: Crash damage found and repaired by `verify_integrity`
+//! - `Preferences` / `PreferencesListener`: Typed settings with registered defaults and change notifications
+//! - `EncryptedKvStore` / `KeyProvider`: `KvStore` with values encrypted under a host-provided key
+//! - `AuthManager` / `AuthToken`: API tokens kept in an `EncryptedKvStore` and attached to requests per host
+//! - `TokenRefresher`: Host callback returning a new token when one expires or is rejected
+//! - `HttpInterceptor`: Host callback that changes outgoing requests, e.g. to sign them, and sees their responses
+//! - `SecureBytes` / `SecureString`: Secret buffers zeroed when dropped (Rust only)
+//! - `KvMigration` / `KvMigrationStep` / `DatabaseMigration` / `MigrationReport`: Schema upgrades applied when a store opens
+//! - `Database` / `SqlValue` / `SqlStatement` / `QueryResult` / `SqlRow`: SQLite storage with the `sqlite` feature
+//! - `EventBus` / `LibraryEvent`: Process-wide channel of library events, obtained with `event_bus()`
+//! - `RecordedEvent`: A recent library event with its time, returned by `get_recent_events`
+//! - `EventListener`: Host callback receiving library events
+//! - `OperationScope`: Group of operations cancelled with `cancel_all()` and awaited with `await_all()`
 //! - `CancellationToken`: Token for cancelling async operations
+//! - `CancelOnDrop`: Guard that cancels a token when dropped (Rust only)
+//! - `CancellationListener`: Host callback notified when a token is cancelled
+//! - `SeededRng`: Deterministic random number generator for reproducible sequences
+//! - `ParsedUuid`: Canonical form and version of a parsed UUID
+//! - `DiscoveredModel` / `ModelMetadata`: Model files and their parsed headers
+//! - `Record`: A result or app-defined payload tagged with its type, for binary encodings
+//! - `ModelCache` / `CachedModel` / `PruneReport`: Downloaded models listed, pinned, and pruned to a budget
+//!
+//! ## Cancellation
+//!
+//! Every fallible async function accepts an optional `CancellationToken`.
+//! Cancelling the calling Swift `Task` or Kotlin coroutine also works: UniFFI
+//! drops the Rust future, and long-running work such as `discover_models`
+//! stops when that happens. The token is only needed to cancel from elsewhere
+//! or to share a deadline between calls. A token created with
+//! `CancellationToken::with_timeout` makes any function fail with a distinct
+//! `TemplateError::Timeout` once the deadline passes, so hosts do not need
+//! their own racing logic.
+//!
+//! ## Logging
+//!
+//! The library logs through the `log` facade. `set_logger(sink)` forwards every
+//! record at or above `LibraryConfig::log_level` to a host callback, so messages
+//! can be routed to os_log on iOS or Logcat on Android. `set_log_level(level)`
+//! and `set_log_filter("models=debug,runtime=warn")` change verbosity at any
+//! time, globally or per module. `set_log_throttles(throttles)` keeps only
+//! every Nth record of a chatty module and caps how many it forwards per
+//! second. `enable_file_logging(dir, max_size, max_files)`
+//! also writes records to rotating files, and `collect_log_files(path)` zips
+//! them up for attaching to bug reports.
+//!
+//! Every public operation also runs inside a `tracing` span carrying its name
+//! and an operation id. `set_span_listener(listener)` forwards span open and
+//! close events, with durations, so host profilers can correlate UI latency
+//! with work inside the library.
+//!
+//! `get_library_info()` returns the crate version, git commit, build timestamp,
+//! target, enabled cargo features, and UniFFI contract version, so support can
+//! tell exactly which binary a customer is running. `get_capabilities()` reports
+//! which optional features this build flavor includes, so bindings can hide UI
+//! for anything compiled out.
+//!
+//! `get_recent_events(limit)` returns the last library events (model loads,
+//! errors and cancellations, configuration changes, jobs) kept in memory, for a
+//! diagnostics screen showing what the library has been doing.
+//!
+//! `on_memory_pressure(level)`, called from `didReceiveMemoryWarning` or
+//! `onTrimMemory`, drops what the library can do without and returns roughly
+//! how many bytes were freed.
+//!
+//! `run_self_test(writable_paths)` checks at startup that the runtime starts,
+//! random number generation works, and the given directories are writable,
+//! so misconfiguration shows up before the first user action.
+//!
+//! `get_metrics_snapshot()` returns per-function call and error counts, a
+//! latency histogram, and counters such as `input_bytes`, for diagnostics
+//! screens or support tickets. `reset_metrics()` starts collection over.
+//! Setting `LibraryConfig::collect_timing` attaches a `CallTiming` breakdown
+//! (queue wait, validation, compute, total) to each `EchoResult`.
+//!
+//! With the `otel` cargo feature, `enable_otel_export(config)` exports the same
+//! spans and call metrics to an OTLP/HTTP collector, tagged with the platform and
+//! library version, alongside the app's own OpenTelemetry data.
+//!
+//! ## Storage
+//!
+//! `set_app_directories(cache_dir, data_dir, temp_dir)`, called once at
+//! startup, tells the library where the platform lets the app write. A
+//! relative path passed to any function below is then resolved inside the
+//! directory for its `StorageCategory`: logs and downloaded models in the
+//! purgeable, non-backed-up cache, stores and crash reports in app data,
+//! log archives in temp. `get_storage_breakdown()` reports the bytes each
+//! category uses, for a storage settings screen.
+//!
+//! `check_disk_space(path, required_bytes)` fails with
+//! `TemplateError::InsufficientStorage` (required and available bytes) when
+//! the volume holding `path` lacks room, so a large download or import can be
+//! refused up front instead of failing partway with an `IoError`.
+//! `ArtifactStore` runs the same check before storing anything.
+//!
+//! `FileSandbox::new(base_directory, max_file_size)` reads, writes, appends,
+//! deletes, and lists files by paths relative to a host-chosen directory.
+//! Paths escaping it are rejected, and files over the size limit fail with a
+//! typed error, so small file operations need no platform code.
+//! `write_file_atomic(path, bytes)` replaces any file crash-safely (temporary
+//! file, fsync, rename, directory sync); the library's own stores use it too.
+//!
+//! `FileWatcher(directory, recursive, listener)` reports files created,
+//! modified, and deleted in a directory through the platform's change
+//! notifications, so a model list or config file edited outside the app can
+//! be reloaded without polling. `stop()` ends the watch.
+//!
+//! `BlobStore::open(directory)` stores payloads too large for one call:
+//! `writer()` returns a `BlobWriter` that takes chunks with `append(bytes)` and
+//! returns a blob id from `finish()`, and `reader(blob_id)` returns a
+//! `BlobReader` serving ranges with `read_at(offset, len)`. Unfinished blobs
+//! are never visible to readers.
+//!
+//! `ArtifactStore::open(directory)` keeps downloaded models and other large
+//! artifacts in files named by their SHA-256, with a manifest mapping names
+//! to hashes. `import_file(name, source_path)` and `put(name, data)` store
+//! byte-identical artifacts once however many names they have, and
+//! `verify(name)` rehashes a file to detect corruption.
+//!
+//! `Cache::new(max_entries, max_bytes, default_ttl_ms)` keeps byte values in
+//! memory, evicting the least recently used entries past either limit and
+//! expiring entries after their time to live; `stats()` reports hits, misses,
+//! and evictions. The library caches parsed model headers in a shared instance
+//! that `on_memory_pressure` trims.
+//!
+//! `ModelCache::open(directory)` manages downloaded model files: `list()`
+//! returns each with its size, last-used time, and pin, for a "Manage
+//! downloads" screen. `prune(max_bytes, older_than_ms)` deletes unpinned
+//! models, least recently used first, until the directory fits the budget;
+//! `pin(id)` keeps a model and `mark_used(id)` records that it was loaded.
+//!
+//! `KvStore::open(path)` opens a key-value store persisted to one file, with
+//! `get`/`set`/`delete`/`keys` and typed helpers such as `get_string` and
+//! `set_int`. Every change is written atomically, so iOS and Android share one
+//! storage implementation instead of UserDefaults and SharedPreferences.
+//! `write_batch(changes)` sets and deletes several keys as one update through
+//! a write-ahead journal: after a crash, either the whole batch is present or
+//! none of it, and an interrupted batch is completed on the next open.
+//! `verify_integrity(repair)` reports unfinished or torn journals, temporary
+//! files from interrupted writes, and an unreadable data file, and cleans
+//! them up when `repair` is set.
+//! `EncryptedKvStore::open(path, key_id, provider)` has the same getters and setters but
+//! encrypts every value with AES-256-GCM under a key from the host's
+//! `KeyProvider` (Keychain or Keystore), so secrets never reach disk in
+//! plaintext. The key and decrypted plaintext are held in `SecureBytes`,
+//! which zeroes memory on drop, so they do not linger after use.
+//!
+//! `Preferences::open(path)` builds app settings on the same file format:
+//! `register_defaults(defaults)` at launch, typed getters that fall back to
+//! those defaults, and `add_listener(listener)` for a callback naming each
+//! key that changes, whether set from any thread or written by another
+//! process such as an app extension, so both host apps drop their own
+//! settings plumbing.
+//!
+//! `save_config(path)` and `load_config(path)` persist the `LibraryConfig`, and
+//! `TemplateConfig::save(path)` / `TemplateConfig::load(path)` a `TemplateConfig`,
+//! as JSON tagged with `CONFIG_SCHEMA_VERSION`. Loading is forward-compatible:
+//! missing fields take their defaults and fields from newer releases are
+//! ignored, so settings survive restarts and upgrades without platform code.
+//!
+//! `enable_history(path, max_entries)` records every echo result (a SHA-256 of
+//! the text, never the text itself, with timestamp, duration, and status) to
+//! a file that survives restarts. `query_history(filter, page)` returns the
+//! newest matching entries a page at a time, enough for a "recent activity"
+//! screen without platform storage.
+//!
+//! `export_data(path, passphrase, store, token)` writes the configuration and the
+//! entries of an optional `KvStore` to one file, encrypted with AES-256-GCM
+//! under a key derived from the passphrase (PBKDF2-HMAC-SHA256), for backups
+//! and device migration. `import_data` restores both, or changes nothing if
+//! the passphrase is wrong or the file was modified.
+//!
+//! With the `sqlite` cargo feature, `Database::open(path)` opens a SQLite
+//! database. `execute`, `query`, and `transaction` run parameterized statements
+//! on a background thread and return rows as typed `SqlValue`s; a cancelled
+//! token interrupts the running statement and rolls back the transaction.
+//!
+//! Both stores keep a schema version. Opened with `open_with_migrations(path,
+//! migrations)`, they apply every migration newer than the stored version
+//! (key renames, removals, and sets for `KvStore`; SQL statements for
+//! `Database`) and `migration_report()` lists the versions applied, so data
+//! written by an earlier app release is upgraded before first use.
+//!
+//! ## Networking
+//!
+//! With the `http` cargo feature, `http_request(request, token)` sends an
+//! `HttpRequest` (method, headers, body, timeout) with reqwest and rustls and
+//! returns the `HttpResponse` whatever its status, so networking logic can
+//! live in the shared core and behave the same on both platforms. Failures
+//! to get a response are `NetworkError`s; an elapsed `timeout_ms` is a
+//! `Timeout`, and a cancelled token `OperationCancelled`.
+//!
+//! `enable_http_cache(directory, max_bytes)` keeps `GET` responses on disk.
+//! A response is reused without a request while its `max-age` lasts, and
+//! then revalidated with its `ETag` or `Last-Modified`, so an unchanged
+//! resource costs a `304` instead of a download. `get_http_cache_entries()`
+//! lists what is stored and `clear_http_cache()` deletes it.
+//!
+//! `AuthManager::new(store, refresher)` keeps API tokens for remote hosts
+//! in an `EncryptedKvStore`. `set_token(host, token)` stores one, and once
+//! `set_auth_manager(manager)` installs it, every request the library sends
+//! to that host carries `Authorization: <scheme> <access_token>` unless it
+//! sets the header itself. A token about to expire is replaced through the
+//! host's `TokenRefresher` before it is sent, and `http_request` and
+//! `Outbox` refresh a token the server rejects with 401 and try again.
+//!
+//! `add_http_interceptor(name, interceptor)` covers auth schemes the
+//! `AuthManager` does not: the host's `HttpInterceptor` gets each request
+//! from `http_request`, `sse_request`, and `Outbox` just before it is sent
+//! and returns it with headers added or the payload signed, then sees the
+//! response. Interceptors run in the order they were added.
+//!
+//! `Downloader::new(max_concurrent, persist_path)` fetches files in the
+//! background: `enqueue(url, destination, headers)` returns an id, and
+//! `pause`, `resume`, and `cancel` control each download. Resuming continues
+//! from the partial file with a range request, and a downloader created with
+//! the same persistence path picks up unfinished downloads after a restart.
+//! A `DownloadListener` receives progress with the transfer rate and every
+//! state change. Downloads are checked against free disk space once the
+//! server reports their size. `set_bandwidth_limits` caps the combined rate
+//! of a downloader's transfers, with a separate cap for cellular data, and
+//! `set_download_bandwidth(id, max_bytes_per_second)` caps one download.
+//! Files of 16 MiB or more are fetched in chunks over four connections when
+//! the server supports ranges; each chunk retries and resumes on its own,
+//! and `set_parallel_chunks` changes the split.
+//!
+//! `Outbox::new(persist_path, options)` holds requests that must reach the
+//! server eventually, such as telemetry and sync payloads. `enqueue(request)`
+//! saves the request to disk before sending it, and a background worker
+//! retries failed attempts with exponential backoff, so nothing is lost
+//! while offline or across restarts. Entries the server rejects, that run
+//! out of attempts, or that grow too old are dropped; an `OutboxListener`
+//! hears about each delivery and drop. `retry_now()` skips the backoff when
+//! the host sees connectivity return.
+//!
+//! `Telemetry::new(directory, config)` buffers analytics events from
+//! `track(name, properties)` on disk and uploads them to `config.endpoint`
+//! in gzip-compressed batches, once a batch is full or its oldest event has
+//! waited long enough, retrying through an `Outbox`. `set_opted_out(true)`
+//! discards tracked events and deletes those not yet uploaded, and the choice
+//! persists, so a user's opt-out holds whatever the platform code does.
+//! Properties listed in `redacted_properties` never leave the device.
+//!
+//! `upload_file(url, path, fields, headers, listener, token)` POSTs a file as
+//! `multipart/form-data`, with a text part per field and the file in a part
+//! named `file`. The file is read in chunks while the body is sent, so logs,
+//! crash reports, or user files of any size upload without being held in
+//! memory, and an `UploadListener` is told how many bytes have gone out.
+//!
+//! `WebSocketClient::new(url, options, listener)` keeps a WebSocket open:
+//! `connect()` completes the handshake, `send_text` and `send_bytes` queue
+//! messages, and `close(code, reason)` ends the connection. Incoming
+//! messages and state changes go to the `WebSocketListener`. The client
+//! pings the server to detect dead connections and, with a
+//! `ReconnectPolicy`, reopens dropped ones with exponential backoff.
+//!
+//! `sse_request(request, listener, token)` reads a `text/event-stream`
+//! response as it arrives and hands each `SseEvent` (type, data, id, retry)
+//! to an `SseListener`, for streaming tokens from LLM servers and other
+//! server push. It returns the last event id for resuming with
+//! `Last-Event-ID`.
+//!
+//! `RemoteLlmClient::new(config)` talks to any OpenAI-compatible
+//! chat-completions server. `chat(messages, params, token)` returns the
+//! whole `GenerationResult`, and `chat_stream(messages, params, listener,
+//! token)` also hands each piece of text to a `GenerationListener` as the
+//! server streams it, so apps can offload generation when the device
+//! cannot run a model.
+//!
+//! `HubClient::new(config)` powers a model browser from the Hugging Face
+//! Hub: `search_models(query, filters, token)` finds models by name, owner,
+//! tags, task, or library, `list_files(repo_id, revision, token)` lists a
+//! repository's files with sizes and LFS hashes, and `get_model_card` fetches
+//! its README. An access token in the config opens gated and private
+//! repositories, and `file_url` with `download_headers()` hands a file to
+//! the `Downloader`.
+//!
+//! With the `grpc` cargo feature, proto-defined backend services get typed
+//! clients in the shared core: generate them with `tonic-build`, have the
+//! host call `register_grpc_service(name, config)` with each service's
+//! endpoint, wrap `grpc_channel(name)` in the generated client, and run
+//! calls with `grpc_call(name, token, call)`. Channels speak HTTP/2 over
+//! the same TLS stack as `http_request`, and error statuses come back as
+//! `NetworkError`s with the equivalent HTTP status.
+//!
+//! `set_network_config(config)` changes settings for every connection the
+//! library opens, at any time. Its `pinned_public_keys` maps host names (or
+//! `*.domain` for subdomains) to `sha256/<base64>` hashes of certificate
+//! public keys, as returned by `certificate_pin(certificate_der)`. HTTP
+//! requests, downloads, uploads, WebSockets, and gRPC calls to a pinned
+//! host fail closed with `TemplateError::PinningFailure` unless the
+//! server's validated chain carries one of those keys. Its `proxy` sends all traffic through an HTTP,
+//! HTTPS, or SOCKS5 proxy, with optional credentials and a list of hosts,
+//! domains, and address ranges reached directly, as corporate networks
+//! require. Its `client_identity` is presented to servers that require
+//! mutual TLS: `ClientIdentity::from_pkcs12(data, password)` loads a
+//! `.p12` file, while `ClientIdentity::from_signer(chain, key_type, signer)`
+//! leaves the key in the Secure Enclave or Android Keystore and asks the
+//! host's `ClientKeySigner` to sign each handshake.
+//!
+//! `set_network_status(status)`, called from `NWPathMonitor` or
+//! `ConnectivityManager`, tells the library whether the device is offline,
+//! on Wi-Fi or Ethernet, or on cellular data. The `Downloader`, `Outbox`,
+//! and `RemoteLlmClient` each have a `NetworkPolicy`: `Unmetered` keeps
+//! them off cellular data, and nothing runs while offline. Running
+//! downloads go back to the queue and continue from their partial files
+//! once the network allows it, the outbox retries every entry as soon as
+//! connectivity returns, and chat requests fail fast with a `NetworkError`.
 //!
 //! ## Error Handling
 //!
 //! Functions that can fail return `Result<T, TemplateError>`. See the `error` module
-//! for details on error types and handling.
+//! for details on error types and handling. `localize_error(error)` returns a stable
+//! message key and parameters so hosts can show translated messages instead of the
+//! English `Display` text, and `classify_error(error)` tells generic retry and
+//! reporting code whether an error is retryable, transient, caused by user input,
+//! or internal.
+//!
+//! A panic inside a fallible exported function is caught before it reaches the
+//! FFI boundary and returned as `TemplateError::Internal` with the panic message
+//! and source location, instead of aborting the host app.
+//!
+//! `enable_crash_reports(dir)` also writes every panic to disk as a `CrashReport`
+//! with its message, thread, backtrace, library version, and breadcrumbs. On the
+//! next launch, `get_pending_crash_reports()` returns them for uploading, and
+//! `delete_crash_report(id)` removes each one once it is sent.
+//!
+//! `set_error_listener(listener)` registers a host callback that receives an
+//! `ErrorReport` (operation, kind, code, and the error) for every error returned
+//! by an exported function, for centralized error analytics.
+//!
+//! `set_error_mappings(table)` registers a host error domain and code for each
+//! library error code (e.g. `TIMEOUT`); `host_error_code(error)` and the error
+//! report return the mapped value, so apps can align library errors with their
+//! existing `NSError` domains or Kotlin error hierarchies.
+//!
+//! `error_to_json(error)` (or `TemplateError::to_json()`) serializes an error with
+//! a stable schema of `code`, `variant`, `message`, and `fields` for structured
+//! logging pipelines.
+//!
+//! Building with the `debug-errors` feature attaches a breadcrumb trail of
+//! internal steps and a backtrace to `ModelLoadError` and `Internal` errors,
+//! available from `TemplateError::debug_info()` or the `debug_info` field.
 
+mod artifacts;
+mod auth;
+mod backup;
+mod blobs;
+mod blocking;
+mod cache;
+mod cancellation;
+mod client_identity;
+mod config;
+mod config_file;
+mod crash;
+mod database;
+mod diagnostics;
+mod directories;
+mod disk_space;
+mod downloader;
+mod encrypted_kv_store;
 mod error;
+mod error_map;
+mod events;
+mod file_logging;
+mod file_watcher;
+mod files;
+mod grpc;
+mod hashing;
+mod history;
+mod http;
+mod http_cache;
+mod hub;
+mod ids;
+mod info;
+mod interceptors;
+mod jobs;
+mod journal;
+mod json;
+mod kv_store;
+mod logging;
+mod memory;
+mod metrics;
+mod migrations;
+mod model_cache;
+mod models;
+mod network;
+mod otel;
+mod outbox;
+mod pinning;
+mod preferences;
+mod protobuf;
+mod proxy;
+mod records;
+mod remote_llm;
+mod reporting;
+mod retry;
+mod runtime;
+mod sanitize;
+mod scope;
+mod secure;
+mod secure_random;
+mod self_test;
+mod shield;
+mod spans;
+mod sse;
+mod stream;
+mod tasks;
+mod telemetry;
 mod template;
+mod throttle;
+mod timing;
+mod transform;
+mod unicode;
+mod upload;
+mod validation;
+mod warnings;
+mod websocket;
 
 // Export the public API
-pub use crate::error::{TemplateError, TemplateResult, DEFAULT_MAX_SIZE, MAX_INPUT_SIZE};
-pub use crate::template::{echo, random, CancellationToken, EchoResult, TemplateConfig};
+pub use crate::artifacts::{ArtifactInfo, ArtifactStore};
+pub use crate::auth::{set_auth_manager, AuthManager, AuthToken, TokenRefresher};
+pub use crate::backup::{export_data, import_data, BackupSummary};
+pub use crate::blobs::{BlobReader, BlobStore, BlobWriter, MAX_BLOB_READ};
+pub use crate::blocking::{
+    discover_models_blocking, echo_blocking, generate_uuid_v4_blocking, generate_uuid_v7_blocking,
+    is_valid_uuid_blocking, parse_uuid_blocking, random_blocking, random_bytes_blocking,
+    random_choice_blocking, random_exponential_blocking, random_int_blocking,
+    random_normal_blocking, random_seeded_blocking, random_uniform_blocking,
+    secure_random_bytes_blocking, secure_random_double_blocking,
+};
+pub use crate::cache::{Cache, CacheStats};
+pub use crate::cancellation::{CancelOnDrop, CancellationListener, CancellationToken};
+pub use crate::client_identity::{
+    ClientIdentity, ClientKeySigner, ClientKeyType, ClientSignatureAlgorithm,
+};
+pub use crate::config::{
+    get_config, initialize, load_config, save_config, update_config, LibraryConfig, LogLevel,
+};
+pub use crate::config_file::CONFIG_SCHEMA_VERSION;
+pub use crate::crash::{
+    delete_crash_report, disable_crash_reports, enable_crash_reports, get_pending_crash_reports,
+    CrashReport,
+};
+pub use crate::database::{
+    Database, DatabaseMigration, QueryResult, SqlRow, SqlStatement, SqlValue,
+};
+pub use crate::directories::{
+    get_app_directories, get_storage_breakdown, set_app_directories, storage_directory,
+    AppDirectories, StorageBreakdown, StorageCategory, StorageUsage,
+};
+pub use crate::disk_space::{check_disk_space, get_free_space};
+pub use crate::downloader::{
+    BandwidthLimits, DownloadInfo, DownloadListener, DownloadProgress, DownloadState, Downloader,
+    ParallelChunks, DOWNLOAD_PROGRESS_INTERVAL_MS, MAX_DOWNLOAD_CONNECTIONS,
+};
+pub use crate::encrypted_kv_store::{EncryptedKvStore, KeyProvider, ENCRYPTION_KEY_LENGTH};
+pub use crate::error::{
+    classify_error, error_severity, error_to_json, localize_error, ErrorClassification, ErrorKind,
+    LocalizedMessage, PreviewMode, Severity, TemplateError, TemplateResult, DEFAULT_MAX_SIZE,
+    DEFAULT_PREVIEW_LENGTH, MAX_INPUT_SIZE,
+};
+pub use crate::error_map::{host_error_code, set_error_mappings, HostErrorCode};
+pub use crate::events::{
+    event_bus, get_recent_events, EventBus, EventListener, LibraryEvent, RecordedEvent,
+    RECENT_EVENTS_CAPACITY,
+};
+pub use crate::file_logging::{collect_log_files, disable_file_logging, enable_file_logging};
+pub use crate::file_watcher::{FileChange, FileChangeKind, FileWatchListener, FileWatcher};
+pub use crate::files::{write_file_atomic, FileMetadata, FileSandbox};
+pub use crate::grpc::{
+    get_grpc_services, register_grpc_service, unregister_grpc_service, GrpcServiceConfig,
+};
+#[cfg(feature = "grpc")]
+pub use crate::grpc::{grpc_call, grpc_channel, grpc_status_error};
+pub use crate::hashing::HashAlgorithm;
+pub use crate::history::{
+    clear_history, disable_history, enable_history, query_history, HistoryEntry, HistoryFilter,
+    HistoryPage, HistoryStatus, PageRequest, MAX_HISTORY_PAGE,
+};
+pub use crate::http::{http_request, HttpMethod, HttpRequest, HttpResponse};
+pub use crate::http_cache::{
+    clear_http_cache, disable_http_cache, enable_http_cache, get_http_cache_entries,
+    remove_http_cache_entry, HttpCacheEntry,
+};
+pub use crate::hub::{
+    HubClient, HubConfig, HubFile, HubModel, ModelCard, ModelSearchFilters, ModelSort, HUB_ENDPOINT,
+};
+pub use crate::ids::{generate_uuid_v4, generate_uuid_v7, is_valid_uuid, parse_uuid, ParsedUuid};
+pub use crate::info::{get_capabilities, get_library_info, Capabilities, LibraryInfo};
+pub use crate::interceptors::{
+    add_http_interceptor, get_http_interceptors, remove_http_interceptor, HttpInterceptor,
+};
+pub use crate::jobs::{JobInfo, JobKind, JobListener, JobQueue, JobState};
+pub use crate::journal::{IntegrityIssue, IntegrityIssueKind, IntegrityReport};
+pub use crate::json::{
+    chat_messages_from_json, chat_messages_to_json, discovered_model_from_json,
+    discovered_model_to_json, echo_result_from_json, echo_result_to_json,
+    generation_params_from_json, generation_params_to_json, generation_result_from_json,
+    generation_result_to_json, model_metadata_from_json, model_metadata_to_json,
+};
+pub use crate::kv_store::{KvChange, KvMigration, KvMigrationStep, KvStore, KvValue};
+pub use crate::logging::{
+    set_log_filter, set_log_level, set_log_throttles, set_logger, LogRecord, LogThrottle,
+    LoggerCallback,
+};
+pub use crate::memory::{on_memory_pressure, MemoryPressureLevel};
+pub use crate::metrics::{
+    get_metrics_snapshot, reset_metrics, HistogramBucket, MetricsSnapshot, OperationMetrics,
+    LATENCY_BUCKET_BOUNDS_US,
+};
+pub use crate::migrations::MigrationReport;
+pub use crate::model_cache::{CachedModel, ModelCache, PruneReport};
+pub use crate::models::{
+    discover_models, load_model_metadata, DiscoveredModel, ModelFormat, ModelMetadata,
+};
+pub use crate::network::{
+    get_network_config, get_network_status, set_network_config, set_network_status, NetworkConfig,
+    NetworkPolicy, NetworkStatus,
+};
+pub use crate::otel::{disable_otel_export, enable_otel_export, OtelConfig};
+pub use crate::outbox::{
+    Outbox, OutboxDropReason, OutboxEntry, OutboxListener, OutboxOptions, OUTBOX_DEFAULT_TIMEOUT_MS,
+};
+pub use crate::pinning::certificate_pin;
+pub use crate::preferences::{Preferences, PreferencesListener, PREFERENCES_POLL_INTERVAL_MS};
+pub use crate::protobuf::{decode_protobuf, encode_protobuf, error_to_protobuf};
+pub use crate::proxy::{ProxyConfig, ProxyKind};
+pub use crate::records::{decode_cbor, decode_msgpack, encode_cbor, encode_msgpack, Record};
+pub use crate::remote_llm::{
+    ChatMessage, ChatRole, GenerationListener, GenerationParams, GenerationResult, RemoteLlmClient,
+    RemoteLlmConfig,
+};
+pub use crate::reporting::{set_error_listener, ErrorListener, ErrorReport};
+pub use crate::retry::{retry_delay_ms, retrying, should_retry, RetryPolicy};
+pub use crate::runtime::{init_runtime, run_with_timeout, shutdown_runtime, RuntimeOptions};
+pub use crate::sanitize::{SanitizationOptions, SanitizationReport};
+pub use crate::scope::OperationScope;
+pub use crate::secure::{SecureBytes, SecureString};
+pub use crate::secure_random::{secure_random_bytes, secure_random_double};
+pub use crate::self_test::{run_self_test, SelfTestCheck, SelfTestReport};
+pub use crate::spans::{set_span_listener, SpanEvent, SpanEventKind, SpanListener};
+pub use crate::sse::{sse_request, SseEvent, SseListener, SseParser, SSE_DEFAULT_EVENT};
+pub use crate::stream::{echo_stream, EchoStream};
+pub use crate::tasks::{get_task, spawn_echo, TaskHandle, TaskStatus};
+pub use crate::telemetry::{Telemetry, TelemetryConfig, TelemetryEvent};
+pub use crate::template::{
+    echo, random, random_bytes, random_choice, random_exponential, random_int, random_normal,
+    random_seeded, random_uniform, EchoResult, SeededRng, TemplateConfig, TemplateConfigBuilder,
+};
+pub use crate::throttle::{Debouncer, RateLimiter};
+pub use crate::timing::CallTiming;
+pub use crate::transform::TextTransform;
+pub use crate::unicode::{LengthUnit, UnicodeNormalization};
+pub use crate::upload::{
+    upload_file, UploadListener, UploadProgress, UPLOAD_FILE_FIELD, UPLOAD_PROGRESS_INTERVAL_MS,
+};
+pub use crate::validation::{
+    validate, ValidationIssue, ValidationIssueKind, ValidationReport, MAX_VALIDATION_ISSUES,
+};
+pub use crate::warnings::{Warning, WarningKind};
+pub use crate::websocket::{
+    ReconnectPolicy, WebSocketClient, WebSocketListener, WebSocketOptions, WebSocketState,
+    WEBSOCKET_NORMAL_CLOSURE,
+};
 
 // Include the UDL file for UniFFI
 uniffi::include_scaffolding!("template");
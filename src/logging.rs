@@ -0,0 +1,340 @@
+//! Forwarding of library log records to a host-provided sink
+//!
+//! The library logs through the `log` facade. `set_logger` installs a bridge
+//! that hands every record at or above `LibraryConfig::log_level` to the host,
+//! so Rust logs land in os_log on iOS and Logcat on Android.
+//!
+//! `set_log_level` and `set_log_filter` change what is forwarded at any time,
+//! so field debugging does not need a differently built binary. Records also
+//! go to rotating files while `enable_file_logging` is on.
+//!
+//! `set_log_throttles` samples and rate-caps chatty modules, so tight loops
+//! logging at debug level do not flood mobile log buffers.
+
+use crate::config::{self, LogLevel};
+use crate::error::{TemplateError, TemplateResult};
+use crate::file_logging;
+use crate::metrics;
+use crate::shield;
+use crate::throttle::RateLimiter;
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex, Once, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One log message from the library
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    /// Severity of the message
+    pub level: LogLevel,
+    /// Module that logged it, e.g. `rust_multiplatform_template_lib::models`
+    pub target: String,
+    /// The formatted message
+    pub message: String,
+    /// Unix timestamp in milliseconds
+    pub timestamp_ms: u64,
+}
+
+/// Sampling and rate cap for the records of one module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogThrottle {
+    /// Module or full log target, matched like `set_log_filter` modules
+    pub target: String,
+    /// Forward only every Nth record; 1 forwards all of them
+    pub sample_every: u32,
+    /// Most records forwarded per second after sampling; 0 means no cap
+    pub max_per_second: u32,
+}
+
+/// Sink for library log records, implemented by the host
+pub trait LoggerCallback: Send + Sync {
+    /// Called on the thread that logged the record; keep it fast
+    fn log(&self, record: LogRecord);
+}
+
+static HOST_LOGGER: RwLock<Option<Arc<dyn LoggerCallback>>> = RwLock::new(None);
+static INSTALL: Once = Once::new();
+
+/// Per-module levels set with `set_log_filter`, longest module path first
+static FILTERS: RwLock<Vec<(String, LogLevel)>> = RwLock::new(Vec::new());
+
+/// A `LogThrottle` with its running state
+struct ThrottleState {
+    throttle: LogThrottle,
+    /// Records seen since the throttle was set
+    seen: u64,
+    /// Bucket of `max_per_second` permits, if capped
+    limiter: Option<RateLimiter>,
+}
+
+impl ThrottleState {
+    /// Counts a record and decides whether to forward it
+    fn admit(&mut self) -> bool {
+        self.seen += 1;
+        if !(self.seen - 1).is_multiple_of(u64::from(self.throttle.sample_every)) {
+            return false;
+        }
+        self.limiter
+            .as_ref()
+            .is_none_or(|limiter| limiter.try_acquire())
+    }
+}
+
+/// Throttles set with `set_log_throttles`, longest module path first
+static THROTTLES: Mutex<Vec<ThrottleState>> = Mutex::new(Vec::new());
+
+/// Counter in `get_metrics_snapshot` of records dropped by throttles
+const DROPPED_COUNTER: &str = "log_records_throttled";
+
+/// Prefix of log targets inside this crate, stripped before matching filters
+const CRATE_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+thread_local! {
+    /// Set while the host sink runs, so records it causes are dropped
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sends log records to the host sink, replacing any previous one
+///
+/// Pass `None` to stop forwarding. Only records at or above
+/// `LibraryConfig::log_level`, or the level set for their module with
+/// `set_log_filter`, are delivered. If the process already has a
+/// `log` logger (e.g. a Rust app embedding the library), that logger keeps
+/// receiving the records instead.
+pub fn set_logger(logger: Option<Box<dyn LoggerCallback>>) {
+    *HOST_LOGGER.write().unwrap() = logger.map(Arc::from);
+    install();
+}
+
+/// Installs the bridge as the `log` logger, once, and applies the level
+pub(crate) fn install() {
+    INSTALL.call_once(|| {
+        let _ = log::set_logger(&Bridge);
+    });
+    apply_level(config::current().log_level);
+}
+
+/// Changes the default log level, overriding `LibraryConfig::log_level`
+///
+/// Takes effect immediately and is reflected in `get_config()`. Modules with
+/// a level from `set_log_filter` keep that level.
+pub fn set_log_level(level: LogLevel) {
+    config::set_log_level(level);
+    apply_level(level);
+}
+
+/// Sets per-module log levels from a filter such as `"models=debug,runtime=warn"`
+///
+/// Each comma-separated directive is `module=level`, where `module` is a
+/// module of this library (`models`, `template`, ...) or a full log target.
+/// A directive without `=` sets the default level, like `set_log_level`. Levels
+/// are `off`, `error`, `warn`, `info`, `debug`, and `trace`, in any case. An
+/// empty filter removes all per-module levels.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If a directive has an empty module
+///   or an unknown level; the previous filter stays in place
+pub fn set_log_filter(filter: String) -> TemplateResult<()> {
+    shield::guard("set_log_filter", || {
+        let mut default = None;
+        let mut directives = Vec::new();
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if module.is_empty() {
+                        return Err(TemplateError::invalid_input(
+                            format!("Log filter directive '{}' has no module", directive),
+                            None,
+                        ));
+                    }
+                    directives.push((module.to_string(), parse_level(level)?));
+                }
+                None => default = Some(parse_level(directive)?),
+            }
+        }
+        directives.sort_by_key(|(module, _)| Reverse(module.len()));
+        *FILTERS.write().unwrap() = directives;
+        match default {
+            Some(level) => set_log_level(level),
+            None => apply_level(config::current().log_level),
+        }
+        Ok(())
+    })
+}
+
+/// Samples and rate-caps log records per module, replacing previous throttles
+///
+/// Each throttle applies to the records of its module and submodules that
+/// pass the level filter; the most specific throttle wins. Error records are
+/// never throttled. Dropped records are counted in the
+/// `log_records_throttled` counter of `get_metrics_snapshot`. An empty list
+/// removes all throttles.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If a throttle has an empty target or
+///   `sample_every` is 0; the previous throttles stay in place
+pub fn set_log_throttles(throttles: Vec<LogThrottle>) -> TemplateResult<()> {
+    shield::guard("set_log_throttles", || {
+        let mut states = Vec::with_capacity(throttles.len());
+        for mut throttle in throttles {
+            throttle.target = throttle.target.trim().to_string();
+            if throttle.target.is_empty() {
+                return Err(TemplateError::invalid_input(
+                    "Log throttle has no target".to_string(),
+                    None,
+                ));
+            }
+            if throttle.sample_every == 0 {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "sample_every for log throttle '{}' must be greater than 0",
+                        throttle.target
+                    ),
+                    None,
+                ));
+            }
+            let limiter = match throttle.max_per_second {
+                0 => None,
+                max => Some(RateLimiter::new(max, f64::from(max))?),
+            };
+            states.push(ThrottleState {
+                throttle,
+                seen: 0,
+                limiter,
+            });
+        }
+        states.sort_by_key(|state| Reverse(state.throttle.target.len()));
+        *THROTTLES.lock().unwrap() = states;
+        Ok(())
+    })
+}
+
+fn parse_level(level: &str) -> TemplateResult<LogLevel> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok(LogLevel::Off),
+        "error" => Ok(LogLevel::Error),
+        "warn" => Ok(LogLevel::Warn),
+        "info" => Ok(LogLevel::Info),
+        "debug" => Ok(LogLevel::Debug),
+        "trace" => Ok(LogLevel::Trace),
+        _ => Err(TemplateError::invalid_input(
+            format!("Unknown log level '{}'", level.trim()),
+            None,
+        )),
+    }
+}
+
+/// Makes the `log` facade filter at `level`, or lower for filtered modules
+pub(crate) fn apply_level(level: LogLevel) {
+    let most_verbose = FILTERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, level)| *level)
+        .fold(level, LogLevel::max);
+    log::set_max_level(most_verbose.to_filter());
+}
+
+/// Whether `filter` names the module of `target` or one of its parents
+fn matches_module(filter: &str, target: &str) -> bool {
+    let module = target.strip_prefix(CRATE_TARGET).unwrap_or(target);
+    [target, module].iter().any(|path| {
+        path.strip_prefix(filter)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    })
+}
+
+/// Level that applies to records logged by `target`
+fn level_for(target: &str) -> LogLevel {
+    FILTERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(filter, _)| matches_module(filter, target))
+        .map_or_else(|| config::current().log_level, |(_, level)| *level)
+}
+
+/// Whether a record from `target` passes its throttle, if any
+fn admit(level: log::Level, target: &str) -> bool {
+    if level == log::Level::Error {
+        return true;
+    }
+    let mut throttles = THROTTLES.lock().unwrap();
+    let Some(state) = throttles
+        .iter_mut()
+        .find(|state| matches_module(&state.throttle.target, target))
+    else {
+        return true;
+    };
+    let admitted = state.admit();
+    drop(throttles);
+    if !admitted {
+        metrics::add(DROPPED_COUNTER, 1);
+    }
+    admitted
+}
+
+impl LogLevel {
+    fn to_filter(self) -> log::LevelFilter {
+        match self {
+            Self::Off => log::LevelFilter::Off,
+            Self::Error => log::LevelFilter::Error,
+            Self::Warn => log::LevelFilter::Warn,
+            Self::Info => log::LevelFilter::Info,
+            Self::Debug => log::LevelFilter::Debug,
+            Self::Trace => log::LevelFilter::Trace,
+        }
+    }
+
+    fn from_level(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Self::Error,
+            log::Level::Warn => Self::Warn,
+            log::Level::Info => Self::Info,
+            log::Level::Debug => Self::Debug,
+            log::Level::Trace => Self::Trace,
+        }
+    }
+}
+
+/// `log::Log` implementation forwarding to the host sink
+struct Bridge;
+
+impl log::Log for Bridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= level_for(metadata.target()).to_filter()
+            && (HOST_LOGGER.read().unwrap().is_some() || file_logging::is_enabled())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if FORWARDING.with(|forwarding| forwarding.replace(true)) {
+            return;
+        }
+        if !admit(record.level(), record.target()) {
+            FORWARDING.with(|forwarding| forwarding.set(false));
+            return;
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let record = LogRecord {
+            level: LogLevel::from_level(record.level()),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            timestamp_ms,
+        };
+        file_logging::write(&record);
+        if let Some(logger) = HOST_LOGGER.read().unwrap().clone() {
+            logger.log(record);
+        }
+        FORWARDING.with(|forwarding| forwarding.set(false));
+    }
+
+    fn flush(&self) {}
+}
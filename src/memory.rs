@@ -0,0 +1,37 @@
+//! Releasing memory when the host OS is running low
+
+use crate::cache;
+use crate::events::{self, RECENT_EVENTS_CAPACITY};
+use crate::tasks;
+
+/// How urgently the host OS wants memory back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryPressureLevel {
+    /// Memory is getting low, e.g. `onTrimMemory(TRIM_MEMORY_RUNNING_LOW)`
+    Moderate,
+    /// The app may be killed, e.g. `didReceiveMemoryWarning` or
+    /// `onTrimMemory(TRIM_MEMORY_RUNNING_CRITICAL)`
+    Critical,
+}
+
+/// Frees memory the library can do without and returns how many bytes
+///
+/// Call from `didReceiveMemoryWarning` on iOS or `onTrimMemory` on Android.
+/// Both levels forget finished background tasks. `Moderate` trims the
+/// recent-events buffer and the shared cache to their newest quarter;
+/// `Critical` empties them.
+/// The returned count is an estimate of heap memory released.
+pub fn on_memory_pressure(level: MemoryPressureLevel) -> u64 {
+    let cache = cache::shared();
+    let (keep_events, keep_cached) = match level {
+        MemoryPressureLevel::Moderate => (
+            RECENT_EVENTS_CAPACITY as usize / 4,
+            cache.stats().entry_count as usize / 4,
+        ),
+        MemoryPressureLevel::Critical => (0, 0),
+    };
+    let freed =
+        tasks::release_memory() + events::release_memory(keep_events) + cache.trim(keep_cached);
+    log::info!("Memory pressure {:?}: released {} bytes", level, freed);
+    freed
+}
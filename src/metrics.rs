@@ -0,0 +1,155 @@
+//! In-process metrics for diagnostics screens and support tickets
+//!
+//! Every guarded export records a call, its outcome, and its latency. Other
+//! code adds to named counters with `add`. `get_metrics_snapshot()` returns
+//! everything collected since start-up or the last `reset_metrics()`.
+
+use crate::error::TemplateError;
+use crate::otel;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Upper bounds, in microseconds, of the latency histogram buckets
+///
+/// A final bucket with an upper bound of `u64::MAX` collects the rest.
+pub const LATENCY_BUCKET_BOUNDS_US: [u64; 6] = [100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// Number of calls that finished within `upper_bound_us`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// Inclusive upper bound of the bucket in microseconds
+    pub upper_bound_us: u64,
+    /// Calls whose latency fell in this bucket (not cumulative)
+    pub count: u64,
+}
+
+/// Call statistics for one exported function
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationMetrics {
+    /// Operation name, as used in errors and spans
+    pub operation: String,
+    /// Calls that completed, successfully or not
+    pub calls: u64,
+    /// Calls that returned an error
+    pub errors: u64,
+    /// Errors per kind, keyed by `ErrorKind::code()`
+    pub errors_by_code: HashMap<String, u64>,
+    /// Sum of call latencies in microseconds
+    pub total_duration_us: u64,
+    /// Longest call latency in microseconds
+    pub max_duration_us: u64,
+    /// Latency histogram, one bucket per `LATENCY_BUCKET_BOUNDS_US` plus overflow
+    pub latency_buckets: Vec<HistogramBucket>,
+}
+
+/// Everything the registry has collected
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Unix timestamp in milliseconds when collection started or was reset
+    pub since_ms: u64,
+    /// Unix timestamp in milliseconds when the snapshot was taken
+    pub taken_ms: u64,
+    /// Per-operation statistics, sorted by operation name
+    pub operations: Vec<OperationMetrics>,
+    /// Named counters, e.g. `input_bytes`
+    pub counters: HashMap<String, u64>,
+}
+
+struct Registry {
+    since_ms: u64,
+    operations: HashMap<String, OperationMetrics>,
+    counters: HashMap<String, u64>,
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let registry = registry.get_or_insert_with(|| Registry {
+        since_ms: now_ms(),
+        operations: HashMap::new(),
+        counters: HashMap::new(),
+    });
+    f(registry)
+}
+
+/// Records one completed call of `operation`
+pub(crate) fn record_call(operation: &str, elapsed: Duration, error: Option<&TemplateError>) {
+    let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+    let code = error.map(|e| e.kind().code());
+    otel::record_call(operation, micros, code.as_deref());
+    with_registry(|registry| {
+        let metrics = registry
+            .operations
+            .entry(operation.to_string())
+            .or_insert_with(|| OperationMetrics {
+                operation: operation.to_string(),
+                calls: 0,
+                errors: 0,
+                errors_by_code: HashMap::new(),
+                total_duration_us: 0,
+                max_duration_us: 0,
+                latency_buckets: LATENCY_BUCKET_BOUNDS_US
+                    .iter()
+                    .copied()
+                    .chain([u64::MAX])
+                    .map(|upper_bound_us| HistogramBucket {
+                        upper_bound_us,
+                        count: 0,
+                    })
+                    .collect(),
+            });
+        metrics.calls += 1;
+        metrics.total_duration_us = metrics.total_duration_us.saturating_add(micros);
+        metrics.max_duration_us = metrics.max_duration_us.max(micros);
+        if let Some(bucket) = metrics
+            .latency_buckets
+            .iter_mut()
+            .find(|b| micros <= b.upper_bound_us)
+        {
+            bucket.count += 1;
+        }
+        if let Some(code) = code {
+            metrics.errors += 1;
+            *metrics.errors_by_code.entry(code).or_insert(0) += 1;
+        }
+    });
+}
+
+/// Adds `amount` to the counter `name`
+pub(crate) fn add(name: &str, amount: u64) {
+    with_registry(|registry| {
+        let counter = registry.counters.entry(name.to_string()).or_insert(0);
+        *counter = counter.saturating_add(amount);
+    });
+}
+
+/// Returns the metrics collected since start-up or the last reset
+pub fn get_metrics_snapshot() -> MetricsSnapshot {
+    with_registry(|registry| {
+        let mut operations: Vec<_> = registry.operations.values().cloned().collect();
+        operations.sort_by(|a, b| a.operation.cmp(&b.operation));
+        MetricsSnapshot {
+            since_ms: registry.since_ms,
+            taken_ms: now_ms(),
+            operations,
+            counters: registry.counters.clone(),
+        }
+    })
+}
+
+/// Clears all metrics and restarts collection
+pub fn reset_metrics() {
+    with_registry(|registry| {
+        registry.since_ms = now_ms();
+        registry.operations.clear();
+        registry.counters.clear();
+    });
+}
@@ -0,0 +1,66 @@
+//! Versioned schema migrations for persisted data
+//!
+//! `KvStore` and `Database` record a schema version alongside their data.
+//! Opening one with a list of migrations applies, in order, every migration
+//! newer than the stored version and records the new version, so data
+//! written by an earlier release of the app is upgraded before it is used.
+//! Migrations that already ran are skipped on later opens.
+
+use crate::error::{TemplateError, TemplateResult};
+
+/// What happened to a store's schema when it was opened
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MigrationReport {
+    /// Schema version found when the store was opened (0 for new or unversioned data)
+    pub from_version: u32,
+    /// Schema version after the migrations ran
+    pub to_version: u32,
+    /// Versions of the migrations applied during this open, in order
+    pub applied: Vec<u32>,
+}
+
+impl MigrationReport {
+    /// A report for a store opened without migrations
+    pub(crate) fn unchanged(version: u32) -> Self {
+        Self {
+            from_version: version,
+            to_version: version,
+            applied: Vec::new(),
+        }
+    }
+}
+
+/// Indexes of the migrations newer than `current`, in order
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the versions are not unique,
+///   ascending, and greater than 0, or if `current` is newer than the last
+///   migration, meaning the data was written by a newer release
+pub(crate) fn pending(current: u32, versions: &[u32]) -> TemplateResult<Vec<usize>> {
+    if versions.first() == Some(&0) || versions.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(TemplateError::invalid_input(
+            format!(
+                "Migration versions must be ascending and greater than 0, got {:?}",
+                versions
+            ),
+            None,
+        ));
+    }
+    let latest = versions.last().copied().unwrap_or(0);
+    if current > latest {
+        return Err(TemplateError::invalid_input(
+            format!(
+                "Data has schema version {}, newer than the latest migration {}",
+                current, latest
+            ),
+            None,
+        ));
+    }
+    Ok(versions
+        .iter()
+        .enumerate()
+        .filter(|(_, version)| **version > current)
+        .map(|(index, _)| index)
+        .collect())
+}
@@ -0,0 +1,321 @@
+//! Management of a directory of downloaded model files
+//!
+//! `ModelCache` lists the model files under one directory with their sizes
+//! and last-used times, deletes them individually, and prunes the directory
+//! to a byte budget or an age, least recently used first. Pinned models are
+//! never pruned. Pins and last-used times are kept in a small index file in
+//! the directory; a model the index does not know was last used when its
+//! file was last modified.
+
+use crate::cancellation::CancellationToken;
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files::write_atomic;
+use crate::models::{collect_model_files, ModelFormat};
+use crate::runtime;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the index file kept in the cache directory
+const INDEX_FILE: &str = ".model_cache.json";
+
+/// A model file in a `ModelCache`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedModel {
+    /// Path relative to the cache directory, with `/` separators
+    pub id: String,
+    /// Full path to the file
+    pub path: String,
+    pub format: ModelFormat,
+    pub size_bytes: u64,
+    /// When the model was last marked used, or else last modified, in
+    /// milliseconds since the Unix epoch
+    pub last_used_ms: Option<u64>,
+    /// Whether `prune` leaves this model alone
+    pub pinned: bool,
+}
+
+/// What `ModelCache::prune` deleted
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PruneReport {
+    /// Ids of the deleted models, least recently used first
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexEntry {
+    #[serde(default)]
+    last_used_ms: Option<u64>,
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// Directory of model files kept under a budget
+pub struct ModelCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    directory: PathBuf,
+    index: Mutex<BTreeMap<String, IndexEntry>>,
+}
+
+impl ModelCache {
+    /// Opens the model cache in `directory`, creating it if missing
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory or its index cannot be read or created
+    /// * `Err(TemplateError::ParseError)` - If the index file is corrupt
+    pub fn open(directory: String) -> TemplateResult<Self> {
+        shield::guard("ModelCache::open", || {
+            let directory = directories::resolve(StorageCategory::Downloads, &directory);
+            fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+            let index_path = directory.join(INDEX_FILE);
+            let index = match fs::read(&index_path) {
+                Ok(json) => {
+                    serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))?
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+                Err(e) => return Err(TemplateError::io_error(&index_path, &e)),
+            };
+            Ok(Self {
+                inner: Arc::new(Inner {
+                    directory,
+                    index: Mutex::new(index),
+                }),
+            })
+        })
+    }
+
+    /// Every model file in the cache, sorted by id (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be read
+    pub async fn list(&self) -> TemplateResult<Vec<CachedModel>> {
+        let inner = self.inner.clone();
+        shield::guard_async("ModelCache::list", async move {
+            runtime::spawn_blocking(move || inner.models()).await
+        })
+        .await
+    }
+
+    /// Deletes unpinned models, least recently used first (async)
+    ///
+    /// Models not used for `older_than_ms` milliseconds are deleted first.
+    /// Then, while the cache is larger than `max_bytes`, the least recently
+    /// used remaining model is deleted. Pinned models count toward the total
+    /// but are kept, so the cache can stay above `max_bytes`.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be read or a file cannot be deleted
+    pub async fn prune(
+        &self,
+        max_bytes: Option<u64>,
+        older_than_ms: Option<u64>,
+    ) -> TemplateResult<PruneReport> {
+        let inner = self.inner.clone();
+        shield::guard_async("ModelCache::prune", async move {
+            runtime::spawn_blocking(move || inner.prune(max_bytes, older_than_ms)).await
+        })
+        .await
+    }
+
+    /// Keeps the model `id` from being pruned
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::ModelNotFound)` - If there is no such model in the cache
+    /// * `Err(TemplateError::IoError)` - If the index cannot be written
+    pub fn pin(&self, id: String) -> TemplateResult<()> {
+        shield::guard("ModelCache::pin", || {
+            self.inner.update(&id, |entry| entry.pinned = true)
+        })
+    }
+
+    /// Lets the model `id` be pruned again
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::ModelNotFound)` - If there is no such model in the cache
+    /// * `Err(TemplateError::IoError)` - If the index cannot be written
+    pub fn unpin(&self, id: String) -> TemplateResult<()> {
+        shield::guard("ModelCache::unpin", || {
+            self.inner.update(&id, |entry| entry.pinned = false)
+        })
+    }
+
+    /// Records that the model `id` was just used, e.g. when the app loads it
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::ModelNotFound)` - If there is no such model in the cache
+    /// * `Err(TemplateError::IoError)` - If the index cannot be written
+    pub fn mark_used(&self, id: String) -> TemplateResult<()> {
+        shield::guard("ModelCache::mark_used", || {
+            let now = now_ms();
+            self.inner
+                .update(&id, |entry| entry.last_used_ms = Some(now))
+        })
+    }
+
+    /// Deletes the model `id`, pinned or not, returning whether it existed
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `id` is not a model file path in the cache
+    /// * `Err(TemplateError::IoError)` - If the file cannot be deleted
+    pub fn delete(&self, id: String) -> TemplateResult<bool> {
+        shield::guard("ModelCache::delete", || {
+            let path = self.inner.resolve(&id)?;
+            let existed = match fs::remove_file(&path) {
+                Ok(()) => true,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+                Err(e) => return Err(TemplateError::io_error(&path, &e)),
+            };
+            let mut index = self.inner.index.lock().unwrap();
+            if index.remove(&id).is_some() {
+                self.inner.save(&index)?;
+            }
+            Ok(existed)
+        })
+    }
+}
+
+impl Inner {
+    fn models(&self) -> TemplateResult<Vec<CachedModel>> {
+        let mut files = Vec::new();
+        collect_model_files(&self.directory, &mut files, &CancellationToken::new())?;
+        let index = self.index.lock().unwrap();
+        let mut models: Vec<CachedModel> = files
+            .into_iter()
+            .filter_map(|(path, format)| {
+                let metadata = fs::metadata(&path).ok()?;
+                let id = self.id_of(&path)?;
+                let entry = index.get(&id).cloned().unwrap_or_default();
+                let modified_ms = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as u64);
+                Some(CachedModel {
+                    id,
+                    path: path.to_string_lossy().into_owned(),
+                    format,
+                    size_bytes: metadata.len(),
+                    last_used_ms: entry.last_used_ms.or(modified_ms),
+                    pinned: entry.pinned,
+                })
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
+    fn prune(
+        &self,
+        max_bytes: Option<u64>,
+        older_than_ms: Option<u64>,
+    ) -> TemplateResult<PruneReport> {
+        let mut models = self.models()?;
+        models.sort_by_key(|model| model.last_used_ms.unwrap_or(0));
+        let mut total: u64 = models.iter().map(|model| model.size_bytes).sum();
+        let cutoff = older_than_ms.map(|age| now_ms().saturating_sub(age));
+        let mut report = PruneReport::default();
+
+        for model in models.iter().filter(|model| !model.pinned) {
+            let too_old = cutoff.is_some_and(|cutoff| model.last_used_ms.unwrap_or(0) < cutoff);
+            let over_budget = max_bytes.is_some_and(|max| total > max);
+            if !too_old && !over_budget {
+                continue;
+            }
+            fs::remove_file(&model.path)
+                .map_err(|e| TemplateError::io_error(Path::new(&model.path), &e))?;
+            total -= model.size_bytes;
+            report.freed_bytes += model.size_bytes;
+            report.removed.push(model.id.clone());
+        }
+
+        if !report.removed.is_empty() {
+            let mut index = self.index.lock().unwrap();
+            for id in &report.removed {
+                index.remove(id);
+            }
+            self.save(&index)?;
+            log::info!(
+                "Pruned {} models ({} bytes) from {}",
+                report.removed.len(),
+                report.freed_bytes,
+                self.directory.display()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Changes the index entry of an existing model and saves the index
+    fn update(&self, id: &str, change: impl FnOnce(&mut IndexEntry)) -> TemplateResult<()> {
+        let path = self.resolve(id)?;
+        if !path.is_file() {
+            return Err(TemplateError::model_not_found(&path));
+        }
+        let mut index = self.index.lock().unwrap();
+        let mut updated = index.clone();
+        change(updated.entry(id.to_string()).or_default());
+        self.save(&updated)?;
+        *index = updated;
+        Ok(())
+    }
+
+    fn save(&self, index: &BTreeMap<String, IndexEntry>) -> TemplateResult<()> {
+        let path = self.directory.join(INDEX_FILE);
+        let json = serde_json::to_vec(index).map_err(|e| TemplateError::json_error(&e))?;
+        write_atomic(&path, &json).map_err(|e| TemplateError::io_error(&path, &e))
+    }
+
+    /// The file for `id`, which must be a model file path inside the directory
+    fn resolve(&self, id: &str) -> TemplateResult<PathBuf> {
+        let relative = Path::new(id);
+        let inside = !id.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !inside {
+            return Err(TemplateError::invalid_input(
+                format!("Not a model id: '{}'", id),
+                None,
+            ));
+        }
+        let path = self.directory.join(relative);
+        if ModelFormat::from_path(&path).is_none() {
+            return Err(TemplateError::invalid_input(
+                format!("Not a model id: '{}'", id),
+                None,
+            ));
+        }
+        Ok(path)
+    }
+
+    /// The id of a file found under the directory
+    fn id_of(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.directory).ok()?;
+        let parts: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        Some(parts.join("/"))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
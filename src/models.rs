@@ -0,0 +1,501 @@
+//! Model discovery and header metadata extraction
+
+use crate::cache;
+use crate::cancellation::{self, CancellationToken};
+use crate::diagnostics::{self, breadcrumb};
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::runtime;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+/// Magic bytes at the start of every GGUF file
+const GGUF_MAGIC: [u8; 4] = *b"GGUF";
+
+/// Largest safetensors JSON header we are willing to parse (100MB)
+const MAX_SAFETENSORS_HEADER: u64 = 100_000_000;
+
+/// Largest GGUF string we are willing to read (for key names and values)
+const MAX_GGUF_STRING: u64 = 1_000_000;
+
+/// Deepest nesting of GGUF arrays we are willing to skip
+const MAX_GGUF_ARRAY_DEPTH: u32 = 3;
+
+/// Supported model file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFormat {
+    /// llama.cpp GGUF container
+    Gguf,
+    /// Hugging Face safetensors container
+    Safetensors,
+}
+
+impl ModelFormat {
+    /// Detect the format from a file extension
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gguf" => Some(Self::Gguf),
+            "safetensors" => Some(Self::Safetensors),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata parsed from a model file header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    /// Container format version (always 0 for safetensors)
+    pub version: u32,
+    /// Number of tensors declared in the header
+    pub tensor_count: u64,
+    /// Number of metadata key/value pairs in the header
+    pub metadata_count: u64,
+    /// Model architecture (e.g. "llama"), if declared
+    pub architecture: Option<String>,
+    /// Human-readable model name, if declared
+    pub name: Option<String>,
+}
+
+/// A model file found by `discover_models`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredModel {
+    /// Full path to the file
+    pub path: String,
+    /// File name without the directory
+    pub file_name: String,
+    /// Size of the file in bytes
+    pub size_bytes: u64,
+    /// Format detected from the file extension
+    pub format: ModelFormat,
+    /// Whether the header was parsed successfully
+    pub is_valid: bool,
+    /// Parsed header metadata, if valid
+    pub metadata: Option<ModelMetadata>,
+    /// Why the header could not be parsed, if invalid
+    pub error_message: Option<String>,
+}
+
+/// Scans a directory for model files and extracts their header metadata (async)
+///
+/// The directory is walked recursively. Every `.gguf` and `.safetensors` file
+/// is included in the result, and headers are parsed in parallel across the
+/// available CPU cores. Files whose headers cannot be parsed are still
+/// returned with `is_valid` set to `false` and an `error_message`.
+///
+/// The scan runs on a background thread. Dropping the returned future (for
+/// example when the calling Swift `Task` or Kotlin coroutine is cancelled)
+/// stops the scan, so `token` is only needed for cancelling from elsewhere.
+///
+/// # Arguments
+///
+/// * `directory` - The directory to scan
+/// * `token` - Optional cancellation token
+///
+/// # Returns
+///
+/// * `Ok(Vec<DiscoveredModel>)` - The discovered models, sorted by path
+/// * `Err(TemplateError::IoError)` - If the directory cannot be read
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn discover_models(
+    directory: String,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<DiscoveredModel>> {
+    shield::guard_async("discover_models", async move {
+        cancellation::check_cancelled(token.as_deref(), "discover_models")?;
+
+        tokio::task::yield_now().await;
+
+        let op_token = cancellation::operation_token(token.as_deref());
+        let guard = op_token.drop_guard();
+
+        let scanned = directory.clone();
+        let models = runtime::spawn_blocking(move || {
+            let mut files = Vec::new();
+            collect_model_files(Path::new(&scanned), &mut files, &op_token)?;
+            files.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok::<_, TemplateError>(inspect_in_parallel(&files, Some(&op_token)))
+        })
+        .await?;
+
+        guard.disarm();
+
+        cancellation::check_cancelled(token.as_deref(), "discover_models")?;
+
+        log::debug!("Found {} model files in {}", models.len(), directory);
+        events::publish(LibraryEvent::ModelsDiscovered {
+            directory,
+            count: models.len() as u32,
+        });
+        Ok(models)
+    })
+    .await
+}
+
+/// Reads the header metadata of a single model file (async)
+///
+/// # Returns
+///
+/// * `Ok(ModelMetadata)` - The parsed header
+/// * `Err(TemplateError::ModelNotFound)` - If no file exists at `path`
+/// * `Err(TemplateError::InvalidModelFormat)` - If the extension is not `.gguf` or `.safetensors`
+/// * `Err(TemplateError::ModelLoadError)` - If the header cannot be read or parsed
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn load_model_metadata(
+    path: String,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<ModelMetadata> {
+    shield::guard_async("load_model_metadata", async move {
+        cancellation::check_cancelled(token.as_deref(), "load_model_metadata")?;
+        tokio::task::yield_now().await;
+
+        let path = PathBuf::from(path);
+        let metadata = runtime::spawn_blocking(move || {
+            if !path.is_file() {
+                return Err(TemplateError::model_not_found(&path));
+            }
+            let format = ModelFormat::from_path(&path).ok_or_else(|| {
+                TemplateError::invalid_model_format(
+                    &path,
+                    "Expected a .gguf or .safetensors file".to_string(),
+                )
+            })?;
+            let metadata = cached_header(&path, format)
+                .map_err(|e| TemplateError::model_load_error(&path, e))?;
+            events::publish(LibraryEvent::ModelLoaded {
+                path: path.display().to_string(),
+                format,
+            });
+            Ok(metadata)
+        })
+        .await?;
+
+        cancellation::check_cancelled(token.as_deref(), "load_model_metadata")?;
+        Ok(metadata)
+    })
+    .await
+}
+
+/// Recursively collects files with a known model extension
+///
+/// Stops early, returning what it has so far, once `token` is cancelled.
+pub(crate) fn collect_model_files(
+    dir: &Path,
+    files: &mut Vec<(PathBuf, ModelFormat)>,
+    token: &CancellationToken,
+) -> TemplateResult<()> {
+    if token.is_cancelled() {
+        return Ok(());
+    }
+    let entries = std::fs::read_dir(dir).map_err(|e| TemplateError::io_error(dir, &e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| TemplateError::io_error(dir, &e))?;
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|e| TemplateError::io_error(&path, &e))?;
+
+        if file_type.is_dir() {
+            collect_model_files(&path, files, token)?;
+        } else if let Some(format) = ModelFormat::from_path(&path) {
+            files.push((path, format));
+        }
+    }
+
+    Ok(())
+}
+
+/// Inspects files on a pool of scoped worker threads, preserving input order
+fn inspect_in_parallel(
+    files: &[(PathBuf, ModelFormat)],
+    token: Option<&CancellationToken>,
+) -> Vec<DiscoveredModel> {
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(files.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<DiscoveredModel>>> = Mutex::new(vec![None; files.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if token.is_some_and(|t| t.is_cancelled()) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((path, format)) = files.get(index) else {
+                    break;
+                };
+                let model = inspect_model(path, *format);
+                results.lock().unwrap()[index] = Some(model);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Builds a `DiscoveredModel` for a single file
+fn inspect_model(path: &Path, format: ModelFormat) -> DiscoveredModel {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let size_bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    let (metadata, error_message) = match cached_header(path, format) {
+        Ok(metadata) => (Some(metadata), None),
+        Err(message) => (None, Some(message)),
+    };
+
+    DiscoveredModel {
+        path: path.to_string_lossy().into_owned(),
+        file_name,
+        size_bytes,
+        format,
+        is_valid: metadata.is_some(),
+        metadata,
+        error_message,
+    }
+}
+
+/// Parses the header of a model file, reusing an earlier parse if the file is unchanged
+///
+/// Parsed headers are kept in the shared cache, keyed by path, size, and
+/// modification time.
+fn cached_header(path: &Path, format: ModelFormat) -> Result<ModelMetadata, String> {
+    let Some(key) = header_cache_key(path) else {
+        return read_header(path, format);
+    };
+    let cache = cache::shared();
+    if let Some(metadata) = cache
+        .get(key.clone())
+        .and_then(|json| serde_json::from_slice(&json).ok())
+    {
+        return Ok(metadata);
+    }
+    let metadata = read_header(path, format)?;
+    if let Ok(json) = serde_json::to_vec(&metadata) {
+        cache.set(key, json, None);
+    }
+    Ok(metadata)
+}
+
+/// The shared-cache key for the header of the file at `path`, as it is now
+fn header_cache_key(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!(
+        "model_metadata:{}:{}:{}",
+        path.display(),
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+/// Opens a model file and parses its header
+fn read_header(path: &Path, format: ModelFormat) -> Result<ModelMetadata, String> {
+    let _span = tracing::debug_span!("read_header", path = %path.display()).entered();
+    diagnostics::begin("read_header");
+    breadcrumb!("open {} as {:?}", path.display(), format);
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let file_len = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut reader = BufReader::new(file);
+    match format {
+        ModelFormat::Gguf => read_gguf_header(&mut reader, file_len),
+        ModelFormat::Safetensors => read_safetensors_header(&mut reader),
+    }
+}
+
+/// Parses the fixed GGUF header and scans metadata for well-known keys
+fn read_gguf_header<R: Read + Seek>(
+    reader: &mut R,
+    file_len: u64,
+) -> Result<ModelMetadata, String> {
+    let mut magic = [0u8; 4];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| "File too small for GGUF header".to_string())?;
+    if magic != GGUF_MAGIC {
+        return Err("Missing GGUF magic bytes".to_string());
+    }
+
+    let version = read_u32(reader)?;
+    breadcrumb!("GGUF version {}", version);
+    let (tensor_count, metadata_count) = match version {
+        1 => (read_u32(reader)? as u64, read_u32(reader)? as u64),
+        2 | 3 => (read_u64(reader)?, read_u64(reader)?),
+        _ => return Err(format!("Unsupported GGUF version {}", version)),
+    };
+
+    let mut metadata = ModelMetadata {
+        version,
+        tensor_count,
+        metadata_count,
+        architecture: None,
+        name: None,
+    };
+
+    for _ in 0..metadata_count {
+        if metadata.architecture.is_some() && metadata.name.is_some() {
+            break;
+        }
+        let key = read_gguf_string(reader)?;
+        let value_type = read_u32(reader)?;
+        breadcrumb!("GGUF metadata key {} of type {}", key, value_type);
+        match (key.as_str(), value_type) {
+            ("general.architecture", GGUF_TYPE_STRING) => {
+                metadata.architecture = Some(read_gguf_string(reader)?)
+            }
+            ("general.name", GGUF_TYPE_STRING) => metadata.name = Some(read_gguf_string(reader)?),
+            _ => skip_gguf_value(reader, value_type, file_len, 0)?,
+        }
+    }
+
+    Ok(metadata)
+}
+
+const GGUF_TYPE_STRING: u32 = 8;
+const GGUF_TYPE_ARRAY: u32 = 9;
+
+/// Skips over a GGUF metadata value of the given type
+///
+/// `depth` counts the arrays enclosing the value. Arrays of strings or
+/// arrays are checked against the bytes left in the file before their items
+/// are walked, so a forged count fails fast instead of looping.
+fn skip_gguf_value<R: Read + Seek>(
+    reader: &mut R,
+    value_type: u32,
+    file_len: u64,
+    depth: u32,
+) -> Result<(), String> {
+    match value_type {
+        GGUF_TYPE_STRING => {
+            let len = read_u64(reader)?;
+            skip_bytes(reader, len)
+        }
+        GGUF_TYPE_ARRAY => {
+            if depth >= MAX_GGUF_ARRAY_DEPTH {
+                return Err(format!(
+                    "GGUF arrays nested more than {} deep",
+                    MAX_GGUF_ARRAY_DEPTH
+                ));
+            }
+            let item_type = read_u32(reader)?;
+            let count = read_u64(reader)?;
+            if let Some(size) = gguf_scalar_size(item_type) {
+                return skip_bytes(reader, count.saturating_mul(size));
+            }
+            // Strings and arrays both start with at least 8 bytes
+            let position = reader.stream_position().map_err(|e| e.to_string())?;
+            if count.saturating_mul(8) > file_len.saturating_sub(position) {
+                return Err(format!("GGUF array of {} items overruns the file", count));
+            }
+            (0..count).try_for_each(|_| skip_gguf_value(reader, item_type, file_len, depth + 1))
+        }
+        _ => match gguf_scalar_size(value_type) {
+            Some(size) => skip_bytes(reader, size),
+            None => Err(format!("Unknown GGUF value type {}", value_type)),
+        },
+    }
+}
+
+/// Size in bytes of a fixed-width GGUF value type
+fn gguf_scalar_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+/// Reads a length-prefixed GGUF string
+fn read_gguf_string<R: Read>(reader: &mut R) -> Result<String, String> {
+    let len = read_u64(reader)?;
+    if len > MAX_GGUF_STRING {
+        return Err(format!("GGUF string too long: {} bytes", len));
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| "Truncated GGUF string".to_string())?;
+    String::from_utf8(buf).map_err(|_| "GGUF string is not valid UTF-8".to_string())
+}
+
+/// Parses the JSON header of a safetensors file
+fn read_safetensors_header<R: Read>(reader: &mut R) -> Result<ModelMetadata, String> {
+    let header_len = read_u64(reader)?;
+    breadcrumb!("safetensors header of {} bytes", header_len);
+    if header_len == 0 || header_len > MAX_SAFETENSORS_HEADER {
+        return Err(format!("Invalid safetensors header length {}", header_len));
+    }
+
+    let mut buf = vec![0u8; header_len as usize];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| "Truncated safetensors header".to_string())?;
+
+    let header: serde_json::Value = serde_json::from_slice(&buf)
+        .map_err(|e| format!("Invalid safetensors header JSON: {}", e))?;
+    let entries = header
+        .as_object()
+        .ok_or_else(|| "Safetensors header is not a JSON object".to_string())?;
+
+    let user_metadata = entries.get("__metadata__").and_then(|m| m.as_object());
+    let lookup = |key: &str| {
+        user_metadata
+            .and_then(|m| m.get(key))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+
+    Ok(ModelMetadata {
+        version: 0,
+        tensor_count: entries.keys().filter(|k| *k != "__metadata__").count() as u64,
+        metadata_count: user_metadata.map_or(0, |m| m.len() as u64),
+        architecture: lookup("architecture"),
+        name: lookup("name"),
+    })
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    let mut buf = [0u8; 4];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| "Unexpected end of header".to_string())?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> {
+    let mut buf = [0u8; 8];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| "Unexpected end of header".to_string())?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn skip_bytes<R: Read>(reader: &mut R, len: u64) -> Result<(), String> {
+    let skipped =
+        std::io::copy(&mut reader.take(len), &mut std::io::sink()).map_err(|e| e.to_string())?;
+    if skipped != len {
+        return Err("Unexpected end of header".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Network settings applied to every connection the library makes
+//!
+//! `set_network_config` can be called at any time: requests and WebSocket
+//! connections started afterwards use the new settings, while those in
+//! flight finish with the old ones. Settings are checked and stored even
+//! without the `http` feature, so hosts configure the library the same way
+//! whatever it was built with.
+//!
+//! `set_network_status` is how the host reports connectivity, from
+//! `NWPathMonitor` or `ConnectivityManager`. The library does not watch the
+//! network itself: until the host reports a status it is `Unknown` and
+//! nothing is held back. Components with a `NetworkPolicy` wait while the
+//! status does not allow them to connect and pick up again once it does.
+
+use crate::client_identity::ClientIdentity;
+use crate::error::TemplateResult;
+use crate::pinning::PinSet;
+use crate::proxy::{Proxy, ProxyConfig};
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::watch;
+
+#[cfg(feature = "http")]
+use crate::error::TemplateError;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+#[cfg(feature = "http")]
+use crate::http;
+
+/// Settings for the HTTP stack, set with `set_network_config`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NetworkConfig {
+    /// Pinned public keys by host name, as `sha256/<base64>` hashes of a
+    /// certificate's SubjectPublicKeyInfo (see `certificate_pin`)
+    ///
+    /// `*.example.com` applies to every subdomain of `example.com`, but not
+    /// to `example.com` itself. Connections to a pinned host fail with
+    /// `PinningFailure` unless its certificate chain carries one of the
+    /// host's keys, so list a backup key to be able to rotate the current one.
+    pub pinned_public_keys: HashMap<String, Vec<String>>,
+    /// Proxy for every connection, or `None` to connect directly (reqwest
+    /// then honors the `HTTP_PROXY` and `HTTPS_PROXY` environment variables)
+    pub proxy: Option<ProxyConfig>,
+    /// Certificate and key presented to any server that asks for a client
+    /// certificate, for backends that require mutual TLS
+    pub client_identity: Option<Arc<ClientIdentity>>,
+}
+
+/// How the device is connected, as reported with `set_network_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetworkStatus {
+    /// No status reported yet; treated as connected without restrictions
+    #[default]
+    Unknown,
+    /// No usable connection
+    Offline,
+    Wifi,
+    Ethernet,
+    /// A metered connection: cellular data, or a hotspot sharing it
+    Cellular,
+}
+
+/// Which connections a component may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetworkPolicy {
+    /// Any connection
+    #[default]
+    Any,
+    /// Only connections that are not metered, e.g. for large downloads
+    Unmetered,
+}
+
+impl NetworkPolicy {
+    /// Whether the policy lets a component connect while the network is in `status`
+    pub(crate) fn allows(self, status: NetworkStatus) -> bool {
+        match status {
+            NetworkStatus::Offline => false,
+            NetworkStatus::Cellular => self == Self::Any,
+            NetworkStatus::Unknown | NetworkStatus::Wifi | NetworkStatus::Ethernet => true,
+        }
+    }
+}
+
+/// The settings in effect, with their pins parsed
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+struct NetworkState {
+    config: NetworkConfig,
+    pins: Arc<PinSet>,
+    proxy: Option<Arc<Proxy>>,
+}
+
+static NETWORK_STATE: RwLock<Option<NetworkState>> = RwLock::new(None);
+
+/// Latest status from the host; components subscribe to it to pause and resume
+static NETWORK_STATUS: OnceLock<watch::Sender<NetworkStatus>> = OnceLock::new();
+
+fn status_sender() -> &'static watch::Sender<NetworkStatus> {
+    NETWORK_STATUS.get_or_init(|| watch::Sender::new(NetworkStatus::Unknown))
+}
+
+/// Replaces the network settings
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If a pinned host has no pins, a
+///   host name or pin is malformed, or the proxy settings are invalid; the
+///   previous settings are kept
+pub fn set_network_config(config: NetworkConfig) -> TemplateResult<()> {
+    shield::guard("set_network_config", || {
+        let pins = PinSet::parse(&config.pinned_public_keys)?;
+        let proxy = config.proxy.as_ref().map(Proxy::parse).transpose()?;
+        log::info!(
+            "Network configuration updated: {} pinned hosts, proxy {}, client identity {}",
+            config.pinned_public_keys.len(),
+            match &config.proxy {
+                Some(proxy) => format!("{:?} {}:{}", proxy.kind, proxy.host, proxy.port),
+                None => "off".to_string(),
+            },
+            if config.client_identity.is_some() {
+                "set"
+            } else {
+                "off"
+            }
+        );
+        *NETWORK_STATE.write().unwrap() = Some(NetworkState {
+            config,
+            pins: Arc::new(pins),
+            proxy: proxy.map(Arc::new),
+        });
+        #[cfg(feature = "http")]
+        http::reset_client();
+        #[cfg(feature = "grpc")]
+        grpc::reset_channels();
+        Ok(())
+    })
+}
+
+/// Returns the network settings in effect
+pub fn get_network_config() -> NetworkConfig {
+    NETWORK_STATE
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|state| state.config.clone())
+        .unwrap_or_default()
+}
+
+/// Pins from the settings in effect
+#[cfg(feature = "http")]
+pub(crate) fn pins() -> Arc<PinSet> {
+    NETWORK_STATE
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|state| state.pins.clone())
+        .unwrap_or_default()
+}
+
+/// Proxy from the settings in effect
+#[cfg(feature = "http")]
+pub(crate) fn proxy() -> Option<Arc<Proxy>> {
+    NETWORK_STATE
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|state| state.proxy.clone())
+}
+
+/// Client identity from the settings in effect
+#[cfg(feature = "http")]
+pub(crate) fn client_identity() -> Option<Arc<ClientIdentity>> {
+    NETWORK_STATE
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|state| state.config.client_identity.clone())
+}
+
+/// Reports how the device is connected; call it whenever the platform's
+/// network monitor reports a change
+///
+/// Downloads, `Outbox` deliveries, and `RemoteLlmClient` requests whose
+/// `NetworkPolicy` does not allow the new status stop until it does: running
+/// downloads go back to the queue, and requests in flight fail with a
+/// `NetworkError`. When the network changes to a status a policy allows,
+/// queued work starts right away instead of waiting out its backoff.
+/// Reporting the status in effect again changes nothing.
+pub fn set_network_status(status: NetworkStatus) {
+    status_sender().send_if_modified(|current| {
+        if *current == status {
+            return false;
+        }
+        log::info!("Network status changed: {:?} -> {:?}", current, status);
+        *current = status;
+        true
+    });
+}
+
+/// Returns the status last reported with `set_network_status`
+pub fn get_network_status() -> NetworkStatus {
+    *status_sender().borrow()
+}
+
+/// Receiver notified of every status the host reports
+pub(crate) fn watch_status() -> watch::Receiver<NetworkStatus> {
+    status_sender().subscribe()
+}
+
+/// Runs `request` while `policy` allows the network status, failing with a
+/// `NetworkError` right away or as soon as it no longer does
+#[cfg(feature = "http")]
+pub(crate) async fn while_allowed<T>(
+    policy: NetworkPolicy,
+    url: String,
+    request: impl std::future::Future<Output = TemplateResult<T>>,
+) -> TemplateResult<T> {
+    tokio::select! {
+        biased;
+        error = disallowed(policy, &url) => Err(error),
+        result = request => result,
+    }
+}
+
+/// Resolves with a `NetworkError` once `policy` stops allowing the status;
+/// never resolves while it keeps allowing it
+#[cfg(feature = "http")]
+async fn disallowed(policy: NetworkPolicy, url: &str) -> TemplateError {
+    let mut receiver = watch_status();
+    loop {
+        let status = *receiver.borrow_and_update();
+        if !policy.allows(status) {
+            return unavailable(policy, status, url);
+        }
+        if receiver.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+fn unavailable(policy: NetworkPolicy, status: NetworkStatus, url: &str) -> TemplateError {
+    let message = match status {
+        NetworkStatus::Offline => "The device is offline".to_string(),
+        _ => format!(
+            "The {:?} network policy does not allow a {:?} connection",
+            policy, status
+        ),
+    };
+    TemplateError::network_error(url, None, message)
+}
@@ -0,0 +1,278 @@
+//! OpenTelemetry export of spans and metrics (`otel` feature)
+//!
+//! `enable_otel_export` sends the library's operation spans and call metrics
+//! to an OTLP/HTTP collector, so they show up in the app's existing
+//! OpenTelemetry pipeline. Without the `otel` feature the functions exist so
+//! the bindings stay the same, but enabling export fails.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "otel")]
+use crate::spans;
+#[cfg(feature = "otel")]
+use opentelemetry::metrics::{Counter, Histogram, MeterProvider as _};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span as _, TraceContextExt as _, Tracer as _, TracerProvider as _};
+#[cfg(feature = "otel")]
+use opentelemetry::{Context, KeyValue};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::{WithExportConfig as _, WithHttpConfig as _};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "otel")]
+use std::sync::RwLock;
+#[cfg(feature = "otel")]
+use std::time::Duration;
+
+/// Where and how to export telemetry
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtelConfig {
+    /// Base URL of the OTLP/HTTP collector, e.g. `https://otel.example.com:4318`;
+    /// `/v1/traces` and `/v1/metrics` are appended
+    pub endpoint: String,
+    /// `service.name` resource attribute, usually the app's name
+    pub service_name: String,
+    /// `os.name` resource attribute, e.g. `ios` or `android`
+    pub platform: String,
+    /// Extra HTTP headers sent with every export, e.g. an API key
+    pub headers: HashMap<String, String>,
+    /// How often metrics are exported, in milliseconds
+    pub metrics_interval_ms: u64,
+}
+
+/// Name of the instrumentation scope reported with spans and metrics
+#[cfg(feature = "otel")]
+const SCOPE: &str = env!("CARGO_PKG_NAME");
+
+#[cfg(feature = "otel")]
+struct Exporter {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+    tracer: Tracer,
+    calls: Counter<u64>,
+    errors: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+#[cfg(feature = "otel")]
+static EXPORTER: RwLock<Option<Exporter>> = RwLock::new(None);
+
+/// Whether spans and metrics are being exported; checked on every span
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Starts exporting spans and metrics to an OTLP/HTTP collector
+///
+/// Replaces any previous export configuration. Resources carry
+/// `service.name`, `os.name`, and the library's name and version.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the endpoint is not a valid URL,
+///   `metrics_interval_ms` is 0, or the library was built without the
+///   `otel` feature
+pub fn enable_otel_export(config: OtelConfig) -> TemplateResult<()> {
+    shield::guard("enable_otel_export", || {
+        if config.metrics_interval_ms == 0 {
+            return Err(TemplateError::invalid_input(
+                "metrics_interval_ms must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        #[cfg(feature = "otel")]
+        {
+            // The blocking HTTP client cannot be created on an async runtime thread
+            let exporter = std::thread::spawn(move || build_exporter(config))
+                .join()
+                .map_err(|_| {
+                    TemplateError::internal("enable_otel_export", "exporter setup panicked", None)
+                })??;
+            if let Some(previous) = EXPORTER.write().unwrap().replace(exporter) {
+                shutdown(previous);
+            }
+            EXPORTING.store(true, Ordering::Release);
+            spans::install();
+            Ok(())
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = config;
+            Err(TemplateError::invalid_input(
+                "OpenTelemetry export requires the `otel` feature".to_string(),
+                None,
+            ))
+        }
+    })
+}
+
+/// Flushes pending telemetry and stops exporting
+///
+/// Blocks until the flush finishes or times out. Does nothing if export is
+/// not enabled.
+pub fn disable_otel_export() {
+    EXPORTING.store(false, Ordering::Release);
+    #[cfg(feature = "otel")]
+    if let Some(exporter) = EXPORTER.write().unwrap().take() {
+        shutdown(exporter);
+    }
+}
+
+#[cfg(feature = "otel")]
+fn build_exporter(config: OtelConfig) -> TemplateResult<Exporter> {
+    let endpoint = config.endpoint.trim_end_matches('/');
+    let invalid = |e: opentelemetry_otlp::ExporterBuildError| {
+        TemplateError::invalid_input(format!("Invalid OTLP exporter setup: {}", e), None)
+    };
+    let resource = Resource::builder_empty()
+        .with_service_name(config.service_name)
+        .with_attributes([
+            KeyValue::new("os.name", config.platform),
+            KeyValue::new("library.name", env!("CARGO_PKG_NAME")),
+            KeyValue::new("library.version", env!("CARGO_PKG_VERSION")),
+        ])
+        .build();
+
+    let span_exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint))
+        .with_headers(config.headers.clone())
+        .build()
+        .map_err(invalid)?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_resource(resource.clone())
+        .with_batch_exporter(span_exporter)
+        .build();
+
+    let metric_exporter = opentelemetry_otlp::MetricExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/metrics", endpoint))
+        .with_headers(config.headers)
+        .build()
+        .map_err(invalid)?;
+    let reader = PeriodicReader::builder(metric_exporter)
+        .with_interval(Duration::from_millis(config.metrics_interval_ms))
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_resource(resource)
+        .with_reader(reader)
+        .build();
+
+    let meter = meter_provider.meter(SCOPE);
+    Ok(Exporter {
+        tracer: tracer_provider.tracer(SCOPE),
+        calls: meter
+            .u64_counter("template.calls")
+            .with_description("Completed calls of exported functions")
+            .build(),
+        errors: meter
+            .u64_counter("template.errors")
+            .with_description("Calls of exported functions that returned an error")
+            .build(),
+        duration: meter
+            .f64_histogram("template.call.duration")
+            .with_unit("ms")
+            .with_description("Latency of exported functions")
+            .build(),
+        tracer_provider,
+        meter_provider,
+    })
+}
+
+#[cfg(feature = "otel")]
+fn shutdown(exporter: Exporter) {
+    if let Err(e) = exporter.tracer_provider.shutdown() {
+        log::warn!("Failed to flush OpenTelemetry spans: {}", e);
+    }
+    if let Err(e) = exporter.meter_provider.shutdown() {
+        log::warn!("Failed to flush OpenTelemetry metrics: {}", e);
+    }
+}
+
+/// Whether spans should be created for export
+pub(crate) fn is_exporting() -> bool {
+    EXPORTING.load(Ordering::Acquire)
+}
+
+/// An OpenTelemetry span mirroring an open `tracing` span
+pub(crate) struct ExportedSpan {
+    #[cfg(feature = "otel")]
+    span: opentelemetry_sdk::trace::Span,
+}
+
+impl ExportedSpan {
+    /// Ends the span, queueing it for export
+    pub(crate) fn end(self) {
+        #[cfg(feature = "otel")]
+        {
+            let mut span = self.span;
+            span.end();
+        }
+    }
+}
+
+/// Starts an OpenTelemetry span for a newly opened `tracing` span
+pub(crate) fn start_span(
+    name: &str,
+    parent: Option<&ExportedSpan>,
+    operation_id: Option<u64>,
+) -> Option<ExportedSpan> {
+    #[cfg(feature = "otel")]
+    {
+        if !is_exporting() {
+            return None;
+        }
+        let exporter = EXPORTER.read().unwrap();
+        let exporter = exporter.as_ref()?;
+        let context = match parent {
+            Some(parent) => {
+                Context::new().with_remote_span_context(parent.span.span_context().clone())
+            }
+            None => Context::new(),
+        };
+        let mut builder = exporter.tracer.span_builder(name.to_string());
+        if let Some(operation_id) = operation_id {
+            builder = builder.with_attributes([KeyValue::new("operation_id", operation_id as i64)]);
+        }
+        Some(ExportedSpan {
+            span: exporter.tracer.build_with_context(builder, &context),
+        })
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = (name, parent, operation_id);
+        None
+    }
+}
+
+/// Records one completed call in the exported metrics
+pub(crate) fn record_call(operation: &str, duration_us: u64, error_code: Option<&str>) {
+    #[cfg(feature = "otel")]
+    {
+        if !is_exporting() {
+            return;
+        }
+        let exporter = EXPORTER.read().unwrap();
+        let Some(exporter) = exporter.as_ref() else {
+            return;
+        };
+        let operation = KeyValue::new("operation", operation.to_string());
+        exporter.calls.add(1, std::slice::from_ref(&operation));
+        exporter.duration.record(
+            duration_us as f64 / 1000.0,
+            std::slice::from_ref(&operation),
+        );
+        if let Some(code) = error_code {
+            exporter
+                .errors
+                .add(1, &[operation, KeyValue::new("code", code.to_string())]);
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (operation, duration_us, error_code);
+}
@@ -0,0 +1,703 @@
+//! Persisted queue of requests replayed until delivered (`http` feature)
+//!
+//! `Outbox` is for requests that must reach the server eventually but not
+//! right away, such as telemetry batches and sync payloads. `enqueue` writes
+//! the request to the persistence file before the first attempt, so nothing
+//! is lost if the app is killed or offline, and a background worker sends
+//! entries one at a time, oldest first. A failed attempt is retried with
+//! exponential backoff; errors that retrying cannot fix (a 4xx other than
+//! 408 and 429, a pinning failure) drop the entry instead. `retry_now`
+//! skips the remaining backoff. Nothing is sent while the outbox's
+//! `NetworkPolicy` does not allow the status reported with
+//! `set_network_status`, and every entry is retried as soon as the network
+//! changes to a status it allows. Without the `http` feature the types
+//! exist so the bindings stay the same, but creating an outbox fails.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::files;
+use crate::http::{HttpMethod, HttpRequest, HttpResponse};
+use crate::network::{self, NetworkPolicy, NetworkStatus};
+use crate::retry::RetryPolicy;
+use crate::runtime;
+use crate::shield;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Notify};
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::http;
+#[cfg(feature = "http")]
+use crate::interceptors;
+
+/// Limit for one attempt of a request that sets no `timeout_ms`, so a
+/// stalled connection cannot hold up the entries behind it
+pub const OUTBOX_DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Fraction of each backoff delay that is randomized, so clients that went
+/// offline together do not all retry at once
+const BACKOFF_JITTER: f64 = 0.2;
+
+/// Limits and backoff for an `Outbox`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxOptions {
+    /// Attempts per entry before it is dropped; 0 retries forever
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry
+    pub base_delay_ms: u64,
+    /// Upper bound for a single delay
+    pub max_delay_ms: u64,
+    /// Entries older than this are dropped instead of sent; `None` keeps them
+    pub max_age_ms: Option<u64>,
+    /// Entries kept at most; enqueueing past it drops the oldest
+    pub max_entries: u32,
+    /// Connections entries may be sent over; they wait for one otherwise
+    pub network_policy: NetworkPolicy,
+}
+
+impl Default for OutboxOptions {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            base_delay_ms: 1_000,
+            max_delay_ms: 300_000,
+            max_age_ms: None,
+            max_entries: 1_000,
+            network_policy: NetworkPolicy::Any,
+        }
+    }
+}
+
+/// Snapshot of a queued request, passed to listeners and returned by `Outbox::entries`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEntry {
+    /// Id assigned by the outbox
+    pub id: u64,
+    pub url: String,
+    pub method: HttpMethod,
+    /// Attempts made so far, including those before a restart
+    pub attempts: u32,
+    /// When the request was enqueued, in milliseconds since the Unix epoch
+    pub created_at_ms: u64,
+    /// Why the last attempt failed, if one did
+    pub last_error: Option<String>,
+}
+
+/// Why an entry left the outbox without being delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxDropReason {
+    /// The server or the library refused the request in a way retrying cannot fix
+    Rejected,
+    /// `OutboxOptions::max_attempts` attempts failed
+    AttemptsExhausted,
+    /// The entry outlived `OutboxOptions::max_age_ms`
+    Expired,
+    /// Newer entries pushed it past `OutboxOptions::max_entries`
+    Overflow,
+}
+
+/// Callback notified when entries leave the outbox, implemented by the host
+pub trait OutboxListener: Send + Sync {
+    /// Called with the 2xx response that delivered an entry; may be called from any thread
+    fn on_outbox_delivered(&self, entry: OutboxEntry, response: HttpResponse);
+    /// Called when an entry is given up on; `entry.last_error` says what failed
+    fn on_outbox_dropped(&self, entry: OutboxEntry, reason: OutboxDropReason);
+}
+
+/// Contents of the persistence file
+#[derive(Serialize, Deserialize)]
+struct PersistedOutbox {
+    /// Kept so ids are not reused once every entry was delivered
+    next_id: u64,
+    entries: Vec<PersistedEntry>,
+}
+
+/// Queued request as written to the persistence file
+#[derive(Serialize, Deserialize)]
+struct PersistedEntry {
+    id: u64,
+    url: String,
+    method: HttpMethod,
+    headers: HashMap<String, String>,
+    /// Base64, so binary payloads survive JSON
+    body: Option<String>,
+    timeout_ms: Option<u64>,
+    attempts: u32,
+    created_at_ms: u64,
+    last_error: Option<String>,
+}
+
+/// Requests persisted to disk and sent in the background until delivered
+pub struct Outbox {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    options: OutboxOptions,
+    persist_path: PathBuf,
+    state: Mutex<OutboxState>,
+    listener: Mutex<Option<Arc<dyn OutboxListener>>>,
+    /// Wakes the worker when entries are added or made due
+    wake: Notify,
+    /// Set when the `Outbox` is dropped, to stop the worker
+    closed: watch::Sender<bool>,
+    /// Number of entries queued
+    pending: watch::Sender<usize>,
+}
+
+#[derive(Default)]
+struct OutboxState {
+    next_id: u64,
+    entries: BTreeMap<u64, Entry>,
+    /// Entry being sent, which overflow never drops
+    in_flight: Option<u64>,
+}
+
+struct Entry {
+    info: OutboxEntry,
+    request: HttpRequest,
+    /// When the next attempt may start
+    due: Instant,
+}
+
+/// What the worker does next
+enum Step {
+    Send(u64, HttpRequest),
+    Wait(Option<Duration>),
+}
+
+impl Outbox {
+    /// Create an outbox, picking up entries left in `persist_path` by a previous one
+    ///
+    /// A relative `persist_path` is placed in the data directory set with
+    /// `set_app_directories`. Entries picked up are sent right away.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `max_entries` is 0,
+    ///   `base_delay_ms` exceeds `max_delay_ms`, or the library was built
+    ///   without the `http` feature
+    /// * `Err(TemplateError::IoError)` - If the persistence file cannot be read
+    /// * `Err(TemplateError::ParseError)` - If the persistence file is corrupt
+    pub fn new(persist_path: String, options: OutboxOptions) -> TemplateResult<Self> {
+        shield::guard("Outbox::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "Outbox requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            if options.max_entries == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_entries must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+            if options.base_delay_ms > options.max_delay_ms {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "base_delay_ms ({}) must not exceed max_delay_ms ({})",
+                        options.base_delay_ms, options.max_delay_ms
+                    ),
+                    None,
+                ));
+            }
+
+            let persist_path = directories::resolve(StorageCategory::Data, &persist_path);
+            let persisted = load(&persist_path)?;
+            let mut state = OutboxState {
+                next_id: persisted.next_id.max(1),
+                ..OutboxState::default()
+            };
+            let now = Instant::now();
+            for persisted in persisted.entries {
+                state.next_id = state.next_id.max(persisted.id + 1);
+                let body = match persisted.body {
+                    Some(encoded) => Some(
+                        BASE64
+                            .decode(encoded)
+                            .map_err(|e| TemplateError::parse_error("base64", e.to_string()))?,
+                    ),
+                    None => None,
+                };
+                state.entries.insert(
+                    persisted.id,
+                    Entry {
+                        info: OutboxEntry {
+                            id: persisted.id,
+                            url: persisted.url.clone(),
+                            method: persisted.method,
+                            attempts: persisted.attempts,
+                            created_at_ms: persisted.created_at_ms,
+                            last_error: persisted.last_error,
+                        },
+                        request: HttpRequest {
+                            url: persisted.url,
+                            method: persisted.method,
+                            headers: persisted.headers,
+                            body,
+                            timeout_ms: persisted.timeout_ms,
+                        },
+                        due: now,
+                    },
+                );
+            }
+
+            let inner = Arc::new(Inner {
+                options,
+                persist_path,
+                pending: watch::Sender::new(state.entries.len()),
+                state: Mutex::new(state),
+                listener: Mutex::new(None),
+                wake: Notify::new(),
+                closed: watch::Sender::new(false),
+            });
+            runtime::handle().spawn(Inner::run(inner.clone()));
+            Ok(Self { inner })
+        })
+    }
+
+    /// Registers the listener notified of delivered and dropped entries, replacing any previous one
+    pub fn set_listener(&self, listener: Box<dyn OutboxListener>) {
+        *self.inner.listener.lock().unwrap() = Some(Arc::from(listener));
+    }
+
+    /// Persists `request` and queues it for sending; returns its id
+    ///
+    /// Any status outside 2xx counts as a failed attempt. A request without
+    /// `timeout_ms` is given `OUTBOX_DEFAULT_TIMEOUT_MS` per attempt. If the
+    /// outbox is full, the oldest entry not being sent is dropped with
+    /// `OutboxDropReason::Overflow`.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid
+    /// * `Err(TemplateError::IoError)` - If the persistence file cannot be
+    ///   written; the request is not queued
+    pub fn enqueue(&self, request: HttpRequest) -> TemplateResult<u64> {
+        shield::guard("Outbox::enqueue", || {
+            // Reject what every attempt would fail on before queueing
+            #[cfg(feature = "http")]
+            let _ = http::build(&request)?;
+            let (id, overflowed) = {
+                let mut state = self.inner.state.lock().unwrap();
+                let id = state.next_id;
+                state.next_id += 1;
+                state.entries.insert(
+                    id,
+                    Entry {
+                        info: OutboxEntry {
+                            id,
+                            url: request.url.clone(),
+                            method: request.method,
+                            attempts: 0,
+                            created_at_ms: now_ms(),
+                            last_error: None,
+                        },
+                        request,
+                        due: Instant::now(),
+                    },
+                );
+                let mut overflowed = Vec::new();
+                while state.entries.len() > self.inner.options.max_entries as usize {
+                    let in_flight = state.in_flight;
+                    let Some(oldest) = state
+                        .entries
+                        .keys()
+                        .copied()
+                        .find(|&queued| Some(queued) != in_flight && queued != id)
+                    else {
+                        break;
+                    };
+                    overflowed.extend(state.entries.remove(&oldest));
+                }
+                if let Err(e) = self.inner.persist(&state) {
+                    state.entries.remove(&id);
+                    for entry in overflowed {
+                        state.entries.insert(entry.info.id, entry);
+                    }
+                    return Err(e);
+                }
+                (id, overflowed)
+            };
+            for entry in overflowed {
+                log::warn!("Outbox full, dropped entry {}", entry.info.id);
+                self.inner.dropped(entry.info, OutboxDropReason::Overflow);
+            }
+            self.inner.publish_pending();
+            self.inner.wake.notify_one();
+            Ok(id)
+        })
+    }
+
+    /// Snapshot of every queued entry, oldest first
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    /// Removes an entry without sending it or notifying the listener
+    ///
+    /// Returns `false` if no such entry is queued. An attempt already in
+    /// progress still completes, but is not reported.
+    pub fn remove(&self, id: u64) -> bool {
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            if state.entries.remove(&id).is_none() {
+                return false;
+            }
+            self.inner.save(&state);
+        }
+        self.inner.publish_pending();
+        true
+    }
+
+    /// Makes every entry due now instead of after its backoff delay
+    ///
+    /// `set_network_status` does this when connectivity returns. Attempt
+    /// counts are kept, so later failures still back off and count towards
+    /// `max_attempts`.
+    pub fn retry_now(&self) {
+        self.inner.retry_now();
+    }
+
+    /// Waits until every entry has been delivered, dropped, or removed (async)
+    ///
+    /// While offline this can take until the entries expire or run out of
+    /// attempts, so hosts usually race it against a timeout.
+    pub async fn wait_idle(&self) {
+        let mut receiver = self.inner.pending.subscribe();
+        let _ = receiver.wait_for(|pending| *pending == 0).await;
+    }
+}
+
+impl Drop for Outbox {
+    /// Stops the worker; queued entries stay persisted for the next outbox
+    fn drop(&mut self) {
+        self.inner.closed.send_replace(true);
+    }
+}
+
+impl Inner {
+    /// Sends due entries one at a time until the outbox is dropped
+    async fn run(inner: Arc<Self>) {
+        let mut closed = inner.closed.subscribe();
+        let mut status = network::watch_status();
+        let mut online = inner
+            .options
+            .network_policy
+            .allows(*status.borrow_and_update());
+        loop {
+            // An attempt cut short here leaves its entry persisted, so it is
+            // sent again by the next outbox: delivery is at least once
+            tokio::select! {
+                // The flag is only ever set, so any change means closed
+                _ = closed.changed() => return,
+                _ = inner.step(&mut status, &mut online) => {}
+            }
+        }
+    }
+
+    /// Sends the next due entry, or waits until one is due, is added, or
+    /// the network status changes
+    async fn step(&self, status: &mut watch::Receiver<NetworkStatus>, online: &mut bool) {
+        let delay = match self.next_step(*online) {
+            Step::Send(id, request) => {
+                let outcome = self.deliver(request).await;
+                self.finish(id, outcome);
+                return;
+            }
+            Step::Wait(delay) => delay,
+        };
+        let sleep = async {
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = self.wake.notified() => {}
+            _ = sleep => {}
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return std::future::pending().await;
+                }
+                // A new connection is worth trying at once, even if the
+                // previous one was allowed too
+                *online = self.options.network_policy.allows(*status.borrow_and_update());
+                if *online {
+                    log::info!("Network changed, retrying outbox entries");
+                    self.retry_now();
+                }
+            }
+        }
+    }
+
+    fn retry_now(&self) {
+        let now = Instant::now();
+        for entry in self.state.lock().unwrap().entries.values_mut() {
+            entry.due = now;
+        }
+        self.wake.notify_one();
+    }
+
+    /// The oldest due entry, marked in flight, or how long until one is due
+    /// (or, while `online` is false, until one expires); expired entries are
+    /// dropped on the way
+    fn next_step(&self, online: bool) -> Step {
+        let mut expired = Vec::new();
+        let step = {
+            let mut state = self.state.lock().unwrap();
+            let mut expires_in = None;
+            if let Some(max_age_ms) = self.options.max_age_ms {
+                let now_ms = now_ms();
+                let oldest_kept = now_ms.saturating_sub(max_age_ms);
+                let ids: Vec<u64> = state
+                    .entries
+                    .values()
+                    .filter(|entry| entry.info.created_at_ms < oldest_kept)
+                    .map(|entry| entry.info.id)
+                    .collect();
+                expired.extend(ids.iter().filter_map(|id| state.entries.remove(id)));
+                if !expired.is_empty() {
+                    self.save(&state);
+                }
+                expires_in = state
+                    .entries
+                    .values()
+                    .map(|entry| {
+                        entry
+                            .info
+                            .created_at_ms
+                            .saturating_add(max_age_ms)
+                            .saturating_sub(now_ms)
+                            .saturating_add(1)
+                    })
+                    .min()
+                    .map(Duration::from_millis);
+            }
+            let now = Instant::now();
+            let next = state
+                .entries
+                .values()
+                .min_by_key(|entry| (entry.due, entry.info.id))
+                .map(|entry| (entry.info.id, entry.due));
+            match next {
+                _ if !online => Step::Wait(expires_in),
+                Some((id, due)) if due <= now => {
+                    state.in_flight = Some(id);
+                    let mut request = state.entries[&id].request.clone();
+                    request.timeout_ms = request.timeout_ms.or(Some(OUTBOX_DEFAULT_TIMEOUT_MS));
+                    Step::Send(id, request)
+                }
+                // Wake up in time to drop the next entry that expires
+                Some((_, due)) => Step::Wait(Some(
+                    expires_in.map_or(due - now, |expires_in| expires_in.min(due - now)),
+                )),
+                None => Step::Wait(None),
+            }
+        };
+        if !expired.is_empty() {
+            for entry in expired {
+                self.dropped(entry.info, OutboxDropReason::Expired);
+            }
+            self.publish_pending();
+        }
+        step
+    }
+
+    /// Sends one attempt, repeated once with a refreshed token if the
+    /// server rejects the `AuthManager`'s token; a status outside 2xx is an
+    /// error
+    #[cfg(feature = "http")]
+    async fn deliver(&self, mut request: HttpRequest) -> TemplateResult<HttpResponse> {
+        let sent = auth::authorize(&request.url, &mut request.headers).await?;
+        let mut response = self.send(&request).await?;
+        if let Some(sent) = sent.filter(|_| response.status == 401) {
+            if auth::reauthorize(&request.url, &sent, &mut request.headers).await? {
+                response = self.send(&request).await?;
+            }
+        }
+        if !response.is_success() {
+            return Err(TemplateError::network_error(
+                &request.url,
+                Some(response.status),
+                format!("Server responded with {}", response.status),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Sends `request`, abandoned if the network policy stops allowing the
+    /// connection
+    #[cfg(feature = "http")]
+    async fn send(&self, request: &HttpRequest) -> TemplateResult<HttpResponse> {
+        let intercepted = interceptors::intercept(request).await;
+        let request = intercepted.as_ref().unwrap_or(request);
+        let builder = http::build(request)?;
+        let send = async {
+            let response = builder
+                .send()
+                .await
+                .map_err(|e| http::request_error(&request.url, request.timeout_ms, &e))?;
+            http::read(response, request.timeout_ms).await
+        };
+        let response =
+            network::while_allowed(self.options.network_policy, request.url.clone(), send).await?;
+        interceptors::observe(request, &response).await;
+        Ok(response)
+    }
+
+    #[cfg(not(feature = "http"))]
+    async fn deliver(&self, _request: HttpRequest) -> TemplateResult<HttpResponse> {
+        unreachable!("Outbox::new fails without the `http` feature")
+    }
+
+    /// Records an attempt's outcome: removes the entry once delivered or
+    /// given up on, and otherwise schedules the next attempt
+    fn finish(&self, id: u64, outcome: TemplateResult<HttpResponse>) {
+        let (info, outcome) = {
+            let mut state = self.state.lock().unwrap();
+            state.in_flight = None;
+            let Some(entry) = state.entries.get_mut(&id) else {
+                // Removed while in flight
+                return;
+            };
+            entry.info.attempts += 1;
+            let attempts = entry.info.attempts;
+            let outcome = match outcome {
+                Ok(response) => Ok(response),
+                Err(e) => {
+                    log::warn!("Outbox entry {} attempt {} failed: {}", id, attempts, e);
+                    entry.info.last_error = Some(e.to_string());
+                    let max_attempts = self.options.max_attempts;
+                    let reason = if !e.is_retryable() {
+                        OutboxDropReason::Rejected
+                    } else if max_attempts != 0 && attempts >= max_attempts {
+                        OutboxDropReason::AttemptsExhausted
+                    } else {
+                        entry.due = Instant::now() + Duration::from_millis(self.backoff(attempts));
+                        self.save(&state);
+                        return;
+                    };
+                    events::publish(LibraryEvent::BackgroundError {
+                        operation: "outbox".to_string(),
+                        kind: e.kind(),
+                        error_message: e.to_string(),
+                    });
+                    Err(reason)
+                }
+            };
+            let info = state.entries.remove(&id).map(|entry| entry.info);
+            self.save(&state);
+            (info, outcome)
+        };
+        if let Some(info) = info {
+            match outcome {
+                Ok(response) => {
+                    let listener = self.listener.lock().unwrap().clone();
+                    if let Some(listener) = listener {
+                        listener.on_outbox_delivered(info, response);
+                    }
+                }
+                Err(reason) => self.dropped(info, reason),
+            }
+        }
+        self.publish_pending();
+    }
+
+    /// Delay before the attempt after `attempts` failed ones
+    fn backoff(&self, attempts: u32) -> u64 {
+        RetryPolicy {
+            base_delay_ms: self.options.base_delay_ms,
+            max_delay_ms: self.options.max_delay_ms,
+            jitter: BACKOFF_JITTER,
+            ..RetryPolicy::default()
+        }
+        .delay(attempts)
+    }
+
+    fn dropped(&self, entry: OutboxEntry, reason: OutboxDropReason) {
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener.on_outbox_dropped(entry, reason);
+        }
+    }
+
+    /// Updates the entry count `wait_idle` watches; called after listeners
+    /// so they have heard about every entry once it reaches 0
+    fn publish_pending(&self) {
+        let pending = self.state.lock().unwrap().entries.len();
+        self.pending.send_replace(pending);
+    }
+
+    /// Like `persist`, for changes already made that the worker cannot undo
+    fn save(&self, state: &OutboxState) {
+        if let Err(e) = self.persist(state) {
+            log::warn!("Could not save outbox: {}", e);
+        }
+    }
+
+    /// Saves every queued entry, replacing the file atomically
+    fn persist(&self, state: &OutboxState) -> TemplateResult<()> {
+        let entries: Vec<PersistedEntry> = state
+            .entries
+            .values()
+            .map(|entry| PersistedEntry {
+                id: entry.info.id,
+                url: entry.request.url.clone(),
+                method: entry.request.method,
+                headers: entry.request.headers.clone(),
+                body: entry.request.body.as_ref().map(|body| BASE64.encode(body)),
+                timeout_ms: entry.request.timeout_ms,
+                attempts: entry.info.attempts,
+                created_at_ms: entry.info.created_at_ms,
+                last_error: entry.info.last_error.clone(),
+            })
+            .collect();
+        let persisted = PersistedOutbox {
+            next_id: state.next_id,
+            entries,
+        };
+        let json =
+            serde_json::to_vec_pretty(&persisted).map_err(|e| TemplateError::json_error(&e))?;
+        files::write_atomic(&self.persist_path, &json)
+            .map_err(|e| TemplateError::io_error(&self.persist_path, &e))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Reads what a previous outbox saved; a missing file means no entries
+fn load(path: &Path) -> TemplateResult<PersistedOutbox> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(PersistedOutbox {
+                next_id: 1,
+                entries: Vec::new(),
+            })
+        }
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    serde_json::from_slice(&json).map_err(|e| TemplateError::json_error(&e))
+}
@@ -0,0 +1,336 @@
+//! Public key pinning for TLS connections to chosen hosts
+//!
+//! A pin is the SHA-256 hash of a certificate's SubjectPublicKeyInfo,
+//! written `sha256/<base64>` as in HPKP and OkHttp, so it survives
+//! certificate renewals that keep the key. Pins only add a check: the
+//! chain must still validate against the bundled roots, and then at least
+//! one certificate in it must carry a pinned key, or the handshake fails
+//! with `PinningFailure`. `certificate_pin` computes the pin of a DER
+//! certificate, for hosts that ship the certificate rather than its hash.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[cfg(feature = "http")]
+use crate::client_identity::ClientIdentity;
+#[cfg(feature = "http")]
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+#[cfg(feature = "http")]
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+#[cfg(feature = "http")]
+use std::sync::Arc;
+
+/// Prefix of a pin in its written form
+const PIN_PREFIX: &str = "sha256/";
+
+/// DER tag of a SEQUENCE
+const DER_SEQUENCE: u8 = 0x30;
+
+/// DER tag of the explicit `[0]` version field of a TBSCertificate
+const DER_VERSION: u8 = 0xa0;
+
+/// SHA-256 hash of a SubjectPublicKeyInfo
+type PinHash = [u8; 32];
+
+/// Returns the pin (`sha256/<base64>`) of a DER-encoded X.509 certificate
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If `certificate_der` is not a DER certificate
+pub fn certificate_pin(certificate_der: Vec<u8>) -> TemplateResult<String> {
+    shield::guard("certificate_pin", || {
+        let hash = spki_hash(&certificate_der).ok_or_else(|| {
+            TemplateError::invalid_input("Not a DER-encoded X.509 certificate".to_string(), None)
+        })?;
+        Ok(format_pin(&hash))
+    })
+}
+
+/// Pins by host, parsed from `NetworkConfig::pinned_public_keys`
+#[derive(Debug, Default)]
+pub(crate) struct PinSet {
+    /// Pins for hosts matched exactly
+    hosts: HashMap<String, Vec<PinHash>>,
+    /// Pins for every subdomain of a domain, from `*.domain` entries
+    subdomains: HashMap<String, Vec<PinHash>>,
+}
+
+impl PinSet {
+    /// Parses and checks every host name and pin
+    pub(crate) fn parse(pins: &HashMap<String, Vec<String>>) -> TemplateResult<Self> {
+        let mut set = Self::default();
+        for (host, host_pins) in pins {
+            let name = normalize_host(host);
+            let (table, name) = match name.strip_prefix("*.") {
+                Some(domain) => (&mut set.subdomains, domain),
+                None => (&mut set.hosts, name.as_str()),
+            };
+            if name.is_empty() || name.contains(['*', '/', ' ']) {
+                return Err(TemplateError::invalid_input(
+                    format!("Invalid pinned host name: '{}'", host),
+                    None,
+                ));
+            }
+            if host_pins.is_empty() {
+                return Err(TemplateError::invalid_input(
+                    format!("No pins given for host '{}'", host),
+                    None,
+                ));
+            }
+            let hashes = table.entry(name.to_string()).or_default();
+            for pin in host_pins {
+                hashes.push(parse_pin(pin).ok_or_else(|| {
+                    TemplateError::invalid_input(
+                        format!(
+                            "Invalid pin for host '{}': '{}' is not a base64 SHA-256 hash",
+                            host, pin
+                        ),
+                        None,
+                    )
+                })?);
+            }
+        }
+        Ok(set)
+    }
+
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hosts.is_empty() && self.subdomains.is_empty()
+    }
+
+    /// Pins that apply to `host`, from its own entry and from `*.` entries
+    /// for any of its parent domains; empty if the host is not pinned
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    fn for_host(&self, host: &str) -> Vec<PinHash> {
+        let host = normalize_host(host);
+        let mut pins = self.hosts.get(&host).cloned().unwrap_or_default();
+        let mut domain = host.as_str();
+        while let Some((_, parent)) = domain.split_once('.') {
+            if let Some(parent_pins) = self.subdomains.get(parent) {
+                pins.extend_from_slice(parent_pins);
+            }
+            domain = parent;
+        }
+        pins
+    }
+}
+
+/// Host names are matched without case or a trailing dot
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Decodes a pin, with or without its `sha256/` prefix
+fn parse_pin(pin: &str) -> Option<PinHash> {
+    let encoded = pin.trim();
+    let encoded = encoded.strip_prefix(PIN_PREFIX).unwrap_or(encoded);
+    BASE64.decode(encoded).ok()?.try_into().ok()
+}
+
+fn format_pin(hash: &PinHash) -> String {
+    format!("{}{}", PIN_PREFIX, BASE64.encode(hash))
+}
+
+/// SHA-256 of the SubjectPublicKeyInfo of a DER certificate
+pub(crate) fn spki_hash(certificate: &[u8]) -> Option<PinHash> {
+    Some(Sha256::digest(subject_public_key_info(certificate)?).into())
+}
+
+/// The SubjectPublicKeyInfo element of a DER certificate, tag and length included
+///
+/// Certificate ::= SEQUENCE { tbsCertificate SEQUENCE { [0] version OPTIONAL,
+/// serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo, ... }, ... }
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let (tag, _, certificate, _) = der_element(certificate)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, _, mut fields, _) = der_element(certificate)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    if fields.first() == Some(&DER_VERSION) {
+        fields = der_element(fields)?.3;
+    }
+    for _ in 0..5 {
+        fields = der_element(fields)?.3;
+    }
+    let (tag, spki, _, _) = der_element(fields)?;
+    (tag == DER_SEQUENCE).then_some(spki)
+}
+
+/// A DER element's tag, the whole element, its contents, and the bytes after it
+type DerElement<'a> = (u8, &'a [u8], &'a [u8], &'a [u8]);
+
+/// Splits the first DER element off `der`
+fn der_element(der: &[u8]) -> Option<DerElement<'_>> {
+    let (&tag, rest) = der.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count]
+            .iter()
+            .fold(0usize, |length, &byte| length << 8 | byte as usize);
+        (length, &rest[count..])
+    };
+    if rest.len() < length {
+        return None;
+    }
+    let header = der.len() - rest.len();
+    Some((
+        tag,
+        &der[..header + length],
+        &rest[..length],
+        &rest[length..],
+    ))
+}
+
+/// Handshake failure raised by `PinningVerifier`, turned into `PinningFailure`
+#[cfg(feature = "http")]
+#[derive(Debug, thiserror::Error)]
+#[error("the certificate chain has none of the pinned keys (presented: {presented})")]
+struct PinMismatch {
+    host: String,
+    /// Pins of the certificates the server sent
+    presented: String,
+}
+
+/// Certificate verifier that validates the chain against the bundled roots,
+/// then requires a pinned key for pinned hosts
+#[cfg(feature = "http")]
+#[derive(Debug)]
+struct PinningVerifier {
+    roots: Arc<rustls::client::WebPkiServerVerifier>,
+    pins: Arc<PinSet>,
+}
+
+#[cfg(feature = "http")]
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.roots.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let host = server_name.to_str();
+        let pins = self.pins.for_host(&host);
+        if pins.is_empty() {
+            return Ok(verified);
+        }
+        let presented: Vec<PinHash> = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(|certificate| spki_hash(certificate))
+            .collect();
+        if presented.iter().any(|hash| pins.contains(hash)) {
+            return Ok(verified);
+        }
+        log::warn!("Certificate pinning failed for {}", host);
+        Err(rustls::Error::Other(rustls::OtherError(Arc::new(
+            PinMismatch {
+                host: host.into_owned(),
+                presented: presented
+                    .iter()
+                    .map(format_pin)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            },
+        ))))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.roots
+            .verify_tls12_signature(message, certificate, signature)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.roots
+            .verify_tls13_signature(message, certificate, signature)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.roots.supported_verify_schemes()
+    }
+}
+
+/// TLS settings trusting the bundled roots and enforcing `pins`, presenting
+/// `identity` to servers that ask for a client certificate
+#[cfg(feature = "http")]
+pub(crate) fn tls_config(
+    pins: Arc<PinSet>,
+    identity: Option<Arc<ClientIdentity>>,
+) -> rustls::ClientConfig {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let roots = rustls::client::WebPkiServerVerifier::builder_with_provider(
+        Arc::new(roots),
+        provider.clone(),
+    )
+    .build()
+    .expect("bundled root certificates are valid");
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("default TLS versions are supported")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinningVerifier { roots, pins }));
+    match identity {
+        Some(identity) => builder.with_client_cert_resolver(identity.resolver()),
+        None => builder.with_no_client_auth(),
+    }
+}
+
+/// The `PinningFailure` behind `error`, if a pin mismatch caused it
+#[cfg(feature = "http")]
+pub(crate) fn failure(error: &(dyn std::error::Error + 'static)) -> Option<TemplateError> {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        // `io::Error::source` skips the error it wraps, so look at it directly
+        let wrapped = error
+            .downcast_ref::<std::io::Error>()
+            .and_then(std::io::Error::get_ref)
+            .map(|inner| inner as &(dyn std::error::Error + 'static));
+        for candidate in std::iter::once(error).chain(wrapped) {
+            let mismatch = match candidate.downcast_ref::<rustls::Error>() {
+                Some(rustls::Error::Other(other)) => other.0.downcast_ref::<PinMismatch>(),
+                _ => candidate.downcast_ref::<PinMismatch>(),
+            };
+            if let Some(mismatch) = mismatch {
+                return Some(TemplateError::pinning_failure(
+                    &mismatch.host,
+                    mismatch.to_string(),
+                ));
+            }
+        }
+        next = error.source();
+    }
+    None
+}
@@ -0,0 +1,282 @@
+//! Typed app settings with defaults and change notifications
+//!
+//! `Preferences` wraps a `KvStore` with what settings screens need on top of
+//! storage: defaults registered at startup, typed getters that fall back to
+//! them, and listeners told which key changed. Changes made through any
+//! `Preferences` in this process notify its listeners on the calling thread.
+//! Changes written to the file by another process (an app extension or a
+//! second `Preferences` on the same path) are noticed by a watcher thread,
+//! started with the first listener, which polls the file every
+//! `PREFERENCES_POLL_INTERVAL_MS` and notifies from that thread.
+
+use crate::error::TemplateResult;
+use crate::kv_store::{self, KvStore, KvValue};
+use crate::shield;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the file is checked for changes by other processes
+pub const PREFERENCES_POLL_INTERVAL_MS: u64 = 500;
+
+/// Callback notified when a preference changes
+pub trait PreferencesListener: Send + Sync {
+    /// `key` was set, removed, or changed by another process
+    fn on_preference_changed(&self, key: String);
+}
+
+/// Typed settings persisted to one file, with defaults and listeners
+pub struct Preferences {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    store: KvStore,
+    defaults: Mutex<HashMap<String, KvValue>>,
+    next_id: AtomicU64,
+    listeners: Mutex<BTreeMap<u64, Arc<dyn PreferencesListener>>>,
+    watching: AtomicBool,
+}
+
+impl Preferences {
+    /// Opens the preferences stored at `path`, creating the file on the first write
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file or its directory cannot be read or created
+    /// * `Err(TemplateError::ParseError)` - If the file is not a store written by this library
+    pub fn open(path: String) -> TemplateResult<Self> {
+        shield::guard("Preferences::open", || {
+            Ok(Self {
+                inner: Arc::new(Inner {
+                    store: KvStore::open(path)?,
+                    defaults: Mutex::new(HashMap::new()),
+                    next_id: AtomicU64::new(1),
+                    listeners: Mutex::new(BTreeMap::new()),
+                    watching: AtomicBool::new(false),
+                }),
+            })
+        })
+    }
+
+    /// Sets the values returned for keys that have never been set
+    ///
+    /// Defaults are not persisted; register them on every launch. Registering
+    /// a default again replaces it. Listeners are not notified.
+    pub fn register_defaults(&self, defaults: HashMap<String, KvValue>) {
+        self.inner.defaults.lock().unwrap().extend(defaults);
+    }
+
+    /// The value set for `key`, or else its registered default
+    pub fn get(&self, key: String) -> Option<KvValue> {
+        self.inner
+            .store
+            .get(key.clone())
+            .or_else(|| self.inner.defaults.lock().unwrap().get(&key).cloned())
+    }
+
+    /// Whether `key` has a value set, as opposed to only a default
+    pub fn contains(&self, key: String) -> bool {
+        self.inner.store.get(key).is_some()
+    }
+
+    /// Stores `value` under `key`, notifying listeners if it changed
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub fn set(&self, key: String, value: KvValue) -> TemplateResult<()> {
+        shield::guard("Preferences::set", || self.inner.change(key, Some(value)))
+    }
+
+    /// Removes the value set for `key`, so its default applies again
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub fn remove(&self, key: String) -> TemplateResult<()> {
+        shield::guard("Preferences::remove", || self.inner.change(key, None))
+    }
+
+    /// Keys with a value set, in sorted order; keys with only a default are not included
+    pub fn keys(&self) -> Vec<String> {
+        self.inner.store.keys()
+    }
+
+    /// The string for `key`, if set or registered as a default
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a string
+    pub fn get_string(&self, key: String) -> TemplateResult<Option<String>> {
+        shield::guard("Preferences::get_string", || {
+            kv_store::typed(&key, self.get(key.clone()), "string", |value| match value {
+                KvValue::String { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a string under `key`
+    pub fn set_string(&self, key: String, value: String) -> TemplateResult<()> {
+        self.set(key, KvValue::String { value })
+    }
+
+    /// The integer for `key`, if set or registered as a default
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not an integer
+    pub fn get_int(&self, key: String) -> TemplateResult<Option<i64>> {
+        shield::guard("Preferences::get_int", || {
+            kv_store::typed(&key, self.get(key.clone()), "int", |value| match value {
+                KvValue::Int { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores an integer under `key`
+    pub fn set_int(&self, key: String, value: i64) -> TemplateResult<()> {
+        self.set(key, KvValue::Int { value })
+    }
+
+    /// The boolean for `key`, if set or registered as a default
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not a boolean
+    pub fn get_bool(&self, key: String) -> TemplateResult<Option<bool>> {
+        shield::guard("Preferences::get_bool", || {
+            kv_store::typed(&key, self.get(key.clone()), "bool", |value| match value {
+                KvValue::Bool { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores a boolean under `key`
+    pub fn set_bool(&self, key: String, value: bool) -> TemplateResult<()> {
+        self.set(key, KvValue::Bool { value })
+    }
+
+    /// The bytes for `key`, if set or registered as a default
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the value is not bytes
+    pub fn get_bytes(&self, key: String) -> TemplateResult<Option<Vec<u8>>> {
+        shield::guard("Preferences::get_bytes", || {
+            kv_store::typed(&key, self.get(key.clone()), "bytes", |value| match value {
+                KvValue::Bytes { value } => Some(value),
+                _ => None,
+            })
+        })
+    }
+
+    /// Stores bytes under `key`
+    pub fn set_bytes(&self, key: String, value: Vec<u8>) -> TemplateResult<()> {
+        self.set(key, KvValue::Bytes { value })
+    }
+
+    /// Registers a listener and returns its id
+    ///
+    /// The first listener starts the thread watching for changes by other processes.
+    pub fn add_listener(&self, listener: Box<dyn PreferencesListener>) -> u64 {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .listeners
+            .lock()
+            .unwrap()
+            .insert(id, Arc::from(listener));
+        if !self.inner.watching.swap(true, Ordering::AcqRel) {
+            start_watcher(Arc::downgrade(&self.inner));
+        }
+        id
+    }
+
+    /// Removes a listener; returns `false` if the id is unknown
+    pub fn remove_listener(&self, listener_id: u64) -> bool {
+        self.inner
+            .listeners
+            .lock()
+            .unwrap()
+            .remove(&listener_id)
+            .is_some()
+    }
+
+    /// Rereads the file now, notifying listeners of keys changed by other
+    /// processes, and returns those keys
+    ///
+    /// Useful when the app returns to the foreground, without waiting for
+    /// the watcher.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be read
+    /// * `Err(TemplateError::ParseError)` - If the file is not a store written by this library
+    pub fn reload(&self) -> TemplateResult<Vec<String>> {
+        shield::guard("Preferences::reload", || self.inner.reload())
+    }
+}
+
+impl Inner {
+    fn change(&self, key: String, value: Option<KvValue>) -> TemplateResult<()> {
+        let previous = self.store.swap(key.clone(), value.clone())?;
+        if previous != value {
+            self.notify(&[key]);
+        }
+        Ok(())
+    }
+
+    fn reload(&self) -> TemplateResult<Vec<String>> {
+        let changed = self.store.reload()?;
+        self.notify(&changed);
+        Ok(changed)
+    }
+
+    /// Calls every listener for each key, outside of any lock
+    fn notify(&self, keys: &[String]) {
+        if keys.is_empty() {
+            return;
+        }
+        let listeners: Vec<_> = self.listeners.lock().unwrap().values().cloned().collect();
+        for key in keys {
+            for listener in &listeners {
+                listener.on_preference_changed(key.clone());
+            }
+        }
+    }
+
+    /// Modification time and size of the file, to detect outside writes
+    fn file_signature(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(self.store.path()).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+/// Polls the file until the `Preferences` is dropped
+fn start_watcher(inner: Weak<Inner>) {
+    let mut last = inner.upgrade().and_then(|inner| inner.file_signature());
+    let spawned = thread::Builder::new()
+        .name("template-preferences".to_string())
+        .spawn(move || loop {
+            thread::sleep(Duration::from_millis(PREFERENCES_POLL_INTERVAL_MS));
+            let Some(inner) = inner.upgrade() else {
+                break;
+            };
+            let current = inner.file_signature();
+            if current != last {
+                last = current;
+                if let Err(e) = inner.reload() {
+                    log::warn!("Could not reload preferences: {}", e);
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("Could not start preferences watcher: {}", e);
+    }
+}
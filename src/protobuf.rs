@@ -0,0 +1,597 @@
+//! Protocol Buffers encoding of records and errors
+//!
+//! `proto/records.proto` is the schema backend teams compile for their own
+//! language; `encode_protobuf` writes a `template.v1.Record` and
+//! `error_to_protobuf` a `template.v1.Error`, so library output can be
+//! consumed without a hand-written JSON contract. The messages below are
+//! written out the way `prost-build` generates them from that file, which
+//! keeps `protoc` out of the build; the two must change together.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::models::{DiscoveredModel, ModelFormat, ModelMetadata};
+use crate::records::Record;
+use crate::remote_llm::{ChatMessage, ChatRole, GenerationParams, GenerationResult};
+use crate::sanitize::SanitizationReport;
+use crate::shield;
+use crate::template::EchoResult;
+use crate::timing::CallTiming;
+use crate::transform::TextTransform;
+use crate::warnings::{Warning, WarningKind};
+use prost::Message;
+
+/// Encodes `record` as a `template.v1.Record` message
+pub fn encode_protobuf(record: Record) -> Vec<u8> {
+    schema::Record::from(record).encode_to_vec()
+}
+
+/// Decodes a `template.v1.Record` message, e.g. one written by `encode_protobuf`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `data` is not a record, or holds
+///   an enum value this release does not know
+pub fn decode_protobuf(data: Vec<u8>) -> TemplateResult<Record> {
+    shield::guard("decode_protobuf", || {
+        schema::Record::decode(data.as_slice())
+            .map_err(|e| parse_error(e.to_string()))?
+            .try_into()
+    })
+}
+
+/// Encodes `error` as a `template.v1.Error` message, with the same `code`,
+/// `variant`, `message`, and `fields` as `error_to_json`
+pub fn error_to_protobuf(error: TemplateError) -> Vec<u8> {
+    let value: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap_or_default();
+    let text = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+    schema::Error {
+        code: text("code"),
+        variant: text("variant"),
+        message: text("message"),
+        fields_json: value["fields"].to_string(),
+    }
+    .encode_to_vec()
+}
+
+fn parse_error(message: String) -> TemplateError {
+    TemplateError::parse_error("Protobuf", message)
+}
+
+/// Field that must be set, or an enum value other than 0
+fn required<T>(value: Option<T>, field: &str) -> TemplateResult<T> {
+    value.ok_or_else(|| parse_error(format!("Missing or unknown value for {}", field)))
+}
+
+/// Messages of `proto/records.proto`
+mod schema {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EchoResult {
+        #[prost(string, tag = "1")]
+        pub text: String,
+        #[prost(uint32, tag = "2")]
+        pub length: u32,
+        #[prost(uint64, tag = "3")]
+        pub timestamp: u64,
+        #[prost(string, optional, tag = "4")]
+        pub hash: Option<String>,
+        #[prost(enumeration = "TextTransform", repeated, tag = "5")]
+        pub transforms_applied: Vec<i32>,
+        #[prost(message, optional, tag = "6")]
+        pub sanitization: Option<SanitizationReport>,
+        #[prost(message, repeated, tag = "7")]
+        pub warnings: Vec<Warning>,
+        #[prost(message, optional, tag = "8")]
+        pub timing: Option<CallTiming>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum TextTransform {
+        Unspecified = 0,
+        Trim = 1,
+        Lowercase = 2,
+        Uppercase = 3,
+        CollapseWhitespace = 4,
+        Reverse = 5,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct SanitizationReport {
+        #[prost(uint32, tag = "1")]
+        pub control_chars_removed: u32,
+        #[prost(bool, tag = "2")]
+        pub bom_removed: bool,
+        #[prost(uint32, tag = "3")]
+        pub line_endings_normalized: u32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum WarningKind {
+        Unspecified = 0,
+        InputSanitized = 1,
+        InputNormalized = 2,
+        InputNotNormalized = 3,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Warning {
+        #[prost(enumeration = "WarningKind", tag = "1")]
+        pub kind: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct CallTiming {
+        #[prost(uint64, tag = "1")]
+        pub queue_wait_us: u64,
+        #[prost(uint64, tag = "2")]
+        pub validation_us: u64,
+        #[prost(uint64, tag = "3")]
+        pub compute_us: u64,
+        #[prost(uint64, tag = "4")]
+        pub total_us: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ModelMetadata {
+        #[prost(uint32, tag = "1")]
+        pub version: u32,
+        #[prost(uint64, tag = "2")]
+        pub tensor_count: u64,
+        #[prost(uint64, tag = "3")]
+        pub metadata_count: u64,
+        #[prost(string, optional, tag = "4")]
+        pub architecture: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub name: Option<String>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ModelFormat {
+        Unspecified = 0,
+        Gguf = 1,
+        Safetensors = 2,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DiscoveredModel {
+        #[prost(string, tag = "1")]
+        pub path: String,
+        #[prost(string, tag = "2")]
+        pub file_name: String,
+        #[prost(uint64, tag = "3")]
+        pub size_bytes: u64,
+        #[prost(enumeration = "ModelFormat", tag = "4")]
+        pub format: i32,
+        #[prost(bool, tag = "5")]
+        pub is_valid: bool,
+        #[prost(message, optional, tag = "6")]
+        pub metadata: Option<ModelMetadata>,
+        #[prost(string, optional, tag = "7")]
+        pub error_message: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerationParams {
+        #[prost(uint32, optional, tag = "1")]
+        pub max_tokens: Option<u32>,
+        #[prost(double, optional, tag = "2")]
+        pub temperature: Option<f64>,
+        #[prost(double, optional, tag = "3")]
+        pub top_p: Option<f64>,
+        #[prost(string, repeated, tag = "4")]
+        pub stop: Vec<String>,
+        #[prost(uint64, optional, tag = "5")]
+        pub seed: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerationResult {
+        #[prost(string, tag = "1")]
+        pub text: String,
+        #[prost(string, optional, tag = "2")]
+        pub finish_reason: Option<String>,
+        #[prost(string, tag = "3")]
+        pub model: String,
+        #[prost(uint32, optional, tag = "4")]
+        pub prompt_tokens: Option<u32>,
+        #[prost(uint32, optional, tag = "5")]
+        pub completion_tokens: Option<u32>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ChatRole {
+        Unspecified = 0,
+        System = 1,
+        User = 2,
+        Assistant = 3,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ChatMessage {
+        #[prost(enumeration = "ChatRole", tag = "1")]
+        pub role: i32,
+        #[prost(string, tag = "2")]
+        pub content: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ChatMessages {
+        #[prost(message, repeated, tag = "1")]
+        pub messages: Vec<ChatMessage>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Bytes {
+        #[prost(string, tag = "1")]
+        pub kind: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Record {
+        #[prost(oneof = "record::Record", tags = "1, 2, 3, 4, 5, 6, 7")]
+        pub record: Option<record::Record>,
+    }
+
+    pub mod record {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Record {
+            #[prost(message, tag = "1")]
+            EchoResult(super::EchoResult),
+            #[prost(message, tag = "2")]
+            ModelMetadata(super::ModelMetadata),
+            #[prost(message, tag = "3")]
+            DiscoveredModel(super::DiscoveredModel),
+            #[prost(message, tag = "4")]
+            GenerationParams(super::GenerationParams),
+            #[prost(message, tag = "5")]
+            GenerationResult(super::GenerationResult),
+            #[prost(message, tag = "6")]
+            ChatMessages(super::ChatMessages),
+            #[prost(message, tag = "7")]
+            Bytes(super::Bytes),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Error {
+        #[prost(string, tag = "1")]
+        pub code: String,
+        #[prost(string, tag = "2")]
+        pub variant: String,
+        #[prost(string, tag = "3")]
+        pub message: String,
+        #[prost(string, tag = "4")]
+        pub fields_json: String,
+    }
+}
+
+impl From<Record> for schema::Record {
+    fn from(record: Record) -> Self {
+        use schema::record::Record as Message;
+        let record = match record {
+            Record::EchoResult { result } => Message::EchoResult(result.into()),
+            Record::ModelMetadata { metadata } => Message::ModelMetadata(metadata.into()),
+            Record::DiscoveredModel { model } => Message::DiscoveredModel(model.into()),
+            Record::GenerationParams { params } => Message::GenerationParams(params.into()),
+            Record::GenerationResult { result } => Message::GenerationResult(result.into()),
+            Record::ChatMessages { messages } => Message::ChatMessages(schema::ChatMessages {
+                messages: messages.into_iter().map(Into::into).collect(),
+            }),
+            Record::Bytes { kind, data } => Message::Bytes(schema::Bytes { kind, data }),
+        };
+        Self {
+            record: Some(record),
+        }
+    }
+}
+
+impl TryFrom<schema::Record> for Record {
+    type Error = TemplateError;
+
+    fn try_from(record: schema::Record) -> TemplateResult<Self> {
+        use schema::record::Record as Message;
+        Ok(match required(record.record, "Record.record")? {
+            Message::EchoResult(result) => Self::EchoResult {
+                result: result.try_into()?,
+            },
+            Message::ModelMetadata(metadata) => Self::ModelMetadata {
+                metadata: metadata.into(),
+            },
+            Message::DiscoveredModel(model) => Self::DiscoveredModel {
+                model: model.try_into()?,
+            },
+            Message::GenerationParams(params) => Self::GenerationParams {
+                params: params.into(),
+            },
+            Message::GenerationResult(result) => Self::GenerationResult {
+                result: result.into(),
+            },
+            Message::ChatMessages(chat) => Self::ChatMessages {
+                messages: chat
+                    .messages
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<TemplateResult<_>>()?,
+            },
+            Message::Bytes(bytes) => Self::Bytes {
+                kind: bytes.kind,
+                data: bytes.data,
+            },
+        })
+    }
+}
+
+impl From<EchoResult> for schema::EchoResult {
+    fn from(result: EchoResult) -> Self {
+        Self {
+            text: result.text,
+            length: result.length,
+            timestamp: result.timestamp,
+            hash: result.hash,
+            transforms_applied: result
+                .transforms_applied
+                .into_iter()
+                .map(|transform| schema::TextTransform::from(transform) as i32)
+                .collect(),
+            sanitization: result
+                .sanitization
+                .map(|report| schema::SanitizationReport {
+                    control_chars_removed: report.control_chars_removed,
+                    bom_removed: report.bom_removed,
+                    line_endings_normalized: report.line_endings_normalized,
+                }),
+            warnings: result
+                .warnings
+                .into_iter()
+                .map(|warning| schema::Warning {
+                    kind: schema::WarningKind::from(warning.kind) as i32,
+                    message: warning.message,
+                })
+                .collect(),
+            timing: result.timing.map(|timing| schema::CallTiming {
+                queue_wait_us: timing.queue_wait_us,
+                validation_us: timing.validation_us,
+                compute_us: timing.compute_us,
+                total_us: timing.total_us,
+            }),
+        }
+    }
+}
+
+impl TryFrom<schema::EchoResult> for EchoResult {
+    type Error = TemplateError;
+
+    fn try_from(result: schema::EchoResult) -> TemplateResult<Self> {
+        let transforms_applied = result
+            .transforms_applied
+            .into_iter()
+            .map(|transform| {
+                let transform = schema::TextTransform::try_from(transform).ok();
+                required(
+                    transform.and_then(from_transform),
+                    "EchoResult.transforms_applied",
+                )
+            })
+            .collect::<TemplateResult<_>>()?;
+        let warnings = result
+            .warnings
+            .into_iter()
+            .map(|warning| {
+                let kind = schema::WarningKind::try_from(warning.kind).ok();
+                Ok(Warning {
+                    kind: required(kind.and_then(from_warning_kind), "Warning.kind")?,
+                    message: warning.message,
+                })
+            })
+            .collect::<TemplateResult<_>>()?;
+        Ok(Self {
+            text: result.text,
+            length: result.length,
+            timestamp: result.timestamp,
+            hash: result.hash,
+            transforms_applied,
+            sanitization: result.sanitization.map(|report| SanitizationReport {
+                control_chars_removed: report.control_chars_removed,
+                bom_removed: report.bom_removed,
+                line_endings_normalized: report.line_endings_normalized,
+            }),
+            warnings,
+            timing: result.timing.map(|timing| CallTiming {
+                queue_wait_us: timing.queue_wait_us,
+                validation_us: timing.validation_us,
+                compute_us: timing.compute_us,
+                total_us: timing.total_us,
+            }),
+        })
+    }
+}
+
+impl From<TextTransform> for schema::TextTransform {
+    fn from(transform: TextTransform) -> Self {
+        match transform {
+            TextTransform::Trim => Self::Trim,
+            TextTransform::Lowercase => Self::Lowercase,
+            TextTransform::Uppercase => Self::Uppercase,
+            TextTransform::CollapseWhitespace => Self::CollapseWhitespace,
+            TextTransform::Reverse => Self::Reverse,
+        }
+    }
+}
+
+fn from_transform(transform: schema::TextTransform) -> Option<TextTransform> {
+    match transform {
+        schema::TextTransform::Unspecified => None,
+        schema::TextTransform::Trim => Some(TextTransform::Trim),
+        schema::TextTransform::Lowercase => Some(TextTransform::Lowercase),
+        schema::TextTransform::Uppercase => Some(TextTransform::Uppercase),
+        schema::TextTransform::CollapseWhitespace => Some(TextTransform::CollapseWhitespace),
+        schema::TextTransform::Reverse => Some(TextTransform::Reverse),
+    }
+}
+
+impl From<WarningKind> for schema::WarningKind {
+    fn from(kind: WarningKind) -> Self {
+        match kind {
+            WarningKind::InputSanitized => Self::InputSanitized,
+            WarningKind::InputNormalized => Self::InputNormalized,
+            WarningKind::InputNotNormalized => Self::InputNotNormalized,
+        }
+    }
+}
+
+fn from_warning_kind(kind: schema::WarningKind) -> Option<WarningKind> {
+    match kind {
+        schema::WarningKind::Unspecified => None,
+        schema::WarningKind::InputSanitized => Some(WarningKind::InputSanitized),
+        schema::WarningKind::InputNormalized => Some(WarningKind::InputNormalized),
+        schema::WarningKind::InputNotNormalized => Some(WarningKind::InputNotNormalized),
+    }
+}
+
+impl From<ModelMetadata> for schema::ModelMetadata {
+    fn from(metadata: ModelMetadata) -> Self {
+        Self {
+            version: metadata.version,
+            tensor_count: metadata.tensor_count,
+            metadata_count: metadata.metadata_count,
+            architecture: metadata.architecture,
+            name: metadata.name,
+        }
+    }
+}
+
+impl From<schema::ModelMetadata> for ModelMetadata {
+    fn from(metadata: schema::ModelMetadata) -> Self {
+        Self {
+            version: metadata.version,
+            tensor_count: metadata.tensor_count,
+            metadata_count: metadata.metadata_count,
+            architecture: metadata.architecture,
+            name: metadata.name,
+        }
+    }
+}
+
+impl From<DiscoveredModel> for schema::DiscoveredModel {
+    fn from(model: DiscoveredModel) -> Self {
+        let format = match model.format {
+            ModelFormat::Gguf => schema::ModelFormat::Gguf,
+            ModelFormat::Safetensors => schema::ModelFormat::Safetensors,
+        };
+        Self {
+            path: model.path,
+            file_name: model.file_name,
+            size_bytes: model.size_bytes,
+            format: format as i32,
+            is_valid: model.is_valid,
+            metadata: model.metadata.map(Into::into),
+            error_message: model.error_message,
+        }
+    }
+}
+
+impl TryFrom<schema::DiscoveredModel> for DiscoveredModel {
+    type Error = TemplateError;
+
+    fn try_from(model: schema::DiscoveredModel) -> TemplateResult<Self> {
+        let format = match schema::ModelFormat::try_from(model.format) {
+            Ok(schema::ModelFormat::Gguf) => Some(ModelFormat::Gguf),
+            Ok(schema::ModelFormat::Safetensors) => Some(ModelFormat::Safetensors),
+            _ => None,
+        };
+        Ok(Self {
+            path: model.path,
+            file_name: model.file_name,
+            size_bytes: model.size_bytes,
+            format: required(format, "DiscoveredModel.format")?,
+            is_valid: model.is_valid,
+            metadata: model.metadata.map(Into::into),
+            error_message: model.error_message,
+        })
+    }
+}
+
+impl From<GenerationParams> for schema::GenerationParams {
+    fn from(params: GenerationParams) -> Self {
+        Self {
+            max_tokens: params.max_tokens,
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stop: params.stop,
+            seed: params.seed,
+        }
+    }
+}
+
+impl From<schema::GenerationParams> for GenerationParams {
+    fn from(params: schema::GenerationParams) -> Self {
+        Self {
+            max_tokens: params.max_tokens,
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stop: params.stop,
+            seed: params.seed,
+        }
+    }
+}
+
+impl From<GenerationResult> for schema::GenerationResult {
+    fn from(result: GenerationResult) -> Self {
+        Self {
+            text: result.text,
+            finish_reason: result.finish_reason,
+            model: result.model,
+            prompt_tokens: result.prompt_tokens,
+            completion_tokens: result.completion_tokens,
+        }
+    }
+}
+
+impl From<schema::GenerationResult> for GenerationResult {
+    fn from(result: schema::GenerationResult) -> Self {
+        Self {
+            text: result.text,
+            finish_reason: result.finish_reason,
+            model: result.model,
+            prompt_tokens: result.prompt_tokens,
+            completion_tokens: result.completion_tokens,
+        }
+    }
+}
+
+impl From<ChatMessage> for schema::ChatMessage {
+    fn from(message: ChatMessage) -> Self {
+        let role = match message.role {
+            ChatRole::System => schema::ChatRole::System,
+            ChatRole::User => schema::ChatRole::User,
+            ChatRole::Assistant => schema::ChatRole::Assistant,
+        };
+        Self {
+            role: role as i32,
+            content: message.content,
+        }
+    }
+}
+
+impl TryFrom<schema::ChatMessage> for ChatMessage {
+    type Error = TemplateError;
+
+    fn try_from(message: schema::ChatMessage) -> TemplateResult<Self> {
+        let role = match schema::ChatRole::try_from(message.role) {
+            Ok(schema::ChatRole::System) => Some(ChatRole::System),
+            Ok(schema::ChatRole::User) => Some(ChatRole::User),
+            Ok(schema::ChatRole::Assistant) => Some(ChatRole::Assistant),
+            _ => None,
+        };
+        Ok(Self {
+            role: required(role, "ChatMessage.role")?,
+            content: message.content,
+        })
+    }
+}
@@ -0,0 +1,442 @@
+//! Proxy server for every connection the library opens
+//!
+//! With `NetworkConfig::proxy` set, connections go through that proxy
+//! except to hosts on its bypass list. reqwest speaks to HTTP, HTTPS, and
+//! SOCKS5 proxies for requests, downloads, and uploads; WebSocket
+//! connections are tunnelled here, with `CONNECT` through HTTP proxies and
+//! the SOCKS5 handshake through SOCKS5 proxies, and cannot use HTTPS
+//! proxies. Host names are resolved by SOCKS5 proxies, since corporate
+//! networks often cannot resolve them locally.
+
+use crate::error::{TemplateError, TemplateResult};
+use std::net::IpAddr;
+
+#[cfg(feature = "http")]
+use base64::engine::general_purpose::STANDARD as BASE64;
+#[cfg(feature = "http")]
+use base64::Engine;
+#[cfg(feature = "http")]
+use std::sync::Arc;
+#[cfg(feature = "http")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "http")]
+use tokio::net::TcpStream;
+
+/// Longest CONNECT response head accepted from a proxy
+#[cfg(feature = "http")]
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+/// Protocol spoken to the proxy server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProxyKind {
+    /// Plain HTTP proxy; HTTPS traffic is tunnelled with `CONNECT`
+    #[default]
+    Http,
+    /// HTTP proxy reached over TLS
+    Https,
+    /// SOCKS5 proxy, which also resolves host names
+    Socks5,
+}
+
+/// Proxy server settings, part of `NetworkConfig`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// User name for proxy authentication (Basic for HTTP(S), RFC 1929 for SOCKS5)
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts reached without the proxy: `example.com` (also matching its
+    /// subdomains), `*.example.com`, IP addresses, CIDR ranges such as
+    /// `10.0.0.0/8`, or `*` for every host
+    pub bypass: Vec<String>,
+}
+
+/// One entry of the bypass list
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BypassRule {
+    All,
+    /// A domain and its subdomains
+    Domain(String),
+    Address(IpAddr),
+    Network(IpAddr, u8),
+}
+
+/// A checked `ProxyConfig` with its bypass list parsed
+#[derive(Debug)]
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub(crate) struct Proxy {
+    config: ProxyConfig,
+    bypass: Vec<BypassRule>,
+}
+
+impl Proxy {
+    /// Checks `config` and parses its bypass list
+    pub(crate) fn parse(config: &ProxyConfig) -> TemplateResult<Self> {
+        let host = config.host.trim();
+        if host.is_empty() || host.contains(|c: char| c.is_whitespace() || "/@?#".contains(c)) {
+            return Err(TemplateError::invalid_input(
+                format!("Invalid proxy host: '{}'", config.host),
+                None,
+            ));
+        }
+        if config.port == 0 {
+            return Err(TemplateError::invalid_input(
+                "Proxy port must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        if config.password.is_some() && config.username.is_none() {
+            return Err(TemplateError::invalid_input(
+                "Proxy password given without a username".to_string(),
+                None,
+            ));
+        }
+        let bypass = config
+            .bypass
+            .iter()
+            .map(|entry| {
+                parse_bypass_rule(entry).ok_or_else(|| {
+                    TemplateError::invalid_input(
+                        format!("Invalid proxy bypass entry: '{}'", entry),
+                        None,
+                    )
+                })
+            })
+            .collect::<TemplateResult<_>>()?;
+        let proxy = Self {
+            config: ProxyConfig {
+                host: host.to_string(),
+                ..config.clone()
+            },
+            bypass,
+        };
+        #[cfg(feature = "http")]
+        proxy.url()?;
+        Ok(proxy)
+    }
+
+    /// Whether connections to `host` skip the proxy
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    pub(crate) fn bypasses(&self, host: &str) -> bool {
+        let host = normalize_host(host);
+        let address = host.parse::<IpAddr>().ok();
+        self.bypass.iter().any(|rule| match (rule, address) {
+            (BypassRule::All, _) => true,
+            (BypassRule::Address(rule), Some(address)) => *rule == address,
+            (BypassRule::Network(network, prefix), Some(address)) => {
+                in_network(address, *network, *prefix)
+            }
+            (BypassRule::Domain(domain), None) => {
+                host == *domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            }
+            _ => false,
+        })
+    }
+
+    /// The proxy as a URL without credentials, for error messages
+    #[cfg(feature = "http")]
+    fn address(&self) -> String {
+        let scheme = match self.config.kind {
+            ProxyKind::Http => "http",
+            ProxyKind::Https => "https",
+            ProxyKind::Socks5 => "socks5h",
+        };
+        let host = &self.config.host;
+        if host.parse::<std::net::Ipv6Addr>().is_ok() {
+            format!("{}://[{}]:{}", scheme, host, self.config.port)
+        } else {
+            format!("{}://{}:{}", scheme, host, self.config.port)
+        }
+    }
+
+    /// The proxy as a URL with its credentials, as reqwest takes it
+    #[cfg(feature = "http")]
+    fn url(&self) -> TemplateResult<reqwest::Url> {
+        let invalid =
+            || TemplateError::invalid_input(format!("Invalid proxy: '{}'", self.address()), None);
+        let mut url = reqwest::Url::parse(&self.address()).map_err(|_| invalid())?;
+        if let Some(username) = &self.config.username {
+            url.set_username(username).map_err(|_| invalid())?;
+            url.set_password(self.config.password.as_deref())
+                .map_err(|_| invalid())?;
+        }
+        Ok(url)
+    }
+
+    /// A reqwest proxy that sends every request through this one, except
+    /// those to bypassed hosts
+    #[cfg(feature = "http")]
+    pub(crate) fn reqwest_proxy(self: &Arc<Self>) -> reqwest::Proxy {
+        let url = self.url().expect("proxy URL checked by Proxy::parse");
+        let proxy = self.clone();
+        reqwest::Proxy::custom(move |target| {
+            let bypassed = target.host_str().is_some_and(|host| proxy.bypasses(host));
+            (!bypassed).then(|| url.clone())
+        })
+    }
+
+    /// Opens a TCP connection to `host:port` through the proxy
+    ///
+    /// HTTPS proxies are not supported here, as the tunnel itself would
+    /// need TLS.
+    #[cfg(feature = "http")]
+    pub(crate) async fn tunnel(&self, host: &str, port: u16) -> TemplateResult<TcpStream> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.config.kind == ProxyKind::Https {
+            return Err(self.error(
+                None,
+                "Tunnelling through an HTTPS proxy is not supported".to_string(),
+            ));
+        }
+        let mut stream = TcpStream::connect((self.config.host.as_str(), self.config.port))
+            .await
+            .map_err(|e| self.error(None, format!("Cannot reach proxy: {}", e)))?;
+        let result = match self.config.kind {
+            ProxyKind::Socks5 => self.socks5_connect(&mut stream, host, port).await,
+            _ => self.http_connect(&mut stream, host, port).await,
+        };
+        result.map_err(|e| match e {
+            ProxyFailure::Refused(status, message) => self.error(status, message),
+            ProxyFailure::Io(e) => self.error(None, format!("Proxy connection failed: {}", e)),
+        })?;
+        Ok(stream)
+    }
+
+    #[cfg(feature = "http")]
+    async fn http_connect(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+    ) -> Result<(), ProxyFailure> {
+        let authority = match host.parse::<std::net::Ipv6Addr>() {
+            Ok(_) => format!("[{}]:{}", host, port),
+            Err(_) => format!("{}:{}", host, port),
+        };
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some(username) = &self.config.username {
+            let credentials = format!(
+                "{}:{}",
+                username,
+                self.config.password.as_deref().unwrap_or_default()
+            );
+            request.push_str(&format!(
+                "Proxy-Authorization: Basic {}\r\n",
+                BASE64.encode(credentials)
+            ));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // Read byte by byte so nothing after the response head, which
+        // belongs to the tunnel, is consumed
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            if head.len() >= MAX_CONNECT_RESPONSE {
+                return Err(ProxyFailure::Refused(
+                    None,
+                    "Proxy response head too long".to_string(),
+                ));
+            }
+            head.push(stream.read_u8().await?);
+        }
+        let head = String::from_utf8_lossy(&head);
+        let status_line = head.lines().next().unwrap_or_default();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok());
+        match status {
+            Some(200..=299) => Ok(()),
+            _ => Err(ProxyFailure::Refused(
+                status,
+                format!(
+                    "Proxy refused tunnel to {}: {}",
+                    authority,
+                    status_line.trim()
+                ),
+            )),
+        }
+    }
+
+    /// RFC 1928 CONNECT, with RFC 1929 username/password authentication
+    /// when a username is set
+    #[cfg(feature = "http")]
+    async fn socks5_connect(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+    ) -> Result<(), ProxyFailure> {
+        const VERSION: u8 = 5;
+        const NO_AUTHENTICATION: u8 = 0;
+        const USERNAME_PASSWORD: u8 = 2;
+        const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+        let refused = |message: String| Err(ProxyFailure::Refused(None, message));
+
+        let greeting: &[u8] = match self.config.username {
+            Some(_) => &[VERSION, 2, NO_AUTHENTICATION, USERNAME_PASSWORD],
+            None => &[VERSION, 1, NO_AUTHENTICATION],
+        };
+        stream.write_all(greeting).await?;
+        let mut choice = [0u8; 2];
+        stream.read_exact(&mut choice).await?;
+        match choice {
+            [VERSION, NO_AUTHENTICATION] => {}
+            [VERSION, USERNAME_PASSWORD] => {
+                let username = self.config.username.as_deref().unwrap_or_default();
+                let password = self.config.password.as_deref().unwrap_or_default();
+                if username.len() > 255 || password.len() > 255 {
+                    return refused("SOCKS5 credentials longer than 255 bytes".to_string());
+                }
+                let mut request = vec![1, username.len() as u8];
+                request.extend_from_slice(username.as_bytes());
+                request.push(password.len() as u8);
+                request.extend_from_slice(password.as_bytes());
+                stream.write_all(&request).await?;
+                let mut reply = [0u8; 2];
+                stream.read_exact(&mut reply).await?;
+                if reply[1] != 0 {
+                    return refused("SOCKS5 proxy rejected the credentials".to_string());
+                }
+            }
+            [VERSION, NO_ACCEPTABLE_METHOD] => {
+                return refused(
+                    "SOCKS5 proxy accepts none of the offered authentication methods".to_string(),
+                );
+            }
+            _ => return refused("Not a SOCKS5 proxy".to_string()),
+        }
+
+        let mut request = vec![VERSION, 1, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(address)) => {
+                request.push(1);
+                request.extend_from_slice(&address.octets());
+            }
+            Ok(IpAddr::V6(address)) => {
+                request.push(4);
+                request.extend_from_slice(&address.octets());
+            }
+            Err(_) if host.len() <= 255 => {
+                request.push(3);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+            }
+            Err(_) => return refused(format!("Host name too long for SOCKS5: '{}'", host)),
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            return refused(format!(
+                "SOCKS5 proxy refused connection to {}:{}: {}",
+                host,
+                port,
+                socks5_reply_message(reply[1])
+            ));
+        }
+        let bound_address_length = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => stream.read_u8().await? as usize,
+            _ => return refused("Malformed SOCKS5 reply".to_string()),
+        };
+        let mut bound = vec![0u8; bound_address_length + 2];
+        stream.read_exact(&mut bound).await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    fn error(&self, status_code: Option<u16>, message: String) -> TemplateError {
+        TemplateError::network_error(&self.address(), status_code, message)
+    }
+}
+
+/// Why a tunnel could not be opened
+#[cfg(feature = "http")]
+enum ProxyFailure {
+    /// The proxy answered but refused, with the HTTP status if it sent one
+    Refused(Option<u16>, String),
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "http")]
+impl From<std::io::Error> for ProxyFailure {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// RFC 1928 reply codes
+#[cfg(feature = "http")]
+fn socks5_reply_message(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// Host names are matched without case, brackets, or a trailing dot
+fn normalize_host(host: &str) -> String {
+    host.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+fn parse_bypass_rule(entry: &str) -> Option<BypassRule> {
+    let entry = entry.trim();
+    if entry == "*" {
+        return Some(BypassRule::All);
+    }
+    if let Some((address, prefix)) = entry.split_once('/') {
+        let address = normalize_host(address).parse::<IpAddr>().ok()?;
+        let prefix = prefix.parse::<u8>().ok()?;
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        return (prefix <= bits).then_some(BypassRule::Network(address, prefix));
+    }
+    let host = normalize_host(entry);
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Some(BypassRule::Address(address));
+    }
+    let domain = host
+        .strip_prefix("*.")
+        .or_else(|| host.strip_prefix('.'))
+        .unwrap_or(&host);
+    let valid = !domain.is_empty()
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    valid.then(|| BypassRule::Domain(domain.to_string()))
+}
+
+/// Whether `address` is in `network/prefix`
+fn in_network(address: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (address, network) {
+        (IpAddr::V4(address), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(address) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(address), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(address) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
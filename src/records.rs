@@ -0,0 +1,120 @@
+//! Binary interchange of the library's records
+//!
+//! A `Record` wraps one of the library's results, or an app-defined payload
+//! in `Record::Bytes`, so a single pair of functions encodes them all and a
+//! backend can tell what it received. `encode_msgpack` writes MessagePack,
+//! a fraction of the size of the same record as JSON, for apps that sync
+//! results to a server. `encode_protobuf` writes the same records as
+//! Protocol Buffers instead, and `encode_cbor` as CBOR for device-to-cloud
+//! pipelines built on it, such as COSE-signed payloads; CBOR needs the
+//! `cbor` feature, and without it both CBOR functions fail.
+//!
+//! In MessagePack each record is a map with a `type` key naming the
+//! variant in `snake_case` (`"echo_result"`) beside the variant's fields,
+//! and each record type is a map keyed by field name, like the JSON from
+//! `echo_result_to_json`. Byte payloads are MessagePack `bin` values.
+//! CBOR uses the same layout, with text-string keys and byte strings.
+//! Decoders in any language can read either without a schema.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::models::{DiscoveredModel, ModelMetadata};
+use crate::remote_llm::{ChatMessage, GenerationParams, GenerationResult};
+use crate::shield;
+use crate::template::EchoResult;
+use serde::{Deserialize, Serialize};
+
+/// One of the library's records, tagged with its type for binary encoding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    EchoResult {
+        result: EchoResult,
+    },
+    ModelMetadata {
+        metadata: ModelMetadata,
+    },
+    DiscoveredModel {
+        model: DiscoveredModel,
+    },
+    GenerationParams {
+        params: GenerationParams,
+    },
+    GenerationResult {
+        result: GenerationResult,
+    },
+    ChatMessages {
+        messages: Vec<ChatMessage>,
+    },
+    /// App-defined payload; `kind` says how to read `data`
+    Bytes {
+        kind: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+/// Encodes `record` as MessagePack
+pub fn encode_msgpack(record: Record) -> TemplateResult<Vec<u8>> {
+    shield::guard("encode_msgpack", || {
+        Ok(rmp_serde::to_vec_named(&record).expect("records always serialize"))
+    })
+}
+
+/// Decodes a record encoded with `encode_msgpack`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `data` is not a MessagePack record
+pub fn decode_msgpack(data: Vec<u8>) -> TemplateResult<Record> {
+    shield::guard("decode_msgpack", || {
+        rmp_serde::from_slice(&data)
+            .map_err(|e| TemplateError::parse_error("MessagePack", e.to_string()))
+    })
+}
+
+/// Encodes `record` as CBOR
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the library was built without the `cbor` feature
+pub fn encode_cbor(record: Record) -> TemplateResult<Vec<u8>> {
+    shield::guard("encode_cbor", || {
+        #[cfg(feature = "cbor")]
+        {
+            let mut data = Vec::new();
+            ciborium::into_writer(&record, &mut data).expect("records always serialize");
+            Ok(data)
+        }
+        #[cfg(not(feature = "cbor"))]
+        {
+            let _ = record;
+            Err(requires_cbor())
+        }
+    })
+}
+
+/// Decodes a record encoded with `encode_cbor`
+///
+/// # Returns
+///
+/// * `Err(TemplateError::ParseError)` - If `data` is not a CBOR record
+/// * `Err(TemplateError::InvalidInput)` - If the library was built without the `cbor` feature
+pub fn decode_cbor(data: Vec<u8>) -> TemplateResult<Record> {
+    shield::guard("decode_cbor", || {
+        #[cfg(feature = "cbor")]
+        {
+            ciborium::from_reader(data.as_slice())
+                .map_err(|e| TemplateError::parse_error("CBOR", e.to_string()))
+        }
+        #[cfg(not(feature = "cbor"))]
+        {
+            let _ = data;
+            Err(requires_cbor())
+        }
+    })
+}
+
+#[cfg(not(feature = "cbor"))]
+fn requires_cbor() -> TemplateError {
+    TemplateError::invalid_input("CBOR requires the `cbor` feature".to_string(), None)
+}
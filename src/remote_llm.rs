@@ -0,0 +1,417 @@
+//! Remote text generation over the OpenAI chat-completions API (`http` feature)
+//!
+//! `RemoteLlmClient` sends a conversation to any server speaking
+//! `POST {base_url}/chat/completions` (OpenAI, llama.cpp's server, vLLM,
+//! Ollama, LM Studio) and returns the reply, either whole or streamed token
+//! by token through a `GenerationListener`. Conversations are plain
+//! `ChatMessage`s and sampling settings are `GenerationParams`, so an app
+//! that cannot run a model on the device can send the same request to a
+//! server instead. Requests fail with a `NetworkError` while the config's
+//! `NetworkPolicy` does not allow the status reported with
+//! `set_network_status`, including requests in flight when it changes, so
+//! the app can fall back to a local model. Without the `http` feature the
+//! types exist so the bindings stay the same, but creating a client fails.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::network::NetworkPolicy;
+use crate::shield;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+use crate::http::{self, HttpMethod, HttpRequest};
+#[cfg(feature = "http")]
+use crate::interceptors;
+#[cfg(feature = "http")]
+use crate::network;
+#[cfg(feature = "http")]
+use crate::sse;
+
+/// Operation name used in errors
+#[cfg(feature = "http")]
+const OPERATION: &str = "remote_llm";
+
+/// Data of the event that ends an OpenAI stream
+#[cfg(feature = "http")]
+const STREAM_DONE: &str = "[DONE]";
+
+/// Who wrote a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+/// One message of a conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+/// Sampling settings for a generation; unset values use the server's defaults
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    /// Most tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Sequences that end the generation when produced
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Seed for reproducible sampling, where the server supports it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// Generated reply and token usage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerationResult {
+    pub text: String,
+    /// Why generation stopped, as reported by the server (e.g. `stop`, `length`)
+    pub finish_reason: Option<String>,
+    /// Model that produced the reply, as reported by the server
+    pub model: String,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+}
+
+/// Callback receiving generated text as it is produced, implemented by the host
+pub trait GenerationListener: Send + Sync {
+    /// Called on a runtime thread with each new piece of text, in order
+    fn on_token(&self, token: String);
+}
+
+/// Server, model, and credentials for a `RemoteLlmClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteLlmConfig {
+    /// API root the `/chat/completions` path is appended to, e.g. `https://api.openai.com/v1`
+    pub base_url: String,
+    pub model: String,
+    /// Sent as `Authorization: Bearer <api_key>`
+    pub api_key: Option<String>,
+    /// Extra headers for every request, e.g. an organization id
+    pub headers: HashMap<String, String>,
+    /// Limit for a whole request, including a streamed reply
+    pub timeout_ms: Option<u64>,
+    /// Connections requests may use, e.g. `Unmetered` to keep generation off cellular data
+    pub network_policy: NetworkPolicy,
+}
+
+/// Client for an OpenAI-compatible chat-completions server
+pub struct RemoteLlmClient {
+    config: RemoteLlmConfig,
+    /// `base_url` with `/chat/completions` appended
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    endpoint: String,
+}
+
+/// Body of a chat-completions request
+#[cfg(feature = "http")]
+#[derive(Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    #[serde(flatten)]
+    params: &'a GenerationParams,
+}
+
+/// Body of a chat-completions response, or one streamed chunk of it
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct CompletionResponse {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct Choice {
+    /// Whole reply, in a non-streamed response
+    message: Option<Content>,
+    /// Next piece of the reply, in a streamed chunk
+    delta: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct Content {
+    content: Option<String>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+/// Error body returned by OpenAI-compatible servers
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[cfg(feature = "http")]
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl RemoteLlmClient {
+    /// Create a client; nothing is sent until `chat` or `chat_stream`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `base_url` or a header is
+    ///   invalid, `model` is empty, or the library was built without the
+    ///   `http` feature
+    pub fn new(config: RemoteLlmConfig) -> TemplateResult<Self> {
+        shield::guard("RemoteLlmClient::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "RemoteLlmClient requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            if config.model.trim().is_empty() {
+                return Err(TemplateError::invalid_input(
+                    "model must not be empty".to_string(),
+                    None,
+                ));
+            }
+            let endpoint = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+            let client = Self { config, endpoint };
+            // Building a request checks the URL and headers up front
+            #[cfg(feature = "http")]
+            let _ = http::build(&client.request(&[], &GenerationParams::default(), false)?)?;
+            Ok(client)
+        })
+    }
+
+    /// The client's configuration
+    pub fn config(&self) -> RemoteLlmConfig {
+        self.config.clone()
+    }
+
+    /// Sends `messages` and waits for the whole reply (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::NetworkError)` - If the server could not be
+    ///   reached or responded with an error status, or the network policy
+    ///   does not allow the connection
+    /// * `Err(TemplateError::ParseError)` - If the response is not a chat completion
+    /// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        params: GenerationParams,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<GenerationResult> {
+        shield::guard_async("RemoteLlmClient::chat", async move {
+            #[cfg(feature = "http")]
+            {
+                let request = self.request(&messages, &params, false)?;
+                let url = self.endpoint.clone();
+                let timeout_ms = self.config.timeout_ms;
+                let policy = self.config.network_policy;
+                let request = network::while_allowed(policy, url.clone(), async move {
+                    let (request, response) = send(request).await?;
+                    let response = http::read(response, timeout_ms).await?;
+                    interceptors::observe(&request, &response).await;
+                    if !response.is_success() {
+                        return Err(status_error(&url, response.status, &response.body));
+                    }
+                    let completion: CompletionResponse = serde_json::from_slice(&response.body)
+                        .map_err(|e| TemplateError::json_error(&e))?;
+                    let usage = completion.usage;
+                    let choice = completion.choices.into_iter().next();
+                    Ok(GenerationResult {
+                        text: choice
+                            .as_ref()
+                            .and_then(|choice| choice.message.as_ref())
+                            .and_then(|message| message.content.clone())
+                            .unwrap_or_default(),
+                        finish_reason: choice.and_then(|choice| choice.finish_reason),
+                        model: completion.model,
+                        prompt_tokens: usage.as_ref().and_then(|usage| usage.prompt_tokens),
+                        completion_tokens: usage.and_then(|usage| usage.completion_tokens),
+                    })
+                });
+                http::on_runtime(token.as_deref(), OPERATION, request).await
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (messages, params, token);
+                unreachable!("RemoteLlmClient::new fails without the `http` feature")
+            }
+        })
+        .await
+    }
+
+    /// Sends `messages` and streams the reply to `listener` as it is generated (async)
+    ///
+    /// Returns the whole reply once the stream ends. Token counts are only
+    /// filled in if the server reports usage in the stream.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::NetworkError)` - If the server could not be
+    ///   reached, responded with an error status, or the stream broke off,
+    ///   or the network policy does not allow the connection
+    /// * `Err(TemplateError::ParseError)` - If a streamed chunk is not a chat completion chunk
+    /// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub async fn chat_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        params: GenerationParams,
+        listener: Box<dyn GenerationListener>,
+        token: Option<Arc<CancellationToken>>,
+    ) -> TemplateResult<GenerationResult> {
+        shield::guard_async("RemoteLlmClient::chat_stream", async move {
+            #[cfg(feature = "http")]
+            {
+                let request = self.request(&messages, &params, true)?;
+                let url = self.endpoint.clone();
+                let timeout_ms = self.config.timeout_ms;
+                let policy = self.config.network_policy;
+                let request = network::while_allowed(policy, url.clone(), async move {
+                    let (request, response) = send(request).await?;
+                    interceptors::observe(&request, &http::head(&response)).await;
+                    if !response.status().is_success() {
+                        let status = response.status().as_u16();
+                        let body = response.bytes().await.unwrap_or_default();
+                        return Err(status_error(&url, status, &body));
+                    }
+                    let mut result = GenerationResult {
+                        text: String::new(),
+                        finish_reason: None,
+                        model: String::new(),
+                        prompt_tokens: None,
+                        completion_tokens: None,
+                    };
+                    let mut done = false;
+                    sse::read_events(response, &url, timeout_ms, |event| {
+                        if done || event.data == STREAM_DONE {
+                            done = true;
+                            return Ok(());
+                        }
+                        let chunk: CompletionResponse = serde_json::from_str(&event.data)
+                            .map_err(|e| TemplateError::json_error(&e))?;
+                        if !chunk.model.is_empty() {
+                            result.model = chunk.model;
+                        }
+                        if let Some(usage) = chunk.usage {
+                            result.prompt_tokens = usage.prompt_tokens;
+                            result.completion_tokens = usage.completion_tokens;
+                        }
+                        if let Some(choice) = chunk.choices.into_iter().next() {
+                            if let Some(text) = choice.delta.and_then(|delta| delta.content) {
+                                if !text.is_empty() {
+                                    result.text.push_str(&text);
+                                    listener.on_token(text);
+                                }
+                            }
+                            if choice.finish_reason.is_some() {
+                                result.finish_reason = choice.finish_reason;
+                            }
+                        }
+                        Ok(())
+                    })
+                    .await?;
+                    if !done && result.finish_reason.is_none() {
+                        return Err(TemplateError::network_error(
+                            &url,
+                            None,
+                            "Stream ended before the reply was complete".to_string(),
+                        ));
+                    }
+                    Ok(result)
+                });
+                http::on_runtime(token.as_deref(), OPERATION, request).await
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = (messages, params, listener, token);
+                unreachable!("RemoteLlmClient::new fails without the `http` feature")
+            }
+        })
+        .await
+    }
+
+    /// The chat-completions request for `messages`
+    #[cfg(feature = "http")]
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        params: &GenerationParams,
+        stream: bool,
+    ) -> TemplateResult<HttpRequest> {
+        let body = serde_json::to_vec(&CompletionRequest {
+            model: &self.config.model,
+            messages,
+            stream,
+            params,
+        })
+        .map_err(|e| TemplateError::json_error(&e))?;
+        let mut headers = self.config.headers.clone();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        if let Some(api_key) = &self.config.api_key {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", api_key));
+        }
+        if stream {
+            headers.insert("Accept".to_string(), "text/event-stream".to_string());
+        }
+        Ok(HttpRequest {
+            url: self.endpoint.clone(),
+            method: HttpMethod::Post,
+            headers,
+            body: Some(body),
+            timeout_ms: self.config.timeout_ms,
+        })
+    }
+}
+
+/// Sends `request` through the interceptors, returning the request as sent
+/// and the response with its body unread
+#[cfg(feature = "http")]
+async fn send(mut request: HttpRequest) -> TemplateResult<(HttpRequest, reqwest::Response)> {
+    if let Some(intercepted) = interceptors::intercept(&request).await {
+        request = intercepted;
+    }
+    let response = http::build(&request)?
+        .send()
+        .await
+        .map_err(|e| http::request_error(&request.url, request.timeout_ms, &e))?;
+    Ok((request, response))
+}
+
+/// `NetworkError` for an error status, with the server's message if it sent one
+#[cfg(feature = "http")]
+fn status_error(url: &str, status: u16, body: &[u8]) -> TemplateError {
+    let message = serde_json::from_slice::<ErrorResponse>(body)
+        .map(|response| response.error.message)
+        .unwrap_or_else(|_| String::from_utf8_lossy(body).chars().take(200).collect());
+    TemplateError::network_error(
+        url,
+        Some(status),
+        format!("Server responded with {}: {}", status, message),
+    )
+}
@@ -0,0 +1,68 @@
+//! Process-wide hook for error analytics
+
+use crate::error::{ErrorKind, Severity, TemplateError};
+use crate::error_map::HostErrorCode;
+use crate::shield;
+use std::cell::Cell;
+use std::sync::{Arc, RwLock};
+
+/// An error returned to the host, with the operation that produced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    /// Exported function or method that returned the error
+    pub operation: String,
+    /// Kind of the error
+    pub kind: ErrorKind,
+    /// Stable code such as `TIMEOUT`, the same as the host `errorCode`
+    pub code: String,
+    /// Severity of the error
+    pub severity: Severity,
+    /// Host code registered with `set_error_mappings`, if any
+    pub host_code: Option<HostErrorCode>,
+    /// The error itself
+    pub error: TemplateError,
+}
+
+/// Callback receiving every error the library returns, implemented by the host
+pub trait ErrorListener: Send + Sync {
+    /// Called on the thread that produced the error, before the call returns
+    fn on_error(&self, report: ErrorReport);
+}
+
+static ERROR_LISTENER: RwLock<Option<Arc<dyn ErrorListener>>> = RwLock::new(None);
+
+thread_local! {
+    /// Set while a listener runs, so errors it causes are not reported again
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Registers the listener notified of every error, replacing any previous one
+///
+/// Every fallible exported function reports its error here before
+/// returning it, so hosts can feed error analytics from one place instead
+/// of wrapping each call site. Pass `None` to remove the listener. The
+/// listener runs before the failing call returns; keep it fast.
+pub fn set_error_listener(listener: Option<Box<dyn ErrorListener>>) {
+    *ERROR_LISTENER.write().unwrap() = listener.map(Arc::from);
+}
+
+/// Reports an error returned by `operation` to the host listener, if any
+pub(crate) fn report(operation: &str, error: &TemplateError) {
+    let Some(listener) = ERROR_LISTENER.read().unwrap().clone() else {
+        return;
+    };
+    if REPORTING.with(|reporting| reporting.replace(true)) {
+        return;
+    }
+    let report = ErrorReport {
+        operation: operation.to_string(),
+        kind: error.kind(),
+        code: error.kind().code(),
+        severity: error.severity(),
+        host_code: error.host_code(),
+        error: error.clone(),
+    };
+    // A failing listener must not turn the error into a panic at the boundary
+    let _ = shield::catch(|| listener.on_error(report));
+    REPORTING.with(|reporting| reporting.set(false));
+}
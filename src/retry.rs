@@ -0,0 +1,156 @@
+//! Retrying idempotent operations with exponential backoff
+
+use crate::cancellation::{self, CancellationToken};
+use crate::error::{ErrorKind, TemplateError, TemplateResult};
+use crate::runtime;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+/// How often and how quickly to retry a failing operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry
+    pub base_delay_ms: u64,
+    /// Upper bound for a single delay
+    pub max_delay_ms: u64,
+    /// Fraction of each delay (0.0-1.0) that is randomized to spread out retries
+    pub jitter: f64,
+    /// Error kinds worth retrying; any other error is returned immediately
+    pub retry_on: Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 100,
+            max_delay_ms: 10_000,
+            jitter: 0.2,
+            retry_on: vec![ErrorKind::Timeout, ErrorKind::IoError],
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn validate(&self) -> TemplateResult<()> {
+        if self.max_attempts == 0 {
+            return Err(TemplateError::invalid_input(
+                "max_attempts must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(TemplateError::invalid_input(
+                format!("jitter must be between 0.0 and 1.0, got {}", self.jitter),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delay before retry number `attempt` (1 for the first retry), without jitter
+    fn base_delay(&self, attempt: u32) -> u64 {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        self.base_delay_ms
+            .saturating_mul(factor)
+            .min(self.max_delay_ms)
+    }
+
+    /// Delay before retry number `attempt`, with jitter applied
+    pub(crate) fn delay(&self, attempt: u32) -> u64 {
+        let delay = self.base_delay(attempt) as f64;
+        let jitter = delay * self.jitter * rand::rng().random::<f64>();
+        (delay - jitter).round() as u64
+    }
+}
+
+/// Delay in milliseconds a host should wait before retry number `attempt`
+///
+/// `attempt` counts retries, starting at 1. Lets hosts drive their own retry
+/// loops with the same backoff as the library.
+pub fn retry_delay_ms(policy: RetryPolicy, attempt: u32) -> u64 {
+    policy.delay(attempt)
+}
+
+/// Whether an error of `kind` should be retried after `attempts` attempts
+pub fn should_retry(policy: RetryPolicy, kind: ErrorKind, attempts: u32) -> bool {
+    attempts < policy.max_attempts && policy.retry_on.contains(&kind)
+}
+
+/// Runs `operation` until it succeeds, fails with a non-retryable error, or
+/// runs out of attempts (async)
+///
+/// Only use this for idempotent operations. The closure receives the attempt
+/// number, starting at 1.
+///
+/// # Returns
+///
+/// * `Ok(T)` - The first successful result
+/// * `Err(TemplateError::RetriesExhausted)` - If every attempt failed with a
+///   retryable error; carries the attempt count and the last error
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+///   between attempts
+/// * Any non-retryable error from the operation, unchanged
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::{retrying, RetryPolicy, TemplateError};
+///
+/// # tokio_test::block_on(async {
+/// let policy = RetryPolicy { base_delay_ms: 1, ..RetryPolicy::default() };
+/// let value = retrying(&policy, "flaky", None, |attempt| async move {
+///     if attempt < 2 {
+///         Err(TemplateError::timeout("flaky", 10))
+///     } else {
+///         Ok(attempt)
+///     }
+/// })
+/// .await
+/// .unwrap();
+/// assert_eq!(value, 2);
+/// # })
+/// ```
+pub async fn retrying<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation: &str,
+    token: Option<&CancellationToken>,
+    mut f: F,
+) -> TemplateResult<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = TemplateResult<T>>,
+{
+    policy.validate()?;
+
+    let mut attempt = 1;
+    loop {
+        cancellation::check_cancelled(token, operation)?;
+
+        let error = match f(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        if !policy.retry_on.contains(&error.kind()) {
+            return Err(error);
+        }
+        if attempt >= policy.max_attempts {
+            if attempt == 1 {
+                return Err(error);
+            }
+            return Err(TemplateError::retries_exhausted(operation, attempt, &error));
+        }
+
+        let delay = Duration::from_millis(policy.delay(attempt));
+        runtime::sleep_cancellable(delay, token, operation).await?;
+        attempt += 1;
+    }
+}
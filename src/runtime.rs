@@ -0,0 +1,261 @@
+//! Internal tokio runtime support for timers and background work
+
+use crate::cancellation::{self, CancellationToken};
+use crate::config;
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Handle, Runtime};
+
+/// Default prefix for the names of internal runtime threads
+const DEFAULT_THREAD_NAME_PREFIX: &str = "template-runtime";
+
+/// Settings for the internal runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeOptions {
+    /// Number of worker threads
+    pub worker_threads: u32,
+    /// Thread names are this prefix followed by `-<n>`
+    pub thread_name_prefix: String,
+}
+
+impl RuntimeOptions {
+    fn validate(&self) -> TemplateResult<()> {
+        if self.worker_threads == 0 {
+            return Err(TemplateError::invalid_input(
+                "worker_threads must be greater than 0".to_string(),
+                None,
+            ));
+        }
+        if self.thread_name_prefix.is_empty() {
+            return Err(TemplateError::invalid_input(
+                "thread_name_prefix must not be empty".to_string(),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    fn build(&self) -> std::io::Result<Runtime> {
+        let prefix = self.thread_name_prefix.clone();
+        let counter = AtomicUsize::new(0);
+        let mut builder = Builder::new_multi_thread();
+        builder
+            .worker_threads(self.worker_threads as usize)
+            .thread_name_fn(move || {
+                format!("{}-{}", prefix, counter.fetch_add(1, Ordering::Relaxed))
+            })
+            .enable_time();
+        // Sockets for `http_request`
+        #[cfg(feature = "http")]
+        builder.enable_io();
+        builder.build()
+    }
+}
+
+impl Default for RuntimeOptions {
+    fn default() -> Self {
+        Self {
+            worker_threads: 1,
+            thread_name_prefix: DEFAULT_THREAD_NAME_PREFIX.to_string(),
+        }
+    }
+}
+
+/// Runtime driving timers for callers polling from a foreign executor
+static RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+
+/// Starts the internal runtime with explicit settings
+///
+/// Optional: the runtime otherwise starts on first use with
+/// `LibraryConfig::runtime_threads` workers. Call this at app startup to
+/// control when threads are spawned and how they are named.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the options are invalid or the
+///   runtime cannot be started
+/// * `Err(TemplateError::AlreadyInitialized)` - If the runtime is already running;
+///   call `shutdown_runtime` first to restart it with new options
+pub fn init_runtime(options: RuntimeOptions) -> TemplateResult<()> {
+    shield::guard("init_runtime", || {
+        options.validate()?;
+        let mut runtime = RUNTIME.lock().unwrap();
+        if runtime.is_some() {
+            return Err(TemplateError::AlreadyInitialized);
+        }
+        let started = options.build().map_err(|e| {
+            TemplateError::invalid_input(format!("Failed to start runtime: {}", e), None)
+        })?;
+        log::info!(
+            "Started runtime with {} worker threads",
+            options.worker_threads
+        );
+        *runtime = Some(started);
+        Ok(())
+    })
+}
+
+/// Stops the internal runtime, waiting up to `grace_ms` for its tasks
+///
+/// Call this on app termination. Pending timers are dropped; tokens created
+/// with `CancellationToken::with_timeout` still report their deadline when
+/// checked. Does nothing if the runtime is not running, and a later call that
+/// needs the runtime starts it again.
+pub fn shutdown_runtime(grace_ms: u64) {
+    let Some(runtime) = RUNTIME.lock().unwrap().take() else {
+        return;
+    };
+    log::info!("Shutting down runtime (grace {} ms)", grace_ms);
+    // Shutting down blocks, which tokio forbids inside an async context
+    std::thread::spawn(move || runtime.shutdown_timeout(Duration::from_millis(grace_ms)))
+        .join()
+        .expect("runtime shutdown panicked");
+}
+
+/// Returns the caller's tokio runtime, or the internal runtime
+///
+/// Futures exported over UniFFI are polled by the Swift/Kotlin executors,
+/// which have no tokio reactor, so timers must be registered elsewhere.
+pub(crate) fn handle() -> Handle {
+    if let Ok(handle) = Handle::try_current() {
+        return handle;
+    }
+    RUNTIME
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| {
+            let options = RuntimeOptions {
+                worker_threads: config::current().runtime_threads,
+                ..RuntimeOptions::default()
+            };
+            log::debug!(
+                "Starting runtime on first use with {} worker threads",
+                options.worker_threads
+            );
+            options.build().expect("failed to build template runtime")
+        })
+        .handle()
+        .clone()
+}
+
+/// Drives a future to completion on the internal runtime, blocking the caller
+///
+/// Tokio forbids blocking inside an async context, so when called from one
+/// the future is driven from a separate thread instead.
+pub(crate) fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if Handle::try_current().is_ok() {
+        return std::thread::scope(|scope| {
+            match scope.spawn(|| handle().block_on(future)).join() {
+                Ok(value) => value,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        });
+    }
+    handle().block_on(future)
+}
+
+/// Runs blocking work on the runtime's blocking pool and awaits it
+///
+/// The calling future stays responsive to being dropped while the work runs;
+/// pair this with a `CancelOnDrop` guard so the work notices.
+pub(crate) async fn spawn_blocking<T, F>(work: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    // Catch on the worker so the panic keeps its location when resumed here,
+    // and run in the caller's span so nested spans keep their parent
+    let span = tracing::Span::current();
+    match handle()
+        .spawn_blocking(move || span.in_scope(|| shield::catch(work)))
+        .await
+    {
+        Ok(Ok(value)) => value,
+        Ok(Err(panic)) => shield::resume(panic),
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// Sleeps for `duration` using the internal runtime's timer
+pub(crate) async fn sleep(duration: Duration) {
+    let sleep = {
+        let handle = handle();
+        let _guard = handle.enter();
+        tokio::time::sleep(duration)
+    };
+    sleep.await
+}
+
+/// Longest a cancellable sleep waits before checking its token again
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Sleeps for `duration`, returning early with an error if `token` is cancelled
+pub(crate) async fn sleep_cancellable(
+    duration: Duration,
+    token: Option<&CancellationToken>,
+    operation: &str,
+) -> TemplateResult<()> {
+    let Some(token) = token else {
+        sleep(duration).await;
+        return Ok(());
+    };
+    let deadline = Instant::now() + duration;
+    loop {
+        cancellation::check_cancelled(Some(token), operation)?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        sleep(remaining.min(CANCEL_POLL_INTERVAL)).await;
+    }
+}
+
+/// Races a future against a timer, returning `TemplateError::Timeout` if it loses
+///
+/// With `timeout_ms` set to `None` the future runs to completion. The timer
+/// runs on the internal runtime, so this works from any executor. Foreign
+/// callers get the same behavior by passing a token created with
+/// `CancellationToken::with_timeout`.
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::{echo, run_with_timeout};
+///
+/// # tokio_test::block_on(async {
+/// let result = run_with_timeout("echo", Some(1_000), echo("Hi".to_string(), None, None))
+///     .await
+///     .unwrap();
+/// assert!(result.is_some());
+/// # })
+/// ```
+pub async fn run_with_timeout<T, F>(
+    operation: &str,
+    timeout_ms: Option<u64>,
+    future: F,
+) -> TemplateResult<T>
+where
+    F: Future<Output = TemplateResult<T>>,
+{
+    let Some(ms) = timeout_ms else {
+        return future.await;
+    };
+
+    let timed = {
+        let handle = handle();
+        let _guard = handle.enter();
+        tokio::time::timeout(Duration::from_millis(ms), future)
+    };
+
+    timed
+        .await
+        .unwrap_or_else(|_| Err(TemplateError::timeout(operation, ms)))
+}
@@ -0,0 +1,95 @@
+//! Input sanitization applied by `TemplateConfig` before validation
+
+use crate::error::{TemplateError, TemplateResult};
+use serde::{Deserialize, Serialize};
+
+/// Byte order mark that some editors and platforms prepend to text
+const BOM: char = '\u{FEFF}';
+
+/// Which sanitization steps to apply to input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizationOptions {
+    /// Remove control characters other than tab, newline and carriage return
+    pub strip_control_chars: bool,
+    /// Remove a leading byte order mark
+    pub strip_bom: bool,
+    /// Reject input containing bidirectional override/isolate characters
+    pub reject_bidi_overrides: bool,
+    /// Convert CRLF and lone CR line endings to LF
+    pub normalize_line_endings: bool,
+}
+
+impl SanitizationOptions {
+    /// Whether any sanitization step is enabled
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.strip_control_chars
+            || self.strip_bom
+            || self.reject_bidi_overrides
+            || self.normalize_line_endings
+    }
+}
+
+/// What sanitization changed in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SanitizationReport {
+    /// Number of control characters removed
+    pub control_chars_removed: u32,
+    /// Whether a leading byte order mark was removed
+    pub bom_removed: bool,
+    /// Number of CRLF or CR line endings converted to LF
+    pub line_endings_normalized: u32,
+}
+
+/// Returns true for Unicode bidirectional embedding, override and isolate controls
+///
+/// These can make text render in a different order than it is stored
+/// ("Trojan Source"), so security-sensitive inputs often reject them.
+pub(crate) fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Applies the enabled sanitization steps, returning the new text and a report
+pub(crate) fn sanitize(
+    input: &str,
+    options: &SanitizationOptions,
+) -> TemplateResult<(String, SanitizationReport)> {
+    let mut report = SanitizationReport::default();
+
+    if options.reject_bidi_overrides {
+        if let Some(position) = input.chars().position(is_bidi_control) {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "Input contains a bidirectional override character at position {}",
+                    position
+                ),
+                None,
+            ));
+        }
+    }
+
+    let mut text = input;
+    if options.strip_bom {
+        if let Some(rest) = text.strip_prefix(BOM) {
+            text = rest;
+            report.bom_removed = true;
+        }
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if options.normalize_line_endings && c == '\r' {
+            chars.next_if_eq(&'\n');
+            output.push('\n');
+            report.line_endings_normalized += 1;
+        } else if options.strip_control_chars && c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+        {
+            report.control_chars_removed += 1;
+        } else {
+            output.push(c);
+        }
+    }
+
+    Ok((output, report))
+}
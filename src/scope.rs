@@ -0,0 +1,87 @@
+//! Groups of operations that are cancelled and awaited together
+
+use crate::cancellation::CancellationToken;
+use crate::tasks::{self, TaskHandle, TaskStatus};
+use std::sync::{Arc, Mutex};
+
+/// Tracks the operations started through it so they can be torn down at once
+///
+/// Pass `token()` to any exported function, or start background work with
+/// `spawn_echo`. `cancel_all()` cancels all of it; dropping the scope does
+/// the same, so a ViewModel can own one and forget about cleanup.
+pub struct OperationScope {
+    token: CancellationToken,
+    tasks: Mutex<Vec<Arc<TaskHandle>>>,
+}
+
+impl OperationScope {
+    /// Create an empty scope
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a new token that is cancelled by `cancel_all()`
+    ///
+    /// Each call returns a separate child token, so cancelling one operation
+    /// does not affect the rest of the scope.
+    pub fn token(&self) -> Arc<CancellationToken> {
+        self.token.child()
+    }
+
+    /// Starts `echo` in the background as part of this scope
+    pub fn spawn_echo(&self, input: String, timeout_ms: Option<u64>) -> Arc<TaskHandle> {
+        let handle = tasks::spawn_echo(input, Some(self.token()), timeout_ms);
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| task.status() == TaskStatus::Running);
+        tasks.push(handle.clone());
+        handle
+    }
+
+    /// Number of background tasks in this scope that are still running
+    pub fn active_count(&self) -> u32 {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|task| task.status() == TaskStatus::Running)
+            .count() as u32
+    }
+
+    /// Cancels every operation started through this scope
+    ///
+    /// Operations started afterwards are cancelled immediately.
+    pub fn cancel_all(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether `cancel_all()` has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Waits until every background task in this scope has finished (async)
+    ///
+    /// Individual task errors are not reported here; use each task's
+    /// `await_result()` to inspect them.
+    pub async fn await_all(&self) {
+        let pending = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in pending {
+            let _ = task.await_result().await;
+        }
+    }
+}
+
+impl Default for OperationScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for OperationScope {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
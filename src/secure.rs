@@ -0,0 +1,86 @@
+//! Buffers for secrets that are wiped when dropped
+//!
+//! A `Vec<u8>` or `String` that is dropped leaves its contents in freed
+//! memory until the allocator reuses it. `SecureBytes` and `SecureString`
+//! overwrite their contents with zeros first (using `zeroize`, which the
+//! compiler cannot optimize away), and never print them in `Debug` output.
+//! The library keeps encryption keys and decrypted values in them.
+
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Bytes that are zeroed when dropped (Rust only)
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecureBytes(Zeroizing<Vec<u8>>);
+
+impl SecureBytes {
+    /// Takes ownership of `bytes`, which are wiped when this is dropped
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    /// The secret bytes
+    ///
+    /// Copies made from the returned slice are not wiped.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecureBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl fmt::Debug for SecureBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecureBytes(<{} bytes redacted>)", self.len())
+    }
+}
+
+/// A string that is zeroed when dropped (Rust only)
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecureString(Zeroizing<String>);
+
+impl SecureString {
+    /// Takes ownership of `value`, which is wiped when this is dropped
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    /// The secret text
+    ///
+    /// Copies made from the returned string are not wiped.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecureString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for SecureString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecureString(<{} bytes redacted>)", self.len())
+    }
+}
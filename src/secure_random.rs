@@ -0,0 +1,79 @@
+//! Cryptographically secure random generation backed by the OS CSPRNG
+//!
+//! Use these functions for nonces, tokens, and keys. The functions in the
+//! template module use a fast thread-local PRNG and must not be used for
+//! anything security sensitive.
+
+use crate::cancellation::{check_cancelled, CancellationToken};
+use crate::error::{TemplateError, TemplateResult, MAX_INPUT_SIZE};
+use crate::shield;
+use std::sync::Arc;
+
+/// Fills a buffer from the operating system's CSPRNG
+pub(crate) fn fill_secure(buf: &mut [u8]) -> TemplateResult<()> {
+    getrandom::fill(buf).map_err(|e| TemplateError::entropy_unavailable(&e.to_string()))
+}
+
+/// Generates `len` cryptographically secure random bytes (async)
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - The random bytes
+/// * `Err(TemplateError::InvalidInput)` - If `len` exceeds 1MB
+/// * `Err(TemplateError::EntropyUnavailable)` - If the OS CSPRNG fails
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::secure_random_bytes;
+///
+/// # tokio_test::block_on(async {
+/// let nonce = secure_random_bytes(12, None).await.unwrap();
+/// assert_eq!(nonce.len(), 12);
+/// # })
+/// ```
+pub async fn secure_random_bytes(
+    len: u32,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<u8>> {
+    shield::guard_async("secure_random_bytes", async move {
+        if len as usize > MAX_INPUT_SIZE {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "Requested {} bytes exceeds maximum of {} bytes",
+                    len, MAX_INPUT_SIZE
+                ),
+                None,
+            ));
+        }
+        check_cancelled(token.as_deref(), "secure_random_bytes")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "secure_random_bytes")?;
+        let mut bytes = vec![0u8; len as usize];
+        fill_secure(&mut bytes)?;
+        Ok(bytes)
+    })
+    .await
+}
+
+/// Generates a cryptographically secure random double in [0.0, 1.0) (async)
+///
+/// Uses the top 53 bits of a secure random `u64`, so every representable
+/// value in the range is equally likely.
+///
+/// # Returns
+///
+/// * `Ok(f64)` - The random value
+/// * `Err(TemplateError::EntropyUnavailable)` - If the OS CSPRNG fails
+pub async fn secure_random_double(token: Option<Arc<CancellationToken>>) -> TemplateResult<f64> {
+    shield::guard_async("secure_random_double", async move {
+        check_cancelled(token.as_deref(), "secure_random_double")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "secure_random_double")?;
+        let mut buf = [0u8; 8];
+        fill_secure(&mut buf)?;
+        let bits = u64::from_le_bytes(buf) >> 11;
+        Ok(bits as f64 * (1.0 / (1u64 << 53) as f64))
+    })
+    .await
+}
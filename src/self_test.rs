@@ -0,0 +1,127 @@
+//! Startup self-test that surfaces misconfiguration early
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use crate::secure_random::fill_secure;
+use crate::shield;
+use crate::template::echo;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Outcome of one self-test check
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestCheck {
+    /// What was checked, e.g. `runtime` or `filesystem:/path`
+    pub name: String,
+    /// Whether the check succeeded
+    pub passed: bool,
+    /// Why the check failed, if it did
+    pub message: Option<String>,
+    /// How long the check took, in microseconds
+    pub duration_us: u64,
+}
+
+/// Results of `run_self_test`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    /// Whether every check succeeded
+    pub passed: bool,
+    /// Every check, in the order they ran
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// Checks that the library can do its work on this device (async)
+///
+/// Meant for app startup, so misconfiguration shows up before the first user
+/// action. Every check runs even if an earlier one fails:
+/// - `runtime`: the internal runtime starts and runs blocking work and timers
+/// - `secure_random`: the OS CSPRNG returns bytes
+/// - `echo`: a short input passes validation and hashing
+/// - `filesystem:<path>`: a file can be created, read, and deleted in each of
+///   `writable_paths`
+///
+/// This never fails; problems are reported in the returned checks.
+pub async fn run_self_test(writable_paths: Vec<String>) -> SelfTestReport {
+    let mut checks = vec![
+        run_check("runtime", check_runtime()).await,
+        run_check("secure_random", async { check_secure_random() }).await,
+        run_check("echo", check_echo()).await,
+    ];
+    for path in writable_paths {
+        let name = format!("filesystem:{}", path);
+        checks.push(run_check(&name, async { check_writable(PathBuf::from(path)) }).await);
+    }
+    SelfTestReport {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    }
+}
+
+async fn run_check(
+    name: &str,
+    check: impl std::future::Future<Output = TemplateResult<()>>,
+) -> SelfTestCheck {
+    let started = Instant::now();
+    let result = shield::guard_async("run_self_test", check).await;
+    if let Err(e) = &result {
+        log::warn!("Self-test check {} failed: {}", name, e);
+    }
+    SelfTestCheck {
+        name: name.to_string(),
+        passed: result.is_ok(),
+        message: result.err().map(|e| e.to_string()),
+        duration_us: started.elapsed().as_micros() as u64,
+    }
+}
+
+/// A failed check that is not an error of any other kind
+fn check_failed(message: &str) -> TemplateError {
+    TemplateError::Internal {
+        error_message: message.to_string(),
+        location: None,
+        debug_info: None,
+    }
+}
+
+async fn check_runtime() -> TemplateResult<()> {
+    if runtime::spawn_blocking(|| 42).await != 42 {
+        return Err(check_failed("blocking work returned the wrong value"));
+    }
+    runtime::sleep(Duration::from_millis(1)).await;
+    Ok(())
+}
+
+fn check_secure_random() -> TemplateResult<()> {
+    let mut bytes = [0u8; 32];
+    fill_secure(&mut bytes)?;
+    if bytes.iter().all(|b| *b == 0) {
+        return Err(TemplateError::entropy_unavailable(
+            "OS random source returned only zeros",
+        ));
+    }
+    Ok(())
+}
+
+async fn check_echo() -> TemplateResult<()> {
+    let input = "self-test";
+    match echo(input.to_string(), None, None).await? {
+        Some(result) if result.text == input && result.hash.is_some() => Ok(()),
+        _ => Err(check_failed("echo returned an unexpected result")),
+    }
+}
+
+fn check_writable(directory: PathBuf) -> TemplateResult<()> {
+    let path = directory.join(format!(".template-self-test-{}", Uuid::new_v4()));
+    let contents = b"self-test";
+    fs::write(&path, contents).map_err(|e| TemplateError::io_error(&path, &e))?;
+    let read = fs::read(&path);
+    let removed = fs::remove_file(&path);
+    let read = read.map_err(|e| TemplateError::io_error(&path, &e))?;
+    removed.map_err(|e| TemplateError::io_error(&path, &e))?;
+    if read != contents {
+        return Err(check_failed("file contents changed between write and read"));
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! Panic shield for exported functions
+//!
+//! A panic unwinding across the FFI boundary aborts the host process. Every
+//! exported function that can fail runs its body through `guard` or
+//! `guard_async`, which catch the panic and return `TemplateError::Internal`
+//! instead. Both also pass every error they return to the host's error
+//! listener and the event log, run the body inside the operation's `tracing`
+//! span, and record the call in the metrics registry.
+
+use crate::crash;
+use crate::diagnostics;
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::metrics;
+use crate::reporting;
+use crate::spans;
+use std::any::Any;
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Once;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tracing::Instrument;
+
+thread_local! {
+    /// Location and debug context of the most recent panic on this thread
+    static LAST_PANIC: RefCell<PanicContext> = const { RefCell::new(PanicContext {
+        location: None,
+        debug_info: None,
+    }) };
+}
+
+static HOOK: Once = Once::new();
+
+#[derive(Default)]
+struct PanicContext {
+    location: Option<String>,
+    debug_info: Option<String>,
+}
+
+/// Records panic locations and crash reports, then defers to the previous hook
+pub(crate) fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            crash::record(info, location.clone());
+            let context = PanicContext {
+                location,
+                debug_info: diagnostics::capture(),
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = context);
+            previous(info);
+        }));
+    });
+}
+
+/// A panic caught on another thread, resumed with its location attached
+pub(crate) struct CaughtPanic {
+    message: String,
+    context: PanicContext,
+}
+
+/// Runs `work`, capturing a panic's message and location
+///
+/// Used for work handed to other threads, whose panics are resumed on the
+/// caller's thread where the location would otherwise be lost.
+pub(crate) fn catch<T>(work: impl FnOnce() -> T) -> Result<T, CaughtPanic> {
+    install_hook();
+    panic::catch_unwind(AssertUnwindSafe(work)).map_err(caught_panic)
+}
+
+/// Resumes a panic caught with `catch` on the current thread
+pub(crate) fn resume(panic: CaughtPanic) -> ! {
+    panic::resume_unwind(Box::new(panic))
+}
+
+fn caught_panic(payload: Box<dyn Any + Send>) -> CaughtPanic {
+    let payload = match payload.downcast::<CaughtPanic>() {
+        Ok(panic) => return *panic,
+        Err(payload) => payload,
+    };
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    CaughtPanic {
+        message,
+        context: LAST_PANIC.with(|last| std::mem::take(&mut *last.borrow_mut())),
+    }
+}
+
+fn internal_error(operation: &str, panic: CaughtPanic) -> TemplateError {
+    log::error!("Caught panic in {}: {}", operation, panic.message);
+    let mut error = TemplateError::internal(operation, &panic.message, panic.context.location);
+    if let TemplateError::Internal { debug_info, .. } = &mut error {
+        *debug_info = panic.context.debug_info;
+    }
+    error
+}
+
+fn finish<T>(operation: &str, started: Instant, result: TemplateResult<T>) -> TemplateResult<T> {
+    metrics::record_call(operation, started.elapsed(), result.as_ref().err());
+    if let Err(e) = &result {
+        reporting::report(operation, e);
+        events::publish(LibraryEvent::OperationFailed {
+            operation: operation.to_string(),
+            kind: e.kind(),
+            error_message: e.to_string(),
+        });
+    }
+    result
+}
+
+/// Runs the body of a synchronous exported function, converting panics
+pub(crate) fn guard<T>(
+    operation: &str,
+    body: impl FnOnce() -> TemplateResult<T>,
+) -> TemplateResult<T> {
+    let span = spans::operation_span(operation);
+    let _entered = span.enter();
+    let started = Instant::now();
+    let result = catch(body).unwrap_or_else(|panic| Err(internal_error(operation, panic)));
+    finish(operation, started, result)
+}
+
+/// Runs the body of an async exported function, converting panics
+pub(crate) async fn guard_async<T>(
+    operation: &str,
+    body: impl Future<Output = TemplateResult<T>>,
+) -> TemplateResult<T> {
+    install_hook();
+    let started = Instant::now();
+    let body = CatchUnwind {
+        future: Box::pin(body.instrument(spans::operation_span(operation))),
+    };
+    let result = match body.await {
+        Ok(result) => result,
+        Err(panic) => Err(internal_error(operation, panic)),
+    };
+    finish(operation, started, result)
+}
+
+/// Future adapter that catches panics raised while polling
+struct CatchUnwind<F> {
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, CaughtPanic>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(caught_panic(payload))),
+        }
+    }
+}
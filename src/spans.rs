@@ -0,0 +1,260 @@
+//! `tracing` spans around public operations, forwarded to the host
+//!
+//! Every fallible exported function runs inside an `operation` span carrying
+//! the operation name and a unique operation id. `set_span_listener` installs
+//! a subscriber that reports span open and close events, with durations, to a
+//! host callback so profilers can line up UI latency with Rust internals.
+
+use crate::otel::{self, ExportedSpan};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Span};
+use tracing_core::span::Current;
+
+/// Whether a span started or ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanEventKind {
+    /// The span was created
+    Opened,
+    /// The span ended; `duration_us` is set
+    Closed,
+}
+
+/// A span opening or closing inside the library
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanEvent {
+    /// Whether the span opened or closed
+    pub kind: SpanEventKind,
+    /// Id of the span, unique while it is open
+    pub span_id: u64,
+    /// Id of the enclosing span, if any
+    pub parent_id: Option<u64>,
+    /// Operation name for `operation` spans, otherwise the span name
+    pub name: String,
+    /// Id shared by one call to a public operation
+    pub operation_id: Option<u64>,
+    /// Unix timestamp of the event in milliseconds
+    pub timestamp_ms: u64,
+    /// How long the span was open, for `Closed` events
+    pub duration_us: Option<u64>,
+}
+
+/// Callback receiving span events, implemented by the host
+pub trait SpanListener: Send + Sync {
+    /// Called on the thread that opened or closed the span; keep it fast
+    fn on_span_event(&self, event: SpanEvent);
+}
+
+static SPAN_LISTENER: RwLock<Option<Arc<dyn SpanListener>>> = RwLock::new(None);
+static INSTALL: Once = Once::new();
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Sends span open/close events to the host, replacing any previous listener
+///
+/// Pass `None` to stop. The first call installs the library's subscriber as
+/// the global `tracing` subscriber; if the process already has one (e.g. a
+/// Rust app embedding the library), that subscriber receives the spans
+/// instead.
+pub fn set_span_listener(listener: Option<Box<dyn SpanListener>>) {
+    *SPAN_LISTENER.write().unwrap() = listener.map(Arc::from);
+    install();
+}
+
+/// Installs the forwarding subscriber, once, and re-evaluates which spans are enabled
+pub(crate) fn install() {
+    INSTALL.call_once(|| {
+        let _ = tracing::subscriber::set_global_default(Forwarder::default());
+    });
+    tracing::callsite::rebuild_interest_cache();
+}
+
+/// Creates the span a public operation runs in
+pub(crate) fn operation_span(operation: &str) -> Span {
+    tracing::info_span!(
+        "operation",
+        operation,
+        operation_id = NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn listener() -> Option<Arc<dyn SpanListener>> {
+    SPAN_LISTENER.read().unwrap().clone()
+}
+
+/// Span bookkeeping until the span closes
+struct OpenSpan {
+    metadata: &'static Metadata<'static>,
+    name: String,
+    parent_id: Option<u64>,
+    operation_id: Option<u64>,
+    started: Instant,
+    /// Handles to the span; it closes when the last one is dropped
+    refs: usize,
+    /// Copy of the span exported to OpenTelemetry, if export is enabled
+    exported: Option<ExportedSpan>,
+}
+
+/// Reads the `operation` and `operation_id` fields of a span
+#[derive(Default)]
+struct Fields {
+    operation: Option<String>,
+    operation_id: Option<u64>,
+}
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "operation_id" {
+            self.operation_id = Some(value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "operation" {
+            self.operation = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// `tracing` subscriber forwarding span lifecycles to the host listener
+#[derive(Default)]
+struct Forwarder {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, OpenSpan>>,
+}
+
+impl tracing::Subscriber for Forwarder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && (SPAN_LISTENER.read().unwrap().is_some() || otel::is_exporting())
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let parent_id = match attributes.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attributes.is_contextual() => {
+                ENTERED.with(|entered| entered.borrow().last().copied())
+            }
+            None => None,
+        };
+        let name = fields
+            .operation
+            .unwrap_or_else(|| attributes.metadata().name().to_string());
+        let mut spans = self.spans.lock().unwrap();
+        let parent_export = parent_id
+            .and_then(|parent| spans.get(&parent))
+            .and_then(|parent| parent.exported.as_ref());
+        let span = OpenSpan {
+            metadata: attributes.metadata(),
+            exported: otel::start_span(&name, parent_export, fields.operation_id),
+            name,
+            parent_id,
+            operation_id: fields.operation_id,
+            started: Instant::now(),
+            refs: 1,
+        };
+        let opened = SpanEvent {
+            kind: SpanEventKind::Opened,
+            span_id: id,
+            parent_id: span.parent_id,
+            name: span.name.clone(),
+            operation_id: span.operation_id,
+            timestamp_ms: now_ms(),
+            duration_us: None,
+        };
+        spans.insert(id, span);
+        drop(spans);
+        if let Some(listener) = listener() {
+            listener.on_span_event(opened);
+        }
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(position) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(position);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            span.refs += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let closed = {
+            let mut spans = self.spans.lock().unwrap();
+            match spans.get_mut(&id.into_u64()) {
+                Some(span) if span.refs > 1 => {
+                    span.refs -= 1;
+                    None
+                }
+                Some(_) => spans.remove(&id.into_u64()),
+                None => None,
+            }
+        };
+        let Some(mut span) = closed else {
+            return false;
+        };
+        if let Some(exported) = span.exported.take() {
+            exported.end();
+        }
+        if let Some(listener) = listener() {
+            listener.on_span_event(SpanEvent {
+                kind: SpanEventKind::Closed,
+                span_id: id.into_u64(),
+                parent_id: span.parent_id,
+                name: span.name,
+                operation_id: span.operation_id,
+                timestamp_ms: now_ms(),
+                duration_us: Some(span.started.elapsed().as_micros() as u64),
+            });
+        }
+        true
+    }
+
+    fn current_span(&self) -> Current {
+        let Some(id) = ENTERED.with(|entered| entered.borrow().last().copied()) else {
+            return Current::none();
+        };
+        match self.spans.lock().unwrap().get(&id) {
+            Some(span) => Current::new(Id::from_u64(id), span.metadata),
+            None => Current::none(),
+        }
+    }
+}
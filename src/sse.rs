@@ -0,0 +1,245 @@
+//! Server-Sent Events over HTTP (`http` feature)
+//!
+//! `sse_request` sends an `HttpRequest` and parses the `text/event-stream`
+//! response as it arrives, handing each event to an `SseListener`, so a
+//! host can show tokens streamed by an LLM server as they are generated.
+//! `SseParser` does the parsing for both this function and the remote
+//! inference client, following the WHATWG event-stream format: `data`
+//! lines are joined with newlines, `event` sets the type, `id` sets the
+//! last event id, `retry` the reconnection time, and lines starting with
+//! `:` are comments. Without the `http` feature the types exist so the
+//! bindings stay the same, but requests fail.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::http::HttpRequest;
+use crate::shield;
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::http;
+#[cfg(feature = "http")]
+use crate::interceptors;
+
+/// Operation name used in errors
+#[cfg(feature = "http")]
+const OPERATION: &str = "sse_request";
+
+/// Event type used when an event has no `event` field
+pub const SSE_DEFAULT_EVENT: &str = "message";
+
+/// One event from a `text/event-stream`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Value of the `event` field, or `message`
+    pub event: String,
+    /// `data` lines joined with `\n`
+    pub data: String,
+    /// Last event id seen in the stream, if any, to send back as `Last-Event-ID`
+    pub id: Option<String>,
+    /// Reconnection time requested by the server with a `retry` field
+    pub retry_ms: Option<u64>,
+}
+
+/// Callback receiving Server-Sent Events, implemented by the host
+pub trait SseListener: Send + Sync {
+    /// Called on a runtime thread for each event, in order
+    fn on_sse_event(&self, event: SseEvent);
+}
+
+/// Incremental `text/event-stream` parser (Rust only)
+///
+/// Bytes can be fed in chunks of any size; lines split across chunks,
+/// including a `\r\n` split between its two bytes, are handled.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// Bytes of the line being read
+    line: Vec<u8>,
+    /// The previous chunk ended with `\r`, so a leading `\n` is part of that line break
+    after_cr: bool,
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+    last_id: Option<String>,
+    retry_ms: Option<u64>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `bytes` and returns the events they complete
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            match byte {
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    /// Handles one complete line, returning the event an empty line dispatches
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line[0] == b':' {
+            return None;
+        }
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.parse::<u64>() {
+                    self.retry_ms = Some(ms);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Ends the event being read; events without data are dropped
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(SseEvent {
+            event: event
+                .filter(|event| !event.is_empty())
+                .unwrap_or_else(|| SSE_DEFAULT_EVENT.to_string()),
+            data: std::mem::take(&mut self.data),
+            id: self.last_id.clone(),
+            retry_ms: self.retry_ms,
+        })
+    }
+}
+
+/// Sends `request` and delivers the response's events to `listener` until it ends (async)
+///
+/// `Accept: text/event-stream` is added unless the request sets `Accept`.
+/// `timeout_ms` bounds the whole stream, not the wait for each event. A
+/// stream that ends mid-event drops that event.
+///
+/// # Returns
+///
+/// The last event id seen, to send as `Last-Event-ID` when reconnecting.
+///
+/// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid,
+///   or the library was built without the `http` feature
+/// * `Err(TemplateError::NetworkError)` - If the status is not 2xx or the
+///   connection failed
+/// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn sse_request(
+    request: HttpRequest,
+    listener: Box<dyn SseListener>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Option<String>> {
+    shield::guard_async("sse_request", async move {
+        #[cfg(feature = "http")]
+        {
+            let mut request = request;
+            if !request
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("accept"))
+            {
+                request
+                    .headers
+                    .insert("Accept".to_string(), "text/event-stream".to_string());
+            }
+            auth::authorize(&request.url, &mut request.headers).await?;
+            if let Some(intercepted) = interceptors::intercept(&request).await {
+                request = intercepted;
+            }
+            let builder = http::build(&request)?;
+            let url = request.url.clone();
+            let timeout_ms = request.timeout_ms;
+            http::on_runtime(token.as_deref(), OPERATION, async move {
+                let response = builder
+                    .send()
+                    .await
+                    .map_err(|e| http::request_error(&url, timeout_ms, &e))?;
+                interceptors::observe(&request, &http::head(&response)).await;
+                read_events(response, &url, timeout_ms, |event| {
+                    listener.on_sse_event(event);
+                    Ok(())
+                })
+                .await
+            })
+            .await
+        }
+        #[cfg(not(feature = "http"))]
+        {
+            let _ = (request, listener, token);
+            Err(TemplateError::invalid_input(
+                "sse_request requires the `http` feature".to_string(),
+                None,
+            ))
+        }
+    })
+    .await
+}
+
+/// Parses `response` as an event stream, calling `on_event` for each event
+///
+/// Returns the last event id seen, or the first error `on_event` returns.
+#[cfg(feature = "http")]
+pub(crate) async fn read_events(
+    mut response: reqwest::Response,
+    url: &str,
+    timeout_ms: Option<u64>,
+    mut on_event: impl FnMut(SseEvent) -> TemplateResult<()>,
+) -> TemplateResult<Option<String>> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(TemplateError::network_error(
+            url,
+            Some(status.as_u16()),
+            format!(
+                "Server responded with {}: {}",
+                status,
+                body.chars().take(200).collect::<String>()
+            ),
+        ));
+    }
+    let mut parser = SseParser::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| http::request_error(url, timeout_ms, &e))?
+    {
+        for event in parser.feed(&chunk) {
+            on_event(event)?;
+        }
+    }
+    Ok(parser.last_id)
+}
@@ -0,0 +1,160 @@
+//! Chunked echo for inputs too large to pass in a single call
+
+use crate::cancellation::{self, CancellationToken};
+use crate::error::{TemplateError, TemplateResult};
+use crate::hashing::{HashAlgorithm, StreamingHasher};
+use crate::metrics;
+use crate::shield;
+use crate::template::EchoResult;
+use crate::unicode::is_nfc;
+use crate::warnings::{Warning, WarningKind};
+use std::sync::{Arc, Mutex};
+
+/// Writer-style echo that accepts its input in chunks
+///
+/// Unlike `echo`, the total input is not limited to 1MB. Each chunk is
+/// validated (UTF-8, null bytes) and hashed as it arrives, so errors are
+/// reported on the chunk that caused them. UTF-8 sequences may be split
+/// across chunk boundaries.
+pub struct EchoStream {
+    state: Mutex<Option<StreamState>>,
+    token: Option<Arc<CancellationToken>>,
+}
+
+struct StreamState {
+    text: String,
+    /// Trailing bytes of an incomplete UTF-8 sequence from the last chunk
+    pending: Vec<u8>,
+    hasher: StreamingHasher,
+}
+
+impl EchoStream {
+    /// Create a new stream hashing with SHA-256
+    pub fn new(token: Option<Arc<CancellationToken>>) -> Self {
+        Self {
+            state: Mutex::new(Some(StreamState {
+                text: String::new(),
+                pending: Vec::new(),
+                hasher: StreamingHasher::new(HashAlgorithm::default()),
+            })),
+            token,
+        }
+    }
+
+    /// Append a chunk of UTF-8 encoded bytes
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the chunk contains null bytes or
+    ///   invalid UTF-8, or the stream has already finished
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub fn push_chunk(&self, chunk: Vec<u8>) -> TemplateResult<()> {
+        shield::guard("push_chunk", || {
+            self.check_cancelled()?;
+            let mut guard = self.state.lock().unwrap();
+            let state = guard.as_mut().ok_or_else(finished_error)?;
+
+            if chunk.contains(&0) {
+                return Err(TemplateError::invalid_input(
+                    "Input contains null bytes".to_string(),
+                    None,
+                ));
+            }
+
+            metrics::add("input_bytes", chunk.len() as u64);
+            let mut bytes = std::mem::take(&mut state.pending);
+            bytes.extend_from_slice(&chunk);
+
+            let valid_up_to = match std::str::from_utf8(&bytes) {
+                Ok(_) => bytes.len(),
+                // An incomplete sequence at the end may be completed by the next chunk
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => {
+                    return Err(TemplateError::invalid_input(
+                        "Invalid UTF-8 sequence".to_string(),
+                        None,
+                    ))
+                }
+            };
+
+            let (complete, rest) = bytes.split_at(valid_up_to);
+            state.hasher.update(complete);
+            // Cannot fail: `complete` was validated above
+            state.text.push_str(std::str::from_utf8(complete).unwrap());
+            state.pending = rest.to_vec();
+            Ok(())
+        })
+    }
+
+    /// Finish the stream and return the echoed text with metadata
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(EchoResult))` - The echoed text if not empty
+    /// * `Ok(None)` - If no data was pushed
+    /// * `Err(TemplateError::InvalidInput)` - If the input ends mid UTF-8 sequence,
+    ///   or the stream has already finished
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+    pub fn finish(&self) -> TemplateResult<Option<EchoResult>> {
+        shield::guard("finish", || {
+            self.check_cancelled()?;
+            let state = self
+                .state
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(finished_error)?;
+
+            if !state.pending.is_empty() {
+                return Err(TemplateError::invalid_input(
+                    "Input ends with an incomplete UTF-8 sequence".to_string(),
+                    None,
+                ));
+            }
+
+            if state.text.is_empty() {
+                return Ok(None);
+            }
+
+            let hash = state.hasher.finalize();
+            let warnings = if is_nfc(&state.text) {
+                Vec::new()
+            } else {
+                vec![Warning::new(
+                    WarningKind::InputNotNormalized,
+                    "Input is not in NFC form; its hash depends on how the text was composed",
+                )]
+            };
+            let mut result = EchoResult::new(state.text).with_warnings(warnings);
+            if let Some(hash) = hash {
+                result = result.with_hash(hash);
+            }
+            Ok(Some(result))
+        })
+    }
+
+    fn check_cancelled(&self) -> TemplateResult<()> {
+        cancellation::check_cancelled(self.token.as_deref(), "echo_stream")
+    }
+}
+
+fn finished_error() -> TemplateError {
+    TemplateError::invalid_input("Echo stream already finished".to_string(), None)
+}
+
+/// Starts a chunked echo for inputs larger than a single call allows
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::echo_stream;
+///
+/// let stream = echo_stream(None);
+/// stream.push_chunk(b"Hello, ".to_vec()).unwrap();
+/// stream.push_chunk(b"World".to_vec()).unwrap();
+/// let result = stream.finish().unwrap().unwrap();
+/// assert_eq!(result.text, "Hello, World");
+/// ```
+pub fn echo_stream(token: Option<Arc<CancellationToken>>) -> Arc<EchoStream> {
+    Arc::new(EchoStream::new(token))
+}
@@ -0,0 +1,182 @@
+//! Background tasks that outlive the call that started them
+
+use crate::cancellation::{self, CancellationToken};
+use crate::error::{TemplateError, TemplateResult};
+use crate::events::{self, LibraryEvent};
+use crate::runtime;
+use crate::template::{self, EchoResult};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::watch;
+
+/// Lifecycle state of a background task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Still running
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Cancelled before it finished
+    Cancelled,
+}
+
+type TaskOutcome = TemplateResult<Option<EchoResult>>;
+
+/// Handle to an operation running on the internal runtime
+///
+/// The operation keeps running when the handle is dropped, so hosts can
+/// start a job on one screen and look it up with `get_task` on another.
+pub struct TaskHandle {
+    id: u64,
+    token: Arc<CancellationToken>,
+    /// `None` while running, then the outcome; the first writer wins
+    outcome: watch::Sender<Option<TaskOutcome>>,
+}
+
+impl TaskHandle {
+    /// Unique id of this task within the process
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Current status of the task
+    pub fn status(&self) -> TaskStatus {
+        match &*self.outcome.borrow() {
+            None => TaskStatus::Running,
+            Some(Ok(_)) => TaskStatus::Completed,
+            Some(Err(TemplateError::OperationCancelled { .. })) => TaskStatus::Cancelled,
+            Some(Err(_)) => TaskStatus::Failed,
+        }
+    }
+
+    /// Waits for the task to finish and returns its result (async)
+    ///
+    /// May be called any number of times, from any number of callers.
+    pub async fn await_result(&self) -> TaskOutcome {
+        let mut receiver = self.outcome.subscribe();
+        let outcome = receiver
+            .wait_for(Option::is_some)
+            .await
+            .expect("task handle owns the sender");
+        outcome.clone().expect("waited for an outcome")
+    }
+
+    /// Cancels the task
+    ///
+    /// The status changes to `Cancelled` right away unless the task already
+    /// finished. A task whose token runs out of time reports `Failed` with
+    /// `TemplateError::Timeout` instead.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Records the outcome unless one was already recorded
+    fn finish(&self, outcome: TaskOutcome) {
+        let recorded = self.outcome.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(outcome);
+            true
+        });
+        if !recorded {
+            return;
+        }
+
+        let status = self.status();
+        if status == TaskStatus::Failed {
+            if let Some(Err(e)) = &*self.outcome.borrow() {
+                events::publish(LibraryEvent::BackgroundError {
+                    operation: "echo".to_string(),
+                    kind: e.kind(),
+                    error_message: e.to_string(),
+                });
+            }
+        }
+        events::publish(LibraryEvent::TaskFinished {
+            id: self.id,
+            status,
+        });
+    }
+}
+
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Tasks that are running or still referenced by a host
+static TASKS: Mutex<Option<HashMap<u64, Weak<TaskHandle>>>> = Mutex::new(None);
+
+/// Starts `echo` in the background and returns a handle to it
+///
+/// The task observes `token` if given; cancelling the handle does not cancel
+/// the caller's token.
+pub fn spawn_echo(
+    input: String,
+    token: Option<Arc<CancellationToken>>,
+    timeout_ms: Option<u64>,
+) -> Arc<TaskHandle> {
+    let task_token = match token {
+        Some(t) => t.child(),
+        None => Arc::new(CancellationToken::new()),
+    };
+    let handle = Arc::new(TaskHandle {
+        id: NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed),
+        token: task_token.clone(),
+        outcome: watch::Sender::new(None),
+    });
+    register(&handle);
+
+    // The running task keeps the handle alive until it finishes
+    let running = handle.clone();
+    let join = runtime::handle().spawn(async move {
+        let outcome = template::echo(input, Some(running.token.clone()), timeout_ms).await;
+        running.finish(outcome);
+    });
+
+    let cancelled = Arc::downgrade(&handle);
+    task_token.on_cancel(move || {
+        join.abort();
+        if let Some(task) = cancelled.upgrade() {
+            task.finish(Err(cancellation::cancelled_error(&task.token, "echo")));
+        }
+    });
+
+    handle
+}
+
+/// Looks up a task by id
+///
+/// Returns `None` once the task has finished and no handle to it remains.
+pub fn get_task(id: u64) -> Option<Arc<TaskHandle>> {
+    TASKS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|tasks| tasks.get(&id))
+        .and_then(Weak::upgrade)
+}
+
+/// Forgets finished tasks, whose allocations the registry keeps alive
+///
+/// Returns an estimate of the bytes freed.
+pub(crate) fn release_memory() -> u64 {
+    let mut tasks = TASKS.lock().unwrap();
+    let Some(tasks) = tasks.as_mut() else {
+        return 0;
+    };
+    let (count, capacity) = (tasks.len(), tasks.capacity());
+    tasks.retain(|_, task| task.strong_count() > 0);
+    tasks.shrink_to_fit();
+    let entries = (capacity - tasks.capacity()) * std::mem::size_of::<(u64, Weak<TaskHandle>)>();
+    let handles = (count - tasks.len()) * std::mem::size_of::<TaskHandle>();
+    (entries + handles) as u64
+}
+
+fn register(handle: &Arc<TaskHandle>) {
+    let mut tasks = TASKS.lock().unwrap();
+    let tasks = tasks.get_or_insert_with(HashMap::new);
+    tasks.retain(|_, task| task.strong_count() > 0);
+    tasks.insert(handle.id, Arc::downgrade(handle));
+}
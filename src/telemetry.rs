@@ -0,0 +1,439 @@
+//! Batched upload of analytics events (`http` feature)
+//!
+//! `Telemetry::track` buffers events in a file in the telemetry directory,
+//! so events survive the app being killed before they are sent. Buffered
+//! events are uploaded in batches once `max_batch_events` have been tracked
+//! or the oldest has waited `max_batch_age_ms`, whichever comes first. Each
+//! batch is one JSON `POST` to the endpoint, gzip-compressed unless turned
+//! off, and is handed to an `Outbox` in the same directory, which retries it
+//! with backoff until the server accepts it. Delivery is at least once, so
+//! every batch carries a `batch_id` the server can use to drop repeats.
+//!
+//! Privacy is enforced here rather than left to each platform: while the
+//! user has opted out with `set_opted_out`, tracked events are discarded,
+//! and opting out deletes the events buffered and the batches not yet
+//! uploaded. The choice is saved in the directory and applies after a
+//! restart. Properties named in `redacted_properties` are removed from every
+//! event before it is buffered. Without the `http` feature the types exist
+//! so the bindings stay the same, but creating a `Telemetry` fails.
+
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::files;
+use crate::http::{HttpMethod, HttpRequest};
+use crate::network::NetworkPolicy;
+use crate::outbox::{Outbox, OutboxOptions};
+use crate::runtime;
+use crate::shield;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Notify};
+
+#[cfg(feature = "http")]
+use crate::http;
+
+/// File holding buffered events, one JSON object per line
+const EVENTS_FILE: &str = "events.jsonl";
+
+/// File holding the batches waiting for upload, kept by the `Outbox`
+const UPLOADS_FILE: &str = "uploads.json";
+
+/// File present while the user has opted out
+const OPTED_OUT_FILE: &str = "opted_out";
+
+/// Where and how `Telemetry` uploads events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelemetryConfig {
+    /// Absolute `http` or `https` URL each batch is posted to
+    pub endpoint: String,
+    /// Sent with every batch, e.g. an API key
+    pub headers: HashMap<String, String>,
+    /// Events per batch; a full batch is uploaded right away
+    pub max_batch_events: u32,
+    /// Buffered events are uploaded once the oldest is this old
+    pub max_batch_age_ms: u64,
+    /// Events buffered at most; tracking past it drops the oldest
+    pub max_buffered_events: u32,
+    /// Whether batches are sent with `Content-Encoding: gzip`
+    pub compress: bool,
+    /// Property names removed from every event, e.g. `email`
+    pub redacted_properties: Vec<String>,
+    /// Connections batches may be uploaded over
+    pub network_policy: NetworkPolicy,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            headers: HashMap::new(),
+            max_batch_events: 100,
+            max_batch_age_ms: 60_000,
+            max_buffered_events: 10_000,
+            compress: true,
+            redacted_properties: Vec::new(),
+            network_policy: NetworkPolicy::Any,
+        }
+    }
+}
+
+/// An analytics event as buffered and uploaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    pub name: String,
+    /// When the event was tracked, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub properties: HashMap<String, String>,
+}
+
+/// Body of an upload
+#[derive(Serialize)]
+struct Batch<'a> {
+    /// Random id, the same on every attempt to upload the batch
+    batch_id: String,
+    events: &'a [TelemetryEvent],
+}
+
+/// Buffers analytics events and uploads them in batches
+pub struct Telemetry {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: TelemetryConfig,
+    events_path: PathBuf,
+    opted_out_path: PathBuf,
+    state: Mutex<TelemetryState>,
+    outbox: Outbox,
+    /// Wakes the worker when the first event is buffered
+    wake: Notify,
+    /// Set when the `Telemetry` is dropped, to stop the worker
+    closed: watch::Sender<bool>,
+}
+
+struct TelemetryState {
+    events: Vec<TelemetryEvent>,
+    opted_out: bool,
+}
+
+impl Telemetry {
+    /// Create a telemetry client keeping its files in `directory`, picking
+    /// up events and batches left there by a previous one
+    ///
+    /// A relative `directory` is placed in the data directory set with
+    /// `set_app_directories`.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the endpoint is not an
+    ///   absolute `http(s)` URL, a limit is 0, or the library was built
+    ///   without the `http` feature
+    /// * `Err(TemplateError::IoError)` - If the directory cannot be created or read
+    /// * `Err(TemplateError::ParseError)` - If a file in it is corrupt
+    pub fn new(directory: String, config: TelemetryConfig) -> TemplateResult<Self> {
+        shield::guard("Telemetry::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "Telemetry requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            #[cfg(feature = "http")]
+            http::parse_url(&config.endpoint)?;
+            if config.max_batch_events == 0 || config.max_buffered_events == 0 {
+                return Err(TemplateError::invalid_input(
+                    "max_batch_events and max_buffered_events must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+
+            let directory = directories::resolve(StorageCategory::Data, &directory);
+            fs::create_dir_all(&directory).map_err(|e| TemplateError::io_error(&directory, &e))?;
+            let events_path = directory.join(EVENTS_FILE);
+            let opted_out_path = directory.join(OPTED_OUT_FILE);
+            let opted_out = opted_out_path.exists();
+            let events = if opted_out {
+                Vec::new()
+            } else {
+                load_events(&events_path)?
+            };
+            let outbox = Outbox::new(
+                directory.join(UPLOADS_FILE).to_string_lossy().into_owned(),
+                OutboxOptions {
+                    network_policy: config.network_policy,
+                    ..OutboxOptions::default()
+                },
+            )?;
+
+            let inner = Arc::new(Inner {
+                config,
+                events_path,
+                opted_out_path,
+                state: Mutex::new(TelemetryState { events, opted_out }),
+                outbox,
+                wake: Notify::new(),
+                closed: watch::Sender::new(false),
+            });
+            runtime::handle().spawn(Inner::run(inner.clone()));
+            Ok(Self { inner })
+        })
+    }
+
+    /// Buffers an event named `name`, timestamped now; does nothing while
+    /// the user has opted out
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `name` is empty
+    /// * `Err(TemplateError::IoError)` - If the event cannot be buffered
+    pub fn track(&self, name: String, properties: HashMap<String, String>) -> TemplateResult<()> {
+        shield::guard("Telemetry::track", || {
+            if name.trim().is_empty() {
+                return Err(TemplateError::invalid_input(
+                    "Event name must not be empty".to_string(),
+                    None,
+                ));
+            }
+            let mut properties = properties;
+            for redacted in &self.inner.config.redacted_properties {
+                properties.remove(redacted);
+            }
+            let event = TelemetryEvent {
+                name,
+                timestamp_ms: now_ms(),
+                properties,
+            };
+
+            let mut state = self.inner.state.lock().unwrap();
+            if state.opted_out {
+                return Ok(());
+            }
+            self.inner.append(&event)?;
+            state.events.push(event);
+            let max_buffered = self.inner.config.max_buffered_events as usize;
+            if state.events.len() > max_buffered {
+                let dropped = state.events.len() - max_buffered;
+                log::warn!("Telemetry buffer full, dropped {} events", dropped);
+                state.events.drain(..dropped);
+                self.inner.rewrite(&state.events)?;
+            }
+            if state.events.len() >= self.inner.config.max_batch_events as usize {
+                self.inner.upload(&mut state)?;
+            } else if state.events.len() == 1 {
+                self.inner.wake.notify_one();
+            }
+            Ok(())
+        })
+    }
+
+    /// Queues every buffered event for upload now instead of waiting for a
+    /// full batch, e.g. when the app moves to the background
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the batches cannot be queued
+    pub fn flush(&self) -> TemplateResult<()> {
+        shield::guard("Telemetry::flush", || {
+            let mut state = self.inner.state.lock().unwrap();
+            self.inner.upload(&mut state)
+        })
+    }
+
+    /// Records the user's choice; opting out deletes the buffered events and
+    /// the batches not yet uploaded, and the choice is kept across restarts
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the choice cannot be saved
+    pub fn set_opted_out(&self, opted_out: bool) -> TemplateResult<()> {
+        shield::guard("Telemetry::set_opted_out", || {
+            let mut state = self.inner.state.lock().unwrap();
+            let path = &self.inner.opted_out_path;
+            if !opted_out {
+                match fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(TemplateError::io_error(path, &e))
+                    }
+                    _ => {}
+                }
+                state.opted_out = false;
+                return Ok(());
+            }
+            files::write_atomic(path, b"").map_err(|e| TemplateError::io_error(path, &e))?;
+            state.opted_out = true;
+            state.events.clear();
+            self.inner.rewrite(&state.events)?;
+            for entry in self.inner.outbox.entries() {
+                self.inner.outbox.remove(entry.id);
+            }
+            log::info!("Telemetry opted out, buffered events deleted");
+            Ok(())
+        })
+    }
+
+    /// Whether the user has opted out
+    pub fn is_opted_out(&self) -> bool {
+        self.inner.state.lock().unwrap().opted_out
+    }
+
+    /// Events buffered and not yet queued for upload
+    pub fn buffered_events(&self) -> Vec<TelemetryEvent> {
+        self.inner.state.lock().unwrap().events.clone()
+    }
+
+    /// Batches queued for upload
+    pub fn pending_batches(&self) -> u32 {
+        self.inner.outbox.entries().len() as u32
+    }
+
+    /// Waits until every queued batch has been uploaded or given up on (async)
+    ///
+    /// Buffered events are not queued by this; call `flush` first.
+    pub async fn wait_idle(&self) {
+        self.inner.outbox.wait_idle().await;
+    }
+}
+
+impl Drop for Telemetry {
+    /// Stops the worker; buffered events stay in the directory for the next client
+    fn drop(&mut self) {
+        self.inner.closed.send_replace(true);
+    }
+}
+
+impl Inner {
+    /// Uploads buffered events once the oldest is `max_batch_age_ms` old,
+    /// until the `Telemetry` is dropped
+    async fn run(inner: Arc<Self>) {
+        let mut closed = inner.closed.subscribe();
+        loop {
+            let delay = inner.next_upload();
+            if delay == Some(Duration::ZERO) {
+                let mut state = inner.state.lock().unwrap();
+                if let Err(e) = inner.upload(&mut state) {
+                    log::warn!("Could not queue telemetry batch: {}", e);
+                }
+                continue;
+            }
+            let sleep = async {
+                match delay {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                // The flag is only ever set, so any change means closed
+                _ = closed.changed() => return,
+                _ = inner.wake.notified() => {}
+                _ = sleep => {}
+            }
+        }
+    }
+
+    /// Time until the buffered events are due for upload; `None` if there are none
+    fn next_upload(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        let oldest = state.events.first()?.timestamp_ms;
+        let due_ms = oldest.saturating_add(self.config.max_batch_age_ms);
+        Some(Duration::from_millis(due_ms.saturating_sub(now_ms())))
+    }
+
+    /// Queues the buffered events in batches of `max_batch_events`; events
+    /// whose batch could not be queued stay buffered
+    fn upload(&self, state: &mut TelemetryState) -> TemplateResult<()> {
+        if state.events.is_empty() {
+            return Ok(());
+        }
+        let size = self.config.max_batch_events as usize;
+        let mut queued = 0;
+        let mut result = Ok(());
+        for events in state.events.chunks(size) {
+            if let Err(e) = self.request(events).and_then(|r| self.outbox.enqueue(r)) {
+                result = Err(e);
+                break;
+            }
+            queued += events.len();
+        }
+        state.events.drain(..queued);
+        self.rewrite(&state.events)?;
+        result
+    }
+
+    /// The upload of `events` as one batch
+    fn request(&self, events: &[TelemetryEvent]) -> TemplateResult<HttpRequest> {
+        let batch = Batch {
+            batch_id: uuid::Uuid::new_v4().to_string(),
+            events,
+        };
+        let json = serde_json::to_vec(&batch).map_err(|e| TemplateError::json_error(&e))?;
+        let mut headers = self.config.headers.clone();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        let body = if self.config.compress {
+            headers.insert("Content-Encoding".to_string(), "gzip".to_string());
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(&json)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| TemplateError::io_error(&self.events_path, &e))?
+        } else {
+            json
+        };
+        Ok(HttpRequest {
+            url: self.config.endpoint.clone(),
+            method: HttpMethod::Post,
+            headers,
+            body: Some(body),
+            timeout_ms: None,
+        })
+    }
+
+    /// Adds `event` to the end of the buffer file
+    fn append(&self, event: &TelemetryEvent) -> TemplateResult<()> {
+        let mut line = serde_json::to_vec(event).map_err(|e| TemplateError::json_error(&e))?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.events_path)
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(|e| TemplateError::io_error(&self.events_path, &e))
+    }
+
+    /// Replaces the buffer file with `events`
+    fn rewrite(&self, events: &[TelemetryEvent]) -> TemplateResult<()> {
+        let mut lines = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut lines, event).map_err(|e| TemplateError::json_error(&e))?;
+            lines.push(b'\n');
+        }
+        files::write_atomic(&self.events_path, &lines)
+            .map_err(|e| TemplateError::io_error(&self.events_path, &e))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Reads events buffered by a previous client; a missing file means none,
+/// and a line cut short by a crash is skipped
+fn load_events(path: &std::path::Path) -> TemplateResult<Vec<TelemetryEvent>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(TemplateError::io_error(path, &e)),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
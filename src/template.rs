@@ -1,22 +1,50 @@
 //! Core template functions for demonstration purposes
 
-use crate::error::{TemplateError, TemplateResult, MAX_INPUT_SIZE};
-use rand::Rng;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::cancellation::{check_cancelled, CancellationToken};
+use crate::config;
+use crate::config_file;
+use crate::directories::{self, StorageCategory};
+use crate::error::{
+    PreviewMode, TemplateError, TemplateResult, DEFAULT_MAX_SIZE, DEFAULT_PREVIEW_LENGTH,
+    MAX_INPUT_SIZE,
+};
+use crate::hashing::{hash_text, HashAlgorithm};
+use crate::history;
+use crate::metrics;
+use crate::runtime::run_with_timeout;
+use crate::sanitize::{sanitize, SanitizationOptions, SanitizationReport};
+use crate::shield;
+use crate::timing::{CallTiming, Stopwatch};
+use crate::transform::{apply_transforms, TextTransform};
+use crate::unicode::{is_nfc, LengthUnit, UnicodeNormalization};
+use crate::warnings::{Warning, WarningKind};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Exp, Normal, Uniform};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Result of an echo operation with metadata
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EchoResult {
     /// The echoed text
     pub text: String,
-    /// Length of the text
+    /// Length of the text, in bytes unless `TemplateConfig` selects another unit
     pub length: u32,
     /// Unix timestamp when the operation completed
     pub timestamp: u64,
-    /// Optional hash for debugging
+    /// Hex-encoded hash of the text, if a hash algorithm is selected
     pub hash: Option<String>,
+    /// Transformations applied to the input, in order
+    pub transforms_applied: Vec<TextTransform>,
+    /// What sanitization changed, if sanitization is enabled
+    pub sanitization: Option<SanitizationReport>,
+    /// Non-fatal issues noticed while producing the result
+    pub warnings: Vec<Warning>,
+    /// Time spent in each phase, if `LibraryConfig::collect_timing` is set
+    pub timing: Option<CallTiming>,
 }
 
 impl EchoResult {
@@ -33,34 +61,202 @@ impl EchoResult {
             length,
             timestamp,
             hash: None,
+            transforms_applied: Vec::new(),
+            sanitization: None,
+            warnings: Vec::new(),
+            timing: None,
         }
     }
 
-    /// Create with hash for debugging
+    /// Create with a precomputed hash
     pub fn with_hash(mut self, hash: String) -> Self {
         self.hash = Some(hash);
         self
     }
+
+    /// Create with the list of transformations that produced the text
+    pub fn with_transforms(mut self, transforms: Vec<TextTransform>) -> Self {
+        self.transforms_applied = transforms;
+        self
+    }
+
+    /// Create with the report of the sanitization applied to the input
+    pub fn with_sanitization(mut self, report: SanitizationReport) -> Self {
+        self.sanitization = Some(report);
+        self
+    }
+
+    /// Create with non-fatal issues noticed while producing the text
+    pub fn with_warnings(mut self, warnings: Vec<Warning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Create with the time spent producing the text
+    pub fn with_timing(mut self, timing: CallTiming) -> Self {
+        self.timing = Some(timing);
+        self
+    }
 }
 
 /// Configuration for template operations
-#[derive(Debug, Clone)]
+///
+/// Can be loaded from JSON or TOML with `from_json` / `from_toml`. Missing
+/// fields take their default values and unknown fields are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TemplateConfig {
-    /// Maximum input size allowed
+    /// Maximum input size allowed, in bytes, before and after normalization
     max_input_size: u64,
     /// Whether to enable validation
     enable_validation: bool,
+    /// Algorithm used to compute `EchoResult::hash`
+    hash_algorithm: HashAlgorithm,
+    /// Transformations applied to the input before echoing, in order
+    transforms: Vec<TextTransform>,
+    /// Default timeout for `validate_and_echo` when the call does not set one
+    timeout_ms: Option<u64>,
+    /// Whether validation accepts control characters other than tab/newline/CR
+    allow_control_chars: bool,
+    /// Number of input characters included in `InvalidInput` previews
+    preview_length: u32,
+    /// Whether `InvalidInput` previews show the input or a hash of it
+    preview_mode: PreviewMode,
+    /// Sanitization applied to the input before validation
+    sanitization: SanitizationOptions,
+    /// Unicode normalization applied to the input before validation
+    normalization: UnicodeNormalization,
+    /// Unit used for `EchoResult::length`
+    length_unit: LengthUnit,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            max_input_size: DEFAULT_MAX_SIZE as u64,
+            enable_validation: true,
+            hash_algorithm: HashAlgorithm::default(),
+            transforms: Vec::new(),
+            timeout_ms: None,
+            allow_control_chars: true,
+            preview_length: DEFAULT_PREVIEW_LENGTH,
+            preview_mode: PreviewMode::default(),
+            sanitization: SanitizationOptions::default(),
+            normalization: UnicodeNormalization::default(),
+            length_unit: LengthUnit::default(),
+        }
+    }
 }
 
 impl TemplateConfig {
-    /// Create a new TemplateConfig
+    /// Create a new TemplateConfig hashing results with SHA-256
     pub fn new(max_input_size: u64, enable_validation: bool) -> Self {
+        Self::with_hash_algorithm(max_input_size, enable_validation, HashAlgorithm::default())
+    }
+
+    /// Start building a TemplateConfig from the defaults
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_multiplatform_template_lib::{HashAlgorithm, TemplateConfig};
+    ///
+    /// let config = TemplateConfig::builder()
+    ///     .max_input_size(1024)
+    ///     .hash_algorithm(HashAlgorithm::Blake3)
+    ///     .build();
+    /// assert_eq!(config.max_input_size(), 1024);
+    /// ```
+    pub fn builder() -> Arc<TemplateConfigBuilder> {
+        Arc::new(TemplateConfigBuilder::new())
+    }
+
+    /// Create a new TemplateConfig with an explicit hash algorithm
+    pub fn with_hash_algorithm(
+        max_input_size: u64,
+        enable_validation: bool,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
         Self {
             max_input_size,
             enable_validation,
+            hash_algorithm,
+            ..Self::default()
+        }
+    }
+
+    /// Create a new TemplateConfig applying `transforms` in order before echoing
+    pub fn with_transforms(
+        max_input_size: u64,
+        enable_validation: bool,
+        transforms: Vec<TextTransform>,
+    ) -> Self {
+        Self {
+            transforms,
+            ..Self::new(max_input_size, enable_validation)
         }
     }
 
+    /// Parse a TemplateConfig from JSON
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rust_multiplatform_template_lib::{HashAlgorithm, TemplateConfig};
+    ///
+    /// let config =
+    ///     TemplateConfig::from_json(r#"{"max_input_size": 64, "hash_algorithm": "blake3"}"#.into())
+    ///         .unwrap();
+    /// assert_eq!(config.max_input_size(), 64);
+    /// assert_eq!(config.hash_algorithm(), HashAlgorithm::Blake3);
+    /// ```
+    pub fn from_json(json: String) -> TemplateResult<Self> {
+        shield::guard("from_json", || {
+            serde_json::from_str(&json).map_err(|e| TemplateError::json_error(&e))
+        })
+    }
+
+    /// Parse a TemplateConfig from TOML
+    pub fn from_toml(toml: String) -> TemplateResult<Self> {
+        shield::guard("from_toml", || {
+            toml::from_str(&toml).map_err(|e| TemplateError::toml_error(&e, &toml))
+        })
+    }
+
+    /// Load a TemplateConfig saved with `save`
+    ///
+    /// Fields missing from the file keep their defaults, and fields written
+    /// by a newer release that this one does not understand are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be read, including when it is missing
+    /// * `Err(TemplateError::ParseError)` - If the file is not a saved configuration
+    pub fn load(path: String) -> TemplateResult<Self> {
+        shield::guard("TemplateConfig::load", || {
+            let path = directories::resolve(StorageCategory::Data, &path);
+            config_file::load(&path)?.ok_or_else(|| {
+                TemplateError::io_error(&path, &std::io::Error::from(std::io::ErrorKind::NotFound))
+            })
+        })
+    }
+
+    /// Save this configuration to `path` atomically, with a schema version
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::IoError)` - If the file cannot be written
+    pub fn save(&self, path: String) -> TemplateResult<()> {
+        shield::guard("TemplateConfig::save", || {
+            config_file::save(&directories::resolve(StorageCategory::Data, &path), self)
+        })
+    }
+
+    /// Serialize this configuration to JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("TemplateConfig is always serializable")
+    }
+
     /// Get the maximum input size
     pub fn max_input_size(&self) -> u64 {
         self.max_input_size
@@ -71,81 +267,193 @@ impl TemplateConfig {
         self.enable_validation
     }
 
+    /// Get the hash algorithm
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Get the transformations applied before echoing
+    pub fn transforms(&self) -> Vec<TextTransform> {
+        self.transforms.clone()
+    }
+
+    /// Get the default timeout in milliseconds
+    pub fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+
+    /// Check if control characters are allowed by validation
+    pub fn allow_control_chars(&self) -> bool {
+        self.allow_control_chars
+    }
+
+    /// Get the number of characters included in input previews
+    pub fn preview_length(&self) -> u32 {
+        self.preview_length
+    }
+
+    /// Get how input previews are formatted
+    pub fn preview_mode(&self) -> PreviewMode {
+        self.preview_mode
+    }
+
+    /// Get the sanitization options
+    pub fn sanitization(&self) -> SanitizationOptions {
+        self.sanitization
+    }
+
+    /// Get the Unicode normalization form
+    pub fn normalization(&self) -> UnicodeNormalization {
+        self.normalization
+    }
+
+    /// Get the unit used for `EchoResult::length`
+    pub fn length_unit(&self) -> LengthUnit {
+        self.length_unit
+    }
+
     /// Validate and echo input using this configuration (async)
+    ///
+    /// If `timeout_ms` (or the configured default timeout) is set and the
+    /// operation does not complete in time, `TemplateError::Timeout` is returned.
     pub async fn validate_and_echo(
         &self,
         input: String,
         token: Option<Arc<CancellationToken>>,
+        timeout_ms: Option<u64>,
     ) -> TemplateResult<Option<EchoResult>> {
-        // Check cancellation
-        if let Some(ref t) = token {
-            if t.is_cancelled() {
-                return Err(TemplateError::operation_cancelled("validate_and_echo"));
-            }
-        }
+        shield::guard_async("validate_and_echo", async move {
+            let started = Instant::now();
+            let stopwatch = Stopwatch::start();
+            let timeout_ms = timeout_ms.or(self.timeout_ms);
+            let result = run_with_timeout("validate_and_echo", timeout_ms, async {
+                // Check cancellation
+                check_cancelled(token.as_deref(), "validate_and_echo")?;
 
-        tokio::task::yield_now().await;
+                tokio::task::yield_now().await;
 
-        // Check cancellation again
-        if let Some(ref t) = token {
-            if t.is_cancelled() {
-                return Err(TemplateError::operation_cancelled("validate_and_echo"));
-            }
-        }
+                // Check cancellation again
+                check_cancelled(token.as_deref(), "validate_and_echo")?;
 
-        validate_and_echo_internal(&input, self.max_input_size as usize, self.enable_validation)
+                validate_and_echo_internal(&input, self, stopwatch)
+            })
+            .await;
+            history::record("validate_and_echo", started, &result);
+            result
+        })
+        .await
     }
 }
 
-/// Cancellation token for async operations
-#[derive(Debug, Clone)]
-pub struct CancellationToken {
-    cancelled: Arc<AtomicBool>,
+/// Builder for `TemplateConfig`, starting from `TemplateConfig::default()`
+///
+/// Setters take and return `Arc<Self>` so the same chained calls work from
+/// Swift and Kotlin.
+#[derive(Debug, Default)]
+pub struct TemplateConfigBuilder {
+    config: Mutex<TemplateConfig>,
 }
 
-impl CancellationToken {
-    /// Create a new cancellation token
+impl TemplateConfigBuilder {
+    /// Create a builder with default settings
     pub fn new() -> Self {
-        Self {
-            cancelled: Arc::new(AtomicBool::new(false)),
-        }
+        Self::default()
     }
 
-    /// Cancel the operation
-    pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::Release);
+    fn update(self: Arc<Self>, f: impl FnOnce(&mut TemplateConfig)) -> Arc<Self> {
+        f(&mut self.config.lock().unwrap());
+        self
     }
 
-    /// Check if the operation is cancelled
-    pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::Acquire)
+    /// Set the maximum input size in bytes
+    pub fn max_input_size(self: Arc<Self>, value: u64) -> Arc<Self> {
+        self.update(|c| c.max_input_size = value)
     }
-}
 
-impl Default for CancellationToken {
-    fn default() -> Self {
-        Self {
-            cancelled: Arc::new(AtomicBool::new(false)),
-        }
+    /// Enable or disable input validation
+    pub fn enable_validation(self: Arc<Self>, value: bool) -> Arc<Self> {
+        self.update(|c| c.enable_validation = value)
+    }
+
+    /// Set the hash algorithm for `EchoResult::hash`
+    pub fn hash_algorithm(self: Arc<Self>, value: HashAlgorithm) -> Arc<Self> {
+        self.update(|c| c.hash_algorithm = value)
+    }
+
+    /// Set the transformations applied before echoing
+    pub fn transforms(self: Arc<Self>, value: Vec<TextTransform>) -> Arc<Self> {
+        self.update(|c| c.transforms = value)
+    }
+
+    /// Set the default timeout in milliseconds (`None` for no timeout)
+    pub fn timeout_ms(self: Arc<Self>, value: Option<u64>) -> Arc<Self> {
+        self.update(|c| c.timeout_ms = value)
+    }
+
+    /// Allow or reject control characters during validation
+    pub fn allow_control_chars(self: Arc<Self>, value: bool) -> Arc<Self> {
+        self.update(|c| c.allow_control_chars = value)
+    }
+
+    /// Set the number of characters included in input previews
+    pub fn preview_length(self: Arc<Self>, value: u32) -> Arc<Self> {
+        self.update(|c| c.preview_length = value)
+    }
+
+    /// Show the input (`Truncate`) or only a hash of it (`Redact`) in previews
+    pub fn preview_mode(self: Arc<Self>, value: PreviewMode) -> Arc<Self> {
+        self.update(|c| c.preview_mode = value)
+    }
+
+    /// Set the sanitization applied before validation
+    pub fn sanitization(self: Arc<Self>, value: SanitizationOptions) -> Arc<Self> {
+        self.update(|c| c.sanitization = value)
+    }
+
+    /// Set the Unicode normalization form applied before validation
+    pub fn normalization(self: Arc<Self>, value: UnicodeNormalization) -> Arc<Self> {
+        self.update(|c| c.normalization = value)
+    }
+
+    /// Set the unit used for `EchoResult::length`
+    pub fn length_unit(self: Arc<Self>, value: LengthUnit) -> Arc<Self> {
+        self.update(|c| c.length_unit = value)
+    }
+
+    /// Build the configuration
+    pub fn build(&self) -> Arc<TemplateConfig> {
+        Arc::new(self.config.lock().unwrap().clone())
     }
 }
 
 /// Validates input for common issues
-fn validate_input(input: &str) -> TemplateResult<()> {
+fn validate_input(input: &str, config: &TemplateConfig) -> TemplateResult<()> {
+    let invalid = |message: &str| {
+        TemplateError::invalid_input_with_preview(
+            message.to_string(),
+            Some(input),
+            config.preview_length as usize,
+            config.preview_mode,
+        )
+    };
+
     // Check for null bytes
     if input.contains('\0') {
-        return Err(TemplateError::invalid_input(
-            "Input contains null bytes".to_string(),
-            Some(input),
-        ));
+        return Err(invalid("Input contains null bytes"));
     }
 
     // Validate UTF-8 (already validated by Rust, but check boundaries)
     if !input.is_empty() && !input.is_char_boundary(input.len()) {
-        return Err(TemplateError::invalid_input(
-            "Invalid UTF-8 sequence".to_string(),
-            Some(input),
-        ));
+        return Err(invalid("Invalid UTF-8 sequence"));
+    }
+
+    // Reject control characters other than common whitespace
+    if !config.allow_control_chars
+        && input
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+    {
+        return Err(invalid("Input contains control characters"));
     }
 
     Ok(())
@@ -154,38 +462,115 @@ fn validate_input(input: &str) -> TemplateResult<()> {
 /// Internal implementation of echo with validation
 fn validate_and_echo_internal(
     input: &str,
-    max_size: usize,
-    enable_validation: bool,
+    config: &TemplateConfig,
+    mut stopwatch: Stopwatch,
 ) -> TemplateResult<Option<EchoResult>> {
+    let queue_wait_us = stopwatch.lap();
+
     // Validate input size
     let input_size = input.len();
+    metrics::add("input_bytes", input_size as u64);
+    let max_size = config.max_input_size as usize;
     if input_size > max_size {
         return Err(TemplateError::input_too_large(input_size, max_size, input));
     }
 
+    // Optional sanitization, before validation so stripped characters pass
+    let (input, report) = if config.sanitization.is_enabled() {
+        let (text, report) = sanitize(input, &config.sanitization)?;
+        (Cow::Owned(text), Some(report))
+    } else {
+        (Cow::Borrowed(input), None)
+    };
+
+    let mut warnings = Vec::new();
+    if report.is_some_and(|r| r != SanitizationReport::default()) {
+        warnings.push(Warning::new(
+            WarningKind::InputSanitized,
+            "Sanitization changed the input",
+        ));
+    }
+
+    // Optional Unicode normalization
+    let input = match config.normalization {
+        UnicodeNormalization::None => {
+            if !is_nfc(&input) {
+                warnings.push(Warning::new(
+                    WarningKind::InputNotNormalized,
+                    "Input is not in NFC form; its hash depends on how the text was composed",
+                ));
+            }
+            input
+        }
+        form => {
+            let normalized = form.apply(&input);
+            // Compatibility forms can grow the text many times over, e.g.
+            // U+FDFA becomes 18 characters, so check the limit again
+            if normalized.len() > max_size {
+                return Err(TemplateError::input_too_large(
+                    normalized.len(),
+                    max_size,
+                    &normalized,
+                ));
+            }
+            if normalized != *input {
+                warnings.push(Warning::new(
+                    WarningKind::InputNormalized,
+                    format!("Input was normalized to {}", form.name()),
+                ));
+            }
+            Cow::Owned(normalized)
+        }
+    };
+
     // Optional validation
-    if enable_validation {
-        validate_input(input)?;
+    if config.enable_validation {
+        validate_input(&input, config)?;
     }
+    let validation_us = stopwatch.lap();
+
+    // Apply configured transformations
+    let text = apply_transforms(&input, &config.transforms);
 
     // Return None for empty strings
-    if input.is_empty() {
+    if text.is_empty() {
         return Ok(None);
     }
 
     // Create result with metadata
-    let result = EchoResult::new(input.to_string());
-    Ok(Some(result))
+    let hash = hash_text(&text, config.hash_algorithm);
+    let length = config.length_unit.measure(&text);
+    let mut result = EchoResult::new(text).with_transforms(config.transforms.clone());
+    result.length = length;
+    if let Some(hash) = hash {
+        result = result.with_hash(hash);
+    }
+    if let Some(report) = report {
+        result = result.with_sanitization(report);
+    }
+    if config::current().collect_timing {
+        result = result.with_timing(CallTiming {
+            queue_wait_us,
+            validation_us,
+            compute_us: stopwatch.lap(),
+            total_us: stopwatch.total(),
+        });
+    }
+    Ok(Some(result.with_warnings(warnings)))
 }
 
 /// Echoes back the input string with metadata, or returns None if the string is empty
 ///
 /// This function validates the input size to prevent resource exhaustion attacks.
-/// The maximum allowed input size is 1MB (1,000,000 bytes).
+/// The maximum allowed input size is `LibraryConfig::max_input_size`, 1MB
+/// (1,000,000 bytes) unless changed with `initialize`. The result's `hash`
+/// is the SHA-256 of the text; use `TemplateConfig` to select another algorithm.
 ///
 /// # Arguments
 ///
 /// * `input` - The string to echo back
+/// * `token` - Optional cancellation token
+/// * `timeout_ms` - Optional timeout in milliseconds
 ///
 /// # Returns
 ///
@@ -193,6 +578,7 @@ fn validate_and_echo_internal(
 /// * `Ok(None)` - If the input string is empty
 /// * `Err(TemplateError::InputTooLarge)` - If input exceeds maximum size
 /// * `Err(TemplateError::InvalidInput)` - If input contains invalid data
+/// * `Err(TemplateError::Timeout)` - If `timeout_ms` elapsed first
 ///
 /// # Example
 ///
@@ -200,13 +586,13 @@ fn validate_and_echo_internal(
 /// use rust_multiplatform_template_lib::echo;
 ///
 /// # tokio_test::block_on(async {
-/// let result = echo("Hello".to_string(), None).await.unwrap();
+/// let result = echo("Hello".to_string(), None, None).await.unwrap();
 /// assert!(result.is_some());
 /// let echo_result = result.unwrap();
 /// assert_eq!(echo_result.text, "Hello");
 /// assert_eq!(echo_result.length, 5);
 ///
-/// let empty = echo("".to_string(), None).await.unwrap();
+/// let empty = echo("".to_string(), None, None).await.unwrap();
 /// assert!(empty.is_none());
 /// # })
 /// ```
@@ -218,26 +604,36 @@ fn validate_and_echo_internal(
 pub async fn echo(
     input: String,
     token: Option<Arc<CancellationToken>>,
+    timeout_ms: Option<u64>,
 ) -> TemplateResult<Option<EchoResult>> {
-    // Check cancellation before starting
-    if let Some(ref t) = token {
-        if t.is_cancelled() {
-            return Err(TemplateError::operation_cancelled("echo"));
-        }
-    }
+    shield::guard_async("echo", async move {
+        let started = Instant::now();
+        let stopwatch = Stopwatch::start();
+        let result = run_with_timeout("echo", timeout_ms, async {
+            // Check cancellation before starting
+            check_cancelled(token.as_deref(), "echo")?;
 
-    // Simulate some async work
-    tokio::task::yield_now().await;
+            // Simulate some async work
+            tokio::task::yield_now().await;
 
-    // Check cancellation during processing
-    if let Some(ref t) = token {
-        if t.is_cancelled() {
-            return Err(TemplateError::operation_cancelled("echo"));
-        }
-    }
+            // Check cancellation during processing
+            check_cancelled(token.as_deref(), "echo")?;
 
-    // Perform the actual echo operation
-    validate_and_echo_internal(&input, MAX_INPUT_SIZE, true)
+            // Perform the actual echo operation, with the library's limit
+            // and input previews
+            let library = config::current();
+            let config = TemplateConfig {
+                preview_length: library.preview_length,
+                preview_mode: library.preview_mode,
+                ..TemplateConfig::new(library.max_input_size, true)
+            };
+            validate_and_echo_internal(&input, &config, stopwatch)
+        })
+        .await;
+        history::record("echo", started, &result);
+        result
+    })
+    .await
 }
 
 /// Generates a random number between 0.0 and 1.0 (async)
@@ -262,3 +658,254 @@ pub async fn random() -> f64 {
     tokio::task::yield_now().await;
     rand::rng().random()
 }
+
+/// Validates that `min..=max` is a non-empty range
+fn validate_range(min: i64, max: i64) -> TemplateResult<()> {
+    if min > max {
+        return Err(TemplateError::invalid_input(
+            format!("Invalid range: min ({}) is greater than max ({})", min, max),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Generates a random integer in the inclusive range [min, max] (async)
+///
+/// # Returns
+///
+/// * `Ok(i64)` - A random value with `min <= value <= max`
+/// * `Err(TemplateError::InvalidInput)` - If `min` is greater than `max`
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::random_int;
+///
+/// # tokio_test::block_on(async {
+/// let value = random_int(1, 6, None).await.unwrap();
+/// assert!((1..=6).contains(&value));
+/// # })
+/// ```
+pub async fn random_int(
+    min: i64,
+    max: i64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<i64> {
+    shield::guard_async("random_int", async move {
+        validate_range(min, max)?;
+        check_cancelled(token.as_deref(), "random_int")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_int")?;
+        Ok(rand::rng().random_range(min..=max))
+    })
+    .await
+}
+
+/// Generates `len` random bytes (async)
+///
+/// Uses the fast thread-local RNG; not suitable for keys or nonces.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - The random bytes
+/// * `Err(TemplateError::InvalidInput)` - If `len` exceeds 1MB
+pub async fn random_bytes(
+    len: u32,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<Vec<u8>> {
+    shield::guard_async("random_bytes", async move {
+        if len as usize > MAX_INPUT_SIZE {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "Requested {} bytes exceeds maximum of {} bytes",
+                    len, MAX_INPUT_SIZE
+                ),
+                None,
+            ));
+        }
+        check_cancelled(token.as_deref(), "random_bytes")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_bytes")?;
+        let mut bytes = vec![0u8; len as usize];
+        rand::rng().fill(bytes.as_mut_slice());
+        Ok(bytes)
+    })
+    .await
+}
+
+/// Picks one of the given items uniformly at random (async)
+///
+/// # Returns
+///
+/// * `Ok(String)` - The chosen item
+/// * `Err(TemplateError::InvalidInput)` - If `items` is empty
+pub async fn random_choice(
+    items: Vec<String>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<String> {
+    shield::guard_async("random_choice", async move {
+        if items.is_empty() {
+            return Err(TemplateError::invalid_input(
+                "Cannot choose from an empty list".to_string(),
+                None,
+            ));
+        }
+        check_cancelled(token.as_deref(), "random_choice")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_choice")?;
+        let index = rand::rng().random_range(0..items.len());
+        Ok(items.into_iter().nth(index).unwrap())
+    })
+    .await
+}
+
+/// Samples a value from a normal (Gaussian) distribution (async)
+///
+/// # Returns
+///
+/// * `Ok(f64)` - A sample from N(`mean`, `std_dev`²)
+/// * `Err(TemplateError::InvalidInput)` - If `std_dev` is negative or not finite
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::random_normal;
+///
+/// # tokio_test::block_on(async {
+/// let value = random_normal(10.0, 0.0, None).await.unwrap();
+/// assert_eq!(value, 10.0);
+/// # })
+/// ```
+pub async fn random_normal(
+    mean: f64,
+    std_dev: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    shield::guard_async("random_normal", async move {
+        if std_dev < 0.0 {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "Invalid normal distribution: std_dev ({}) must not be negative",
+                    std_dev
+                ),
+                None,
+            ));
+        }
+        let distribution = Normal::new(mean, std_dev).map_err(|e| {
+            TemplateError::invalid_input(format!("Invalid normal distribution: {}", e), None)
+        })?;
+        check_cancelled(token.as_deref(), "random_normal")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_normal")?;
+        Ok(rand::rng().sample(distribution))
+    })
+    .await
+}
+
+/// Samples a value from an exponential distribution with rate `lambda` (async)
+///
+/// Useful for jittered backoff and modelling inter-arrival times.
+///
+/// # Returns
+///
+/// * `Ok(f64)` - A non-negative sample with mean `1 / lambda`
+/// * `Err(TemplateError::InvalidInput)` - If `lambda` is not positive
+pub async fn random_exponential(
+    lambda: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    shield::guard_async("random_exponential", async move {
+        if lambda <= 0.0 || lambda.is_nan() {
+            return Err(TemplateError::invalid_input(
+                format!(
+                    "Invalid exponential distribution: lambda ({}) must be positive",
+                    lambda
+                ),
+                None,
+            ));
+        }
+        let distribution = Exp::new(lambda).map_err(|e| {
+            TemplateError::invalid_input(format!("Invalid exponential distribution: {}", e), None)
+        })?;
+        check_cancelled(token.as_deref(), "random_exponential")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_exponential")?;
+        Ok(rand::rng().sample(distribution))
+    })
+    .await
+}
+
+/// Samples a value uniformly from the range [low, high) (async)
+///
+/// # Returns
+///
+/// * `Ok(f64)` - A sample with `low <= value < high`
+/// * `Err(TemplateError::InvalidInput)` - If the range is empty or not finite
+pub async fn random_uniform(
+    low: f64,
+    high: f64,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<f64> {
+    shield::guard_async("random_uniform", async move {
+        let distribution = Uniform::new(low, high).map_err(|e| {
+            TemplateError::invalid_input(format!("Invalid uniform distribution: {}", e), None)
+        })?;
+        check_cancelled(token.as_deref(), "random_uniform")?;
+        tokio::task::yield_now().await;
+        check_cancelled(token.as_deref(), "random_uniform")?;
+        Ok(rand::rng().sample(distribution))
+    })
+    .await
+}
+
+/// Generates a deterministic random number between 0.0 and 1.0 from a seed (async)
+///
+/// The same seed always produces the same value on every platform. This is
+/// equivalent to the first `next_double()` of a `SeededRng` created with `seed`.
+///
+/// # Example
+///
+/// ```
+/// use rust_multiplatform_template_lib::random_seeded;
+///
+/// # tokio_test::block_on(async {
+/// assert_eq!(random_seeded(42).await, random_seeded(42).await);
+/// # })
+/// ```
+pub async fn random_seeded(seed: u64) -> f64 {
+    tokio::task::yield_now().await;
+    SeededRng::new(seed).next_double()
+}
+
+/// Deterministic random number generator for reproducible sequences
+///
+/// Backed by ChaCha8, whose output is stable across platforms and library
+/// versions, so host-side tests and simulations can replay the same sequence.
+/// Not suitable for cryptographic use.
+#[derive(Debug)]
+pub struct SeededRng {
+    rng: Mutex<ChaCha8Rng>,
+}
+
+impl SeededRng {
+    /// Create a new generator from a seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(ChaCha8Rng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Next random double in the range [0.0, 1.0)
+    pub fn next_double(&self) -> f64 {
+        self.rng.lock().unwrap().random()
+    }
+
+    /// Next random integer in the inclusive range [min, max]
+    pub fn next_int(&self, min: i64, max: i64) -> TemplateResult<i64> {
+        shield::guard("next_int", || {
+            validate_range(min, max)?;
+            Ok(self.rng.lock().unwrap().random_range(min..=max))
+        })
+    }
+}
@@ -0,0 +1,206 @@
+//! Throttling helpers for expensive operations driven by user input
+
+use crate::cancellation::{self, CancellationToken};
+use crate::error::{TemplateError, TemplateResult};
+use crate::runtime;
+use crate::shield;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket rate limiter
+///
+/// Holds up to `capacity` permits and refills at `refill_per_second`.
+/// Bursts of up to `capacity` calls go through immediately; after that,
+/// calls are spaced out to the refill rate.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    permits: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Create a full limiter
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If `capacity` is 0 or the refill
+    ///   rate is not a positive number
+    pub fn new(capacity: u32, refill_per_second: f64) -> TemplateResult<Self> {
+        shield::guard("RateLimiter::new", || {
+            if capacity == 0 {
+                return Err(TemplateError::invalid_input(
+                    "capacity must be greater than 0".to_string(),
+                    None,
+                ));
+            }
+            if !(refill_per_second.is_finite() && refill_per_second > 0.0) {
+                return Err(TemplateError::invalid_input(
+                    format!(
+                        "refill_per_second must be a positive number, got {}",
+                        refill_per_second
+                    ),
+                    None,
+                ));
+            }
+            Ok(Self {
+                capacity: capacity as f64,
+                refill_per_second,
+                bucket: Mutex::new(Bucket {
+                    permits: capacity as f64,
+                    refilled_at: Instant::now(),
+                }),
+            })
+        })
+    }
+
+    /// Takes a permit if one is available, without waiting
+    pub fn try_acquire(&self) -> bool {
+        self.take_or_wait().is_none()
+    }
+
+    /// Waits until a permit is available and takes it (async)
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled while waiting
+    pub async fn acquire(&self, token: Option<Arc<CancellationToken>>) -> TemplateResult<()> {
+        shield::guard_async("acquire", async move {
+            loop {
+                cancellation::check_cancelled(token.as_deref(), "rate_limiter_acquire")?;
+                match self.take_or_wait() {
+                    None => return Ok(()),
+                    Some(wait) => {
+                        runtime::sleep_cancellable(wait, token.as_deref(), "rate_limiter_acquire")
+                            .await?
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// Number of whole permits currently available
+    pub fn available_permits(&self) -> u32 {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.permits.floor() as u32
+    }
+
+    /// Takes a permit, or returns how long until one is available
+    fn take_or_wait(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        if bucket.permits >= 1.0 {
+            bucket.permits -= 1.0;
+            None
+        } else {
+            let missing = 1.0 - bucket.permits;
+            Some(Duration::from_secs_f64(missing / self.refill_per_second))
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.permits = (bucket.permits + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.refilled_at = now;
+    }
+}
+
+/// Coalesces rapid calls so only the last one in a burst goes ahead
+///
+/// Each call to `debounce()` waits for the quiet period and returns `true`
+/// only if no other call arrived in the meantime. Hosts call it on every
+/// keystroke and run the expensive operation when it returns `true`.
+pub struct Debouncer {
+    delay: Duration,
+    /// Incremented by every call; a waiter wins if it is still current
+    generation: AtomicU64,
+}
+
+impl Debouncer {
+    /// Create a debouncer with the given quiet period
+    pub fn new(delay_ms: u64) -> Self {
+        Self {
+            delay: Duration::from_millis(delay_ms),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits for the quiet period; `true` if this was the last call (async)
+    pub async fn debounce(&self) -> bool {
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        runtime::sleep(self.delay).await;
+        self.generation.load(Ordering::Acquire) == generation
+    }
+
+    /// Makes every pending `debounce()` call return `false`
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Paces a byte stream, or several sharing it, to a rate in bytes per second
+///
+/// Bytes are taken after they arrive, so the bucket can go into debt; the
+/// caller then waits before reading more, which lets TCP flow control slow
+/// the sender. Up to one second of unused rate is saved for bursts.
+pub(crate) struct ByteThrottle {
+    bucket: Mutex<ByteBucket>,
+}
+
+struct ByteBucket {
+    /// `None` for no limit
+    bytes_per_second: Option<u64>,
+    /// Bytes that may go through now; negative while in debt
+    available: f64,
+    refilled_at: Instant,
+}
+
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+impl ByteThrottle {
+    pub(crate) fn new(bytes_per_second: Option<u64>) -> Self {
+        Self {
+            bucket: Mutex::new(ByteBucket {
+                bytes_per_second,
+                available: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Changes the rate; a new rate starts from an empty bucket
+    pub(crate) fn set_rate(&self, bytes_per_second: Option<u64>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.bytes_per_second != bytes_per_second {
+            *bucket = ByteBucket {
+                bytes_per_second,
+                available: 0.0,
+                refilled_at: Instant::now(),
+            };
+        }
+    }
+
+    /// Takes `bytes` and returns how long to wait before taking more
+    pub(crate) fn consume(&self, bytes: u64) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let Some(rate) = bucket.bytes_per_second.map(|rate| rate as f64) else {
+            return Duration::ZERO;
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.available = (bucket.available + elapsed * rate).min(rate) - bytes as f64;
+        bucket.refilled_at = now;
+        if bucket.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available / rate)
+        }
+    }
+}
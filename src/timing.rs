@@ -0,0 +1,50 @@
+//! Per-call timing attached to results when `LibraryConfig::collect_timing` is set
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Where the time of one call went, in microseconds
+///
+/// Phases are measured from the first time the call's future is polled.
+/// `total_us` can exceed the sum of the phases by the time spent building
+/// the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallTiming {
+    /// Waiting to be scheduled after the call started
+    pub queue_wait_us: u64,
+    /// Sanitizing, normalizing, and validating the input
+    pub validation_us: u64,
+    /// Transforming, measuring, and hashing the text
+    pub compute_us: u64,
+    /// From the start of the call until the result was ready
+    pub total_us: u64,
+}
+
+/// Measures consecutive phases of a call
+pub(crate) struct Stopwatch {
+    started: Instant,
+    lap: Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            lap: now,
+        }
+    }
+
+    /// Microseconds since the previous lap, or the start
+    pub(crate) fn lap(&mut self) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.lap);
+        self.lap = now;
+        elapsed.as_micros() as u64
+    }
+
+    /// Microseconds since the start
+    pub(crate) fn total(&self) -> u64 {
+        self.started.elapsed().as_micros() as u64
+    }
+}
@@ -0,0 +1,57 @@
+//! Text transformations applied by `TemplateConfig` before echoing
+
+use serde::{Deserialize, Serialize};
+
+/// A text transformation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextTransform {
+    /// Remove leading and trailing whitespace
+    Trim,
+    /// Convert to lowercase
+    Lowercase,
+    /// Convert to uppercase
+    Uppercase,
+    /// Replace every run of whitespace with a single space
+    CollapseWhitespace,
+    /// Reverse the order of characters
+    Reverse,
+}
+
+impl TextTransform {
+    /// Applies this transformation to `input`
+    pub(crate) fn apply(self, input: &str) -> String {
+        match self {
+            Self::Trim => input.trim().to_string(),
+            Self::Lowercase => input.to_lowercase(),
+            Self::Uppercase => input.to_uppercase(),
+            Self::CollapseWhitespace => collapse_whitespace(input),
+            Self::Reverse => input.chars().rev().collect(),
+        }
+    }
+}
+
+/// Applies each transformation in order
+pub(crate) fn apply_transforms(input: &str, transforms: &[TextTransform]) -> String {
+    transforms
+        .iter()
+        .fold(input.to_string(), |text, transform| transform.apply(&text))
+}
+
+/// Replaces every run of whitespace with a single space, keeping the ends
+fn collapse_whitespace(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut in_whitespace = false;
+    for c in input.chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                output.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            output.push(c);
+            in_whitespace = false;
+        }
+    }
+    output
+}
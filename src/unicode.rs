@@ -0,0 +1,79 @@
+//! Unicode normalization and length measurement
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization as _;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Unicode normalization form applied to input before processing
+///
+/// Normalizing makes the hash and length of an `EchoResult` independent of
+/// whether the host sent precomposed (é) or decomposed (e + ◌́) text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeNormalization {
+    /// Leave the input unchanged (default)
+    #[default]
+    None,
+    /// Canonical composition
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+    /// Compatibility composition
+    Nfkc,
+    /// Compatibility decomposition
+    Nfkd,
+}
+
+impl UnicodeNormalization {
+    /// Normalizes `input`, or returns it unchanged for `None`
+    pub(crate) fn apply(self, input: &str) -> String {
+        match self {
+            Self::None => input.to_string(),
+            Self::Nfc => input.nfc().collect(),
+            Self::Nfd => input.nfd().collect(),
+            Self::Nfkc => input.nfkc().collect(),
+            Self::Nfkd => input.nfkd().collect(),
+        }
+    }
+
+    /// Name of the form as written in Unicode documents, e.g. "NFC"
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Nfc => "NFC",
+            Self::Nfd => "NFD",
+            Self::Nfkc => "NFKC",
+            Self::Nfkd => "NFKD",
+        }
+    }
+}
+
+/// Whether `input` is already in NFC form
+pub(crate) fn is_nfc(input: &str) -> bool {
+    unicode_normalization::is_nfc(input)
+}
+
+/// Unit used for `EchoResult::length`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    /// UTF-8 bytes (default)
+    #[default]
+    Bytes,
+    /// Unicode scalar values
+    Chars,
+    /// Extended grapheme clusters, i.e. user-perceived characters
+    Graphemes,
+}
+
+impl LengthUnit {
+    /// Measures `text` in this unit, saturating at `u32::MAX`
+    pub(crate) fn measure(self, text: &str) -> u32 {
+        let length = match self {
+            Self::Bytes => text.len(),
+            Self::Chars => text.chars().count(),
+            Self::Graphemes => text.graphemes(true).count(),
+        };
+        u32::try_from(length).unwrap_or(u32::MAX)
+    }
+}
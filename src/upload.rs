@@ -0,0 +1,241 @@
+//! Multipart file uploads (`http` feature)
+//!
+//! `upload_file` POSTs a file as `multipart/form-data`, for sending logs,
+//! crash reports, or user files through the shared core. The file is read
+//! in chunks as the request body is sent, so memory use does not grow with
+//! its size, and an `UploadListener` is told how many bytes have gone out.
+//! Without the `http` feature the types exist so the bindings stay the
+//! same, but uploads fail.
+
+use crate::cancellation::CancellationToken;
+use crate::directories::{self, StorageCategory};
+use crate::error::{TemplateError, TemplateResult};
+use crate::http::HttpResponse;
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::http::{self, HttpMethod, HttpRequest};
+#[cfg(feature = "http")]
+use crate::interceptors;
+#[cfg(feature = "http")]
+use std::fs::File;
+#[cfg(feature = "http")]
+use std::io::Read;
+#[cfg(feature = "http")]
+use std::path::Path;
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+/// Shortest time between two progress callbacks for one upload
+pub const UPLOAD_PROGRESS_INTERVAL_MS: u64 = 250;
+
+/// Name of the multipart part holding the file
+pub const UPLOAD_FILE_FIELD: &str = "file";
+
+/// Size of the chunks the file is read in
+#[cfg(feature = "http")]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Operation name used in errors
+#[cfg(feature = "http")]
+const OPERATION: &str = "upload_file";
+
+/// Progress of an upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// File bytes handed to the connection so far
+    pub sent_bytes: u64,
+    /// Size of the file
+    pub total_bytes: u64,
+    /// Transfer rate since the previous progress callback
+    pub bytes_per_second: u64,
+}
+
+/// Callback notified of upload progress, implemented by the host
+pub trait UploadListener: Send + Sync {
+    /// Called at most every `UPLOAD_PROGRESS_INTERVAL_MS`, and once more when
+    /// the whole file has been sent; may be called from any thread
+    fn on_upload_progress(&self, progress: UploadProgress);
+}
+
+/// Uploads the file at `path` to `url` as a multipart form and reads the response (async)
+///
+/// The form has a text part for each of `fields` and the file in a part
+/// named `UPLOAD_FILE_FIELD`, with its file name and an
+/// `application/octet-stream` content type. A relative `path` is resolved
+/// in the data directory set with `set_app_directories`. `headers` are sent
+/// with the request, e.g. an authorization token. As with `http_request`,
+/// any status code is returned as a response.
+///
+/// # Returns
+///
+/// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid,
+///   or the library was built without the `http` feature
+/// * `Err(TemplateError::IoError)` - If the file cannot be read
+/// * `Err(TemplateError::NetworkError)` - If no response was received
+/// * `Err(TemplateError::OperationCancelled)` - If the token was cancelled
+pub async fn upload_file(
+    url: String,
+    path: String,
+    fields: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    listener: Option<Box<dyn UploadListener>>,
+    token: Option<Arc<CancellationToken>>,
+) -> TemplateResult<HttpResponse> {
+    shield::guard_async("upload_file", async move {
+        let path = directories::resolve(StorageCategory::Data, &path);
+        #[cfg(feature = "http")]
+        {
+            let file = File::open(&path).map_err(|e| TemplateError::io_error(&path, &e))?;
+            let total_bytes = file
+                .metadata()
+                .map_err(|e| TemplateError::io_error(&path, &e))?
+                .len();
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| UPLOAD_FILE_FIELD.to_string());
+            let part = reqwest::multipart::Part::stream_with_length(
+                body(file, &path, total_bytes, listener.map(Arc::from)),
+                total_bytes,
+            )
+            .file_name(file_name)
+            .mime_str("application/octet-stream")
+            .expect("valid MIME type");
+            let form = fields
+                .into_iter()
+                .fold(reqwest::multipart::Form::new(), |form, (name, value)| {
+                    form.text(name, value)
+                })
+                .part(UPLOAD_FILE_FIELD, part);
+            let mut request = HttpRequest {
+                url,
+                method: HttpMethod::Post,
+                headers,
+                body: None,
+                timeout_ms: None,
+            };
+            auth::authorize(&request.url, &mut request.headers).await?;
+            if let Some(intercepted) = interceptors::intercept(&request).await {
+                request = intercepted;
+            }
+            // The form replaces any body, since the file is streamed
+            let builder = http::with_headers(
+                http::client()
+                    .post(http::parse_url(&request.url)?)
+                    .multipart(form),
+                &request.headers,
+            )?;
+
+            let url = request.url.clone();
+            let response = http::on_runtime(token.as_deref(), OPERATION, async move {
+                let response = builder
+                    .send()
+                    .await
+                    .map_err(|e| http::request_error(&url, None, &e))?;
+                http::read(response, None).await
+            })
+            .await?;
+            interceptors::observe(&request, &response).await;
+            Ok(response)
+        }
+        #[cfg(not(feature = "http"))]
+        {
+            let _ = (url, path, fields, headers, listener, token);
+            Err(TemplateError::invalid_input(
+                "upload_file requires the `http` feature".to_string(),
+                None,
+            ))
+        }
+    })
+    .await
+}
+
+/// Request body reading `file` a chunk at a time and reporting progress
+///
+/// Fails the request if the file ends before `total_bytes`, since the
+/// multipart length was already announced.
+#[cfg(feature = "http")]
+fn body(
+    file: File,
+    path: &Path,
+    total_bytes: u64,
+    listener: Option<Arc<dyn UploadListener>>,
+) -> reqwest::Body {
+    struct Reader {
+        file: File,
+        path: std::path::PathBuf,
+        total_bytes: u64,
+        sent: u64,
+        listener: Option<Arc<dyn UploadListener>>,
+        reported: (Instant, u64),
+    }
+
+    impl Reader {
+        fn report(&mut self) {
+            let Some(listener) = &self.listener else {
+                return;
+            };
+            let elapsed = self.reported.0.elapsed();
+            let done = self.sent == self.total_bytes;
+            if !done && elapsed < Duration::from_millis(UPLOAD_PROGRESS_INTERVAL_MS) {
+                return;
+            }
+            let bytes_per_second = match elapsed.as_secs_f64() {
+                secs if secs > 0.0 => ((self.sent - self.reported.1) as f64 / secs) as u64,
+                _ => 0,
+            };
+            self.reported = (Instant::now(), self.sent);
+            listener.on_upload_progress(UploadProgress {
+                sent_bytes: self.sent,
+                total_bytes: self.total_bytes,
+                bytes_per_second,
+            });
+        }
+    }
+
+    let reader = Reader {
+        file,
+        path: path.to_path_buf(),
+        total_bytes,
+        sent: 0,
+        listener,
+        reported: (Instant::now(), 0),
+    };
+    let chunks = futures_util::stream::unfold(reader, |mut reader| async move {
+        let remaining = reader.total_bytes - reader.sent;
+        if remaining == 0 {
+            return None;
+        }
+        let mut chunk = vec![0; remaining.min(CHUNK_SIZE as u64) as usize];
+        // Chunks are small, so reading them inline keeps the runtime responsive
+        let read = match reader.file.read(&mut chunk) {
+            Ok(0) => {
+                let error = std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "File shrank to {} of {} bytes during upload",
+                        reader.sent, reader.total_bytes
+                    ),
+                );
+                reader.sent = reader.total_bytes;
+                return Some((Err(error), reader));
+            }
+            Ok(read) => read,
+            Err(error) => {
+                log::warn!("Could not read {}: {}", reader.path.display(), error);
+                reader.sent = reader.total_bytes;
+                return Some((Err(error), reader));
+            }
+        };
+        chunk.truncate(read);
+        reader.sent += read as u64;
+        reader.report();
+        Some((Ok(chunk), reader))
+    });
+    reqwest::Body::wrap_stream(chunks)
+}
@@ -0,0 +1,191 @@
+//! Validation that reports every problem in an input at once
+
+use crate::sanitize::is_bidi_control;
+use crate::template::TemplateConfig;
+use crate::unicode::UnicodeNormalization;
+use std::sync::Arc;
+
+/// Most issues collected for one input; further issues set `truncated`
+pub const MAX_VALIDATION_ISSUES: u32 = 100;
+
+/// Kind of problem found by `validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationIssueKind {
+    /// Input is larger than `max_input_size`
+    TooLarge,
+    /// Input contains a null byte
+    NullByte,
+    /// Input contains a control character other than tab/newline/CR
+    ControlCharacter,
+    /// Input contains a bidirectional override or isolate character
+    BidiOverride,
+    /// Input is not in the expected Unicode normalization form
+    NotNormalized,
+}
+
+/// One problem found in the input, with its position
+///
+/// Offsets are given in UTF-8 bytes, Unicode scalar values, and UTF-16 code
+/// units, so hosts can highlight the problem in their native string type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// Kind of problem
+    pub kind: ValidationIssueKind,
+    /// Human-readable description
+    pub message: String,
+    /// Offset of the problem in UTF-8 bytes
+    pub byte_offset: u64,
+    /// Offset of the problem in Unicode scalar values
+    pub char_offset: u64,
+    /// Offset of the problem in UTF-16 code units
+    pub utf16_offset: u64,
+}
+
+/// Every problem found in an input
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// Whether no problems were found
+    pub is_valid: bool,
+    /// Problems in order of position, at most `MAX_VALIDATION_ISSUES`
+    pub issues: Vec<ValidationIssue>,
+    /// Whether more problems were found than are listed
+    pub truncated: bool,
+}
+
+/// Position of a character in each of the offset units
+#[derive(Clone, Copy, Default)]
+struct Position {
+    byte: u64,
+    char: u64,
+    utf16: u64,
+}
+
+impl Position {
+    fn advance(&mut self, c: char) {
+        self.byte += c.len_utf8() as u64;
+        self.char += 1;
+        self.utf16 += c.len_utf16() as u64;
+    }
+}
+
+struct Collector {
+    issues: Vec<ValidationIssue>,
+    truncated: bool,
+}
+
+impl Collector {
+    fn push(&mut self, kind: ValidationIssueKind, message: String, at: Position) {
+        if self.issues.len() >= MAX_VALIDATION_ISSUES as usize {
+            self.truncated = true;
+            return;
+        }
+        self.issues.push(ValidationIssue {
+            kind,
+            message,
+            byte_offset: at.byte,
+            char_offset: at.char,
+            utf16_offset: at.utf16,
+        });
+    }
+}
+
+/// Checks `input` against `config` and reports every problem found
+///
+/// Unlike `validate_and_echo`, which fails on the first problem, this
+/// collects all of them with their positions so UIs can show complete
+/// feedback. The input is checked as given, before sanitization.
+///
+/// Checks:
+/// - size against `max_input_size`
+/// - null bytes
+/// - control characters, unless `allow_control_chars` is set
+/// - bidirectional overrides, if sanitization rejects them
+/// - Unicode normalization: against the configured form, or NFC if none
+pub fn validate(input: String, config: Arc<TemplateConfig>) -> ValidationReport {
+    let mut collector = Collector {
+        issues: Vec::new(),
+        truncated: false,
+    };
+    let reject_bidi = config.sanitization().reject_bidi_overrides;
+    let max_size = config.max_input_size();
+    let mut position = Position::default();
+    let mut size_reported = false;
+
+    for c in input.chars() {
+        if !size_reported && position.byte + c.len_utf8() as u64 > max_size {
+            collector.push(
+                ValidationIssueKind::TooLarge,
+                format!(
+                    "Input of {} bytes exceeds maximum of {} bytes",
+                    input.len(),
+                    max_size
+                ),
+                position,
+            );
+            size_reported = true;
+        }
+        if c == '\0' {
+            collector.push(
+                ValidationIssueKind::NullByte,
+                "Input contains a null byte".to_string(),
+                position,
+            );
+        } else if !config.allow_control_chars()
+            && c.is_control()
+            && !matches!(c, '\t' | '\n' | '\r')
+        {
+            collector.push(
+                ValidationIssueKind::ControlCharacter,
+                format!("Input contains control character U+{:04X}", c as u32),
+                position,
+            );
+        } else if reject_bidi && is_bidi_control(c) {
+            collector.push(
+                ValidationIssueKind::BidiOverride,
+                format!(
+                    "Input contains bidirectional override character U+{:04X}",
+                    c as u32
+                ),
+                position,
+            );
+        }
+        position.advance(c);
+    }
+
+    let form = match config.normalization() {
+        UnicodeNormalization::None => UnicodeNormalization::Nfc,
+        form => form,
+    };
+    if let Some(at) = first_unnormalized(&input, form) {
+        collector.push(
+            ValidationIssueKind::NotNormalized,
+            format!("Input is not in {} form", form.name()),
+            at,
+        );
+    }
+
+    // Normalization is found after the scan; keep issues in position order
+    collector.issues.sort_by_key(|issue| issue.byte_offset);
+    ValidationReport {
+        is_valid: collector.issues.is_empty(),
+        issues: collector.issues,
+        truncated: collector.truncated,
+    }
+}
+
+/// Position of the first character that changes when normalized to `form`
+fn first_unnormalized(input: &str, form: UnicodeNormalization) -> Option<Position> {
+    let normalized = form.apply(input);
+    if normalized == input {
+        return None;
+    }
+    let mut position = Position::default();
+    let mut expected = normalized.chars();
+    for c in input.chars() {
+        if expected.next() != Some(c) {
+            return Some(position);
+        }
+        position.advance(c);
+    }
+    Some(position)
+}
@@ -0,0 +1,37 @@
+//! Non-fatal issues reported alongside successful results
+
+use serde::{Deserialize, Serialize};
+
+/// Kind of non-fatal issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// Sanitization removed or rewrote characters of the input
+    InputSanitized,
+    /// Unicode normalization changed the input
+    InputNormalized,
+    /// The input is not NFC and no normalization was applied, so its hash
+    /// and length depend on how the host composed the text
+    InputNotNormalized,
+}
+
+/// A non-fatal issue that did not stop the operation
+///
+/// Results carry these instead of failing, so hosts can show or log them
+/// without treating the call as an error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Kind of issue
+    pub kind: WarningKind,
+    /// Human-readable description
+    pub message: String,
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
@@ -0,0 +1,644 @@
+//! WebSocket client with callback delivery (`http` feature)
+//!
+//! `WebSocketClient` keeps one connection open on the internal runtime and
+//! hands every incoming text or binary message, and every change of
+//! connection state, to a `WebSocketListener`. The client pings the server
+//! every `ping_interval_ms` and treats the connection as dead if nothing
+//! arrives within `pong_timeout_ms` after that; pings from the server are
+//! answered automatically. With a `ReconnectPolicy`, a dropped connection
+//! is reopened with exponential backoff until `close` is called. The
+//! connection goes through the proxy set with `set_network_config`, if any,
+//! unless its bypass list names the host. Without the `http` feature the
+//! types exist so the bindings stay the same, but creating a client fails.
+
+use crate::cancellation::CancellationToken;
+use crate::error::{TemplateError, TemplateResult};
+use crate::shield;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+#[cfg(feature = "http")]
+use crate::auth;
+#[cfg(feature = "http")]
+use crate::http::{self, HttpMethod, HttpRequest, HttpResponse};
+#[cfg(feature = "http")]
+use crate::interceptors;
+#[cfg(feature = "http")]
+use crate::network;
+#[cfg(feature = "http")]
+use crate::pinning;
+#[cfg(feature = "http")]
+use crate::retry::RetryPolicy;
+#[cfg(feature = "http")]
+use crate::runtime;
+#[cfg(feature = "http")]
+use futures_util::{SinkExt, StreamExt};
+#[cfg(feature = "http")]
+use std::time::Duration;
+#[cfg(feature = "http")]
+use tokio::time::Instant;
+#[cfg(feature = "http")]
+use tokio_tungstenite::tungstenite::{
+    self, client::IntoClientRequest, protocol::CloseFrame, Message,
+};
+
+/// Operation name used in errors
+#[cfg(feature = "http")]
+const OPERATION: &str = "websocket";
+
+/// Close code sent by `close` for a normal closure
+pub const WEBSOCKET_NORMAL_CLOSURE: u16 = 1000;
+
+/// How long `close` waits for the server to acknowledge before dropping the connection
+#[cfg(feature = "http")]
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection state of a `WebSocketClient`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSocketState {
+    /// Opening the first connection
+    Connecting,
+    Connected,
+    /// The connection dropped; waiting to reconnect or reconnecting
+    Reconnecting,
+    /// `close` was called; waiting for the server to acknowledge
+    Closing,
+    /// Not connected, and not trying to
+    Closed,
+}
+
+/// How to reopen a dropped connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Consecutive failed attempts before giving up; 0 retries forever
+    pub max_attempts: u32,
+    /// Delay before the first attempt; doubled for each further attempt
+    pub base_delay_ms: u64,
+    /// Upper bound for a single delay
+    pub max_delay_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 0,
+            base_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+/// Settings for a `WebSocketClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketOptions {
+    /// Sent with the opening handshake, e.g. an authorization token
+    pub headers: HashMap<String, String>,
+    /// How often to ping the server; 0 disables pings
+    pub ping_interval_ms: u64,
+    /// How long after a ping to wait for any frame before the connection is considered dead
+    pub pong_timeout_ms: u64,
+    /// Reconnect when the connection drops; `None` closes instead
+    pub reconnect: Option<ReconnectPolicy>,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self {
+            headers: HashMap::new(),
+            ping_interval_ms: 30_000,
+            pong_timeout_ms: 10_000,
+            reconnect: None,
+        }
+    }
+}
+
+/// Callback receiving messages and state changes, implemented by the host
+///
+/// Methods are called on a runtime thread, one at a time and in order.
+pub trait WebSocketListener: Send + Sync {
+    fn on_text_message(&self, text: String);
+    fn on_binary_message(&self, data: Vec<u8>);
+    /// Called with the new state; `error_message` says why the connection dropped or closed
+    fn on_state_changed(&self, state: WebSocketState, error_message: Option<String>);
+}
+
+/// Frame queued by `send_text`, `send_bytes`, or `close`
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+enum Outgoing {
+    Text(String),
+    Binary(Vec<u8>),
+    Close(u16, String),
+}
+
+/// A WebSocket connection that reports messages and state changes to a listener
+pub struct WebSocketClient {
+    inner: Arc<Inner>,
+}
+
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+struct Inner {
+    url: String,
+    options: WebSocketOptions,
+    listener: Box<dyn WebSocketListener>,
+    shared: Mutex<Shared>,
+}
+
+struct Shared {
+    state: WebSocketState,
+    /// Frames for the connection, while connected
+    outgoing: Option<mpsc::UnboundedSender<Outgoing>>,
+    /// Cancelled by `close` to stop connecting and reconnecting
+    token: Option<Arc<CancellationToken>>,
+}
+
+impl WebSocketClient {
+    /// Create a client for a `ws` or `wss` URL; nothing is sent until `connect`
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the URL or a header is invalid,
+    ///   or the library was built without the `http` feature
+    pub fn new(
+        url: String,
+        options: WebSocketOptions,
+        listener: Box<dyn WebSocketListener>,
+    ) -> TemplateResult<Self> {
+        shield::guard("WebSocketClient::new", || {
+            if cfg!(not(feature = "http")) {
+                return Err(TemplateError::invalid_input(
+                    "WebSocketClient requires the `http` feature".to_string(),
+                    None,
+                ));
+            }
+            #[cfg(feature = "http")]
+            request(&url, &options.headers)?;
+            Ok(Self {
+                inner: Arc::new(Inner {
+                    url,
+                    options,
+                    listener,
+                    shared: Mutex::new(Shared {
+                        state: WebSocketState::Closed,
+                        outgoing: None,
+                        token: None,
+                    }),
+                }),
+            })
+        })
+    }
+
+    /// Opens the connection, returning once the handshake completes (async)
+    ///
+    /// After a dropped connection, the client reconnects by itself if it has
+    /// a `ReconnectPolicy`; `connect` is only needed again once it is `Closed`.
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::InvalidInput)` - If the client is not `Closed`
+    /// * `Err(TemplateError::NetworkError)` - If the handshake failed
+    /// * `Err(TemplateError::OperationCancelled)` - If `close` was called first
+    pub async fn connect(&self) -> TemplateResult<()> {
+        shield::guard_async("WebSocketClient::connect", async {
+            let token = {
+                let mut shared = self.inner.shared.lock().unwrap();
+                if shared.state != WebSocketState::Closed {
+                    return Err(TemplateError::invalid_input(
+                        "WebSocket is already connected or connecting".to_string(),
+                        None,
+                    ));
+                }
+                let token = Arc::new(CancellationToken::new());
+                shared.token = Some(token.clone());
+                shared.state = WebSocketState::Connecting;
+                token
+            };
+            self.inner
+                .listener
+                .on_state_changed(WebSocketState::Connecting, None);
+            let (ready, connected) = oneshot::channel();
+            self.inner.spawn(token, ready);
+            connected.await.unwrap_or_else(|_| {
+                Err(TemplateError::network_error(
+                    &self.inner.url,
+                    None,
+                    "Connection task stopped".to_string(),
+                ))
+            })
+        })
+        .await
+    }
+
+    /// Queues a text message
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::NetworkError)` - If the client is not connected
+    pub fn send_text(&self, text: String) -> TemplateResult<()> {
+        shield::guard("WebSocketClient::send_text", || {
+            self.inner.send(Outgoing::Text(text))
+        })
+    }
+
+    /// Queues a binary message
+    ///
+    /// # Returns
+    ///
+    /// * `Err(TemplateError::NetworkError)` - If the client is not connected
+    pub fn send_bytes(&self, data: Vec<u8>) -> TemplateResult<()> {
+        shield::guard("WebSocketClient::send_bytes", || {
+            self.inner.send(Outgoing::Binary(data))
+        })
+    }
+
+    /// Closes the connection with `code` and `reason` and stops reconnecting
+    ///
+    /// Queued messages are sent first. The state becomes `Closed` once the
+    /// server acknowledges, or after a few seconds if it does not.
+    pub fn close(&self, code: u16, reason: String) {
+        let (outgoing, token) = {
+            let mut shared = self.inner.shared.lock().unwrap();
+            if matches!(
+                shared.state,
+                WebSocketState::Closing | WebSocketState::Closed
+            ) {
+                return;
+            }
+            (shared.outgoing.take(), shared.token.clone())
+        };
+        if let Some(token) = token {
+            token.cancel();
+        }
+        if let Some(outgoing) = outgoing {
+            self.inner.set_state(WebSocketState::Closing, None);
+            let _ = outgoing.send(Outgoing::Close(code, reason));
+        }
+    }
+
+    /// Current connection state
+    pub fn state(&self) -> WebSocketState {
+        self.inner.shared.lock().unwrap().state
+    }
+
+    /// The URL the client connects to
+    pub fn url(&self) -> String {
+        self.inner.url.clone()
+    }
+}
+
+impl Drop for WebSocketClient {
+    fn drop(&mut self) {
+        self.close(WEBSOCKET_NORMAL_CLOSURE, String::new());
+    }
+}
+
+impl Inner {
+    fn set_state(&self, state: WebSocketState, error_message: Option<String>) {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            if shared.state == state {
+                return;
+            }
+            shared.state = state;
+        }
+        self.listener.on_state_changed(state, error_message);
+    }
+
+    fn send(&self, frame: Outgoing) -> TemplateResult<()> {
+        let shared = self.shared.lock().unwrap();
+        shared
+            .outgoing
+            .as_ref()
+            .filter(|_| shared.state == WebSocketState::Connected)
+            .and_then(|outgoing| outgoing.send(frame).ok())
+            .ok_or_else(|| {
+                TemplateError::network_error(
+                    &self.url,
+                    None,
+                    "WebSocket is not connected".to_string(),
+                )
+            })
+    }
+
+    #[cfg(feature = "http")]
+    fn spawn(
+        self: &Arc<Self>,
+        token: Arc<CancellationToken>,
+        ready: oneshot::Sender<TemplateResult<()>>,
+    ) {
+        let inner = self.clone();
+        runtime::handle().spawn(async move { inner.run(token, ready).await });
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn spawn(
+        self: &Arc<Self>,
+        _token: Arc<CancellationToken>,
+        _ready: oneshot::Sender<TemplateResult<()>>,
+    ) {
+        unreachable!("WebSocketClient::new fails without the `http` feature")
+    }
+
+    /// Connects, runs the connection, and reconnects until closed or out of attempts
+    #[cfg(feature = "http")]
+    async fn run(&self, token: Arc<CancellationToken>, ready: oneshot::Sender<TemplateResult<()>>) {
+        let mut ready = Some(ready);
+        let mut failures = 0;
+        loop {
+            let opened = tokio::select! {
+                opened = self.open() => opened,
+                error = http::cancelled(Some(&token), OPERATION) => Err(error),
+            };
+            let error = match opened {
+                Ok(stream) => {
+                    failures = 0;
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    {
+                        let mut shared = self.shared.lock().unwrap();
+                        if token.is_cancelled() {
+                            // `close` ran during the handshake and found nothing to close
+                            drop(shared);
+                            let mut stream = stream;
+                            let _ = stream.close(None).await;
+                            self.closed(ready.take(), None);
+                            return;
+                        }
+                        shared.outgoing = Some(sender);
+                    }
+                    self.set_state(WebSocketState::Connected, None);
+                    if let Some(ready) = ready.take() {
+                        let _ = ready.send(Ok(()));
+                    }
+                    let ended = self.session(stream, receiver).await;
+                    self.shared.lock().unwrap().outgoing = None;
+                    match ended {
+                        Ok(()) => {
+                            self.closed(None, None);
+                            return;
+                        }
+                        Err(e) => e,
+                    }
+                }
+                // The first connection is not retried; `connect` reports its error
+                Err(e) if ready.is_some() => {
+                    self.closed(ready.take(), Some(e));
+                    return;
+                }
+                Err(e) => e,
+            };
+
+            if token.is_cancelled() {
+                self.closed(None, None);
+                return;
+            }
+            log::warn!("WebSocket connection to {} lost: {}", self.url, error);
+            let Some(policy) = &self.options.reconnect else {
+                self.closed(None, Some(error));
+                return;
+            };
+            failures += 1;
+            if policy.max_attempts != 0 && failures > policy.max_attempts {
+                self.closed(None, Some(error));
+                return;
+            }
+            self.set_state(WebSocketState::Reconnecting, Some(error.to_string()));
+            let backoff = RetryPolicy {
+                base_delay_ms: policy.base_delay_ms,
+                max_delay_ms: policy.max_delay_ms,
+                ..RetryPolicy::default()
+            };
+            let delay = Duration::from_millis(backoff.delay(failures));
+            if runtime::sleep_cancellable(delay, Some(&token), OPERATION)
+                .await
+                .is_err()
+            {
+                self.closed(None, None);
+                return;
+            }
+        }
+    }
+
+    /// Moves to `Closed`, answering a pending `connect` with `error`
+    #[cfg(feature = "http")]
+    fn closed(
+        &self,
+        ready: Option<oneshot::Sender<TemplateResult<()>>>,
+        error: Option<TemplateError>,
+    ) {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            shared.outgoing = None;
+            shared.token = None;
+        }
+        let message = error.as_ref().map(TemplateError::to_string);
+        self.set_state(WebSocketState::Closed, message);
+        if let Some(ready) = ready {
+            let _ = ready.send(match error {
+                Some(error) => Err(error),
+                None => Err(TemplateError::operation_cancelled(OPERATION)),
+            });
+        }
+    }
+
+    #[cfg(feature = "http")]
+    async fn open(
+        &self,
+    ) -> TemplateResult<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    > {
+        let mut sent = HttpRequest {
+            url: self.url.clone(),
+            method: HttpMethod::Get,
+            headers: self.options.headers.clone(),
+            body: None,
+            timeout_ms: None,
+        };
+        auth::authorize(&sent.url, &mut sent.headers).await?;
+        if let Some(intercepted) = interceptors::intercept(&sent).await {
+            sent = intercepted;
+        }
+        let request = request(&sent.url, &sent.headers)?;
+        let pins = network::pins();
+        let identity = network::client_identity();
+        let connector = (!pins.is_empty() || identity.is_some()).then(|| {
+            tokio_tungstenite::Connector::Rustls(Arc::new(pinning::tls_config(pins, identity)))
+        });
+        let host = request.uri().host().unwrap_or_default().to_string();
+        let port = request
+            .uri()
+            .port_u16()
+            .unwrap_or(match request.uri().scheme_str() {
+                Some("wss") => 443,
+                _ => 80,
+            });
+        let connected = match network::proxy().filter(|proxy| !proxy.bypasses(&host)) {
+            Some(proxy) => {
+                let tunnel = proxy.tunnel(&host, port).await?;
+                tokio_tungstenite::client_async_tls_with_config(request, tunnel, None, connector)
+                    .await
+            }
+            None => {
+                tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector)
+                    .await
+            }
+        };
+        let (stream, response) = connected.map_err(|e| websocket_error(&self.url, e))?;
+        interceptors::observe(&sent, &handshake_response(&sent.url, &response)).await;
+        Ok(stream)
+    }
+
+    /// Delivers messages, sends queued frames and pings, and returns once the
+    /// connection ends: `Ok` after `close`, otherwise with why it dropped
+    #[cfg(feature = "http")]
+    async fn session(
+        &self,
+        stream: tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+        mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+    ) -> TemplateResult<()> {
+        let (mut sink, mut source) = stream.split();
+        let ping_interval = Duration::from_millis(self.options.ping_interval_ms);
+        let pong_timeout = Duration::from_millis(self.options.pong_timeout_ms);
+        let mut pinger = (!ping_interval.is_zero())
+            .then(|| tokio::time::interval_at(Instant::now() + ping_interval, ping_interval));
+        let mut last_received = Instant::now();
+        let mut close_deadline: Option<Instant> = None;
+        let mut server_close: Option<String> = None;
+        loop {
+            let deadline = close_deadline;
+            tokio::select! {
+                frame = source.next() => {
+                    last_received = Instant::now();
+                    match frame {
+                        Some(Ok(Message::Text(text))) => self.listener.on_text_message(text.to_string()),
+                        Some(Ok(Message::Binary(data))) => self.listener.on_binary_message(data.to_vec()),
+                        Some(Ok(Message::Close(frame))) => {
+                            // tungstenite replies to the close frame itself
+                            server_close = Some(match frame {
+                                Some(frame) => format!(
+                                    "Closed by server with code {}: '{}'",
+                                    u16::from(frame.code),
+                                    frame.reason
+                                ),
+                                None => "Closed by server".to_string(),
+                            });
+                        }
+                        // Pings are answered by tungstenite; pongs only show the connection is alive
+                        Some(Ok(_)) => {}
+                        Some(Err(_)) | None if close_deadline.is_some() => return Ok(()),
+                        Some(Err(e)) => return Err(websocket_error(&self.url, e)),
+                        None => {
+                            return Err(TemplateError::network_error(
+                                &self.url,
+                                None,
+                                server_close.unwrap_or_else(|| "Connection closed".to_string()),
+                            ))
+                        }
+                    }
+                }
+                frame = outgoing.recv(), if close_deadline.is_none() => {
+                    let message = match frame {
+                        Some(Outgoing::Text(text)) => Message::text(text),
+                        Some(Outgoing::Binary(data)) => Message::binary(data),
+                        Some(Outgoing::Close(code, reason)) => {
+                            close_deadline = Some(Instant::now() + CLOSE_TIMEOUT);
+                            Message::Close(Some(CloseFrame {
+                                code: code.into(),
+                                reason: reason.into(),
+                            }))
+                        }
+                        // The sender is only dropped by `close`, which queues a close frame first
+                        None => {
+                            close_deadline = Some(Instant::now() + CLOSE_TIMEOUT);
+                            Message::Close(None)
+                        }
+                    };
+                    if let Err(e) = sink.send(message).await {
+                        if close_deadline.is_some() {
+                            return Ok(());
+                        }
+                        return Err(websocket_error(&self.url, e));
+                    }
+                }
+                _ = async {
+                    match &mut pinger {
+                        Some(pinger) => {
+                            pinger.tick().await;
+                        }
+                        None => std::future::pending().await,
+                    }
+                }, if close_deadline.is_none() => {
+                    if last_received.elapsed() > ping_interval + pong_timeout {
+                        return Err(TemplateError::timeout(OPERATION, self.options.pong_timeout_ms));
+                    }
+                    sink.send(Message::Ping(Default::default()))
+                        .await
+                        .map_err(|e| websocket_error(&self.url, e))?;
+                }
+                _ = async move {
+                    match deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                } => return Ok(()),
+            }
+        }
+    }
+}
+
+/// The handshake request for `url` with `headers`, rejecting anything invalid
+#[cfg(feature = "http")]
+fn request(
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> TemplateResult<tungstenite::handshake::client::Request> {
+    let invalid_url =
+        || TemplateError::invalid_input(format!("Not an absolute ws(s) URL: '{}'", url), None);
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid_url())?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(invalid_url());
+    }
+    let mut request = url.into_client_request().map_err(|_| invalid_url())?;
+    for (name, value) in headers {
+        let name = tungstenite::http::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+            TemplateError::invalid_input(format!("Invalid header name: '{}'", name), None)
+        })?;
+        let value = tungstenite::http::HeaderValue::from_str(value).map_err(|_| {
+            TemplateError::invalid_input(format!("Invalid value for header '{}'", name), None)
+        })?;
+        request.headers_mut().insert(name, value);
+    }
+    Ok(request)
+}
+
+/// The server's answer to the opening handshake, as told to interceptors
+#[cfg(feature = "http")]
+fn handshake_response(
+    url: &str,
+    response: &tungstenite::handshake::client::Response,
+) -> HttpResponse {
+    HttpResponse {
+        status: response.status().as_u16(),
+        headers: http::joined_headers(response.headers()),
+        body: response.body().clone().unwrap_or_default(),
+        url: url.to_string(),
+    }
+}
+
+#[cfg(feature = "http")]
+fn websocket_error(url: &str, error: tungstenite::Error) -> TemplateError {
+    if let Some(failure) = pinning::failure(&error) {
+        return failure;
+    }
+    match error {
+        tungstenite::Error::Http(response) => TemplateError::network_error(
+            url,
+            Some(response.status().as_u16()),
+            format!("Handshake rejected with {}", response.status()),
+        ),
+        error => TemplateError::network_error(url, None, error.to_string()),
+    }
+}
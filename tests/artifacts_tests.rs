@@ -0,0 +1,104 @@
+use rust_multiplatform_template_lib::{ArtifactStore, TemplateError};
+use std::fs;
+
+fn open(dir: &tempfile::TempDir) -> ArtifactStore {
+    ArtifactStore::open(dir.path().join("artifacts").to_string_lossy().into_owned()).unwrap()
+}
+
+fn object_count(dir: &tempfile::TempDir) -> usize {
+    fs::read_dir(dir.path().join("artifacts/objects"))
+        .unwrap()
+        .count()
+}
+
+#[tokio::test]
+async fn test_identical_artifacts_are_stored_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    let source = dir.path().join("download.gguf");
+    fs::write(&source, b"model weights").unwrap();
+
+    let first = store
+        .import_file(
+            "llama-q4".to_string(),
+            source.to_string_lossy().into_owned(),
+        )
+        .await
+        .unwrap();
+    let second = store
+        .put("llama-q4-copy".to_string(), b"model weights".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(
+        first.sha256,
+        "a2d42c4aa884e21216cbb8da4c7ba2fcf9b6033b2331666e666145c24caf7a38"
+    );
+    assert_eq!(first.sha256, second.sha256);
+    assert_eq!(first.path, second.path);
+    assert_eq!(first.size_bytes, 13);
+    assert_eq!(fs::read(&first.path).unwrap(), b"model weights");
+    assert_eq!(object_count(&dir), 1);
+
+    let names: Vec<_> = store.list().into_iter().map(|a| a.name).collect();
+    assert_eq!(names, vec!["llama-q4", "llama-q4-copy"]);
+
+    // The shared object stays until its last name is removed
+    assert!(store.remove("llama-q4".to_string()).unwrap());
+    assert!(!store.remove("llama-q4".to_string()).unwrap());
+    assert_eq!(object_count(&dir), 1);
+    assert!(store.remove("llama-q4-copy".to_string()).unwrap());
+    assert_eq!(object_count(&dir), 0);
+}
+
+#[tokio::test]
+async fn test_replacing_a_name_releases_old_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    let old = store
+        .put("model".to_string(), b"v1".to_vec())
+        .await
+        .unwrap();
+    let new = store
+        .put("model".to_string(), b"v2".to_vec())
+        .await
+        .unwrap();
+    assert_ne!(old.sha256, new.sha256);
+    assert!(!std::path::Path::new(&old.path).exists());
+    assert_eq!(store.get("model".to_string()), Some(new.clone()));
+    assert_eq!(object_count(&dir), 1);
+
+    drop(store);
+    assert_eq!(open(&dir).get("model".to_string()), Some(new));
+}
+
+#[tokio::test]
+async fn test_verify_detects_modified_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    let info = store
+        .put("model".to_string(), vec![7u8; 100_000])
+        .await
+        .unwrap();
+    assert!(store.verify("model".to_string()).await.unwrap());
+
+    fs::write(&info.path, vec![7u8; 99_999]).unwrap();
+    assert!(!store.verify("model".to_string()).await.unwrap());
+
+    assert!(matches!(
+        store.verify("missing".to_string()).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        store.put(String::new(), vec![1]).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        store
+            .import_file(
+                "x".to_string(),
+                dir.path().join("nope").to_string_lossy().into_owned()
+            )
+            .await,
+        Err(TemplateError::IoError { .. })
+    ));
+}
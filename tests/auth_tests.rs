@@ -0,0 +1,294 @@
+use rust_multiplatform_template_lib::{
+    AuthManager, AuthToken, EncryptedKvStore, KeyProvider, TemplateError, TokenRefresher,
+};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{http_request, set_auth_manager, HttpMethod, HttpRequest};
+#[cfg(feature = "http")]
+use std::sync::Mutex;
+
+struct FixedKey;
+
+impl KeyProvider for FixedKey {
+    fn get_key(&self, _key_id: String) -> Option<Vec<u8>> {
+        Some(vec![7; 32])
+    }
+}
+
+fn store(path: &Path) -> Arc<EncryptedKvStore> {
+    Arc::new(
+        EncryptedKvStore::open(
+            path.to_string_lossy().into_owned(),
+            "auth".to_string(),
+            Box::new(FixedKey),
+        )
+        .unwrap(),
+    )
+}
+
+fn token(access_token: &str, expires_at_ms: Option<u64>) -> AuthToken {
+    AuthToken {
+        access_token: access_token.to_string(),
+        scheme: "Bearer".to_string(),
+        refresh_token: Some("refresh-secret".to_string()),
+        expires_at_ms,
+    }
+}
+
+/// Replaces any token with `fresh-<n>`, counting its calls; gives up on
+/// tokens without a refresh token
+struct Refresher {
+    calls: Arc<AtomicU32>,
+}
+
+impl TokenRefresher for Refresher {
+    fn refresh_token(&self, host: String, token: AuthToken) -> Option<AuthToken> {
+        assert_eq!(host, "127.0.0.1");
+        token.refresh_token.as_ref()?;
+        let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        // Long enough for concurrent requests to wait on this refresh
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        Some(AuthToken {
+            access_token: format!("fresh-{}", calls),
+            ..token
+        })
+    }
+}
+
+fn manager(path: &Path) -> (AuthManager, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let refresher = Refresher {
+        calls: calls.clone(),
+    };
+    (
+        AuthManager::new(store(path), Some(Box::new(refresher))),
+        calls,
+    )
+}
+
+#[test]
+fn test_tokens_are_stored_encrypted_per_host() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auth.json");
+    let first = store(&path);
+    first
+        .set_string("unrelated".to_string(), "kept".to_string())
+        .unwrap();
+    AuthManager::new(first, None)
+        .set_token("API.example.com".to_string(), token("hunter2", None))
+        .unwrap();
+
+    let manager = AuthManager::new(store(&path), None);
+    assert_eq!(
+        manager.get_token("api.example.com".to_string()).unwrap(),
+        Some(token("hunter2", None))
+    );
+    assert_eq!(manager.get_token("example.com".to_string()).unwrap(), None);
+    assert_eq!(manager.hosts(), ["api.example.com"]);
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("hunter2"));
+    assert!(!raw.contains("refresh-secret"));
+
+    assert!(manager.remove_token("api.example.com".to_string()).unwrap());
+    assert!(!manager.remove_token("api.example.com".to_string()).unwrap());
+    assert!(manager.hosts().is_empty());
+}
+
+#[test]
+fn test_invalid_hosts_and_tokens_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = AuthManager::new(store(&dir.path().join("auth.json")), None);
+    for host in ["", "https://api.example.com", "api.example.com/v1", "a b"] {
+        assert!(matches!(
+            manager.set_token(host.to_string(), token("secret", None)),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    let invalid = [
+        token("", None),
+        token("line\nbreak", None),
+        AuthToken {
+            scheme: "Bear er".to_string(),
+            ..token("secret", None)
+        },
+    ];
+    for token in invalid {
+        assert!(matches!(
+            manager.set_token("api.example.com".to_string(), token),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert!(manager.hosts().is_empty());
+}
+
+#[tokio::test]
+async fn test_refresh_token_asks_the_refresher() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auth.json");
+    let without_refresher = AuthManager::new(store(&path), None);
+    assert!(matches!(
+        without_refresher
+            .refresh_token("127.0.0.1".to_string())
+            .await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    drop(without_refresher);
+
+    let (manager, calls) = manager(&path);
+    assert_eq!(
+        manager
+            .refresh_token("127.0.0.1".to_string())
+            .await
+            .unwrap(),
+        None
+    );
+    manager
+        .set_token("127.0.0.1".to_string(), token("old", None))
+        .unwrap();
+    let refreshed = manager
+        .refresh_token("127.0.0.1".to_string())
+        .await
+        .unwrap();
+    assert_eq!(refreshed, Some(token("fresh-1", None)));
+    assert_eq!(
+        manager.get_token("127.0.0.1".to_string()).unwrap(),
+        refreshed
+    );
+
+    // A refresher that gives up leaves the stored token alone
+    let signed_out = AuthToken {
+        refresh_token: None,
+        ..token("old", None)
+    };
+    manager
+        .set_token("127.0.0.1".to_string(), signed_out.clone())
+        .unwrap();
+    assert_eq!(
+        manager
+            .refresh_token("127.0.0.1".to_string())
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        manager.get_token("127.0.0.1".to_string()).unwrap(),
+        Some(signed_out)
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+/// Records the `Authorization` header of each request; `/private` answers
+/// 401 unless it carries `Bearer fresh-<n>`
+#[cfg(feature = "http")]
+fn serve() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = seen.clone();
+    let base = common::serve(move |request, mut stream| {
+        let authorization = request.header("authorization").map(str::to_string);
+        recorded.lock().unwrap().push(authorization.clone());
+        let accepted = authorization.is_some_and(|value| value.starts_with("Bearer fresh-"));
+        let status = if accepted {
+            "200 OK"
+        } else {
+            "401 Unauthorized"
+        };
+        common::respond(&mut stream, status, "", b"");
+    });
+    (base, seen)
+}
+
+#[cfg(feature = "http")]
+async fn get(url: &str, headers: &[(&str, &str)]) -> u16 {
+    let request = HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: None,
+        timeout_ms: Some(5_000),
+    };
+    http_request(request, None).await.unwrap().status
+}
+
+// The manager is process-wide, so these checks run in one test
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_tokens_are_attached_and_refreshed() {
+    let (base, seen) = serve();
+    let url = format!("{}/private", base);
+    let dir = tempfile::tempdir().unwrap();
+    let (manager, calls) = manager(&dir.path().join("auth.json"));
+    let manager = Arc::new(manager);
+    manager
+        .set_token("127.0.0.1".to_string(), token("stale", None))
+        .unwrap();
+
+    // Nothing is attached until the manager is installed
+    assert_eq!(get(&url, &[]).await, 401);
+    set_auth_manager(Some(manager.clone()));
+
+    // A rejected token is refreshed and the request sent again
+    assert_eq!(get(&url, &[]).await, 200);
+    assert_eq!(
+        seen.lock().unwrap()[..],
+        [
+            None,
+            Some("Bearer stale".to_string()),
+            Some("Bearer fresh-1".to_string())
+        ]
+    );
+    assert_eq!(get(&url, &[]).await, 200);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // A request's own header is left alone
+    assert_eq!(get(&url, &[("authorization", "Bearer mine")]).await, 401);
+    assert_eq!(
+        seen.lock().unwrap().last().unwrap().as_deref(),
+        Some("Bearer mine")
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // An expiring token is refreshed once before concurrent requests use it
+    manager
+        .set_token("127.0.0.1".to_string(), token("expiring", Some(1)))
+        .unwrap();
+    seen.lock().unwrap().clear();
+    let requests: Vec<_> = (0..4)
+        .map(|_| {
+            let url = url.clone();
+            tokio::spawn(async move { get(&url, &[]).await })
+        })
+        .collect();
+    for request in requests {
+        assert_eq!(request.await.unwrap(), 200);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert!(seen
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|authorization| authorization.as_deref() == Some("Bearer fresh-2")));
+
+    // Hosts without a token, and requests once the manager is removed, go
+    // out without one
+    assert!(manager.remove_token("127.0.0.1".to_string()).unwrap());
+    assert_eq!(get(&url, &[]).await, 401);
+    manager
+        .set_token("127.0.0.1".to_string(), token("fresh-9", None))
+        .unwrap();
+    assert_eq!(get(&url, &[]).await, 200);
+    set_auth_manager(None);
+    assert_eq!(get(&url, &[]).await, 401);
+    assert_eq!(
+        seen.lock().unwrap()[4..],
+        [None, Some("Bearer fresh-9".to_string()), None]
+    );
+}
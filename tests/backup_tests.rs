@@ -0,0 +1,118 @@
+use rust_multiplatform_template_lib::{
+    export_data, get_config, import_data, update_config, CancellationToken, KvStore, LibraryConfig,
+    LogLevel, TemplateError,
+};
+use std::fs;
+use std::sync::Arc;
+
+fn store(dir: &tempfile::TempDir, name: &str) -> Arc<KvStore> {
+    Arc::new(KvStore::open(dir.path().join(name).to_string_lossy().into_owned()).unwrap())
+}
+
+// The library configuration is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_export_and_import_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("backup.bin").to_string_lossy().into_owned();
+    let passphrase = "correct horse battery staple".to_string();
+
+    let exported_config = LibraryConfig {
+        log_level: LogLevel::Info,
+        max_input_size: 2048,
+        ..LibraryConfig::default()
+    };
+    update_config(exported_config.clone()).unwrap();
+    let source = store(&dir, "source.json");
+    source
+        .set_string("token".to_string(), "abc".to_string())
+        .unwrap();
+    source.set_int("launches".to_string(), 7).unwrap();
+
+    let summary = export_data(path.clone(), passphrase.clone(), Some(source), None)
+        .await
+        .unwrap();
+    assert_eq!(summary.kv_entry_count, 2);
+    // Nothing readable ends up in the file
+    let archive = fs::read(&path).unwrap();
+    assert!(!archive.windows(3).any(|w| w == b"abc"));
+
+    update_config(LibraryConfig::default()).unwrap();
+    let target = store(&dir, "target.json");
+    target.set_bool("stale".to_string(), true).unwrap();
+
+    // A wrong passphrase changes nothing
+    assert!(matches!(
+        import_data(
+            path.clone(),
+            "wrong".to_string(),
+            Some(target.clone()),
+            None
+        )
+        .await,
+        Err(TemplateError::EncryptionError { .. })
+    ));
+    assert_eq!(target.keys(), vec!["stale"]);
+    assert_eq!(get_config(), LibraryConfig::default());
+
+    // So does a cancelled token, or a store that cannot be written
+    let cancelled = Arc::new(CancellationToken::new());
+    cancelled.cancel();
+    assert!(matches!(
+        import_data(
+            path.clone(),
+            passphrase.clone(),
+            Some(target.clone()),
+            Some(cancelled.clone())
+        )
+        .await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+    let gone = tempfile::tempdir().unwrap();
+    let unwritable = store(&gone, "store.json");
+    drop(gone);
+    assert!(matches!(
+        import_data(path.clone(), passphrase.clone(), Some(unwritable), None).await,
+        Err(TemplateError::IoError { .. })
+    ));
+    assert_eq!(target.keys(), vec!["stale"]);
+    assert_eq!(get_config(), LibraryConfig::default());
+
+    let restored = import_data(path.clone(), passphrase.clone(), Some(target.clone()), None)
+        .await
+        .unwrap();
+    assert_eq!(restored, summary);
+    assert_eq!(get_config(), exported_config);
+    assert_eq!(target.keys(), vec!["launches", "token"]);
+    assert_eq!(
+        target.get_string("token".to_string()).unwrap(),
+        Some("abc".to_string())
+    );
+
+    // Any modified byte is detected
+    let mut tampered = archive.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    fs::write(&path, &tampered).unwrap();
+    assert!(matches!(
+        import_data(path.clone(), passphrase.clone(), None, None).await,
+        Err(TemplateError::EncryptionError { .. })
+    ));
+
+    assert!(matches!(
+        export_data(path.clone(), passphrase.clone(), None, Some(cancelled)).await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+    assert_eq!(fs::read(&path).unwrap(), tampered);
+
+    fs::write(&path, b"not a backup").unwrap();
+    assert!(matches!(
+        import_data(path.clone(), passphrase, None, None).await,
+        Err(TemplateError::ParseError { .. })
+    ));
+    assert!(matches!(
+        export_data(path, String::new(), None, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    update_config(LibraryConfig::default()).unwrap();
+}
@@ -0,0 +1,120 @@
+use rust_multiplatform_template_lib::{BlobStore, TemplateError, MAX_BLOB_READ};
+
+fn open(dir: &tempfile::TempDir) -> BlobStore {
+    BlobStore::open(dir.path().join("blobs").to_string_lossy().into_owned()).unwrap()
+}
+
+#[test]
+fn test_blob_written_and_read_in_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    let payload: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+    let writer = store.writer().unwrap();
+    for chunk in payload.chunks(3_000) {
+        writer.append(chunk.to_vec()).unwrap();
+    }
+    assert_eq!(writer.bytes_written(), 10_000);
+    let id = writer.finish().unwrap();
+
+    let reader = store.reader(id.clone()).unwrap();
+    assert_eq!(reader.size(), 10_000);
+    let mut read = Vec::new();
+    let mut offset = 0;
+    loop {
+        let chunk = reader.read_at(offset, 4_096).unwrap();
+        if chunk.is_empty() {
+            break;
+        }
+        offset += chunk.len() as u64;
+        read.extend(chunk);
+    }
+    assert_eq!(read, payload);
+    assert_eq!(
+        reader.read_at(9_998, 10).unwrap(),
+        payload[9_998..].to_vec()
+    );
+    assert!(reader.read_at(20_000, 10).unwrap().is_empty());
+
+    assert!(store.delete(id.clone()).unwrap());
+    assert!(!store.delete(id.clone()).unwrap());
+    assert!(matches!(
+        store.reader(id),
+        Err(TemplateError::IoError { .. })
+    ));
+}
+
+#[test]
+fn test_unfinished_blob_is_not_visible() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+
+    let aborted = store.writer().unwrap();
+    aborted.append(vec![1, 2, 3]).unwrap();
+    aborted.abort();
+    assert!(matches!(
+        aborted.append(vec![4]),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        aborted.finish(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let dropped = store.writer().unwrap();
+    dropped.append(vec![1, 2, 3]).unwrap();
+    drop(dropped);
+
+    let finished = store.writer().unwrap();
+    let id = finished.finish().unwrap();
+    assert!(matches!(
+        finished.finish(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    // Only the finished, empty blob is left
+    let files: Vec<_> = std::fs::read_dir(dir.path().join("blobs"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(files, vec![id.clone()]);
+    assert_eq!(store.reader(id).unwrap().size(), 0);
+}
+
+#[test]
+fn test_blob_store_rejects_bad_ids_and_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    assert!(matches!(
+        store.reader("../secrets".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        store.delete("".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let writer = store.writer().unwrap();
+    writer.append(vec![0; 8]).unwrap();
+    let reader = store.reader(writer.finish().unwrap()).unwrap();
+    assert!(matches!(
+        reader.read_at(0, MAX_BLOB_READ + 1),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_blob_store_open_removes_leftover_partial_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    let writer = store.writer().unwrap();
+    writer.append(vec![1]).unwrap();
+    // Simulates the app being killed mid-write
+    std::mem::forget(writer);
+
+    open(&dir);
+    assert_eq!(
+        std::fs::read_dir(dir.path().join("blobs")).unwrap().count(),
+        0
+    );
+}
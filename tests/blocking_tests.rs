@@ -0,0 +1,83 @@
+use rust_multiplatform_template_lib::{
+    discover_models_blocking, echo_blocking, generate_uuid_v4_blocking, generate_uuid_v7_blocking,
+    is_valid_uuid_blocking, parse_uuid_blocking, random_blocking, random_bytes_blocking,
+    random_choice_blocking, random_exponential_blocking, random_int_blocking,
+    random_normal_blocking, random_seeded_blocking, random_uniform_blocking,
+    secure_random_bytes_blocking, secure_random_double_blocking, CancellationToken, TemplateConfig,
+    TemplateError,
+};
+use std::sync::Arc;
+
+#[test]
+fn test_echo_blocking() {
+    let result = echo_blocking("Hello".to_string(), None, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.text, "Hello");
+    assert!(echo_blocking(String::new(), None, None).unwrap().is_none());
+}
+
+#[test]
+fn test_echo_blocking_with_cancelled_token() {
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+    assert!(matches!(
+        echo_blocking("Hello".to_string(), Some(token), None),
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+#[test]
+fn test_random_blocking_variants() {
+    assert!((0.0..1.0).contains(&random_blocking()));
+    assert!((1..=6).contains(&random_int_blocking(1, 6, None).unwrap()));
+    assert_eq!(random_bytes_blocking(16, None).unwrap().len(), 16);
+    assert_eq!(secure_random_bytes_blocking(16, None).unwrap().len(), 16);
+    assert!(random_int_blocking(6, 1, None).is_err());
+
+    let items = vec!["a".to_string(), "b".to_string()];
+    assert!(items.contains(&random_choice_blocking(items.clone(), None).unwrap()));
+    assert!(random_normal_blocking(0.0, 1.0, None).unwrap().is_finite());
+    assert!(random_exponential_blocking(2.0, None).unwrap() >= 0.0);
+    assert!((1.0..2.0).contains(&random_uniform_blocking(1.0, 2.0, None).unwrap()));
+    assert_eq!(random_seeded_blocking(42), random_seeded_blocking(42));
+    assert!((0.0..1.0).contains(&secure_random_double_blocking(None).unwrap()));
+    assert!(random_choice_blocking(Vec::new(), None).is_err());
+}
+
+#[test]
+fn test_uuid_blocking_variants() {
+    let v4 = parse_uuid_blocking(generate_uuid_v4_blocking(), None).unwrap();
+    assert_eq!(v4.version, 4);
+    let v7 = parse_uuid_blocking(generate_uuid_v7_blocking(), None).unwrap();
+    assert_eq!(v7.version, 7);
+    assert!(is_valid_uuid_blocking(generate_uuid_v4_blocking()));
+    assert!(!is_valid_uuid_blocking("not-a-uuid".to_string()));
+}
+
+#[test]
+fn test_validate_and_echo_blocking() {
+    let config = TemplateConfig::default();
+    let result = config
+        .validate_and_echo_blocking("Hello".to_string(), None, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.text, "Hello");
+}
+
+#[test]
+fn test_discover_models_blocking() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("broken.gguf"), b"NOPE").unwrap();
+    let models = discover_models_blocking(dir.path().to_string_lossy().into_owned(), None).unwrap();
+    assert_eq!(models.len(), 1);
+}
+
+#[tokio::test]
+async fn test_blocking_variant_inside_async_context() {
+    // Must not panic even though a tokio runtime is already running here
+    let result = echo_blocking("Hello".to_string(), None, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.text, "Hello");
+}
@@ -0,0 +1,95 @@
+use rust_multiplatform_template_lib::{Cache, CacheStats};
+use std::thread;
+use std::time::Duration;
+
+fn key(name: &str) -> String {
+    name.to_string()
+}
+
+#[test]
+fn test_cache_get_set_remove() {
+    let cache = Cache::new(0, 0, None);
+    assert_eq!(cache.get(key("a")), None);
+
+    cache.set(key("a"), vec![1, 2, 3], None);
+    assert_eq!(cache.get(key("a")), Some(vec![1, 2, 3]));
+
+    cache.set(key("a"), vec![4], None);
+    assert_eq!(cache.get(key("a")), Some(vec![4]));
+
+    assert!(cache.remove(key("a")));
+    assert!(!cache.remove(key("a")));
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 2,
+            misses: 1,
+            ..CacheStats::default()
+        }
+    );
+}
+
+#[test]
+fn test_cache_evicts_least_recently_used_entry() {
+    let cache = Cache::new(2, 0, None);
+    cache.set(key("a"), vec![1], None);
+    cache.set(key("b"), vec![2], None);
+    // Reading "a" makes "b" the least recently used
+    assert!(cache.get(key("a")).is_some());
+    cache.set(key("c"), vec![3], None);
+
+    assert_eq!(cache.get(key("b")), None);
+    assert!(cache.get(key("a")).is_some());
+    assert!(cache.get(key("c")).is_some());
+    let stats = cache.stats();
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.entry_count, 2);
+}
+
+#[test]
+fn test_cache_byte_limit() {
+    // Sizes count the key and the value: each entry here is 1 + 4 bytes
+    let cache = Cache::new(0, 10, None);
+    cache.set(key("a"), vec![0; 4], None);
+    cache.set(key("b"), vec![0; 4], None);
+    assert_eq!(cache.stats().total_bytes, 10);
+
+    cache.set(key("c"), vec![0; 4], None);
+    assert_eq!(cache.get(key("a")), None);
+    assert_eq!(cache.stats().total_bytes, 10);
+
+    // A value over the limit on its own is not stored and evicts nothing
+    cache.set(key("big"), vec![0; 10], None);
+    assert_eq!(cache.get(key("big")), None);
+    assert_eq!(cache.stats().entry_count, 2);
+}
+
+#[test]
+fn test_cache_ttl_expiry() {
+    let cache = Cache::new(0, 0, Some(20));
+    cache.set(key("default"), vec![1], None);
+    cache.set(key("long"), vec![2], Some(60_000));
+    cache.set(key("purged"), vec![3], None);
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(cache.get(key("default")), None);
+    assert_eq!(cache.get(key("long")), Some(vec![2]));
+    assert_eq!(cache.purge_expired(), 1);
+
+    let stats = cache.stats();
+    assert_eq!(stats.expirations, 2);
+    assert_eq!(stats.entry_count, 1);
+}
+
+#[test]
+fn test_cache_clear_keeps_counters() {
+    let cache = Cache::new(0, 0, None);
+    cache.set(key("a"), vec![1], None);
+    assert!(cache.get(key("a")).is_some());
+
+    cache.clear();
+    let stats = cache.stats();
+    assert_eq!(stats.entry_count, 0);
+    assert_eq!(stats.total_bytes, 0);
+    assert_eq!(stats.hits, 1);
+}
@@ -0,0 +1,295 @@
+use rust_multiplatform_template_lib::{
+    echo, parse_uuid, random_bytes, random_int, random_uniform, run_with_timeout,
+    secure_random_double, CancellationListener, CancellationToken, TemplateError,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_token_without_timeout_has_no_deadline() {
+    let token = CancellationToken::new();
+    assert_eq!(token.remaining_ms(), None);
+}
+
+#[tokio::test]
+async fn test_token_with_timeout_cancels_itself() {
+    let token = CancellationToken::with_timeout(50);
+    assert!(!token.is_cancelled());
+    let remaining = token.remaining_ms().unwrap();
+    assert!(remaining > 0 && remaining <= 50);
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(token.is_cancelled());
+    assert_eq!(token.remaining_ms(), Some(0));
+}
+
+#[test]
+fn test_token_with_timeout_without_tokio_runtime() {
+    let token = CancellationToken::with_timeout(20);
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(token.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_token_cancelled_before_deadline() {
+    let token = CancellationToken::with_timeout(60_000);
+    token.cancel();
+    assert!(token.is_cancelled());
+    assert_eq!(token.remaining_ms(), Some(0));
+}
+
+#[tokio::test]
+async fn test_echo_with_expired_token() {
+    let token = Arc::new(CancellationToken::with_timeout(0));
+    match echo("test".to_string(), Some(token.clone()), None).await {
+        Err(TemplateError::Timeout {
+            operation,
+            timeout_ms,
+        }) => {
+            assert_eq!(operation, "echo");
+            assert_eq!(timeout_ms, 0);
+        }
+        _ => panic!("Expected Timeout error"),
+    }
+    assert!(token.is_timed_out());
+}
+
+#[tokio::test]
+async fn test_timed_out_child_token_reports_timeout() {
+    let parent = CancellationToken::with_timeout(0);
+    let child = parent.child();
+    assert!(matches!(
+        random_int(1, 6, Some(child)).await,
+        Err(TemplateError::Timeout { timeout_ms: 0, .. })
+    ));
+}
+
+#[test]
+fn test_explicit_cancel_is_not_a_timeout() {
+    let token = CancellationToken::with_timeout(60_000);
+    token.cancel();
+    assert!(token.is_cancelled());
+    assert!(!token.is_timed_out());
+    assert!(!CancellationToken::new().is_timed_out());
+}
+
+#[tokio::test]
+async fn test_cancel_before_deadline_stays_cancelled_after_it() {
+    let token = Arc::new(CancellationToken::with_timeout(20));
+    token.cancel();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!token.is_timed_out());
+    assert!(!token.child().is_timed_out());
+    assert!(matches!(
+        random_int(1, 6, Some(token)).await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_run_with_timeout() {
+    let result = run_with_timeout("sleep", Some(10), async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Ok(())
+    })
+    .await;
+    assert!(matches!(result, Err(TemplateError::Timeout { .. })));
+
+    let value = run_with_timeout("ready", None, async { Ok(42) }).await;
+    assert_eq!(value, Ok(42));
+}
+
+#[test]
+fn test_cancel_parent_cancels_children() {
+    let parent = CancellationToken::new();
+    let child1 = parent.child();
+    let child2 = parent.child();
+    let grandchild = child1.child();
+
+    parent.cancel();
+    assert!(child1.is_cancelled());
+    assert!(child2.is_cancelled());
+    assert!(grandchild.is_cancelled());
+}
+
+#[test]
+fn test_cancel_child_leaves_parent_and_siblings() {
+    let parent = CancellationToken::new();
+    let child1 = parent.child();
+    let child2 = parent.child();
+    let grandchild = child1.child();
+
+    child1.cancel();
+    assert!(grandchild.is_cancelled());
+    assert!(!parent.is_cancelled());
+    assert!(!child2.is_cancelled());
+}
+
+#[test]
+fn test_child_of_cancelled_token_starts_cancelled() {
+    let parent = CancellationToken::new();
+    parent.cancel();
+    assert!(parent.child().is_cancelled());
+}
+
+#[test]
+fn test_deep_hierarchy() {
+    let root = CancellationToken::new();
+    let mut chain = vec![root.child()];
+    for _ in 0..1_000 {
+        let next = chain.last().unwrap().child();
+        chain.push(next);
+    }
+
+    assert!(chain.iter().all(|t| !t.is_cancelled()));
+    root.cancel();
+    assert!(chain.iter().all(|t| t.is_cancelled()));
+}
+
+#[test]
+fn test_concurrent_cancel_and_child_creation() {
+    for _ in 0..50 {
+        let root = Arc::new(CancellationToken::new());
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let root = root.clone();
+                std::thread::spawn(move || {
+                    if i == 4 {
+                        root.cancel();
+                        Vec::new()
+                    } else {
+                        (0..100).map(|_| root.child()).collect()
+                    }
+                })
+            })
+            .collect();
+
+        let children: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        assert!(root.is_cancelled());
+        assert!(children.iter().all(|c| c.is_cancelled()));
+    }
+}
+
+#[tokio::test]
+async fn test_child_inherits_deadline() {
+    let parent = CancellationToken::with_timeout(30);
+    let child = parent.child();
+    assert!(child.remaining_ms().is_some());
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(child.is_cancelled());
+}
+
+#[test]
+fn test_on_cancel_runs_exactly_once() {
+    let token = CancellationToken::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    token.on_cancel(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    token.cancel();
+    token.cancel();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_on_cancel_after_cancel_runs_immediately() {
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    token.on_cancel(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+struct CountingListener(Arc<AtomicUsize>);
+
+impl CancellationListener for CountingListener {
+    fn on_cancelled(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_listeners_fire_for_children() {
+    let parent = CancellationToken::new();
+    let child = parent.child();
+    let calls = Arc::new(AtomicUsize::new(0));
+    parent.add_listener(Box::new(CountingListener(calls.clone())));
+    child.add_listener(Box::new(CountingListener(calls.clone())));
+
+    parent.cancel();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_listener_fires_on_timeout() {
+    let token = CancellationToken::with_timeout(20);
+    let calls = Arc::new(AtomicUsize::new(0));
+    token.add_listener(Box::new(CountingListener(calls.clone())));
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_cancelled_token_stops_exported_functions() {
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+
+    let operations = [
+        random_int(0, 10, Some(token.clone())).await.err(),
+        random_bytes(8, Some(token.clone())).await.err(),
+        random_uniform(0.0, 1.0, Some(token.clone())).await.err(),
+        secure_random_double(Some(token.clone())).await.err(),
+        parse_uuid(
+            "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+            Some(token.clone()),
+        )
+        .await
+        .err(),
+    ];
+    let names = [
+        "random_int",
+        "random_bytes",
+        "random_uniform",
+        "secure_random_double",
+        "parse_uuid",
+    ];
+
+    for (error, name) in operations.into_iter().zip(names) {
+        match error {
+            Some(TemplateError::OperationCancelled { operation }) => assert_eq!(operation, name),
+            other => panic!("Expected OperationCancelled for {}, got {:?}", name, other),
+        }
+    }
+}
+
+#[test]
+fn test_drop_guard_cancels_token() {
+    let token = CancellationToken::new();
+    {
+        let _guard = token.drop_guard();
+        assert!(!token.is_cancelled());
+    }
+    assert!(token.is_cancelled());
+}
+
+#[test]
+fn test_disarmed_drop_guard_leaves_token_alone() {
+    let token = CancellationToken::new();
+    let guard = token.drop_guard();
+    let returned = guard.disarm();
+    assert!(!token.is_cancelled());
+    assert!(!returned.is_cancelled());
+}
@@ -0,0 +1,163 @@
+use rust_multiplatform_template_lib::{
+    ClientIdentity, ClientKeySigner, ClientKeyType, ClientSignatureAlgorithm, TemplateError,
+};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    get_network_config, http_request, set_network_config, HttpMethod, HttpRequest, NetworkConfig,
+};
+#[cfg(feature = "http")]
+use std::sync::Arc;
+
+/// PKCS#12 with a P-256 key and its self-signed certificate for
+/// `device.example.com`, protected with the password `secret`
+#[cfg(feature = "http")]
+const IDENTITY_HEX: &str = "\
+    3082041c020103308203d206092a864886f70d010701a08203c3048203bf308203bb3082027206092a864886f70d0107\
+    06a08202633082025f0201003082025806092a864886f70d010701305706092a864886f70d01050d304a302906092a86\
+    4886f70d01050c301c0408d8add930ce97decd02020800300c06082a864886f70d02090500301d060960864801650304\
+    012a04105941e4be393aee9e160179d78edc2467808201f0a793f947f552ef9cadc7d01a6d5f4c32b41bc2cb248e9ec0\
+    7307cd0922c71e6732b2d5eae91a2d4529ae18417b1435f5c8a85832799d5b3af6a9ee989a1e5f3ac09a86ae0d580fa7\
+    07e9bdbd133ed6f2fbc71694852a19dca1321fa861207b69667b7d2581296b0cc468b42e48eee2b16c174def5f6f2c49\
+    a4a83bb03b06bb6d4b9a0095d8d9467d6bb00736b0354f08285179ffa460dd3d6125cb88fb07c3ca000b8e457f2b2b22\
+    7ed081996943643fd75baaa313d2d6a9e030f14a4f1f0be20d4ac6c0c82cec79ef1519fa4f9ffae1b3d4deb876d9ebde\
+    754422ed2962c35bbb96ef1d5e40f0455d5dfd408ba5833bb972862fea8e4ef5acc845a13e08002f7aacef0d991b9968\
+    56b9dd81cf34722afe8251f8bd83c319b1f538f9d75eb838348af72442f28611b6d9b5cc3a1d9a359a8a97526d4a5e74\
+    f86488c6d2c0088dc74dbee1c41fa67de50c40e97650a26c2a9beee5c6715d0bfcfcb4e0cd7ad73d0cfb8942c5fc0276\
+    79f4390f32204cb7d29c47a97b0b80e0fbc86324222610f0b886139363d45f0935cd6093f6437f30abe5051331aa3dc4\
+    8d50e6e62cd3f7a2ae7f4b521e2ebd0bdb0e0d851b31090e54d7e50faf75d1136ee8c5625ab7318dde2b19c7c19ff594\
+    36648f8b3384609a5161a29c31e806d6e7e3b2eb01084bbc3cb683782c703374c5114188ce0a43a43082014106092a86\
+    4886f70d010701a08201320482012e3082012a30820126060b2a864886f70d010c0a0102a081ef3081ec305706092a86\
+    4886f70d01050d304a302906092a864886f70d01050c301c040801bda5ccde64b72102020800300c06082a864886f70d\
+    02090500301d060960864801650304012a041093143faf08c88f33cfa8f623e5c09e02048190dced13e18e3015adb8ac\
+    dc928cabd23e4b7ed4e4d7a1aad4a2155a985bc2fc1dfe58c37684186fdfb921293ce2ae7046b9e5f1071ad925c63df9\
+    c5ce1f77c4a9b77cc74a75d7d075f884f4c7215a0c0a847ea1259f80f367a6da155f93ced9ca85893259fa776f77f471\
+    fb43733b38f2e51fa8ba401141ccda1f3d60963b4aabac58b7ec1f5aa1ad8efec02acf683fd53125302306092a864886\
+    f70d01091531160414e637e444728487922292e3ae2cb5dd44f93cd13b30413031300d06096086480165030402010500\
+    0420095706729051dff376929f6e277bba71d938d6911c2e43f0632d37ea18e687ab0408e86b72850be119da02020800";
+
+/// The certificate inside `IDENTITY_HEX`
+const CERTIFICATE_HEX: &str = "\
+    3082019030820137a00302010202146031b6c6c0c867a501f68eaf0e2e2f5c273c9b4f300a06082a8648ce3d04030230\
+    1d311b301906035504030c126465766963652e6578616d706c652e636f6d3020170d3236313031363038353534315a18\
+    0f32313236303932323038353534315a301d311b301906035504030c126465766963652e6578616d706c652e636f6d30\
+    59301306072a8648ce3d020106082a8648ce3d0301070342000447445217882424f53be6a6d93a081d499da7edfb6205\
+    5bc4fd653f3011cc0410726e99c3d1b353612f7f74a2dd32afe43c5ef29ffc3be90ec77ae9ec098fef39a3533051301d\
+    0603551d0e04160414dcfa9785710dfd2b7d120ba0633ee430fda2bfff301f0603551d23041830168014dcfa9785710d\
+    fd2b7d120ba0633ee430fda2bfff300f0603551d130101ff040530030101ff300a06082a8648ce3d0403020347003044\
+    02205bc4ea8769067ced270cde42721f75763728207549bdf9a93dbcd45219c2ba5b02201f879976fba9d9a85243413a\
+    b4f2ba899b2d0ade33af4fcc374954a1e72cd021";
+
+/// Stands in for a Secure Enclave key; never asked to sign in these tests
+struct Declining;
+
+impl ClientKeySigner for Declining {
+    fn sign(&self, _message: Vec<u8>, _algorithm: ClientSignatureAlgorithm) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+fn certificate() -> Vec<u8> {
+    hex::decode(CERTIFICATE_HEX).unwrap()
+}
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_client_identity_requires_feature() {
+    assert!(matches!(
+        ClientIdentity::from_pkcs12(vec![0x30], "secret".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        ClientIdentity::from_signer(
+            vec![certificate()],
+            ClientKeyType::EcdsaP256,
+            Box::new(Declining)
+        ),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_client_identity_from_pkcs12() {
+    let data = hex::decode(IDENTITY_HEX).unwrap();
+    let identity = ClientIdentity::from_pkcs12(data.clone(), "secret".to_string()).unwrap();
+    assert_eq!(identity.certificate_chain(), [certificate()]);
+
+    assert!(matches!(
+        ClientIdentity::from_pkcs12(data.clone(), "wrong".to_string()),
+        Err(TemplateError::EncryptionError { .. })
+    ));
+    for invalid in [Vec::new(), b"not pkcs12".to_vec(), data[..200].to_vec()] {
+        assert!(matches!(
+            ClientIdentity::from_pkcs12(invalid, "secret".to_string()),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_client_identity_from_signer() {
+    let identity = ClientIdentity::from_signer(
+        vec![certificate()],
+        ClientKeyType::EcdsaP256,
+        Box::new(Declining),
+    )
+    .unwrap();
+    assert_eq!(identity.certificate_chain(), [certificate()]);
+
+    for invalid in [
+        Vec::new(),
+        vec![b"not a certificate".to_vec()],
+        vec![certificate(), certificate()[..100].to_vec()],
+    ] {
+        assert!(matches!(
+            ClientIdentity::from_signer(invalid, ClientKeyType::Rsa, Box::new(Declining)),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+}
+
+/// Answers every request on a plain HTTP connection with `200 ok`
+#[cfg(feature = "http")]
+fn serve() -> String {
+    let base = common::serve(|_, mut stream| common::respond(&mut stream, "200 OK", "", b"ok"));
+    format!("{}/", base)
+}
+
+// The network config is process-wide, so these checks run in one test
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_client_identity_in_network_config() {
+    let data = hex::decode(IDENTITY_HEX).unwrap();
+    let identity = Arc::new(ClientIdentity::from_pkcs12(data, "secret".to_string()).unwrap());
+    let config = NetworkConfig {
+        client_identity: Some(identity.clone()),
+        ..NetworkConfig::default()
+    };
+    set_network_config(config.clone()).unwrap();
+    assert_eq!(get_network_config(), config);
+    assert!(Arc::ptr_eq(
+        get_network_config().client_identity.as_ref().unwrap(),
+        &identity
+    ));
+
+    // Requests go through the client rebuilt with the identity
+    let request = HttpRequest {
+        url: serve(),
+        method: HttpMethod::Get,
+        headers: Default::default(),
+        body: None,
+        timeout_ms: Some(5_000),
+    };
+    let response = http_request(request, None).await.unwrap();
+    assert_eq!(response.body, b"ok");
+
+    set_network_config(NetworkConfig::default()).unwrap();
+    assert!(get_network_config().client_identity.is_none());
+}
@@ -0,0 +1,115 @@
+use rust_multiplatform_template_lib::{
+    get_config, load_config, save_config, update_config, HashAlgorithm, LibraryConfig, LogLevel,
+    TemplateConfig, TemplateError, CONFIG_SCHEMA_VERSION,
+};
+use std::fs;
+
+fn path_in(dir: &tempfile::TempDir, name: &str) -> String {
+    dir.path().join(name).to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_template_config_save_and_load_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = path_in(&dir, "template.json");
+    let config = TemplateConfig::from_json(
+        r#"{"max_input_size": 64, "hash_algorithm": "blake3", "timeout_ms": 250}"#.into(),
+    )
+    .unwrap();
+
+    config.save(path.clone()).unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(saved["schema_version"], CONFIG_SCHEMA_VERSION);
+
+    let loaded = TemplateConfig::load(path).unwrap();
+    assert_eq!(loaded.max_input_size(), 64);
+    assert_eq!(loaded.hash_algorithm(), HashAlgorithm::Blake3);
+    assert_eq!(loaded.timeout_ms(), Some(250));
+}
+
+#[test]
+fn test_template_config_load_reads_newer_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = path_in(&dir, "template.json");
+    fs::write(
+        &path,
+        r#"{
+            "schema_version": 99,
+            "config": {
+                "max_input_size": 32,
+                "hash_algorithm": "some_future_hash",
+                "added_later": {"nested": true}
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let loaded = TemplateConfig::load(path).unwrap();
+    assert_eq!(loaded.max_input_size(), 32);
+    // Unreadable and unknown fields fall back to the defaults
+    assert_eq!(loaded.hash_algorithm(), HashAlgorithm::default());
+}
+
+#[test]
+fn test_template_config_load_errors() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let missing = TemplateConfig::load(path_in(&dir, "missing.json"));
+    assert!(matches!(missing, Err(TemplateError::IoError { .. })));
+
+    let corrupt = path_in(&dir, "corrupt.json");
+    fs::write(&corrupt, "not json").unwrap();
+    assert!(matches!(
+        TemplateConfig::load(corrupt),
+        Err(TemplateError::ParseError { .. })
+    ));
+}
+
+// The library configuration is process-wide, so these checks run in one test
+#[test]
+fn test_library_config_save_and_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = path_in(&dir, "library.json");
+
+    assert_eq!(load_config(path.clone()).unwrap(), None);
+
+    let saved = LibraryConfig {
+        max_input_size: 4096,
+        log_level: LogLevel::Debug,
+        collect_timing: true,
+        ..LibraryConfig::default()
+    };
+    update_config(saved.clone()).unwrap();
+    save_config(path.clone()).unwrap();
+
+    update_config(LibraryConfig::default()).unwrap();
+    assert_eq!(load_config(path.clone()).unwrap(), Some(saved.clone()));
+    assert_eq!(get_config(), saved);
+
+    // Fields missing from an older file keep their defaults
+    fs::write(
+        &path,
+        r#"{"schema_version": 1, "config": {"log_level": "error"}}"#,
+    )
+    .unwrap();
+    let loaded = load_config(path.clone()).unwrap().unwrap();
+    assert_eq!(loaded.log_level, LogLevel::Error);
+    assert_eq!(
+        loaded.max_input_size,
+        LibraryConfig::default().max_input_size
+    );
+
+    // An invalid saved configuration is rejected and not applied
+    fs::write(
+        &path,
+        r#"{"schema_version": 1, "config": {"runtime_threads": 0}}"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        load_config(path),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert_eq!(get_config().log_level, LogLevel::Error);
+
+    update_config(LibraryConfig::default()).unwrap();
+}
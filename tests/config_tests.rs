@@ -0,0 +1,64 @@
+use rust_multiplatform_template_lib::{
+    echo, get_config, initialize, update_config, LibraryConfig, LogLevel, PreviewMode,
+    TemplateError, MAX_INPUT_SIZE,
+};
+
+// The library configuration is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_library_config_lifecycle() {
+    let defaults = get_config();
+    assert_eq!(defaults, LibraryConfig::default());
+    assert_eq!(defaults.max_input_size, MAX_INPUT_SIZE as u64);
+
+    let invalid = LibraryConfig {
+        max_input_size: 0,
+        ..LibraryConfig::default()
+    };
+    assert!(matches!(
+        initialize(invalid),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let config = LibraryConfig {
+        max_input_size: 8,
+        log_level: LogLevel::Debug,
+        runtime_threads: 2,
+        ..LibraryConfig::default()
+    };
+    initialize(config.clone()).unwrap();
+    assert_eq!(get_config(), config);
+    assert!(matches!(
+        initialize(config.clone()),
+        Err(TemplateError::AlreadyInitialized)
+    ));
+
+    // echo reads its limit from the shared configuration
+    assert!(echo("12345678".to_string(), None, None).await.is_ok());
+    match echo("123456789".to_string(), None, None).await {
+        Err(TemplateError::InputTooLarge { max, .. }) => assert_eq!(max, 8),
+        _ => panic!("Expected InputTooLarge error"),
+    }
+
+    update_config(LibraryConfig {
+        max_input_size: 16,
+        ..config
+    })
+    .unwrap();
+    assert_eq!(get_config().max_input_size, 16);
+    assert!(echo("123456789".to_string(), None, None).await.is_ok());
+
+    // and formats its input previews with the shared preview settings
+    update_config(LibraryConfig {
+        preview_mode: PreviewMode::Redact,
+        ..get_config()
+    })
+    .unwrap();
+    match echo("a\0b".to_string(), None, None).await {
+        Err(TemplateError::InvalidInput { input_preview, .. }) => {
+            let preview = input_preview.unwrap();
+            assert!(preview.starts_with("[redacted sha256:"));
+            assert!(!preview.contains("a\0b"));
+        }
+        other => panic!("Expected InvalidInput error, got {:?}", other),
+    }
+}
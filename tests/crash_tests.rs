@@ -0,0 +1,58 @@
+use rust_multiplatform_template_lib::{
+    delete_crash_report, disable_crash_reports, enable_crash_reports, get_pending_crash_reports,
+    TemplateError,
+};
+use std::fs;
+
+// Crash reporting is process-wide, so these checks run in one test
+#[test]
+fn test_panics_are_recorded_and_can_be_deleted() {
+    assert!(matches!(
+        get_pending_crash_reports(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let crashes = dir.path().join("crashes");
+    enable_crash_reports(crashes.to_string_lossy().into_owned()).unwrap();
+    fs::write(crashes.join("garbage.json"), "not json").unwrap();
+
+    std::thread::Builder::new()
+        .name("crashing-worker".to_string())
+        .spawn(|| panic!("simulated crash"))
+        .unwrap()
+        .join()
+        .unwrap_err();
+
+    let reports = get_pending_crash_reports().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.message, "simulated crash");
+    assert_eq!(report.thread, "crashing-worker");
+    assert!(report
+        .location
+        .as_deref()
+        .unwrap()
+        .contains("crash_tests.rs"));
+    assert!(!report.backtrace.is_empty());
+    assert_eq!(report.library_version, env!("CARGO_PKG_VERSION"));
+    assert!(report.timestamp_ms > 0);
+
+    assert!(matches!(
+        delete_crash_report("../garbage".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    delete_crash_report(report.id.clone()).unwrap();
+    delete_crash_report(report.id.clone()).unwrap();
+    assert!(get_pending_crash_reports().unwrap().is_empty());
+
+    disable_crash_reports();
+    std::thread::spawn(|| panic!("not recorded"))
+        .join()
+        .unwrap_err();
+    assert!(matches!(
+        get_pending_crash_reports(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert_eq!(fs::read_dir(&crashes).unwrap().count(), 1);
+}
@@ -0,0 +1,244 @@
+use rust_multiplatform_template_lib::{Database, TemplateError};
+
+#[cfg(feature = "sqlite")]
+use rust_multiplatform_template_lib::{
+    CancellationToken, DatabaseMigration, MigrationReport, SqlStatement, SqlValue,
+};
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
+
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn test_database_requires_feature() {
+    match Database::open(":memory:".to_string()) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("sqlite"))
+        }
+        Err(e) => panic!("Expected InvalidInput, got {:?}", e),
+        Ok(_) => panic!("Expected InvalidInput, got a database"),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn text(value: &str) -> SqlValue {
+    SqlValue::Text {
+        value: value.to_string(),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+async fn open_with_table() -> Database {
+    let db = Database::open(":memory:".to_string()).unwrap();
+    db.execute(
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT UNIQUE, score REAL, data BLOB)"
+            .to_string(),
+        Vec::new(),
+        None,
+    )
+    .await
+    .unwrap();
+    db
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_execute_and_query_typed_rows() {
+    let db = open_with_table().await;
+    let inserted = db
+        .execute(
+            "INSERT INTO notes (title, score, data) VALUES (?, ?, ?), (?, NULL, NULL)".to_string(),
+            vec![
+                text("first"),
+                SqlValue::Real { value: 1.5 },
+                SqlValue::Blob { value: vec![1, 2] },
+                text("second"),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(inserted, 2);
+
+    let result = db
+        .query(
+            "SELECT id, title, score, data FROM notes WHERE id >= ? ORDER BY id".to_string(),
+            vec![SqlValue::Integer { value: 1 }],
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.columns, ["id", "title", "score", "data"]);
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(
+        result.rows[0].values,
+        [
+            SqlValue::Integer { value: 1 },
+            text("first"),
+            SqlValue::Real { value: 1.5 },
+            SqlValue::Blob { value: vec![1, 2] },
+        ]
+    );
+    assert_eq!(result.rows[1].values[2], SqlValue::Null);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_transaction_rolls_back_on_failure() {
+    let db = open_with_table().await;
+    let insert = |title: &str| SqlStatement {
+        sql: "INSERT INTO notes (title) VALUES (?)".to_string(),
+        params: vec![text(title)],
+    };
+
+    let counts = db
+        .transaction(vec![insert("a"), insert("b")], None)
+        .await
+        .unwrap();
+    assert_eq!(counts, [1, 1]);
+
+    match db.transaction(vec![insert("c"), insert("a")], None).await {
+        Err(TemplateError::DatabaseError { code, .. }) => {
+            assert_eq!(code.as_deref(), Some("ConstraintViolation"))
+        }
+        other => panic!("Expected DatabaseError, got {:?}", other),
+    }
+    let count = db
+        .query("SELECT COUNT(*) FROM notes".to_string(), Vec::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(count.rows[0].values, [SqlValue::Integer { value: 2 }]);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_invalid_sql_and_cancelled_token() {
+    let db = Database::open(":memory:".to_string()).unwrap();
+    let error = db
+        .query("SELEC 1".to_string(), Vec::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, TemplateError::DatabaseError { .. }));
+    assert!(!error.is_retryable());
+
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+    assert!(matches!(
+        db.execute("SELECT 1".to_string(), Vec::new(), Some(token))
+            .await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_cancel_interrupts_running_query() {
+    let db = Database::open(":memory:".to_string()).unwrap();
+    let token = Arc::new(CancellationToken::new());
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        canceller.cancel();
+    });
+    // Counts to a billion; far longer than the test waits
+    let sql = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000000000) SELECT COUNT(*) FROM n";
+    assert!(matches!(
+        db.query(sql.to_string(), Vec::new(), Some(token)).await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+#[cfg(feature = "sqlite")]
+fn statement(sql: &str) -> SqlStatement {
+    SqlStatement {
+        sql: sql.to_string(),
+        params: Vec::new(),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_open_with_migrations_applies_pending_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.db").to_string_lossy().into_owned();
+    let v1 = DatabaseMigration {
+        version: 1,
+        statements: vec![statement(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT)",
+        )],
+    };
+    let v2 = DatabaseMigration {
+        version: 2,
+        statements: vec![statement(
+            "ALTER TABLE notes ADD COLUMN pinned INTEGER DEFAULT 0",
+        )],
+    };
+
+    let db = Database::open_with_migrations(path.clone(), vec![v1.clone()]).unwrap();
+    assert_eq!(db.migration_report().applied, vec![1]);
+    drop(db);
+
+    let db = Database::open_with_migrations(path.clone(), vec![v1.clone(), v2.clone()]).unwrap();
+    assert_eq!(
+        db.migration_report(),
+        MigrationReport {
+            from_version: 1,
+            to_version: 2,
+            applied: vec![2],
+        }
+    );
+    db.execute(
+        "INSERT INTO notes (title, pinned) VALUES ('a', 1)".to_string(),
+        Vec::new(),
+        None,
+    )
+    .await
+    .unwrap();
+    drop(db);
+
+    assert_eq!(
+        Database::open(path).unwrap().migration_report(),
+        MigrationReport {
+            from_version: 2,
+            to_version: 2,
+            applied: Vec::new(),
+        }
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_failed_migration_is_rolled_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.db").to_string_lossy().into_owned();
+    let migrations = vec![
+        DatabaseMigration {
+            version: 1,
+            statements: vec![statement("CREATE TABLE notes (id INTEGER PRIMARY KEY)")],
+        },
+        DatabaseMigration {
+            version: 2,
+            statements: vec![
+                statement("CREATE TABLE tags (id INTEGER PRIMARY KEY)"),
+                statement("NOT VALID SQL"),
+            ],
+        },
+    ];
+    assert!(matches!(
+        Database::open_with_migrations(path.clone(), migrations),
+        Err(TemplateError::DatabaseError { .. })
+    ));
+
+    // Version 1 was kept, version 2 left nothing behind
+    let db = Database::open(path).unwrap();
+    assert_eq!(db.migration_report().to_version, 1);
+    let tables = db
+        .query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name".to_string(),
+            Vec::new(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(tables.rows.len(), 1);
+    assert_eq!(tables.rows[0].values, vec![text("notes")]);
+}
@@ -0,0 +1,86 @@
+use rust_multiplatform_template_lib::{
+    get_app_directories, get_storage_breakdown, set_app_directories, storage_directory,
+    ArtifactStore, KvStore, StorageCategory, TemplateError,
+};
+
+fn string(path: &std::path::Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+// App directories are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_app_directories_place_files_by_category() {
+    assert!(get_app_directories().is_none());
+    assert!(matches!(
+        storage_directory(StorageCategory::Data),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        set_app_directories("cache".to_string(), "data".to_string(), "tmp".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let root = tempfile::tempdir().unwrap();
+    let (cache, data, temp) = (
+        root.path().join("Caches"),
+        root.path().join("Application Support"),
+        root.path().join("tmp"),
+    );
+    set_app_directories(string(&cache), string(&data), string(&temp)).unwrap();
+    assert!(cache.is_dir() && data.is_dir() && temp.is_dir());
+    assert_eq!(get_app_directories().unwrap().data_dir, string(&data));
+    assert_eq!(
+        storage_directory(StorageCategory::Downloads).unwrap(),
+        string(&cache.join("downloads"))
+    );
+    assert_eq!(
+        storage_directory(StorageCategory::CrashReports).unwrap(),
+        string(&data.join("crash_reports"))
+    );
+
+    // Relative paths land in the directory of their category
+    let store = KvStore::open("settings/store.json".to_string()).unwrap();
+    store.set_bytes("blob".to_string(), vec![0; 1000]).unwrap();
+    assert!(data.join("settings/store.json").is_file());
+
+    let artifacts = ArtifactStore::open("artifacts".to_string()).unwrap();
+    let model = artifacts
+        .put("model".to_string(), vec![1; 5000])
+        .await
+        .unwrap();
+    assert!(model
+        .path
+        .starts_with(&string(&cache.join("downloads/artifacts"))));
+
+    // Absolute paths are used as given
+    let elsewhere = root.path().join("elsewhere.json");
+    KvStore::open(string(&elsewhere))
+        .unwrap()
+        .set_int("a".to_string(), 1)
+        .unwrap();
+    assert!(elsewhere.is_file());
+
+    std::fs::write(cache.join("thumbnail.png"), vec![0; 300]).unwrap();
+    std::fs::write(temp.join("scratch"), vec![0; 20]).unwrap();
+
+    let breakdown = get_storage_breakdown().await.unwrap();
+    let bytes = |category| {
+        breakdown
+            .usage
+            .iter()
+            .find(|usage| usage.category == category)
+            .unwrap()
+            .bytes
+    };
+    // Downloads are not counted again as part of the cache
+    assert!(bytes(StorageCategory::Downloads) >= 5000);
+    assert_eq!(bytes(StorageCategory::Cache), 300);
+    assert_eq!(bytes(StorageCategory::Temp), 20);
+    assert!(bytes(StorageCategory::Data) >= 1000);
+    assert_eq!(bytes(StorageCategory::Logs), 0);
+    assert_eq!(bytes(StorageCategory::CrashReports), 0);
+    assert_eq!(
+        breakdown.total_bytes,
+        breakdown.usage.iter().map(|usage| usage.bytes).sum::<u64>()
+    );
+}
@@ -0,0 +1,46 @@
+use rust_multiplatform_template_lib::{
+    check_disk_space, classify_error, get_free_space, localize_error, ErrorKind, TemplateError,
+};
+
+fn path(dir: &tempfile::TempDir, name: &str) -> String {
+    dir.path().join(name).to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_free_space_of_missing_path_uses_existing_ancestor() {
+    let dir = tempfile::tempdir().unwrap();
+    let free = get_free_space(path(&dir, "")).unwrap();
+    assert!(free > 0);
+
+    let missing = get_free_space(path(&dir, "downloads/models/model.gguf")).unwrap();
+    assert!(missing > 0);
+    assert!(!dir.path().join("downloads").exists());
+}
+
+#[test]
+fn test_check_disk_space_reports_shortfall() {
+    let dir = tempfile::tempdir().unwrap();
+    let destination = path(&dir, "model.gguf");
+    assert!(check_disk_space(destination.clone(), 0).unwrap() > 0);
+
+    let error = check_disk_space(destination.clone(), u64::MAX).unwrap_err();
+    let TemplateError::InsufficientStorage {
+        path,
+        required,
+        available,
+    } = &error
+    else {
+        panic!("expected InsufficientStorage, got {:?}", error);
+    };
+    assert_eq!(path, &destination);
+    assert_eq!(*required, u64::MAX);
+    assert!(*available < u64::MAX);
+
+    assert_eq!(error.kind(), ErrorKind::InsufficientStorage);
+    assert_eq!(error.kind().code(), "INSUFFICIENT_STORAGE");
+    let classification = classify_error(error.clone());
+    assert!(classification.transient && !classification.retryable);
+    let localized = localize_error(error);
+    assert_eq!(localized.key, "template.error.insufficient_storage");
+    assert_eq!(localized.params["required"], u64::MAX.to_string());
+}
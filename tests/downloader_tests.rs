@@ -0,0 +1,752 @@
+use rust_multiplatform_template_lib::{Downloader, TemplateError};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    add_http_interceptor, remove_http_interceptor, set_network_status, BandwidthLimits,
+    DownloadInfo, DownloadListener, DownloadProgress, DownloadState, HttpInterceptor, HttpRequest,
+    HttpResponse, NetworkPolicy, NetworkStatus, ParallelChunks,
+};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::io::Write;
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+#[cfg(feature = "http")]
+use std::path::Path;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_downloader_requires_feature() {
+    match Downloader::new(2, None) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        Err(e) => panic!("Expected InvalidInput, got {:?}", e),
+        Ok(_) => panic!("Expected InvalidInput, got a downloader"),
+    }
+}
+
+#[cfg(feature = "http")]
+const FILE_SIZE: usize = 256 * 1024;
+
+#[cfg(feature = "http")]
+const CHUNK_SIZE: usize = 8 * 1024;
+
+#[cfg(feature = "http")]
+fn contents() -> Vec<u8> {
+    (0..FILE_SIZE).map(|i| (i % 251) as u8).collect()
+}
+
+/// Serves `contents()` at `/file` in chunks `chunk_delay` apart, honoring
+/// `Range: bytes=N-` and `bytes=N-M` when `If-Range` is absent or matches
+/// its ETag, and 404 elsewhere; records the `Range` header of every request
+///
+/// `/flaky` serves the same file, but closes the connection halfway through
+/// `bytes=N-M` ranges that start on a quarter of the file
+#[cfg(feature = "http")]
+struct Server {
+    base: String,
+    ranges: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+#[cfg(feature = "http")]
+impl Server {
+    fn start(chunk_delay: Duration) -> Self {
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let recorded = ranges.clone();
+        let base =
+            common::serve(move |request, stream| respond(request, stream, chunk_delay, &recorded));
+        Self { base, ranges }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+}
+
+#[cfg(feature = "http")]
+fn respond(
+    request: common::Request,
+    mut stream: TcpStream,
+    chunk_delay: Duration,
+    ranges: &Mutex<Vec<Option<String>>>,
+) {
+    let path = request.path.as_str();
+    let range = request.header("range").map(str::to_string);
+    let if_range = request.header("if-range");
+    ranges.lock().unwrap().push(range.clone());
+
+    if path != "/file" && path != "/flaky" {
+        return common::respond(&mut stream, "404 Not Found", "", b"");
+    }
+    let body = contents();
+    let bounds = range
+        .filter(|_| if_range.is_none_or(|tag| tag == "\"v1\""))
+        .and_then(|range| {
+            let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+            let end = match end {
+                "" => None,
+                end => Some(end.parse::<usize>().ok()?),
+            };
+            Some((start.parse::<usize>().ok()?, end))
+        });
+    let start = bounds.map(|(start, _)| start);
+    let end = bounds.and_then(|(_, end)| end).unwrap_or(FILE_SIZE - 1);
+    let head = match start {
+        Some(start) => format!(
+            "HTTP/1.1 206 Partial Content\r\ncontent-range: bytes {}-{}/{}\r\n\
+             content-length: {}\r\netag: \"v1\"\r\nconnection: close\r\n\r\n",
+            start,
+            end,
+            FILE_SIZE,
+            end + 1 - start
+        ),
+        None => format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\netag: \"v1\"\r\nconnection: close\r\n\r\n",
+            FILE_SIZE
+        ),
+    };
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    let start = start.unwrap_or(0);
+    let mut body = &body[start..=end];
+    let bounded = bounds.is_some_and(|(_, end)| end.is_some());
+    if path == "/flaky" && bounded && start.is_multiple_of(FILE_SIZE / 4) {
+        body = &body[..body.len() / 2];
+    }
+    for chunk in body.chunks(CHUNK_SIZE) {
+        if stream.write_all(chunk).is_err() {
+            return;
+        }
+        thread::sleep(chunk_delay);
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Default)]
+struct Recorded {
+    progress: Vec<DownloadProgress>,
+    states: Vec<DownloadState>,
+}
+
+#[cfg(feature = "http")]
+struct RecordingListener(Arc<Mutex<Recorded>>);
+
+#[cfg(feature = "http")]
+impl DownloadListener for RecordingListener {
+    fn on_download_progress(&self, progress: DownloadProgress) {
+        self.0.lock().unwrap().progress.push(progress);
+    }
+
+    fn on_download_state_changed(&self, download: DownloadInfo) {
+        self.0.lock().unwrap().states.push(download.state);
+    }
+}
+
+#[cfg(feature = "http")]
+fn listen(downloader: &Downloader) -> Arc<Mutex<Recorded>> {
+    let recorded = Arc::new(Mutex::new(Recorded::default()));
+    downloader.set_listener(Box::new(RecordingListener(recorded.clone())));
+    recorded
+}
+
+#[cfg(feature = "http")]
+fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(feature = "http")]
+fn wait_idle(downloader: &Downloader) {
+    tokio_test::block_on(downloader.wait_idle());
+}
+
+#[cfg(feature = "http")]
+fn destination(dir: &tempfile::TempDir, name: &str) -> String {
+    dir.path().join(name).to_string_lossy().into_owned()
+}
+
+#[cfg(feature = "http")]
+fn partial(destination: &str) -> String {
+    format!("{}.partial", destination)
+}
+
+/// Held by tests that change the process-wide network status
+#[cfg(feature = "http")]
+static NETWORK_STATUS: Mutex<()> = Mutex::new(());
+
+/// Downloads `/file` to `name` and returns how long it took
+#[cfg(feature = "http")]
+fn timed_download(
+    downloader: &Downloader,
+    server: &Server,
+    dir: &tempfile::TempDir,
+    name: &str,
+) -> Duration {
+    let started = Instant::now();
+    downloader
+        .enqueue(server.url("/file"), destination(dir, name), HashMap::new())
+        .unwrap();
+    wait_idle(downloader);
+    started.elapsed()
+}
+
+/// Starts downloading `/file` and pauses it once some bytes have arrived
+#[cfg(feature = "http")]
+fn start_and_pause(downloader: &Downloader, server: &Server, destination: &str) -> u64 {
+    let id = downloader
+        .enqueue(server.url("/file"), destination.to_string(), HashMap::new())
+        .unwrap();
+    wait_until(|| {
+        Path::new(&partial(destination))
+            .metadata()
+            .is_ok_and(|m| m.len() > 0)
+    });
+    assert!(downloader.pause(id));
+    wait_idle(downloader);
+    assert_eq!(
+        downloader.download(id).unwrap().state,
+        DownloadState::Paused
+    );
+    id
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_download_reports_progress_and_completes() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(2, None).unwrap();
+    let recorded = listen(&downloader);
+    let destination = destination(&dir, "models/model.gguf");
+
+    let id = downloader
+        .enqueue(server.url("/file"), destination.clone(), HashMap::new())
+        .unwrap();
+    wait_idle(&downloader);
+
+    let info = downloader.download(id).unwrap();
+    assert_eq!(info.state, DownloadState::Completed);
+    assert_eq!(info.downloaded_bytes, FILE_SIZE as u64);
+    assert_eq!(info.total_bytes, Some(FILE_SIZE as u64));
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    assert!(!Path::new(&partial(&destination)).exists());
+
+    let recorded = recorded.lock().unwrap();
+    assert_eq!(
+        recorded.states,
+        [
+            DownloadState::Queued,
+            DownloadState::Running,
+            DownloadState::Completed
+        ]
+    );
+    assert!(!recorded.progress.is_empty());
+    for progress in &recorded.progress {
+        assert_eq!(progress.id, id);
+        assert_eq!(progress.total_bytes, Some(FILE_SIZE as u64));
+        assert!(progress.bytes_per_second > 0);
+    }
+    assert!(recorded
+        .progress
+        .windows(2)
+        .all(|pair| pair[0].downloaded_bytes < pair[1].downloaded_bytes));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_resume_requests_only_remaining_bytes() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    let destination = destination(&dir, "model.gguf");
+
+    let id = start_and_pause(&downloader, &server, &destination);
+    let kept = std::fs::metadata(partial(&destination)).unwrap().len();
+    assert!(kept > 0 && kept < FILE_SIZE as u64);
+    assert_eq!(downloader.download(id).unwrap().downloaded_bytes, kept);
+    assert!(!downloader.resume(id + 1));
+
+    assert!(downloader.resume(id));
+    wait_idle(&downloader);
+    assert_eq!(
+        downloader.download(id).unwrap().state,
+        DownloadState::Completed
+    );
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    assert_eq!(
+        server.ranges.lock().unwrap().last().unwrap().as_deref(),
+        Some(format!("bytes={}-", kept).as_str())
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_unfinished_downloads_survive_restart() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let state = destination(&dir, "downloads.json");
+    let destination = destination(&dir, "model.gguf");
+
+    let id = {
+        let downloader = Downloader::new(1, Some(state.clone())).unwrap();
+        start_and_pause(&downloader, &server, &destination)
+    };
+
+    let downloader = Downloader::new(1, Some(state.clone())).unwrap();
+    let restored = downloader.download(id).unwrap();
+    assert_eq!(restored.state, DownloadState::Paused);
+    assert_eq!(restored.max_bytes_per_second, None);
+    assert!(downloader
+        .set_download_bandwidth(id, Some(1 << 30))
+        .unwrap());
+    drop(downloader);
+
+    let downloader = Downloader::new(1, Some(state.clone())).unwrap();
+    let restored = downloader.download(id).unwrap();
+    assert_eq!(restored.max_bytes_per_second, Some(1 << 30));
+    assert_eq!(restored.url, server.url("/file"));
+    assert_eq!(
+        restored.downloaded_bytes,
+        std::fs::metadata(partial(&destination)).unwrap().len()
+    );
+    assert!(downloader.resume(id));
+    wait_idle(&downloader);
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+
+    // Finished downloads are not restored
+    let downloader = Downloader::new(1, Some(state)).unwrap();
+    assert!(downloader.downloads().is_empty());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_cancel_failure_and_concurrency_limit() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    let (first, second) = (destination(&dir, "a.bin"), destination(&dir, "b.bin"));
+
+    let running = downloader
+        .enqueue(server.url("/file"), first.clone(), HashMap::new())
+        .unwrap();
+    let missing = downloader
+        .enqueue(server.url("/missing"), second.clone(), HashMap::new())
+        .unwrap();
+    assert_eq!(
+        downloader.download(missing).unwrap().state,
+        DownloadState::Queued
+    );
+
+    wait_until(|| Path::new(&partial(&first)).exists());
+    assert!(downloader.cancel(running));
+    wait_idle(&downloader);
+    assert_eq!(
+        downloader.download(running).unwrap().state,
+        DownloadState::Cancelled
+    );
+    assert!(!Path::new(&partial(&first)).exists());
+    assert!(!downloader.cancel(running));
+    assert!(!downloader.resume(running));
+
+    let failed = downloader.download(missing).unwrap();
+    assert_eq!(failed.state, DownloadState::Failed);
+    assert!(failed.error_message.unwrap().contains("404"));
+    assert!(!Path::new(&second).exists());
+    assert!(downloader.resume(missing));
+    wait_idle(&downloader);
+    assert_eq!(
+        downloader.download(missing).unwrap().state,
+        DownloadState::Failed
+    );
+    assert!(downloader.cancel(missing));
+    assert_eq!(
+        downloader.download(missing).unwrap().state,
+        DownloadState::Cancelled
+    );
+}
+
+/// Sends requests for `/moved` on `base` to `/file` instead, and records
+/// the responses from `base`
+#[cfg(feature = "http")]
+struct Redirector {
+    base: String,
+    seen: Arc<Mutex<Vec<(HttpRequest, HttpResponse)>>>,
+}
+
+#[cfg(feature = "http")]
+impl HttpInterceptor for Redirector {
+    fn intercept_request(&self, mut request: HttpRequest) -> HttpRequest {
+        if request.url == format!("{}/moved", self.base) {
+            request.url = format!("{}/file", self.base);
+        }
+        request
+    }
+
+    fn on_response(&self, request: HttpRequest, response: HttpResponse) {
+        if request.url.starts_with(&self.base) {
+            self.seen.lock().unwrap().push((request, response));
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_downloads_go_through_interceptors() {
+    let server = Server::start(Duration::ZERO);
+    let dir = tempfile::tempdir().unwrap();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let redirector = Redirector {
+        base: server.base.clone(),
+        seen: seen.clone(),
+    };
+    add_http_interceptor("redirector".to_string(), Box::new(redirector)).unwrap();
+
+    let downloader = Downloader::new(2, None).unwrap();
+    let destination = destination(&dir, "moved.bin");
+    let id = downloader
+        .enqueue(server.url("/moved"), destination.clone(), HashMap::new())
+        .unwrap();
+    wait_idle(&downloader);
+    assert!(remove_http_interceptor("redirector".to_string()));
+    assert_eq!(
+        downloader.download(id).unwrap().state,
+        DownloadState::Completed
+    );
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+
+    // The response to the probe for range support is reported without the
+    // body, which went to the file
+    let seen = seen.lock().unwrap();
+    let (request, response) = &seen[0];
+    assert_eq!(request.url, server.url("/file"));
+    assert_eq!(response.status, 206);
+    assert_eq!(response.headers["etag"], "\"v1\"");
+    assert!(response.body.is_empty());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_invalid_downloads_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+        Downloader::new(0, None),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    let downloader = Downloader::new(1, None).unwrap();
+    assert!(matches!(
+        downloader.enqueue(
+            "not a url".to_string(),
+            destination(&dir, "a"),
+            HashMap::new()
+        ),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        downloader.enqueue(
+            "http://127.0.0.1:9/file".to_string(),
+            destination(&dir, "a"),
+            HashMap::from([("bad header".to_string(), "x".to_string())])
+        ),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(!downloader.pause(42));
+    for invalid in [
+        ParallelChunks {
+            connections: 0,
+            ..ParallelChunks::default()
+        },
+        ParallelChunks {
+            connections: 17,
+            ..ParallelChunks::default()
+        },
+        ParallelChunks {
+            max_attempts_per_chunk: 0,
+            ..ParallelChunks::default()
+        },
+    ] {
+        assert!(matches!(
+            downloader.set_parallel_chunks(invalid),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert_eq!(downloader.parallel_chunks(), ParallelChunks::default());
+
+    let server = Server::start(Duration::from_millis(50));
+    let id = downloader
+        .enqueue(server.url("/file"), destination(&dir, "a"), HashMap::new())
+        .unwrap();
+    assert!(matches!(
+        downloader.enqueue(server.url("/file"), destination(&dir, "a"), HashMap::new()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(downloader.cancel(id));
+    wait_idle(&downloader);
+}
+
+/// Splits files of a quarter of `contents()` or more into four chunks
+#[cfg(feature = "http")]
+fn split_in_four(downloader: &Downloader) {
+    let parallel = ParallelChunks {
+        connections: 4,
+        min_file_size_bytes: FILE_SIZE as u64 / 4,
+        max_attempts_per_chunk: 3,
+    };
+    downloader.set_parallel_chunks(parallel).unwrap();
+    assert_eq!(downloader.parallel_chunks(), parallel);
+}
+
+/// Requests for a chunk of `contents()`, by index, that reached the server
+#[cfg(feature = "http")]
+fn chunk_requests(server: &Server) -> Vec<Vec<String>> {
+    let quarter = FILE_SIZE / 4;
+    let mut requests = vec![Vec::new(); 4];
+    for range in server.ranges.lock().unwrap().iter().flatten() {
+        let Some((start, end)) = range.strip_prefix("bytes=").unwrap().split_once('-') else {
+            continue;
+        };
+        if !end.is_empty() {
+            let start: usize = start.parse().unwrap();
+            requests[start / quarter].push(range.clone());
+        }
+    }
+    requests
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_large_files_download_in_chunks() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    split_in_four(&downloader);
+    let destination = destination(&dir, "model.gguf");
+
+    let id = downloader
+        .enqueue(server.url("/file"), destination.clone(), HashMap::new())
+        .unwrap();
+    wait_idle(&downloader);
+    let info = downloader.download(id).unwrap();
+    assert_eq!(info.state, DownloadState::Completed);
+    assert_eq!(info.downloaded_bytes, FILE_SIZE as u64);
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    assert!(!Path::new(&partial(&destination)).exists());
+    assert!(!Path::new(&format!("{}.1", partial(&destination))).exists());
+
+    let quarter = FILE_SIZE / 4;
+    assert_eq!(
+        server.ranges.lock().unwrap()[0].as_deref(),
+        Some("bytes=0-")
+    );
+    assert_eq!(
+        chunk_requests(&server),
+        (0..4)
+            .map(|index| vec![format!(
+                "bytes={}-{}",
+                index * quarter,
+                (index + 1) * quarter - 1
+            )])
+            .collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_chunks_retry_and_resume_on_their_own() {
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let state = destination(&dir, "downloads.json");
+
+    // Each chunk is cut off halfway once, and continues from there
+    let downloader = Downloader::new(1, None).unwrap();
+    split_in_four(&downloader);
+    let destination = destination(&dir, "flaky.gguf");
+    downloader
+        .enqueue(server.url("/flaky"), destination.clone(), HashMap::new())
+        .unwrap();
+    wait_idle(&downloader);
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    let (quarter, eighth) = (FILE_SIZE / 4, FILE_SIZE / 8);
+    for (index, requests) in chunk_requests(&server).iter().enumerate() {
+        let (start, end) = (index * quarter, (index + 1) * quarter - 1);
+        assert_eq!(
+            requests,
+            &[
+                format!("bytes={}-{}", start, end),
+                format!("bytes={}-{}", start + eighth, end)
+            ]
+        );
+    }
+
+    // Paused chunks are picked up again after a restart
+    let destination = destination.replace("flaky", "model");
+    let second = format!("{}.1", partial(&destination));
+    let id = {
+        let downloader = Downloader::new(1, Some(state.clone())).unwrap();
+        split_in_four(&downloader);
+        let id = downloader
+            .enqueue(server.url("/file"), destination.clone(), HashMap::new())
+            .unwrap();
+        wait_until(|| Path::new(&second).metadata().is_ok_and(|m| m.len() > 0));
+        assert!(downloader.pause(id));
+        wait_idle(&downloader);
+        id
+    };
+    let downloader = Downloader::new(1, Some(state)).unwrap();
+    let downloaded = downloader.download(id).unwrap().downloaded_bytes;
+    assert!(downloaded > 0 && downloaded < FILE_SIZE as u64);
+    assert!(downloader.resume(id));
+    wait_idle(&downloader);
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    assert!(!Path::new(&second).exists());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_bandwidth_caps() {
+    let server = Server::start(Duration::ZERO);
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    assert_eq!(downloader.bandwidth_limits(), BandwidthLimits::default());
+
+    // Half a second for the file, shared by every download
+    let limits = BandwidthLimits {
+        max_bytes_per_second: Some(FILE_SIZE as u64 * 2),
+        cellular_max_bytes_per_second: None,
+    };
+    downloader.set_bandwidth_limits(limits).unwrap();
+    assert_eq!(downloader.bandwidth_limits(), limits);
+    assert!(timed_download(&downloader, &server, &dir, "a.bin") >= Duration::from_millis(400));
+
+    // A quarter of a second for the file, for this download only
+    let downloader = Downloader::new(1, None).unwrap();
+    let destination = destination(&dir, "b.bin");
+    let started = Instant::now();
+    let id = downloader
+        .enqueue(server.url("/file"), destination.clone(), HashMap::new())
+        .unwrap();
+    assert!(downloader
+        .set_download_bandwidth(id, Some(FILE_SIZE as u64 * 4))
+        .unwrap());
+    wait_idle(&downloader);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    let info = downloader.download(id).unwrap();
+    assert_eq!(info.max_bytes_per_second, Some(FILE_SIZE as u64 * 4));
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+
+    // Finished downloads and zero caps are rejected
+    assert!(!downloader.set_download_bandwidth(id, None).unwrap());
+    assert!(!downloader.set_download_bandwidth(id + 1, None).unwrap());
+    for invalid in [
+        BandwidthLimits {
+            max_bytes_per_second: Some(0),
+            cellular_max_bytes_per_second: None,
+        },
+        BandwidthLimits {
+            max_bytes_per_second: None,
+            cellular_max_bytes_per_second: Some(0),
+        },
+    ] {
+        assert!(matches!(
+            downloader.set_bandwidth_limits(invalid),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert_eq!(downloader.bandwidth_limits(), BandwidthLimits::default());
+    assert!(matches!(
+        downloader.set_download_bandwidth(id, Some(0)),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+// Only tests holding `NETWORK_STATUS` change the network status, and only to
+// statuses the `Any` policy of the other tests allows
+#[cfg(feature = "http")]
+#[test]
+fn test_cellular_cap_applies_on_cellular_only() {
+    let _network = NETWORK_STATUS.lock().unwrap_or_else(|e| e.into_inner());
+    let server = Server::start(Duration::ZERO);
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    downloader
+        .set_bandwidth_limits(BandwidthLimits {
+            max_bytes_per_second: Some(1 << 30),
+            cellular_max_bytes_per_second: Some(FILE_SIZE as u64 * 2),
+        })
+        .unwrap();
+
+    set_network_status(NetworkStatus::Cellular);
+    assert!(timed_download(&downloader, &server, &dir, "a.bin") >= Duration::from_millis(400));
+    set_network_status(NetworkStatus::Wifi);
+    // The downloader hears about the new status on its runtime
+    thread::sleep(Duration::from_millis(50));
+    assert!(timed_download(&downloader, &server, &dir, "b.bin") < Duration::from_millis(400));
+    set_network_status(NetworkStatus::Unknown);
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_unmetered_downloads_wait_for_wifi() {
+    let _network = NETWORK_STATUS.lock().unwrap_or_else(|e| e.into_inner());
+    let server = Server::start(Duration::from_millis(20));
+    let dir = tempfile::tempdir().unwrap();
+    let downloader = Downloader::new(1, None).unwrap();
+    let recorded = listen(&downloader);
+    assert_eq!(downloader.network_policy(), NetworkPolicy::Any);
+    downloader.set_network_policy(NetworkPolicy::Unmetered);
+    let destination = destination(&dir, "model.gguf");
+
+    set_network_status(NetworkStatus::Cellular);
+    let id = downloader
+        .enqueue(server.url("/file"), destination.clone(), HashMap::new())
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(
+        downloader.download(id).unwrap().state,
+        DownloadState::Queued
+    );
+    assert!(server.ranges.lock().unwrap().is_empty());
+
+    set_network_status(NetworkStatus::Wifi);
+    wait_until(|| {
+        Path::new(&partial(&destination))
+            .metadata()
+            .is_ok_and(|m| m.len() > 0)
+    });
+    set_network_status(NetworkStatus::Cellular);
+    wait_until(|| downloader.download(id).unwrap().state == DownloadState::Queued);
+    let kept = std::fs::metadata(partial(&destination)).unwrap().len();
+    assert!(kept > 0 && kept < FILE_SIZE as u64);
+
+    set_network_status(NetworkStatus::Wifi);
+    wait_idle(&downloader);
+    assert_eq!(std::fs::read(&destination).unwrap(), contents());
+    assert_eq!(
+        server.ranges.lock().unwrap().last().unwrap().as_deref(),
+        Some(format!("bytes={}-", kept).as_str())
+    );
+    assert_eq!(
+        recorded.lock().unwrap().states,
+        [
+            DownloadState::Queued,
+            DownloadState::Running,
+            DownloadState::Queued,
+            DownloadState::Running,
+            DownloadState::Completed
+        ]
+    );
+}
@@ -0,0 +1,106 @@
+use rust_multiplatform_template_lib::{
+    EncryptedKvStore, KeyProvider, KvStore, KvValue, TemplateError,
+};
+use std::path::Path;
+
+struct FixedKey(Option<Vec<u8>>);
+
+impl KeyProvider for FixedKey {
+    fn get_key(&self, key_id: String) -> Option<Vec<u8>> {
+        assert_eq!(key_id, "prefs");
+        self.0.clone()
+    }
+}
+
+fn open(path: &Path, key: u8) -> EncryptedKvStore {
+    EncryptedKvStore::open(
+        path.to_string_lossy().into_owned(),
+        "prefs".to_string(),
+        Box::new(FixedKey(Some(vec![key; 32]))),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_values_round_trip_and_are_not_stored_in_plaintext() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.json");
+    let store = open(&path, 7);
+    store
+        .set_string("token".to_string(), "hunter2-secret".to_string())
+        .unwrap();
+    store.set_int("pin".to_string(), 1234).unwrap();
+    store.set_bool("enrolled".to_string(), true).unwrap();
+    store.set_bytes("salt".to_string(), vec![9, 8, 7]).unwrap();
+    drop(store);
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("hunter2"));
+    assert!(!raw.contains("1234"));
+
+    let store = open(&path, 7);
+    assert_eq!(store.keys(), vec!["enrolled", "pin", "salt", "token"]);
+    assert_eq!(
+        store.get_string("token".to_string()).unwrap(),
+        Some("hunter2-secret".to_string())
+    );
+    assert_eq!(store.get_int("pin".to_string()).unwrap(), Some(1234));
+    assert_eq!(store.get_bool("enrolled".to_string()).unwrap(), Some(true));
+    assert_eq!(
+        store.get_bytes("salt".to_string()).unwrap(),
+        Some(vec![9, 8, 7])
+    );
+    assert_eq!(store.get("missing".to_string()).unwrap(), None);
+    assert!(matches!(
+        store.get_int("token".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(store.delete("pin".to_string()).unwrap());
+    assert_eq!(store.get_int("pin".to_string()).unwrap(), None);
+}
+
+#[test]
+fn test_wrong_key_or_moved_value_fails_to_decrypt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.json");
+    open(&path, 1)
+        .set_string("a".to_string(), "secret".to_string())
+        .unwrap();
+
+    assert!(matches!(
+        open(&path, 2).get("a".to_string()),
+        Err(TemplateError::EncryptionError { .. })
+    ));
+
+    // Copying a sealed value to another key is detected
+    let plain = KvStore::open(path.to_string_lossy().into_owned()).unwrap();
+    let sealed = plain.get("a".to_string()).unwrap();
+    plain.set("b".to_string(), sealed).unwrap();
+    plain
+        .set("c".to_string(), KvValue::String { value: "x".into() })
+        .unwrap();
+    drop(plain);
+    let store = open(&path, 1);
+    for key in ["b", "c"] {
+        assert!(matches!(
+            store.get_string(key.to_string()),
+            Err(TemplateError::EncryptionError { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_open_requires_a_valid_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("secrets.json")
+        .to_string_lossy()
+        .into_owned();
+    for key in [None, Some(vec![0u8; 16])] {
+        assert!(matches!(
+            EncryptedKvStore::open(path.clone(), "prefs".to_string(), Box::new(FixedKey(key))),
+            Err(TemplateError::EncryptionError { .. })
+        ));
+    }
+}
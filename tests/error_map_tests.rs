@@ -0,0 +1,48 @@
+use rust_multiplatform_template_lib::{
+    host_error_code, set_error_mappings, ErrorKind, HostErrorCode, TemplateError,
+};
+use std::collections::HashMap;
+
+fn host(domain: &str, code: i64) -> HostErrorCode {
+    HostErrorCode {
+        domain: domain.to_string(),
+        code,
+    }
+}
+
+// The mapping table is process-wide, so these checks run in one test
+#[test]
+fn test_error_mappings_lifecycle() {
+    let timeout = TemplateError::timeout("op", 1);
+    assert_eq!(host_error_code(timeout.clone()), None);
+
+    let mappings = HashMap::from([
+        ("TIMEOUT".to_string(), host("com.example.net", 408)),
+        ("IO_ERROR".to_string(), host("com.example.disk", 1)),
+    ]);
+    set_error_mappings(mappings).unwrap();
+    assert_eq!(timeout.host_code(), Some(host("com.example.net", 408)));
+    assert_eq!(TemplateError::operation_cancelled("op").host_code(), None);
+
+    // Unknown codes are rejected and the previous table is kept
+    let bad = HashMap::from([("NOPE".to_string(), host("x", 1))]);
+    assert!(matches!(
+        set_error_mappings(bad),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert_eq!(
+        host_error_code(timeout.clone()),
+        Some(host("com.example.net", 408))
+    );
+
+    set_error_mappings(HashMap::new()).unwrap();
+    assert_eq!(timeout.host_code(), None);
+}
+
+#[test]
+fn test_error_kind_codes_round_trip() {
+    for kind in ErrorKind::ALL {
+        assert_eq!(ErrorKind::from_code(&kind.code()), Some(kind));
+    }
+    assert_eq!(ErrorKind::from_code("timeout"), None);
+}
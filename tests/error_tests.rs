@@ -0,0 +1,284 @@
+use rust_multiplatform_template_lib::{
+    classify_error, error_severity, error_to_json, localize_error, ErrorClassification, ErrorKind,
+    PreviewMode, Severity, TemplateError,
+};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+struct Layer {
+    message: &'static str,
+    source: Option<Box<Layer>>,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+impl std::error::Error for Layer {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|s| s as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[test]
+fn test_io_error_keeps_source_chain() {
+    let inner = Layer {
+        message: "connection reset",
+        source: None,
+    };
+    let middle = Layer {
+        message: "failed to read block",
+        source: Some(Box::new(inner)),
+    };
+    let io = std::io::Error::other(Layer {
+        message: "failed to load model",
+        source: Some(Box::new(middle)),
+    });
+
+    let error = TemplateError::io_error(Path::new("/models/a.gguf"), &io);
+    assert_eq!(
+        error.details(),
+        Some("failed to read block: connection reset")
+    );
+    assert_eq!(
+        error.to_string(),
+        "I/O error at /models/a.gguf: failed to load model \
+         (caused by: failed to read block: connection reset)"
+    );
+}
+
+#[test]
+fn test_io_error_without_source() {
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+    let error = TemplateError::io_error(Path::new("/x"), &io);
+    assert_eq!(error.details(), None);
+    assert_eq!(error.to_string(), "I/O error at /x: missing");
+    assert_eq!(TemplateError::timeout("op", 1).details(), None);
+}
+
+#[test]
+fn test_message_key_and_params() {
+    let error = TemplateError::timeout("hash_file", 250);
+    assert_eq!(error.message_key(), "template.error.timeout");
+    let params = error.message_params();
+    assert_eq!(params["operation"], "hash_file");
+    assert_eq!(params["timeout_ms"], "250");
+    assert_eq!(
+        error.to_string(),
+        "Operation timed out: hash_file after 250 ms"
+    );
+
+    let error = TemplateError::network_error("https://example.com", None, "refused".into());
+    let params = error.message_params();
+    assert_eq!(params["url"], "https://example.com");
+    assert!(!params.contains_key("status_code"));
+}
+
+#[test]
+fn test_localize_error() {
+    let last = TemplateError::timeout("fetch", 10);
+    let error = TemplateError::retries_exhausted("fetch", 3, &last);
+    let message = localize_error(error.clone());
+    assert_eq!(message.key, error.message_key());
+    assert_eq!(message.key, ErrorKind::RetriesExhausted.message_key());
+    assert_eq!(message.params["attempts"], "3");
+    assert_eq!(message.params["last_error_key"], "template.error.timeout");
+}
+
+#[test]
+fn test_error_classification() {
+    let timeout = TemplateError::timeout("op", 1);
+    assert_eq!(
+        classify_error(timeout.clone()),
+        ErrorClassification {
+            retryable: true,
+            transient: true,
+            user_input: false,
+            internal: false,
+        }
+    );
+
+    let invalid = TemplateError::invalid_input("bad".into(), None);
+    assert!(invalid.is_user_error());
+    assert!(!invalid.is_retryable());
+
+    let exhausted = TemplateError::retries_exhausted("op", 3, &timeout);
+    assert!(!exhausted.is_retryable());
+    assert!(exhausted.is_transient());
+
+    assert!(TemplateError::AlreadyInitialized.is_internal());
+    assert!(
+        !TemplateError::operation_cancelled("op")
+            .classification()
+            .retryable
+    );
+}
+
+#[test]
+fn test_network_error_classification_uses_status() {
+    let error = |status| TemplateError::network_error("https://example.com", status, "x".into());
+    assert!(error(None).is_retryable());
+    assert!(error(Some(503)).is_retryable());
+    assert!(error(Some(429)).is_retryable());
+    assert!(error(Some(408)).is_retryable());
+    assert!(!error(Some(404)).is_retryable());
+    assert!(!error(Some(404)).is_transient());
+}
+
+#[test]
+fn test_database_error_retryable_only_when_busy() {
+    let error = |code: Option<&str>| TemplateError::DatabaseError {
+        code: code.map(str::to_string),
+        error_message: "x".into(),
+    };
+    assert!(error(Some("DatabaseBusy")).is_retryable());
+    assert!(error(Some("DatabaseLocked")).is_transient());
+    assert!(!error(Some("ConstraintViolation")).is_retryable());
+    assert!(!error(None).is_retryable());
+    assert_eq!(error(None).kind().code(), "DATABASE_ERROR");
+}
+
+#[test]
+fn test_internal_error() {
+    let error = TemplateError::internal(
+        "echo",
+        "index out of bounds",
+        Some("src/template.rs:10:5".to_string()),
+    );
+    assert_eq!(
+        error.to_string(),
+        "Internal error: panic in echo: index out of bounds (at src/template.rs:10:5)"
+    );
+    assert_eq!(error.kind(), ErrorKind::Internal);
+    assert!(error.is_internal());
+    assert!(!error.is_retryable());
+    assert_eq!(error.message_params()["location"], "src/template.rs:10:5");
+
+    let error = TemplateError::internal("echo", "boom", None);
+    assert_eq!(error.to_string(), "Internal error: panic in echo: boom");
+}
+
+#[test]
+fn test_preview_cuts_at_char_boundary() {
+    // Byte 5 falls inside the second "é"; slicing by bytes would panic
+    let input = "aéébcdef";
+    let error = TemplateError::invalid_input_with_preview(
+        "bad".into(),
+        Some(input),
+        3,
+        PreviewMode::Truncate,
+    );
+    match error {
+        TemplateError::InvalidInput { input_preview, .. } => {
+            assert_eq!(input_preview.as_deref(), Some("aéé..."));
+        }
+        _ => panic!("Expected InvalidInput"),
+    }
+
+    let error = TemplateError::invalid_input_with_preview(
+        "bad".into(),
+        Some("🎉🎉"),
+        5,
+        PreviewMode::Truncate,
+    );
+    assert!(matches!(
+        error,
+        TemplateError::InvalidInput { input_preview: Some(p), .. } if p == "🎉🎉"
+    ));
+}
+
+#[test]
+fn test_preview_redaction_and_omission() {
+    let redacted = |input: &str| match TemplateError::invalid_input_with_preview(
+        "bad".into(),
+        Some(input),
+        50,
+        PreviewMode::Redact,
+    ) {
+        TemplateError::InvalidInput { input_preview, .. } => input_preview.unwrap(),
+        _ => panic!("Expected InvalidInput"),
+    };
+    assert_eq!(redacted("secret"), redacted("secret"));
+    assert_ne!(redacted("secret"), redacted("secret2"));
+    assert!(!redacted("secret").contains("secret"));
+
+    let error = TemplateError::invalid_input_with_preview(
+        "bad".into(),
+        Some("secret"),
+        0,
+        PreviewMode::Truncate,
+    );
+    assert!(matches!(
+        error,
+        TemplateError::InvalidInput {
+            input_preview: None,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_debug_info_accessor() {
+    let error = TemplateError::ModelLoadError {
+        path: "/m.gguf".into(),
+        error_message: "truncated".into(),
+        debug_info: Some("breadcrumbs".into()),
+    };
+    assert_eq!(error.debug_info(), Some("breadcrumbs"));
+    assert_eq!(error.to_string(), "Failed to load model /m.gguf: truncated");
+    assert_eq!(TemplateError::timeout("op", 1).debug_info(), None);
+}
+
+#[test]
+fn test_error_severity() {
+    assert_eq!(
+        TemplateError::operation_cancelled("op").severity(),
+        Severity::Info
+    );
+    assert_eq!(
+        error_severity(TemplateError::invalid_input("bad".into(), None)),
+        Severity::Warning
+    );
+    assert_eq!(
+        TemplateError::timeout("op", 1).severity(),
+        Severity::Recoverable
+    );
+    assert_eq!(
+        TemplateError::internal("op", "boom", None).severity(),
+        Severity::Fatal
+    );
+    assert!(Severity::Fatal > Severity::Recoverable);
+    assert_eq!(ErrorKind::Internal.severity(), Severity::Fatal);
+}
+
+#[test]
+fn test_error_to_json() {
+    let json: serde_json::Value =
+        serde_json::from_str(&TemplateError::timeout("echo", 500).to_json()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "code": "TIMEOUT",
+            "variant": "Timeout",
+            "message": "Operation timed out: echo after 500 ms",
+            "fields": {"operation": "echo", "timeout_ms": 500},
+        })
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&error_to_json(TemplateError::AlreadyInitialized)).unwrap();
+    assert_eq!(json["variant"], "AlreadyInitialized");
+    assert_eq!(json["fields"], serde_json::json!({}));
+
+    let last = TemplateError::network_error("https://x", Some(503), "down".into());
+    let json: serde_json::Value =
+        serde_json::from_str(&TemplateError::retries_exhausted("fetch", 3, &last).to_json())
+            .unwrap();
+    assert_eq!(json["fields"]["last_error_kind"], "network_error");
+    assert_eq!(json["fields"]["attempts"], 3);
+}
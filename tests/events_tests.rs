@@ -0,0 +1,87 @@
+use rust_multiplatform_template_lib::{
+    discover_models, event_bus, spawn_echo, EventListener, LibraryEvent, TaskStatus,
+};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+struct ChannelListener(Mutex<Sender<LibraryEvent>>);
+
+impl EventListener for ChannelListener {
+    fn on_event(&self, event: LibraryEvent) {
+        let _ = self.0.lock().unwrap().send(event);
+    }
+}
+
+fn subscribe() -> (u64, Receiver<LibraryEvent>) {
+    let (sender, receiver) = mpsc::channel();
+    let id = event_bus().subscribe(Box::new(ChannelListener(Mutex::new(sender))));
+    (id, receiver)
+}
+
+/// Waits for the first event matching `predicate`; other tests publish too
+fn wait_for(
+    receiver: &Receiver<LibraryEvent>,
+    predicate: impl Fn(&LibraryEvent) -> bool,
+) -> LibraryEvent {
+    loop {
+        let event = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("event not delivered");
+        if predicate(&event) {
+            return event;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_models_discovered_event() {
+    let (id, receiver) = subscribe();
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().to_string_lossy().into_owned();
+    discover_models(directory.clone(), None).await.unwrap();
+
+    let event = wait_for(
+        &receiver,
+        |e| matches!(e, LibraryEvent::ModelsDiscovered { directory: d, .. } if *d == directory),
+    );
+    assert_eq!(
+        event,
+        LibraryEvent::ModelsDiscovered {
+            directory,
+            count: 0
+        }
+    );
+    assert!(event_bus().unsubscribe(id));
+}
+
+#[tokio::test]
+async fn test_task_events() {
+    let (id, receiver) = subscribe();
+    let ok = spawn_echo("Hello".to_string(), None, None);
+    let failed = spawn_echo("a\0b".to_string(), None, None);
+    ok.await_result().await.unwrap();
+    failed.await_result().await.unwrap_err();
+
+    // The two tasks may finish in either order
+    let mut pending = vec![
+        LibraryEvent::TaskFinished {
+            id: ok.id(),
+            status: TaskStatus::Completed,
+        },
+        LibraryEvent::TaskFinished {
+            id: failed.id(),
+            status: TaskStatus::Failed,
+        },
+    ];
+    while !pending.is_empty() {
+        let event = wait_for(&receiver, |e| pending.contains(e));
+        pending.retain(|e| *e != event);
+    }
+    assert!(event_bus().unsubscribe(id));
+}
+
+#[test]
+fn test_unsubscribe_unknown_id() {
+    assert!(!event_bus().unsubscribe(u64::MAX));
+}
@@ -0,0 +1,52 @@
+use rust_multiplatform_template_lib::{
+    collect_log_files, disable_file_logging, discover_models, enable_file_logging, set_log_level,
+    LogLevel, TemplateError,
+};
+use std::fs::{self, File};
+
+// File logging and the log level are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_file_logging_rotates_and_collects() {
+    assert!(matches!(
+        enable_file_logging("unused".to_string(), 0, 3),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let logs = tempfile::tempdir().unwrap();
+    let models = tempfile::tempdir().unwrap();
+    let models_dir = models.path().to_string_lossy().into_owned();
+    let log_dir = logs.path().join("nested");
+    enable_file_logging(log_dir.to_string_lossy().into_owned(), 256, 3).unwrap();
+    set_log_level(LogLevel::Debug);
+
+    for _ in 0..20 {
+        discover_models(models_dir.clone(), None).await.unwrap();
+    }
+
+    let current = fs::read_to_string(log_dir.join("template.log")).unwrap();
+    assert!(current.contains("Debug rust_multiplatform_template_lib::models: Found 0 model files"));
+    assert!(current.len() <= 256);
+    assert!(log_dir.join("template.1.log").is_file());
+    assert!(log_dir.join("template.2.log").is_file());
+    assert!(!log_dir.join("template.3.log").exists());
+
+    let archive_path = logs.path().join("logs.zip");
+    let count = collect_log_files(archive_path.to_string_lossy().into_owned())
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+    let mut archive = zip::ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+    let names: Vec<_> = archive.file_names().map(str::to_string).collect();
+    assert_eq!(names, ["template.2.log", "template.1.log", "template.log"]);
+    let mut newest = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("template.log").unwrap(), &mut newest)
+        .unwrap();
+    assert!(newest.contains("Found 0 model files"));
+
+    disable_file_logging();
+    set_log_level(LogLevel::Warn);
+    assert!(matches!(
+        collect_log_files(archive_path.to_string_lossy().into_owned()).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
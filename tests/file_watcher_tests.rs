@@ -0,0 +1,101 @@
+use rust_multiplatform_template_lib::{
+    FileChange, FileChangeKind, FileWatchListener, FileWatcher, TemplateError,
+};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct RecordingListener(Arc<Mutex<Vec<FileChange>>>);
+
+impl FileWatchListener for RecordingListener {
+    fn on_file_changed(&self, change: FileChange) {
+        self.0.lock().unwrap().push(change);
+    }
+}
+
+/// Waits until a change of `kind` for `path` has been recorded
+fn wait_for(changes: &Mutex<Vec<FileChange>>, kind: FileChangeKind, path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if changes
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|change| change.kind == kind && change.path == path)
+        {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+fn watch(dir: &tempfile::TempDir, recursive: bool) -> (FileWatcher, Arc<Mutex<Vec<FileChange>>>) {
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let watcher = FileWatcher::new(
+        dir.path().to_string_lossy().into_owned(),
+        recursive,
+        Box::new(RecordingListener(changes.clone())),
+    )
+    .unwrap();
+    (watcher, changes)
+}
+
+#[test]
+fn test_reports_created_modified_and_deleted_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let (watcher, changes) = watch(&dir, false);
+    assert!(watcher.is_watching());
+    let file = Path::new(&watcher.directory()).join("config.toml");
+
+    std::fs::write(&file, "a = 1").unwrap();
+    assert!(wait_for(&changes, FileChangeKind::Created, &file));
+
+    std::fs::write(&file, "a = 2").unwrap();
+    assert!(wait_for(&changes, FileChangeKind::Modified, &file));
+
+    std::fs::remove_file(&file).unwrap();
+    assert!(wait_for(&changes, FileChangeKind::Deleted, &file));
+}
+
+#[test]
+fn test_rename_reported_as_delete_and_create() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("models")).unwrap();
+    let (watcher, changes) = watch(&dir, true);
+    let directory = Path::new(&watcher.directory()).to_path_buf();
+    let (from, to) = (
+        directory.join("models/download.partial"),
+        directory.join("models/model.gguf"),
+    );
+    std::fs::write(&from, [0u8; 16]).unwrap();
+    assert!(wait_for(&changes, FileChangeKind::Created, &from));
+
+    std::fs::rename(&from, &to).unwrap();
+    assert!(wait_for(&changes, FileChangeKind::Deleted, &from));
+    assert!(wait_for(&changes, FileChangeKind::Created, &to));
+}
+
+#[test]
+fn test_stop_ends_notifications() {
+    let dir = tempfile::tempdir().unwrap();
+    let (watcher, changes) = watch(&dir, false);
+    watcher.stop();
+    assert!(!watcher.is_watching());
+
+    std::fs::write(dir.path().join("late.txt"), "x").unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(changes.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_missing_directory_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = FileWatcher::new(
+        dir.path().join("missing").to_string_lossy().into_owned(),
+        false,
+        Box::new(RecordingListener(Arc::new(Mutex::new(Vec::new())))),
+    );
+    assert!(matches!(result, Err(TemplateError::IoError { .. })));
+}
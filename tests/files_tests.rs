@@ -0,0 +1,166 @@
+use rust_multiplatform_template_lib::{write_file_atomic, FileSandbox, TemplateError};
+
+fn sandbox(dir: &tempfile::TempDir, max_file_size: u64) -> FileSandbox {
+    FileSandbox::new(
+        dir.path().join("sandbox").to_string_lossy().into_owned(),
+        max_file_size,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_write_read_append_list_delete() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = sandbox(&dir, 1024);
+
+    files
+        .write("notes/a.txt".to_string(), b"hello".to_vec())
+        .await
+        .unwrap();
+    files
+        .append("notes/a.txt".to_string(), b" world".to_vec())
+        .await
+        .unwrap();
+    files
+        .append("notes/b.txt".to_string(), b"new".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(
+        files.read("notes/a.txt".to_string()).await.unwrap(),
+        b"hello world"
+    );
+
+    let metadata = files.metadata("notes/a.txt".to_string()).await.unwrap();
+    assert_eq!(metadata.size, 11);
+    assert!(!metadata.is_directory);
+    assert!(metadata.modified_ms.is_some());
+
+    let root = files.list(String::new()).await.unwrap();
+    assert_eq!(root.len(), 1);
+    assert_eq!(root[0].path, "notes");
+    assert!(root[0].is_directory);
+    let notes: Vec<_> = files
+        .list("notes".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    assert_eq!(
+        notes,
+        [
+            std::path::Path::new("notes")
+                .join("a.txt")
+                .to_string_lossy(),
+            std::path::Path::new("notes")
+                .join("b.txt")
+                .to_string_lossy(),
+        ]
+    );
+
+    assert!(files.exists("notes/./a.txt".to_string()).await.unwrap());
+    assert!(files.delete("notes/a.txt".to_string()).await.unwrap());
+    assert!(!files.delete("notes/a.txt".to_string()).await.unwrap());
+    assert!(!files.exists("notes/a.txt".to_string()).await.unwrap());
+    assert!(matches!(
+        files.delete("notes".to_string()).await,
+        Err(TemplateError::IoError { .. })
+    ));
+    assert!(matches!(
+        files.read("missing".to_string()).await,
+        Err(TemplateError::IoError { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_paths_outside_the_sandbox_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = sandbox(&dir, 1024);
+    std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+    let outside = dir.path().join("secret.txt").to_string_lossy().into_owned();
+    for path in ["../secret.txt", "a/../../secret.txt", outside.as_str()] {
+        assert!(
+            matches!(
+                files.read(path.to_string()).await,
+                Err(TemplateError::InvalidInput { .. })
+            ),
+            "{} was not rejected",
+            path
+        );
+    }
+    assert!(matches!(
+        files.delete(String::new()).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("sandbox/link")).unwrap();
+        assert!(matches!(
+            files.read("link/secret.txt".to_string()).await,
+            Err(TemplateError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            files.write("link/new.txt".to_string(), b"x".to_vec()).await,
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_size_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = sandbox(&dir, 8);
+
+    assert!(matches!(
+        files.write("big".to_string(), vec![0; 9]).await,
+        Err(TemplateError::InputTooLarge {
+            size: 9,
+            max: 8,
+            ..
+        })
+    ));
+    files.write("log".to_string(), vec![0; 6]).await.unwrap();
+    assert!(matches!(
+        files.append("log".to_string(), vec![0; 3]).await,
+        Err(TemplateError::InputTooLarge {
+            size: 9,
+            max: 8,
+            ..
+        })
+    ));
+    std::fs::write(dir.path().join("sandbox/large"), [0; 20]).unwrap();
+    assert!(matches!(
+        files.read("large".to_string()).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        FileSandbox::new(dir.path().to_string_lossy().into_owned(), 0),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_write_file_atomic_replaces_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("settings.json");
+    let target = path.to_string_lossy().into_owned();
+
+    write_file_atomic(target.clone(), b"old".to_vec())
+        .await
+        .unwrap();
+    write_file_atomic(target.clone(), b"new contents".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"new contents");
+    // No temporary files are left behind
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let missing_dir = dir.path().join("missing/settings.json");
+    assert!(matches!(
+        write_file_atomic(missing_dir.to_string_lossy().into_owned(), b"x".to_vec()).await,
+        Err(TemplateError::IoError { .. })
+    ));
+    assert_eq!(std::fs::read(&path).unwrap(), b"new contents");
+}
@@ -0,0 +1,224 @@
+use rust_multiplatform_template_lib::{
+    get_grpc_services, register_grpc_service, unregister_grpc_service, GrpcServiceConfig,
+    TemplateError,
+};
+
+#[cfg(feature = "grpc")]
+use rust_multiplatform_template_lib::{
+    grpc_call, grpc_channel, grpc_status_error, CancellationToken,
+};
+#[cfg(feature = "grpc")]
+use std::convert::Infallible;
+#[cfg(feature = "grpc")]
+use std::sync::Arc;
+#[cfg(feature = "grpc")]
+use std::task::{Context, Poll};
+#[cfg(feature = "grpc")]
+use tonic::codec::ProstCodec;
+#[cfg(feature = "grpc")]
+use tonic::codegen::{http, BoxFuture, Service};
+#[cfg(feature = "grpc")]
+use tonic::{Code, Request, Response, Status};
+
+fn config(endpoint: &str) -> GrpcServiceConfig {
+    GrpcServiceConfig {
+        endpoint: endpoint.to_string(),
+        timeout_ms: Some(5_000),
+        connect_timeout_ms: Some(5_000),
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+#[test]
+fn test_grpc_requires_feature() {
+    let result = register_grpc_service("echo.Echo".to_string(), config("http://127.0.0.1:1"));
+    assert!(matches!(result, Err(TemplateError::InvalidInput { .. })));
+    assert!(get_grpc_services().is_empty());
+    assert!(!unregister_grpc_service("echo.Echo".to_string()));
+}
+
+/// `echo.Echo`, written out the way `tonic-build` generates servers: `Say`
+/// greets its string argument, or fails with `NotFound` when it is empty
+/// and never answers when it is `"wait"`
+#[cfg(feature = "grpc")]
+#[derive(Clone)]
+struct Echo;
+
+#[cfg(feature = "grpc")]
+impl tonic::server::NamedService for Echo {
+    const NAME: &'static str = "echo.Echo";
+}
+
+#[cfg(feature = "grpc")]
+struct Say;
+
+#[cfg(feature = "grpc")]
+impl tonic::server::UnaryService<String> for Say {
+    type Response = String;
+    type Future = BoxFuture<Response<String>, Status>;
+
+    fn call(&mut self, request: Request<String>) -> Self::Future {
+        Box::pin(async move {
+            match request.into_inner().as_str() {
+                "" => Err(Status::not_found("nobody to greet")),
+                "wait" => std::future::pending().await,
+                name => Ok(Response::new(format!("Hello, {}", name))),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl<B> Service<http::Request<B>> for Echo
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<tonic::codegen::StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        Box::pin(async move {
+            let mut grpc = tonic::server::Grpc::new(ProstCodec::<String, String>::default());
+            Ok(grpc.unary(Say, request).await)
+        })
+    }
+}
+
+/// Serves `Echo` on a free local port; returns its cleartext endpoint
+#[cfg(feature = "grpc")]
+async fn serve() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let incoming = futures_util::stream::unfold(listener, |listener| async move {
+        let accepted = listener.accept().await.map(|(stream, _)| stream);
+        Some((accepted, listener))
+    });
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(Echo)
+            .serve_with_incoming(incoming),
+    );
+    endpoint
+}
+
+/// Calls `echo.Echo/Say` the way a generated client does
+#[cfg(feature = "grpc")]
+async fn say(name: &str, token: Option<Arc<CancellationToken>>) -> Result<String, TemplateError> {
+    let mut client = tonic::client::Grpc::new(grpc_channel("echo.Echo")?);
+    let request = Request::new(name.to_string());
+    grpc_call("echo.Echo", token, async move {
+        client
+            .ready()
+            .await
+            .map_err(|e| Status::unknown(format!("Service was not ready: {}", e)))?;
+        let path = http::uri::PathAndQuery::from_static("/echo.Echo/Say");
+        client
+            .unary(request, path, ProstCodec::<String, String>::default())
+            .await
+    })
+    .await
+}
+
+// The registry is process-wide, so these checks run in one test
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn test_registered_services_are_called() {
+    assert!(matches!(
+        grpc_channel("echo.Echo"),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    register_grpc_service("echo.Echo".to_string(), config(&serve().await)).unwrap();
+    assert_eq!(get_grpc_services(), ["echo.Echo"]);
+    assert_eq!(say("grpc", None).await.unwrap(), "Hello, grpc");
+
+    match say("", None).await {
+        Err(TemplateError::NetworkError {
+            url,
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert!(url.ends_with("/echo.Echo"));
+            assert_eq!(status_code, Some(404));
+            assert_eq!(error_message, "gRPC NotFound: nobody to greet");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let token = Arc::new(CancellationToken::with_timeout(100));
+    assert!(matches!(
+        say("wait", Some(token)).await,
+        Err(TemplateError::Timeout { .. })
+    ));
+
+    // Nothing listens on the new endpoint, and the old channel is replaced
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+    register_grpc_service("echo.Echo".to_string(), config(&endpoint)).unwrap();
+    assert!(matches!(
+        say("grpc", None).await,
+        Err(TemplateError::NetworkError {
+            status_code: None,
+            ..
+        })
+    ));
+
+    assert!(unregister_grpc_service("echo.Echo".to_string()));
+    assert!(!unregister_grpc_service("echo.Echo".to_string()));
+    assert!(get_grpc_services().is_empty());
+}
+
+#[cfg(feature = "grpc")]
+#[test]
+fn test_invalid_services_are_rejected() {
+    for (name, endpoint) in [
+        ("", "http://127.0.0.1:50051"),
+        ("echo.Echo/Say", "http://127.0.0.1:50051"),
+        ("invalid.Endpoint", "127.0.0.1:50051"),
+        ("invalid.Endpoint", "ftp://127.0.0.1"),
+    ] {
+        assert!(matches!(
+            register_grpc_service(name.to_string(), config(endpoint)),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert!(!get_grpc_services().contains(&"invalid.Endpoint".to_string()));
+}
+
+#[cfg(feature = "grpc")]
+#[test]
+fn test_status_errors() {
+    let cases = [
+        (Code::InvalidArgument, 400),
+        (Code::Unauthenticated, 401),
+        (Code::PermissionDenied, 403),
+        (Code::ResourceExhausted, 429),
+        (Code::Internal, 500),
+        (Code::Unavailable, 503),
+        (Code::DeadlineExceeded, 504),
+    ];
+    for (code, status) in cases {
+        let error = grpc_status_error("unregistered.Service", &Status::new(code, "failed"));
+        assert!(matches!(
+            &error,
+            TemplateError::NetworkError { url, status_code, .. }
+                if url == "unregistered.Service" && *status_code == Some(status)
+        ));
+        assert_eq!(
+            error.is_retryable(),
+            matches!(status, 429 | 500 | 503 | 504)
+        );
+    }
+    assert!(matches!(
+        grpc_status_error("unregistered.Service", &Status::cancelled("stopped")),
+        TemplateError::OperationCancelled { .. }
+    ));
+}
@@ -0,0 +1,110 @@
+use rust_multiplatform_template_lib::{
+    clear_history, disable_history, echo, enable_history, query_history, CancellationToken,
+    ErrorKind, HistoryFilter, HistoryStatus, PageRequest, TemplateError,
+};
+use std::fs;
+use std::sync::Arc;
+
+fn page(offset: u32, limit: u32) -> PageRequest {
+    PageRequest { offset, limit }
+}
+
+// History is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_history_records_and_queries_echo_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history/echo.jsonl");
+    let path_string = path.to_string_lossy().into_owned();
+
+    // Nothing is recorded until history is enabled
+    echo("before".to_string(), None, None).await.unwrap();
+    let empty = query_history(HistoryFilter::default(), page(0, 10)).unwrap();
+    assert_eq!(empty.total_count, 0);
+
+    enable_history(path_string.clone(), 3).unwrap();
+    echo("one".to_string(), None, None).await.unwrap();
+    echo("\0".to_string(), None, None).await.unwrap_err();
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+    echo("two".to_string(), Some(token), None)
+        .await
+        .unwrap_err();
+    echo("three".to_string(), None, None).await.unwrap();
+
+    // Only the newest three are kept, newest first
+    let all = query_history(HistoryFilter::default(), page(0, 10)).unwrap();
+    assert_eq!(all.total_count, 3);
+    assert_eq!(all.next_offset, None);
+    let statuses: Vec<_> = all.entries.iter().map(|e| e.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            HistoryStatus::Succeeded,
+            HistoryStatus::Cancelled,
+            HistoryStatus::Failed
+        ]
+    );
+    assert_eq!(all.entries[0].operation, "echo");
+    assert_eq!(
+        all.entries[0].text_hash.as_deref(),
+        Some("8b5b9db0c13db24256c829aa364aa90c6d2eba318b9232a4ab9313b954d3555f")
+    );
+    assert_eq!(all.entries[2].error_kind, Some(ErrorKind::InvalidInput));
+    assert!(all.entries[0].id > all.entries[1].id);
+    // The text itself is never written
+    assert!(!fs::read_to_string(&path).unwrap().contains("three"));
+
+    let first_page = query_history(HistoryFilter::default(), page(0, 2)).unwrap();
+    assert_eq!(first_page.entries.len(), 2);
+    assert_eq!(first_page.next_offset, Some(2));
+    let failed = query_history(
+        HistoryFilter {
+            status: Some(HistoryStatus::Failed),
+            ..HistoryFilter::default()
+        },
+        page(0, 10),
+    )
+    .unwrap();
+    assert_eq!(failed.total_count, 1);
+    let future = query_history(
+        HistoryFilter {
+            since_ms: Some(u64::MAX),
+            ..HistoryFilter::default()
+        },
+        page(0, 10),
+    )
+    .unwrap();
+    assert_eq!(future.total_count, 0);
+    assert!(matches!(
+        query_history(HistoryFilter::default(), page(0, 0)),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    // History survives a restart, and a line cut short by a crash is dropped
+    disable_history();
+    echo("while disabled".to_string(), None, None)
+        .await
+        .unwrap();
+    let mut contents = fs::read(&path).unwrap();
+    contents.extend_from_slice(b"{\"id\": 99, \"oper");
+    fs::write(&path, contents).unwrap();
+    enable_history(path_string.clone(), 3).unwrap();
+    let reloaded = query_history(HistoryFilter::default(), page(0, 10)).unwrap();
+    assert_eq!(reloaded, all);
+    echo("four".to_string(), None, None).await.unwrap();
+    let latest = query_history(HistoryFilter::default(), page(0, 1)).unwrap();
+    assert_eq!(latest.entries[0].id, all.entries[0].id + 1);
+
+    clear_history().unwrap();
+    assert_eq!(
+        query_history(HistoryFilter::default(), page(0, 10))
+            .unwrap()
+            .total_count,
+        0
+    );
+    assert!(matches!(
+        enable_history(path_string, 0),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    disable_history();
+}
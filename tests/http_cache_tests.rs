@@ -0,0 +1,233 @@
+use rust_multiplatform_template_lib::{enable_http_cache, TemplateError};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    clear_http_cache, disable_http_cache, get_http_cache_entries, http_request,
+    remove_http_cache_entry, HttpMethod, HttpRequest, HttpResponse,
+};
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_invalid_cache_settings_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+        enable_http_cache(dir.path().to_string_lossy().into_owned(), 0),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+/// Path and `If-None-Match` of each request received
+#[cfg(feature = "http")]
+type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Serves `/fresh` for a minute, `/validated` with an ETag and `no-cache`
+/// (304 when the ETag is sent back), `/uncached` with `no-store`, `/plain`
+/// with no caching headers, `/vary` with the `Accept-Language` it was sent,
+/// and `/big` with 600 bytes
+#[cfg(feature = "http")]
+struct Server {
+    base: String,
+    requests: Requests,
+}
+
+#[cfg(feature = "http")]
+impl Server {
+    fn start() -> Self {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let base = common::serve(move |request, stream| respond(request, stream, &recorded));
+        Self { base, requests }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// How many requests for `path` reached the server
+    fn count(&self, path: &str) -> usize {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().filter(|(p, _)| p == path).count()
+    }
+}
+
+#[cfg(feature = "http")]
+fn respond(
+    request: common::Request,
+    mut stream: TcpStream,
+    requests: &Mutex<Vec<(String, Option<String>)>>,
+) {
+    let if_none_match = request.header("if-none-match").map(str::to_string);
+    requests
+        .lock()
+        .unwrap()
+        .push((request.path.clone(), if_none_match.clone()));
+
+    let (status, head, body) = match request.path.split('?').next().unwrap() {
+        "/fresh" => ("200 OK", "cache-control: max-age=60\r\n", b"fresh".to_vec()),
+        "/validated" if if_none_match.as_deref() == Some("\"v1\"") => {
+            ("304 Not Modified", "etag: \"v1\"\r\n", Vec::new())
+        }
+        "/validated" => (
+            "200 OK",
+            "cache-control: no-cache\r\netag: \"v1\"\r\n",
+            b"validated".to_vec(),
+        ),
+        "/uncached" => (
+            "200 OK",
+            "cache-control: no-store\r\netag: \"v1\"\r\n",
+            b"uncached".to_vec(),
+        ),
+        "/plain" => ("200 OK", "", b"plain".to_vec()),
+        "/vary" => (
+            "200 OK",
+            "cache-control: max-age=60\r\nvary: Accept-Language\r\n",
+            request
+                .header("accept-language")
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec(),
+        ),
+        "/big" => ("200 OK", "cache-control: max-age=60\r\n", vec![b'x'; 600]),
+        _ => ("404 Not Found", "", Vec::new()),
+    };
+    common::respond(&mut stream, status, head, &body);
+}
+
+#[cfg(feature = "http")]
+fn request(method: HttpMethod, url: &str, headers: &[(&str, &str)]) -> HttpRequest {
+    HttpRequest {
+        url: url.to_string(),
+        method,
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: None,
+        timeout_ms: Some(5_000),
+    }
+}
+
+#[cfg(feature = "http")]
+async fn get(url: &str, headers: &[(&str, &str)]) -> HttpResponse {
+    let response = http_request(request(HttpMethod::Get, url, headers), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    response
+}
+
+#[cfg(feature = "http")]
+fn cached_urls() -> Vec<String> {
+    let mut urls: Vec<String> = get_http_cache_entries()
+        .into_iter()
+        .map(|entry| entry.url)
+        .collect();
+    urls.sort();
+    urls
+}
+
+// The cache is process-wide, so these checks run in one test
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_cache_lifecycle() {
+    let server = Server::start();
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().to_string_lossy().into_owned();
+    enable_http_cache(directory.clone(), 1_000).unwrap();
+
+    // Fresh responses are served without a request
+    let fresh = server.url("/fresh");
+    for _ in 0..2 {
+        assert_eq!(get(&fresh, &[]).await.body, b"fresh");
+    }
+    assert_eq!(server.count("/fresh"), 1);
+    let entry = &get_http_cache_entries()[0];
+    assert_eq!(entry.url, fresh);
+    assert_eq!(entry.size_bytes, 5);
+    assert_eq!(entry.hit_count, 1);
+    assert!(entry.fresh_until_ms >= entry.stored_at_ms + 59_000);
+
+    // Stale responses are revalidated with their ETag
+    let validated = server.url("/validated");
+    for _ in 0..2 {
+        let response = get(&validated, &[]).await;
+        assert_eq!(response.body, b"validated");
+        assert_eq!(response.headers["etag"], "\"v1\"");
+    }
+    assert_eq!(
+        server.requests.lock().unwrap()[1..],
+        [
+            ("/validated".to_string(), None),
+            ("/validated".to_string(), Some("\"v1\"".to_string()))
+        ]
+    );
+
+    // no-store, and responses without max-age or a validator, are not kept
+    for path in ["/uncached", "/plain"] {
+        for _ in 0..2 {
+            get(&server.url(path), &[]).await;
+        }
+        assert_eq!(server.count(path), 2);
+    }
+    // A request of its own can skip the cache
+    get(&fresh, &[("Cache-Control", "no-store")]).await;
+    assert_eq!(server.count("/fresh"), 2);
+
+    // Responses are only reused for the same values of Vary headers
+    let vary = server.url("/vary");
+    for (language, requests) in [("en", 1), ("fr", 2), ("fr", 2)] {
+        let response = get(&vary, &[("Accept-Language", language)]).await;
+        assert_eq!(response.body, language.as_bytes());
+        assert_eq!(server.count("/vary"), requests);
+    }
+    assert_eq!(
+        cached_urls(),
+        [fresh.clone(), validated.clone(), vary.clone()]
+    );
+
+    // Unsafe requests drop what is stored for their URL
+    http_request(request(HttpMethod::Post, &fresh, &[]), None)
+        .await
+        .unwrap();
+    get(&fresh, &[]).await;
+    assert_eq!(server.count("/fresh"), 4);
+
+    // Entries survive disabling and enabling the cache
+    disable_http_cache();
+    assert!(get_http_cache_entries().is_empty());
+    get(&fresh, &[]).await;
+    assert_eq!(server.count("/fresh"), 5);
+    enable_http_cache(directory.clone(), 1_000).unwrap();
+    get(&fresh, &[]).await;
+    assert_eq!(server.count("/fresh"), 5);
+
+    assert!(remove_http_cache_entry(vary.clone()));
+    assert!(!remove_http_cache_entry(vary.clone()));
+    get(&vary, &[("Accept-Language", "fr")]).await;
+    assert_eq!(server.count("/vary"), 3);
+
+    // The least recently used entries make room for new ones
+    let (first, second) = (server.url("/big?1"), server.url("/big?2"));
+    get(&first, &[]).await;
+    get(&second, &[]).await;
+    assert_eq!(cached_urls(), std::slice::from_ref(&second));
+    assert_eq!(get(&second, &[]).await.body.len(), 600);
+    assert_eq!(server.count("/big?2"), 1);
+
+    clear_http_cache().unwrap();
+    assert!(get_http_cache_entries().is_empty());
+    let files: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|file| file.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["index.json"]);
+    get(&second, &[]).await;
+    assert_eq!(server.count("/big?2"), 2);
+    disable_http_cache();
+}
@@ -0,0 +1,313 @@
+use rust_multiplatform_template_lib::{
+    add_http_interceptor, get_http_interceptors, http_request, remove_http_interceptor,
+    HttpInterceptor, HttpMethod, HttpRequest, HttpResponse, TemplateError,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::CancellationToken;
+#[cfg(feature = "http")]
+use std::future::Future;
+#[cfg(feature = "http")]
+use std::net::TcpListener;
+#[cfg(feature = "http")]
+use std::pin::pin;
+#[cfg(feature = "http")]
+use std::task::{Context, Poll, Wake, Waker};
+#[cfg(feature = "http")]
+use std::thread::{self, Thread};
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers: HashMap::new(),
+        body: None,
+        timeout_ms: None,
+    }
+}
+
+#[cfg(not(feature = "http"))]
+#[tokio::test]
+async fn test_http_requires_feature() {
+    match http_request(get("http://127.0.0.1/"), None).await {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other),
+    }
+}
+
+/// Serves `/echo` (the request body back, with the method in `x-method`),
+/// `/redirect` (to `/echo`), `/signed` (the `x-signed-by` header as the
+/// body), `/slow` (after 2 seconds), and 404 otherwise
+#[cfg(feature = "http")]
+fn serve() -> String {
+    common::serve(|request, mut stream| match request.path.as_str() {
+        "/echo" => {
+            let headers = format!("x-method: {}\r\nx-tag: a\r\nx-tag: b\r\n", request.method);
+            common::respond(&mut stream, "200 OK", &headers, &request.body)
+        }
+        "/redirect" => common::respond(&mut stream, "302 Found", "location: /echo\r\n", b""),
+        "/signed" => {
+            let signed_by = request.header("x-signed-by").unwrap_or_default();
+            common::respond(&mut stream, "200 OK", "", signed_by.as_bytes())
+        }
+        "/slow" => {
+            thread::sleep(Duration::from_secs(2));
+            common::respond(&mut stream, "200 OK", "", b"late")
+        }
+        _ => common::respond(&mut stream, "404 Not Found", "", b"missing"),
+    })
+}
+
+/// Minimal executor without a tokio runtime, like the Swift/Kotlin executors
+#[cfg(feature = "http")]
+fn block_on_foreign<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_post_returns_status_headers_and_body() {
+    let base = serve();
+    let request = HttpRequest {
+        method: HttpMethod::Post,
+        headers: HashMap::from([("content-type".to_string(), "text/plain".to_string())]),
+        body: Some(b"hello".to_vec()),
+        ..get(&format!("{}/echo", base))
+    };
+    let response = http_request(request, None).await.unwrap();
+    assert!(response.is_success());
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"hello");
+    assert_eq!(response.headers["x-method"], "POST");
+    assert_eq!(response.headers["x-tag"], "a, b");
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_error_status_is_a_response_and_redirects_are_followed() {
+    let base = serve();
+    let response = http_request(get(&format!("{}/nothing", base)), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 404);
+    assert!(!response.is_success());
+    assert_eq!(response.body, b"missing");
+
+    let response = http_request(get(&format!("{}/redirect", base)), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.url, format!("{}/echo", base));
+    assert_eq!(response.headers["x-method"], "GET");
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_request_without_tokio_runtime() {
+    let base = serve();
+    let response = block_on_foreign(http_request(get(&format!("{}/echo", base)), None)).unwrap();
+    assert_eq!(response.status, 200);
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_timeout_and_cancellation() {
+    let base = serve();
+    let slow = get(&format!("{}/slow", base));
+
+    let request = HttpRequest {
+        timeout_ms: Some(100),
+        ..slow.clone()
+    };
+    assert!(matches!(
+        http_request(request, None).await,
+        Err(TemplateError::Timeout {
+            timeout_ms: 100,
+            ..
+        })
+    ));
+
+    let token = Arc::new(CancellationToken::new());
+    let canceller = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        canceller.cancel();
+    });
+    assert!(matches!(
+        http_request(slow.clone(), Some(token)).await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+
+    let token = Arc::new(CancellationToken::with_timeout(100));
+    assert!(matches!(
+        http_request(slow, Some(token)).await,
+        Err(TemplateError::Timeout { .. })
+    ));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_failures_without_response() {
+    let closed = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    };
+    match http_request(get(&closed), None).await {
+        Err(TemplateError::NetworkError {
+            url,
+            status_code,
+            details,
+            ..
+        }) => {
+            assert_eq!(url, closed);
+            assert_eq!(status_code, None);
+            // The causes say why reqwest's generic message came about
+            assert!(details.is_some_and(|details| !details.is_empty()));
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    for url in ["not a url", "ftp://example.com/file", "/relative"] {
+        assert!(matches!(
+            http_request(get(url), None).await,
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    let request = HttpRequest {
+        headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+        ..get(&closed)
+    };
+    assert!(matches!(
+        http_request(request, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+/// Requests as sent, with their responses
+type Seen = Arc<Mutex<Vec<(HttpRequest, HttpResponse)>>>;
+
+/// Adds `x-signed-by` with its name appended, and records the responses it
+/// sees; requests other than to `/signed` pass untouched, so the other tests
+/// here are unaffected
+struct Signer {
+    name: &'static str,
+    responses: Seen,
+}
+
+impl HttpInterceptor for Signer {
+    fn intercept_request(&self, mut request: HttpRequest) -> HttpRequest {
+        if !request.url.ends_with("/signed") {
+            return request;
+        }
+        let signed_by = match request.headers.get("x-signed-by") {
+            Some(previous) => format!("{},{}", previous, self.name),
+            None => self.name.to_string(),
+        };
+        request.headers.insert("x-signed-by".to_string(), signed_by);
+        request
+    }
+
+    fn on_response(&self, request: HttpRequest, response: HttpResponse) {
+        if request.url.ends_with("/signed") {
+            self.responses.lock().unwrap().push((request, response));
+        }
+    }
+}
+
+fn signer(name: &'static str) -> (Box<Signer>, Seen) {
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let signer = Signer {
+        name,
+        responses: responses.clone(),
+    };
+    (Box::new(signer), responses)
+}
+
+#[cfg(feature = "http")]
+async fn post_signed(base: &str) -> HttpResponse {
+    let request = HttpRequest {
+        method: HttpMethod::Post,
+        body: Some(b"payload".to_vec()),
+        timeout_ms: Some(5_000),
+        ..get(&format!("{}/signed", base))
+    };
+    http_request(request, None).await.unwrap()
+}
+
+// Interceptors are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_interceptors_change_requests_in_order() {
+    let (first, first_responses) = signer("first");
+    let (second, second_responses) = signer("second");
+    add_http_interceptor("first".to_string(), first).unwrap();
+    add_http_interceptor("second".to_string(), second).unwrap();
+    assert_eq!(get_http_interceptors(), ["first", "second"]);
+    assert!(matches!(
+        add_http_interceptor(" ".to_string(), signer("blank").0),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    #[cfg(feature = "http")]
+    {
+        let base = serve();
+        let response = post_signed(&base).await;
+        assert_eq!(response.body, b"first,second");
+
+        // Each interceptor sees the request as it was sent, and the response
+        for responses in [&first_responses, &second_responses] {
+            let responses = responses.lock().unwrap();
+            let (request, seen) = &responses[0];
+            assert_eq!(request.headers["x-signed-by"], "first,second");
+            assert_eq!(request.body.as_deref(), Some(&b"payload"[..]));
+            assert_eq!(seen, &response);
+        }
+
+        // Replacing an interceptor keeps its place
+        let (replacement, replacement_responses) = signer("replacement");
+        add_http_interceptor("first".to_string(), replacement).unwrap();
+        assert_eq!(get_http_interceptors(), ["first", "second"]);
+        assert_eq!(post_signed(&base).await.body, b"replacement,second");
+        assert_eq!(first_responses.lock().unwrap().len(), 1);
+        assert_eq!(replacement_responses.lock().unwrap().len(), 1);
+        assert_eq!(second_responses.lock().unwrap().len(), 2);
+
+        assert!(remove_http_interceptor("first".to_string()));
+        assert!(remove_http_interceptor("second".to_string()));
+        assert!(post_signed(&base).await.body.is_empty());
+        assert_eq!(second_responses.lock().unwrap().len(), 2);
+    }
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = (first_responses, second_responses);
+        assert!(remove_http_interceptor("first".to_string()));
+        assert!(remove_http_interceptor("second".to_string()));
+    }
+
+    assert!(!remove_http_interceptor("second".to_string()));
+    assert!(get_http_interceptors().is_empty());
+}
@@ -0,0 +1,365 @@
+use rust_multiplatform_template_lib::{HubClient, HubConfig, TemplateError};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{HubFile, HubModel, ModelSearchFilters, ModelSort};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "http")]
+fn config(endpoint: &str, access_token: Option<&str>) -> HubConfig {
+    HubConfig {
+        endpoint: endpoint.to_string(),
+        access_token: access_token.map(str::to_string),
+        timeout_ms: Some(5_000),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_hub_requires_feature() {
+    match HubClient::new(HubConfig::default()) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other.map(|_| ())),
+    }
+}
+
+/// Path with query and `Authorization` header of each request received
+#[cfg(feature = "http")]
+type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Fake Hub: a search result, a two-page file tree for `org/model`, a
+/// gated `org/gated` that needs the `hf_secret` token, and model cards for
+/// `org/model` on `main` and `refs/pr/1`
+#[cfg(feature = "http")]
+struct Server {
+    base: String,
+    requests: Requests,
+}
+
+#[cfg(feature = "http")]
+impl Server {
+    fn start() -> Self {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let base = common::serve(move |request, stream| respond(request, stream, &recorded));
+        Self { base, requests }
+    }
+
+    fn client(&self, access_token: Option<&str>) -> HubClient {
+        HubClient::new(config(&self.base, access_token)).unwrap()
+    }
+
+    fn paths(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+#[cfg(feature = "http")]
+fn respond(
+    request: common::Request,
+    mut stream: TcpStream,
+    requests: &Mutex<Vec<(String, Option<String>)>>,
+) {
+    let path = request.path.as_str();
+    let authorization = request.header("authorization");
+    requests
+        .lock()
+        .unwrap()
+        .push((path.to_string(), authorization.map(str::to_string)));
+
+    let base = format!("http://{}", request.header("host").unwrap());
+    let tree = "/api/models/org/model/tree/main?recursive=true";
+    let (status, head, body) = match path {
+        p if p.starts_with("/api/models?") => (
+            "200 OK",
+            String::new(),
+            r#"[{"id":"org/llama-gguf","downloads":1200,"likes":30,"pipeline_tag":"text-generation",
+                 "library_name":"gguf","tags":["gguf","license:mit"],"private":false,"gated":false,
+                 "lastModified":"2025-01-02T03:04:05.000Z"},
+                {"id":"llama-base","downloads":5,"likes":1,"tags":[],"gated":"manual"}]"#
+                .to_string(),
+        ),
+        p if p == tree => (
+            "200 OK",
+            format!("link: <{}{}&cursor=2>; rel=\"next\"\r\n", base, tree),
+            r#"[{"type":"directory","oid":"d1","size":0,"path":"onnx"},
+                {"type":"file","oid":"f1","size":10,"path":"config.json"},
+                {"type":"file","oid":"f2","size":134,"path":"onnx/model.onnx",
+                 "lfs":{"oid":"5e3c","size":1048576,"pointerSize":134}}]"#
+                .to_string(),
+        ),
+        p if p == format!("{}&cursor=2", tree) => (
+            "200 OK",
+            String::new(),
+            r#"[{"type":"file","oid":"f3","size":20,"path":"README.md"}]"#.to_string(),
+        ),
+        "/api/models/org/gated/tree/main?recursive=true"
+            if authorization != Some("Bearer hf_secret") =>
+        {
+            (
+                "401 Unauthorized",
+                "x-error-code: GatedRepo\r\nx-error-message: Access to model org/gated is restricted.\r\n"
+                    .to_string(),
+                r#"{"error":"Access to model org/gated is restricted."}"#.to_string(),
+            )
+        }
+        "/api/models/org/gated/tree/main?recursive=true" => {
+            ("200 OK", String::new(), "[]".to_string())
+        }
+        "/org/model/resolve/main/README.md" => (
+            "200 OK",
+            String::new(),
+            "---\nlicense: mit\ntags:\n- gguf\n---\n\n# Model\n".to_string(),
+        ),
+        "/org/model/resolve/refs%2Fpr%2F1/README.md" => {
+            ("200 OK", String::new(), "# Pull request\n".to_string())
+        }
+        _ => (
+            "404 Not Found",
+            String::new(),
+            r#"{"error":"Repository not found"}"#.to_string(),
+        ),
+    };
+    common::respond(&mut stream, status, &head, body.as_bytes());
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_search_models() {
+    let server = Server::start();
+    let filters = ModelSearchFilters {
+        author: Some("org".to_string()),
+        tags: vec!["gguf".to_string(), "license:mit".to_string()],
+        sort: ModelSort::Likes,
+        limit: 5,
+        ..ModelSearchFilters::default()
+    };
+    let models = server
+        .client(None)
+        .search_models(" llama ".to_string(), filters, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        models,
+        [
+            HubModel {
+                id: "org/llama-gguf".to_string(),
+                author: Some("org".to_string()),
+                downloads: 1200,
+                likes: 30,
+                pipeline_tag: Some("text-generation".to_string()),
+                library_name: Some("gguf".to_string()),
+                tags: vec!["gguf".to_string(), "license:mit".to_string()],
+                private: false,
+                gated: false,
+                last_modified: Some("2025-01-02T03:04:05.000Z".to_string()),
+            },
+            HubModel {
+                id: "llama-base".to_string(),
+                author: None,
+                downloads: 5,
+                likes: 1,
+                pipeline_tag: None,
+                library_name: None,
+                tags: Vec::new(),
+                private: false,
+                gated: true,
+                last_modified: None,
+            }
+        ]
+    );
+
+    let path = server.paths().remove(0);
+    for pair in [
+        "search=llama",
+        "author=org",
+        "filter=gguf",
+        "filter=license%3Amit",
+        "sort=likes",
+        "direction=-1",
+        "limit=5",
+        "expand%5B%5D=gated",
+    ] {
+        assert!(path.contains(pair), "{} is missing {}", path, pair);
+    }
+    assert!(!path.contains("pipeline_tag="));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_list_files_follows_pages() {
+    let server = Server::start();
+    let files = server
+        .client(None)
+        .list_files("org/model".to_string(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        files,
+        [
+            HubFile {
+                path: "config.json".to_string(),
+                size_bytes: 10,
+                sha256: None,
+            },
+            HubFile {
+                path: "onnx/model.onnx".to_string(),
+                size_bytes: 1_048_576,
+                sha256: Some("5e3c".to_string()),
+            },
+            HubFile {
+                path: "README.md".to_string(),
+                size_bytes: 20,
+                sha256: None,
+            },
+        ]
+    );
+    assert_eq!(server.paths().len(), 2);
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_gated_repositories_need_a_token() {
+    let server = Server::start();
+    match server
+        .client(None)
+        .list_files("org/gated".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::NetworkError {
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert_eq!(status_code, Some(401));
+            assert!(error_message.contains("is restricted"), "{}", error_message);
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    let client = server.client(Some("hf_secret"));
+    assert!(client
+        .list_files("org/gated".to_string(), None, None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        client.download_headers(),
+        HashMap::from([("Authorization".to_string(), "Bearer hf_secret".to_string())])
+    );
+    assert_eq!(
+        server.requests.lock().unwrap()[1].1.as_deref(),
+        Some("Bearer hf_secret")
+    );
+    assert!(server.client(None).download_headers().is_empty());
+
+    match client
+        .list_files("org/missing".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::NetworkError {
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert_eq!(status_code, Some(404));
+            assert!(error_message.contains("Repository not found"));
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_model_cards() {
+    let server = Server::start();
+    let client = server.client(None);
+    let card = client
+        .get_model_card("org/model".to_string(), None, None)
+        .await
+        .unwrap();
+    assert_eq!(card.repo_id, "org/model");
+    assert_eq!(
+        card.metadata.as_deref(),
+        Some("license: mit\ntags:\n- gguf\n")
+    );
+    assert_eq!(card.content, "# Model\n");
+
+    let card = client
+        .get_model_card("org/model".to_string(), Some("refs/pr/1".to_string()), None)
+        .await
+        .unwrap();
+    assert_eq!(card.metadata, None);
+    assert_eq!(card.content, "# Pull request\n");
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_invalid_requests_are_rejected() {
+    assert!(matches!(
+        HubClient::new(config("huggingface.co", None)),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let client = HubClient::new(HubConfig::default()).unwrap();
+    assert_eq!(
+        client
+            .file_url(
+                "org/model".to_string(),
+                "onnx/model.onnx".to_string(),
+                Some("refs/pr/1".to_string())
+            )
+            .unwrap(),
+        "https://huggingface.co/org/model/resolve/refs%2Fpr%2F1/onnx/model.onnx"
+    );
+    for repo_id in [
+        "",
+        "org/",
+        "a/b/c",
+        "../model",
+        "org/.hidden",
+        "-org/model",
+        "org/a..b",
+    ] {
+        assert!(
+            matches!(
+                client.file_url(repo_id.to_string(), "config.json".to_string(), None),
+                Err(TemplateError::InvalidInput { .. })
+            ),
+            "{} was accepted",
+            repo_id
+        );
+    }
+    for path in ["", "onnx//model.onnx", "../secrets", "onnx/"] {
+        assert!(matches!(
+            client.file_url("org/model".to_string(), path.to_string(), None),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert!(matches!(
+        client
+            .list_files("org/model".to_string(), Some(" ".to_string()), None)
+            .await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    for limit in [0, 1001] {
+        let filters = ModelSearchFilters {
+            limit,
+            ..ModelSearchFilters::default()
+        };
+        assert!(matches!(
+            client.search_models(String::new(), filters, None).await,
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+}
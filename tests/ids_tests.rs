@@ -0,0 +1,57 @@
+use rust_multiplatform_template_lib::{
+    generate_uuid_v4, generate_uuid_v7, is_valid_uuid, parse_uuid, TemplateError,
+};
+
+#[tokio::test]
+async fn test_generate_uuid_v4() {
+    let id = generate_uuid_v4().await;
+    assert_eq!(id.len(), 36);
+    assert_eq!(id, id.to_lowercase());
+    assert_ne!(id, generate_uuid_v4().await);
+
+    let parsed = parse_uuid(id.clone(), None).await.unwrap();
+    assert_eq!(parsed.canonical, id);
+    assert_eq!(parsed.version, 4);
+    assert!(parsed.timestamp_ms.is_none());
+}
+
+#[tokio::test]
+async fn test_generate_uuid_v7_is_sortable() {
+    let mut ids = Vec::new();
+    for _ in 0..20 {
+        ids.push(generate_uuid_v7().await);
+    }
+    let mut sorted = ids.clone();
+    sorted.sort();
+    assert_eq!(ids, sorted);
+
+    let parsed = parse_uuid(ids[0].clone(), None).await.unwrap();
+    assert_eq!(parsed.version, 7);
+    assert!(parsed.timestamp_ms.unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_parse_uuid_normalizes_forms() {
+    let canonical = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    for input in [
+        "67E55044-10B1-426F-9247-BB680E5FE0C8",
+        "67e5504410b1426f9247bb680e5fe0c8",
+        "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+        "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8",
+    ] {
+        let parsed = parse_uuid(input.to_string(), None).await.unwrap();
+        assert_eq!(parsed.canonical, canonical);
+        assert!(is_valid_uuid(input.to_string()).await);
+    }
+}
+
+#[tokio::test]
+async fn test_parse_uuid_invalid() {
+    assert!(!is_valid_uuid("not-a-uuid".to_string()).await);
+    match parse_uuid("not-a-uuid".to_string(), None).await {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("Invalid UUID"));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+}
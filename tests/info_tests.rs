@@ -0,0 +1,63 @@
+use rust_multiplatform_template_lib::{
+    get_capabilities, get_library_info, HashAlgorithm, ModelFormat, MAX_INPUT_SIZE,
+};
+
+#[test]
+fn test_library_info_describes_this_build() {
+    let info = get_library_info();
+    assert_eq!(info.name, env!("CARGO_PKG_NAME"));
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(info.build_timestamp > 0);
+    assert!(!info.target.is_empty());
+    assert_eq!(info.profile, "debug");
+    assert!(info.uniffi_contract_version > 0);
+    if let Some(commit) = &info.git_commit {
+        assert!(commit.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+    assert_eq!(
+        info.features.contains(&"cbor".to_string()),
+        cfg!(feature = "cbor")
+    );
+    assert_eq!(
+        info.features.contains(&"debug-errors".to_string()),
+        cfg!(feature = "debug-errors")
+    );
+    assert_eq!(
+        info.features.contains(&"grpc".to_string()),
+        cfg!(feature = "grpc")
+    );
+    assert_eq!(
+        info.features.contains(&"http".to_string()),
+        cfg!(feature = "http")
+    );
+    assert_eq!(
+        info.features.contains(&"otel".to_string()),
+        cfg!(feature = "otel")
+    );
+    assert_eq!(
+        info.features.contains(&"sqlite".to_string()),
+        cfg!(feature = "sqlite")
+    );
+}
+
+#[test]
+fn test_capabilities_match_build_flavor() {
+    let capabilities = get_capabilities();
+    assert_eq!(
+        capabilities.has_debug_errors,
+        cfg!(feature = "debug-errors")
+    );
+    assert_eq!(capabilities.has_otel_export, cfg!(feature = "otel"));
+    assert_eq!(capabilities.has_database, cfg!(feature = "sqlite"));
+    assert_eq!(capabilities.has_http, cfg!(feature = "http"));
+    assert_eq!(capabilities.has_grpc, cfg!(feature = "grpc"));
+    assert_eq!(capabilities.has_cbor, cfg!(feature = "cbor"));
+    assert!(capabilities
+        .hash_algorithms
+        .contains(&HashAlgorithm::Sha256));
+    assert_eq!(
+        capabilities.model_formats,
+        [ModelFormat::Gguf, ModelFormat::Safetensors]
+    );
+    assert_eq!(capabilities.max_input_size, MAX_INPUT_SIZE as u64);
+}
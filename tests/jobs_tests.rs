@@ -0,0 +1,156 @@
+use rust_multiplatform_template_lib::{
+    HashAlgorithm, JobInfo, JobKind, JobListener, JobQueue, JobState, TemplateError,
+};
+use std::sync::{Arc, Mutex};
+
+struct RecordingListener(Arc<Mutex<Vec<(u64, JobState)>>>);
+
+impl JobListener for RecordingListener {
+    fn on_job_state_changed(&self, job: JobInfo) {
+        self.0.lock().unwrap().push((job.id, job.state));
+    }
+}
+
+fn hash_job(path: &std::path::Path) -> JobKind {
+    JobKind::HashFile {
+        path: path.to_string_lossy().into_owned(),
+        algorithm: HashAlgorithm::Sha256,
+    }
+}
+
+#[tokio::test]
+async fn test_job_queue_runs_jobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("data.txt");
+    std::fs::write(&file, "hello").unwrap();
+
+    let queue = JobQueue::new(2, None).unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    queue.set_listener(Box::new(RecordingListener(events.clone())));
+
+    let hash_id = queue.enqueue("hash".to_string(), hash_job(&file), 0);
+    let scan_id = queue.enqueue(
+        "scan".to_string(),
+        JobKind::DiscoverModels {
+            directory: dir.path().to_string_lossy().into_owned(),
+        },
+        0,
+    );
+    queue.wait_idle().await;
+
+    let hash = queue.job(hash_id).unwrap();
+    assert_eq!(hash.state, JobState::Completed);
+    assert_eq!(
+        hash.output.as_deref(),
+        Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+    );
+    let scan = queue.job(scan_id).unwrap();
+    assert_eq!(scan.state, JobState::Completed);
+    assert_eq!(scan.output.as_deref(), Some("0"));
+
+    let events = events.lock().unwrap();
+    assert!(events.contains(&(hash_id, JobState::Running)));
+    assert!(events.contains(&(hash_id, JobState::Completed)));
+    assert_eq!(queue.jobs().len(), 2);
+}
+
+#[tokio::test]
+async fn test_job_queue_reports_failures() {
+    let queue = JobQueue::new(1, None).unwrap();
+    let id = queue.enqueue(
+        "missing".to_string(),
+        hash_job(std::path::Path::new("/definitely/not/here")),
+        0,
+    );
+    queue.wait_idle().await;
+
+    let job = queue.job(id).unwrap();
+    assert_eq!(job.state, JobState::Failed);
+    assert!(job.error_message.is_some());
+}
+
+#[tokio::test]
+async fn test_job_queue_priority_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("data.txt");
+    std::fs::write(&file, "x").unwrap();
+
+    let queue = JobQueue::new(1, None).unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    queue.set_listener(Box::new(RecordingListener(events.clone())));
+
+    // The first job starts right away; the rest wait for the single slot
+    let first = queue.enqueue("first".to_string(), hash_job(&file), 0);
+    let low = queue.enqueue("low".to_string(), hash_job(&file), 1);
+    let high = queue.enqueue("high".to_string(), hash_job(&file), 10);
+    queue.wait_idle().await;
+
+    let started: Vec<u64> = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, state)| *state == JobState::Running)
+        .map(|(id, _)| *id)
+        .collect();
+    assert_eq!(started[0], first);
+    assert_eq!(&started[1..], &[high, low]);
+}
+
+#[tokio::test]
+async fn test_job_queue_cancel_queued_job() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("data.txt");
+    std::fs::write(&file, "x").unwrap();
+
+    let queue = JobQueue::new(1, None).unwrap();
+    queue.enqueue("running".to_string(), hash_job(&file), 0);
+    let queued = queue.enqueue("queued".to_string(), hash_job(&file), 0);
+    assert!(queue.cancel(queued));
+    queue.wait_idle().await;
+
+    assert_eq!(queue.job(queued).unwrap().state, JobState::Cancelled);
+    assert!(!queue.cancel(queued));
+    assert!(!queue.cancel(12345));
+}
+
+#[tokio::test]
+async fn test_job_queue_resumes_persisted_jobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("data.txt");
+    std::fs::write(&file, "hello").unwrap();
+    let persist = dir.path().join("jobs.json");
+
+    let saved = r#"[{"id": 7, "name": "resumed", "priority": 0,
+        "kind": {"type": "hash_file", "path": "PATH", "algorithm": "sha256"}}]"#
+        .replace("PATH", &file.to_string_lossy());
+    std::fs::write(&persist, saved).unwrap();
+
+    let queue = JobQueue::new(1, Some(persist.to_string_lossy().into_owned())).unwrap();
+    queue.wait_idle().await;
+    let job = queue.job(7).unwrap();
+    assert_eq!(job.name, "resumed");
+    assert_eq!(job.state, JobState::Completed);
+
+    // New ids continue after resumed ones, and finished jobs are not saved
+    let id = queue.enqueue("next".to_string(), hash_job(&file), 0);
+    assert_eq!(id, 8);
+    queue.wait_idle().await;
+    let contents = std::fs::read_to_string(&persist).unwrap();
+    assert_eq!(contents.trim(), "[]");
+}
+
+#[test]
+fn test_job_queue_rejects_invalid_settings() {
+    assert!(matches!(
+        JobQueue::new(0, None),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let persist = dir.path().join("jobs.json");
+    std::fs::write(&persist, "not json").unwrap();
+    assert!(matches!(
+        JobQueue::new(1, Some(persist.to_string_lossy().into_owned())),
+        Err(TemplateError::ParseError { .. })
+    ));
+}
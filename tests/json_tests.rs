@@ -0,0 +1,142 @@
+use rust_multiplatform_template_lib::{
+    chat_messages_from_json, chat_messages_to_json, discovered_model_from_json,
+    discovered_model_to_json, echo, echo_result_from_json, echo_result_to_json,
+    generation_params_from_json, generation_params_to_json, generation_result_from_json,
+    generation_result_to_json, model_metadata_from_json, model_metadata_to_json, CallTiming,
+    ChatMessage, ChatRole, DiscoveredModel, EchoResult, GenerationParams, GenerationResult,
+    ModelFormat, ModelMetadata, SanitizationReport, TemplateError, TextTransform, Warning,
+    WarningKind,
+};
+
+fn metadata() -> ModelMetadata {
+    ModelMetadata {
+        version: 3,
+        tensor_count: 291,
+        metadata_count: 24,
+        architecture: Some("llama".to_string()),
+        name: None,
+    }
+}
+
+#[tokio::test]
+async fn test_echo_result_round_trips() {
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        echo_result_from_json(echo_result_to_json(result.clone())).unwrap(),
+        result
+    );
+
+    let result = EchoResult {
+        transforms_applied: vec![TextTransform::Trim, TextTransform::CollapseWhitespace],
+        sanitization: Some(SanitizationReport {
+            control_chars_removed: 2,
+            bom_removed: true,
+            line_endings_normalized: 1,
+        }),
+        warnings: vec![Warning {
+            kind: WarningKind::InputSanitized,
+            message: "2 control characters removed".to_string(),
+        }],
+        timing: Some(CallTiming {
+            queue_wait_us: 1,
+            validation_us: 2,
+            compute_us: 3,
+            total_us: 7,
+        }),
+        ..result
+    };
+    let json = echo_result_to_json(result.clone());
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["text"], "hello");
+    assert_eq!(value["transforms_applied"][1], "collapse_whitespace");
+    assert_eq!(value["warnings"][0]["kind"], "input_sanitized");
+    assert_eq!(value["timing"]["total_us"], 7);
+    assert_eq!(echo_result_from_json(json).unwrap(), result);
+}
+
+#[test]
+fn test_model_records_round_trip() {
+    let json = model_metadata_to_json(metadata());
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["architecture"], "llama");
+    assert!(value["name"].is_null());
+    assert_eq!(model_metadata_from_json(json).unwrap(), metadata());
+
+    let model = DiscoveredModel {
+        path: "/models/tiny.gguf".to_string(),
+        file_name: "tiny.gguf".to_string(),
+        size_bytes: 1_048_576,
+        format: ModelFormat::Gguf,
+        is_valid: true,
+        metadata: Some(metadata()),
+        error_message: None,
+    };
+    let json = discovered_model_to_json(model.clone());
+    assert!(json.contains(r#""format":"gguf""#));
+    assert_eq!(discovered_model_from_json(json).unwrap(), model);
+}
+
+#[test]
+fn test_generation_records_round_trip() {
+    let params = GenerationParams {
+        max_tokens: Some(256),
+        temperature: Some(0.7),
+        stop: vec!["\n\n".to_string()],
+        ..GenerationParams::default()
+    };
+    let json = generation_params_to_json(params.clone());
+    assert!(!json.contains("top_p"));
+    assert_eq!(generation_params_from_json(json).unwrap(), params);
+    assert_eq!(
+        generation_params_from_json("{}".to_string()).unwrap(),
+        GenerationParams::default()
+    );
+
+    let result = GenerationResult {
+        text: "Hi there".to_string(),
+        finish_reason: Some("stop".to_string()),
+        model: "gpt-4o-mini".to_string(),
+        prompt_tokens: Some(12),
+        completion_tokens: None,
+    };
+    assert_eq!(
+        generation_result_from_json(generation_result_to_json(result.clone())).unwrap(),
+        result
+    );
+
+    let messages = vec![
+        ChatMessage {
+            role: ChatRole::System,
+            content: "Be brief".to_string(),
+        },
+        ChatMessage {
+            role: ChatRole::User,
+            content: "Hello".to_string(),
+        },
+    ];
+    let json = chat_messages_to_json(messages.clone());
+    assert!(json.starts_with(r#"[{"role":"system","content":"Be brief"}"#));
+    assert_eq!(chat_messages_from_json(json).unwrap(), messages);
+}
+
+#[test]
+fn test_invalid_json_is_rejected() {
+    match model_metadata_from_json("{\n  \"version\": \"three\"\n}".to_string()) {
+        Err(TemplateError::ParseError { format, line, .. }) => {
+            assert_eq!(format, "JSON");
+            assert_eq!(line, Some(2));
+        }
+        other => panic!("Expected ParseError, got {:?}", other),
+    }
+    assert!(matches!(
+        echo_result_from_json("not json".to_string()),
+        Err(TemplateError::ParseError { .. })
+    ));
+    assert!(matches!(
+        chat_messages_from_json(r#"[{"role":"robot","content":"hi"}]"#.to_string()),
+        Err(TemplateError::ParseError { .. })
+    ));
+}
@@ -0,0 +1,315 @@
+use rust_multiplatform_template_lib::{
+    IntegrityIssueKind, KvChange, KvMigration, KvMigrationStep, KvStore, KvValue, MigrationReport,
+    TemplateError,
+};
+use sha2::{Digest, Sha256};
+
+fn open(dir: &tempfile::TempDir) -> KvStore {
+    KvStore::open(
+        dir.path()
+            .join("prefs/store.json")
+            .to_string_lossy()
+            .into_owned(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_kv_store_persists_across_opens() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    assert!(store.keys().is_empty());
+
+    store
+        .set_string("name".to_string(), "Ada".to_string())
+        .unwrap();
+    store.set_int("count".to_string(), -3).unwrap();
+    store.set_bool("onboarded".to_string(), true).unwrap();
+    store
+        .set_bytes("blob".to_string(), vec![0, 1, 255])
+        .unwrap();
+    drop(store);
+
+    let store = open(&dir);
+    assert_eq!(store.keys(), vec!["blob", "count", "name", "onboarded"]);
+    assert_eq!(
+        store.get_string("name".to_string()).unwrap(),
+        Some("Ada".to_string())
+    );
+    assert_eq!(store.get_int("count".to_string()).unwrap(), Some(-3));
+    assert_eq!(store.get_bool("onboarded".to_string()).unwrap(), Some(true));
+    assert_eq!(
+        store.get_bytes("blob".to_string()).unwrap(),
+        Some(vec![0, 1, 255])
+    );
+    assert_eq!(
+        store.get("count".to_string()),
+        Some(KvValue::Int { value: -3 })
+    );
+}
+
+#[test]
+fn test_kv_store_delete_and_overwrite() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    store.set_int("count".to_string(), 1).unwrap();
+    store
+        .set_string("count".to_string(), "one".to_string())
+        .unwrap();
+    assert_eq!(
+        store.get_string("count".to_string()).unwrap(),
+        Some("one".to_string())
+    );
+
+    assert!(store.delete("count".to_string()).unwrap());
+    assert!(!store.delete("count".to_string()).unwrap());
+    assert_eq!(store.get("count".to_string()), None);
+    assert_eq!(store.get_int("missing".to_string()).unwrap(), None);
+
+    drop(store);
+    assert!(open(&dir).keys().is_empty());
+    // Only the store file is left behind
+    let files: Vec<_> = std::fs::read_dir(dir.path().join("prefs"))
+        .unwrap()
+        .collect();
+    assert_eq!(files.len(), 1);
+}
+
+#[test]
+fn test_kv_store_typed_getter_rejects_other_types() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    store.set_bool("flag".to_string(), false).unwrap();
+    assert!(matches!(
+        store.get_int("flag".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_kv_store_rejects_corrupt_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    std::fs::write(&path, "not json").unwrap();
+    assert!(matches!(
+        KvStore::open(path.to_string_lossy().into_owned()),
+        Err(TemplateError::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_kv_store_failed_write_keeps_previous_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let store = KvStore::open(path.to_string_lossy().into_owned()).unwrap();
+    store.set_int("count".to_string(), 1).unwrap();
+
+    // A non-empty directory in place of the file makes the next write fail
+    std::fs::remove_file(&path).unwrap();
+    std::fs::create_dir_all(path.join("blocker")).unwrap();
+    assert!(matches!(
+        store.set_int("count".to_string(), 2),
+        Err(TemplateError::IoError { .. })
+    ));
+    assert_eq!(store.get_int("count".to_string()).unwrap(), Some(1));
+}
+
+#[test]
+fn test_kv_store_write_batch_applies_all_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    store.set_int("stale".to_string(), 1).unwrap();
+
+    store
+        .write_batch(vec![
+            KvChange::Set {
+                key: "a".to_string(),
+                value: KvValue::Int { value: 1 },
+            },
+            KvChange::Set {
+                key: "b".to_string(),
+                value: KvValue::Bool { value: true },
+            },
+            KvChange::Delete {
+                key: "stale".to_string(),
+            },
+        ])
+        .unwrap();
+    assert_eq!(store.keys(), vec!["a", "b"]);
+    assert!(!dir.path().join("prefs/store.json.journal").exists());
+
+    drop(store);
+    assert_eq!(open(&dir).keys(), vec!["a", "b"]);
+}
+
+/// Writes a journal as `write_batch` does before replacing the file
+fn write_journal(dir: &tempfile::TempDir, payload: &str) {
+    let checksum = hex::encode(Sha256::digest(payload.as_bytes()));
+    std::fs::write(
+        dir.path().join("prefs/store.json.journal"),
+        format!("{}\n{}", checksum, payload),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_kv_store_completes_interrupted_batch_on_open() {
+    let dir = tempfile::tempdir().unwrap();
+    open(&dir).set_int("a".to_string(), 0).unwrap();
+    write_journal(
+        &dir,
+        r#"[{"op":"set","key":"a","value":{"type":"int","value":1}},{"op":"set","key":"b","value":{"type":"int","value":2}}]"#,
+    );
+
+    let store = open(&dir);
+    assert_eq!(store.get_int("a".to_string()).unwrap(), Some(1));
+    assert_eq!(store.get_int("b".to_string()).unwrap(), Some(2));
+    assert!(store.verify_integrity(false).unwrap().issues.is_empty());
+}
+
+#[test]
+fn test_kv_store_discards_torn_journal_on_open() {
+    let dir = tempfile::tempdir().unwrap();
+    open(&dir).set_int("a".to_string(), 0).unwrap();
+    std::fs::write(
+        dir.path().join("prefs/store.json.journal"),
+        "0123\n[{\"op\":\"set\"",
+    )
+    .unwrap();
+
+    let store = open(&dir);
+    assert_eq!(store.keys(), vec!["a"]);
+    assert!(!dir.path().join("prefs/store.json.journal").exists());
+}
+
+#[test]
+fn test_kv_store_verify_integrity_reports_and_repairs() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = open(&dir);
+    store.set_int("a".to_string(), 1).unwrap();
+    assert_eq!(store.verify_integrity(true).unwrap().issues, vec![]);
+
+    // Damage left by another process crashing mid-write
+    write_journal(
+        &dir,
+        r#"[{"op":"delete","key":"a"},{"op":"set","key":"b","value":{"type":"bool","value":true}}]"#,
+    );
+    std::fs::write(dir.path().join("prefs/.store.json.1234.tmp"), "partial").unwrap();
+
+    let report = store.verify_integrity(false).unwrap();
+    let kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            IntegrityIssueKind::PendingJournal,
+            IntegrityIssueKind::LeftoverTempFile
+        ]
+    );
+    assert!(!report.repaired);
+    assert_eq!(store.keys(), vec!["a"]);
+
+    let report = store.verify_integrity(true).unwrap();
+    assert!(report.repaired);
+    assert_eq!(store.keys(), vec!["b"]);
+    assert!(!dir.path().join("prefs/.store.json.1234.tmp").exists());
+    assert!(store.verify_integrity(false).unwrap().issues.is_empty());
+
+    // A data file that no longer parses is rewritten from memory
+    std::fs::write(dir.path().join("prefs/store.json"), "{not json").unwrap();
+    let report = store.verify_integrity(true).unwrap();
+    assert_eq!(report.issues[0].kind, IntegrityIssueKind::UnreadableFile);
+    drop(store);
+    assert_eq!(open(&dir).keys(), vec!["b"]);
+}
+
+fn migrations() -> Vec<KvMigration> {
+    vec![
+        KvMigration {
+            version: 1,
+            steps: vec![KvMigrationStep::Rename {
+                from: "user".to_string(),
+                to: "user_name".to_string(),
+            }],
+        },
+        KvMigration {
+            version: 2,
+            steps: vec![
+                KvMigrationStep::Remove {
+                    key: "legacy_flag".to_string(),
+                },
+                KvMigrationStep::Set {
+                    key: "theme".to_string(),
+                    value: KvValue::String {
+                        value: "system".to_string(),
+                    },
+                },
+            ],
+        },
+    ]
+}
+
+#[test]
+fn test_kv_store_migrates_unversioned_file_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    // The format written before schema versions were recorded
+    std::fs::write(
+        &path,
+        r#"{"user": {"type": "string", "value": "Ada"}, "legacy_flag": {"type": "bool", "value": true}}"#,
+    )
+    .unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    let store = KvStore::open_with_migrations(path.clone(), migrations()).unwrap();
+    assert_eq!(
+        store.migration_report(),
+        MigrationReport {
+            from_version: 0,
+            to_version: 2,
+            applied: vec![1, 2],
+        }
+    );
+    assert_eq!(store.keys(), vec!["theme", "user_name"]);
+    store
+        .set_string("theme".to_string(), "dark".to_string())
+        .unwrap();
+    drop(store);
+
+    let store = KvStore::open_with_migrations(path.clone(), migrations()).unwrap();
+    assert_eq!(
+        store.migration_report(),
+        MigrationReport {
+            from_version: 2,
+            to_version: 2,
+            applied: Vec::new(),
+        }
+    );
+    assert_eq!(
+        store.get_string("theme".to_string()).unwrap(),
+        Some("dark".to_string())
+    );
+    assert_eq!(
+        KvStore::open(path).unwrap().migration_report().to_version,
+        2
+    );
+}
+
+#[test]
+fn test_kv_store_rejects_bad_migrations() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json").to_string_lossy().into_owned();
+    let mut unordered = migrations();
+    unordered.reverse();
+    assert!(matches!(
+        KvStore::open_with_migrations(path.clone(), unordered),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    // Data from a newer release is not opened with older migrations
+    drop(KvStore::open_with_migrations(path.clone(), migrations()).unwrap());
+    let older = migrations().into_iter().take(1).collect();
+    assert!(matches!(
+        KvStore::open_with_migrations(path, older),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
@@ -0,0 +1,69 @@
+use rust_multiplatform_template_lib::{
+    discover_models, get_config, set_log_filter, set_log_level, set_logger, LogLevel, LogRecord,
+    LoggerCallback, TemplateError,
+};
+use std::sync::{Arc, Mutex};
+
+struct Recorder(Arc<Mutex<Vec<LogRecord>>>);
+
+impl LoggerCallback for Recorder {
+    fn log(&self, record: LogRecord) {
+        self.0.lock().unwrap().push(record);
+    }
+}
+
+fn discovery_logged(records: &Mutex<Vec<LogRecord>>) -> bool {
+    records
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|r| r.message.starts_with("Found 0 model files"))
+}
+
+// The logger, level, and filter are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_log_level_and_filter_change_at_runtime() {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    set_logger(Some(Box::new(Recorder(records.clone()))));
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().to_string_lossy().into_owned();
+
+    // A per-module level lets debug records through for that module only
+    set_log_filter("models=debug, llama=trace".to_string()).unwrap();
+    discover_models(directory.clone(), None).await.unwrap();
+    assert!(discovery_logged(&records));
+    assert_eq!(get_config().log_level, LogLevel::Warn);
+
+    set_log_filter("models=warn".to_string()).unwrap();
+    records.lock().unwrap().clear();
+    discover_models(directory.clone(), None).await.unwrap();
+    assert!(!discovery_logged(&records));
+
+    // Invalid filters are rejected and leave the previous one in place
+    assert!(matches!(
+        set_log_filter("models=loud".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        set_log_filter("=debug".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    set_log_level(LogLevel::Trace);
+    assert_eq!(get_config().log_level, LogLevel::Trace);
+    discover_models(directory.clone(), None).await.unwrap();
+    assert!(!discovery_logged(&records));
+
+    // Clearing the filter falls back to the global level
+    set_log_filter(String::new()).unwrap();
+    discover_models(directory.clone(), None).await.unwrap();
+    assert!(discovery_logged(&records));
+
+    // A bare level sets the global level
+    set_log_filter("WARN".to_string()).unwrap();
+    assert_eq!(get_config().log_level, LogLevel::Warn);
+    records.lock().unwrap().clear();
+    discover_models(directory, None).await.unwrap();
+    assert!(!discovery_logged(&records));
+
+    set_logger(None);
+}
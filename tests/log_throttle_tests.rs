@@ -0,0 +1,88 @@
+use rust_multiplatform_template_lib::{
+    discover_models, get_metrics_snapshot, set_log_filter, set_log_throttles, set_logger,
+    LogRecord, LogThrottle, LoggerCallback, TemplateError,
+};
+use std::sync::{Arc, Mutex};
+
+struct Recorder(Arc<Mutex<Vec<LogRecord>>>);
+
+impl LoggerCallback for Recorder {
+    fn log(&self, record: LogRecord) {
+        self.0.lock().unwrap().push(record);
+    }
+}
+
+fn throttle(target: &str, sample_every: u32, max_per_second: u32) -> LogThrottle {
+    LogThrottle {
+        target: target.to_string(),
+        sample_every,
+        max_per_second,
+    }
+}
+
+/// Runs model discovery `times` times and counts the debug records it forwarded
+async fn discovery_records(
+    records: &Mutex<Vec<LogRecord>>,
+    directory: &str,
+    times: usize,
+) -> usize {
+    records.lock().unwrap().clear();
+    for _ in 0..times {
+        discover_models(directory.to_string(), None).await.unwrap();
+    }
+    records
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|r| r.message.starts_with("Found 0 model files"))
+        .count()
+}
+
+fn throttled_count() -> u64 {
+    get_metrics_snapshot()
+        .counters
+        .get("log_records_throttled")
+        .copied()
+        .unwrap_or(0)
+}
+
+// The logger, filter, and throttles are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_log_sampling_and_rate_caps() {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    set_logger(Some(Box::new(Recorder(records.clone()))));
+    set_log_filter("models=debug".to_string()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().to_string_lossy().into_owned();
+
+    assert_eq!(discovery_records(&records, &directory, 10).await, 10);
+
+    // Sampling keeps the 1st, 4th, 7th, and 10th records
+    set_log_throttles(vec![throttle("models", 3, 0)]).unwrap();
+    let dropped = throttled_count();
+    assert_eq!(discovery_records(&records, &directory, 10).await, 4);
+    assert!(throttled_count() >= dropped + 6);
+
+    // The rate cap stops forwarding within the second
+    set_log_throttles(vec![throttle("models", 1, 2)]).unwrap();
+    assert_eq!(discovery_records(&records, &directory, 10).await, 2);
+
+    // A throttle for another module does not apply
+    set_log_throttles(vec![throttle("template", 1, 1)]).unwrap();
+    assert_eq!(discovery_records(&records, &directory, 5).await, 5);
+
+    // Invalid throttles are rejected and leave the previous ones in place
+    assert!(matches!(
+        set_log_throttles(vec![throttle("models", 0, 0)]),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        set_log_throttles(vec![throttle(" ", 1, 0)]),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert_eq!(discovery_records(&records, &directory, 5).await, 5);
+
+    set_log_throttles(Vec::new()).unwrap();
+    set_log_filter(String::new()).unwrap();
+    set_logger(None);
+}
@@ -0,0 +1,51 @@
+use rust_multiplatform_template_lib::{
+    discover_models, set_logger, update_config, LibraryConfig, LogLevel, LogRecord, LoggerCallback,
+};
+use std::sync::{Arc, Mutex};
+
+struct Recorder(Arc<Mutex<Vec<LogRecord>>>);
+
+impl LoggerCallback for Recorder {
+    fn log(&self, record: LogRecord) {
+        self.0.lock().unwrap().push(record);
+    }
+}
+
+// The logger and log level are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_logs_reach_host_sink_at_configured_level() {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    set_logger(Some(Box::new(Recorder(records.clone()))));
+    let dir = tempfile::tempdir().unwrap();
+    let directory = dir.path().to_string_lossy().into_owned();
+
+    // Debug messages are filtered at the default Warn level
+    discover_models(directory.clone(), None).await.unwrap();
+    assert!(records
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|r| r.level <= LogLevel::Warn));
+
+    update_config(LibraryConfig {
+        log_level: LogLevel::Debug,
+        ..LibraryConfig::default()
+    })
+    .unwrap();
+    discover_models(directory.clone(), None).await.unwrap();
+    {
+        let records = records.lock().unwrap();
+        let found = records
+            .iter()
+            .find(|r| r.message.starts_with("Found 0 model files"))
+            .expect("discovery should log at debug level");
+        assert_eq!(found.level, LogLevel::Debug);
+        assert!(found.target.ends_with("models"));
+        assert!(found.timestamp_ms > 0);
+    }
+
+    set_logger(None);
+    let count = records.lock().unwrap().len();
+    discover_models(directory, None).await.unwrap();
+    assert_eq!(records.lock().unwrap().len(), count);
+}
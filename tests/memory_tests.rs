@@ -0,0 +1,27 @@
+use rust_multiplatform_template_lib::{
+    get_recent_events, on_memory_pressure, update_config, LibraryConfig, MemoryPressureLevel,
+    RECENT_EVENTS_CAPACITY,
+};
+
+// The recent-events buffer is process-wide, so these checks run in one test
+#[test]
+fn test_memory_pressure_trims_recent_events() {
+    for _ in 0..RECENT_EVENTS_CAPACITY {
+        update_config(LibraryConfig::default()).unwrap();
+    }
+    assert_eq!(
+        get_recent_events(None).len(),
+        RECENT_EVENTS_CAPACITY as usize
+    );
+
+    let freed = on_memory_pressure(MemoryPressureLevel::Moderate);
+    assert!(freed > 0);
+    assert_eq!(
+        get_recent_events(None).len(),
+        RECENT_EVENTS_CAPACITY as usize / 4
+    );
+
+    assert!(on_memory_pressure(MemoryPressureLevel::Critical) > 0);
+    assert!(get_recent_events(None).is_empty());
+    assert_eq!(on_memory_pressure(MemoryPressureLevel::Critical), 0);
+}
@@ -0,0 +1,53 @@
+use rust_multiplatform_template_lib::{
+    echo_stream, get_metrics_snapshot, reset_metrics, TemplateConfig, LATENCY_BUCKET_BOUNDS_US,
+};
+
+// The registry is process-wide, so these checks run in one test
+#[test]
+fn test_metrics_snapshot_and_reset() {
+    reset_metrics();
+    let before = get_metrics_snapshot();
+    assert!(before.operations.is_empty());
+    assert!(before.counters.is_empty());
+
+    TemplateConfig::from_json("{}".to_string()).unwrap();
+    TemplateConfig::from_json("not json".to_string()).unwrap_err();
+    let stream = echo_stream(None);
+    stream.push_chunk(b"hello".to_vec()).unwrap();
+
+    let snapshot = get_metrics_snapshot();
+    assert!(snapshot.since_ms >= before.since_ms);
+    assert!(snapshot.taken_ms >= snapshot.since_ms);
+    let from_json = snapshot
+        .operations
+        .iter()
+        .find(|o| o.operation == "from_json")
+        .unwrap();
+    assert_eq!(from_json.calls, 2);
+    assert_eq!(from_json.errors, 1);
+    assert_eq!(from_json.errors_by_code.values().sum::<u64>(), 1);
+    assert_eq!(
+        from_json.latency_buckets.len(),
+        LATENCY_BUCKET_BOUNDS_US.len() + 1
+    );
+    assert_eq!(
+        from_json
+            .latency_buckets
+            .iter()
+            .map(|b| b.count)
+            .sum::<u64>(),
+        2
+    );
+    assert!(from_json.max_duration_us <= from_json.total_duration_us);
+    assert_eq!(snapshot.counters.get("input_bytes"), Some(&5));
+
+    let names: Vec<_> = snapshot.operations.iter().map(|o| &o.operation).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+
+    reset_metrics();
+    let after = get_metrics_snapshot();
+    assert!(after.operations.is_empty());
+    assert!(after.counters.is_empty());
+}
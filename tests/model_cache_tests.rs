@@ -0,0 +1,133 @@
+use rust_multiplatform_template_lib::{ModelCache, ModelFormat, TemplateError};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+fn write_model(dir: &tempfile::TempDir, name: &str, size: usize, age: Duration) {
+    let path = dir.path().join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, vec![0u8; size]).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() - age)
+        .unwrap();
+}
+
+fn open(dir: &tempfile::TempDir) -> ModelCache {
+    ModelCache::open(dir.path().to_string_lossy().into_owned()).unwrap()
+}
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[tokio::test]
+async fn test_list_reports_sizes_and_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    write_model(&dir, "b.gguf", 300, HOUR);
+    write_model(&dir, "llama/a.safetensors", 200, HOUR);
+    fs::write(dir.path().join("notes.txt"), "not a model").unwrap();
+
+    let models = open(&dir).list().await.unwrap();
+    let summary: Vec<_> = models
+        .iter()
+        .map(|m| (m.id.as_str(), m.size_bytes, m.format, m.pinned))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("b.gguf", 300, ModelFormat::Gguf, false),
+            ("llama/a.safetensors", 200, ModelFormat::Safetensors, false),
+        ]
+    );
+    assert!(models.iter().all(|m| m.last_used_ms.is_some()));
+}
+
+#[tokio::test]
+async fn test_prune_to_budget_removes_least_recently_used_unpinned() {
+    let dir = tempfile::tempdir().unwrap();
+    write_model(&dir, "old.gguf", 100, HOUR * 3);
+    write_model(&dir, "older.gguf", 100, HOUR * 4);
+    write_model(&dir, "oldest.gguf", 100, HOUR * 5);
+    write_model(&dir, "new.gguf", 100, HOUR);
+    let cache = open(&dir);
+    cache.pin("oldest.gguf".to_string()).unwrap();
+    cache.mark_used("older.gguf".to_string()).unwrap();
+
+    let report = cache.prune(Some(250), None).await.unwrap();
+    assert_eq!(report.removed, vec!["old.gguf", "new.gguf"]);
+    assert_eq!(report.freed_bytes, 200);
+
+    let remaining: Vec<_> = cache
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(remaining, vec!["older.gguf", "oldest.gguf"]);
+
+    // Only the pinned model would be left to remove
+    let report = cache.prune(Some(0), None).await.unwrap();
+    assert_eq!(report.removed, vec!["older.gguf"]);
+    assert!(dir.path().join("oldest.gguf").exists());
+}
+
+#[tokio::test]
+async fn test_prune_by_age() {
+    let dir = tempfile::tempdir().unwrap();
+    write_model(&dir, "stale.gguf", 10, HOUR * 48);
+    write_model(&dir, "fresh.gguf", 10, HOUR);
+    let cache = open(&dir);
+
+    let report = cache.prune(None, Some(24 * 3600 * 1000)).await.unwrap();
+    assert_eq!(report.removed, vec!["stale.gguf"]);
+    assert!(cache.prune(None, None).await.unwrap().removed.is_empty());
+}
+
+#[tokio::test]
+async fn test_pins_and_usage_survive_reopen() {
+    let dir = tempfile::tempdir().unwrap();
+    write_model(&dir, "a.gguf", 10, HOUR * 48);
+    let cache = open(&dir);
+    cache.pin("a.gguf".to_string()).unwrap();
+    cache.mark_used("a.gguf".to_string()).unwrap();
+    drop(cache);
+
+    let model = open(&dir).list().await.unwrap().remove(0);
+    assert!(model.pinned);
+    let now_ms = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    assert!(model.last_used_ms.unwrap() > now_ms - 60_000);
+
+    let cache = open(&dir);
+    cache.unpin("a.gguf".to_string()).unwrap();
+    assert!(!cache.list().await.unwrap()[0].pinned);
+}
+
+#[tokio::test]
+async fn test_delete_and_invalid_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    write_model(&dir, "a.gguf", 10, HOUR);
+    let cache = open(&dir);
+    cache.pin("a.gguf".to_string()).unwrap();
+
+    assert!(cache.delete("a.gguf".to_string()).unwrap());
+    assert!(!cache.delete("a.gguf".to_string()).unwrap());
+    assert!(cache.list().await.unwrap().is_empty());
+
+    assert!(matches!(
+        cache.pin("missing.gguf".to_string()),
+        Err(TemplateError::ModelNotFound { .. })
+    ));
+    for id in ["../a.gguf", "/etc/a.gguf", "notes.txt", ""] {
+        assert!(
+            matches!(
+                cache.delete(id.to_string()),
+                Err(TemplateError::InvalidInput { .. })
+            ),
+            "{id}"
+        );
+    }
+}
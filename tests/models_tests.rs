@@ -0,0 +1,250 @@
+use rust_multiplatform_template_lib::{
+    discover_models, load_model_metadata, CancellationToken, ModelFormat, TemplateError,
+};
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+
+fn gguf_string(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn write_gguf(path: &Path) {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"GGUF");
+    buf.extend_from_slice(&3u32.to_le_bytes());
+    buf.extend_from_slice(&2u64.to_le_bytes()); // tensor count
+    buf.extend_from_slice(&3u64.to_le_bytes()); // metadata count
+
+    // u32 value that must be skipped
+    gguf_string(&mut buf, "general.alignment");
+    buf.extend_from_slice(&4u32.to_le_bytes());
+    buf.extend_from_slice(&32u32.to_le_bytes());
+
+    gguf_string(&mut buf, "general.architecture");
+    buf.extend_from_slice(&8u32.to_le_bytes());
+    gguf_string(&mut buf, "llama");
+
+    gguf_string(&mut buf, "general.name");
+    buf.extend_from_slice(&8u32.to_le_bytes());
+    gguf_string(&mut buf, "Tiny Llama");
+
+    fs::write(path, buf).unwrap();
+}
+
+fn write_safetensors(path: &Path) {
+    let header =
+        r#"{"__metadata__":{"name":"tiny"},"a":{"dtype":"F32","shape":[1],"data_offsets":[0,4]}}"#;
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(&[0u8; 4]);
+    fs::write(path, buf).unwrap();
+}
+
+#[tokio::test]
+async fn test_discover_models_parses_headers() {
+    let dir = tempfile::tempdir().unwrap();
+    write_gguf(&dir.path().join("a.gguf"));
+    fs::create_dir(dir.path().join("nested")).unwrap();
+    write_safetensors(&dir.path().join("nested").join("b.safetensors"));
+    fs::write(dir.path().join("notes.txt"), "not a model").unwrap();
+
+    let models = discover_models(dir.path().to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+    assert_eq!(models.len(), 2);
+
+    let gguf = &models[0];
+    assert_eq!(gguf.file_name, "a.gguf");
+    assert_eq!(gguf.format, ModelFormat::Gguf);
+    assert!(gguf.is_valid);
+    let metadata = gguf.metadata.as_ref().unwrap();
+    assert_eq!(metadata.version, 3);
+    assert_eq!(metadata.tensor_count, 2);
+    assert_eq!(metadata.metadata_count, 3);
+    assert_eq!(metadata.architecture.as_deref(), Some("llama"));
+    assert_eq!(metadata.name.as_deref(), Some("Tiny Llama"));
+
+    let safetensors = &models[1];
+    assert_eq!(safetensors.format, ModelFormat::Safetensors);
+    assert!(safetensors.is_valid);
+    let metadata = safetensors.metadata.as_ref().unwrap();
+    assert_eq!(metadata.tensor_count, 1);
+    assert_eq!(metadata.metadata_count, 1);
+    assert_eq!(metadata.name.as_deref(), Some("tiny"));
+}
+
+#[tokio::test]
+async fn test_discover_models_reports_invalid_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("broken.gguf"), b"NOPE").unwrap();
+
+    let models = discover_models(dir.path().to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+    assert_eq!(models.len(), 1);
+    assert!(!models[0].is_valid);
+    assert!(models[0].metadata.is_none());
+    assert!(models[0].error_message.is_some());
+}
+
+#[tokio::test]
+async fn test_discover_models_missing_directory() {
+    let result = discover_models("/definitely/not/a/real/dir".to_string(), None).await;
+    match result {
+        Err(TemplateError::IoError { path, .. }) => {
+            assert_eq!(path, "/definitely/not/a/real/dir");
+        }
+        _ => panic!("Expected IoError"),
+    }
+}
+
+#[tokio::test]
+async fn test_discover_models_with_cancellation() {
+    let dir = tempfile::tempdir().unwrap();
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+
+    let result = discover_models(dir.path().to_string_lossy().into_owned(), Some(token)).await;
+    match result {
+        Err(TemplateError::OperationCancelled { operation }) => {
+            assert_eq!(operation, "discover_models");
+        }
+        _ => panic!("Expected OperationCancelled error"),
+    }
+}
+
+#[tokio::test]
+async fn test_dropping_discover_models_leaves_caller_token_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    write_gguf(&dir.path().join("a.gguf"));
+    let token = Arc::new(CancellationToken::new());
+
+    // Poll once, then drop the in-flight future as a cancelled host task would
+    let mut future = Box::pin(discover_models(
+        dir.path().to_string_lossy().into_owned(),
+        Some(token.clone()),
+    ));
+    std::future::poll_fn(|cx| {
+        assert!(future.as_mut().poll(cx).is_pending());
+        std::task::Poll::Ready(())
+    })
+    .await;
+    drop(future);
+    assert!(!token.is_cancelled());
+
+    // The same token still works for later calls
+    let models = discover_models(dir.path().to_string_lossy().into_owned(), Some(token))
+        .await
+        .unwrap();
+    assert_eq!(models.len(), 1);
+}
+
+#[tokio::test]
+async fn test_load_model_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.gguf");
+    write_gguf(&path);
+
+    let metadata = load_model_metadata(path.to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+    assert_eq!(metadata.architecture.as_deref(), Some("llama"));
+}
+
+#[tokio::test]
+async fn test_load_model_metadata_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path_of = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+    assert!(matches!(
+        load_model_metadata(path_of("missing.gguf"), None).await,
+        Err(TemplateError::ModelNotFound { .. })
+    ));
+
+    fs::write(dir.path().join("notes.txt"), "text").unwrap();
+    assert!(matches!(
+        load_model_metadata(path_of("notes.txt"), None).await,
+        Err(TemplateError::InvalidModelFormat { .. })
+    ));
+
+    fs::write(dir.path().join("broken.safetensors"), b"\x01").unwrap();
+    match load_model_metadata(path_of("broken.safetensors"), None).await {
+        Err(TemplateError::ModelLoadError {
+            path,
+            error_message,
+            debug_info,
+        }) => {
+            assert!(path.ends_with("broken.safetensors"));
+            assert!(!error_message.is_empty());
+            #[cfg(not(feature = "debug-errors"))]
+            assert_eq!(debug_info, None);
+            #[cfg(feature = "debug-errors")]
+            {
+                let debug_info = debug_info.unwrap();
+                assert!(debug_info.contains("breadcrumbs:\n  read_header"));
+                assert!(debug_info.contains("open "));
+                assert!(debug_info.contains("backtrace:"));
+            }
+        }
+        other => panic!("Expected ModelLoadError, got {:?}", other),
+    }
+}
+
+/// A GGUF header whose only metadata value is `value` of `value_type`
+fn write_gguf_value(path: &Path, value_type: u32, value: &[u8]) {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"GGUF");
+    buf.extend_from_slice(&3u32.to_le_bytes());
+    buf.extend_from_slice(&0u64.to_le_bytes()); // tensor count
+    buf.extend_from_slice(&1u64.to_le_bytes()); // metadata count
+    gguf_string(&mut buf, "general.tags");
+    buf.extend_from_slice(&value_type.to_le_bytes());
+    buf.extend_from_slice(value);
+    fs::write(path, buf).unwrap();
+}
+
+#[tokio::test]
+async fn test_load_model_metadata_rejects_hostile_arrays() {
+    let dir = tempfile::tempdir().unwrap();
+    let load_error = |name: &str| {
+        let path = dir.path().join(name).to_string_lossy().into_owned();
+        async move {
+            match load_model_metadata(path, None).await {
+                Err(TemplateError::ModelLoadError { error_message, .. }) => error_message,
+                other => panic!("Expected ModelLoadError, got {:?}", other),
+            }
+        }
+    };
+
+    // Arrays of arrays, far deeper than any real model nests them
+    let mut nested = Vec::new();
+    for _ in 0..10_000 {
+        nested.extend_from_slice(&9u32.to_le_bytes());
+        nested.extend_from_slice(&1u64.to_le_bytes());
+    }
+    write_gguf_value(&dir.path().join("nested.gguf"), 9, &nested);
+    assert!(load_error("nested.gguf").await.contains("nested"));
+
+    // An array claiming more strings than the file could hold
+    let mut strings = 8u32.to_le_bytes().to_vec();
+    strings.extend_from_slice(&u64::MAX.to_le_bytes());
+    write_gguf_value(&dir.path().join("strings.gguf"), 9, &strings);
+    assert!(load_error("strings.gguf").await.contains("overruns"));
+
+    // Two levels of nesting are still read
+    let mut shallow = 9u32.to_le_bytes().to_vec();
+    shallow.extend_from_slice(&1u64.to_le_bytes());
+    shallow.extend_from_slice(&8u32.to_le_bytes());
+    shallow.extend_from_slice(&1u64.to_le_bytes());
+    gguf_string(&mut shallow, "chat");
+    write_gguf_value(&dir.path().join("shallow.gguf"), 9, &shallow);
+    let path = dir.path().join("shallow.gguf");
+    let metadata = load_model_metadata(path.to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+    assert_eq!(metadata.metadata_count, 1);
+}
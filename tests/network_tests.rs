@@ -0,0 +1,156 @@
+use rust_multiplatform_template_lib::{
+    certificate_pin, classify_error, get_network_config, get_network_status, localize_error,
+    set_network_config, set_network_status, ErrorKind, NetworkConfig, NetworkStatus, TemplateError,
+};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{http_request, HttpMethod, HttpRequest};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+
+/// Self-signed P-256 certificate for `pinned.example.com`
+const CERTIFICATE_HEX: &str = "\
+    3082019030820135a00302010202147f1ada123835c6197f86ddf3a2df7d918ff83e88300a06082a8648ce3d04030230\
+    1d311b301906035504030c1270696e6e65642e6578616d706c652e636f6d301e170d3236313031363038303333395a17\
+    0d3336313031333038303333395a301d311b301906035504030c1270696e6e65642e6578616d706c652e636f6d305930\
+    1306072a8648ce3d020106082a8648ce3d03010703420004e98be864e988b62dd4d71566ca551e0b2f81b2b3cbe82a22\
+    f5ae09962d834d08c9e018a064a8b173cd7ec26d6c007d4249cbbce8388f458bcc4de3670ada53f8a3533051301d0603\
+    551d0e041604145fc99cd07dedb8929d2037999771f55f3a073e7e301f0603551d230418301680145fc99cd07dedb892\
+    9d2037999771f55f3a073e7e300f0603551d130101ff040530030101ff300a06082a8648ce3d04030203490030460221\
+    009eeb903a907ca2b45abe6468d71ebdc2cde11402aa62f569d0aedf14776c58d0022100dc661489ccd6e198768396ea\
+    803da0140953720f95ec4d65190838380c06a87b";
+
+/// Pin of the certificate's public key, from
+/// `openssl x509 -pubkey | openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64`
+const CERTIFICATE_PIN: &str = "sha256/fBsluwDChDSncbABTEV/I9eT96jo2E50sLgJWcvY++U=";
+
+fn pins(entries: &[(&str, &[&str])]) -> NetworkConfig {
+    NetworkConfig {
+        pinned_public_keys: entries
+            .iter()
+            .map(|(host, pins)| {
+                (
+                    host.to_string(),
+                    pins.iter().map(|pin| pin.to_string()).collect(),
+                )
+            })
+            .collect(),
+        ..NetworkConfig::default()
+    }
+}
+
+#[test]
+fn test_certificate_pin() {
+    let certificate = hex::decode(CERTIFICATE_HEX).unwrap();
+    assert_eq!(
+        certificate_pin(certificate.clone()).unwrap(),
+        CERTIFICATE_PIN
+    );
+    for invalid in [
+        Vec::new(),
+        b"not a certificate".to_vec(),
+        certificate[..200].to_vec(),
+    ] {
+        assert!(matches!(
+            certificate_pin(invalid),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_pinning_failure_error() {
+    let error = TemplateError::pinning_failure("api.example.com", "no pinned key".to_string());
+    assert_eq!(
+        error.to_string(),
+        "Certificate pinning failed for api.example.com: no pinned key"
+    );
+    assert_eq!(error.kind(), ErrorKind::PinningFailure);
+    assert_eq!(error.kind().code(), "PINNING_FAILURE");
+    let classification = classify_error(error.clone());
+    assert!(!classification.retryable && !classification.transient);
+    let localized = localize_error(error);
+    assert_eq!(localized.key, "template.error.pinning_failure");
+    assert_eq!(localized.params["host"], "api.example.com");
+}
+
+// The network settings are process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_network_config_lifecycle() {
+    assert_eq!(get_network_config(), NetworkConfig::default());
+
+    let backup = "sha256/47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+    let config = pins(&[
+        ("api.example.com", &[CERTIFICATE_PIN, backup]),
+        (
+            "*.cdn.example.com",
+            &["fBsluwDChDSncbABTEV/I9eT96jo2E50sLgJWcvY++U="],
+        ),
+    ]);
+    set_network_config(config.clone()).unwrap();
+    assert_eq!(get_network_config(), config);
+
+    for invalid in [
+        pins(&[("api.example.com", &[])]),
+        pins(&[("api.example.com", &["sha256/not base64"])]),
+        pins(&[("api.example.com", &["sha256/AAAA"])]),
+        pins(&[("", &[CERTIFICATE_PIN])]),
+        pins(&[("*.", &[CERTIFICATE_PIN])]),
+        pins(&[("api.*.example.com", &[CERTIFICATE_PIN])]),
+        pins(&[("https://api.example.com", &[CERTIFICATE_PIN])]),
+    ] {
+        assert!(matches!(
+            set_network_config(invalid),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert_eq!(get_network_config(), config);
+
+    // Pins only apply to the hosts they name: other requests still go
+    // through the client rebuilt with the pinning verifier
+    #[cfg(feature = "http")]
+    {
+        let response = http_request(get(&serve()), None).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+    }
+
+    set_network_config(NetworkConfig::default()).unwrap();
+    assert!(get_network_config().pinned_public_keys.is_empty());
+}
+
+#[test]
+fn test_network_status() {
+    assert_eq!(get_network_status(), NetworkStatus::Unknown);
+    for status in [
+        NetworkStatus::Offline,
+        NetworkStatus::Cellular,
+        NetworkStatus::Cellular,
+        NetworkStatus::Wifi,
+    ] {
+        set_network_status(status);
+        assert_eq!(get_network_status(), status);
+    }
+    set_network_status(NetworkStatus::Unknown);
+}
+
+/// Answers every request with `200 ok`
+#[cfg(feature = "http")]
+fn serve() -> String {
+    let base = common::serve(|_, mut stream| common::respond(&mut stream, "200 OK", "", b"ok"));
+    format!("{}/", base)
+}
+
+#[cfg(feature = "http")]
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers: HashMap::new(),
+        body: None,
+        timeout_ms: Some(5_000),
+    }
+}
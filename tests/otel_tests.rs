@@ -0,0 +1,53 @@
+use rust_multiplatform_template_lib::{
+    disable_otel_export, enable_otel_export, OtelConfig, TemplateError,
+};
+use std::collections::HashMap;
+
+fn config(endpoint: &str) -> OtelConfig {
+    OtelConfig {
+        endpoint: endpoint.to_string(),
+        service_name: "template-tests".to_string(),
+        platform: "test".to_string(),
+        headers: HashMap::from([("x-api-key".to_string(), "secret".to_string())]),
+        metrics_interval_ms: 60_000,
+    }
+}
+
+#[test]
+fn test_rejects_zero_metrics_interval() {
+    let result = enable_otel_export(OtelConfig {
+        metrics_interval_ms: 0,
+        ..config("http://127.0.0.1:4318")
+    });
+    assert!(matches!(result, Err(TemplateError::InvalidInput { .. })));
+}
+
+#[cfg(not(feature = "otel"))]
+#[test]
+fn test_export_requires_feature() {
+    match enable_otel_export(config("http://127.0.0.1:4318")) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("otel"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other),
+    }
+    disable_otel_export();
+}
+
+// Export is process-wide, so these checks run in one test
+#[cfg(feature = "otel")]
+#[test]
+fn test_export_lifecycle() {
+    assert!(matches!(
+        enable_otel_export(config("http://bad host")),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    // Nothing listens on the discard port; failed exports are dropped
+    enable_otel_export(config("http://127.0.0.1:9/")).unwrap();
+    rust_multiplatform_template_lib::TemplateConfig::from_json("{}".to_string()).unwrap();
+    rust_multiplatform_template_lib::TemplateConfig::from_json("not json".to_string()).unwrap_err();
+    enable_otel_export(config("http://127.0.0.1:9")).unwrap();
+    disable_otel_export();
+    disable_otel_export();
+}
@@ -0,0 +1,424 @@
+use rust_multiplatform_template_lib::{Outbox, OutboxOptions, TemplateError};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    set_network_status, HttpMethod, HttpRequest, HttpResponse, NetworkPolicy, NetworkStatus,
+    OutboxDropReason, OutboxEntry, OutboxListener,
+};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_outbox_requires_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("outbox.json")
+        .to_string_lossy()
+        .into_owned();
+    match Outbox::new(path, OutboxOptions::default()) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        Err(e) => panic!("Expected InvalidInput, got {:?}", e),
+        Ok(_) => panic!("Expected InvalidInput, got an outbox"),
+    }
+}
+
+/// Answers 503 while offline and to the first `failures` requests; then
+/// `/reject` with 400 and anything else with 200. Records every body received.
+#[cfg(feature = "http")]
+struct Server {
+    base: String,
+    online: Arc<AtomicBool>,
+    bodies: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[cfg(feature = "http")]
+impl Server {
+    fn start(failures: usize) -> Self {
+        let online = Arc::new(AtomicBool::new(true));
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let remaining = Arc::new(AtomicUsize::new(failures));
+        let (is_online, recorded) = (online.clone(), bodies.clone());
+        let base = common::serve(move |request, mut stream| {
+            let fail = !is_online.load(Ordering::SeqCst)
+                || remaining
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok();
+            let status = if fail {
+                "503 Service Unavailable"
+            } else if request.path == "/reject" {
+                "400 Bad Request"
+            } else {
+                "200 OK"
+            };
+            recorded.lock().unwrap().push(request.body);
+            common::respond(&mut stream, status, "", b"ok");
+        });
+        Self {
+            base,
+            online,
+            bodies,
+        }
+    }
+
+    fn post(&self, path: &str, body: &[u8]) -> HttpRequest {
+        HttpRequest {
+            url: format!("{}{}", self.base, path),
+            method: HttpMethod::Post,
+            headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
+            body: Some(body.to_vec()),
+            timeout_ms: Some(5_000),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Default)]
+struct Recorded {
+    delivered: Vec<(OutboxEntry, HttpResponse)>,
+    dropped: Vec<(OutboxEntry, OutboxDropReason)>,
+}
+
+#[cfg(feature = "http")]
+struct RecordingListener(Arc<Mutex<Recorded>>);
+
+#[cfg(feature = "http")]
+impl OutboxListener for RecordingListener {
+    fn on_outbox_delivered(&self, entry: OutboxEntry, response: HttpResponse) {
+        self.0.lock().unwrap().delivered.push((entry, response));
+    }
+
+    fn on_outbox_dropped(&self, entry: OutboxEntry, reason: OutboxDropReason) {
+        self.0.lock().unwrap().dropped.push((entry, reason));
+    }
+}
+
+#[cfg(feature = "http")]
+fn listen(outbox: &Outbox) -> Arc<Mutex<Recorded>> {
+    let recorded = Arc::new(Mutex::new(Recorded::default()));
+    outbox.set_listener(Box::new(RecordingListener(recorded.clone())));
+    recorded
+}
+
+#[cfg(feature = "http")]
+fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(feature = "http")]
+fn wait_idle(outbox: &Outbox) {
+    tokio_test::block_on(outbox.wait_idle());
+}
+
+#[cfg(feature = "http")]
+fn persist_path(dir: &tempfile::TempDir) -> String {
+    dir.path()
+        .join("outbox.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Backoff short enough for tests to wait out
+#[cfg(feature = "http")]
+fn fast() -> OutboxOptions {
+    OutboxOptions {
+        base_delay_ms: 10,
+        max_delay_ms: 50,
+        ..OutboxOptions::default()
+    }
+}
+
+/// Backoff long enough that entries wait for `retry_now`
+#[cfg(feature = "http")]
+fn slow() -> OutboxOptions {
+    OutboxOptions {
+        base_delay_ms: 60_000,
+        max_delay_ms: 60_000,
+        ..OutboxOptions::default()
+    }
+}
+
+/// Waits until the first attempt at every entry has failed
+#[cfg(feature = "http")]
+fn wait_for_first_attempts(outbox: &Outbox) {
+    wait_until(|| {
+        outbox
+            .entries()
+            .iter()
+            .all(|entry| entry.attempts >= 1 && entry.last_error.is_some())
+    });
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_failed_requests_are_retried_until_delivered() {
+    let server = Server::start(2);
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(persist_path(&dir), fast()).unwrap();
+    let recorded = listen(&outbox);
+
+    let id = outbox
+        .enqueue(server.post("/events", br#"{"event":"launch"}"#))
+        .unwrap();
+    wait_idle(&outbox);
+
+    assert!(outbox.entries().is_empty());
+    assert_eq!(
+        *server.bodies.lock().unwrap(),
+        vec![br#"{"event":"launch"}"#.to_vec(); 3]
+    );
+    let recorded = recorded.lock().unwrap();
+    assert!(recorded.dropped.is_empty());
+    let (entry, response) = &recorded.delivered[0];
+    assert_eq!(entry.id, id);
+    assert_eq!(entry.method, HttpMethod::Post);
+    assert_eq!(entry.attempts, 3);
+    assert!(entry.last_error.as_deref().unwrap().contains("503"));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"ok");
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_rejected_and_exhausted_entries_are_dropped() {
+    let server = Server::start(0);
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(
+        persist_path(&dir),
+        OutboxOptions {
+            max_attempts: 2,
+            ..fast()
+        },
+    )
+    .unwrap();
+    let recorded = listen(&outbox);
+
+    let rejected = outbox.enqueue(server.post("/reject", b"{}")).unwrap();
+    wait_idle(&outbox);
+    server.online.store(false, Ordering::SeqCst);
+    let exhausted = outbox.enqueue(server.post("/events", b"{}")).unwrap();
+    wait_idle(&outbox);
+
+    let recorded = recorded.lock().unwrap();
+    assert!(recorded.delivered.is_empty());
+    let dropped: Vec<(u64, u32, OutboxDropReason)> = recorded
+        .dropped
+        .iter()
+        .map(|(entry, reason)| (entry.id, entry.attempts, *reason))
+        .collect();
+    assert_eq!(
+        dropped,
+        [
+            (rejected, 1, OutboxDropReason::Rejected),
+            (exhausted, 2, OutboxDropReason::AttemptsExhausted)
+        ]
+    );
+    assert!(recorded.dropped[0]
+        .0
+        .last_error
+        .as_deref()
+        .unwrap()
+        .contains("400"));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_entries_survive_restart() {
+    let server = Server::start(0);
+    server.online.store(false, Ordering::SeqCst);
+    let dir = tempfile::tempdir().unwrap();
+    let body = vec![0u8, 159, 146, 150, 255];
+
+    let id = {
+        let outbox = Outbox::new(persist_path(&dir), slow()).unwrap();
+        let id = outbox.enqueue(server.post("/sync", &body)).unwrap();
+        wait_for_first_attempts(&outbox);
+        id
+    };
+
+    // Entries picked up are sent right away, whatever their backoff was
+    server.online.store(true, Ordering::SeqCst);
+    let outbox = Outbox::new(persist_path(&dir), slow()).unwrap();
+    let recorded = listen(&outbox);
+    wait_idle(&outbox);
+    assert_eq!(server.bodies.lock().unwrap().last().unwrap(), &body);
+    let (entry, _) = &recorded.lock().unwrap().delivered[0];
+    assert_eq!(entry.id, id);
+    assert_eq!(entry.url, format!("{}/sync", server.base));
+    assert_eq!(entry.attempts, 2);
+    drop(outbox);
+
+    let outbox = Outbox::new(persist_path(&dir), slow()).unwrap();
+    assert!(outbox.entries().is_empty());
+    assert!(outbox.enqueue(server.post("/sync", &body)).unwrap() > id);
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_retry_now_remove_and_limits() {
+    let server = Server::start(0);
+    server.online.store(false, Ordering::SeqCst);
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(
+        persist_path(&dir),
+        OutboxOptions {
+            max_entries: 2,
+            ..slow()
+        },
+    )
+    .unwrap();
+    let recorded = listen(&outbox);
+
+    let first = outbox.enqueue(server.post("/events", b"1")).unwrap();
+    wait_for_first_attempts(&outbox);
+    let second = outbox.enqueue(server.post("/events", b"2")).unwrap();
+    let third = outbox.enqueue(server.post("/events", b"3")).unwrap();
+    let ids: Vec<u64> = outbox.entries().iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, [second, third]);
+    let (entry, reason) = recorded.lock().unwrap().dropped[0].clone();
+    assert_eq!(reason, OutboxDropReason::Overflow);
+    assert_eq!(entry.id, first);
+    assert_eq!(entry.attempts, 1);
+    assert!(entry.last_error.unwrap().contains("503"));
+
+    wait_for_first_attempts(&outbox);
+    assert!(outbox.remove(second));
+    assert!(!outbox.remove(second));
+    server.online.store(true, Ordering::SeqCst);
+    outbox.retry_now();
+    wait_idle(&outbox);
+    let delivered: Vec<u64> = recorded
+        .lock()
+        .unwrap()
+        .delivered
+        .iter()
+        .map(|(entry, _)| entry.id)
+        .collect();
+    assert_eq!(delivered, [third]);
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_old_entries_expire() {
+    let server = Server::start(0);
+    server.online.store(false, Ordering::SeqCst);
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(
+        persist_path(&dir),
+        OutboxOptions {
+            max_age_ms: Some(200),
+            ..slow()
+        },
+    )
+    .unwrap();
+    let recorded = listen(&outbox);
+
+    let id = outbox.enqueue(server.post("/events", b"{}")).unwrap();
+    wait_idle(&outbox);
+    assert_eq!(recorded.lock().unwrap().dropped[0].0.id, id);
+    assert_eq!(
+        recorded.lock().unwrap().dropped[0].1,
+        OutboxDropReason::Expired
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_invalid_outboxes_and_requests_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    for options in [
+        OutboxOptions {
+            max_entries: 0,
+            ..OutboxOptions::default()
+        },
+        OutboxOptions {
+            base_delay_ms: 10_000,
+            max_delay_ms: 1_000,
+            ..OutboxOptions::default()
+        },
+    ] {
+        assert!(matches!(
+            Outbox::new(persist_path(&dir), options),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+
+    std::fs::write(persist_path(&dir), b"not json").unwrap();
+    assert!(matches!(
+        Outbox::new(persist_path(&dir), OutboxOptions::default()),
+        Err(TemplateError::ParseError { .. })
+    ));
+    std::fs::remove_file(persist_path(&dir)).unwrap();
+
+    let outbox = Outbox::new(persist_path(&dir), OutboxOptions::default()).unwrap();
+    let server = Server::start(0);
+    let mut request = server.post("/events", b"{}");
+    request.url = "not a url".to_string();
+    assert!(matches!(
+        outbox.enqueue(request),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    let mut request = server.post("/events", b"{}");
+    request
+        .headers
+        .insert("bad header".to_string(), "x".to_string());
+    assert!(matches!(
+        outbox.enqueue(request),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(outbox.entries().is_empty());
+}
+
+// The network status is process-wide: only this test changes it, and only to
+// statuses the `Any` policy of the other tests allows
+#[cfg(feature = "http")]
+#[test]
+fn test_entries_wait_for_network_and_retry_when_it_changes() {
+    let server = Server::start(0);
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(
+        persist_path(&dir),
+        OutboxOptions {
+            network_policy: NetworkPolicy::Unmetered,
+            ..slow()
+        },
+    )
+    .unwrap();
+    let recorded = listen(&outbox);
+
+    set_network_status(NetworkStatus::Cellular);
+    outbox.enqueue(server.post("/sync", b"{}")).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(outbox.entries()[0].attempts, 0);
+    assert!(server.bodies.lock().unwrap().is_empty());
+
+    server.online.store(false, Ordering::SeqCst);
+    set_network_status(NetworkStatus::Wifi);
+    wait_for_first_attempts(&outbox);
+
+    // Back on a usable network, the entry is retried without waiting out
+    // its minute-long backoff
+    server.online.store(true, Ordering::SeqCst);
+    set_network_status(NetworkStatus::Cellular);
+    set_network_status(NetworkStatus::Wifi);
+    wait_idle(&outbox);
+    assert_eq!(recorded.lock().unwrap().delivered[0].0.attempts, 2);
+}
@@ -0,0 +1,126 @@
+use rust_multiplatform_template_lib::{
+    KvStore, KvValue, Preferences, PreferencesListener, TemplateError, PREFERENCES_POLL_INTERVAL_MS,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct RecordingListener(Arc<Mutex<Vec<String>>>);
+
+impl PreferencesListener for RecordingListener {
+    fn on_preference_changed(&self, key: String) {
+        self.0.lock().unwrap().push(key);
+    }
+}
+
+fn take(keys: &Mutex<Vec<String>>) -> Vec<String> {
+    std::mem::take(&mut *keys.lock().unwrap())
+}
+
+fn path(dir: &tempfile::TempDir) -> String {
+    dir.path().join("prefs.json").to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_defaults_apply_until_set() {
+    let dir = tempfile::tempdir().unwrap();
+    let prefs = Preferences::open(path(&dir)).unwrap();
+    prefs.register_defaults(HashMap::from([
+        (
+            "theme".to_string(),
+            KvValue::String {
+                value: "system".to_string(),
+            },
+        ),
+        ("volume".to_string(), KvValue::Int { value: 5 }),
+    ]));
+
+    assert_eq!(
+        prefs.get_string("theme".to_string()).unwrap().as_deref(),
+        Some("system")
+    );
+    assert!(!prefs.contains("theme".to_string()));
+    assert!(prefs.keys().is_empty());
+
+    prefs
+        .set_string("theme".to_string(), "dark".to_string())
+        .unwrap();
+    assert_eq!(
+        prefs.get_string("theme".to_string()).unwrap().as_deref(),
+        Some("dark")
+    );
+    assert_eq!(prefs.keys(), vec!["theme"]);
+
+    prefs.remove("theme".to_string()).unwrap();
+    assert_eq!(
+        prefs.get_string("theme".to_string()).unwrap().as_deref(),
+        Some("system")
+    );
+
+    assert_eq!(prefs.get_int("volume".to_string()).unwrap(), Some(5));
+    assert_eq!(prefs.get_bool("missing".to_string()).unwrap(), None);
+    assert!(matches!(
+        prefs.get_bool("volume".to_string()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_listeners_notified_of_changes_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let prefs = Preferences::open(path(&dir)).unwrap();
+    let keys = Arc::new(Mutex::new(Vec::new()));
+    let id = prefs.add_listener(Box::new(RecordingListener(keys.clone())));
+
+    prefs.set_bool("sync".to_string(), true).unwrap();
+    prefs.set_bool("sync".to_string(), true).unwrap();
+    prefs.set_int("count".to_string(), 1).unwrap();
+    prefs.remove("sync".to_string()).unwrap();
+    prefs.remove("never_set".to_string()).unwrap();
+    assert_eq!(take(&keys), vec!["sync", "count", "sync"]);
+
+    assert!(prefs.remove_listener(id));
+    assert!(!prefs.remove_listener(id));
+    prefs.set_int("count".to_string(), 2).unwrap();
+    assert!(take(&keys).is_empty());
+}
+
+#[test]
+fn test_reload_picks_up_outside_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let prefs = Preferences::open(path(&dir)).unwrap();
+    prefs.set_int("a".to_string(), 1).unwrap();
+    prefs.set_int("b".to_string(), 1).unwrap();
+
+    let other = KvStore::open(path(&dir)).unwrap();
+    other.set_int("a".to_string(), 2).unwrap();
+    other.delete("b".to_string()).unwrap();
+    other.set_bool("c".to_string(), true).unwrap();
+
+    assert_eq!(prefs.reload().unwrap(), vec!["a", "b", "c"]);
+    assert_eq!(prefs.get_int("a".to_string()).unwrap(), Some(2));
+    assert!(prefs.reload().unwrap().is_empty());
+}
+
+#[test]
+fn test_watcher_notifies_changes_from_another_instance() {
+    let dir = tempfile::tempdir().unwrap();
+    let prefs = Preferences::open(path(&dir)).unwrap();
+    let keys = Arc::new(Mutex::new(Vec::new()));
+    prefs.add_listener(Box::new(RecordingListener(keys.clone())));
+
+    let other = Preferences::open(path(&dir)).unwrap();
+    other
+        .set_string("theme".to_string(), "dark".to_string())
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_millis(PREFERENCES_POLL_INTERVAL_MS * 10);
+    while keys.lock().unwrap().is_empty() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(take(&keys), vec!["theme"]);
+    assert_eq!(
+        prefs.get_string("theme".to_string()).unwrap().as_deref(),
+        Some("dark")
+    );
+}
@@ -0,0 +1,167 @@
+use rust_multiplatform_template_lib::{
+    decode_protobuf, echo, encode_protobuf, error_to_protobuf, CallTiming, ChatMessage, ChatRole,
+    DiscoveredModel, EchoResult, GenerationParams, GenerationResult, ModelFormat, ModelMetadata,
+    Record, SanitizationReport, TemplateError, TextTransform, Warning, WarningKind,
+};
+
+fn metadata() -> ModelMetadata {
+    ModelMetadata {
+        version: 3,
+        tensor_count: 291,
+        metadata_count: 24,
+        architecture: Some("llama".to_string()),
+        name: None,
+    }
+}
+
+fn assert_parse_error(data: &[u8]) {
+    match decode_protobuf(data.to_vec()) {
+        Err(TemplateError::ParseError { format, .. }) => assert_eq!(format, "Protobuf"),
+        other => panic!("Expected ParseError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_protobuf_round_trips_every_record() {
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let records = [
+        Record::EchoResult {
+            result: result.clone(),
+        },
+        Record::EchoResult {
+            result: EchoResult {
+                transforms_applied: vec![TextTransform::Trim, TextTransform::Reverse],
+                sanitization: Some(SanitizationReport {
+                    control_chars_removed: 2,
+                    bom_removed: true,
+                    line_endings_normalized: 0,
+                }),
+                warnings: vec![Warning {
+                    kind: WarningKind::InputNotNormalized,
+                    message: "not NFC".to_string(),
+                }],
+                timing: Some(CallTiming {
+                    queue_wait_us: 1,
+                    validation_us: 2,
+                    compute_us: 3,
+                    total_us: 7,
+                }),
+                ..result
+            },
+        },
+        Record::ModelMetadata {
+            metadata: metadata(),
+        },
+        Record::DiscoveredModel {
+            model: DiscoveredModel {
+                path: "/models/tiny.safetensors".to_string(),
+                file_name: "tiny.safetensors".to_string(),
+                size_bytes: 2_048,
+                format: ModelFormat::Safetensors,
+                is_valid: false,
+                metadata: None,
+                error_message: Some("truncated header".to_string()),
+            },
+        },
+        Record::GenerationParams {
+            params: GenerationParams {
+                max_tokens: Some(256),
+                top_p: Some(0.9),
+                stop: vec!["\n\n".to_string()],
+                ..GenerationParams::default()
+            },
+        },
+        Record::GenerationResult {
+            result: GenerationResult {
+                text: "Hi there".to_string(),
+                finish_reason: None,
+                model: "gpt-4o-mini".to_string(),
+                prompt_tokens: Some(0),
+                completion_tokens: Some(3),
+            },
+        },
+        Record::ChatMessages {
+            messages: vec![
+                ChatMessage {
+                    role: ChatRole::System,
+                    content: "Be brief".to_string(),
+                },
+                ChatMessage {
+                    role: ChatRole::Assistant,
+                    content: String::new(),
+                },
+            ],
+        },
+        Record::Bytes {
+            kind: "app/settings".to_string(),
+            data: vec![0, 1, 2, 255],
+        },
+    ];
+    for record in records {
+        assert_eq!(
+            decode_protobuf(encode_protobuf(record.clone())).unwrap(),
+            record
+        );
+    }
+}
+
+#[test]
+fn test_protobuf_wire_format_follows_schema() {
+    let encoded = encode_protobuf(Record::Bytes {
+        kind: "k".to_string(),
+        data: vec![9],
+    });
+    // Record.bytes (7) holding Bytes.kind (1) and Bytes.data (2)
+    assert_eq!(encoded, [0x3a, 0x06, 0x0a, 0x01, b'k', 0x12, 0x01, 0x09]);
+
+    let encoded = encode_protobuf(Record::ChatMessages {
+        messages: vec![ChatMessage {
+            role: ChatRole::User,
+            content: "hi".to_string(),
+        }],
+    });
+    // Record.chat_messages (6) > ChatMessages.messages (1) > role USER (2), content
+    assert_eq!(
+        encoded,
+        [0x32, 0x08, 0x0a, 0x06, 0x08, 0x02, 0x12, 0x02, b'h', b'i']
+    );
+}
+
+#[test]
+fn test_invalid_protobuf_is_rejected() {
+    // No record set
+    assert_parse_error(&[]);
+    // Truncated message
+    let encoded = encode_protobuf(Record::ModelMetadata {
+        metadata: metadata(),
+    });
+    assert_parse_error(&encoded[..encoded.len() - 3]);
+    // A chat message whose role is unspecified, and one from a newer schema
+    assert_parse_error(&[0x32, 0x04, 0x0a, 0x02, 0x08, 0x00]);
+    assert_parse_error(&[0x32, 0x04, 0x0a, 0x02, 0x08, 0x63]);
+}
+
+#[test]
+fn test_error_to_protobuf() {
+    let error = TemplateError::Timeout {
+        operation: "echo".to_string(),
+        timeout_ms: 500,
+    };
+    let encoded = error_to_protobuf(error.clone());
+    // Error.code (1) and Error.variant (2) come first
+    assert!(encoded.starts_with(b"\x0a\x07TIMEOUT\x12\x07Timeout\x1a"));
+    let message = error.to_string();
+    assert!(encoded
+        .windows(message.len())
+        .any(|window| window == message.as_bytes()));
+    let fields = br#"{"operation":"echo","timeout_ms":500}"#;
+    assert!(encoded.ends_with(fields));
+    assert_eq!(encoded[encoded.len() - fields.len() - 2], 0x22);
+
+    // Variants without fields have an empty object
+    let encoded = error_to_protobuf(TemplateError::AlreadyInitialized);
+    assert!(encoded.ends_with(b"\x22\x02{}"));
+}
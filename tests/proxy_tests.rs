@@ -0,0 +1,334 @@
+use rust_multiplatform_template_lib::{
+    get_network_config, set_network_config, NetworkConfig, ProxyConfig, ProxyKind, TemplateError,
+};
+
+#[cfg(feature = "http")]
+use futures_util::{SinkExt, StreamExt};
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    http_request, HttpMethod, HttpRequest, WebSocketClient, WebSocketListener, WebSocketOptions,
+    WebSocketState,
+};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "http")]
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+#[cfg(feature = "http")]
+use tokio::net::{TcpListener, TcpStream};
+
+/// The network settings are process-wide, so tests that change them take turns
+static NETWORK_CONFIG: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn proxy(kind: ProxyKind, port: u16) -> ProxyConfig {
+    ProxyConfig {
+        kind,
+        host: "127.0.0.1".to_string(),
+        port,
+        username: Some("user".to_string()),
+        password: Some("pass".to_string()),
+        bypass: Vec::new(),
+    }
+}
+
+fn with_proxy(proxy: ProxyConfig) -> NetworkConfig {
+    NetworkConfig {
+        proxy: Some(proxy),
+        ..NetworkConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_proxy_config_is_validated() {
+    let _turn = NETWORK_CONFIG.lock().await;
+    let config = with_proxy(ProxyConfig {
+        bypass: vec![
+            "localhost".to_string(),
+            "*.corp.example.com".to_string(),
+            "10.0.0.0/8".to_string(),
+            "[::1]".to_string(),
+        ],
+        ..proxy(ProxyKind::Socks5, 1080)
+    });
+    set_network_config(config.clone()).unwrap();
+    assert_eq!(get_network_config(), config);
+
+    let invalid = [
+        ProxyConfig {
+            host: " ".to_string(),
+            ..proxy(ProxyKind::Http, 8080)
+        },
+        ProxyConfig {
+            host: "user@proxy".to_string(),
+            ..proxy(ProxyKind::Http, 8080)
+        },
+        proxy(ProxyKind::Http, 0),
+        ProxyConfig {
+            username: None,
+            ..proxy(ProxyKind::Http, 8080)
+        },
+        ProxyConfig {
+            bypass: vec!["10.0.0.0/33".to_string()],
+            ..proxy(ProxyKind::Http, 8080)
+        },
+        ProxyConfig {
+            bypass: vec!["not a host".to_string()],
+            ..proxy(ProxyKind::Http, 8080)
+        },
+        ProxyConfig {
+            bypass: vec![String::new()],
+            ..proxy(ProxyKind::Http, 8080)
+        },
+    ];
+    for invalid in invalid {
+        assert!(matches!(
+            set_network_config(with_proxy(invalid)),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert_eq!(get_network_config(), config);
+    set_network_config(NetworkConfig::default()).unwrap();
+}
+
+/// HTTP proxy answering every forwarded request itself, with its request
+/// line and `Proxy-Authorization` header in the body; `CONNECT` requests are
+/// tunnelled to `tunnel_to` after checking the requested authority
+#[cfg(feature = "http")]
+async fn http_proxy(tunnel_to: String, expected_authority: String) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (tunnel_to, expected_authority) = (tunnel_to.clone(), expected_authority.clone());
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).await.unwrap();
+                let mut authorization = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("proxy-authorization") {
+                            authorization = value.trim().to_string();
+                        }
+                    }
+                }
+                let mut stream = reader.into_inner();
+                if request_line.starts_with("CONNECT ") {
+                    let authority = request_line.split_whitespace().nth(1).unwrap();
+                    if authority != expected_authority || authorization != "Basic dXNlcjpwYXNz" {
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n")
+                            .await;
+                        return;
+                    }
+                    let mut target = TcpStream::connect(tunnel_to).await.unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await
+                        .unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut stream, &mut target).await;
+                    return;
+                }
+                let body = format!("{}|{}", request_line.trim(), authorization);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    port
+}
+
+/// SOCKS5 proxy requiring `user`/`pass`, tunnelling to `tunnel_to` after
+/// checking that the client asked for `expected_host` by name
+#[cfg(feature = "http")]
+async fn socks5_proxy(tunnel_to: String, expected_host: String) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let (tunnel_to, expected_host) = (tunnel_to.clone(), expected_host.clone());
+            tokio::spawn(async move {
+                let mut header = [0u8; 2];
+                stream.read_exact(&mut header).await.unwrap();
+                let mut methods = vec![0u8; header[1] as usize];
+                stream.read_exact(&mut methods).await.unwrap();
+                assert!(methods.contains(&2));
+                stream.write_all(&[5, 2]).await.unwrap();
+
+                let field = |length: u8| vec![0u8; length as usize];
+                let mut version = [0u8; 2];
+                stream.read_exact(&mut version).await.unwrap();
+                let mut username = field(version[1]);
+                stream.read_exact(&mut username).await.unwrap();
+                let mut password = field(stream.read_u8().await.unwrap());
+                stream.read_exact(&mut password).await.unwrap();
+                let accepted = username == b"user" && password == b"pass";
+                stream.write_all(&[1, u8::from(!accepted)]).await.unwrap();
+                if !accepted {
+                    return;
+                }
+
+                let mut request = [0u8; 4];
+                stream.read_exact(&mut request).await.unwrap();
+                assert_eq!(request[3], 3, "host should be sent by name");
+                let mut host = field(stream.read_u8().await.unwrap());
+                stream.read_exact(&mut host).await.unwrap();
+                let _port = stream.read_u16().await.unwrap();
+                if host != expected_host.as_bytes() {
+                    let _ = stream.write_all(&[5, 4, 0, 1, 0, 0, 0, 0, 0, 0]).await;
+                    return;
+                }
+                let mut target = TcpStream::connect(tunnel_to).await.unwrap();
+                stream
+                    .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+                    .await
+                    .unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut target).await;
+            });
+        }
+    });
+    port
+}
+
+/// WebSocket server echoing text messages; returns its address
+#[cfg(feature = "http")]
+async fn echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                while let Some(Ok(message)) = socket.next().await {
+                    if message.is_text() {
+                        let _ = socket.send(message).await;
+                    }
+                }
+            });
+        }
+    });
+    address
+}
+
+#[cfg(feature = "http")]
+struct RecordingListener(Arc<Mutex<Vec<String>>>);
+
+#[cfg(feature = "http")]
+impl WebSocketListener for RecordingListener {
+    fn on_text_message(&self, text: String) {
+        self.0.lock().unwrap().push(text);
+    }
+
+    fn on_binary_message(&self, _data: Vec<u8>) {}
+
+    fn on_state_changed(&self, _state: WebSocketState, _error_message: Option<String>) {}
+}
+
+/// Connects to `url` and checks that a message makes the round trip
+#[cfg(feature = "http")]
+async fn websocket_round_trip(url: &str) -> Result<(), TemplateError> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let client = WebSocketClient::new(
+        url.to_string(),
+        WebSocketOptions::default(),
+        Box::new(RecordingListener(received.clone())),
+    )?;
+    client.connect().await?;
+    client.send_text("through the proxy".to_string())?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while received.lock().unwrap().is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(*received.lock().unwrap(), vec!["through the proxy"]);
+    client.close(1000, String::new());
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers: HashMap::new(),
+        body: None,
+        timeout_ms: Some(5_000),
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_requests_go_through_http_proxy_unless_bypassed() {
+    let _turn = NETWORK_CONFIG.lock().await;
+    let port = http_proxy(String::new(), String::new()).await;
+    set_network_config(with_proxy(ProxyConfig {
+        bypass: vec!["direct.test".to_string()],
+        ..proxy(ProxyKind::Http, port)
+    }))
+    .unwrap();
+
+    let response = http_request(get("http://api.example.test/models"), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(response.body).unwrap(),
+        "GET http://api.example.test/models HTTP/1.1|Basic dXNlcjpwYXNz"
+    );
+
+    // Bypassed hosts are connected to directly, and this one does not exist
+    assert!(matches!(
+        http_request(get("http://cdn.direct.test/"), None).await,
+        Err(TemplateError::NetworkError { .. })
+    ));
+    set_network_config(NetworkConfig::default()).unwrap();
+}
+
+#[cfg(feature = "http")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_websocket_is_tunnelled_through_proxies() {
+    let _turn = NETWORK_CONFIG.lock().await;
+    let echo = echo_server().await;
+    let echo_port = echo.rsplit(':').next().unwrap().to_string();
+    let url = format!("ws://echo.test:{}/", echo_port);
+
+    let port = http_proxy(echo.clone(), format!("echo.test:{}", echo_port)).await;
+    set_network_config(with_proxy(proxy(ProxyKind::Http, port))).unwrap();
+    websocket_round_trip(&url).await.unwrap();
+
+    let port = socks5_proxy(echo.clone(), "echo.test".to_string()).await;
+    set_network_config(with_proxy(proxy(ProxyKind::Socks5, port))).unwrap();
+    websocket_round_trip(&url).await.unwrap();
+
+    set_network_config(with_proxy(ProxyConfig {
+        password: Some("wrong".to_string()),
+        ..proxy(ProxyKind::Socks5, port)
+    }))
+    .unwrap();
+    assert!(matches!(
+        websocket_round_trip(&url).await,
+        Err(TemplateError::NetworkError { .. })
+    ));
+
+    let port = http_proxy(echo.clone(), "elsewhere.test:1".to_string()).await;
+    set_network_config(with_proxy(proxy(ProxyKind::Http, port))).unwrap();
+    match websocket_round_trip(&url).await {
+        Err(TemplateError::NetworkError { status_code, .. }) => {
+            assert_eq!(status_code, Some(403))
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+    set_network_config(NetworkConfig::default()).unwrap();
+}
@@ -0,0 +1,75 @@
+use rust_multiplatform_template_lib::{
+    get_recent_events, load_model_metadata, update_config, ErrorKind, LibraryConfig, LibraryEvent,
+    ModelFormat, TemplateConfig, RECENT_EVENTS_CAPACITY,
+};
+use std::fs;
+
+fn write_safetensors(path: &std::path::Path) {
+    let header = r#"{"a":{"dtype":"F32","shape":[1],"data_offsets":[0,4]}}"#;
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(&[0u8; 4]);
+    fs::write(path, buf).unwrap();
+}
+
+// The event buffer is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_recent_events_are_recorded_without_subscribers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tiny.safetensors");
+    write_safetensors(&path);
+    let path = path.to_string_lossy().into_owned();
+    load_model_metadata(path.clone(), None).await.unwrap();
+    TemplateConfig::from_json("not json".to_string()).unwrap_err();
+    let config = LibraryConfig {
+        max_input_size: 1234,
+        ..LibraryConfig::default()
+    };
+    update_config(config.clone()).unwrap();
+
+    let events = get_recent_events(None);
+    assert!(events.windows(2).all(|w| w[0].sequence < w[1].sequence));
+    assert!(events.iter().all(|e| e.timestamp_ms > 0));
+    let kinds: Vec<_> = events.iter().map(|e| &e.event).collect();
+    let loaded = kinds
+        .iter()
+        .position(|e| {
+            **e == LibraryEvent::ModelLoaded {
+                path: path.clone(),
+                format: ModelFormat::Safetensors,
+            }
+        })
+        .unwrap();
+    let failed = kinds
+        .iter()
+        .position(|e| {
+            matches!(e, LibraryEvent::OperationFailed { operation, kind, .. }
+                if operation == "from_json" && *kind == ErrorKind::ParseError)
+        })
+        .unwrap();
+    let changed = kinds
+        .iter()
+        .position(|e| {
+            **e == LibraryEvent::ConfigChanged {
+                config: config.clone(),
+            }
+        })
+        .unwrap();
+    assert!(loaded < failed && failed < changed);
+
+    let last = get_recent_events(Some(1));
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0], *get_recent_events(None).last().unwrap());
+
+    // Only the newest events are kept
+    for _ in 0..RECENT_EVENTS_CAPACITY {
+        TemplateConfig::from_json("not json".to_string()).unwrap_err();
+    }
+    let events = get_recent_events(None);
+    assert_eq!(events.len(), RECENT_EVENTS_CAPACITY as usize);
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e.event, LibraryEvent::ConfigChanged { .. })));
+    update_config(LibraryConfig::default()).unwrap();
+}
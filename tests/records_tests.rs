@@ -0,0 +1,179 @@
+use rust_multiplatform_template_lib::{
+    decode_cbor, decode_msgpack, echo, echo_result_to_json, encode_cbor, encode_msgpack,
+    ChatMessage, ChatRole, DiscoveredModel, GenerationParams, GenerationResult, ModelFormat,
+    ModelMetadata, Record, TemplateError,
+};
+
+fn metadata() -> ModelMetadata {
+    ModelMetadata {
+        version: 3,
+        tensor_count: 291,
+        metadata_count: 24,
+        architecture: Some("llama".to_string()),
+        name: Some("Tiny".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_msgpack_round_trips_every_record() {
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let records = [
+        Record::EchoResult {
+            result: result.clone(),
+        },
+        Record::ModelMetadata {
+            metadata: metadata(),
+        },
+        Record::DiscoveredModel {
+            model: DiscoveredModel {
+                path: "/models/tiny.gguf".to_string(),
+                file_name: "tiny.gguf".to_string(),
+                size_bytes: 1_048_576,
+                format: ModelFormat::Gguf,
+                is_valid: true,
+                metadata: Some(metadata()),
+                error_message: None,
+            },
+        },
+        Record::GenerationParams {
+            params: GenerationParams {
+                temperature: Some(0.7),
+                seed: Some(42),
+                ..GenerationParams::default()
+            },
+        },
+        Record::GenerationResult {
+            result: GenerationResult {
+                text: "Hi there".to_string(),
+                finish_reason: Some("stop".to_string()),
+                model: "gpt-4o-mini".to_string(),
+                prompt_tokens: Some(12),
+                completion_tokens: Some(3),
+            },
+        },
+        Record::ChatMessages {
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: "Hello".to_string(),
+            }],
+        },
+        Record::Bytes {
+            kind: "app/settings".to_string(),
+            data: vec![0, 1, 2, 255],
+        },
+    ];
+    for record in records {
+        assert_eq!(
+            decode_msgpack(encode_msgpack(record.clone()).unwrap()).unwrap(),
+            record
+        );
+    }
+
+    // Smaller than the same result as JSON
+    let encoded = encode_msgpack(Record::EchoResult {
+        result: result.clone(),
+    })
+    .unwrap();
+    assert!(encoded.len() < echo_result_to_json(result).len());
+}
+
+#[test]
+fn test_msgpack_layout() {
+    let encoded = encode_msgpack(Record::Bytes {
+        kind: "blob".to_string(),
+        data: vec![7; 3],
+    })
+    .unwrap();
+    // A map of three entries: the type tag, then the fields by name
+    assert_eq!(encoded[0], 0x83);
+    assert_eq!(&encoded[1..6], b"\xa4type");
+    assert_eq!(&encoded[6..12], b"\xa5bytes");
+    // `data` is a `bin` value rather than an array of integers
+    assert!(encoded.ends_with(b"\xa4data\xc4\x03\x07\x07\x07"));
+}
+
+#[test]
+fn test_invalid_msgpack_is_rejected() {
+    let encoded = encode_msgpack(Record::ModelMetadata {
+        metadata: metadata(),
+    })
+    .unwrap();
+    for invalid in [
+        Vec::new(),
+        b"not msgpack".to_vec(),
+        encoded[..encoded.len() - 4].to_vec(),
+        // A map whose `type` names no record
+        b"\x81\xa4type\xa7unknown".to_vec(),
+    ] {
+        match decode_msgpack(invalid) {
+            Err(TemplateError::ParseError { format, .. }) => assert_eq!(format, "MessagePack"),
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(not(feature = "cbor"))]
+#[test]
+fn test_cbor_requires_feature() {
+    let record = Record::ModelMetadata {
+        metadata: metadata(),
+    };
+    assert!(matches!(
+        encode_cbor(record),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        decode_cbor(vec![0xa0]),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_cbor_round_trips_records() {
+    let records = [
+        Record::ModelMetadata {
+            metadata: metadata(),
+        },
+        Record::GenerationParams {
+            params: GenerationParams {
+                temperature: Some(0.7),
+                ..GenerationParams::default()
+            },
+        },
+        Record::Bytes {
+            kind: "blob".to_string(),
+            data: vec![7; 3],
+        },
+    ];
+    for record in records {
+        let encoded = encode_cbor(record.clone()).unwrap();
+        assert_eq!(decode_cbor(encoded).unwrap(), record);
+    }
+
+    let encoded = encode_cbor(Record::Bytes {
+        kind: "blob".to_string(),
+        data: vec![7; 3],
+    })
+    .unwrap();
+    // A map of three entries: the type tag, then the fields by name
+    assert_eq!(encoded[0], 0xa3);
+    assert_eq!(&encoded[1..6], b"\x64type");
+    assert_eq!(&encoded[6..12], b"\x65bytes");
+    // `data` is a byte string rather than an array of integers
+    assert!(encoded.ends_with(b"\x64data\x43\x07\x07\x07"));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_invalid_cbor_is_rejected() {
+    for invalid in [Vec::new(), vec![0xff], b"\xa1\x64type\x67unknown".to_vec()] {
+        match decode_cbor(invalid) {
+            Err(TemplateError::ParseError { format, .. }) => assert_eq!(format, "CBOR"),
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use rust_multiplatform_template_lib::{
+    ChatMessage, ChatRole, NetworkPolicy, RemoteLlmClient, RemoteLlmConfig, TemplateError,
+};
+use std::collections::HashMap;
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    set_network_status, GenerationListener, GenerationParams, NetworkStatus,
+};
+#[cfg(feature = "http")]
+use std::io::Write;
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+
+fn config(base_url: &str) -> RemoteLlmConfig {
+    RemoteLlmConfig {
+        base_url: base_url.to_string(),
+        model: "tiny-chat".to_string(),
+        api_key: Some("secret".to_string()),
+        headers: HashMap::new(),
+        timeout_ms: Some(5_000),
+        network_policy: NetworkPolicy::Any,
+    }
+}
+
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+fn conversation() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: ChatRole::System,
+            content: "Be brief.".to_string(),
+        },
+        ChatMessage {
+            role: ChatRole::User,
+            content: "Say hello".to_string(),
+        },
+    ]
+}
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_remote_llm_requires_feature() {
+    match RemoteLlmClient::new(config("http://127.0.0.1/v1")) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other.map(|_| ())),
+    }
+}
+
+/// Fake OpenAI server: checks the key, echoes the request's `stream` flag
+/// and sampling settings in its reply, and answers a `bad` model with 404
+#[cfg(feature = "http")]
+fn serve() -> String {
+    format!("{}/v1/", common::serve(respond))
+}
+
+#[cfg(feature = "http")]
+fn respond(request: common::Request, mut stream: TcpStream) {
+    const JSON: &str = "content-type: application/json\r\n";
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let failure = if request.path != "/v1/chat/completions" {
+        Some(("404 Not Found", "no such path"))
+    } else if request.header("authorization") != Some("Bearer secret") {
+        Some(("401 Unauthorized", "bad key"))
+    } else if body["model"] == "bad" {
+        Some(("404 Not Found", "model 'bad' does not exist"))
+    } else {
+        None
+    };
+    if let Some((status, message)) = failure {
+        let error = serde_json::json!({ "error": { "message": message } }).to_string();
+        return common::respond(&mut stream, status, JSON, error.as_bytes());
+    }
+
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "Say hello");
+    assert!(body.get("top_p").is_none());
+    let reply = format!(
+        "Hello there (temperature {}, max {})",
+        body["temperature"], body["max_tokens"]
+    );
+    if body["stream"] == true {
+        let mut events = String::new();
+        for (index, piece) in reply.split_inclusive(' ').enumerate() {
+            let role = if index == 0 {
+                serde_json::json!({ "role": "assistant", "content": "" })
+            } else {
+                serde_json::json!({})
+            };
+            for delta in [role, serde_json::json!({ "content": piece })] {
+                let chunk = serde_json::json!({
+                    "model": "tiny-chat-1",
+                    "choices": [{ "delta": delta, "finish_reason": null }],
+                });
+                events.push_str(&format!("data: {}\n\n", chunk));
+            }
+        }
+        let last = serde_json::json!({
+            "model": "tiny-chat-1",
+            "choices": [{ "delta": {}, "finish_reason": "stop" }],
+        });
+        events.push_str(&format!("data: {}\n\ndata: [DONE]\n\n", last));
+        let head =
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(events.as_bytes());
+    } else {
+        let completion = serde_json::json!({
+            "model": "tiny-chat-1",
+            "choices": [{
+                "message": { "role": "assistant", "content": reply },
+                "finish_reason": "stop",
+            }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 7 },
+        })
+        .to_string();
+        common::respond(&mut stream, "200 OK", JSON, completion.as_bytes());
+    }
+}
+
+#[cfg(feature = "http")]
+struct RecordingListener(Arc<Mutex<Vec<String>>>);
+
+#[cfg(feature = "http")]
+impl GenerationListener for RecordingListener {
+    fn on_token(&self, token: String) {
+        self.0.lock().unwrap().push(token);
+    }
+}
+
+#[cfg(feature = "http")]
+fn params() -> GenerationParams {
+    GenerationParams {
+        max_tokens: Some(32),
+        temperature: Some(0.5),
+        ..GenerationParams::default()
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_chat_returns_reply_and_usage() {
+    let client = RemoteLlmClient::new(config(&serve())).unwrap();
+    let result = client.chat(conversation(), params(), None).await.unwrap();
+    assert_eq!(result.text, "Hello there (temperature 0.5, max 32)");
+    assert_eq!(result.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(result.model, "tiny-chat-1");
+    assert_eq!(result.prompt_tokens, Some(12));
+    assert_eq!(result.completion_tokens, Some(7));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_chat_stream_delivers_tokens_in_order() {
+    let client = RemoteLlmClient::new(config(&serve())).unwrap();
+    let tokens = Arc::new(Mutex::new(Vec::new()));
+    let result = client
+        .chat_stream(
+            conversation(),
+            params(),
+            Box::new(RecordingListener(tokens.clone())),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.text, "Hello there (temperature 0.5, max 32)");
+    assert_eq!(result.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(result.model, "tiny-chat-1");
+    let tokens = tokens.lock().unwrap();
+    assert_eq!(tokens.len(), 6);
+    assert_eq!(tokens.concat(), result.text);
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_server_errors_carry_status_and_message() {
+    let base = serve();
+    let client = RemoteLlmClient::new(RemoteLlmConfig {
+        api_key: Some("wrong".to_string()),
+        ..config(&base)
+    })
+    .unwrap();
+    match client.chat(conversation(), params(), None).await {
+        Err(TemplateError::NetworkError {
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert_eq!(status_code, Some(401));
+            assert!(error_message.contains("bad key"));
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    let client = RemoteLlmClient::new(RemoteLlmConfig {
+        model: "bad".to_string(),
+        ..config(&base)
+    })
+    .unwrap();
+    let tokens = Arc::new(Mutex::new(Vec::new()));
+    match client
+        .chat_stream(
+            conversation(),
+            params(),
+            Box::new(RecordingListener(tokens)),
+            None,
+        )
+        .await
+    {
+        Err(TemplateError::NetworkError { status_code, .. }) => {
+            assert_eq!(status_code, Some(404))
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_invalid_config() {
+    for base_url in ["not a url", "ftp://example.com/v1"] {
+        assert!(matches!(
+            RemoteLlmClient::new(config(base_url)),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    assert!(matches!(
+        RemoteLlmClient::new(RemoteLlmConfig {
+            model: " ".to_string(),
+            ..config("http://127.0.0.1/v1")
+        }),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+// The network status is process-wide: only this test changes it, and only to
+// statuses the `Any` policy of the other tests allows
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_unmetered_policy_keeps_requests_off_cellular() {
+    let client = RemoteLlmClient::new(RemoteLlmConfig {
+        network_policy: NetworkPolicy::Unmetered,
+        ..config(&serve())
+    })
+    .unwrap();
+
+    set_network_status(NetworkStatus::Cellular);
+    match client.chat(conversation(), params(), None).await {
+        Err(TemplateError::NetworkError {
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert_eq!(status_code, None);
+            assert!(error_message.contains("Cellular"));
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    set_network_status(NetworkStatus::Wifi);
+    let result = client.chat(conversation(), params(), None).await.unwrap();
+    assert_eq!(result.finish_reason.as_deref(), Some("stop"));
+}
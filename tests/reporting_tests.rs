@@ -0,0 +1,42 @@
+use rust_multiplatform_template_lib::{
+    echo_blocking, parse_uuid, set_error_listener, ErrorKind, ErrorListener, ErrorReport, Severity,
+    TemplateConfig,
+};
+use std::sync::{Arc, Mutex};
+
+struct Recorder(Arc<Mutex<Vec<ErrorReport>>>);
+
+impl ErrorListener for Recorder {
+    fn on_error(&self, report: ErrorReport) {
+        self.0.lock().unwrap().push(report);
+    }
+}
+
+// One test so the process-wide listener is not shared between parallel tests
+#[tokio::test]
+async fn test_error_listener_receives_returned_errors() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    set_error_listener(Some(Box::new(Recorder(reports.clone()))));
+
+    assert!(parse_uuid("not-a-uuid".to_string(), None).await.is_err());
+    assert!(TemplateConfig::from_json("{".to_string()).is_err());
+    assert!(echo_blocking("ok".to_string(), None, None).is_ok());
+
+    {
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].operation, "parse_uuid");
+        assert_eq!(reports[0].kind, ErrorKind::InvalidInput);
+        assert_eq!(reports[0].code, "INVALID_INPUT");
+        assert_eq!(reports[0].severity, Severity::Warning);
+        assert_eq!(reports[0].kind, reports[0].error.kind());
+        assert_eq!(reports[1].operation, "from_json");
+        assert_eq!(reports[1].code, "PARSE_ERROR");
+    }
+
+    set_error_listener(None);
+    assert!(parse_uuid("still-not-a-uuid".to_string(), None)
+        .await
+        .is_err());
+    assert_eq!(reports.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,146 @@
+use rust_multiplatform_template_lib::{
+    retry_delay_ms, retrying, should_retry, CancellationToken, ErrorKind, RetryPolicy,
+    TemplateError,
+};
+use std::cell::Cell;
+
+fn fast_policy() -> RetryPolicy {
+    RetryPolicy {
+        base_delay_ms: 1,
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn test_retrying_succeeds_after_transient_failures() {
+    let calls = Cell::new(0);
+    let value = retrying(&fast_policy(), "flaky", None, |attempt| {
+        calls.set(calls.get() + 1);
+        async move {
+            if attempt < 3 {
+                Err(TemplateError::timeout("flaky", 5))
+            } else {
+                Ok("done")
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(value, "done");
+    assert_eq!(calls.get(), 3);
+}
+
+#[tokio::test]
+async fn test_retrying_exhausts_attempts() {
+    let result: Result<(), _> = retrying(&fast_policy(), "flaky", None, |_| async {
+        Err(TemplateError::timeout("flaky", 5))
+    })
+    .await;
+    match result {
+        Err(TemplateError::RetriesExhausted {
+            operation,
+            attempts,
+            last_error_kind,
+            ..
+        }) => {
+            assert_eq!(operation, "flaky");
+            assert_eq!(attempts, 3);
+            assert_eq!(last_error_kind, ErrorKind::Timeout);
+        }
+        other => panic!("Expected RetriesExhausted, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_retrying_returns_non_retryable_errors_immediately() {
+    let calls = Cell::new(0);
+    let result: Result<(), _> = retrying(&fast_policy(), "op", None, |_| {
+        calls.set(calls.get() + 1);
+        async { Err(TemplateError::invalid_input("bad".to_string(), None)) }
+    })
+    .await;
+    assert!(matches!(result, Err(TemplateError::InvalidInput { .. })));
+    assert_eq!(calls.get(), 1);
+}
+
+#[tokio::test]
+async fn test_retrying_single_attempt_returns_original_error() {
+    let policy = RetryPolicy {
+        max_attempts: 1,
+        ..fast_policy()
+    };
+    let result: Result<(), _> = retrying(&policy, "op", None, |_| async {
+        Err(TemplateError::timeout("op", 5))
+    })
+    .await;
+    assert!(matches!(result, Err(TemplateError::Timeout { .. })));
+}
+
+#[tokio::test]
+async fn test_retrying_stops_when_cancelled() {
+    let token = CancellationToken::new();
+    let result: Result<(), _> = retrying(&fast_policy(), "op", Some(&token), |_| {
+        token.cancel();
+        async { Err(TemplateError::timeout("op", 5)) }
+    })
+    .await;
+    assert!(matches!(
+        result,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_retrying_rejects_invalid_policy() {
+    let policy = RetryPolicy {
+        max_attempts: 0,
+        ..RetryPolicy::default()
+    };
+    let result = retrying(&policy, "op", None, |_| async { Ok(()) }).await;
+    assert!(matches!(result, Err(TemplateError::InvalidInput { .. })));
+}
+
+#[test]
+fn test_retry_delay_backoff() {
+    let policy = RetryPolicy {
+        base_delay_ms: 100,
+        max_delay_ms: 350,
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    };
+    assert_eq!(retry_delay_ms(policy.clone(), 1), 100);
+    assert_eq!(retry_delay_ms(policy.clone(), 2), 200);
+    assert_eq!(retry_delay_ms(policy.clone(), 3), 350);
+    assert_eq!(retry_delay_ms(policy, 200), 350);
+}
+
+#[test]
+fn test_retry_delay_jitter_stays_in_range() {
+    let policy = RetryPolicy {
+        base_delay_ms: 100,
+        jitter: 0.5,
+        ..RetryPolicy::default()
+    };
+    for _ in 0..100 {
+        let delay = retry_delay_ms(policy.clone(), 1);
+        assert!((50..=100).contains(&delay));
+    }
+}
+
+#[test]
+fn test_should_retry() {
+    let policy = RetryPolicy::default();
+    assert!(should_retry(policy.clone(), ErrorKind::Timeout, 1));
+    assert!(!should_retry(policy.clone(), ErrorKind::Timeout, 3));
+    assert!(!should_retry(policy, ErrorKind::InvalidInput, 1));
+}
+
+#[test]
+fn test_error_kind() {
+    assert_eq!(TemplateError::timeout("op", 1).kind(), ErrorKind::Timeout);
+    assert_eq!(
+        TemplateError::AlreadyInitialized.kind(),
+        ErrorKind::AlreadyInitialized
+    );
+}
@@ -0,0 +1,56 @@
+use rust_multiplatform_template_lib::{
+    init_runtime, shutdown_runtime, CancellationToken, RuntimeOptions, TemplateError,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Whether a timeout token's timer fires without anyone polling the token
+fn timer_fires() -> bool {
+    let fired = Arc::new(AtomicBool::new(false));
+    let token = CancellationToken::with_timeout(10);
+    let flag = fired.clone();
+    token.on_cancel(move || flag.store(true, Ordering::SeqCst));
+    std::thread::sleep(Duration::from_millis(100));
+    fired.load(Ordering::SeqCst)
+}
+
+// The internal runtime is process-wide, so these checks run in one test
+#[test]
+fn test_runtime_lifecycle() {
+    // Shutting down before anything started is a no-op
+    shutdown_runtime(0);
+
+    let invalid = RuntimeOptions {
+        worker_threads: 0,
+        ..RuntimeOptions::default()
+    };
+    assert!(matches!(
+        init_runtime(invalid),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let options = RuntimeOptions {
+        worker_threads: 2,
+        thread_name_prefix: "test-runtime".to_string(),
+    };
+    init_runtime(options.clone()).unwrap();
+    assert!(matches!(
+        init_runtime(options.clone()),
+        Err(TemplateError::AlreadyInitialized)
+    ));
+
+    // Timers run on the explicitly started runtime
+    assert!(timer_fires());
+
+    shutdown_runtime(100);
+    shutdown_runtime(100);
+
+    // Restarting after shutdown is allowed
+    init_runtime(options).unwrap();
+    shutdown_runtime(100);
+
+    // And the runtime starts again on demand
+    assert!(timer_fires());
+    shutdown_runtime(0);
+}
@@ -0,0 +1,59 @@
+use rust_multiplatform_template_lib::{discover_models, OperationScope, TaskStatus, TemplateError};
+
+#[tokio::test]
+async fn test_scope_await_all() {
+    let scope = OperationScope::new();
+    let handles: Vec<_> = (0..5)
+        .map(|i| scope.spawn_echo(format!("item {}", i), None))
+        .collect();
+
+    scope.await_all().await;
+    assert_eq!(scope.active_count(), 0);
+    for handle in handles {
+        assert_eq!(handle.status(), TaskStatus::Completed);
+    }
+}
+
+#[tokio::test]
+async fn test_scope_cancel_all_cancels_tokens() {
+    let scope = OperationScope::new();
+    let first = scope.token();
+    let second = scope.token();
+
+    first.cancel();
+    assert!(!second.is_cancelled());
+    assert!(!scope.is_cancelled());
+
+    scope.cancel_all();
+    assert!(scope.is_cancelled());
+    assert!(second.is_cancelled());
+
+    // Operations started after cancel_all() are cancelled immediately
+    let dir = tempfile::tempdir().unwrap();
+    let result = discover_models(
+        dir.path().to_string_lossy().into_owned(),
+        Some(scope.token()),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+    let handle = scope.spawn_echo("Hello".to_string(), None);
+    assert_eq!(handle.status(), TaskStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn test_dropping_scope_cancels_operations() {
+    let scope = OperationScope::new();
+    let token = scope.token();
+    drop(scope);
+    assert!(token.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_empty_scope() {
+    let scope = OperationScope::default();
+    assert_eq!(scope.active_count(), 0);
+    scope.await_all().await;
+}
@@ -0,0 +1,25 @@
+use rust_multiplatform_template_lib::{SecureBytes, SecureString};
+
+#[test]
+fn test_secure_buffers_expose_contents() {
+    let bytes = SecureBytes::new(vec![1, 2, 3]);
+    assert_eq!(bytes.expose(), &[1, 2, 3]);
+    assert_eq!(bytes.len(), 3);
+    assert_eq!(bytes.clone(), SecureBytes::from(vec![1, 2, 3]));
+    assert!(SecureBytes::default().is_empty());
+
+    let text = SecureString::from("sk-live-123".to_string());
+    assert_eq!(text.expose(), "sk-live-123");
+    assert_eq!(text.len(), 11);
+}
+
+#[test]
+fn test_secure_buffers_redact_debug_output() {
+    let bytes = SecureBytes::new(b"secret".to_vec());
+    assert_eq!(format!("{:?}", bytes), "SecureBytes(<6 bytes redacted>)");
+
+    let text = SecureString::new("sk-live-123".to_string());
+    let debug = format!("{:?}", text);
+    assert!(!debug.contains("sk-live"));
+    assert_eq!(debug, "SecureString(<11 bytes redacted>)");
+}
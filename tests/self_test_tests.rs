@@ -0,0 +1,30 @@
+use rust_multiplatform_template_lib::run_self_test;
+
+#[tokio::test]
+async fn test_self_test_passes_on_working_setup() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = run_self_test(vec![dir.path().to_string_lossy().into_owned()]).await;
+    assert!(report.passed, "{:?}", report);
+    let names: Vec<_> = report.checks.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(&names[..3], ["runtime", "secure_random", "echo"]);
+    assert!(names[3].starts_with("filesystem:"));
+    assert!(report.checks.iter().all(|c| c.message.is_none()));
+    // The probe file is cleaned up
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_self_test_reports_unwritable_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    let report = run_self_test(vec![missing.to_string_lossy().into_owned()]).await;
+    assert!(!report.passed);
+    let failed: Vec<_> = report.checks.iter().filter(|c| !c.passed).collect();
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].name.ends_with("missing"));
+    assert!(failed[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .starts_with("I/O error"));
+}
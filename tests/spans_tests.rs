@@ -0,0 +1,72 @@
+use rust_multiplatform_template_lib::{
+    load_model_metadata, set_span_listener, SpanEvent, SpanEventKind, SpanListener, TemplateConfig,
+};
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+struct Recorder(Arc<Mutex<Vec<SpanEvent>>>);
+
+impl SpanListener for Recorder {
+    fn on_span_event(&self, event: SpanEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+fn write_safetensors(path: &std::path::Path) {
+    let header = r#"{"a":{"dtype":"F32","shape":[1],"data_offsets":[0,4]}}"#;
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(&[0u8; 4]);
+    fs::write(path, buf).unwrap();
+}
+
+fn closed<'a>(events: &'a [SpanEvent], name: &str) -> &'a SpanEvent {
+    events
+        .iter()
+        .find(|e| e.kind == SpanEventKind::Closed && e.name == name)
+        .unwrap_or_else(|| panic!("no closed span named {name}"))
+}
+
+// The span listener is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_operation_spans_reach_host_listener() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    set_span_listener(Some(Box::new(Recorder(events.clone()))));
+
+    // Synchronous operations open and close a span with an operation id
+    TemplateConfig::from_json("{}".to_string()).unwrap();
+    {
+        let events = events.lock().unwrap();
+        let close = closed(&events, "from_json");
+        let open = events
+            .iter()
+            .find(|e| e.kind == SpanEventKind::Opened && e.span_id == close.span_id)
+            .unwrap();
+        assert!(open.operation_id.is_some());
+        assert_eq!(open.operation_id, close.operation_id);
+        assert!(open.duration_us.is_none());
+        assert!(close.duration_us.is_some());
+        assert!(close.timestamp_ms >= open.timestamp_ms);
+    }
+
+    // Work on the blocking pool is parented to the operation that started it
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.safetensors");
+    write_safetensors(&path);
+    load_model_metadata(path.to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+    {
+        let events = events.lock().unwrap();
+        let operation = closed(&events, "load_model_metadata");
+        let header = closed(&events, "read_header");
+        assert_eq!(header.parent_id, Some(operation.span_id));
+        assert!(header.operation_id.is_none());
+    }
+
+    set_span_listener(None);
+    let count = events.lock().unwrap().len();
+    TemplateConfig::from_json("{}".to_string()).unwrap();
+    assert_eq!(events.lock().unwrap().len(), count);
+}
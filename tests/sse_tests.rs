@@ -0,0 +1,211 @@
+use rust_multiplatform_template_lib::{
+    sse_request, HttpMethod, HttpRequest, SseEvent, SseListener, SseParser, TemplateError,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::CancellationToken;
+#[cfg(feature = "http")]
+use std::io::Write;
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        url: url.to_string(),
+        method: HttpMethod::Get,
+        headers: HashMap::new(),
+        body: None,
+        timeout_ms: None,
+    }
+}
+
+fn event(event: &str, data: &str, id: Option<&str>) -> SseEvent {
+    SseEvent {
+        event: event.to_string(),
+        data: data.to_string(),
+        id: id.map(str::to_string),
+        retry_ms: None,
+    }
+}
+
+struct RecordingListener(Arc<Mutex<Vec<SseEvent>>>);
+
+impl SseListener for RecordingListener {
+    fn on_sse_event(&self, event: SseEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn test_parser_fields_and_comments() {
+    let mut parser = SseParser::new();
+    let events = parser.feed(
+        b": keep-alive\n\
+          data: first\n\n\
+          event: token\ndata:no space\ndata:  two spaces\nid: 7\n\n\
+          data\n\n\
+          event: empty\n\n\
+          retry: 1500\nretry: soon\ndata: last\nunknown: field\n\n",
+    );
+    assert_eq!(
+        events,
+        vec![
+            event("message", "first", None),
+            event("token", "no space\n two spaces", Some("7")),
+            event("message", "", Some("7")),
+            SseEvent {
+                retry_ms: Some(1500),
+                ..event("message", "last", Some("7"))
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parser_handles_any_chunking_and_line_endings() {
+    let stream = b"data: a\r\n\r\nevent: b\rdata: b1\rdata: b2\r\rdata: c\n\ndata: partial";
+    let expected = vec![
+        event("message", "a", None),
+        event("b", "b1\nb2", None),
+        event("message", "c", None),
+    ];
+    for chunk_size in 1..stream.len() {
+        let mut parser = SseParser::new();
+        let events: Vec<SseEvent> = stream
+            .chunks(chunk_size)
+            .flat_map(|chunk| parser.feed(chunk))
+            .collect();
+        assert_eq!(events, expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[cfg(not(feature = "http"))]
+#[tokio::test]
+async fn test_sse_requires_feature() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    match sse_request(
+        get("http://127.0.0.1/"),
+        Box::new(RecordingListener(received)),
+        None,
+    )
+    .await
+    {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other),
+    }
+}
+
+/// Serves `/events` (three events written in pieces, with the request's
+/// `accept` header echoed as the first event), `/hang` (one event, then
+/// nothing), and 500 otherwise
+#[cfg(feature = "http")]
+fn serve() -> String {
+    common::serve(respond)
+}
+
+#[cfg(feature = "http")]
+fn respond(request: common::Request, mut stream: TcpStream) {
+    let accept = request.header("accept").unwrap_or_default();
+    let pieces: Vec<String> = match request.path.as_str() {
+        "/events" => vec![
+            format!("data: {}\n\n", accept),
+            "event: token\nid: 1\nda".to_string(),
+            "ta: Hel".to_string(),
+            "lo\n\nevent: done\ndata: [DONE]\n\n".to_string(),
+        ],
+        "/hang" => vec!["data: waiting\n\n".to_string()],
+        _ => return common::respond(&mut stream, "500 Internal Server Error", "", b"oops"),
+    };
+    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    for piece in pieces {
+        if stream.write_all(piece.as_bytes()).is_err() {
+            return;
+        }
+        let _ = stream.flush();
+        thread::sleep(Duration::from_millis(20));
+    }
+    if request.path == "/hang" {
+        thread::sleep(Duration::from_secs(5));
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_events_are_delivered_in_order() {
+    let base = serve();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let last_id = sse_request(
+        get(&format!("{}/events", base)),
+        Box::new(RecordingListener(received.clone())),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(last_id.as_deref(), Some("1"));
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            event("message", "text/event-stream", None),
+            event("token", "Hello", Some("1")),
+            event("done", "[DONE]", Some("1")),
+        ]
+    );
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_error_status_and_cancellation() {
+    let base = serve();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    match sse_request(
+        get(&format!("{}/broken", base)),
+        Box::new(RecordingListener(received.clone())),
+        None,
+    )
+    .await
+    {
+        Err(TemplateError::NetworkError {
+            status_code,
+            error_message,
+            ..
+        }) => {
+            assert_eq!(status_code, Some(500));
+            assert!(error_message.contains("oops"));
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    let token = Arc::new(CancellationToken::new());
+    let canceller = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        canceller.cancel();
+    });
+    assert!(matches!(
+        sse_request(
+            get(&format!("{}/hang", base)),
+            Box::new(RecordingListener(received.clone())),
+            Some(token),
+        )
+        .await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![event("message", "waiting", None)]
+    );
+}
@@ -0,0 +1,92 @@
+use rust_multiplatform_template_lib::{
+    echo, echo_stream, CancellationToken, TemplateError, MAX_INPUT_SIZE,
+};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_echo_stream_matches_echo() {
+    let stream = echo_stream(None);
+    stream.push_chunk(b"Hello ".to_vec()).unwrap();
+    stream.push_chunk("世界".as_bytes().to_vec()).unwrap();
+    let streamed = stream.finish().unwrap().unwrap();
+
+    let direct = echo("Hello 世界".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(streamed.text, direct.text);
+    assert_eq!(streamed.length, direct.length);
+    assert_eq!(streamed.hash, direct.hash);
+}
+
+#[test]
+fn test_echo_stream_split_utf8_sequence() {
+    let bytes = "🌍".as_bytes();
+    let stream = echo_stream(None);
+    for byte in bytes {
+        stream.push_chunk(vec![*byte]).unwrap();
+    }
+    assert_eq!(stream.finish().unwrap().unwrap().text, "🌍");
+
+    let truncated = echo_stream(None);
+    truncated.push_chunk(bytes[..2].to_vec()).unwrap();
+    assert!(matches!(
+        truncated.finish(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_echo_stream_larger_than_max_input() {
+    let stream = echo_stream(None);
+    let chunk = vec![b'a'; MAX_INPUT_SIZE / 2];
+    for _ in 0..3 {
+        stream.push_chunk(chunk.clone()).unwrap();
+    }
+    let result = stream.finish().unwrap().unwrap();
+    assert_eq!(result.text.len(), MAX_INPUT_SIZE / 2 * 3);
+}
+
+#[test]
+fn test_echo_stream_rejects_invalid_chunks() {
+    let stream = echo_stream(None);
+    match stream.push_chunk(b"a\0b".to_vec()) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("null bytes"));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+    assert!(matches!(
+        stream.push_chunk(vec![0xff, 0xfe]),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_echo_stream_empty_and_finished() {
+    let stream = echo_stream(None);
+    assert!(stream.finish().unwrap().is_none());
+    assert!(matches!(
+        stream.push_chunk(b"late".to_vec()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert!(matches!(
+        stream.finish(),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[test]
+fn test_echo_stream_with_cancellation() {
+    let token = Arc::new(CancellationToken::new());
+    let stream = echo_stream(Some(token.clone()));
+    stream.push_chunk(b"data".to_vec()).unwrap();
+    token.cancel();
+
+    match stream.push_chunk(b"more".to_vec()) {
+        Err(TemplateError::OperationCancelled { operation }) => {
+            assert_eq!(operation, "echo_stream");
+        }
+        _ => panic!("Expected OperationCancelled error"),
+    }
+}
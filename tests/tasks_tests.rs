@@ -0,0 +1,84 @@
+use rust_multiplatform_template_lib::{
+    get_task, spawn_echo, CancellationToken, TaskStatus, TemplateError,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_spawn_echo_completes() {
+    let handle = spawn_echo("Hello".to_string(), None, None);
+    let result = handle.await_result().await.unwrap().unwrap();
+    assert_eq!(result.text, "Hello");
+    assert_eq!(handle.status(), TaskStatus::Completed);
+
+    // The result can be awaited again
+    assert!(handle.await_result().await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_spawn_echo_failure() {
+    let handle = spawn_echo("a\0b".to_string(), None, None);
+    assert!(matches!(
+        handle.await_result().await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+    assert_eq!(handle.status(), TaskStatus::Failed);
+}
+
+#[tokio::test]
+async fn test_cancel_task() {
+    let handle = spawn_echo("Hello".to_string(), None, None);
+    handle.cancel();
+    let result = handle.await_result().await;
+    // The echo may have completed before cancel() was called
+    match handle.status() {
+        TaskStatus::Cancelled => {
+            assert!(matches!(
+                result,
+                Err(TemplateError::OperationCancelled { .. })
+            ))
+        }
+        TaskStatus::Completed => assert!(result.is_ok()),
+        status => panic!("Unexpected status {:?}", status),
+    }
+}
+
+#[tokio::test]
+async fn test_task_with_cancelled_parent_token() {
+    let token = Arc::new(CancellationToken::new());
+    token.cancel();
+    let handle = spawn_echo("Hello".to_string(), Some(token), None);
+    assert_eq!(handle.status(), TaskStatus::Cancelled);
+    assert!(handle.await_result().await.is_err());
+}
+
+#[tokio::test]
+async fn test_cancelling_task_leaves_caller_token_alone() {
+    let token = Arc::new(CancellationToken::new());
+    let handle = spawn_echo("Hello".to_string(), Some(token.clone()), None);
+    handle.cancel();
+    assert!(!token.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_get_task_by_id() {
+    let handle = spawn_echo("Hello".to_string(), None, None);
+    let id = handle.id();
+    let found = get_task(id).unwrap();
+    assert_eq!(found.id(), id);
+    found.await_result().await.unwrap();
+
+    // Finished and unreferenced tasks are forgotten
+    drop(found);
+    drop(handle);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(get_task(id).is_none());
+    assert!(get_task(u64::MAX).is_none());
+}
+
+#[test]
+fn test_spawn_echo_without_tokio_runtime() {
+    let handle = spawn_echo("Hello".to_string(), None, None);
+    let result = tokio_test::block_on(handle.await_result()).unwrap();
+    assert_eq!(result.unwrap().text, "Hello");
+}
@@ -0,0 +1,283 @@
+use rust_multiplatform_template_lib::{Telemetry, TelemetryConfig, TemplateError};
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::io::Read;
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_telemetry_requires_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = TelemetryConfig {
+        endpoint: "https://telemetry.example.com/events".to_string(),
+        ..TelemetryConfig::default()
+    };
+    match Telemetry::new(dir.path().to_string_lossy().into_owned(), config) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        Err(e) => panic!("Expected InvalidInput, got {:?}", e),
+        Ok(_) => panic!("Expected InvalidInput, got a telemetry client"),
+    }
+}
+
+/// An upload as received: lowercased headers and the raw body
+#[cfg(feature = "http")]
+type Upload = (HashMap<String, String>, Vec<u8>);
+
+/// Answers every request with 200 and records it
+#[cfg(feature = "http")]
+struct Server {
+    url: String,
+    uploads: Arc<Mutex<Vec<Upload>>>,
+}
+
+#[cfg(feature = "http")]
+impl Server {
+    fn start() -> Self {
+        let uploads = Arc::new(Mutex::new(Vec::new()));
+        let recorded = uploads.clone();
+        let base = common::serve(move |request, mut stream| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((request.headers, request.body));
+            common::respond(&mut stream, "200 OK", "", b"");
+        });
+        Self {
+            url: format!("{}/events", base),
+            uploads,
+        }
+    }
+
+    fn config(&self) -> TelemetryConfig {
+        TelemetryConfig {
+            endpoint: self.url.clone(),
+            headers: HashMap::from([("x-api-key".to_string(), "key".to_string())]),
+            ..TelemetryConfig::default()
+        }
+    }
+
+    /// Event names of each upload, in the order received
+    fn batches(&self) -> Vec<Vec<String>> {
+        self.uploads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|upload| {
+                batch(upload)["events"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|event| event["name"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The JSON batch of an upload, decompressed if it was sent gzipped
+#[cfg(feature = "http")]
+fn batch((headers, body): &Upload) -> serde_json::Value {
+    let json = match headers.get("content-encoding").map(String::as_str) {
+        Some("gzip") => {
+            let mut json = Vec::new();
+            flate2::read::GzDecoder::new(&body[..])
+                .read_to_end(&mut json)
+                .unwrap();
+            json
+        }
+        _ => body.clone(),
+    };
+    serde_json::from_slice(&json).unwrap()
+}
+
+#[cfg(feature = "http")]
+fn directory(dir: &tempfile::TempDir) -> String {
+    dir.path().to_string_lossy().into_owned()
+}
+
+#[cfg(feature = "http")]
+fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(feature = "http")]
+fn wait_idle(telemetry: &Telemetry) {
+    tokio_test::block_on(telemetry.wait_idle());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_full_batches_are_uploaded_compressed() {
+    let server = Server::start();
+    let dir = tempfile::tempdir().unwrap();
+    let config = TelemetryConfig {
+        max_batch_events: 2,
+        ..server.config()
+    };
+    let telemetry = Telemetry::new(directory(&dir), config).unwrap();
+
+    for name in ["launch", "open", "search", "close", "suspend"] {
+        telemetry
+            .track(name.to_string(), properties(&[("screen", "home")]))
+            .unwrap();
+    }
+    wait_idle(&telemetry);
+    assert_eq!(server.batches(), [["launch", "open"], ["search", "close"]]);
+    assert_eq!(telemetry.buffered_events().len(), 1);
+
+    telemetry.flush().unwrap();
+    wait_idle(&telemetry);
+    assert!(telemetry.buffered_events().is_empty());
+    assert_eq!(telemetry.pending_batches(), 0);
+    assert_eq!(server.batches()[2], ["suspend"]);
+
+    let uploads = server.uploads.lock().unwrap();
+    let (headers, _) = &uploads[0];
+    assert_eq!(headers["content-encoding"], "gzip");
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers["x-api-key"], "key");
+    let first = batch(&uploads[0]);
+    assert_eq!(first["events"][0]["properties"]["screen"], "home");
+    assert!(first["events"][0]["timestamp_ms"].as_u64().unwrap() > 0);
+    assert_ne!(first["batch_id"], batch(&uploads[1])["batch_id"]);
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_old_events_are_uploaded_without_a_full_batch() {
+    let server = Server::start();
+    let dir = tempfile::tempdir().unwrap();
+    let config = TelemetryConfig {
+        max_batch_age_ms: 100,
+        compress: false,
+        ..server.config()
+    };
+    let telemetry = Telemetry::new(directory(&dir), config).unwrap();
+
+    telemetry
+        .track("launch".to_string(), HashMap::new())
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while server.uploads.lock().unwrap().is_empty() {
+        assert!(Instant::now() < deadline, "timed out waiting");
+        thread::sleep(Duration::from_millis(10));
+    }
+    wait_idle(&telemetry);
+
+    let uploads = server.uploads.lock().unwrap();
+    assert!(!uploads[0].0.contains_key("content-encoding"));
+    assert_eq!(batch(&uploads[0])["events"][0]["name"], "launch");
+    assert!(telemetry.buffered_events().is_empty());
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_opt_out_discards_events_and_persists() {
+    let server = Server::start();
+    let dir = tempfile::tempdir().unwrap();
+    let config = TelemetryConfig {
+        redacted_properties: vec!["email".to_string()],
+        ..server.config()
+    };
+
+    {
+        let telemetry = Telemetry::new(directory(&dir), config.clone()).unwrap();
+        assert!(!telemetry.is_opted_out());
+        telemetry
+            .track(
+                "sign_in".to_string(),
+                properties(&[("email", "user@example.com"), ("method", "password")]),
+            )
+            .unwrap();
+        let events = telemetry.buffered_events();
+        assert_eq!(events[0].name, "sign_in");
+        assert_eq!(events[0].properties, properties(&[("method", "password")]));
+    }
+
+    // Buffered events survive a restart, until the user opts out
+    let telemetry = Telemetry::new(directory(&dir), config.clone()).unwrap();
+    assert_eq!(telemetry.buffered_events().len(), 1);
+    telemetry.set_opted_out(true).unwrap();
+    assert!(telemetry.is_opted_out());
+    assert!(telemetry.buffered_events().is_empty());
+    telemetry
+        .track("ignored".to_string(), HashMap::new())
+        .unwrap();
+    assert!(telemetry.buffered_events().is_empty());
+    telemetry.flush().unwrap();
+    drop(telemetry);
+
+    let telemetry = Telemetry::new(directory(&dir), config).unwrap();
+    assert!(telemetry.is_opted_out());
+    assert!(telemetry.buffered_events().is_empty());
+    telemetry.set_opted_out(false).unwrap();
+    telemetry
+        .track("resumed".to_string(), HashMap::new())
+        .unwrap();
+    telemetry.flush().unwrap();
+    wait_idle(&telemetry);
+    assert_eq!(server.batches(), [["resumed"]]);
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_invalid_configs_and_events_are_rejected() {
+    let server = Server::start();
+    let dir = tempfile::tempdir().unwrap();
+    for config in [
+        TelemetryConfig {
+            endpoint: "not a url".to_string(),
+            ..server.config()
+        },
+        TelemetryConfig {
+            max_batch_events: 0,
+            ..server.config()
+        },
+        TelemetryConfig {
+            max_buffered_events: 0,
+            ..server.config()
+        },
+    ] {
+        assert!(matches!(
+            Telemetry::new(directory(&dir), config),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+
+    let config = TelemetryConfig {
+        max_buffered_events: 2,
+        max_batch_events: 10,
+        ..server.config()
+    };
+    let telemetry = Telemetry::new(directory(&dir), config).unwrap();
+    assert!(matches!(
+        telemetry.track(" ".to_string(), HashMap::new()),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    // Past the cap the oldest events are dropped
+    for name in ["first", "second", "third"] {
+        telemetry.track(name.to_string(), HashMap::new()).unwrap();
+    }
+    let names: Vec<_> = telemetry
+        .buffered_events()
+        .into_iter()
+        .map(|event| event.name)
+        .collect();
+    assert_eq!(names, ["second", "third"]);
+}
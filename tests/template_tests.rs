@@ -1,11 +1,19 @@
 use rust_multiplatform_template_lib::{
-    echo, random, CancellationToken, TemplateConfig, TemplateError, MAX_INPUT_SIZE,
+    echo, random, random_bytes, random_choice, random_exponential, random_int, random_normal,
+    random_seeded, random_uniform, secure_random_bytes, secure_random_double, CancellationToken,
+    HashAlgorithm, LengthUnit, PreviewMode, SanitizationOptions, SeededRng, TemplateConfig,
+    TemplateError, TextTransform, UnicodeNormalization, WarningKind, DEFAULT_MAX_SIZE,
+    DEFAULT_PREVIEW_LENGTH, MAX_INPUT_SIZE,
 };
+use std::future::Future;
+use std::pin::pin;
 use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
 
 #[tokio::test]
 async fn test_echo_with_value() {
-    let result = echo("test".to_string(), None).await.unwrap();
+    let result = echo("test".to_string(), None, None).await.unwrap();
     assert!(result.is_some());
     let echo_result = result.unwrap();
     assert_eq!(echo_result.text, "test");
@@ -14,7 +22,7 @@ async fn test_echo_with_value() {
 
 #[tokio::test]
 async fn test_echo_with_empty() {
-    let result = echo("".to_string(), None).await.unwrap();
+    let result = echo("".to_string(), None, None).await.unwrap();
     assert!(result.is_none());
 }
 
@@ -28,14 +36,14 @@ async fn test_random_in_range() {
 
 #[tokio::test]
 async fn test_echo_with_whitespace() {
-    let result = echo("   ".to_string(), None).await.unwrap();
+    let result = echo("   ".to_string(), None, None).await.unwrap();
     assert!(result.is_some());
     assert_eq!(result.unwrap().text, "   ");
 }
 
 #[tokio::test]
 async fn test_echo_with_unicode() {
-    let result = echo("Hello 世界 🌍".to_string(), None).await.unwrap();
+    let result = echo("Hello 世界 🌍".to_string(), None, None).await.unwrap();
     assert!(result.is_some());
     let echo_result = result.unwrap();
     assert_eq!(echo_result.text, "Hello 世界 🌍");
@@ -53,7 +61,7 @@ async fn test_random_generates_different_values() {
 async fn test_echo_input_too_large() {
     // Create a string larger than MAX_INPUT_SIZE
     let large_input = "a".repeat(MAX_INPUT_SIZE + 1);
-    let result = echo(large_input, None).await;
+    let result = echo(large_input, None, None).await;
 
     assert!(result.is_err());
     match result {
@@ -70,7 +78,7 @@ async fn test_echo_input_too_large() {
 async fn test_echo_at_max_size() {
     // Create a string exactly at MAX_INPUT_SIZE
     let max_input = "a".repeat(MAX_INPUT_SIZE);
-    let result = echo(max_input.clone(), None).await.unwrap();
+    let result = echo(max_input.clone(), None, None).await.unwrap();
 
     assert!(result.is_some());
     assert_eq!(result.unwrap().text, max_input);
@@ -80,7 +88,7 @@ async fn test_echo_at_max_size() {
 async fn test_echo_just_under_max_size() {
     // Create a string just under MAX_INPUT_SIZE
     let input = "a".repeat(MAX_INPUT_SIZE - 1);
-    let result = echo(input.clone(), None).await.unwrap();
+    let result = echo(input.clone(), None, None).await.unwrap();
 
     assert!(result.is_some());
     assert_eq!(result.unwrap().text, input);
@@ -90,7 +98,7 @@ async fn test_echo_just_under_max_size() {
 async fn test_echo_with_null_bytes() {
     // Test input with null bytes
     let input_with_null = "hello\0world".to_string();
-    let result = echo(input_with_null, None).await;
+    let result = echo(input_with_null, None, None).await;
 
     assert!(result.is_err());
     match result {
@@ -113,7 +121,7 @@ async fn test_template_config() {
 
     // Test with valid input
     let result = config
-        .validate_and_echo("test".to_string(), None)
+        .validate_and_echo("test".to_string(), None, None)
         .await
         .unwrap();
     assert!(result.is_some());
@@ -121,7 +129,7 @@ async fn test_template_config() {
 
     // Test with input exceeding config max size
     let large_input = "a".repeat(101);
-    let result = config.validate_and_echo(large_input, None).await;
+    let result = config.validate_and_echo(large_input, None, None).await;
     assert!(result.is_err());
 }
 
@@ -141,7 +149,7 @@ async fn test_echo_with_cancellation() {
     // Cancel immediately
     token.cancel();
 
-    let result = echo("test".to_string(), Some(token)).await;
+    let result = echo("test".to_string(), Some(token), None).await;
 
     assert!(result.is_err());
     match result {
@@ -156,7 +164,7 @@ async fn test_echo_with_cancellation() {
 async fn smoke_uniffi_api() {
     // echo should return EchoResult with metadata
     let input = "ping";
-    let result = echo(input.to_string(), None).await;
+    let result = echo(input.to_string(), None, None).await;
     match result {
         Ok(Some(echo_result)) => {
             assert_eq!(echo_result.text, input, "echo should return the input text");
@@ -174,3 +182,540 @@ async fn smoke_uniffi_api() {
         "random should return a value in range [0.0, 1.0)"
     );
 }
+
+#[tokio::test]
+async fn test_random_seeded_is_deterministic() {
+    let value1 = random_seeded(42).await;
+    let value2 = random_seeded(42).await;
+    assert_eq!(value1, value2);
+    assert!((0.0..1.0).contains(&value1));
+    assert_ne!(value1, random_seeded(43).await);
+}
+
+#[test]
+fn test_seeded_rng_sequences() {
+    let rng1 = SeededRng::new(7);
+    let rng2 = SeededRng::new(7);
+    for _ in 0..50 {
+        assert_eq!(rng1.next_double(), rng2.next_double());
+        let value = rng1.next_int(-5, 5).unwrap();
+        assert_eq!(value, rng2.next_int(-5, 5).unwrap());
+        assert!((-5..=5).contains(&value));
+    }
+
+    assert_eq!(rng1.next_int(3, 3).unwrap(), 3);
+    match rng1.next_int(5, 1) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("Invalid range"));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+}
+
+#[tokio::test]
+async fn test_random_int_in_range() {
+    for _ in 0..100 {
+        let value = random_int(-3, 3, None).await.unwrap();
+        assert!((-3..=3).contains(&value));
+    }
+    assert_eq!(random_int(9, 9, None).await.unwrap(), 9);
+    assert!(matches!(
+        random_int(2, 1, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_random_bytes() {
+    assert!(random_bytes(0, None).await.unwrap().is_empty());
+    assert_eq!(random_bytes(32, None).await.unwrap().len(), 32);
+    assert!(matches!(
+        random_bytes(MAX_INPUT_SIZE as u32 + 1, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_random_choice() {
+    let items = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    for _ in 0..20 {
+        let choice = random_choice(items.clone(), None).await.unwrap();
+        assert!(items.contains(&choice));
+    }
+    assert!(matches!(
+        random_choice(Vec::new(), None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_random_normal() {
+    let mut sum = 0.0;
+    for _ in 0..1000 {
+        sum += random_normal(5.0, 1.0, None).await.unwrap();
+    }
+    let mean = sum / 1000.0;
+    assert!((mean - 5.0).abs() < 0.3);
+
+    assert!(matches!(
+        random_normal(0.0, -1.0, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_random_exponential() {
+    for _ in 0..100 {
+        assert!(random_exponential(2.0, None).await.unwrap() >= 0.0);
+    }
+    assert!(matches!(
+        random_exponential(0.0, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_random_uniform() {
+    for _ in 0..100 {
+        let value = random_uniform(-2.0, 2.0, None).await.unwrap();
+        assert!((-2.0..2.0).contains(&value));
+    }
+    assert!(matches!(
+        random_uniform(1.0, 1.0, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_secure_random_bytes() {
+    let bytes1 = secure_random_bytes(32, None).await.unwrap();
+    let bytes2 = secure_random_bytes(32, None).await.unwrap();
+    assert_eq!(bytes1.len(), 32);
+    assert_ne!(bytes1, bytes2);
+    assert!(matches!(
+        secure_random_bytes(MAX_INPUT_SIZE as u32 + 1, None).await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_secure_random_double_in_range() {
+    for _ in 0..100 {
+        let value = secure_random_double(None).await.unwrap();
+        assert!((0.0..1.0).contains(&value));
+    }
+}
+
+#[tokio::test]
+async fn test_echo_with_timeout_completes() {
+    let result = echo("test".to_string(), None, Some(1_000)).await.unwrap();
+    assert_eq!(result.unwrap().text, "test");
+
+    let config = TemplateConfig::new(100, true);
+    let result = config
+        .validate_and_echo("test".to_string(), None, Some(1_000))
+        .await
+        .unwrap();
+    assert_eq!(result.unwrap().text, "test");
+}
+
+/// Minimal executor without a tokio runtime, like the Swift/Kotlin executors
+fn block_on_foreign<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn test_echo_with_timeout_without_tokio_runtime() {
+    let result = block_on_foreign(echo("test".to_string(), None, Some(1_000)));
+    assert_eq!(result.unwrap().unwrap().text, "test");
+}
+
+#[tokio::test]
+async fn test_echo_computes_sha256_hash() {
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        result.hash.as_deref(),
+        Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+    );
+}
+
+#[tokio::test]
+async fn test_template_config_hash_algorithms() {
+    let config = TemplateConfig::new(100, true);
+    assert_eq!(config.hash_algorithm(), HashAlgorithm::Sha256);
+
+    let hash_with = |algorithm| async move {
+        TemplateConfig::with_hash_algorithm(100, true, algorithm)
+            .validate_and_echo("hello".to_string(), None, None)
+            .await
+            .unwrap()
+            .unwrap()
+            .hash
+    };
+
+    assert_eq!(
+        hash_with(HashAlgorithm::Blake3).await.as_deref(),
+        Some("ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f")
+    );
+    assert_eq!(hash_with(HashAlgorithm::Xxhash).await.unwrap().len(), 16);
+    assert_eq!(hash_with(HashAlgorithm::None).await, None);
+}
+
+#[tokio::test]
+async fn test_template_config_transforms() {
+    let transforms = vec![
+        TextTransform::Trim,
+        TextTransform::CollapseWhitespace,
+        TextTransform::Uppercase,
+    ];
+    let config = TemplateConfig::with_transforms(100, true, transforms.clone());
+    assert_eq!(config.transforms(), transforms);
+
+    let result = config
+        .validate_and_echo("  hello   big\tworld ".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.text, "HELLO BIG WORLD");
+    assert_eq!(result.length, 15);
+    assert_eq!(result.transforms_applied, transforms);
+
+    let reversed = TemplateConfig::with_transforms(100, true, vec![TextTransform::Reverse])
+        .validate_and_echo("añb".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reversed.text, "bña");
+}
+
+#[tokio::test]
+async fn test_transforms_to_empty_returns_none() {
+    let config = TemplateConfig::with_transforms(100, true, vec![TextTransform::Trim]);
+    let result = config
+        .validate_and_echo("   ".to_string(), None, None)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+
+    let plain = echo("x".to_string(), None, None).await.unwrap().unwrap();
+    assert!(plain.transforms_applied.is_empty());
+}
+
+#[test]
+fn test_template_config_default_and_builder() {
+    let defaults = TemplateConfig::default();
+    assert_eq!(defaults.max_input_size(), DEFAULT_MAX_SIZE as u64);
+    assert!(defaults.enable_validation());
+    assert_eq!(defaults.hash_algorithm(), HashAlgorithm::Sha256);
+    assert_eq!(defaults.timeout_ms(), None);
+    assert!(defaults.allow_control_chars());
+    assert_eq!(defaults.preview_length(), DEFAULT_PREVIEW_LENGTH);
+
+    let config = TemplateConfig::builder()
+        .max_input_size(10)
+        .enable_validation(false)
+        .hash_algorithm(HashAlgorithm::None)
+        .transforms(vec![TextTransform::Trim])
+        .timeout_ms(Some(500))
+        .allow_control_chars(false)
+        .preview_length(5)
+        .build();
+    assert_eq!(config.max_input_size(), 10);
+    assert!(!config.enable_validation());
+    assert_eq!(config.hash_algorithm(), HashAlgorithm::None);
+    assert_eq!(config.transforms(), vec![TextTransform::Trim]);
+    assert_eq!(config.timeout_ms(), Some(500));
+    assert!(!config.allow_control_chars());
+    assert_eq!(config.preview_length(), 5);
+}
+
+#[tokio::test]
+async fn test_template_config_rejects_control_chars() {
+    let config = TemplateConfig::builder()
+        .allow_control_chars(false)
+        .preview_length(3)
+        .build();
+
+    let ok = config
+        .validate_and_echo("tab\tand\nnewline".to_string(), None, None)
+        .await
+        .unwrap();
+    assert!(ok.is_some());
+
+    match config
+        .validate_and_echo("bell\u{7}".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::InvalidInput {
+            error_message,
+            input_preview,
+        }) => {
+            assert!(error_message.contains("control characters"));
+            assert_eq!(input_preview.as_deref(), Some("bel..."));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+}
+
+#[tokio::test]
+async fn test_template_config_redacts_preview() {
+    let config = TemplateConfig::builder()
+        .allow_control_chars(false)
+        .preview_mode(PreviewMode::Redact)
+        .build();
+    assert_eq!(config.preview_mode(), PreviewMode::Redact);
+
+    match config
+        .validate_and_echo("password\u{7}".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::InvalidInput { input_preview, .. }) => {
+            let preview = input_preview.unwrap();
+            assert!(preview.starts_with("[redacted sha256:"));
+            assert!(!preview.contains("password"));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+}
+
+#[test]
+fn test_template_config_json_round_trip() {
+    let config = TemplateConfig::builder()
+        .max_input_size(256)
+        .hash_algorithm(HashAlgorithm::Xxhash)
+        .transforms(vec![TextTransform::Trim, TextTransform::CollapseWhitespace])
+        .timeout_ms(Some(250))
+        .build();
+
+    let json = config.to_json();
+    assert!(json.contains("\"collapse_whitespace\""));
+
+    let parsed = TemplateConfig::from_json(json).unwrap();
+    assert_eq!(parsed.max_input_size(), 256);
+    assert_eq!(parsed.hash_algorithm(), HashAlgorithm::Xxhash);
+    assert_eq!(parsed.transforms(), config.transforms());
+    assert_eq!(parsed.timeout_ms(), Some(250));
+}
+
+#[test]
+fn test_template_config_from_toml() {
+    let config = TemplateConfig::from_toml(
+        r#"
+        max_input_size = 128
+        enable_validation = false
+        transforms = ["lowercase"]
+        "#
+        .to_string(),
+    )
+    .unwrap();
+    assert_eq!(config.max_input_size(), 128);
+    assert!(!config.enable_validation());
+    assert_eq!(config.transforms(), vec![TextTransform::Lowercase]);
+    // Missing fields use the defaults
+    assert_eq!(config.hash_algorithm(), HashAlgorithm::Sha256);
+    assert_eq!(config.preview_length(), DEFAULT_PREVIEW_LENGTH);
+}
+
+#[test]
+fn test_template_config_parse_errors() {
+    match TemplateConfig::from_json("{\n  \"hash_algorithm\": \"md5\"}".to_string()) {
+        Err(TemplateError::ParseError {
+            format,
+            error_message,
+            line,
+            column,
+        }) => {
+            assert_eq!(format, "JSON");
+            assert!(error_message.contains("md5"));
+            assert_eq!(line, Some(2));
+            assert!(column.is_some());
+        }
+        _ => panic!("Expected ParseError"),
+    }
+
+    match TemplateConfig::from_toml(
+        "enable_validation = true\nmax_input_size = \"big\"".to_string(),
+    ) {
+        Err(TemplateError::ParseError {
+            format,
+            line,
+            column,
+            ..
+        }) => {
+            assert_eq!(format, "TOML");
+            assert_eq!(line, Some(2));
+            assert_eq!(column, Some(18));
+        }
+        _ => panic!("Expected ParseError"),
+    }
+}
+
+#[tokio::test]
+async fn test_template_config_sanitization() {
+    let config = TemplateConfig::builder()
+        .allow_control_chars(false)
+        .sanitization(SanitizationOptions {
+            strip_control_chars: true,
+            strip_bom: true,
+            reject_bidi_overrides: false,
+            normalize_line_endings: true,
+        })
+        .build();
+
+    let result = config
+        .validate_and_echo("\u{FEFF}a\u{7}b\r\nc\rd\0".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.text, "ab\nc\nd");
+    let report = result.sanitization.unwrap();
+    assert!(report.bom_removed);
+    assert_eq!(report.control_chars_removed, 2);
+    assert_eq!(report.line_endings_normalized, 2);
+
+    // No report when sanitization is disabled
+    let plain = echo("x".to_string(), None, None).await.unwrap().unwrap();
+    assert!(plain.sanitization.is_none());
+}
+
+#[tokio::test]
+async fn test_template_config_rejects_bidi_overrides() {
+    let config = TemplateConfig::builder()
+        .sanitization(SanitizationOptions {
+            reject_bidi_overrides: true,
+            ..SanitizationOptions::default()
+        })
+        .build();
+
+    match config
+        .validate_and_echo("abc\u{202E}def".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("position 3"));
+        }
+        _ => panic!("Expected InvalidInput error"),
+    }
+}
+
+#[tokio::test]
+async fn test_unicode_normalization_makes_hashes_consistent() {
+    let precomposed = "caf\u{e9}".to_string();
+    let decomposed = "cafe\u{301}".to_string();
+
+    let echo_with = |config: Arc<TemplateConfig>, input: String| async move {
+        config
+            .validate_and_echo(input, None, None)
+            .await
+            .unwrap()
+            .unwrap()
+    };
+
+    let plain = TemplateConfig::builder().build();
+    assert_ne!(
+        echo_with(plain.clone(), precomposed.clone()).await.hash,
+        echo_with(plain, decomposed.clone()).await.hash
+    );
+
+    let nfc = TemplateConfig::builder()
+        .normalization(UnicodeNormalization::Nfc)
+        .build();
+    let a = echo_with(nfc.clone(), precomposed).await;
+    let b = echo_with(nfc, decomposed).await;
+    assert_eq!(a.text, b.text);
+    assert_eq!(a.hash, b.hash);
+    assert_eq!(a.length, 5);
+}
+
+#[tokio::test]
+async fn test_normalized_input_is_checked_against_the_size_limit() {
+    let config = TemplateConfig::builder()
+        .max_input_size(16)
+        .normalization(UnicodeNormalization::Nfkc)
+        .build();
+
+    // Three bytes that decompose into 18 characters
+    match config
+        .validate_and_echo("\u{FDFA}".to_string(), None, None)
+        .await
+    {
+        Err(TemplateError::InputTooLarge { size, max, .. }) => {
+            assert!(size > 16);
+            assert_eq!(max, 16);
+        }
+        other => panic!("Expected InputTooLarge error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_echo_result_warnings() {
+    let decomposed = "cafe\u{301}".to_string();
+
+    // Clean input produces no warnings
+    let plain = echo("café".to_string(), None, None).await.unwrap().unwrap();
+    assert!(plain.warnings.is_empty());
+
+    let result = echo(decomposed.clone(), None, None).await.unwrap().unwrap();
+    let kinds: Vec<_> = result.warnings.iter().map(|w| w.kind).collect();
+    assert_eq!(kinds, vec![WarningKind::InputNotNormalized]);
+
+    let config = TemplateConfig::builder()
+        .normalization(UnicodeNormalization::Nfc)
+        .sanitization(SanitizationOptions {
+            strip_bom: true,
+            ..SanitizationOptions::default()
+        })
+        .build();
+    let result = config
+        .validate_and_echo(format!("\u{FEFF}{}", decomposed), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let kinds: Vec<_> = result.warnings.iter().map(|w| w.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![WarningKind::InputSanitized, WarningKind::InputNormalized]
+    );
+    assert!(result.warnings[1].message.contains("NFC"));
+}
+
+#[tokio::test]
+async fn test_length_units() {
+    let input = "e\u{301}👍🏽".to_string();
+    let length_in = |unit| {
+        let input = input.clone();
+        async move {
+            TemplateConfig::builder()
+                .length_unit(unit)
+                .build()
+                .validate_and_echo(input, None, None)
+                .await
+                .unwrap()
+                .unwrap()
+                .length
+        }
+    };
+
+    assert_eq!(length_in(LengthUnit::Bytes).await, 11);
+    assert_eq!(length_in(LengthUnit::Chars).await, 4);
+    assert_eq!(length_in(LengthUnit::Graphemes).await, 2);
+}
@@ -0,0 +1,85 @@
+use rust_multiplatform_template_lib::{CancellationToken, Debouncer, RateLimiter, TemplateError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_rate_limiter_burst_then_empty() {
+    let limiter = RateLimiter::new(3, 1.0).unwrap();
+    assert_eq!(limiter.available_permits(), 3);
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(!limiter.try_acquire());
+    assert_eq!(limiter.available_permits(), 0);
+}
+
+#[test]
+fn test_rate_limiter_refills() {
+    let limiter = RateLimiter::new(1, 100.0).unwrap();
+    assert!(limiter.try_acquire());
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(limiter.try_acquire());
+}
+
+#[tokio::test]
+async fn test_rate_limiter_acquire_waits() {
+    let limiter = RateLimiter::new(1, 20.0).unwrap();
+    limiter.acquire(None).await.unwrap();
+
+    let start = Instant::now();
+    limiter.acquire(None).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(40));
+}
+
+#[tokio::test]
+async fn test_rate_limiter_acquire_cancelled() {
+    let limiter = RateLimiter::new(1, 0.001).unwrap();
+    assert!(limiter.try_acquire());
+
+    let token = Arc::new(CancellationToken::with_timeout(20));
+    assert!(matches!(
+        limiter.acquire(Some(token)).await,
+        Err(TemplateError::Timeout { .. })
+    ));
+}
+
+#[test]
+fn test_rate_limiter_rejects_invalid_settings() {
+    assert!(RateLimiter::new(0, 1.0).is_err());
+    assert!(RateLimiter::new(1, 0.0).is_err());
+    assert!(RateLimiter::new(1, f64::NAN).is_err());
+}
+
+#[tokio::test]
+async fn test_debouncer_only_last_call_wins() {
+    let debouncer = Arc::new(Debouncer::new(30));
+    let mut calls = Vec::new();
+    for _ in 0..3 {
+        let d = debouncer.clone();
+        calls.push(tokio::spawn(async move { d.debounce().await }));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let mut results = Vec::new();
+    for call in calls {
+        results.push(call.await.unwrap());
+    }
+    assert_eq!(results, vec![false, false, true]);
+}
+
+#[tokio::test]
+async fn test_debouncer_separate_bursts() {
+    let debouncer = Debouncer::new(5);
+    assert!(debouncer.debounce().await);
+    assert!(debouncer.debounce().await);
+}
+
+#[tokio::test]
+async fn test_debouncer_cancel() {
+    let debouncer = Arc::new(Debouncer::new(30));
+    let d = debouncer.clone();
+    let pending = tokio::spawn(async move { d.debounce().await });
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    debouncer.cancel();
+    assert!(!pending.await.unwrap());
+}
@@ -0,0 +1,42 @@
+use rust_multiplatform_template_lib::{echo, update_config, LibraryConfig, TemplateConfig};
+
+// The library configuration is process-wide, so these checks run in one test
+#[tokio::test]
+async fn test_timing_is_attached_only_when_enabled() {
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(result.timing.is_none());
+
+    update_config(LibraryConfig {
+        collect_timing: true,
+        ..LibraryConfig::default()
+    })
+    .unwrap();
+
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let timing = result.timing.unwrap();
+    assert!(timing.total_us >= timing.queue_wait_us + timing.validation_us + timing.compute_us);
+
+    let config = TemplateConfig::default();
+    let result = config
+        .validate_and_echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(result.timing.is_some());
+
+    // Empty input has no result to attach timing to
+    assert!(echo(String::new(), None, None).await.unwrap().is_none());
+
+    update_config(LibraryConfig::default()).unwrap();
+    let result = echo("hello".to_string(), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(result.timing.is_none());
+}
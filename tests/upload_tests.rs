@@ -0,0 +1,226 @@
+use rust_multiplatform_template_lib::{upload_file, TemplateError};
+use std::collections::HashMap;
+
+#[cfg(feature = "http")]
+mod common;
+
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{
+    add_http_interceptor, remove_http_interceptor, CancellationToken, HttpInterceptor, HttpRequest,
+    HttpResponse, UploadListener, UploadProgress, UPLOAD_FILE_FIELD,
+};
+#[cfg(feature = "http")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "http")]
+use std::thread;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+#[cfg(not(feature = "http"))]
+#[tokio::test]
+async fn test_upload_requires_feature() {
+    match upload_file(
+        "http://127.0.0.1/".to_string(),
+        "file.txt".to_string(),
+        HashMap::new(),
+        HashMap::new(),
+        None,
+        None,
+    )
+    .await
+    {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other),
+    }
+}
+
+/// Serves `/upload` (the request body back, with its content type in
+/// `x-content-type`) and `/slow` (answers the request after 2 seconds)
+#[cfg(feature = "http")]
+fn serve() -> String {
+    common::serve(|request, mut stream| {
+        if request.path == "/slow" {
+            thread::sleep(Duration::from_secs(2));
+        }
+        let headers = format!(
+            "x-content-type: {}\r\nx-authorization: {}\r\n",
+            request.header("content-type").unwrap_or_default(),
+            request.header("authorization").unwrap_or_default()
+        );
+        common::respond(&mut stream, "201 Created", &headers, &request.body)
+    })
+}
+
+#[cfg(feature = "http")]
+struct RecordingListener(Arc<Mutex<Vec<UploadProgress>>>);
+
+#[cfg(feature = "http")]
+impl UploadListener for RecordingListener {
+    fn on_upload_progress(&self, progress: UploadProgress) {
+        self.0.lock().unwrap().push(progress);
+    }
+}
+
+#[cfg(feature = "http")]
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_upload_sends_fields_and_file_with_progress() {
+    let base = serve();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("crash.log");
+    let contents: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &contents).unwrap();
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let response = upload_file(
+        format!("{}/upload", base),
+        path.to_string_lossy().into_owned(),
+        HashMap::from([("app_version".to_string(), "1.2.3".to_string())]),
+        HashMap::from([("authorization".to_string(), "Bearer secret".to_string())]),
+        Some(Box::new(RecordingListener(recorded.clone()))),
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status, 201);
+    assert_eq!(response.headers["x-authorization"], "Bearer secret");
+    assert!(response.headers["x-content-type"].starts_with("multipart/form-data; boundary="));
+    let body = &response.body;
+    assert!(contains(body, b"name=\"app_version\"\r\n\r\n1.2.3\r\n"));
+    assert!(contains(
+        body,
+        format!("name=\"{}\"; filename=\"crash.log\"", UPLOAD_FILE_FIELD).as_bytes()
+    ));
+    assert!(contains(body, b"Content-Type: application/octet-stream"));
+    assert!(contains(body, &contents));
+
+    let progress = recorded.lock().unwrap();
+    let last = progress.last().expect("progress was reported");
+    assert_eq!(last.sent_bytes, contents.len() as u64);
+    assert_eq!(last.total_bytes, contents.len() as u64);
+    assert!(progress
+        .windows(2)
+        .all(|pair| pair[0].sent_bytes < pair[1].sent_bytes));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_upload_failures() {
+    let base = serve();
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir
+        .path()
+        .join("missing.bin")
+        .to_string_lossy()
+        .into_owned();
+    assert!(matches!(
+        upload_file(
+            format!("{}/upload", base),
+            missing,
+            HashMap::new(),
+            HashMap::new(),
+            None,
+            None
+        )
+        .await,
+        Err(TemplateError::IoError { .. })
+    ));
+
+    let path = dir.path().join("file.bin");
+    std::fs::write(&path, b"data").unwrap();
+    let path = path.to_string_lossy().into_owned();
+    assert!(matches!(
+        upload_file(
+            "ftp://example.com/upload".to_string(),
+            path.clone(),
+            HashMap::new(),
+            HashMap::new(),
+            None,
+            None
+        )
+        .await,
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let token = Arc::new(CancellationToken::new());
+    let canceller = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        canceller.cancel();
+    });
+    assert!(matches!(
+        upload_file(
+            format!("{}/slow", base),
+            path,
+            HashMap::new(),
+            HashMap::new(),
+            None,
+            Some(token)
+        )
+        .await,
+        Err(TemplateError::OperationCancelled { .. })
+    ));
+}
+
+/// Signs requests to `base` and records the responses to them
+#[cfg(feature = "http")]
+struct Signer {
+    base: String,
+    responses: Arc<Mutex<Vec<HttpResponse>>>,
+}
+
+#[cfg(feature = "http")]
+impl HttpInterceptor for Signer {
+    fn intercept_request(&self, mut request: HttpRequest) -> HttpRequest {
+        if request.url.starts_with(&self.base) {
+            request
+                .headers
+                .insert("Authorization".to_string(), "Signed upload".to_string());
+        }
+        request
+    }
+
+    fn on_response(&self, request: HttpRequest, response: HttpResponse) {
+        if request.url.starts_with(&self.base) {
+            self.responses.lock().unwrap().push(response);
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_uploads_go_through_interceptors() {
+    let base = serve();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.bin");
+    std::fs::write(&path, b"data").unwrap();
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let signer = Signer {
+        base: base.clone(),
+        responses: responses.clone(),
+    };
+    add_http_interceptor("upload_signer".to_string(), Box::new(signer)).unwrap();
+
+    let response = upload_file(
+        format!("{}/upload", base),
+        path.to_string_lossy().into_owned(),
+        HashMap::new(),
+        HashMap::new(),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(remove_http_interceptor("upload_signer".to_string()));
+    assert_eq!(response.headers["x-authorization"], "Signed upload");
+    assert_eq!(*responses.lock().unwrap(), [response]);
+}
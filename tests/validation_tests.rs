@@ -0,0 +1,73 @@
+use rust_multiplatform_template_lib::{
+    validate, SanitizationOptions, TemplateConfig, UnicodeNormalization, ValidationIssueKind,
+    MAX_VALIDATION_ISSUES,
+};
+
+#[test]
+fn test_validate_valid_input() {
+    let report = validate("hello".to_string(), TemplateConfig::builder().build());
+    assert!(report.is_valid);
+    assert!(report.issues.is_empty());
+    assert!(!report.truncated);
+}
+
+#[test]
+fn test_validate_reports_every_issue_with_positions() {
+    let config = TemplateConfig::builder()
+        .max_input_size(8)
+        .allow_control_chars(false)
+        .sanitization(SanitizationOptions {
+            reject_bidi_overrides: true,
+            ..SanitizationOptions::default()
+        })
+        .build();
+
+    // "é" is 2 bytes, 1 char, 1 UTF-16 unit; "🎉" is 4 bytes, 1 char, 2 units
+    let report = validate("é🎉\0a\u{7}b\u{202E}".to_string(), config);
+    assert!(!report.is_valid);
+    let kinds: Vec<_> = report.issues.iter().map(|i| i.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ValidationIssueKind::NullByte,
+            ValidationIssueKind::TooLarge,
+            ValidationIssueKind::ControlCharacter,
+            ValidationIssueKind::BidiOverride,
+        ]
+    );
+
+    let null = &report.issues[0];
+    assert_eq!(
+        (null.byte_offset, null.char_offset, null.utf16_offset),
+        (6, 2, 3)
+    );
+    let bell = &report.issues[2];
+    assert_eq!(
+        (bell.byte_offset, bell.char_offset, bell.utf16_offset),
+        (8, 4, 5)
+    );
+    assert!(bell.message.contains("U+0007"));
+}
+
+#[test]
+fn test_validate_reports_unnormalized_input() {
+    // "e" followed by a combining acute accent is not NFC
+    let report = validate("cafe\u{301}".to_string(), TemplateConfig::builder().build());
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, ValidationIssueKind::NotNormalized);
+    assert_eq!(report.issues[0].char_offset, 3);
+    assert!(report.issues[0].message.contains("NFC"));
+
+    // Decomposed text is fine when NFD is the configured form
+    let config = TemplateConfig::builder()
+        .normalization(UnicodeNormalization::Nfd)
+        .build();
+    assert!(validate("cafe\u{301}".to_string(), config).is_valid);
+}
+
+#[test]
+fn test_validate_caps_issues() {
+    let report = validate("\0".repeat(500), TemplateConfig::builder().build());
+    assert_eq!(report.issues.len(), MAX_VALIDATION_ISSUES as usize);
+    assert!(report.truncated);
+}
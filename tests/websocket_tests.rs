@@ -0,0 +1,231 @@
+use rust_multiplatform_template_lib::{
+    TemplateError, WebSocketClient, WebSocketListener, WebSocketOptions, WebSocketState,
+};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "http")]
+use futures_util::{SinkExt, StreamExt};
+#[cfg(feature = "http")]
+use rust_multiplatform_template_lib::{ReconnectPolicy, WEBSOCKET_NORMAL_CLOSURE};
+#[cfg(feature = "http")]
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Received {
+    Text(String),
+    Binary(Vec<u8>),
+    State(WebSocketState),
+}
+
+struct RecordingListener(Arc<Mutex<Vec<Received>>>);
+
+impl WebSocketListener for RecordingListener {
+    fn on_text_message(&self, text: String) {
+        self.0.lock().unwrap().push(Received::Text(text));
+    }
+
+    fn on_binary_message(&self, data: Vec<u8>) {
+        self.0.lock().unwrap().push(Received::Binary(data));
+    }
+
+    fn on_state_changed(&self, state: WebSocketState, _error_message: Option<String>) {
+        self.0.lock().unwrap().push(Received::State(state));
+    }
+}
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn test_websocket_requires_feature() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    match WebSocketClient::new(
+        "ws://127.0.0.1/".to_string(),
+        WebSocketOptions::default(),
+        Box::new(RecordingListener(received)),
+    ) {
+        Err(TemplateError::InvalidInput { error_message, .. }) => {
+            assert!(error_message.contains("http"))
+        }
+        other => panic!("Expected InvalidInput, got {:?}", other.map(|_| ())),
+    }
+}
+
+/// Echoes every text and binary message; the first `drop_first` connections
+/// are closed by the server right after the handshake
+#[cfg(feature = "http")]
+async fn serve(drop_first: usize) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let accepted = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let accepted = accepted.clone();
+            tokio::spawn(async move {
+                let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                if accepted.fetch_add(1, Ordering::SeqCst) < drop_first {
+                    return;
+                }
+                while let Some(Ok(message)) = socket.next().await {
+                    if message.is_text() || message.is_binary() {
+                        let _ = socket.send(message).await;
+                    }
+                }
+            });
+        }
+    });
+    format!("ws://{}", address)
+}
+
+/// Waits until `received` holds `expected`
+#[cfg(feature = "http")]
+async fn wait_for(received: &Mutex<Vec<Received>>, expected: Received) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if received.lock().unwrap().contains(&expected) {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    false
+}
+
+#[cfg(feature = "http")]
+fn client(url: &str, options: WebSocketOptions) -> (WebSocketClient, Arc<Mutex<Vec<Received>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let client = WebSocketClient::new(
+        url.to_string(),
+        options,
+        Box::new(RecordingListener(received.clone())),
+    )
+    .unwrap();
+    (client, received)
+}
+
+#[cfg(feature = "http")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_messages_are_echoed_and_close_is_reported() {
+    let url = serve(0).await;
+    let (client, received) = client(&url, WebSocketOptions::default());
+    assert_eq!(client.state(), WebSocketState::Closed);
+    client.connect().await.unwrap();
+    assert_eq!(client.state(), WebSocketState::Connected);
+
+    client.send_text("hello".to_string()).unwrap();
+    client.send_bytes(vec![1, 2, 3]).unwrap();
+    assert!(wait_for(&received, Received::Text("hello".to_string())).await);
+    assert!(wait_for(&received, Received::Binary(vec![1, 2, 3])).await);
+
+    client.close(WEBSOCKET_NORMAL_CLOSURE, "done".to_string());
+    assert!(wait_for(&received, Received::State(WebSocketState::Closed)).await);
+    let states: Vec<Received> = received
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|received| matches!(received, Received::State(_)))
+        .cloned()
+        .collect();
+    assert_eq!(
+        states,
+        [
+            WebSocketState::Connecting,
+            WebSocketState::Connected,
+            WebSocketState::Closing,
+            WebSocketState::Closed,
+        ]
+        .map(Received::State)
+    );
+    assert!(matches!(
+        client.send_text("late".to_string()),
+        Err(TemplateError::NetworkError { .. })
+    ));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reconnects_after_server_drops_connection() {
+    let url = serve(1).await;
+    let options = WebSocketOptions {
+        reconnect: Some(ReconnectPolicy {
+            max_attempts: 3,
+            base_delay_ms: 10,
+            max_delay_ms: 50,
+        }),
+        ..WebSocketOptions::default()
+    };
+    let (client, received) = client(&url, options);
+    client.connect().await.unwrap();
+    assert!(wait_for(&received, Received::State(WebSocketState::Reconnecting)).await);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.state() != WebSocketState::Connected && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    client.send_text("again".to_string()).unwrap();
+    assert!(wait_for(&received, Received::Text("again".to_string())).await);
+}
+
+#[cfg(feature = "http")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_without_reconnect_a_dropped_connection_closes() {
+    let url = serve(1).await;
+    let (client, received) = client(&url, WebSocketOptions::default());
+    client.connect().await.unwrap();
+    assert!(wait_for(&received, Received::State(WebSocketState::Closed)).await);
+    assert!(!received
+        .lock()
+        .unwrap()
+        .contains(&Received::State(WebSocketState::Reconnecting)));
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_invalid_input_and_failed_handshake() {
+    for url in ["not a url", "http://example.com/socket", "/relative"] {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        assert!(matches!(
+            WebSocketClient::new(
+                url.to_string(),
+                WebSocketOptions::default(),
+                Box::new(RecordingListener(received)),
+            ),
+            Err(TemplateError::InvalidInput { .. })
+        ));
+    }
+    let options = WebSocketOptions {
+        headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+        ..WebSocketOptions::default()
+    };
+    let received = Arc::new(Mutex::new(Vec::new()));
+    assert!(matches!(
+        WebSocketClient::new(
+            "ws://127.0.0.1/".to_string(),
+            options,
+            Box::new(RecordingListener(received)),
+        ),
+        Err(TemplateError::InvalidInput { .. })
+    ));
+
+    let closed = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("ws://{}/", listener.local_addr().unwrap())
+    };
+    let (client, received) = client(&closed, WebSocketOptions::default());
+    assert!(matches!(
+        client.send_text("early".to_string()),
+        Err(TemplateError::NetworkError { .. })
+    ));
+    assert!(matches!(
+        client.connect().await,
+        Err(TemplateError::NetworkError { .. })
+    ));
+    assert_eq!(client.state(), WebSocketState::Closed);
+    assert!(received
+        .lock()
+        .unwrap()
+        .contains(&Received::State(WebSocketState::Closed)));
+}